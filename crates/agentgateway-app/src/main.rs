@@ -4,6 +4,7 @@ use std::path::PathBuf;
 use std::sync::Arc;
 
 use agent_core::{strng, telemetry, version};
+use agentgateway::mcp::registry::{CompiledRegistry, DocsFormat, parse_registry, render_docs};
 use agentgateway::types::agent::ListenerTarget;
 use agentgateway::{BackendConfig, Config, LoggingFormat, client, serdes};
 use clap::Parser;
@@ -43,6 +44,14 @@ struct Args {
 	#[arg(long, value_name = "validate-only")]
 	validate_only: bool,
 
+	/// Print reference documentation for a registry file and exit
+	#[arg(long, value_name = "registry")]
+	registry_docs: Option<PathBuf>,
+
+	/// Format of the registry documentation (markdown or html)
+	#[arg(long, value_name = "format", default_value = "markdown")]
+	docs_format: String,
+
 	/// Print version (as a simple version string)
 	#[arg(short = 'V', value_name = "version")]
 	version_short: bool,
@@ -62,6 +71,8 @@ fn main() -> anyhow::Result<()> {
 		config,
 		file,
 		validate_only,
+		registry_docs,
+		docs_format,
 		version_short,
 		version_long,
 		copy_self,
@@ -78,6 +89,9 @@ fn main() -> anyhow::Result<()> {
 	if let Some(copy_self) = copy_self {
 		return copy_binary(copy_self);
 	}
+	if let Some(registry) = registry_docs {
+		return print_registry_docs(registry, &docs_format);
+	}
 	tokio::runtime::Builder::new_current_thread()
 		.enable_all()
 		.build()
@@ -128,6 +142,14 @@ fn copy_binary(copy_self: PathBuf) -> anyhow::Result<()> {
 	return Ok(());
 }
 
+fn print_registry_docs(file: PathBuf, format: &str) -> anyhow::Result<()> {
+	let format: DocsFormat = format.parse().map_err(anyhow::Error::msg)?;
+	let contents = fs_err::read_to_string(&file)?;
+	let registry = CompiledRegistry::compile(parse_registry(&contents, false)?)?;
+	print!("{}", render_docs(&registry, format));
+	Ok(())
+}
+
 async fn validate(contents: String, filename: Option<PathBuf>) -> anyhow::Result<()> {
 	let config = agentgateway::config::parse_config(contents, filename)?;
 	let client = client::Client::new(&config.dns, None, BackendConfig::default(), None);
@@ -140,6 +162,7 @@ async fn validate(contents: String, filename: Option<PathBuf>) -> anyhow::Result
 				gateway_namespace: strng::literal!("default"),
 				listener_name: None,
 			},
+			agentgateway::events::EventBus::default(),
 			cs.as_str(),
 		)
 		.await?;
@@ -219,6 +219,32 @@ mod composition_benchmarks {
 		});
 	}
 
+	// =========================================================================
+	// Registry Store Read Benchmarks
+	// =========================================================================
+
+	#[divan::bench(threads = [1, 4, 8])]
+	fn registry_read_rwlock(bencher: Bencher) {
+		use agentgateway::mcp::registry::{RegistryStore, RegistryStoreRef};
+		use std::sync::RwLock;
+
+		// The previous Stores::registry read path, kept as a baseline
+		let registry = RwLock::new(Some(RegistryStoreRef::new(RegistryStore::new())));
+
+		bencher.bench(|| registry.read().ok().and_then(|r| r.clone()));
+	}
+
+	#[divan::bench(threads = [1, 4, 8])]
+	fn registry_read_stores(bencher: Bencher) {
+		use agentgateway::mcp::registry::{RegistryStore, RegistryStoreRef};
+		use agentgateway::store::Stores;
+
+		let stores = Stores::new();
+		stores.set_registry(Some(RegistryStoreRef::new(RegistryStore::new())));
+
+		bencher.bench(|| stores.get_registry());
+	}
+
 	// =========================================================================
 	// Default Injection Benchmarks
 	// =========================================================================
@@ -43,6 +43,7 @@ pub async fn run(config: Arc<Config>) -> anyhow::Result<Bound> {
 	)
 	.await
 	.context("readiness server starts")?;
+	let readiness_stores = readiness_server.stores();
 	// Run the readiness server in the data plane worker pool.
 	data_plane_pool.send(DataPlaneTask {
 		block_shutdown: false,
@@ -73,10 +74,10 @@ pub async fn run(config: Arc<Config>) -> anyhow::Result<Bound> {
 	// Build metrics and then the upstream client with metrics wired in
 	let sub_registry = metrics::sub_registry(&mut registry);
 	let tracer = trc::Tracer::new(&config.tracing)?.map(Arc::new);
-	let metrics_handle = Arc::new(crate::metrics::Metrics::new(
-		sub_registry,
-		config.logging.excluded_metrics.clone(),
-	));
+	let metrics_handle = Arc::new(
+		crate::metrics::Metrics::new(sub_registry, config.logging.excluded_metrics.clone())
+			.with_attribution_labels(config.logging.attribution_labels.clone()),
+	);
 	let client = client::Client::new(
 		&config.dns,
 		pool,
@@ -95,10 +96,11 @@ pub async fn run(config: Arc<Config>) -> anyhow::Result<Bound> {
 		std::mem::drop(state_mgr_task);
 	});
 	let stores = state_mgr.stores();
+	let _ = readiness_stores.set(stores.clone());
+	crate::metrics::RegistryCollector::register(metrics::sub_registry(&mut registry), stores.clone());
 	// Run the XDS state manager in the current tokio worker pool.
 	tokio::spawn(state_mgr.run());
 
-	#[allow(unused_mut)]
 	let mut admin_server = crate::management::admin::Service::new(
 		config.clone(),
 		stores.clone(),
@@ -124,7 +126,10 @@ pub async fn run(config: Arc<Config>) -> anyhow::Result<Bound> {
 		mcp_state: mcp::App::new(stores.clone(), config.session_encoder.clone()),
 	};
 
-	let gw = proxy::Gateway::new(Arc::new(pi), drain_rx.clone());
+	let pi = Arc::new(pi);
+	admin_server.set_proxy_inputs(pi.clone());
+
+	let gw = proxy::Gateway::new(pi, drain_rx.clone());
 
 	// Run the agentgateway in the data plane worker pool.
 	let mut xds_rx_for_proxy = xds_rx.clone();
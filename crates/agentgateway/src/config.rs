@@ -10,6 +10,7 @@ use std::{cmp, env};
 
 use crate::control::caclient;
 use crate::telemetry::log::{LoggingFields, MetricFields};
+use crate::telemetry::metrics::AttributionLabels;
 use crate::telemetry::trc;
 use crate::types::discovery::Identity;
 use crate::{
@@ -229,6 +230,15 @@ pub fn parse_config(contents: String, filename: Option<PathBuf>) -> anyhow::Resu
 		Some(s) => crate::http::sessionpersistence::Encoder::aes(s.key.expose_secret())?,
 	};
 
+	// Runtime state snapshots are always encrypted; reuse the session key when it is an AES key
+	let state_snapshot_encoder = match parse::<String>("STATE_SNAPSHOT_KEY")? {
+		Some(key) => Some(crate::http::sessionpersistence::Encoder::aes(&key)?),
+		None => match &session_encoder {
+			crate::http::sessionpersistence::Encoder::Aes(_) => Some(session_encoder.clone()),
+			crate::http::sessionpersistence::Encoder::Base64(_) => None,
+		},
+	};
+
 	Ok(crate::Config {
 		network: network.into(),
 		admin_addr,
@@ -304,6 +314,13 @@ pub fn parse_config(contents: String, filename: Option<PathBuf>) -> anyhow::Resu
 				.as_ref()
 				.and_then(|t| t.path.clone())
 				.unwrap_or_else(|| "/v1/traces".to_string()),
+			composition_sampling: Arc::new(
+				raw
+					.tracing
+					.as_ref()
+					.map(|t| t.composition_sampling.clone())
+					.unwrap_or_default(),
+			),
 		},
 		logging: telemetry::log::Config {
 			filter: raw
@@ -350,6 +367,13 @@ pub fn parse_config(contents: String, filename: Option<PathBuf>) -> anyhow::Resu
 						.collect::<frozen_collections::FzHashSet<String>>()
 				})
 				.unwrap_or_default(),
+			attribution_labels: AttributionLabels::new(
+				raw
+					.metrics
+					.as_ref()
+					.map(|f| f.attribution_labels.clone())
+					.unwrap_or_default(),
+			),
 			metric_fields: Arc::new(
 				raw
 					.metrics
@@ -390,6 +414,7 @@ pub fn parse_config(contents: String, filename: Option<PathBuf>) -> anyhow::Resu
 			),
 		},
 		session_encoder,
+		state_snapshot_encoder,
 		hbone: Arc::new(agent_hbone::Config {
 			// window size: per-stream limit
 			window_size: parse("HTTP2_STREAM_WINDOW_SIZE")?
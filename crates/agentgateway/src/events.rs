@@ -0,0 +1,225 @@
+// Internal event bus for gateway health events
+//
+// Components publish typed events as they happen to the bus they are given, normally the
+// gateway's own (`Stores::events`); telemetry sinks and the admin `/debug/events` stream
+// subscribe to it. Publishing never blocks: events are dropped when nobody is subscribed,
+// and subscribers that fall behind skip the oldest events.
+
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::Serialize;
+use tokio::sync::broadcast;
+
+use crate::mcp::registry::ReloadTrigger;
+use crate::mcp::registry::executor::ExecutionId;
+
+/// Events buffered per subscriber before the oldest are skipped
+pub const DEFAULT_EVENT_CAPACITY: usize = 1024;
+
+/// An event published on the bus
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GatewayEvent {
+	/// Publish time (milliseconds since the Unix epoch)
+	pub at_ms: u64,
+	#[serde(flatten)]
+	pub kind: EventKind,
+}
+
+/// What happened, serialized with a `type` tag
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum EventKind {
+	/// A registry reload attempt finished; `error` is set if the previous registry stayed active
+	#[serde(rename_all = "camelCase")]
+	RegistryReloaded {
+		trigger: ReloadTrigger,
+		#[serde(skip_serializing_if = "Option::is_none")]
+		source: Option<String>,
+		tools: usize,
+		duration_ms: f64,
+		#[serde(skip_serializing_if = "Option::is_none")]
+		error: Option<String>,
+	},
+	/// A circuit breaker tripped and is rejecting requests
+	#[serde(rename_all = "camelCase")]
+	CircuitOpened { name: String, failures: u32 },
+	/// A composition execution began
+	#[serde(rename_all = "camelCase")]
+	CompositionStarted {
+		composition: String,
+		execution: ExecutionId,
+	},
+	/// A pipeline step of a composition execution finished
+	#[serde(rename_all = "camelCase")]
+	CompositionStepCompleted {
+		composition: String,
+		execution: ExecutionId,
+		step: String,
+		duration_ms: u64,
+		ok: bool,
+	},
+	/// A composition execution returned an error
+	#[serde(rename_all = "camelCase")]
+	CompositionFailed {
+		composition: String,
+		execution: ExecutionId,
+		error: String,
+		duration_ms: u64,
+	},
+	/// A composition execution succeeded
+	#[serde(rename_all = "camelCase")]
+	CompositionFinished {
+		composition: String,
+		execution: ExecutionId,
+		duration_ms: u64,
+	},
+	/// A failed operation's input was sent to its dead letter destination
+	#[serde(rename_all = "camelCase")]
+	DeadLetterEmitted {
+		#[serde(skip_serializing_if = "Option::is_none")]
+		error: Option<String>,
+		attempts: u8,
+	},
+}
+
+impl EventKind {
+	/// The event's `type` tag
+	pub fn name(&self) -> &'static str {
+		match self {
+			EventKind::RegistryReloaded { .. } => "registry_reloaded",
+			EventKind::CircuitOpened { .. } => "circuit_opened",
+			EventKind::CompositionStarted { .. } => "composition_started",
+			EventKind::CompositionStepCompleted { .. } => "composition_step_completed",
+			EventKind::CompositionFailed { .. } => "composition_failed",
+			EventKind::CompositionFinished { .. } => "composition_finished",
+			EventKind::DeadLetterEmitted { .. } => "dead_letter_emitted",
+		}
+	}
+
+	/// The composition a composition lifecycle event belongs to
+	pub fn composition(&self) -> Option<&str> {
+		match self {
+			EventKind::CompositionStarted { composition, .. }
+			| EventKind::CompositionStepCompleted { composition, .. }
+			| EventKind::CompositionFailed { composition, .. }
+			| EventKind::CompositionFinished { composition, .. } => Some(composition),
+			_ => None,
+		}
+	}
+}
+
+impl GatewayEvent {
+	/// Stamp an event with the current time
+	pub fn new(kind: EventKind) -> Self {
+		let at_ms = SystemTime::now()
+			.duration_since(UNIX_EPOCH)
+			.map(|d| d.as_millis() as u64)
+			.unwrap_or_default();
+		Self { at_ms, kind }
+	}
+}
+
+/// Broadcast channel of gateway events; clones publish to the same subscribers
+#[derive(Debug, Clone)]
+pub struct EventBus {
+	tx: broadcast::Sender<Arc<GatewayEvent>>,
+}
+
+impl Default for EventBus {
+	fn default() -> Self {
+		Self::new(DEFAULT_EVENT_CAPACITY)
+	}
+}
+
+impl EventBus {
+	/// Create a bus buffering up to `capacity` events per subscriber
+	pub fn new(capacity: usize) -> Self {
+		let (tx, _) = broadcast::channel(capacity.max(1));
+		Self { tx }
+	}
+
+	/// Publish an event to current subscribers
+	pub fn publish(&self, kind: EventKind) {
+		// An error only means there are no subscribers
+		let _ = self.tx.send(Arc::new(GatewayEvent::new(kind)));
+	}
+
+	/// Receive events published from now on
+	pub fn subscribe(&self) -> broadcast::Receiver<Arc<GatewayEvent>> {
+		self.tx.subscribe()
+	}
+
+	/// Number of active subscribers
+	pub fn subscriber_count(&self) -> usize {
+		self.tx.receiver_count()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use serde_json::json;
+
+	use super::*;
+
+	#[tokio::test]
+	async fn test_publish_reaches_subscribers() {
+		let bus = EventBus::new(4);
+		// Publishing without subscribers is a no-op
+		bus.publish(EventKind::CircuitOpened {
+			name: "dropped".to_string(),
+			failures: 1,
+		});
+
+		let mut rx = bus.subscribe();
+		let execution = ExecutionId::new();
+		bus.clone().publish(EventKind::CompositionFailed {
+			composition: "research".to_string(),
+			execution,
+			error: "timeout".to_string(),
+			duration_ms: 12,
+		});
+
+		let event = rx.recv().await.unwrap();
+		assert_eq!(event.kind.name(), "composition_failed");
+		assert_eq!(event.kind.composition(), Some("research"));
+		let mut value = serde_json::to_value(event.as_ref()).unwrap();
+		value.as_object_mut().unwrap().remove("atMs");
+		assert_eq!(
+			value,
+			json!({
+				"type": "composition_failed",
+				"composition": "research",
+				"execution": execution.to_string(),
+				"error": "timeout",
+				"durationMs": 12
+			})
+		);
+		assert!(rx.try_recv().is_err());
+	}
+
+	#[tokio::test]
+	async fn test_slow_subscribers_skip_oldest() {
+		let bus = EventBus::new(2);
+		let mut rx = bus.subscribe();
+		for attempts in 1..=3 {
+			bus.publish(EventKind::DeadLetterEmitted {
+				error: None,
+				attempts,
+			});
+		}
+
+		assert!(matches!(
+			rx.recv().await,
+			Err(broadcast::error::RecvError::Lagged(1))
+		));
+		assert_eq!(
+			rx.recv().await.unwrap().kind,
+			EventKind::DeadLetterEmitted {
+				error: None,
+				attempts: 2
+			}
+		);
+	}
+}
@@ -2,6 +2,7 @@ use std::time::Duration;
 
 use serde_json::Value;
 
+use crate::events::{EventBus, EventKind};
 use crate::*;
 
 #[cfg(test)]
@@ -73,6 +74,7 @@ fn default_max_attempts() -> u8 {
 /// * `policy` - Dead letter policy configuration
 /// * `executor` - The inner executor that performs the actual operation
 /// * `dead_letter_handler` - Handler for sending to the dead letter destination
+/// * `events` - Bus to publish the dead-letter event on, if any
 /// * `input` - The input to the operation
 ///
 /// # Returns
@@ -82,6 +84,7 @@ pub async fn execute_with_dead_letter<E, D>(
 	policy: &Policy,
 	executor: &E,
 	dead_letter_handler: &D,
+	events: Option<&EventBus>,
 	input: Value,
 ) -> Result<Value, ExecutionError>
 where
@@ -115,6 +118,12 @@ where
 
 	// Send to dead letter handler (ignore errors for now)
 	let _ = dead_letter_handler.send(payload).await;
+	if let Some(events) = events {
+		events.publish(EventKind::DeadLetterEmitted {
+			error: last_error.as_ref().map(|e| e.to_string()),
+			attempts: policy.max_attempts,
+		});
+	}
 
 	if policy.rethrow {
 		Err(last_error.unwrap_or_else(|| ExecutionError::new("Unknown error")))
@@ -69,7 +69,8 @@ async fn test_dead_letter_success() {
 	let dead_letter = MockDeadLetterHandler::new();
 	let input = json!({"key": "value"});
 
-	let result = execute_with_dead_letter(&policy, &executor, &dead_letter, input.clone()).await;
+	let result =
+		execute_with_dead_letter(&policy, &executor, &dead_letter, None, input.clone()).await;
 
 	assert!(result.is_ok());
 	assert_eq!(result.unwrap(), json!({"result": "success"}));
@@ -91,7 +92,8 @@ async fn test_dead_letter_on_failure() {
 	let dead_letter = MockDeadLetterHandler::new();
 	let input = json!({"key": "value"});
 
-	let result = execute_with_dead_letter(&policy, &executor, &dead_letter, input.clone()).await;
+	let result =
+		execute_with_dead_letter(&policy, &executor, &dead_letter, None, input.clone()).await;
 
 	// Should return Ok(Null) when rethrow is false
 	assert!(result.is_ok());
@@ -119,7 +121,8 @@ async fn test_dead_letter_with_retry() {
 	let dead_letter = MockDeadLetterHandler::new();
 	let input = json!({"key": "value"});
 
-	let result = execute_with_dead_letter(&policy, &executor, &dead_letter, input.clone()).await;
+	let result =
+		execute_with_dead_letter(&policy, &executor, &dead_letter, None, input.clone()).await;
 
 	assert!(result.is_ok());
 	assert_eq!(result.unwrap(), json!({"result": "success after retries"}));
@@ -141,7 +144,8 @@ async fn test_dead_letter_rethrow() {
 	let dead_letter = MockDeadLetterHandler::new();
 	let input = json!({"key": "value"});
 
-	let result = execute_with_dead_letter(&policy, &executor, &dead_letter, input.clone()).await;
+	let result =
+		execute_with_dead_letter(&policy, &executor, &dead_letter, None, input.clone()).await;
 
 	// Should return error when rethrow is true
 	assert!(result.is_err());
@@ -166,7 +170,8 @@ async fn test_dead_letter_no_rethrow() {
 	let dead_letter = MockDeadLetterHandler::new();
 	let input = json!({"key": "value"});
 
-	let result = execute_with_dead_letter(&policy, &executor, &dead_letter, input.clone()).await;
+	let result =
+		execute_with_dead_letter(&policy, &executor, &dead_letter, None, input.clone()).await;
 
 	// Should return Ok(Null) when rethrow is false
 	assert!(result.is_ok());
@@ -187,7 +192,7 @@ async fn test_dead_letter_payload() {
 	let dead_letter = MockDeadLetterHandler::new();
 	let input = json!({"request": "data", "nested": {"value": 123}});
 
-	let _ = execute_with_dead_letter(&policy, &executor, &dead_letter, input.clone()).await;
+	let _ = execute_with_dead_letter(&policy, &executor, &dead_letter, None, input.clone()).await;
 
 	let received = dead_letter.get_received();
 	assert_eq!(received.len(), 1);
@@ -203,3 +208,31 @@ async fn test_dead_letter_payload() {
 	let timestamp = payload["timestamp"].as_str().unwrap();
 	assert!(chrono::DateTime::parse_from_rfc3339(timestamp).is_ok());
 }
+
+#[tokio::test]
+async fn test_dead_letter_emits_event() {
+	let policy = Policy {
+		dead_letter_tool: "dead_letter_queue".to_string(),
+		max_attempts: 2,
+		backoff: None,
+		rethrow: false,
+	};
+
+	let executor = MockExecutor::new(10, json!({"result": "success"})); // Will always fail
+	let dead_letter = MockDeadLetterHandler::new();
+	let events = EventBus::new(4);
+	let mut rx = events.subscribe();
+
+	let _ = execute_with_dead_letter(
+		&policy,
+		&executor,
+		&dead_letter,
+		Some(&events),
+		json!({"request": "data"}),
+	)
+	.await;
+
+	let event = rx.try_recv().unwrap();
+	assert_eq!(event.kind.name(), "dead_letter_emitted");
+	assert!(rx.try_recv().is_err());
+}
@@ -369,6 +369,7 @@ fn make_min_req_log() -> crate::telemetry::log::RequestLog {
 		fields: LoggingFields::default(),
 		metric_fields: Arc::new(MetricFields::default()),
 		excluded_metrics: FzHashSet::default(),
+		attribution_labels: Default::default(),
 		level: "info".to_string(),
 		format: crate::LoggingFormat::Text,
 	};
@@ -380,6 +381,7 @@ fn make_min_req_log() -> crate::telemetry::log::RequestLog {
 		random_sampling: None,
 		client_sampling: None,
 		path: "/v1/traces".to_string(),
+		composition_sampling: Default::default(),
 	};
 	let cel = log::CelLogging::new(log_cfg, tracing_cfg);
 	let mut prom = Registry::default();
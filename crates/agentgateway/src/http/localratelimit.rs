@@ -49,6 +49,54 @@ pub enum RateLimitType {
 	Tokens,
 }
 
+/// Quota left under a request rate limit, reported to callers so they can self-throttle
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RateLimitStatus {
+	pub limit: u64,
+	pub remaining: u64,
+	pub reset_seconds: u64,
+}
+
+impl RateLimitStatus {
+	/// The status closer to being exhausted
+	pub fn tightest(self, other: RateLimitStatus) -> RateLimitStatus {
+		if other.remaining < self.remaining {
+			other
+		} else {
+			self
+		}
+	}
+
+	/// Set the `x-ratelimit-*` headers describing this status
+	pub fn apply_headers(&self, headers: &mut ::http::HeaderMap) {
+		headers.insert(http::x_headers::X_RATELIMIT_LIMIT, self.limit.into());
+		headers.insert(
+			http::x_headers::X_RATELIMIT_REMAINING,
+			self.remaining.into(),
+		);
+		headers.insert(
+			http::x_headers::X_RATELIMIT_RESET,
+			self.reset_seconds.into(),
+		);
+	}
+}
+
+/// Request extension marking a request that exceeded a rate limit, but whose rejection is
+/// left to the backend so it can answer in its own protocol (MCP answers in JSON-RPC)
+#[derive(Debug, Clone, Copy)]
+pub struct RateLimited(pub RateLimitStatus);
+
+impl From<RateLimited> for ProxyError {
+	fn from(RateLimited(status): RateLimited) -> Self {
+		ProxyError::RateLimitExceeded {
+			limit: status.limit,
+			remaining: status.remaining,
+			reset_seconds: status.reset_seconds,
+		}
+	}
+}
+
 impl TryFrom<RateLimitSpec> for RateLimit {
 	type Error = ratelimit::Error;
 	fn try_from(value: RateLimitSpec) -> Result<Self, Self::Error> {
@@ -64,11 +112,13 @@ impl TryFrom<RateLimitSpec> for RateLimit {
 }
 
 impl RateLimit {
-	pub fn check_request(&self) -> Result<(), ProxyError> {
+	/// Take a token for a request, returning the quota left afterwards
+	///
+	/// Returns None for token limits, which only apply to LLM requests.
+	pub fn check_request(&self) -> Result<Option<RateLimitStatus>, ProxyError> {
 		if self.spec.limit_type != RateLimitType::Requests {
-			return Ok(());
+			return Ok(None);
 		}
-		// TODO: return headers on success, not just failure
 		self
 			.ratelimit
 			.try_wait()
@@ -76,7 +126,13 @@ impl RateLimit {
 				limit,
 				remaining,
 				reset_seconds: reset.as_secs(),
-			})
+			})?;
+		Ok(Some(RateLimitStatus {
+			limit: self.ratelimit.max_tokens(),
+			remaining: self.ratelimit.available(),
+			reset_seconds: (self.ratelimit.next_refill() - clocksource::precise::Instant::now())
+				.as_secs(),
+		}))
 	}
 
 	pub fn check_llm_request(&self, req: &LLMRequest) -> Result<(), ProxyError> {
@@ -174,7 +230,6 @@ mod ratelimit {
 		}
 
 		/// Returns the number of tokens currently available.
-		#[allow(dead_code)]
 		pub fn available(&self) -> u64 {
 			self.available.load(Ordering::Relaxed)
 		}
@@ -9,6 +9,7 @@ use std::time::{Duration, SystemTime};
 use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
 
+use crate::events::{EventBus, EventKind};
 use crate::*;
 
 /// Error type for circuit breaker operations
@@ -269,12 +270,34 @@ impl Default for CircuitBreakerSpec {
 /// Circuit breaker executor that manages state and controls request flow.
 pub struct CircuitBreakerExecutor<S: StateStore> {
 	store: Arc<S>,
+	/// Bus that circuit trips are published to, if any
+	events: Option<EventBus>,
 }
 
 impl<S: StateStore> CircuitBreakerExecutor<S> {
 	/// Create a new circuit breaker executor with the given state store
 	pub fn new(store: Arc<S>) -> Self {
-		Self { store }
+		Self {
+			store,
+			events: None,
+		}
+	}
+
+	/// Builder: publish circuit trips to `events`
+	pub fn with_events(mut self, events: EventBus) -> Self {
+		self.events = Some(events);
+		self
+	}
+
+	/// Open the circuit and announce it on the event bus
+	fn trip(&self, spec: &CircuitBreakerSpec, state: &mut CircuitState) {
+		state.transition_to_open();
+		if let Some(events) = &self.events {
+			events.publish(EventKind::CircuitOpened {
+				name: spec.name.clone(),
+				failures: state.failure_count,
+			});
+		}
 	}
 
 	/// Execute a request through the circuit breaker.
@@ -368,7 +391,7 @@ impl<S: StateStore> CircuitBreakerExecutor<S> {
 
 				// Check if we should trip the circuit
 				if state.failure_count >= spec.failure_threshold {
-					state.transition_to_open();
+					self.trip(spec, state);
 				}
 
 				// Save updated state
@@ -410,7 +433,7 @@ impl<S: StateStore> CircuitBreakerExecutor<S> {
 			},
 			Err(e) => {
 				// Failure in half-open state - back to open
-				state.transition_to_open();
+				self.trip(spec, state);
 				self
 					.store
 					.save_circuit_state(&spec.name, state)
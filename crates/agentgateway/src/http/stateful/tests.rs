@@ -8,6 +8,7 @@
 //! - test_circuit_half_open_recovery: Success closes circuit
 //! - test_circuit_half_open_failure: Failure reopens circuit
 //! - test_circuit_failure_window: Failures must be within window
+//! - test_circuit_trip_publishes_event: Opening the circuit is announced on the event bus
 
 use std::sync::Arc;
 use std::time::Duration;
@@ -341,3 +342,26 @@ async fn test_circuit_breaker_error_display() {
 	assert!(s.contains("test"));
 	assert!(s.contains("open"));
 }
+
+#[tokio::test]
+async fn test_circuit_trip_publishes_event() {
+	let events = EventBus::new(4);
+	let mut rx = events.subscribe();
+	let store = Arc::new(InMemoryStateStore::new());
+	let executor = CircuitBreakerExecutor::new(store.clone()).with_events(events);
+	let spec = test_spec("test_trip_publishes_event");
+
+	for _ in 0..spec.failure_threshold {
+		let _ = executor.execute(&spec, failure_op()).await;
+	}
+
+	let event = rx.try_recv().unwrap();
+	assert_eq!(
+		event.kind,
+		EventKind::CircuitOpened {
+			name: "test_trip_publishes_event".to_string(),
+			failures: 3,
+		}
+	);
+	assert!(rx.try_recv().is_err());
+}
@@ -26,6 +26,7 @@ pub mod claimcheck;
 pub mod client;
 pub mod config;
 pub mod control;
+pub mod events;
 pub mod http;
 pub mod json;
 pub mod llm;
@@ -53,7 +54,7 @@ use control::caclient;
 use telemetry::{metrics, trc};
 
 use crate::control::{AuthSource, RootCert};
-use crate::telemetry::trc::Protocol;
+use crate::telemetry::trc::{CompositionSampling, Protocol};
 use crate::types::agent::{ListenerTarget, PolicyTargetRef};
 
 #[derive(serde::Deserialize, Clone, Debug)]
@@ -229,6 +230,11 @@ pub struct RawTracing {
 	client_sampling: Option<StringBoolFloat>,
 	/// OTLP path. Default is /v1/traces
 	path: Option<String>,
+	/// Per-composition overrides of the trace sampling decision, keyed by composition name.
+	/// `always` records the composition span even when the request is not sampled;
+	/// `never` drops it even when the request is sampled.
+	#[serde(default)]
+	composition_sampling: HashMap<String, CompositionSampling>,
 }
 
 #[apply(schema_de!)]
@@ -259,6 +265,10 @@ pub struct RawMetrics {
 	#[serde(default)]
 	remove: Vec<String>,
 	fields: Option<RawMetricFields>,
+	/// Caller label values reported on the composition attribution metrics, by label name;
+	/// other values are reported as `other`
+	#[serde(default)]
+	attribution_labels: HashMap<String, Vec<String>>,
 }
 
 #[apply(schema_de!)]
@@ -396,6 +406,8 @@ pub struct Config {
 	pub proxy_metadata: ProxyMetadata,
 	pub threading_mode: ThreadingMode,
 	pub session_encoder: http::sessionpersistence::Encoder,
+	/// Key for encrypting runtime state snapshots; exports are refused without one.
+	pub state_snapshot_encoder: Option<http::sessionpersistence::Encoder>,
 	/// Handle for tasks/spans emitted on the admin runtime.
 	#[serde(skip)]
 	pub admin_runtime_handle: Option<tokio::runtime::Handle>,
@@ -14,12 +14,21 @@ use hyper::body::Incoming;
 use hyper::header::{CONTENT_TYPE, HeaderValue};
 use tokio::runtime::Handle;
 use tokio::time;
+use tokio_stream::StreamExt;
+use tokio_stream::wrappers::BroadcastStream;
+use tokio_stream::wrappers::errors::BroadcastStreamRecvError;
 use tracing::{info, warn};
 use tracing_subscriber::filter;
 
-use super::hyper_helpers::{Server, empty_response, plaintext_response};
-use crate::Config;
+use super::hyper_helpers::{Server, empty_response, json_response, plaintext_response};
+use crate::events::{EventBus, GatewayEvent};
 use crate::http::Response;
+use crate::mcp::registry::PromotionError;
+use crate::mcp::registry::executor::{RerunError, ToolInvoker, WorkerPermit};
+use crate::mcp::registry::{
+	CompositionVerbosity, DocsFormat, ExecutionPriority, RegistryStoreRef, parse_duration,
+};
+use crate::{Config, ProxyInputs};
 
 pub trait ConfigDumpHandler: Sync + Send {
 	fn key(&self) -> &'static str;
@@ -45,6 +54,8 @@ struct State {
 	config_dump_handlers: Vec<Arc<dyn ConfigDumpHandler>>,
 	admin_fallback: Option<Arc<dyn AdminFallback>>,
 	dataplane_handle: Handle,
+	/// Proxy inputs, for operations that connect to backends
+	proxy_inputs: Option<Arc<ProxyInputs>>,
 }
 
 pub struct Service {
@@ -98,6 +109,7 @@ impl Service {
 				config_dump_handlers: vec![],
 				admin_fallback: None,
 				dataplane_handle,
+				proxy_inputs: None,
 			},
 		)
 		.await
@@ -116,6 +128,10 @@ impl Service {
 		self.s.state_mut().admin_fallback = Some(handler);
 	}
 
+	pub fn set_proxy_inputs(&mut self, inputs: Arc<ProxyInputs>) {
+		self.s.state_mut().proxy_inputs = Some(inputs);
+	}
+
 	pub fn spawn(self) {
 		self.s.spawn(|state, req| async move {
 			match req.uri().path() {
@@ -144,6 +160,30 @@ impl Service {
 					.await
 				},
 				"/logging" => Ok(handle_logging(req).await),
+				"/debug/compositions" => handle_composition_executions(req, &state.stores).await,
+				"/debug/composition_verbosity" => Ok(handle_composition_verbosity(req, &state.stores)),
+				"/debug/registry/kill_switch" => Ok(handle_kill_switch(req, &state.stores)),
+				"/debug/registry/changes" => Ok(handle_registry_changes(req, &state.stores)),
+				"/debug/registry/usage" => Ok(handle_registry_usage(req, &state.stores)),
+				"/debug/registry/docs" => Ok(handle_registry_docs(req, &state.stores)),
+				"/debug/registry/candidate" => handle_registry_candidate(req, &state.stores).await,
+				"/debug/events" => Ok(handle_events(&state.stores.events, |_| true)),
+				"/debug/compositions/events" => Ok(handle_composition_events(req, &state.stores)),
+				"/debug/compositions/cache" => handle_composition_cache(req, &state.stores).await,
+				"/debug/compositions/dead_letters" => {
+					handle_dead_letters(req, &state.stores, state.proxy_inputs.as_ref()).await
+				},
+				"/debug/compositions/captures" => {
+					handle_captures(req, &state.stores, state.proxy_inputs.as_ref()).await
+				},
+				"/debug/compositions/journal" => handle_journal(req, &state.stores).await,
+				"/debug/state/export" => handle_state_export(req, &state.stores, &state.config).await,
+				"/debug/state/import" => handle_state_import(req, &state.stores, &state.config).await,
+				"/debug/upstreams" => handle_upstream_health(&state.stores),
+				"/debug/upstreams/latency" => handle_upstream_latency(&state.stores),
+				"/debug/registry/scaffold" => {
+					handle_registry_scaffold(req, state.proxy_inputs.clone()).await
+				},
 				_ => {
 					if let Some(h) = &state.admin_fallback {
 						Ok(h.handle(req).await)
@@ -171,6 +211,10 @@ async fn handle_dashboard(_req: Request<Incoming>) -> Response {
 		("quitquitquit", "shut down the server"),
 		("config_dump", "dump the current agentgateway configuration"),
 		("logging", "query/changing logging levels"),
+		(
+			"debug/events",
+			"stream gateway health events as server-sent events",
+		),
 	];
 
 	let mut api_rows = String::new();
@@ -324,6 +368,911 @@ async fn handle_config_dump(
 	)
 }
 
+/// Query parameters of an admin request; a repeated parameter keeps its last value
+fn query_params(req: &Request<Incoming>) -> HashMap<String, String> {
+	req
+		.uri()
+		.query()
+		.map(|v| {
+			url::form_urlencoded::parse(v.as_bytes())
+				.into_owned()
+				.collect()
+		})
+		.unwrap_or_default()
+}
+
+fn no_registry() -> Response {
+	plaintext_response(
+		hyper::StatusCode::NOT_FOUND,
+		"no registry configured\n".to_string(),
+	)
+}
+
+fn registry_not_loaded() -> Response {
+	plaintext_response(
+		hyper::StatusCode::SERVICE_UNAVAILABLE,
+		"registry not loaded\n".to_string(),
+	)
+}
+
+fn proxy_not_running() -> Response {
+	plaintext_response(
+		hyper::StatusCode::SERVICE_UNAVAILABLE,
+		"the proxy is not running\n".to_string(),
+	)
+}
+
+fn bad_request(message: &str, help: &str) -> Response {
+	plaintext_response(hyper::StatusCode::BAD_REQUEST, format!("{message}\n{help}"))
+}
+
+/// Dump recent composition executions (newest first), optionally filtered with `?name=<composition>`,
+/// or look one up with `?executionId=<id>`
+async fn handle_composition_executions(
+	req: Request<Incoming>,
+	stores: &crate::store::Stores,
+) -> anyhow::Result<Response> {
+	let Some(registry) = stores.get_registry() else {
+		return Ok(no_registry());
+	};
+	let log = registry.execution_log();
+	let qp = query_params(&req);
+	let body = if let Some(execution_id) = qp.get("executionId") {
+		let Ok(execution_id) = execution_id.parse() else {
+			return Ok(plaintext_response(
+				hyper::StatusCode::BAD_REQUEST,
+				"invalid executionId\n".to_string(),
+			));
+		};
+		let Some((name, record)) = log.find(execution_id) else {
+			return Ok(plaintext_response(
+				hyper::StatusCode::NOT_FOUND,
+				format!("no recorded execution {execution_id}\n"),
+			));
+		};
+		serde_json::to_string_pretty(&HashMap::from([(name, vec![record])]))?
+	} else if let Some(name) = qp.get("name") {
+		serde_json::to_string_pretty(&log.recent(name))?
+	} else {
+		serde_json::to_string_pretty(&log.snapshot())?
+	};
+	Ok(json_response(body))
+}
+
+/// Dump the circuit breaker state of every MCP upstream that has one
+fn handle_upstream_health(stores: &crate::store::Stores) -> anyhow::Result<Response> {
+	let body = serde_json::to_string_pretty(&stores.upstream_health.snapshot())?;
+	Ok(json_response(body))
+}
+
+/// Dump the recent p95 latency of every upstream target registry calls went to, with the
+/// priorities being shed
+fn handle_upstream_latency(stores: &crate::store::Stores) -> anyhow::Result<Response> {
+	let latency = stores
+		.get_registry()
+		.map(|r| r.load_shedder().snapshot())
+		.unwrap_or_default();
+	let body = serde_json::to_string_pretty(&latency)?;
+	Ok(json_response(body))
+}
+
+/// List the tools of an MCP backend target and scaffold a registry entry for each
+/// (`?backend=<namespace/name>&target=<name>`)
+async fn handle_registry_scaffold(
+	req: Request<Incoming>,
+	inputs: Option<Arc<ProxyInputs>>,
+) -> anyhow::Result<Response> {
+	let Some(inputs) = inputs else {
+		return Ok(proxy_not_running());
+	};
+	let params = query_params(&req);
+	let (Some(backend), Some(target)) = (params.get("backend"), params.get("target")) else {
+		return Ok(plaintext_response(
+			hyper::StatusCode::BAD_REQUEST,
+			"usage: /debug/registry/scaffold?backend=<namespace/name>&target=<name>\n".to_string(),
+		));
+	};
+	let doc = match inputs
+		.mcp_state
+		.scaffold_registry(inputs.clone(), backend, target)
+		.await
+	{
+		Ok(doc) => doc,
+		Err(e) => {
+			return Ok(plaintext_response(
+				hyper::StatusCode::BAD_GATEWAY,
+				format!("failed to scaffold registry entries: {e:#}\n"),
+			));
+		},
+	};
+	info!(backend, target, "scaffolded registry entries");
+	Ok(json_response(serde_json::to_string_pretty(&doc)?))
+}
+
+/// Stream composition lifecycle events, optionally for a single composition (`?name=`)
+fn handle_composition_events(req: Request<Incoming>, stores: &crate::store::Stores) -> Response {
+	let Some(registry) = stores.get_registry() else {
+		return no_registry();
+	};
+	let name = query_params(&req).remove("name");
+	handle_events(registry.events(), move |event| {
+		match event.kind.composition() {
+			Some(composition) => name.as_deref().is_none_or(|name| name == composition),
+			None => false,
+		}
+	})
+}
+
+/// Stream gateway events matching `filter` as server-sent events until the client disconnects
+fn handle_events(
+	events: &EventBus,
+	filter: impl Fn(&GatewayEvent) -> bool + Send + 'static,
+) -> Response {
+	let stream = BroadcastStream::new(events.subscribe()).filter_map(move |event| {
+		let frame = match event {
+			Ok(event) if !filter(event.as_ref()) => return None,
+			Ok(event) => match serde_json::to_string(event.as_ref()) {
+				Ok(data) => format!("event: {}\ndata: {data}\n\n", event.kind.name()),
+				Err(e) => return Some(Err(e)),
+			},
+			// Tell the client how many events it missed rather than closing the stream
+			Err(BroadcastStreamRecvError::Lagged(n)) => format!(": skipped {n} events\n\n"),
+		};
+		Some(Ok::<_, serde_json::Error>(bytes::Bytes::from(frame)))
+	});
+	::http::Response::builder()
+		.status(hyper::StatusCode::OK)
+		.header(hyper::header::CONTENT_TYPE, "text/event-stream")
+		.header(hyper::header::CACHE_CONTROL, "no-cache")
+		.body(crate::http::Body::from_stream(stream))
+		.expect("builder with known status code should not fail")
+}
+
+static COMPOSITION_CACHE_HELP: &str = "
+usage: GET /debug/compositions/cache\t\t\t\t\t(To list hit/miss counts and entries per composition)
+usage: POST /debug/compositions/cache?name=<composition>\t\t\t(To purge a composition's entries)
+usage: POST /debug/compositions/cache?prefix=<key prefix>[&name=<composition>]\t(To purge entries by key prefix)
+usage: POST /debug/compositions/cache?all\t\t\t\t(To purge every entry)
+";
+
+/// Inspect cache pattern hit/miss counts, or purge cached results after a backend data fix
+async fn handle_composition_cache(
+	req: Request<Incoming>,
+	stores: &crate::store::Stores,
+) -> anyhow::Result<Response> {
+	let Some(registry) = stores.get_registry() else {
+		return Ok(no_registry());
+	};
+	let cache = registry.result_cache();
+	let body = match *req.method() {
+		hyper::Method::GET => serde_json::to_string_pretty(&cache.snapshot().await?)?,
+		hyper::Method::POST => {
+			let qp = query_params(&req);
+			let name = qp.get("name").map(String::as_str);
+			let prefix = qp.get("prefix").map(String::as_str);
+			// Purging everything must be asked for explicitly
+			if name.is_none() && prefix.is_none() && !qp.contains_key("all") {
+				return Ok(plaintext_response(
+					hyper::StatusCode::BAD_REQUEST,
+					format!("name, prefix or all is required\n{COMPOSITION_CACHE_HELP}"),
+				));
+			}
+			let purged = cache.purge(name, prefix).await?;
+			serde_json::to_string_pretty(&serde_json::json!({ "purged": purged }))?
+		},
+		_ => {
+			return Ok(plaintext_response(
+				hyper::StatusCode::METHOD_NOT_ALLOWED,
+				format!("Invalid HTTP method\n{COMPOSITION_CACHE_HELP}"),
+			));
+		},
+	};
+	Ok(json_response(body))
+}
+
+static DEAD_LETTERS_HELP: &str = "
+usage: GET /debug/compositions/dead_letters[?name=<composition>][&offset=<n>][&limit=<n>]\t(To list entries, newest first)
+usage: GET /debug/compositions/dead_letters?id=<id>\t\t\t\t\t(To inspect an entry)
+usage: GET /debug/compositions/dead_letters?executionId=<execution id>\t\t\t(To list an execution's entries)
+usage: POST /debug/compositions/dead_letters?replay=<id>[,<id>...]&backend=<namespace/name>\t(To replay entries through their composition)
+usage: POST /debug/compositions/dead_letters?discard=<id>[,<id>...]\t\t\t(To drop entries)
+
+hint: limit defaults to 50 (max 500); replays that succeed remove their entry
+hint: replays call the backend with the gateway's own credentials, not the original caller's
+hint: entries whose input was redacted cannot be replayed
+";
+
+/// Resolve the invoker of the `backend` an operator replays or re-runs compositions through,
+/// and wait for a batch lane composition worker to run them on, counted in the lane's queue
+/// depth like the calls of agents
+async fn operator_run(
+	registry: &RegistryStoreRef,
+	qp: &HashMap<String, String>,
+	proxy_inputs: Option<&Arc<ProxyInputs>>,
+	help: &str,
+) -> Result<(Arc<dyn ToolInvoker>, WorkerPermit), Response> {
+	let Some(backend) = qp.get("backend") else {
+		return Err(bad_request("backend is required", help));
+	};
+	let Some(inputs) = proxy_inputs else {
+		return Err(proxy_not_running());
+	};
+	let invoker = inputs
+		.mcp_state
+		.operator_invoker(inputs.clone(), backend)
+		.map_err(|e| bad_request(&format!("invalid backend: {e}"), help))?;
+	let permit = registry
+		.acquire_worker(ExecutionPriority::Batch, Some(inputs.metrics.as_ref()))
+		.await;
+	Ok((invoker, permit))
+}
+
+/// Browse dead-lettered composition failures, and replay or discard them
+async fn handle_dead_letters(
+	req: Request<Incoming>,
+	stores: &crate::store::Stores,
+	proxy_inputs: Option<&Arc<ProxyInputs>>,
+) -> anyhow::Result<Response> {
+	let Some(registry) = stores.get_registry() else {
+		return Ok(no_registry());
+	};
+	let qp = query_params(&req);
+	let parse_ids =
+		|ids: &str| -> Option<Vec<u64>> { ids.split(',').map(|id| id.trim().parse().ok()).collect() };
+	let queue = registry.dead_letters();
+	let body = match *req.method() {
+		hyper::Method::GET => {
+			if let Some(id) = qp.get("id") {
+				let Ok(id) = id.parse() else {
+					return Ok(bad_request("invalid id", DEAD_LETTERS_HELP));
+				};
+				let Some(entry) = queue.get(id) else {
+					return Ok(plaintext_response(
+						hyper::StatusCode::NOT_FOUND,
+						format!("no dead letter entry {id}\n"),
+					));
+				};
+				serde_json::to_string_pretty(&entry)?
+			} else if let Some(execution_id) = qp.get("executionId") {
+				let Ok(execution_id) = execution_id.parse() else {
+					return Ok(bad_request("invalid executionId", DEAD_LETTERS_HELP));
+				};
+				serde_json::to_string_pretty(&queue.for_execution(execution_id))?
+			} else {
+				let offset = match qp.get("offset").map(|v| v.parse::<usize>()) {
+					None => 0,
+					Some(Ok(offset)) => offset,
+					Some(Err(_)) => return Ok(bad_request("invalid offset", DEAD_LETTERS_HELP)),
+				};
+				let limit = match qp.get("limit").map(|v| v.parse::<usize>()) {
+					None => 50,
+					Some(Ok(limit)) => limit.min(500),
+					Some(Err(_)) => return Ok(bad_request("invalid limit", DEAD_LETTERS_HELP)),
+				};
+				let page = queue.list(qp.get("name").map(String::as_str), offset, limit);
+				serde_json::to_string_pretty(&page)?
+			}
+		},
+		hyper::Method::POST => {
+			if let Some(ids) = qp.get("replay") {
+				let Some(ids) = parse_ids(ids) else {
+					return Ok(bad_request("invalid replay ids", DEAD_LETTERS_HELP));
+				};
+				let run = operator_run(&registry, &qp, proxy_inputs, DEAD_LETTERS_HELP).await;
+				let (invoker, permit) = match run {
+					Ok(run) => run,
+					Err(response) => return Ok(response),
+				};
+				let Some(outcomes) = registry.replay_dead_letters(&ids, invoker, permit).await else {
+					return Ok(registry_not_loaded());
+				};
+				serde_json::to_string_pretty(&outcomes)?
+			} else if let Some(ids) = qp.get("discard") {
+				let Some(ids) = parse_ids(ids) else {
+					return Ok(bad_request("invalid discard ids", DEAD_LETTERS_HELP));
+				};
+				let discarded = queue.remove(&ids);
+				serde_json::to_string_pretty(&serde_json::json!({ "discarded": discarded }))?
+			} else {
+				return Ok(bad_request(
+					"replay or discard is required",
+					DEAD_LETTERS_HELP,
+				));
+			}
+		},
+		_ => {
+			return Ok(plaintext_response(
+				hyper::StatusCode::METHOD_NOT_ALLOWED,
+				format!("Invalid HTTP method\n{DEAD_LETTERS_HELP}"),
+			));
+		},
+	};
+	Ok(json_response(body))
+}
+
+static CAPTURES_HELP: &str = "
+usage: GET /debug/compositions/captures[?name=<composition>]\t\t(To list captured executions, newest first)
+usage: GET /debug/compositions/captures?id=<id>\t\t\t(To inspect a capture's steps)
+usage: GET /debug/compositions/captures?executionId=<execution id>\t(To find an execution's capture)
+usage: POST /debug/compositions/captures?id=<id>&from=<step>&backend=<namespace/name>\t(To re-run a capture from a step)
+
+hint: set a composition's verbosity to capture to record its executions
+hint: a re-run body may replace outputs of earlier steps, e.g. {\"search\": {\"hits\": []}}
+hint: re-runs call the backend with the gateway's own credentials, not the original caller's
+hint: captures keep only redacted payloads; redacted step outputs must be replaced to re-run past them
+";
+
+static JOURNAL_HELP: &str = "
+usage: GET /debug/compositions/journal		(To list journaled executions in flight, oldest first)
+usage: POST /debug/compositions/journal?takeover	(To take over the journaled executions of a failed instance)
+
+hint: after a takeover, a caller retrying an in-flight call resumes its execution here;
+fence off the failed instance first, or both may continue the same execution
+";
+
+/// List in-flight executions journaled for a standby, and take them over
+async fn handle_journal(
+	req: Request<Incoming>,
+	stores: &crate::store::Stores,
+) -> anyhow::Result<Response> {
+	let Some(journal) = stores
+		.get_registry()
+		.and_then(|r| r.execution_journal().cloned())
+	else {
+		return Ok(plaintext_response(
+			hyper::StatusCode::NOT_FOUND,
+			format!("no execution journal configured\n{JOURNAL_HELP}"),
+		));
+	};
+	let takeover = query_params(&req).contains_key("takeover");
+	let body = match (req.method().clone(), takeover) {
+		(hyper::Method::GET, false) => {
+			let executions: Vec<_> = journal.list().await?.iter().map(|e| e.redacted()).collect();
+			serde_json::to_string_pretty(&executions)?
+		},
+		(hyper::Method::POST, true) => {
+			let taken = journal.take_over().await?;
+			info!(
+				node = journal.node(),
+				executions = taken,
+				"took over journaled executions"
+			);
+			serde_json::to_string_pretty(&serde_json::json!({"takenOver": taken}))?
+		},
+		_ => {
+			return Ok(plaintext_response(
+				hyper::StatusCode::METHOD_NOT_ALLOWED,
+				format!("Invalid HTTP method\n{JOURNAL_HELP}"),
+			));
+		},
+	};
+	Ok(json_response(body))
+}
+
+static CANDIDATE_HELP: &str = "
+usage: GET /debug/registry/candidate			(To inspect the staged candidate registry and what it changes)
+usage: POST /debug/registry/candidate?test		(To run the smoke tests against the candidate)
+usage: POST /debug/registry/candidate?promote[=<id>]	(To smoke test the candidate and make it active)
+usage: POST /debug/registry/candidate?discard		(To drop the candidate)
+
+hint: pass the id seen on inspection to promote, so a candidate staged since isn't promoted unseen
+";
+
+/// Inspect, smoke test, promote or discard the candidate registry of a store with manual
+/// promotion
+async fn handle_registry_candidate(
+	req: Request<Incoming>,
+	stores: &crate::store::Stores,
+) -> anyhow::Result<Response> {
+	let Some((registry, promotion)) = stores
+		.get_registry()
+		.and_then(|r| r.manual_promotion().cloned().map(|p| (r, p)))
+	else {
+		return Ok(plaintext_response(
+			hyper::StatusCode::NOT_FOUND,
+			format!("manual promotion not configured\n{CANDIDATE_HELP}"),
+		));
+	};
+	let qp = query_params(&req);
+	let no_candidate = || {
+		Ok(plaintext_response(
+			hyper::StatusCode::NOT_FOUND,
+			"no candidate registry staged\n".to_string(),
+		))
+	};
+	let body = match *req.method() {
+		hyper::Method::GET => {
+			let Some(candidate) = promotion.candidate() else {
+				return no_candidate();
+			};
+			serde_json::to_string_pretty(&candidate.summary(registry.get_arc().as_deref()))?
+		},
+		hyper::Method::POST if qp.contains_key("test") => {
+			let Some(candidate) = promotion.candidate() else {
+				return no_candidate();
+			};
+			let results = promotion.test(&candidate).await;
+			serde_json::to_string_pretty(&serde_json::json!({
+				"id": candidate.id,
+				"smokeTests": results,
+			}))?
+		},
+		hyper::Method::POST if qp.contains_key("promote") => {
+			let id = match qp.get("promote").map(String::as_str) {
+				None | Some("") => None,
+				Some(id) => match id.parse() {
+					Ok(id) => Some(id),
+					Err(_) => {
+						return Ok(plaintext_response(
+							hyper::StatusCode::BAD_REQUEST,
+							format!("invalid candidate id\n{CANDIDATE_HELP}"),
+						));
+					},
+				},
+			};
+			match registry.promote_candidate(id).await {
+				Ok(candidate) => {
+					info!(candidate = candidate.id, "promoted candidate registry");
+					serde_json::to_string_pretty(&serde_json::json!({
+						"promoted": candidate.id,
+						"smokeTests": candidate.smoke_test_results().unwrap_or_default(),
+					}))?
+				},
+				Err(PromotionError::NoCandidate) => return no_candidate(),
+				Err(e) => {
+					return Ok(plaintext_response(
+						hyper::StatusCode::CONFLICT,
+						format!("candidate not promoted: {e}\n"),
+					));
+				},
+			}
+		},
+		hyper::Method::POST if qp.contains_key("discard") => {
+			let Some(candidate) = registry.discard_candidate() else {
+				return no_candidate();
+			};
+			serde_json::to_string_pretty(&serde_json::json!({ "discarded": candidate.id }))?
+		},
+		_ => {
+			return Ok(plaintext_response(
+				hyper::StatusCode::BAD_REQUEST,
+				format!("test, promote or discard is required\n{CANDIDATE_HELP}"),
+			));
+		},
+	};
+	Ok(json_response(body))
+}
+
+/// Largest set of step outputs accepted for a re-run
+const CAPTURE_OVERRIDES_LIMIT: usize = 1024 * 1024;
+
+/// Browse captured composition executions, and re-run them from a step
+async fn handle_captures(
+	req: Request<Incoming>,
+	stores: &crate::store::Stores,
+	proxy_inputs: Option<&Arc<ProxyInputs>>,
+) -> anyhow::Result<Response> {
+	let Some(registry) = stores.get_registry() else {
+		return Ok(no_registry());
+	};
+	let qp = query_params(&req);
+	let id = match qp.get("id").map(|id| id.parse::<u64>()) {
+		None => None,
+		Some(Ok(id)) => Some(id),
+		Some(Err(_)) => return Ok(bad_request("invalid id", CAPTURES_HELP)),
+	};
+	let captures = registry.captures();
+	let body = match (req.method().clone(), id) {
+		(hyper::Method::GET, Some(id)) => {
+			let Some(capture) = captures.get(id) else {
+				return Ok(plaintext_response(
+					hyper::StatusCode::NOT_FOUND,
+					format!("no captured execution {id}\n"),
+				));
+			};
+			serde_json::to_string_pretty(&capture)?
+		},
+		(hyper::Method::GET, None) => match qp.get("executionId") {
+			Some(execution_id) => {
+				let Ok(execution_id) = execution_id.parse() else {
+					return Ok(bad_request("invalid executionId", CAPTURES_HELP));
+				};
+				let Some(capture) = captures.find_execution(execution_id) else {
+					return Ok(plaintext_response(
+						hyper::StatusCode::NOT_FOUND,
+						format!("no captured execution {execution_id}\n"),
+					));
+				};
+				serde_json::to_string_pretty(&capture)?
+			},
+			None => serde_json::to_string_pretty(&captures.list(qp.get("name").map(String::as_str)))?,
+		},
+		(hyper::Method::POST, Some(id)) => {
+			let Some(from) = qp.get("from") else {
+				return Ok(bad_request("from is required", CAPTURES_HELP));
+			};
+			let body = crate::http::read_body_with_limit(
+				crate::http::Body::new(req.into_body()),
+				CAPTURE_OVERRIDES_LIMIT,
+			)
+			.await?;
+			let overrides = if body.iter().all(u8::is_ascii_whitespace) {
+				serde_json::Map::new()
+			} else {
+				match serde_json::from_slice(&body) {
+					Ok(overrides) => overrides,
+					Err(e) => {
+						return Ok(bad_request(
+							&format!("invalid step outputs: {e}"),
+							CAPTURES_HELP,
+						));
+					},
+				}
+			};
+			let run = operator_run(&registry, &qp, proxy_inputs, CAPTURES_HELP).await;
+			let (invoker, permit) = match run {
+				Ok(run) => run,
+				Err(response) => return Ok(response),
+			};
+			match registry
+				.rerun_capture(id, from, overrides, invoker, permit)
+				.await
+			{
+				None => return Ok(registry_not_loaded()),
+				Some(Ok(capture)) => serde_json::to_string_pretty(&capture)?,
+				Some(Err(e @ RerunError::NotFound(_))) => {
+					return Ok(plaintext_response(
+						hyper::StatusCode::NOT_FOUND,
+						format!("{e}\n"),
+					));
+				},
+				Some(Err(e)) => return Ok(bad_request(&e.to_string(), CAPTURES_HELP)),
+			}
+		},
+		(hyper::Method::POST, None) => return Ok(bad_request("id is required", CAPTURES_HELP)),
+		_ => {
+			return Ok(plaintext_response(
+				hyper::StatusCode::METHOD_NOT_ALLOWED,
+				format!("Invalid HTTP method\n{CAPTURES_HELP}"),
+			));
+		},
+	};
+	Ok(json_response(body))
+}
+
+static STATE_SNAPSHOT_HELP: &str = "
+usage: GET /debug/state/export\t\t\t(To download an encrypted snapshot of the runtime state stores)
+usage: POST /debug/state/import\t\t\t(To restore a snapshot exported by another instance)
+
+hint: snapshots are sealed with STATE_SNAPSHOT_KEY (or the AES session key), which both instances must share
+";
+
+/// Largest snapshot accepted by the import endpoint
+const STATE_SNAPSHOT_LIMIT: usize = 64 * 1024 * 1024;
+
+/// Export the runtime state stores, sealed with the snapshot key, for a blue/green swap
+async fn handle_state_export(
+	req: Request<Incoming>,
+	stores: &crate::store::Stores,
+	config: &Config,
+) -> anyhow::Result<Response> {
+	if *req.method() != hyper::Method::GET {
+		return Ok(plaintext_response(
+			hyper::StatusCode::METHOD_NOT_ALLOWED,
+			format!("Invalid HTTP method\n{STATE_SNAPSHOT_HELP}"),
+		));
+	}
+	let Some(encoder) = &config.state_snapshot_encoder else {
+		return Ok(plaintext_response(
+			hyper::StatusCode::PRECONDITION_FAILED,
+			format!("no snapshot key configured\n{STATE_SNAPSHOT_HELP}"),
+		));
+	};
+	let snapshot = stores.export_state().await?;
+	let sealed = encoder.encrypt(&serde_json::to_string(&snapshot)?)?;
+	info!(
+		entries = snapshot.stores.values().map(Vec::len).sum::<usize>(),
+		"exported runtime state snapshot"
+	);
+	Ok(
+		::http::Response::builder()
+			.status(hyper::StatusCode::OK)
+			.header(hyper::header::CONTENT_TYPE, "application/octet-stream")
+			.body(sealed.into())
+			.expect("builder with known status code should not fail"),
+	)
+}
+
+/// Restore runtime state exported by another instance sharing the snapshot key
+async fn handle_state_import(
+	req: Request<Incoming>,
+	stores: &crate::store::Stores,
+	config: &Config,
+) -> anyhow::Result<Response> {
+	if *req.method() != hyper::Method::POST {
+		return Ok(plaintext_response(
+			hyper::StatusCode::METHOD_NOT_ALLOWED,
+			format!("Invalid HTTP method\n{STATE_SNAPSHOT_HELP}"),
+		));
+	}
+	let Some(encoder) = &config.state_snapshot_encoder else {
+		return Ok(plaintext_response(
+			hyper::StatusCode::PRECONDITION_FAILED,
+			format!("no snapshot key configured\n{STATE_SNAPSHOT_HELP}"),
+		));
+	};
+	let body = crate::http::read_body_with_limit(
+		crate::http::Body::new(req.into_body()),
+		STATE_SNAPSHOT_LIMIT,
+	)
+	.await?;
+	let snapshot: crate::stateful::StateSnapshot = match encoder
+		.decrypt(String::from_utf8_lossy(&body).trim())
+		.map_err(anyhow::Error::from)
+		.and_then(|plain| Ok(serde_json::from_slice(&plain)?))
+	{
+		Ok(snapshot) => snapshot,
+		Err(e) => {
+			return Ok(plaintext_response(
+				hyper::StatusCode::BAD_REQUEST,
+				format!("invalid snapshot: {e}\n"),
+			));
+		},
+	};
+	let restored = match stores.import_state(&snapshot).await {
+		Ok(restored) => restored,
+		Err(e @ crate::stateful::SnapshotError::UnsupportedVersion(_)) => {
+			return Ok(plaintext_response(
+				hyper::StatusCode::BAD_REQUEST,
+				format!("{e}\n"),
+			));
+		},
+		Err(e) => return Err(e.into()),
+	};
+	info!(restored, "imported runtime state snapshot");
+	Ok(json_response(serde_json::to_string_pretty(
+		&serde_json::json!({ "restored": restored }),
+	)?))
+}
+
+static COMPOSITION_VERBOSITY_HELP: &str = "
+usage: GET /debug/composition_verbosity\t\t\t\t\t(To list active overrides)
+usage: POST /debug/composition_verbosity?tool=<name>&level=<level>[&ttl=<duration>]\t(To override a tool, default ttl 5m)
+usage: POST /debug/composition_verbosity?tool=<name>&reset\t\t\t(To revert a tool)
+usage: POST /debug/composition_verbosity?reset\t\t\t\t(To revert all tools)
+
+hint: level:\toff|summary|steps|full|capture (capture also retains executions for /debug/compositions/captures)
+";
+
+/// Change composition debug verbosity per tool at runtime; overrides revert after their TTL
+fn handle_composition_verbosity(req: Request<Incoming>, stores: &crate::store::Stores) -> Response {
+	let Some(registry) = stores.get_registry() else {
+		return no_registry();
+	};
+	let overrides = registry.verbosity_overrides();
+	let list = || match serde_json::to_string_pretty(&overrides.snapshot()) {
+		Ok(body) => json_response(body),
+		Err(e) => plaintext_response(hyper::StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+	};
+	match *req.method() {
+		hyper::Method::GET => list(),
+		hyper::Method::POST => {
+			let qp = query_params(&req);
+			let tool = qp.get("tool");
+			if qp.contains_key("reset") {
+				match tool {
+					Some(tool) => {
+						overrides.clear(tool);
+					},
+					None => overrides.clear_all(),
+				}
+				return list();
+			}
+			let (Some(tool), Some(level)) = (tool, qp.get("level")) else {
+				return plaintext_response(
+					hyper::StatusCode::BAD_REQUEST,
+					format!("tool and level are required\n{COMPOSITION_VERBOSITY_HELP}"),
+				);
+			};
+			let level = match level.parse::<CompositionVerbosity>() {
+				Ok(level) => level,
+				Err(e) => {
+					return plaintext_response(
+						hyper::StatusCode::BAD_REQUEST,
+						format!("{e}\n{COMPOSITION_VERBOSITY_HELP}"),
+					);
+				},
+			};
+			let ttl = match qp.get("ttl").map(|t| parse_duration(t)) {
+				None => Duration::from_secs(300),
+				Some(Ok(ttl)) => ttl,
+				Some(Err(e)) => {
+					return plaintext_response(
+						hyper::StatusCode::BAD_REQUEST,
+						format!("invalid ttl: {e}\n{COMPOSITION_VERBOSITY_HELP}"),
+					);
+				},
+			};
+			if let Err(e) = overrides.set(tool, level, ttl) {
+				return plaintext_response(
+					hyper::StatusCode::BAD_REQUEST,
+					format!("invalid ttl: {e}\n{COMPOSITION_VERBOSITY_HELP}"),
+				);
+			}
+			list()
+		},
+		_ => plaintext_response(
+			hyper::StatusCode::METHOD_NOT_ALLOWED,
+			format!("Invalid HTTP method\n{COMPOSITION_VERBOSITY_HELP}"),
+		),
+	}
+}
+
+/// What recent registry reloads changed, newest first; `?limit=<n>` keeps the latest n
+fn handle_registry_changes(req: Request<Incoming>, stores: &crate::store::Stores) -> Response {
+	let Some(registry) = stores.get_registry() else {
+		return no_registry();
+	};
+	let qp = query_params(&req);
+	let limit = match qp.get("limit").map(|l| l.parse::<usize>()) {
+		None => usize::MAX,
+		Some(Ok(limit)) => limit,
+		Some(Err(e)) => {
+			return plaintext_response(
+				hyper::StatusCode::BAD_REQUEST,
+				format!("invalid limit: {e}\n"),
+			);
+		},
+	};
+	match serde_json::to_string_pretty(&registry.changelog().recent(limit)) {
+		Ok(body) => json_response(body),
+		Err(e) => plaintext_response(hyper::StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+	}
+}
+
+/// Reference documentation of the active registry, or of one of its tools
+/// (`?format=markdown|html&tool=<name>`)
+fn handle_registry_docs(req: Request<Incoming>, stores: &crate::store::Stores) -> Response {
+	let Some(registry) = stores.get_registry().and_then(|r| r.get_arc()) else {
+		return plaintext_response(
+			hyper::StatusCode::NOT_FOUND,
+			"no registry loaded\n".to_string(),
+		);
+	};
+	let qp = query_params(&req);
+	let format = match qp.get("format").map(|f| f.parse::<DocsFormat>()) {
+		None => DocsFormat::default(),
+		Some(Ok(format)) => format,
+		Some(Err(e)) => return plaintext_response(hyper::StatusCode::BAD_REQUEST, format!("{e}\n")),
+	};
+	let body = match qp.get("tool") {
+		None => crate::mcp::registry::render_docs(&registry, format),
+		Some(tool) => match crate::mcp::registry::render_tool_docs(&registry, tool, format) {
+			Some(body) => body,
+			None => {
+				return plaintext_response(
+					hyper::StatusCode::NOT_FOUND,
+					format!("tool '{tool}' is not in the registry\n"),
+				);
+			},
+		},
+	};
+	::http::Response::builder()
+		.status(hyper::StatusCode::OK)
+		.header(hyper::header::CONTENT_TYPE, format.content_type())
+		.body(body.into())
+		.expect("builder with known status code should not fail")
+}
+
+static USAGE_HELP: &str = "
+usage: GET /debug/registry/usage			(To get tool usage in the last closed window)
+usage: GET /debug/registry/usage?current	(To get exact tool usage in the open window)
+
+hint: in private mode counts are noisy and only closed windows are reported
+";
+
+/// Tool usage counts, for export to analytics systems
+fn handle_registry_usage(req: Request<Incoming>, stores: &crate::store::Stores) -> Response {
+	let Some(usage) = stores.get_registry().and_then(|r| r.usage().cloned()) else {
+		return plaintext_response(
+			hyper::StatusCode::NOT_FOUND,
+			format!("no usage analytics configured\n{USAGE_HELP}"),
+		);
+	};
+	let current = query_params(&req).contains_key("current");
+	let report = if current {
+		let Some(report) = usage.current_report() else {
+			return plaintext_response(
+				hyper::StatusCode::BAD_REQUEST,
+				format!("the open window is not reported in private mode\n{USAGE_HELP}"),
+			);
+		};
+		report
+	} else {
+		let Some(report) = usage.last_report() else {
+			return plaintext_response(
+				hyper::StatusCode::NOT_FOUND,
+				"no usage window has closed yet\n".to_string(),
+			);
+		};
+		report
+	};
+	match serde_json::to_string_pretty(&report) {
+		Ok(body) => json_response(body),
+		Err(e) => plaintext_response(hyper::StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+	}
+}
+
+static KILL_SWITCH_HELP: &str = "
+usage: GET /debug/registry/kill_switch\t\t\t\t\t(To list disabled tools and the kill switch)
+usage: POST /debug/registry/kill_switch?tool=<name>[&reason=<text>]\t(To disable a virtual tool or composition)
+usage: POST /debug/registry/kill_switch?tool=<name>&enable\t\t(To enable a disabled tool)
+usage: POST /debug/registry/kill_switch?passthrough=<true|false>\t(To set the registry aside, or bring it back)
+usage: POST /debug/registry/kill_switch?reset\t\t\t\t(To enable all tools and bring the registry back)
+
+hint: disabled tools are hidden from tools/list and their calls fail with the reason;
+with passthrough, clients see backend tools unchanged, as if no registry were configured
+";
+
+/// Disable virtual tools, or the whole registry, while a composition misbehaves
+fn handle_kill_switch(req: Request<Incoming>, stores: &crate::store::Stores) -> Response {
+	let Some(registry) = stores.get_registry() else {
+		return no_registry();
+	};
+	let switch = registry.kill_switch();
+	let status = || match serde_json::to_string_pretty(&switch.status()) {
+		Ok(body) => json_response(body),
+		Err(e) => plaintext_response(hyper::StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+	};
+	match *req.method() {
+		hyper::Method::GET => status(),
+		hyper::Method::POST => {
+			let qp = query_params(&req);
+			if qp.contains_key("reset") {
+				switch.enable_all();
+				switch.set_passthrough(false);
+				return status();
+			}
+			if let Some(passthrough) = qp.get("passthrough") {
+				let Ok(passthrough) = passthrough.parse::<bool>() else {
+					return plaintext_response(
+						hyper::StatusCode::BAD_REQUEST,
+						format!("passthrough must be true or false\n{KILL_SWITCH_HELP}"),
+					);
+				};
+				switch.set_passthrough(passthrough);
+				return status();
+			}
+			let Some(tool) = qp.get("tool") else {
+				return plaintext_response(
+					hyper::StatusCode::BAD_REQUEST,
+					format!("tool or passthrough is required\n{KILL_SWITCH_HELP}"),
+				);
+			};
+			if qp.contains_key("enable") {
+				switch.enable(tool);
+				return status();
+			}
+			// Catch typos; a disabled tool that a reload removes stays disabled
+			if registry
+				.get_arc()
+				.is_none_or(|r| r.get_tool(tool).is_none())
+			{
+				return plaintext_response(
+					hyper::StatusCode::NOT_FOUND,
+					format!("no virtual tool or composition named '{tool}' in the registry\n"),
+				);
+			}
+			switch.disable(tool, qp.get("reason").cloned());
+			status()
+		},
+		_ => plaintext_response(
+			hyper::StatusCode::METHOD_NOT_ALLOWED,
+			format!("Invalid HTTP method\n{KILL_SWITCH_HELP}"),
+		),
+	}
+}
+
 // mirror envoy's behavior: https://www.envoyproxy.io/docs/envoy/latest/operations/admin#post--logging
 // NOTE: multiple query parameters is not supported, for example
 // curl -X POST http://127.0.0.1:15000/logging?"tap=debug&router=debug"
@@ -338,15 +1287,7 @@ hint: mod_name:\tthe module name, i.e. ztunnel::agentgateway
 async fn handle_logging(req: Request<Incoming>) -> Response {
 	match *req.method() {
 		hyper::Method::POST => {
-			let qp: HashMap<String, String> = req
-				.uri()
-				.query()
-				.map(|v| {
-					url::form_urlencoded::parse(v.as_bytes())
-						.into_owned()
-						.collect()
-				})
-				.unwrap_or_default();
+			let qp = query_params(&req);
 			let level = qp.get("level").cloned();
 			let reset = qp.get("reset").cloned();
 			if level.is_some() || reset.is_some() {
@@ -37,6 +37,14 @@ pub fn plaintext_response(code: hyper::StatusCode, body: String) -> Response {
 		.unwrap()
 }
 
+pub fn json_response(body: String) -> Response {
+	::http::Response::builder()
+		.status(hyper::StatusCode::OK)
+		.header(hyper::header::CONTENT_TYPE, "application/json")
+		.body(body.into())
+		.unwrap()
+}
+
 /// Server implements a generic HTTP server with the follow behavior:
 /// * HTTP/1.1 plaintext only
 /// * Draining
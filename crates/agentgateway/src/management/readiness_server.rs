@@ -1,6 +1,7 @@
 // Originally derived from https://github.com/istio/ztunnel (Apache 2.0 licensed)
 
 use std::net::SocketAddr;
+use std::sync::{Arc, OnceLock};
 
 use agent_core::drain::DrainWatcher;
 use agent_core::readiness;
@@ -11,10 +12,18 @@ use itertools::Itertools;
 use super::hyper_helpers;
 use crate::Address;
 use crate::http::Response;
+use crate::store::Stores;
+
+struct State {
+	ready: readiness::Ready,
+	/// Set once the state manager is up; until then there's no registry to check
+	stores: Arc<OnceLock<Stores>>,
+}
 
 pub struct Server {
-	s: hyper_helpers::Server<readiness::Ready>,
+	s: hyper_helpers::Server<State>,
 	ready: readiness::Ready,
+	stores: Arc<OnceLock<Stores>>,
 }
 
 impl Server {
@@ -23,44 +32,96 @@ impl Server {
 		drain_rx: DrainWatcher,
 		ready: readiness::Ready,
 	) -> anyhow::Result<Self> {
-		hyper_helpers::Server::<readiness::Ready>::bind("readiness", address, drain_rx, ready.clone())
+		let stores = Arc::new(OnceLock::new());
+		let state = State {
+			ready: ready.clone(),
+			stores: stores.clone(),
+		};
+		hyper_helpers::Server::<State>::bind("readiness", address, drain_rx, state)
 			.await
-			.map(|s| Server { s, ready })
+			.map(|s| Server { s, ready, stores })
 	}
 
 	pub fn ready(&self) -> readiness::Ready {
 		self.ready.clone()
 	}
 
+	/// Slot for the stores whose registry readiness the probe reports, filled in once they
+	/// exist
+	pub fn stores(&self) -> Arc<OnceLock<Stores>> {
+		self.stores.clone()
+	}
+
 	pub fn address(&self) -> SocketAddr {
 		self.s.address()
 	}
 
 	pub fn spawn(self) {
-		self.s.spawn(|ready, req| async move {
+		self.s.spawn(|state, req| async move {
 			match req.uri().path() {
-				"/healthz/ready" => Ok(handle_ready(&ready, req).await),
+				"/healthz/ready" => Ok(handle_ready(&state, req).await),
+				"/healthz/registry" => handle_registry(&state, req).await,
 				_ => Ok(hyper_helpers::empty_response(hyper::StatusCode::NOT_FOUND)),
 			}
 		})
 	}
 }
 
-async fn handle_ready(ready: &readiness::Ready, req: Request<Incoming>) -> Response {
+async fn handle_ready(state: &State, req: Request<Incoming>) -> Response {
 	match *req.method() {
 		hyper::Method::GET => {
-			let pending = ready.pending();
-			if pending.is_empty() {
-				return hyper_helpers::plaintext_response(hyper::StatusCode::OK, "ready\n".into());
+			let pending = state.ready.pending();
+			if !pending.is_empty() {
+				return hyper_helpers::plaintext_response(
+					hyper::StatusCode::INTERNAL_SERVER_ERROR,
+					format!(
+						"not ready, pending: {}\n",
+						pending.into_iter().sorted().join(", ")
+					),
+				);
 			}
-			hyper_helpers::plaintext_response(
-				hyper::StatusCode::INTERNAL_SERVER_ERROR,
-				format!(
-					"not ready, pending: {}\n",
-					pending.into_iter().sorted().join(", ")
-				),
-			)
+			// A gateway whose virtual tools would all fail shouldn't get traffic
+			if let Some(registry) = state.stores.get().and_then(Stores::get_registry) {
+				let readiness = registry.readiness().await;
+				if !readiness.ready {
+					return hyper_helpers::plaintext_response(
+						hyper::StatusCode::INTERNAL_SERVER_ERROR,
+						format!("not ready, registry: {}\n", readiness.failures().join("; ")),
+					);
+				}
+			}
+			hyper_helpers::plaintext_response(hyper::StatusCode::OK, "ready\n".into())
 		},
 		_ => hyper_helpers::empty_response(hyper::StatusCode::METHOD_NOT_ALLOWED),
 	}
 }
+
+/// Report each registry readiness check as JSON, with the status code of the overall result
+///
+/// Unlike `/healthz/ready`, this also reports composition upstreams that are failing.
+async fn handle_registry(state: &State, req: Request<Incoming>) -> anyhow::Result<Response> {
+	if *req.method() != hyper::Method::GET {
+		return Ok(hyper_helpers::empty_response(
+			hyper::StatusCode::METHOD_NOT_ALLOWED,
+		));
+	}
+	let Some(registry) = state.stores.get().and_then(Stores::get_registry) else {
+		return Ok(hyper_helpers::plaintext_response(
+			hyper::StatusCode::NOT_FOUND,
+			"no registry configured\n".to_string(),
+		));
+	};
+	let readiness = registry.readiness_with_upstreams().await;
+	let status = if readiness.ready {
+		hyper::StatusCode::OK
+	} else {
+		hyper::StatusCode::SERVICE_UNAVAILABLE
+	};
+	Ok(
+		::http::Response::builder()
+			.status(status)
+			.header(hyper::header::CONTENT_TYPE, "application/json")
+			.body(serde_json::to_string_pretty(&readiness)?.into())
+			.expect("builder with known status code should not fail"),
+	)
+}
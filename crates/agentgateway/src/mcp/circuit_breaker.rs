@@ -0,0 +1,519 @@
+// Per-upstream circuit breakers
+//
+// A failing or very slow upstream otherwise keeps receiving every request routed to it, and
+// each caller waits out its timeout. A backend with a `circuitBreaker` tracks the outcome of
+// recent calls to each of its upstreams; once too many of them fail (or take longer than the
+// slow-call threshold), the breaker opens and the relay refuses calls to that upstream
+// without sending them. After `openDuration` a single probe call is let through: if it
+// succeeds the breaker closes, otherwise it stays open for another `openDuration`.
+//
+// Breakers are exported in state snapshots through `StateStore`, keyed by
+// `{namespace}/{backend}/{target}`, so a blue/green swap doesn't re-close open breakers.
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use agent_core::strng::{self, Strng};
+use async_trait::async_trait;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+use crate::stateful::{StateStore, StoreError};
+use crate::types::agent::{McpCircuitBreaker, ResourceName};
+
+/// Whether calls to an upstream are let through
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum CircuitState {
+	/// Calls go through and their outcomes are tracked
+	Closed,
+	/// Calls are refused until the open period ends
+	Open,
+	/// The open period ended; one probe call decides whether the breaker closes
+	HalfOpen,
+}
+
+#[derive(Debug)]
+enum State {
+	Closed,
+	Open { until: Instant },
+	HalfOpen { probe: Option<Instant> },
+}
+
+#[derive(Debug)]
+struct Breaker {
+	state: State,
+	/// Completion time of recent calls, and whether each failed
+	outcomes: VecDeque<(Instant, bool)>,
+	last_error: Option<String>,
+}
+
+impl Default for Breaker {
+	fn default() -> Self {
+		Self {
+			state: State::Closed,
+			outcomes: VecDeque::new(),
+			last_error: None,
+		}
+	}
+}
+
+/// A breaker as carried in a state snapshot, with times as millis relative to the export
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct BreakerRecord {
+	config: McpCircuitBreaker,
+	state: StateRecord,
+	/// Age of each recent call outcome, and whether it failed
+	outcomes: Vec<(u64, bool)>,
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	last_error: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+enum StateRecord {
+	Closed,
+	/// Time left of the open period
+	Open(u64),
+	/// Age of the outstanding probe call, if any
+	HalfOpen(Option<u64>),
+}
+
+impl Breaker {
+	fn to_record(&self, config: &McpCircuitBreaker, now: Instant) -> BreakerRecord {
+		let ago = |at: Instant| now.saturating_duration_since(at).as_millis() as u64;
+		let state = match self.state {
+			State::Closed => StateRecord::Closed,
+			State::Open { until } => {
+				StateRecord::Open(until.saturating_duration_since(now).as_millis() as u64)
+			},
+			State::HalfOpen { probe } => StateRecord::HalfOpen(probe.map(ago)),
+		};
+		BreakerRecord {
+			config: config.clone(),
+			state,
+			outcomes: self
+				.outcomes
+				.iter()
+				.map(|(at, failed)| (ago(*at), *failed))
+				.collect(),
+			last_error: self.last_error.clone(),
+		}
+	}
+
+	fn from_record(record: BreakerRecord, now: Instant) -> (McpCircuitBreaker, Self) {
+		// Times from before this process started are clamped to now
+		let at = |ms: u64| now.checked_sub(Duration::from_millis(ms)).unwrap_or(now);
+		let state = match record.state {
+			StateRecord::Closed => State::Closed,
+			StateRecord::Open(ms) => State::Open {
+				until: now + Duration::from_millis(ms),
+			},
+			StateRecord::HalfOpen(probe) => State::HalfOpen {
+				probe: probe.map(at),
+			},
+		};
+		let breaker = Self {
+			state,
+			outcomes: record
+				.outcomes
+				.into_iter()
+				.map(|(ms, failed)| (at(ms), failed))
+				.collect(),
+			last_error: record.last_error,
+		};
+		(record.config, breaker)
+	}
+
+	/// Whether a call would be let through, without claiming the half-open probe
+	fn is_available(&self, config: &McpCircuitBreaker, now: Instant) -> bool {
+		match self.state {
+			State::Closed => true,
+			State::Open { until } => now >= until,
+			State::HalfOpen { probe } => probe.is_none_or(|p| now >= p + config.open_duration),
+		}
+	}
+
+	/// Let a call through if the breaker allows it. In the half-open state this claims the
+	/// probe; a probe that never reports back is replaced after another `openDuration`.
+	fn allow(&mut self, config: &McpCircuitBreaker, now: Instant) -> bool {
+		if !self.is_available(config, now) {
+			return false;
+		}
+		if !matches!(self.state, State::Closed) {
+			self.state = State::HalfOpen { probe: Some(now) };
+		}
+		true
+	}
+
+	fn record(&mut self, config: &McpCircuitBreaker, now: Instant, error: Option<String>) -> bool {
+		let failed = error.is_some();
+		if error.is_some() {
+			self.last_error = error;
+		}
+		match self.state {
+			State::HalfOpen { .. } if failed => {
+				self.state = State::Open {
+					until: now + config.open_duration,
+				};
+				true
+			},
+			State::HalfOpen { .. } => {
+				self.state = State::Closed;
+				self.outcomes.clear();
+				false
+			},
+			// A call let through before the breaker opened
+			State::Open { .. } => false,
+			State::Closed => {
+				self.outcomes.push_back((now, failed));
+				self.expire(config, now);
+				let calls = self.outcomes.len();
+				let failures = self.outcomes.iter().filter(|(_, failed)| *failed).count();
+				if calls >= config.minimum_calls as usize
+					&& failures as f64 >= config.failure_rate * calls as f64
+				{
+					self.state = State::Open {
+						until: now + config.open_duration,
+					};
+					self.outcomes.clear();
+					return true;
+				}
+				false
+			},
+		}
+	}
+
+	fn expire(&mut self, config: &McpCircuitBreaker, now: Instant) {
+		while let Some((at, _)) = self.outcomes.front()
+			&& now.duration_since(*at) > config.window
+		{
+			self.outcomes.pop_front();
+		}
+	}
+
+	fn state(&self, now: Instant) -> CircuitState {
+		match self.state {
+			State::Closed => CircuitState::Closed,
+			State::Open { until } if now < until => CircuitState::Open,
+			State::Open { .. } | State::HalfOpen { .. } => CircuitState::HalfOpen,
+		}
+	}
+}
+
+/// Health of one upstream, as reported by the admin server
+#[derive(Debug, Clone, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpstreamStatus {
+	pub backend: String,
+	pub target: String,
+	pub state: CircuitState,
+	/// Calls completed within the breaker's window while it was closed
+	pub recent_calls: usize,
+	pub recent_failures: usize,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub last_error: Option<String>,
+}
+
+/// Circuit breakers for the upstreams of every MCP backend that configures one.
+///
+/// Shared by all sessions (and by every relay built for a backend) so that failures seen by
+/// one caller protect the others.
+#[derive(Debug, Default)]
+pub struct UpstreamHealth {
+	breakers: Mutex<HashMap<(ResourceName, Strng), (McpCircuitBreaker, Breaker)>>,
+}
+
+impl UpstreamHealth {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	fn with_breaker<T>(
+		&self,
+		backend: &ResourceName,
+		target: &Strng,
+		config: &McpCircuitBreaker,
+		f: impl FnOnce(&mut Breaker) -> T,
+	) -> T {
+		let mut breakers = self.breakers.lock();
+		let (cfg, breaker) = breakers
+			.entry((backend.clone(), target.clone()))
+			.or_insert_with(|| (config.clone(), Breaker::default()));
+		// Config changes take effect on the next call
+		*cfg = config.clone();
+		f(breaker)
+	}
+
+	/// Whether calls to an upstream are currently let through
+	pub fn is_available(
+		&self,
+		backend: &ResourceName,
+		target: &Strng,
+		config: &McpCircuitBreaker,
+	) -> bool {
+		let breakers = self.breakers.lock();
+		match breakers.get(&(backend.clone(), target.clone())) {
+			Some((_, breaker)) => breaker.is_available(config, Instant::now()),
+			None => true,
+		}
+	}
+
+	/// Admit a call to an upstream, or refuse it if its breaker is open
+	pub fn allow(&self, backend: &ResourceName, target: &Strng, config: &McpCircuitBreaker) -> bool {
+		self.with_breaker(backend, target, config, |b| b.allow(config, Instant::now()))
+	}
+
+	/// Record the outcome of a call that `allow` admitted
+	pub fn record(
+		&self,
+		backend: &ResourceName,
+		target: &Strng,
+		config: &McpCircuitBreaker,
+		error: Option<String>,
+	) {
+		let opened = self.with_breaker(backend, target, config, |b| {
+			b.record(config, Instant::now(), error)
+		});
+		if opened {
+			warn!(backend = %backend, target = %target, "circuit breaker opened for upstream");
+		}
+	}
+
+	/// State of every upstream seen so far, sorted by backend and target
+	pub fn snapshot(&self) -> Vec<UpstreamStatus> {
+		let now = Instant::now();
+		let breakers = self.breakers.lock();
+		let mut statuses: Vec<_> = breakers
+			.iter()
+			.map(|((backend, target), (config, breaker))| {
+				let recent = breaker
+					.outcomes
+					.iter()
+					.filter(|(at, _)| now.duration_since(*at) <= config.window);
+				UpstreamStatus {
+					backend: backend.to_string(),
+					target: target.to_string(),
+					state: breaker.state(now),
+					recent_calls: recent.clone().count(),
+					recent_failures: recent.filter(|(_, failed)| *failed).count(),
+					last_error: breaker.last_error.clone(),
+				}
+			})
+			.collect();
+		statuses.sort_by(|a, b| (&a.backend, &a.target).cmp(&(&b.backend, &b.target)));
+		statuses
+	}
+}
+
+/// Parse the snapshot key of a breaker, `{namespace}/{backend}/{target}`
+fn breaker_id(key: &str) -> Result<(ResourceName, Strng), StoreError> {
+	let mut parts = key.splitn(3, '/');
+	match (parts.next(), parts.next(), parts.next()) {
+		(Some(namespace), Some(name), Some(target)) => Ok((
+			ResourceName::new(strng::new(name), strng::new(namespace)),
+			strng::new(target),
+		)),
+		_ => Err(StoreError::Storage(format!(
+			"invalid circuit breaker key '{key}'"
+		))),
+	}
+}
+
+#[async_trait]
+impl StateStore for UpstreamHealth {
+	async fn get(&self, key: &str) -> Result<Option<Vec<u8>>, StoreError> {
+		Ok(self.get_with_ttl(key).await?.map(|(value, _)| value))
+	}
+
+	async fn set(&self, key: &str, value: Vec<u8>, _ttl: Option<Duration>) -> Result<(), StoreError> {
+		let id = breaker_id(key)?;
+		let record: BreakerRecord =
+			serde_json::from_slice(&value).map_err(|e| StoreError::Serialization(e.to_string()))?;
+		let breaker = Breaker::from_record(record, Instant::now());
+		self.breakers.lock().insert(id, breaker);
+		Ok(())
+	}
+
+	async fn delete(&self, key: &str) -> Result<(), StoreError> {
+		let id = breaker_id(key)?;
+		self.breakers.lock().remove(&id);
+		Ok(())
+	}
+
+	async fn get_with_ttl(
+		&self,
+		key: &str,
+	) -> Result<Option<(Vec<u8>, Option<Duration>)>, StoreError> {
+		let id = breaker_id(key)?;
+		let breakers = self.breakers.lock();
+		let Some((config, breaker)) = breakers.get(&id) else {
+			return Ok(None);
+		};
+		let value = serde_json::to_vec(&breaker.to_record(config, Instant::now()))
+			.map_err(|e| StoreError::Serialization(e.to_string()))?;
+		Ok(Some((value, None)))
+	}
+
+	async fn keys(&self, prefix: &str) -> Result<Vec<String>, StoreError> {
+		let breakers = self.breakers.lock();
+		Ok(
+			breakers
+				.keys()
+				.map(|(backend, target)| format!("{backend}/{target}"))
+				.filter(|key| key.starts_with(prefix))
+				.collect(),
+		)
+	}
+}
+
+/// A backend's view of the shared [`UpstreamHealth`]
+#[derive(Debug, Clone)]
+pub struct CircuitBreakerScope {
+	pub health: Arc<UpstreamHealth>,
+	pub backend: ResourceName,
+	pub config: McpCircuitBreaker,
+}
+
+impl CircuitBreakerScope {
+	pub fn is_available(&self, target: &Strng) -> bool {
+		self
+			.health
+			.is_available(&self.backend, target, &self.config)
+	}
+
+	pub fn allow(&self, target: &Strng) -> bool {
+		self.health.allow(&self.backend, target, &self.config)
+	}
+
+	/// Record a call that took `elapsed`; slow calls count as failures
+	pub fn record(&self, target: &Strng, elapsed: Duration, error: Option<String>) {
+		let error = error.or_else(|| {
+			self
+				.config
+				.slow_call_threshold
+				.filter(|threshold| elapsed > *threshold)
+				.map(|threshold| format!("call took longer than {threshold:?}"))
+		});
+		self
+			.health
+			.record(&self.backend, target, &self.config, error)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use agent_core::strng;
+
+	use super::*;
+
+	fn scope(open_duration: Duration) -> CircuitBreakerScope {
+		CircuitBreakerScope {
+			health: Arc::new(UpstreamHealth::new()),
+			backend: ResourceName::new(strng::literal!("mcp"), strng::literal!("default")),
+			config: McpCircuitBreaker {
+				failure_rate: 0.5,
+				minimum_calls: 4,
+				window: Duration::from_secs(60),
+				slow_call_threshold: Some(Duration::from_secs(5)),
+				open_duration,
+			},
+		}
+	}
+
+	#[test]
+	fn test_opens_on_failure_rate() {
+		let scope = scope(Duration::from_secs(60));
+		let target = strng::literal!("weather");
+		for ok in [true, false, true] {
+			assert!(scope.allow(&target));
+			let error = (!ok).then(|| "boom".to_string());
+			scope.record(&target, Duration::from_millis(10), error);
+		}
+		// Too few calls to judge yet
+		assert!(scope.is_available(&target));
+
+		// A slow call counts as a failure, making 2 of 4
+		assert!(scope.allow(&target));
+		scope.record(&target, Duration::from_secs(6), None);
+		assert!(!scope.is_available(&target));
+		assert!(!scope.allow(&target));
+
+		let status = &scope.health.snapshot()[0];
+		assert_eq!(status.target, "weather");
+		assert_eq!(status.state, CircuitState::Open);
+		assert!(
+			status
+				.last_error
+				.as_deref()
+				.unwrap()
+				.contains("longer than")
+		);
+
+		// Other upstreams are unaffected
+		assert!(scope.allow(&strng::literal!("search")));
+	}
+
+	#[test]
+	fn test_half_open_probe() {
+		let scope = scope(Duration::ZERO);
+		let target = strng::literal!("weather");
+		for _ in 0..4 {
+			assert!(scope.allow(&target));
+			scope.record(&target, Duration::ZERO, Some("down".to_string()));
+		}
+
+		// The open period is over, so one probe goes through; a failed probe reopens
+		assert_eq!(scope.health.snapshot()[0].state, CircuitState::HalfOpen);
+		assert!(scope.allow(&target));
+		scope.record(&target, Duration::ZERO, Some("still down".to_string()));
+		assert_eq!(
+			scope.health.snapshot()[0].last_error.as_deref(),
+			Some("still down")
+		);
+
+		// A successful probe closes the breaker
+		assert!(scope.allow(&target));
+		scope.record(&target, Duration::ZERO, None);
+		assert_eq!(scope.health.snapshot()[0].state, CircuitState::Closed);
+		assert_eq!(scope.health.snapshot()[0].recent_calls, 0);
+	}
+
+	#[tokio::test]
+	async fn test_open_breakers_move_between_instances() {
+		let blue = scope(Duration::from_secs(60));
+		let target = strng::literal!("weather");
+		for _ in 0..4 {
+			assert!(blue.allow(&target));
+			blue.record(&target, Duration::ZERO, Some("down".to_string()));
+		}
+
+		let green = UpstreamHealth::new();
+		let keys = blue.health.keys("").await.unwrap();
+		assert_eq!(keys, vec!["default/mcp/weather"]);
+		for key in keys {
+			let value = blue.health.get(&key).await.unwrap().unwrap();
+			green.set(&key, value, None).await.unwrap();
+		}
+		assert!(!green.is_available(&blue.backend, &target, &blue.config));
+		let status = &green.snapshot()[0];
+		assert_eq!(status.state, CircuitState::Open);
+		assert_eq!(status.last_error.as_deref(), Some("down"));
+	}
+
+	#[test]
+	fn test_outstanding_probe_blocks_other_calls() {
+		let mut breaker = Breaker::default();
+		let config = scope(Duration::from_secs(30)).config;
+		let now = Instant::now();
+		breaker.state = State::Open { until: now };
+
+		assert!(breaker.allow(&config, now));
+		assert!(!breaker.allow(&config, now + Duration::from_secs(1)));
+		// A probe that never reported back is replaced
+		assert!(breaker.allow(&config, now + Duration::from_secs(31)));
+	}
+}
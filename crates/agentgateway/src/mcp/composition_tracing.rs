@@ -0,0 +1,246 @@
+// Tracing spans for composition execution
+
+use std::fmt::Write;
+
+use agent_core::trcng;
+use opentelemetry::global::BoxedSpan;
+use opentelemetry::trace::{Span, SpanContext, SpanKind, TraceContextExt, Tracer};
+use opentelemetry::{Context, KeyValue, TraceFlags};
+
+use crate::http::{HeaderName, HeaderValue};
+use crate::mcp::registry::executor::InvocationMeta;
+use crate::mcp::upstream::IncomingRequestContext;
+use crate::telemetry::metrics::TraceExemplar;
+use crate::telemetry::trc::CompositionSampling;
+
+/// Create the span for a composition execution as a child of the MCP request span.
+///
+/// By default the span follows the request's sampling decision. A per-composition
+/// override (from `tracing.compositionSampling`) forces it to be recorded or dropped.
+pub fn create_composition_span(
+	name: &str,
+	parent: &BoxedSpan,
+	sampling: Option<CompositionSampling>,
+) -> BoxedSpan {
+	let parent = parent.span_context();
+	let parent = if parent.is_valid() {
+		let flags = if is_sampled(parent.is_sampled(), sampling) {
+			TraceFlags::SAMPLED
+		} else {
+			TraceFlags::default()
+		};
+		SpanContext::new(
+			parent.trace_id(),
+			parent.span_id(),
+			flags,
+			true,
+			parent.trace_state().clone(),
+		)
+	} else {
+		parent.clone()
+	};
+	let ctx = Context::new().with_remote_span_context(parent);
+
+	let tracer = trcng::get_tracer();
+	tracer
+		.span_builder(format!("composition {name}"))
+		.with_kind(SpanKind::Internal)
+		.with_attributes(vec![KeyValue::new("composition.name", name.to_string())])
+		.start_with_context(tracer, &ctx)
+}
+
+/// Exemplar linking a composition latency observation to its trace.
+///
+/// Only sampled spans produce an exemplar, since unsampled trace IDs cannot be looked up.
+pub fn trace_exemplar(span: &SpanContext) -> Option<TraceExemplar> {
+	(span.is_valid() && span.is_sampled()).then(|| TraceExemplar {
+		trace_id: span.trace_id().to_string(),
+	})
+}
+
+const TRACEPARENT: &str = "traceparent";
+const BAGGAGE: &str = "baggage";
+
+/// Baggage keys set on backend calls made by compositions
+const BAGGAGE_CALLER: &str = "agentgateway.caller";
+const BAGGAGE_COMPOSITION: &str = "agentgateway.composition";
+const BAGGAGE_STEP: &str = "agentgateway.step";
+/// Prefix of baggage keys carrying the caller's execution labels
+const BAGGAGE_LABEL_PREFIX: &str = "agentgateway.label.";
+
+/// Prepare the request context for a backend call made by a composition step.
+///
+/// The composition span replaces the request's `traceparent`, and the caller (JWT `sub`),
+/// composition name, step id and execution labels are added to the W3C `baggage` header
+/// so backend logs can be correlated with the composition that triggered them.
+pub fn propagate(
+	ctx: &IncomingRequestContext,
+	span: Option<&SpanContext>,
+	meta: &InvocationMeta,
+) -> IncomingRequestContext {
+	let mut out = ctx.clone();
+	if let Some(span) = span.filter(|s| s.is_valid()) {
+		let tp = format!(
+			"00-{}-{}-{:02x}",
+			span.trace_id(),
+			span.span_id(),
+			span.trace_flags().to_u8()
+		);
+		if let Ok(hv) = HeaderValue::try_from(tp) {
+			out = out.with_header(HeaderName::from_static(TRACEPARENT), hv);
+		}
+	}
+
+	let caller = ctx
+		.claims()
+		.and_then(|c| c.inner.get("sub"))
+		.and_then(|v| v.as_str());
+	let existing = ctx.header(BAGGAGE).and_then(|v| v.to_str().ok());
+	let label_keys: Vec<_> = meta
+		.labels
+		.iter()
+		.map(|(name, value)| (format!("{BAGGAGE_LABEL_PREFIX}{name}"), value))
+		.collect();
+	let mut entries = vec![
+		(BAGGAGE_CALLER, caller),
+		(BAGGAGE_COMPOSITION, meta.composition.as_deref()),
+		(BAGGAGE_STEP, meta.step_id.as_deref()),
+	];
+	entries.extend(label_keys.iter().map(|(k, v)| (k.as_str(), Some(*v))));
+	if let Some(baggage) = merge_baggage(existing, &entries)
+		&& let Ok(hv) = HeaderValue::try_from(baggage)
+	{
+		out = out.with_header(HeaderName::from_static(BAGGAGE), hv);
+	}
+	out
+}
+
+/// Merge entries into an existing baggage header, replacing members with the same key
+fn merge_baggage(existing: Option<&str>, entries: &[(&str, Option<&str>)]) -> Option<String> {
+	let mut members: Vec<String> = existing
+		.into_iter()
+		.flat_map(|b| b.split(','))
+		.map(str::trim)
+		.filter(|m| !m.is_empty())
+		.filter(|m| {
+			let key = m.split(['=', ';']).next().unwrap_or_default().trim();
+			!entries.iter().any(|(k, _)| *k == key)
+		})
+		.map(str::to_string)
+		.collect();
+	for (key, value) in entries {
+		if let Some(value) = value {
+			members.push(format!("{key}={}", encode_baggage_value(value)));
+		}
+	}
+	(!members.is_empty()).then(|| members.join(","))
+}
+
+/// Percent-encode characters outside the W3C baggage-octet range
+fn encode_baggage_value(value: &str) -> String {
+	let mut out = String::with_capacity(value.len());
+	for b in value.bytes() {
+		match b {
+			0x21 | 0x23..=0x2B | 0x2D..=0x3A | 0x3C..=0x5B | 0x5D..=0x7E => out.push(b as char),
+			_ => {
+				let _ = write!(out, "%{b:02X}");
+			},
+		}
+	}
+	out
+}
+
+/// Resolve the sampling decision for a composition span
+fn is_sampled(parent_sampled: bool, sampling: Option<CompositionSampling>) -> bool {
+	match sampling {
+		Some(CompositionSampling::Always) => true,
+		Some(CompositionSampling::Never) => false,
+		None => parent_sampled,
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::sync::Arc;
+
+	use opentelemetry::trace::{SpanId, TraceId, TraceState};
+
+	use super::*;
+	use crate::mcp::registry::executor::ExecutionLabels;
+
+	#[test]
+	fn test_is_sampled() {
+		assert!(is_sampled(true, None));
+		assert!(!is_sampled(false, None));
+		assert!(is_sampled(false, Some(CompositionSampling::Always)));
+		assert!(!is_sampled(true, Some(CompositionSampling::Never)));
+	}
+
+	#[test]
+	fn test_merge_baggage() {
+		assert_eq!(merge_baggage(None, &[(BAGGAGE_STEP, None)]), None);
+		assert_eq!(
+			merge_baggage(
+				Some("tenant=acme, agentgateway.step=stale;prop=1"),
+				&[
+					(BAGGAGE_COMPOSITION, Some("research pipeline")),
+					(BAGGAGE_STEP, Some("search")),
+				]
+			)
+			.as_deref(),
+			Some("tenant=acme,agentgateway.composition=research%20pipeline,agentgateway.step=search")
+		);
+	}
+
+	#[test]
+	fn test_propagate() {
+		let span = SpanContext::new(
+			TraceId::from_hex("4bf92f3577b34da6a3ce929d0e0e4736").unwrap(),
+			SpanId::from_hex("00f067aa0ba902b7").unwrap(),
+			TraceFlags::SAMPLED,
+			false,
+			TraceState::default(),
+		);
+		let meta = InvocationMeta {
+			composition: Some("pipeline".to_string()),
+			step_id: Some("fetch".to_string()),
+			labels: Arc::new(ExecutionLabels::from_iter([("project", "search")])),
+		};
+		let ctx = propagate(&IncomingRequestContext::empty(), Some(&span), &meta);
+		assert_eq!(
+			ctx.header(TRACEPARENT).unwrap(),
+			"00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01"
+		);
+		assert_eq!(
+			ctx.header(BAGGAGE).unwrap(),
+			"agentgateway.composition=pipeline,agentgateway.step=fetch,agentgateway.label.project=search"
+		);
+	}
+
+	#[test]
+	fn test_trace_exemplar() {
+		let trace_id = TraceId::from_hex("4bf92f3577b34da6a3ce929d0e0e4736").unwrap();
+		let span_id = SpanId::from_hex("00f067aa0ba902b7").unwrap();
+		let sampled = SpanContext::new(
+			trace_id,
+			span_id,
+			TraceFlags::SAMPLED,
+			false,
+			TraceState::default(),
+		);
+		assert_eq!(
+			trace_exemplar(&sampled).map(|e| e.trace_id),
+			Some("4bf92f3577b34da6a3ce929d0e0e4736".to_string())
+		);
+
+		let unsampled = SpanContext::new(
+			trace_id,
+			span_id,
+			TraceFlags::default(),
+			false,
+			TraceState::default(),
+		);
+		assert!(trace_exemplar(&unsampled).is_none());
+		assert!(trace_exemplar(&SpanContext::empty_context()).is_none());
+	}
+}
@@ -1,3 +1,4 @@
+use agent_core::strng::{self, RichStrng, Strng};
 use agent_core::trcng;
 use futures_core::Stream;
 use futures_util::StreamExt;
@@ -9,31 +10,62 @@ use opentelemetry::trace::{SpanContext, SpanKind, TraceContextExt, TraceState};
 use opentelemetry::{Context, TraceFlags};
 use rmcp::ErrorData;
 use rmcp::model::{
-	ClientNotification, ClientRequest, Implementation, JsonRpcNotification, JsonRpcRequest,
-	ListPromptsResult, ListResourceTemplatesResult, ListResourcesResult, ListToolsResult, Prompt,
-	PromptsCapability, ProtocolVersion, RequestId, ResourcesCapability, ServerCapabilities,
-	ServerInfo, ServerJsonRpcMessage, ServerResult, Tool, ToolsCapability,
+	ClientNotification, ClientRequest, GetMeta, Implementation, JsonRpcNotification, JsonRpcRequest,
+	ListPromptsResult, ListResourceTemplatesResult, ListResourcesResult, ListToolsResult, Meta,
+	Prompt, PromptsCapability, ProtocolVersion, RequestId, ResourcesCapability, ServerCapabilities,
+	ServerInfo, ServerJsonRpcMessage, ServerNotification, ServerResult, Tool, ToolsCapability,
 };
 use std::borrow::Cow;
+use std::collections::HashSet;
 use std::sync::Arc;
+use std::time::Duration;
 
 use crate::cel::ContextBuilder;
 use crate::http::Response;
 use crate::http::jwt::Claims;
+use crate::http::localratelimit::RateLimitStatus;
 use crate::http::sessionpersistence::MCPSession;
+use crate::mcp::circuit_breaker::CircuitBreakerScope;
 use crate::mcp::mergestream::MergeFn;
 use crate::mcp::rbac::{Identity, McpAuthorizationSet};
-use crate::mcp::registry::RegistryStoreRef;
+use crate::mcp::registry::executor::{
+	BUILTIN_TARGET, CostSummary, ExecutionLabels, WorkerPermit, builtin_tool, invoke_builtin,
+};
+use crate::mcp::registry::{
+	ArgumentCheckPolicy, AuthorizationRequest, CompiledRegistry, CompiledTargetPolicy, CompiledTool,
+	DESCRIBE_TOOL, Debounce, ExecutionPriority, Idempotency, JobPolling, Pagination,
+	RegistryStoreRef, SnapshotMemo, ToolPriority, TransportRetry, argument_problems,
+	check_confirmation, coerce_arguments, describe_tool_definition,
+};
 use crate::mcp::router::McpBackendGroup;
 use crate::mcp::streamablehttp::ServerSseMessage;
+use crate::mcp::tools_cache::ToolListCacheScope;
 use crate::mcp::upstream::{IncomingRequestContext, UpstreamError};
 use crate::mcp::{ClientError, MCPInfo, mergestream, rbac, upstream};
 use crate::proxy::httpproxy::PolicyClient;
 use crate::telemetry::log::AsyncLog;
-use crate::telemetry::trc::TraceParent;
+use crate::telemetry::metrics::{
+	CompositionAttributionLabels, CompositionLabels, CompositionOutcome, CostAttributionLabels,
+	Metrics, ToolCallAttributionLabels, ToolCostLabels, TraceExemplar,
+};
+use crate::telemetry::trc::{CompositionSampling, TraceParent};
 
 const DELIMITER: &str = "_";
 
+/// `_meta` key of the route rate limit quota left to the caller, on tool call results and
+/// rate limited errors
+pub const QUOTA_META_KEY: &str = "agentgateway.dev/quota";
+
+/// `_meta` key of the target that served a call to a virtual tool with fallback targets
+pub const SERVED_BY_META_KEY: &str = "agentgateway.dev/servedBy";
+
+/// Logger name of the `notifications/message` carrying a composition's partial results
+pub const PARTIAL_RESULT_LOGGER: &str = "agentgateway.dev/partial";
+
+/// How long a single upstream may take to answer a fanned out request, unless its backend
+/// sets a request timeout
+const DEFAULT_FANOUT_UPSTREAM_TIMEOUT: Duration = Duration::from_secs(30);
+
 /// Result of resolving a tool call, which may be a virtual tool or composition
 #[derive(Debug, Clone)]
 pub enum ResolvedToolCall {
@@ -55,6 +87,109 @@ pub enum ResolvedToolCall {
 		/// The arguments
 		args: serde_json::Value,
 	},
+	/// A `describe_tool` call, answered from the registry
+	Describe {
+		/// The arguments
+		args: serde_json::Value,
+	},
+}
+
+/// What a tool name resolves to in a registry snapshot, before arguments are bound
+#[derive(Debug, Clone)]
+enum ResolvedName {
+	Backend { target: String, tool_name: String },
+	Virtual(Arc<CompiledTool>),
+	Composition,
+	Describe,
+}
+
+/// A call to a virtual tool answered with one buffered result, resolved to its backend tool
+#[derive(Debug)]
+pub struct BufferedCall<'a> {
+	/// The target service/backend to route the call to
+	pub target: &'a str,
+	/// The actual tool name on the backend
+	pub tool_name: &'a str,
+	/// The arguments with defaults injected
+	pub args: serde_json::Value,
+	/// The virtual tool being called
+	pub virtual_name: &'a str,
+	/// Pagination to follow, merging every page into the result
+	pub pagination: Option<&'a Pagination>,
+	/// Job polling to wait on, returning the finished job's result
+	pub job_polling: Option<&'a JobPolling>,
+}
+
+/// Run an upstream call, retrying transport-level failures according to `retry`
+async fn with_transport_retry<T, F, Fut>(
+	retry: Option<&TransportRetry>,
+	target: &str,
+	mut call: F,
+) -> Result<T, UpstreamError>
+where
+	F: FnMut() -> Fut,
+	Fut: Future<Output = Result<T, UpstreamError>>,
+{
+	let attempts = retry.map(TransportRetry::attempts).unwrap_or(1);
+	let mut attempt = 1;
+	loop {
+		match call().await {
+			Err(e) if e.is_transport() && attempt < attempts => {
+				tracing::warn!(
+					target: "virtual_tools",
+					target_name = target,
+					attempt,
+					error = %e,
+					"transport failure calling backend, retrying"
+				);
+				if let Some(retry) = retry {
+					tokio::time::sleep(retry.backoff()).await;
+				}
+				attempt += 1;
+			},
+			res => return res,
+		}
+	}
+}
+
+/// Run a call against each target of a failover group in turn, moving on to the next while
+/// calls fail before a response arrives, find the target's circuit breaker open, or are shed
+/// by it. Returns the result with the target that served it.
+async fn with_failover<T, F, Fut>(
+	targets: &[String],
+	mut call: F,
+) -> Result<(T, String), UpstreamError>
+where
+	F: FnMut(String) -> Fut,
+	Fut: Future<Output = Result<T, UpstreamError>>,
+{
+	let mut targets = targets.iter().peekable();
+	while let Some(target) = targets.next() {
+		match call(target.clone()).await {
+			Err(e) if fails_over(&e) && targets.peek().is_some() => {
+				tracing::warn!(
+					target: "virtual_tools",
+					target_name = %target,
+					next = targets.peek().map(|t| t.as_str()),
+					error = %e,
+					"backend unavailable, failing over"
+				);
+			},
+			res => return res.map(|value| (value, target.clone())),
+		}
+	}
+	Err(UpstreamError::InvalidRequest(
+		"no target to send the call to".to_string(),
+	))
+}
+
+/// Whether a failed call is sent on to the next target of its failover group
+fn fails_over(e: &UpstreamError) -> bool {
+	e.is_transport()
+		|| matches!(
+			e,
+			UpstreamError::CircuitOpen(_) | UpstreamError::LoadShed(_)
+		)
 }
 
 fn resource_name(default_target_name: Option<&String>, target: &str, name: &str) -> String {
@@ -75,6 +210,17 @@ pub struct Relay {
 	is_multiplexing: bool,
 	/// Optional tool registry for virtual tool mappings
 	registry: Option<RegistryStoreRef>,
+	/// Per-composition trace sampling overrides
+	composition_sampling: Arc<std::collections::HashMap<String, CompositionSampling>>,
+	metrics: Arc<Metrics>,
+	/// Shared cache of upstream ListTools results (optional)
+	tools_cache: Option<ToolListCacheScope>,
+	/// Circuit breakers refusing calls to failing upstreams (optional)
+	circuit_breaker: Option<CircuitBreakerScope>,
+	/// Tool names resolved against the current registry snapshot
+	resolved_names: Arc<SnapshotMemo<ResolvedName>>,
+	/// Backend request timeouts of the targets that set one, bounding fanned out requests
+	fanout_timeouts: Arc<std::collections::HashMap<Strng, Duration>>,
 }
 
 impl Relay {
@@ -92,12 +238,28 @@ impl Relay {
 		} else {
 			Some(backend.targets[0].name.to_string())
 		};
+		let composition_sampling = client.inputs.cfg.tracing.composition_sampling.clone();
+		let metrics = client.inputs.metrics.clone();
+		let fanout_timeouts = backend
+			.targets
+			.iter()
+			.filter_map(|t| {
+				let timeout = t.backend_policies.http.as_ref()?.request_timeout?;
+				Some((t.name.clone(), timeout))
+			})
+			.collect();
 		Ok(Self {
 			upstreams: Arc::new(upstream::UpstreamGroup::new(client, backend)?),
 			policies,
 			default_target_name,
 			is_multiplexing,
 			registry: None,
+			composition_sampling,
+			metrics,
+			tools_cache: None,
+			circuit_breaker: None,
+			resolved_names: Default::default(),
+			fanout_timeouts: Arc::new(fanout_timeouts),
 		})
 	}
 
@@ -107,11 +269,209 @@ impl Relay {
 		self
 	}
 
+	/// Share upstream ListTools results through a cache
+	pub fn with_tools_cache(mut self, cache: ToolListCacheScope) -> Self {
+		self.tools_cache = Some(cache);
+		self
+	}
+
+	/// Refuse calls to upstreams whose circuit breaker is open
+	pub fn with_circuit_breaker(mut self, breaker: CircuitBreakerScope) -> Self {
+		self.circuit_breaker = Some(breaker);
+		self
+	}
+
+	/// Whether calls to `target` are currently let through by its circuit breaker
+	pub fn is_upstream_available(&self, target: &str) -> bool {
+		self
+			.circuit_breaker
+			.as_ref()
+			.is_none_or(|cb| cb.is_available(&strng::new(target)))
+	}
+
+	/// Send a call to `target` through its circuit breaker, recording how it went with the
+	/// breaker and the load shedder. Only failures of the upstream itself count, not rejected
+	/// requests.
+	async fn guarded<T>(
+		&self,
+		target: &str,
+		call: impl Future<Output = Result<T, UpstreamError>>,
+	) -> Result<T, UpstreamError> {
+		let target = strng::new(target);
+		if let Some(breaker) = &self.circuit_breaker
+			&& !breaker.allow(&target)
+		{
+			return Err(UpstreamError::CircuitOpen(target.to_string()));
+		}
+		let started = std::time::Instant::now();
+		let result = call.await;
+		let elapsed = started.elapsed();
+		let error = match &result {
+			Err(e) if e.is_upstream_failure() => Some(e.to_string()),
+			_ => None,
+		};
+		// Rejected requests say nothing about how fast the upstream is
+		if let Some(registry) = &self.registry
+			&& (result.is_ok() || error.is_some())
+		{
+			registry.load_shedder().record(&target, elapsed);
+		}
+		if let Some(breaker) = &self.circuit_breaker {
+			breaker.record(&target, elapsed, error);
+		}
+		result
+	}
+
+	/// Refuse a call of `priority` to `target` while the target's p95 latency is over the
+	/// threshold for the priority
+	fn check_load(&self, target: &str, priority: ToolPriority) -> Result<(), UpstreamError> {
+		match &self.registry {
+			Some(registry) => registry
+				.load_shedder()
+				.check(target, priority)
+				.map_err(UpstreamError::LoadShed),
+			None => Ok(()),
+		}
+	}
+
+	/// Record whether a target answered, so the readiness probe can report compositions whose
+	/// upstreams are failing
+	fn record_readiness<T>(&self, target: &str, result: &Result<T, UpstreamError>) {
+		let Some(registry) = &self.registry else {
+			return;
+		};
+		let upstreams = registry.upstream_readiness();
+		match result {
+			Ok(_) => upstreams.record_success(target),
+			Err(UpstreamError::CircuitOpen(_)) => {
+				upstreams.record_failure(target, "circuit breaker open")
+			},
+			Err(e) if e.is_upstream_failure() => upstreams.record_failure(target, e.to_string()),
+			Err(_) => {},
+		}
+	}
+
 	/// Get the registry reference
 	pub fn registry(&self) -> Option<&RegistryStoreRef> {
 		self.registry.as_ref()
 	}
 
+	/// Get the trace sampling override for a composition, if configured
+	pub fn composition_sampling(&self, name: &str) -> Option<CompositionSampling> {
+		self.composition_sampling.get(name).copied()
+	}
+
+	/// Record a composition execution's latency, with the trace as an exemplar if sampled
+	pub fn record_composition_duration(
+		&self,
+		name: &str,
+		duration: Duration,
+		ok: bool,
+		exemplar: Option<TraceExemplar>,
+	) {
+		let outcome = if ok {
+			CompositionOutcome::Success
+		} else {
+			CompositionOutcome::Error
+		};
+		self
+			.metrics
+			.composition_duration
+			.get_or_create(&CompositionLabels {
+				composition: Some(RichStrng::from(name)).into(),
+				outcome,
+			})
+			.observe(duration.as_secs_f64(), exemplar, None);
+	}
+
+	/// Count a composition execution once for each label the caller attached to it
+	pub fn record_attributed_execution(&self, name: &str, labels: &ExecutionLabels, ok: bool) {
+		let outcome = if ok {
+			CompositionOutcome::Success
+		} else {
+			CompositionOutcome::Error
+		};
+		let attribution = &self.metrics.attribution_labels;
+		for (label, value) in labels.iter() {
+			let Some(value) = attribution.value(label, value) else {
+				continue;
+			};
+			self
+				.metrics
+				.composition_attributed_executions
+				.get_or_create(&CompositionAttributionLabels {
+					composition: Some(RichStrng::from(name)).into(),
+					label: Some(RichStrng::from(label)).into(),
+					value: Some(RichStrng::from(value)).into(),
+					outcome,
+				})
+				.inc();
+		}
+	}
+
+	/// Record the estimated cost of a composition execution, by tool and by caller label
+	pub fn record_composition_cost(&self, name: &str, cost: &CostSummary, labels: &ExecutionLabels) {
+		for (tool, tool_cost) in &cost.tools {
+			self
+				.metrics
+				.composition_tool_cost
+				.get_or_create(&ToolCostLabels {
+					composition: Some(RichStrng::from(name)).into(),
+					tool: Some(RichStrng::from(tool.as_str())).into(),
+				})
+				.inc_by(tool_cost.cost);
+		}
+		if cost.is_empty() {
+			return;
+		}
+		// Caller labels go through the same allowlist as the other attribution metrics
+		let attribution = &self.metrics.attribution_labels;
+		for (label, value) in labels.iter() {
+			let Some(value) = attribution.value(label, value) else {
+				continue;
+			};
+			self
+				.metrics
+				.composition_attributed_cost
+				.get_or_create(&CostAttributionLabels {
+					composition: Some(RichStrng::from(name)).into(),
+					label: Some(RichStrng::from(label)).into(),
+					value: Some(RichStrng::from(value)).into(),
+				})
+				.inc_by(cost.total);
+		}
+	}
+
+	/// Count a backend tool call made by a composition once for each of its labels
+	fn record_attributed_tool_call(&self, tool: &str, meta: &InvocationMeta) {
+		let attribution = &self.metrics.attribution_labels;
+		for (label, value) in meta.labels.iter() {
+			let Some(value) = attribution.value(label, value) else {
+				continue;
+			};
+			self
+				.metrics
+				.composition_attributed_tool_calls
+				.get_or_create(&ToolCallAttributionLabels {
+					composition: meta.composition.as_deref().map(RichStrng::from).into(),
+					tool: Some(RichStrng::from(tool)).into(),
+					label: Some(RichStrng::from(label)).into(),
+					value: Some(RichStrng::from(value)).into(),
+				})
+				.inc();
+		}
+	}
+
+	/// Wait for a composition worker in the registry's pool, counted in this relay's lane
+	/// queue depth while waiting
+	pub async fn acquire_composition_worker(
+		&self,
+		registry: &RegistryStoreRef,
+		priority: ExecutionPriority,
+	) -> WorkerPermit {
+		registry.acquire_worker(priority, Some(&self.metrics)).await
+	}
+
 	/// Resolve a tool call, handling virtual tools, compositions, and regular tools.
 	///
 	/// Returns a ResolvedToolCall which is either:
@@ -120,7 +480,7 @@ impl Relay {
 	///
 	/// For virtual tools, this will:
 	/// - Map the virtual name to the source target and tool
-	/// - Inject default arguments
+	/// - Inject default arguments, including those for `caller` (a JWT subject)
 	///
 	/// For compositions, this returns the composition name for local execution.
 	///
@@ -129,90 +489,437 @@ impl Relay {
 		&self,
 		tool_name: &str,
 		args: serde_json::Value,
+		caller: Option<&str>,
 	) -> Result<ResolvedToolCall, UpstreamError> {
-		// First, check if this is a virtual tool or composition
-		if let Some(ref reg) = self.registry {
-			let guard = reg.get();
-			if let Some(ref compiled_registry) = **guard {
-				if let Some(tool) = compiled_registry.get_tool(tool_name) {
-					// Check if this is a composition
-					if tool.is_composition() {
-						tracing::debug!(
-							target: "virtual_tools",
-							composition = tool_name,
-							"resolved tool as composition"
-						);
-						return Ok(ResolvedToolCall::Composition {
-							name: tool_name.to_string(),
-							args,
-						});
-					}
+		let snapshot = self.registry_snapshot();
+		let args = match &snapshot {
+			Some(registry) => Self::check_input(registry, tool_name, args)?,
+			None => args,
+		};
+		self.resolve_tool_call_with(snapshot.as_ref(), tool_name, args, caller)
+	}
 
-					// This is a source-based virtual tool - resolve to backend
-					if let Some(source_info) = tool.source_info() {
-						let target = source_info.source.target.clone();
-						let backend_tool = source_info.source.tool.clone();
+	/// Check a caller's arguments against the input schema listed for a virtual tool or
+	/// composition, returning the arguments to call it with
+	///
+	/// Calls compositions make are checked by the executor instead, per step.
+	fn check_input(
+		registry: &CompiledRegistry,
+		tool_name: &str,
+		mut args: serde_json::Value,
+	) -> Result<serde_json::Value, UpstreamError> {
+		let policy = registry.input_checks();
+		let Some(schema) = registry
+			.get_tool(tool_name)
+			.and_then(|t| t.known_input_schema())
+			.filter(|_| policy != ArgumentCheckPolicy::Off)
+		else {
+			return Ok(args);
+		};
+		if policy == ArgumentCheckPolicy::Enforce {
+			coerce_arguments(schema, &mut args);
+		}
+		let problems = argument_problems(schema, &args);
+		if problems.is_empty() {
+			return Ok(args);
+		}
+		let error = UpstreamError::InvalidArguments {
+			tool: tool_name.to_string(),
+			problems,
+		};
+		if policy == ArgumentCheckPolicy::Enforce {
+			return Err(error);
+		}
+		tracing::warn!(target: "virtual_tools", tool = %tool_name, "{error}");
+		Ok(args)
+	}
 
-						tracing::debug!(
-							target: "virtual_tools",
-							virtual_tool = tool_name,
-							backend_target = %target,
-							backend_tool = %backend_tool,
-							"resolved virtual tool to backend"
-						);
+	/// Ask the external authorizer, if one is configured, whether the caller may make a
+	/// resolved tool call
+	///
+	/// A denial carrying a reason is reported to the caller with that reason; otherwise it
+	/// looks like an unknown tool, as RBAC denials do.
+	pub async fn authorize_tool_call(
+		&self,
+		tool_name: &str,
+		target: &str,
+		backend_tool: &str,
+		args: &serde_json::Value,
+		ctx: &IncomingRequestContext,
+	) -> Result<(), UpstreamError> {
+		let Some(authorizer) = self.registry.as_ref().and_then(|r| r.authorizer()) else {
+			return Ok(());
+		};
+		let request = AuthorizationRequest::new(
+			tool_name,
+			target,
+			backend_tool,
+			args,
+			ctx.claims().map(|c| &c.inner),
+		);
+		let decision = authorizer.check(&request).await;
+		if decision.allow {
+			return Ok(());
+		}
+		tracing::debug!(
+			target: "virtual_tools",
+			tool = %tool_name,
+			subject = ?request.subject,
+			reason = ?decision.reason,
+			"tool call denied by authorizer"
+		);
+		Err(match decision.reason {
+			Some(reason) => {
+				UpstreamError::InvalidRequest(format!("tool call to '{tool_name}' denied: {reason}"))
+			},
+			None => UpstreamError::Authorization {
+				resource_type: "tool".to_string(),
+				resource_name: tool_name.to_string(),
+			},
+		})
+	}
 
-						// Inject defaults
-						let transformed_args = tool
-							.inject_defaults(args)
-							.map_err(|e| UpstreamError::InvalidRequest(e.to_string()))?;
+	/// Resolve a tool call against a specific registry snapshot, injecting the defaults of
+	/// `caller` (a JWT subject) into virtual tool calls
+	///
+	/// What a name resolves to is memoized per snapshot; operator and schema drift switches
+	/// are checked on every call.
+	pub fn resolve_tool_call_with(
+		&self,
+		registry: Option<&Arc<CompiledRegistry>>,
+		tool_name: &str,
+		args: serde_json::Value,
+		caller: Option<&str>,
+	) -> Result<ResolvedToolCall, UpstreamError> {
+		let resolved = match registry {
+			Some(registry) => self
+				.resolved_names
+				.get_or_try_insert(registry, tool_name, || {
+					self.resolve_name(Some(registry), tool_name)
+				})?,
+			None => self.resolve_name(None, tool_name)?,
+		};
 
-						return Ok(ResolvedToolCall::Backend {
-							target,
-							tool_name: backend_tool,
-							args: transformed_args,
-							virtual_name: Some(tool_name.to_string()),
+		match resolved {
+			ResolvedName::Backend { target, tool_name } => Ok(ResolvedToolCall::Backend {
+				target,
+				tool_name,
+				args,
+				virtual_name: None,
+			}),
+			ResolvedName::Describe => Ok(ResolvedToolCall::Describe { args }),
+			ResolvedName::Composition => {
+				self.check_enabled(tool_name)?;
+				Ok(ResolvedToolCall::Composition {
+					name: tool_name.to_string(),
+					args,
+				})
+			},
+			ResolvedName::Virtual(tool) => {
+				self.check_enabled(tool_name)?;
+				if let Some(reason) = self
+					.registry
+					.as_ref()
+					.and_then(|r| r.schema_drift().disabled_reason(tool_name))
+				{
+					return Err(UpstreamError::InvalidRequest(format!(
+						"virtual tool '{tool_name}' is disabled because its backend schema changed: {reason}"
+					)));
+				}
+				let source = &tool
+					.source_info()
+					.expect("virtual tools resolve to source tools")
+					.source;
+				if source.strict_args {
+					let backend_schema = self.registry.as_ref().and_then(|r| {
+						r.schema_drift()
+							.backend_schema(&source.target, &source.tool)
+					});
+					// Without the backend's schema there is nothing to let arguments through by
+					let Some(problems) = tool.disallowed_arguments(&args, backend_schema.as_deref())
+					else {
+						return Err(UpstreamError::InvalidRequest(format!(
+							"virtual tool '{tool_name}' only accepts the arguments its backend tool lists, which couldn't be listed"
+						)));
+					};
+					if !problems.is_empty() {
+						return Err(UpstreamError::InvalidArguments {
+							tool: tool_name.to_string(),
+							problems,
 						});
 					}
 				}
+
+				// Inject defaults
+				let transformed_args = tool
+					.inject_defaults_for(args, caller)
+					.map_err(|e| UpstreamError::InvalidRequest(e.to_string()))?;
+
+				Ok(ResolvedToolCall::Backend {
+					target: source.target.clone(),
+					tool_name: source.tool.clone(),
+					args: transformed_args,
+					virtual_name: Some(tool_name.to_string()),
+				})
+			},
+		}
+	}
+
+	/// List the backend tools of a strict-args virtual tool whose backend schema hasn't been
+	/// seen yet, so its arguments can be checked without the caller listing tools first
+	///
+	/// Goes through the tools cache when the backend has one, and records the schemas like a
+	/// downstream tools/list does. A failed listing leaves the call to be refused.
+	pub async fn learn_backend_schema(
+		&self,
+		registry: Option<&Arc<CompiledRegistry>>,
+		tool_name: &str,
+		ctx: &IncomingRequestContext,
+	) {
+		let Some((store, registry)) = self.registry.as_ref().zip(registry) else {
+			return;
+		};
+		let Some(source) = registry
+			.get_tool(tool_name)
+			.and_then(|t| t.source_info())
+			.map(|info| &info.source)
+			.filter(|source| source.strict_args)
+		else {
+			return;
+		};
+		let drift = store.schema_drift();
+		if drift.backend_schema(&source.target, &source.tool).is_some() {
+			return;
+		}
+		match self.list_upstream_tools(&strng::new(&source.target), ctx).await {
+			Ok(tools) => {
+				let backend_tools = tools
+					.into_iter()
+					.map(|t| (source.target.clone(), t))
+					.collect_vec();
+				drift.observe(registry, &backend_tools);
+			},
+			Err(e) => tracing::debug!(
+				target: "virtual_tools",
+				tool = %tool_name,
+				backend_target = %source.target,
+				error = %e,
+				"failed to list backend tools for strict arguments"
+			),
+		}
+	}
+
+	/// Fail calls to a tool an operator disabled
+	fn check_enabled(&self, tool_name: &str) -> Result<(), UpstreamError> {
+		match self
+			.registry
+			.as_ref()
+			.and_then(|r| r.kill_switch().disabled_error(tool_name))
+		{
+			Some(error) => Err(UpstreamError::InvalidRequest(error)),
+			None => Ok(()),
+		}
+	}
+
+	/// Whether a registry tool is offered at all: not disabled by an operator and, for
+	/// virtual tools, not withdrawn for an incompatible backend schema change
+	pub fn is_tool_available(&self, tool: &CompiledTool) -> bool {
+		let Some(registry) = self.registry.as_ref() else {
+			return true;
+		};
+		let name = tool.def.name.as_str();
+		!registry.kill_switch().is_disabled(name)
+			&& !(tool.source_info().is_some() && registry.schema_drift().is_disabled(name))
+	}
+
+	/// Whether RBAC lets a caller see a registry tool, checked against the target tools/list
+	/// reports it under
+	pub fn is_tool_allowed(&self, tool: &CompiledTool, cel: &ContextBuilder) -> bool {
+		let target = match tool.source_info() {
+			Some(info) => info.source.target.clone(),
+			None => "_composition".to_string(),
+		};
+		self.policies.validate(
+			&rbac::ResourceType::Tool(rbac::ResourceId::new(target, tool.def.name.clone())),
+			cel,
+		)
+	}
+
+	/// Work out what a tool name refers to in a registry snapshot
+	fn resolve_name(
+		&self,
+		registry: Option<&Arc<CompiledRegistry>>,
+		tool_name: &str,
+	) -> Result<ResolvedName, UpstreamError> {
+		// First, check if this is a virtual tool or composition
+		if let Some(tool) = registry.and_then(|r| r.get_tool(tool_name)) {
+			// Check if this is a composition
+			if tool.is_composition() {
+				tracing::debug!(
+					target: "virtual_tools",
+					composition = tool_name,
+					"resolved tool as composition"
+				);
+				return Ok(ResolvedName::Composition);
+			}
+
+			// This is a source-based virtual tool - resolve to backend
+			if let Some(source_info) = tool.source_info() {
+				tracing::debug!(
+					target: "virtual_tools",
+					virtual_tool = tool_name,
+					backend_target = %source_info.source.target,
+					backend_tool = %source_info.source.tool,
+					"resolved virtual tool to backend"
+				);
+				return Ok(ResolvedName::Virtual(tool.clone()));
 			}
 		}
 
+		// The documentation meta-tool, unless the registry defines a tool of that name
+		if tool_name == DESCRIBE_TOOL && registry.is_some() {
+			return Ok(ResolvedName::Describe);
+		}
+
 		// Not a virtual tool or composition - parse normally
 		let (service_name, actual_tool) = self.parse_resource_name(tool_name)?;
-		Ok(ResolvedToolCall::Backend {
+		Ok(ResolvedName::Backend {
 			target: service_name.to_string(),
 			tool_name: actual_tool.to_string(),
-			args,
-			virtual_name: None,
 		})
 	}
 
-	/// Check if a tool is a composition
-	pub fn is_composition(&self, tool_name: &str) -> bool {
-		if let Some(ref reg) = self.registry {
-			let guard = reg.get();
-			if let Some(ref compiled_registry) = **guard {
-				return compiled_registry.is_composition(tool_name);
-			}
+	/// Upstreams that calls to a backend or source-based virtual tool are sent to, in the
+	/// order they are tried
+	fn tool_targets(&self, registry: Option<&CompiledRegistry>, tool_name: &str) -> Vec<String> {
+		if let Some(tool) = registry.and_then(|r| r.get_tool(tool_name)) {
+			return tool
+				.source_info()
+				.map(|info| info.source.targets().map(str::to_string).collect())
+				.unwrap_or_default();
+		}
+		match self.parse_resource_name(tool_name) {
+			Ok((target, _)) => vec![target.to_string()],
+			Err(_) => Vec::new(),
+		}
+	}
+
+	/// Current compiled registry, if one is loaded and the kill switch isn't engaged.
+	///
+	/// Holding the returned Arc keeps lookups consistent even if the registry reloads.
+	pub fn registry_snapshot(&self) -> Option<Arc<CompiledRegistry>> {
+		let registry = self.registry.as_ref()?;
+		if registry.kill_switch().is_passthrough() {
+			return None;
+		}
+		registry.get_arc()
+	}
+
+	/// Transport retry policy configured for a virtual tool in the registry
+	pub fn transport_retry(&self, virtual_name: Option<&str>) -> Option<TransportRetry> {
+		transport_retry_in(self.registry_snapshot().as_deref()?, virtual_name?)
+	}
+
+	/// Targets a call to `target` tries in turn: `target`, then the fallback targets of the
+	/// virtual tool the call is for
+	pub fn failover_targets(&self, target: &str, virtual_name: Option<&str>) -> Vec<String> {
+		failover_targets_in(self.registry_snapshot().as_deref(), target, virtual_name)
+	}
+
+	/// Priority calls for a tool or composition in the registry are shed at
+	pub fn priority(&self, name: Option<&str>) -> ToolPriority {
+		priority_in(self.registry_snapshot().as_deref(), name)
+	}
+
+	/// Pagination configured for a virtual tool in the registry
+	pub fn pagination(&self, virtual_name: Option<&str>) -> Option<Pagination> {
+		pagination_in(self.registry_snapshot().as_deref()?, virtual_name?)
+	}
+
+	/// Job polling configured for a virtual tool in the registry
+	pub fn job_polling(&self, virtual_name: Option<&str>) -> Option<JobPolling> {
+		job_polling_in(self.registry_snapshot().as_deref()?, virtual_name?)
+	}
+
+	/// Debounce window configured for a tool or composition in the registry
+	pub fn debounce(&self, name: &str) -> Option<Debounce> {
+		self
+			.registry_snapshot()?
+			.get_tool(name)?
+			.def
+			.debounce
+			.clone()
+	}
+
+	/// When the gateway requires confirmation, refuse a call of a tool the registry annotates
+	/// destructive unless the caller confirmed it in the request's `_meta`
+	pub fn check_confirmation(&self, name: &str, meta: Option<&Meta>) -> Result<(), UpstreamError> {
+		match self.registry_snapshot() {
+			Some(registry) if registry.requires_confirmation(name) => {
+				check_confirmation(name, meta.map(|m| &m.0))
+					.map_err(|e| UpstreamError::InvalidRequest(e.to_string()))
+			},
+			_ => Ok(()),
+		}
+	}
+
+	/// Count a call of a tool for usage analytics, if they are configured
+	pub fn record_usage(&self, name: &str, caller: &str) {
+		if let Some(usage) = self.registry().and_then(|r| r.usage()) {
+			usage.record(name, caller);
 		}
-		false
 	}
 
-	/// Transform tool output for virtual tools
-	pub fn transform_tool_output(
+	/// Idempotency keys accepted by a composition in the registry
+	pub fn idempotency(&self, name: &str) -> Option<Idempotency> {
+		self
+			.registry_snapshot()?
+			.get_tool(name)?
+			.def
+			.idempotency
+			.clone()
+	}
+
+	/// Request context for a call to `target`, with the registry's headers for it applied
+	fn target_context<'a>(
 		&self,
-		virtual_name: &str,
-		response: serde_json::Value,
-	) -> Result<serde_json::Value, UpstreamError> {
-		if let Some(ref reg) = self.registry {
-			let guard = reg.get();
-			if let Some(ref compiled_registry) = **guard {
-				return compiled_registry
-					.transform_output(virtual_name, response)
-					.map_err(|e| UpstreamError::InvalidRequest(e.to_string()));
-			}
+		target: &str,
+		ctx: &'a IncomingRequestContext,
+	) -> Cow<'a, IncomingRequestContext> {
+		match self.registry_snapshot() {
+			Some(registry) => match registry.target_policy(target) {
+				Some(policy) => Cow::Owned(with_target_headers(policy, ctx)),
+				None => Cow::Borrowed(ctx),
+			},
+			None => Cow::Borrowed(ctx),
+		}
+	}
+
+	/// Apply the registry's headers and `_meta` entries for `target` to a request
+	fn for_target<'a>(
+		&self,
+		target: &str,
+		r: &mut JsonRpcRequest<ClientRequest>,
+		ctx: &'a IncomingRequestContext,
+	) -> Cow<'a, IncomingRequestContext> {
+		let Some(registry) = self.registry_snapshot() else {
+			return Cow::Borrowed(ctx);
+		};
+		let Some(policy) = registry.target_policy(target) else {
+			return Cow::Borrowed(ctx);
+		};
+		if policy.has_meta() {
+			let claims = ctx.claims().map(|c| &c.inner);
+			r.request.get_meta_mut().extend(Meta(policy.meta(claims)));
 		}
-		Ok(response)
+		Cow::Owned(with_target_headers(policy, ctx))
+	}
+
+	/// Check if a tool is a composition
+	pub fn is_composition(&self, tool_name: &str) -> bool {
+		self
+			.registry_snapshot()
+			.is_some_and(|r| r.is_composition(tool_name))
 	}
 
 	pub fn parse_resource_name<'a, 'b: 'a>(
@@ -232,12 +939,15 @@ impl Relay {
 
 	/// Invoke a tool on a specific target and return the result as JSON.
 	/// This is used by the composition executor to call backend tools.
+	///
+	/// With a transport retry policy, calls that fail before a response arrives are retried.
 	pub async fn invoke_tool(
 		&self,
 		target: &str,
 		tool_name: &str,
 		args: serde_json::Value,
 		ctx: &IncomingRequestContext,
+		retry: Option<&TransportRetry>,
 	) -> Result<serde_json::Value, UpstreamError> {
 		use futures_util::StreamExt;
 
@@ -263,21 +973,37 @@ impl Relay {
 			extensions: Default::default(),
 		};
 
-		let request: JsonRpcRequest<ClientRequest> = JsonRpcRequest {
+		let mut request: JsonRpcRequest<ClientRequest> = JsonRpcRequest {
 			jsonrpc: Default::default(),
 			id: request_id.clone(),
 			request: ClientRequest::CallToolRequest(call_tool_request),
 		};
+		let ctx = self.for_target(target, &mut request, ctx);
+		let ctx = ctx.as_ref();
 
-		// Send the request and get the response stream
-		let mut stream = upstream.generic_stream(request, ctx).await?;
+		// Send the request and wait for the first message from the response stream
+		let response = self
+			.guarded(
+				target,
+				with_transport_retry(retry, target, || {
+					let request = request.clone();
+					async move {
+						let mut stream = upstream.generic_stream(request, ctx).await?;
+						stream
+							.next()
+							.await
+							.ok_or(UpstreamError::Recv)?
+							.map_err(UpstreamError::Http)
+					}
+				}),
+			)
+			.await?;
 
-		// Get the first message from the stream
-		let response = stream
-			.next()
-			.await
-			.ok_or_else(|| UpstreamError::InvalidRequest("No response from tool call".to_string()))?
-			.map_err(|e| UpstreamError::InvalidRequest(format!("Tool call error: {}", e)))?;
+		let limits = self
+			.registry
+			.as_ref()
+			.map(|r| r.json_limits())
+			.unwrap_or_default();
 
 		// Extract the result from the JSON-RPC response
 		match response {
@@ -289,13 +1015,15 @@ impl Relay {
 						// Find text content and try to parse as JSON
 						for content in &ctr.content {
 							if let rmcp::model::RawContent::Text(t) = &content.raw {
-								// Try to parse as JSON, fall back to raw text
-								if let Ok(json) = serde_json::from_str::<serde_json::Value>(&t.text) {
-									return Ok(json);
-								} else {
+								// Try to parse as JSON within the limits, fall back to raw text
+								return match limits.parse(&t.text) {
+									Ok(Some(json)) => Ok(json),
 									// Return as string value if not valid JSON
-									return Ok(serde_json::Value::String(t.text.clone()));
-								}
+									Ok(None) => Ok(serde_json::Value::String(t.text.clone())),
+									Err(e) => Err(UpstreamError::InvalidRequest(format!(
+										"response from {target}/{tool_name} rejected: {e}"
+									))),
+								};
 							}
 						}
 						// No text content found, return null
@@ -318,25 +1046,167 @@ impl Relay {
 			)),
 		}
 	}
+
+	/// List every tool of a target in a short-lived session of its own, outside of any
+	/// downstream session. Used to scaffold registry entries for a newly added server.
+	pub async fn list_target_tools(
+		&self,
+		target: &str,
+		ctx: &IncomingRequestContext,
+	) -> Result<Vec<Tool>, UpstreamError> {
+		let upstream = self
+			.upstreams
+			.get(target)
+			.map_err(|_| UpstreamError::InvalidRequest(format!("unknown service {}", target)))?;
+		let ctx = self.target_context(target, ctx);
+		let ctx = ctx.as_ref();
+		let request = |id: i64, request: ClientRequest| JsonRpcRequest {
+			jsonrpc: Default::default(),
+			id: RequestId::Number(id),
+			request,
+		};
+		let send = |request: JsonRpcRequest<ClientRequest>| async move {
+			let response = upstream
+				.generic_stream(request, ctx)
+				.await?
+				.next()
+				.await
+				.ok_or(UpstreamError::Recv)?
+				.map_err(UpstreamError::Http)?;
+			match response {
+				ServerJsonRpcMessage::Response(resp) => Ok(resp.result),
+				ServerJsonRpcMessage::Error(err) => Err(UpstreamError::InvalidRequest(format!(
+					"{} failed: {}",
+					target, err.error.message
+				))),
+				_ => Err(UpstreamError::InvalidRequest(format!(
+					"unexpected response from {}",
+					target
+				))),
+			}
+		};
+
+		let initialize = rmcp::model::InitializeRequest {
+			method: Default::default(),
+			params: super::session::get_client_info(),
+			extensions: Default::default(),
+		};
+		send(request(0, ClientRequest::InitializeRequest(initialize))).await?;
+		upstream
+			.generic_notification(
+				ClientNotification::InitializedNotification(rmcp::model::InitializedNotification {
+					method: Default::default(),
+					extensions: Default::default(),
+				}),
+				ctx,
+			)
+			.await?;
+
+		let mut tools = Vec::new();
+		let mut cursor = None;
+		// A server that keeps handing out cursors is cut off after 100 pages
+		for id in 1..=100 {
+			let list = rmcp::model::ListToolsRequest {
+				method: Default::default(),
+				params: cursor
+					.take()
+					.map(|cursor| rmcp::model::PaginatedRequestParam {
+						cursor: Some(cursor),
+					}),
+				extensions: Default::default(),
+			};
+			let ServerResult::ListToolsResult(page) =
+				send(request(id, ClientRequest::ListToolsRequest(list))).await?
+			else {
+				return Err(UpstreamError::InvalidRequest(format!(
+					"unexpected response to tools/list from {}",
+					target
+				)));
+			};
+			tools.extend(page.tools);
+			match page.next_cursor {
+				Some(next) if !next.is_empty() => cursor = Some(next),
+				_ => break,
+			}
+		}
+		// Best effort: the session was only needed for the listing
+		let _ = upstream.delete(ctx).await;
+		Ok(tools)
+	}
 }
 
 // =============================================================================
 // RelayToolInvoker - Real ToolInvoker implementation using Relay
 // =============================================================================
 
-use crate::mcp::registry::executor::{ExecutionError, ToolInvoker};
+use rmcp::model::{
+	CreateMessageRequestParam, CreateMessageResult, LoggingLevel, LoggingMessageNotificationParam,
+	Notification, ProgressNotificationParam, ProgressToken,
+};
+use tokio::sync::mpsc;
+
+use crate::mcp::composition_tracing;
+use crate::mcp::registry::executor::{ExecutionError, InvocationMeta, ToolInvoker};
+use crate::mcp::sampling::SamplingBridge;
 
 /// A ToolInvoker implementation that uses the Relay to make real backend calls.
 /// This is used by the CompositionExecutor to invoke tools during composition execution.
 pub struct RelayToolInvoker {
 	relay: Arc<Relay>,
 	ctx: IncomingRequestContext,
+	/// Composition span that backend calls are parented to
+	span: Option<SpanContext>,
+	/// Registry snapshot every lookup is made against, so a reload mid-execution can't change
+	/// the tool set a running composition sees
+	registry: Option<Arc<CompiledRegistry>>,
+	/// Session sampling bridge and the tool call stream that sampling requests and partial
+	/// results go out on
+	sampling: Option<(Arc<SamplingBridge>, mpsc::Sender<ServerJsonRpcMessage>)>,
+	/// Token the client asked for progress notifications under
+	progress_token: Option<ProgressToken>,
 }
 
 impl RelayToolInvoker {
-	/// Create a new RelayToolInvoker
+	/// Create a new RelayToolInvoker, snapshotting the relay's current registry
 	pub fn new(relay: Arc<Relay>, ctx: IncomingRequestContext) -> Self {
-		Self { relay, ctx }
+		let registry = relay.registry_snapshot();
+		Self {
+			relay,
+			ctx,
+			span: None,
+			registry,
+			sampling: None,
+			progress_token: None,
+		}
+	}
+
+	/// Builder: resolve tools against this registry snapshot (e.g. the one the executor uses)
+	pub fn with_registry_snapshot(mut self, registry: Arc<CompiledRegistry>) -> Self {
+		self.registry = Some(registry);
+		self
+	}
+
+	/// Builder: propagate this span context to backends instead of the request's
+	pub fn with_span_context(mut self, span: SpanContext) -> Self {
+		self.span = Some(span);
+		self
+	}
+
+	/// Builder: send sampling requests to the client on `stream`, routing replies through
+	/// the session's bridge
+	pub fn with_sampling(
+		mut self,
+		bridge: Arc<SamplingBridge>,
+		stream: mpsc::Sender<ServerJsonRpcMessage>,
+	) -> Self {
+		self.sampling = Some((bridge, stream));
+		self
+	}
+
+	/// Builder: report progress on the sampling stream under `token`, if the client sent one
+	pub fn with_progress_token(mut self, token: Option<ProgressToken>) -> Self {
+		self.progress_token = token;
+		self
 	}
 }
 
@@ -346,11 +1216,39 @@ impl ToolInvoker for RelayToolInvoker {
 		&self,
 		tool_name: &str,
 		args: serde_json::Value,
+	) -> Result<serde_json::Value, ExecutionError> {
+		self
+			.invoke_with_meta(tool_name, args, &InvocationMeta::default())
+			.await
+	}
+
+	async fn invoke_with_meta(
+		&self,
+		tool_name: &str,
+		args: serde_json::Value,
+		meta: &InvocationMeta,
 	) -> Result<serde_json::Value, ExecutionError> {
 		// Resolve the tool call (handles virtual tools, compositions, and backend tools)
+		let registry = self.registry.as_ref();
+		if let Some(builtin) = builtin_tool(tool_name)
+			&& registry.is_none_or(|r| r.get_tool(tool_name).is_none())
+		{
+			// Steps are authorized like top-level calls, so a composition cannot reach tools the
+			// caller could not call directly
+			self
+				.relay
+				.authorize_tool_call(tool_name, BUILTIN_TARGET, tool_name, &args, &self.ctx)
+				.await
+				.map_err(execution_error)?;
+			return invoke_builtin(builtin, args).await;
+		}
+		self
+			.relay
+			.learn_backend_schema(registry, tool_name, &self.ctx)
+			.await;
 		let resolved = self
 			.relay
-			.resolve_tool_call(tool_name, args)
+			.resolve_tool_call_with(registry, tool_name, args, self.ctx.subject())
 			.map_err(|e| ExecutionError::ToolExecutionFailed(e.to_string()))?;
 
 		match resolved {
@@ -360,21 +1258,82 @@ impl ToolInvoker for RelayToolInvoker {
 				args,
 				virtual_name,
 			} => {
-				// Use the Relay's invoke_tool method which handles the MCP protocol properly
-				let result = self
+				self
 					.relay
-					.invoke_tool(&target, &backend_tool, args, &self.ctx)
+					.authorize_tool_call(tool_name, &target, &backend_tool, &args, &self.ctx)
 					.await
-					.map_err(|e| ExecutionError::ToolExecutionFailed(e.to_string()))?;
+					.map_err(execution_error)?;
+				// Propagate trace context and correlation baggage so backend logs can be tied
+				// back to the composition step
+				let ctx = composition_tracing::propagate(&self.ctx, self.span.as_ref(), meta);
+				self.relay.record_attributed_tool_call(&backend_tool, meta);
 
-				// Apply output transformation if this was a virtual tool
-				if let Some(vname) = virtual_name {
-					self
-						.relay
-						.transform_tool_output(&vname, result)
-						.map_err(|e| ExecutionError::ToolExecutionFailed(e.to_string()))
+				// Use the Relay's invoke_tool method which handles the MCP protocol properly
+				let retry = registry
+					.zip(virtual_name.as_deref())
+					.and_then(|(r, v)| transport_retry_in(r, v));
+				let pagination = registry
+					.zip(virtual_name.as_deref())
+					.and_then(|(r, v)| pagination_in(r, v));
+				let job_polling = registry
+					.zip(virtual_name.as_deref())
+					.and_then(|(r, v)| job_polling_in(r, v));
+				let result = if target == BUILTIN_TARGET {
+					invoke_builtin(&backend_tool, args).await?
 				} else {
-					Ok(result)
+					let targets =
+						failover_targets_in(registry.map(Arc::as_ref), &target, virtual_name.as_deref());
+					// Calls a composition makes are shed at the composition's priority
+					let priority = priority_in(
+						registry.map(Arc::as_ref),
+						meta.composition.as_deref().or(virtual_name.as_deref()),
+					);
+					let (result, served_by) = with_failover(&targets, |target| {
+						let args = args.clone();
+						let (ctx, retry, pagination) = (&ctx, retry.as_ref(), pagination.as_ref());
+						let backend_tool = &backend_tool;
+						async move {
+							self.relay.check_load(&target, priority)?;
+							let invoke = |args| {
+								self
+									.relay
+									.invoke_tool(&target, backend_tool, args, ctx, retry)
+							};
+							match pagination {
+								Some(pagination) => pagination.fetch_all(args, invoke).await,
+								None => invoke(args).await,
+							}
+						}
+					})
+					.await
+					.map_err(execution_error)?;
+					if served_by != target {
+						tracing::info!(
+							target: "virtual_tools",
+							tool = tool_name,
+							served_by = %served_by,
+							"call served by a fallback target"
+						);
+					}
+					match &job_polling {
+						Some(polling) => polling
+							.wait(result, |args| {
+								self
+									.relay
+									.invoke_tool(&served_by, &polling.status_tool, args, &ctx, retry.as_ref())
+							})
+							.await
+							.map_err(execution_error)?,
+						None => result,
+					}
+				};
+
+				// Apply output transformation if this was a virtual tool
+				match (virtual_name, registry) {
+					(Some(vname), Some(registry)) => registry
+						.transform_output(&vname, result)
+						.map_err(|e| ExecutionError::ToolExecutionFailed(e.to_string())),
+					_ => Ok(result),
 				}
 			},
 			ResolvedToolCall::Composition { name, .. } => {
@@ -384,8 +1343,169 @@ impl ToolInvoker for RelayToolInvoker {
 					name
 				)))
 			},
+			ResolvedToolCall::Describe { args } => {
+				self
+					.relay
+					.authorize_tool_call(tool_name, BUILTIN_TARGET, DESCRIBE_TOOL, &args, &self.ctx)
+					.await
+					.map_err(execution_error)?;
+				registry
+					.ok_or_else(|| ExecutionError::ToolExecutionFailed("no registry loaded".to_string()))
+					.and_then(|r| {
+						// Steps aren't subject to RBAC, but still only see the tools on offer
+						crate::mcp::registry::describe(r, &args, |tool| self.relay.is_tool_available(tool))
+							.map_err(ExecutionError::ToolExecutionFailed)
+					})
+			},
+		}
+	}
+
+	async fn create_message(
+		&self,
+		request: CreateMessageRequestParam,
+	) -> Result<CreateMessageResult, ExecutionError> {
+		match &self.sampling {
+			Some((bridge, stream)) => bridge.create_message(stream, request).await,
+			None => Err(ExecutionError::SamplingUnavailable(
+				"sampling requires a stateful MCP session".to_string(),
+			)),
+		}
+	}
+
+	fn can_emit_partial(&self) -> bool {
+		self.sampling.is_some()
+	}
+
+	async fn emit_partial(&self, partial: serde_json::Value) -> Result<(), ExecutionError> {
+		let Some((_, stream)) = &self.sampling else {
+			return Err(ExecutionError::PatternExecutionFailed(
+				"partial results require a stateful MCP session".to_string(),
+			));
+		};
+		let notification = ServerNotification::LoggingMessageNotification(Notification::new(
+			LoggingMessageNotificationParam {
+				level: LoggingLevel::Info,
+				logger: Some(PARTIAL_RESULT_LOGGER.to_string()),
+				data: partial,
+			},
+		));
+		stream
+			.send(ServerJsonRpcMessage::notification(notification))
+			.await
+			.map_err(|_| ExecutionError::PatternExecutionFailed("client stream closed".to_string()))
+	}
+
+	async fn report_progress(&self, completed: u32, total: u32, message: String) {
+		let (Some((_, stream)), Some(token)) = (&self.sampling, &self.progress_token) else {
+			return;
+		};
+		let notification =
+			ServerNotification::ProgressNotification(Notification::new(ProgressNotificationParam {
+				progress_token: token.clone(),
+				progress: f64::from(completed),
+				total: Some(f64::from(total)),
+				message: Some(message),
+			}));
+		// Progress is advisory: a closed stream fails the execution when it next matters
+		let _ = stream
+			.send(ServerJsonRpcMessage::notification(notification))
+			.await;
+	}
+
+	fn is_available(&self, tool_name: &str) -> bool {
+		let targets = self.relay.tool_targets(self.registry.as_deref(), tool_name);
+		targets.is_empty()
+			|| targets
+				.iter()
+				.any(|target| self.relay.is_upstream_available(target))
+	}
+}
+
+/// Composition error for a failed backend call; transport failures are transient, so step
+/// retries pick them up
+fn execution_error(e: UpstreamError) -> ExecutionError {
+	if e.is_transport() {
+		ExecutionError::Transient(e.to_string())
+	} else {
+		ExecutionError::ToolExecutionFailed(e.to_string())
+	}
+}
+
+/// Copy of `ctx` with a target policy's headers set
+fn with_target_headers(
+	policy: &CompiledTargetPolicy,
+	ctx: &IncomingRequestContext,
+) -> IncomingRequestContext {
+	let claims = ctx.claims().map(|c| &c.inner);
+	let mut out = ctx.clone();
+	for (name, value) in policy.headers(claims) {
+		match http::HeaderValue::try_from(value.as_ref()) {
+			Ok(value) => out = out.with_header(name.clone(), value),
+			Err(_) => tracing::warn!(
+				target: "virtual_tools",
+				header = %name,
+				"skipping target header with an invalid value"
+			),
 		}
 	}
+	out
+}
+
+/// Pagination configured for a virtual tool
+fn pagination_in(registry: &CompiledRegistry, virtual_name: &str) -> Option<Pagination> {
+	registry
+		.get_tool(virtual_name)?
+		.source_info()?
+		.source
+		.pagination
+		.clone()
+}
+
+/// Job polling configured for a virtual tool
+fn job_polling_in(registry: &CompiledRegistry, virtual_name: &str) -> Option<JobPolling> {
+	registry
+		.get_tool(virtual_name)?
+		.source_info()?
+		.source
+		.job_polling
+		.as_deref()
+		.cloned()
+}
+
+/// `target` followed by the fallback targets configured for a virtual tool
+fn failover_targets_in(
+	registry: Option<&CompiledRegistry>,
+	target: &str,
+	virtual_name: Option<&str>,
+) -> Vec<String> {
+	let fallbacks = registry
+		.zip(virtual_name)
+		.and_then(|(r, v)| r.get_tool(v)?.source_info())
+		.map(|info| info.source.fallback_targets.as_slice())
+		.unwrap_or_default();
+	std::iter::once(target.to_string())
+		.chain(fallbacks.iter().cloned())
+		.collect()
+}
+
+/// Priority calls for a tool or composition are shed at; calls outside the registry are of
+/// normal priority
+fn priority_in(registry: Option<&CompiledRegistry>, name: Option<&str>) -> ToolPriority {
+	registry
+		.zip(name)
+		.and_then(|(r, n)| r.get_tool(n))
+		.map(|tool| tool.def.priority)
+		.unwrap_or_default()
+}
+
+/// Transport retry policy configured for a virtual tool
+fn transport_retry_in(registry: &CompiledRegistry, virtual_name: &str) -> Option<TransportRetry> {
+	registry
+		.get_tool(virtual_name)?
+		.source_info()?
+		.source
+		.transport_retry
+		.clone()
 }
 
 impl Relay {
@@ -413,7 +1533,13 @@ impl Relay {
 		self.default_target_name.clone()
 	}
 
-	pub fn merge_tools(&self, cel: Arc<ContextBuilder>) -> Box<MergeFn> {
+	/// Merge upstream tool lists, describing registry tools in the locale best matching
+	/// `accept_language` where they define one
+	pub fn merge_tools(
+		&self,
+		cel: Arc<ContextBuilder>,
+		accept_language: Option<String>,
+	) -> Box<MergeFn> {
 		let policies = self.policies.clone();
 		let default_target_name = self.default_target_name.clone();
 		// Clone registry reference for use in closure
@@ -436,10 +1562,52 @@ impl Relay {
 				.collect_vec();
 
 			// Apply registry transformations if configured
-			let transformed_tools = if let Some(ref reg) = registry {
+			let mut describe_tool = None;
+			// Virtual tools and compositions are called by their registry names, so they are
+			// listed without a target prefix
+			let mut registry_tools = HashSet::new();
+			let transformed_tools = if let Some(ref reg) = registry
+				&& !reg.kill_switch().is_passthrough()
+			{
 				let guard = reg.get();
 				if let Some(ref compiled_registry) = **guard {
-					compiled_registry.transform_tools(backend_tools)
+					if compiled_registry.get_tool(DESCRIBE_TOOL).is_none() {
+						describe_tool = Some(describe_tool_definition());
+					}
+					// Withdraw virtual tools whose backend schema drifted incompatibly
+					let drift = reg.schema_drift();
+					drift.observe(compiled_registry, &backend_tools);
+					// and tools disabled by an operator
+					let kill_switch = reg.kill_switch();
+					compiled_registry
+						.transform_tools(backend_tools)
+						.into_iter()
+						.filter(|(_, t)| {
+							!(compiled_registry.is_source_tool(&t.name) && drift.is_disabled(&t.name))
+						})
+						.filter(|(_, t)| {
+							compiled_registry.get_tool(&t.name).is_none() || !kill_switch.is_disabled(&t.name)
+						})
+						.map(|(server_name, mut t)| {
+							if let Some(accept_language) = accept_language.as_deref()
+								&& let Some(localized) =
+									compiled_registry.localized_description(&t.name, accept_language)
+							{
+								t.description = Some(Cow::Owned(localized.to_string()));
+							}
+							(server_name, t)
+						})
+						.inspect(|(server_name, t)| {
+							if compiled_registry.get_tool(&t.name).is_some_and(|tool| {
+								tool.is_composition()
+									|| tool
+										.source_info()
+										.is_some_and(|s| &s.source.target == server_name)
+							}) {
+								registry_tools.insert((server_name.clone(), t.name.to_string()));
+							}
+						})
+						.collect()
 				} else {
 					backend_tools
 				}
@@ -460,14 +1628,27 @@ impl Relay {
 					)
 				})
 				// Rename to handle multiplexing
-				.map(|(server_name, t)| Tool {
-					name: Cow::Owned(resource_name(
-						default_target_name.as_ref(),
-						server_name.as_str(),
-						&t.name,
-					)),
-					..t
+				.map(|(server_name, t)| {
+					let registry_tool = registry_tools.contains(&(server_name.clone(), t.name.to_string()));
+					Tool {
+						name: Cow::Owned(if registry_tool {
+							t.name.to_string()
+						} else {
+							resource_name(default_target_name.as_ref(), server_name.as_str(), &t.name)
+						}),
+						..t
+					}
 				})
+				// The documentation meta-tool is answered by the gateway, so it keeps its name
+				.chain(describe_tool.filter(|_| {
+					policies.validate(
+						&rbac::ResourceType::Tool(rbac::ResourceId::new(
+							BUILTIN_TARGET.to_string(),
+							DESCRIBE_TOOL.to_string(),
+						)),
+						&cel,
+					)
+				}))
 				.collect_vec();
 
 			Ok(
@@ -629,7 +1810,12 @@ impl Relay {
 				"unknown service {service_name}"
 			)));
 		};
-		let stream = us.generic_stream(r, &ctx).await?;
+		let mut r = r;
+		let ctx = self.for_target(service_name, &mut r, &ctx);
+		let stream = self
+			.guarded(service_name, us.generic_stream(r, &ctx))
+			.await?;
+		let stream = self.watch_tool_list_changes(&strng::new(service_name), stream);
 
 		messages_to_response(id, stream)
 	}
@@ -650,17 +1836,41 @@ impl Relay {
 		);
 
 		let id = r.id.clone();
-		let Ok(us) = self.upstreams.get(service_name) else {
-			tracing::warn!(
-				target: "virtual_tools",
-				service = service_name,
-				"backend service not found in upstreams"
-			);
-			return Err(UpstreamError::InvalidRequest(format!(
-				"unknown service {service_name}"
-			)));
-		};
-		let stream = us.generic_stream(r, &ctx).await?;
+		// Only failures to open the response stream are retried or failed over; once streaming
+		// starts the response is forwarded as-is
+		let retry = self.transport_retry(virtual_name.as_deref());
+		let targets = self.failover_targets(service_name, virtual_name.as_deref());
+		let priority = self.priority(virtual_name.as_deref());
+		let (stream, served_by) = with_failover(&targets, |target| {
+			let mut r = r.clone();
+			let ctx = &ctx;
+			let retry = retry.as_ref();
+			async move {
+				self.check_load(&target, priority)?;
+				let Ok(us) = self.upstreams.get(&target) else {
+					tracing::warn!(
+						target: "virtual_tools",
+						service = %target,
+						"backend service not found in upstreams"
+					);
+					return Err(UpstreamError::InvalidRequest(format!(
+						"unknown service {target}"
+					)));
+				};
+				let ctx = self.for_target(&target, &mut r, ctx);
+				let stream = self
+					.guarded(
+						&target,
+						with_transport_retry(retry, &target, || us.generic_stream(r.clone(), &ctx)),
+					)
+					.await?;
+				Ok(self.watch_tool_list_changes(&strng::new(&target), stream))
+			}
+		})
+		.await?;
+		let served_by = (targets.len() > 1).then_some(served_by.as_str());
+		let meta = result_meta(ctx.quota(), served_by);
+		let stream = stream.map(move |msg| msg.map(|m| with_result_meta(m, meta.as_ref())));
 
 		// If we have a virtual name and registry, transform the output
 		if let Some(vname) = virtual_name {
@@ -674,6 +1884,64 @@ impl Relay {
 
 		messages_to_response(id, stream)
 	}
+	/// Call a virtual tool and buffer its whole result, following pagination or waiting on
+	/// the submitted job if configured
+	///
+	/// Unlike other tool calls the response isn't streamed from the backend, so this is
+	/// used where the result is needed as a whole: to merge pages, to wait for a job, or to
+	/// share it with debounced repeats of the call.
+	pub async fn call_buffered(
+		&self,
+		ctx: &IncomingRequestContext,
+		call: BufferedCall<'_>,
+	) -> Result<rmcp::model::CallToolResult, UpstreamError> {
+		let retry = self.transport_retry(Some(call.virtual_name));
+		let targets = self.failover_targets(call.target, Some(call.virtual_name));
+		let priority = self.priority(Some(call.virtual_name));
+		// A call that fails over starts again from the first page, since cursors belong to the
+		// target that handed them out
+		let (result, served_by) = with_failover(&targets, |target| {
+			let args = call.args.clone();
+			let retry = retry.as_ref();
+			async move {
+				self.check_load(&target, priority)?;
+				let invoke = |args| self.invoke_tool(&target, call.tool_name, args, ctx, retry);
+				match call.pagination {
+					Some(pagination) => pagination.fetch_all(args, invoke).await,
+					None => invoke(args).await,
+				}
+			}
+		})
+		.await?;
+		// A job is polled on the target that accepted it, without failing over
+		let result = match call.job_polling {
+			Some(polling) => {
+				polling
+					.wait(result, |args| {
+						self.invoke_tool(&served_by, &polling.status_tool, args, ctx, retry.as_ref())
+					})
+					.await?
+			},
+			None => result,
+		};
+		let result = match self.registry_snapshot() {
+			Some(registry) => registry
+				.transform_output(call.virtual_name, result)
+				.map_err(|e| UpstreamError::InvalidRequest(e.to_string()))?,
+			None => result,
+		};
+		Ok(rmcp::model::CallToolResult {
+			content: vec![rmcp::model::Content::text(
+				serde_json::to_string(&result).unwrap_or_default(),
+			)],
+			structured_content: None,
+			is_error: None,
+			meta: result_meta(
+				ctx.quota(),
+				(targets.len() > 1).then_some(served_by.as_str()),
+			),
+		})
+	}
 	// For some requests, we don't have a sane mapping of incoming requests to a specific
 	// downstream service when multiplexing. Only forward when we have only one backend.
 	pub async fn send_single_without_multiplexing(
@@ -690,8 +1958,8 @@ impl Relay {
 		&self,
 		ctx: IncomingRequestContext,
 	) -> Result<Response, UpstreamError> {
-		for (_, con) in self.upstreams.iter_named() {
-			con.delete(&ctx).await?;
+		for (name, con) in self.upstreams.iter_named() {
+			con.delete(&self.target_context(&name, &ctx)).await?;
 		}
 		Ok(accepted_response())
 	}
@@ -701,7 +1969,10 @@ impl Relay {
 	) -> Result<Response, UpstreamError> {
 		let mut streams = Vec::new();
 		for (name, con) in self.upstreams.iter_named() {
-			streams.push((name, con.get_event_stream(&ctx).await?));
+			let stream = con
+				.get_event_stream(&self.target_context(&name, &ctx))
+				.await?;
+			streams.push((name.clone(), self.watch_tool_list_changes(&name, stream)));
 		}
 
 		let ms = mergestream::MergeStream::new_without_merge(streams);
@@ -714,27 +1985,239 @@ impl Relay {
 		merge: Box<MergeFn>,
 	) -> Result<Response, UpstreamError> {
 		let id = r.id.clone();
-		let mut streams = Vec::new();
-		for (name, con) in self.upstreams.iter_named() {
-			streams.push((name, con.generic_stream(r.clone(), &ctx).await?));
+		let streams = self
+			.open_fanout(|name, con| {
+				let mut r = r.clone();
+				let ctx = &ctx;
+				async move {
+					let ctx = self.for_target(&name, &mut r, ctx);
+					let stream = con.generic_stream(r, &ctx).await?;
+					Ok::<_, UpstreamError>(self.watch_tool_list_changes(&name, stream))
+				}
+			})
+			.await?;
+
+		let ms = mergestream::MergeStream::new(streams, id.clone(), merge);
+		messages_to_response(id, ms)
+	}
+
+	/// Open a stream to every upstream concurrently, so a slow upstream only delays the
+	/// request by its own latency. Each upstream is bounded by its backend's request timeout
+	/// (`DEFAULT_FANOUT_UPSTREAM_TIMEOUT` if unset), and any failure fails the whole request.
+	/// Upstreams whose circuit breaker is open are left out, unless that leaves none.
+	async fn open_fanout<F, Fut>(
+		&self,
+		open: F,
+	) -> Result<Vec<(Strng, mergestream::Messages)>, UpstreamError>
+	where
+		F: Fn(Strng, Arc<upstream::Upstream>) -> Fut,
+		Fut: Future<Output = Result<mergestream::Messages, UpstreamError>>,
+	{
+		let pending = self.upstreams.iter_named().map(|(name, con)| {
+			let fut = open(name.clone(), con);
+			let timeout = self
+				.fanout_timeouts
+				.get(&name)
+				.copied()
+				.unwrap_or(DEFAULT_FANOUT_UPSTREAM_TIMEOUT);
+			async move {
+				let opened = self
+					.guarded(&name, async {
+						tokio::time::timeout(timeout, fut)
+							.await
+							.map_err(|_| UpstreamError::Timeout(name.to_string()))?
+					})
+					.await;
+				self.record_readiness(&name, &opened);
+				match opened {
+					Ok(stream) => Ok((name, Some(stream))),
+					Err(UpstreamError::CircuitOpen(_)) => Ok((name, None)),
+					Err(e) => Err(e),
+				}
+			}
+		});
+		let opened: Vec<_> = futures::future::join_all(pending)
+			.await
+			.into_iter()
+			.collect::<Result<_, UpstreamError>>()?;
+		if !opened.is_empty() && opened.iter().all(|(_, stream)| stream.is_none()) {
+			let names = opened.iter().map(|(name, _)| name.as_str()).join(", ");
+			return Err(UpstreamError::CircuitOpen(names));
 		}
+		Ok(
+			opened
+				.into_iter()
+				.filter_map(|(name, stream)| Some((name, stream?)))
+				.collect(),
+		)
+	}
+	/// Fan out a ListTools request, answering from the tools cache for upstreams that have a
+	/// fresh entry. A miss while another session's ListTools to the same upstream is in flight
+	/// waits for its result. Paginated requests always go to the upstreams.
+	///
+	/// Messages an upstream sends ahead of its response reach the session whose ListTools
+	/// fetched it; sessions answered from the cache or from another's fetch only get the result.
+	pub async fn send_fanout_list_tools(
+		&self,
+		r: JsonRpcRequest<ClientRequest>,
+		ctx: IncomingRequestContext,
+		merge: Box<MergeFn>,
+	) -> Result<Response, UpstreamError> {
+		let paginated = matches!(
+			&r.request,
+			ClientRequest::ListToolsRequest(l) if l.params.as_ref().is_some_and(|p| p.cursor.is_some())
+		);
+		let Some(cache) = self.tools_cache.clone().filter(|_| !paginated) else {
+			return self.send_fanout(r, ctx, merge).await;
+		};
+		let id = r.id.clone();
+		let streams = self
+			.open_fanout(|name, con| {
+				let mut r = r.clone();
+				let ctx = &ctx;
+				let cache = cache.clone();
+				let id = id.clone();
+				async move {
+					if let Some(tools) = cache.get(&name) {
+						return Ok(mergestream::Messages::from_result(
+							id,
+							ListToolsResult {
+								tools,
+								next_cursor: None,
+								meta: None,
+							},
+						));
+					}
+					let ahead = std::sync::Mutex::new(Vec::new());
+					let fetch = || async {
+						let ctx = self.for_target(&name, &mut r, ctx);
+						let stream = con.generic_stream(r, &ctx).await?;
+						let (result, messages) = self.receive_tools(&name, stream).await?;
+						if result.next_cursor.is_none() {
+							cache.insert(&name, result.tools.clone());
+						}
+						*ahead.lock().expect("not poisoned") = messages;
+						Ok(result)
+					};
+					let shared_error =
+						|e| UpstreamError::InvalidRequest(format!("tools/list to {name} failed: {e}"));
+					let result = cache.single_flight(&name, fetch, shared_error).await?;
+					let mut messages = std::mem::take(&mut *ahead.lock().expect("not poisoned"));
+					messages.push(ServerJsonRpcMessage::response(result.into(), id));
+					Ok::<_, UpstreamError>(mergestream::Messages::from_messages(messages))
+				}
+			})
+			.await?;
 
 		let ms = mergestream::MergeStream::new(streams, id.clone(), merge);
 		messages_to_response(id, ms)
 	}
+	/// Wait for the ListTools response on an upstream's stream, with the messages the upstream
+	/// sent ahead of it
+	async fn receive_tools(
+		&self,
+		target: &Strng,
+		stream: mergestream::Messages,
+	) -> Result<(ListToolsResult, Vec<ServerJsonRpcMessage>), UpstreamError> {
+		let mut stream = self.watch_tool_list_changes(target, stream);
+		let mut ahead = Vec::new();
+		while let Some(msg) = stream.next().await {
+			match msg? {
+				ServerJsonRpcMessage::Response(resp) => {
+					let ServerResult::ListToolsResult(result) = resp.result else {
+						return Err(UpstreamError::InvalidRequest(format!(
+							"unexpected response to tools/list from {target}"
+						)));
+					};
+					return Ok((result, ahead));
+				},
+				ServerJsonRpcMessage::Error(err) => {
+					return Err(UpstreamError::InvalidRequest(format!(
+						"tools/list to {target} failed: {}",
+						err.error.message
+					)));
+				},
+				// Notifications and requests sent ahead of the response
+				msg => ahead.push(msg),
+			}
+		}
+		Err(UpstreamError::Recv)
+	}
+	/// ListTools on one upstream of this session, answered from the tools cache when it can be
+	///
+	/// Only the first page is listed. There is no downstream request to forward messages sent
+	/// ahead of the response on, so they are dropped.
+	async fn list_upstream_tools(
+		&self,
+		target: &Strng,
+		ctx: &IncomingRequestContext,
+	) -> Result<Vec<Tool>, UpstreamError> {
+		if let Some(tools) = self.tools_cache.as_ref().and_then(|cache| cache.get(target)) {
+			return Ok(tools);
+		}
+		let con = self
+			.upstreams
+			.get(target)
+			.map_err(|_| UpstreamError::InvalidRequest(format!("unknown service {}", target)))?;
+		let fetch = || async {
+			let mut r = JsonRpcRequest {
+				jsonrpc: Default::default(),
+				id: RequestId::Number(rand::random::<i32>().abs() as i64),
+				request: ClientRequest::ListToolsRequest(rmcp::model::ListToolsRequest {
+					method: Default::default(),
+					params: None,
+					extensions: Default::default(),
+				}),
+			};
+			let ctx = self.for_target(target, &mut r, ctx);
+			let stream = con.generic_stream(r, &ctx).await?;
+			let (result, _) = self.receive_tools(target, stream).await?;
+			Ok::<_, UpstreamError>(result)
+		};
+		let Some(cache) = &self.tools_cache else {
+			return fetch().await.map(|result| result.tools);
+		};
+		let fetch = || async {
+			let result = fetch().await?;
+			if result.next_cursor.is_none() {
+				cache.insert(target, result.tools.clone());
+			}
+			Ok::<_, UpstreamError>(result)
+		};
+		let shared_error =
+			|e| UpstreamError::InvalidRequest(format!("tools/list to {target} failed: {e}"));
+		let result = cache.single_flight(target, fetch, shared_error).await?;
+		Ok(result.tools)
+	}
+	/// Drop an upstream's cached tools when it announces that its tool list changed
+	fn watch_tool_list_changes(
+		&self,
+		target: &Strng,
+		stream: mergestream::Messages,
+	) -> mergestream::Messages {
+		let Some(cache) = self.tools_cache.clone() else {
+			return stream;
+		};
+		let target = target.clone();
+		stream.inspect(move |msg| {
+			if let ServerJsonRpcMessage::Notification(n) = msg
+				&& matches!(
+					n.notification,
+					ServerNotification::ToolListChangedNotification(_)
+				) {
+				cache.invalidate(&target);
+			}
+		})
+	}
 	pub async fn send_notification(
 		&self,
 		r: JsonRpcNotification<ClientNotification>,
 		ctx: IncomingRequestContext,
 	) -> Result<Response, UpstreamError> {
-		let mut streams = Vec::new();
 		for (name, con) in self.upstreams.iter_named() {
-			streams.push((
-				name,
-				con
-					.generic_notification(r.notification.clone(), &ctx)
-					.await?,
-			));
+			con
+				.generic_notification(r.notification.clone(), &self.target_context(&name, &ctx))
+				.await?;
 		}
 
 		Ok(accepted_response())
@@ -816,13 +2299,59 @@ pub(crate) fn messages_to_response(
 	Ok(crate::mcp::session::sse_stream_response(stream, None))
 }
 
-fn accepted_response() -> Response {
+pub(crate) fn accepted_response() -> Response {
 	::http::Response::builder()
 		.status(StatusCode::ACCEPTED)
 		.body(crate::http::Body::empty())
 		.expect("valid response")
 }
 
+/// `_meta` reporting the caller's remaining rate limit quota
+pub fn quota_meta(quota: RateLimitStatus) -> Meta {
+	let mut meta = Meta::default();
+	meta
+		.0
+		.insert(QUOTA_META_KEY.to_string(), serde_json::json!(quota));
+	meta
+}
+
+/// A result's `_meta` with its quota replaced by the caller's, keeping everything else
+pub fn with_caller_quota(meta: Option<Meta>, quota: Option<RateLimitStatus>) -> Option<Meta> {
+	let mut meta = meta.unwrap_or_default();
+	meta.0.remove(QUOTA_META_KEY);
+	if let Some(quota) = quota {
+		meta.0.extend(quota_meta(quota).0);
+	}
+	(!meta.0.is_empty()).then_some(meta)
+}
+
+/// `_meta` of a tool call result: the caller's remaining rate limit quota, and the target
+/// that served the call if it could have failed over
+fn result_meta(quota: Option<RateLimitStatus>, served_by: Option<&str>) -> Option<Meta> {
+	let mut meta = quota.map(quota_meta).unwrap_or_default();
+	if let Some(target) = served_by {
+		meta
+			.0
+			.insert(SERVED_BY_META_KEY.to_string(), serde_json::json!(target));
+	}
+	(!meta.0.is_empty()).then_some(meta)
+}
+
+/// Add `extra` to a tool call result's `_meta`
+fn with_result_meta(msg: ServerJsonRpcMessage, extra: Option<&Meta>) -> ServerJsonRpcMessage {
+	let Some(extra) = extra else {
+		return msg;
+	};
+	let ServerJsonRpcMessage::Response(mut resp) = msg else {
+		return msg;
+	};
+	if let ServerResult::CallToolResult(result) = &mut resp.result {
+		let meta = result.meta.get_or_insert_with(Default::default);
+		meta.0.extend(extra.0.clone());
+	}
+	ServerJsonRpcMessage::Response(resp)
+}
+
 /// Transform a server message if it contains a tool call result
 fn transform_server_message(
 	msg: ServerJsonRpcMessage,
@@ -977,3 +2506,377 @@ fn transform_call_tool_result(
 		meta: result.meta.clone(),
 	})
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::http::authorization::RuleSets;
+	use crate::mcp::registry::{Registry, RegistryStore, ToolDefinition};
+	use crate::mcp::router::McpTarget;
+	use crate::store::BackendPolicies;
+	use crate::test_helpers::proxymock::setup_proxy_test;
+	use crate::types::agent::{
+		McpTargetSpec, ResourceName, SimpleBackend, SimpleBackendReference, StreamableHTTPTargetSpec,
+		Target,
+	};
+
+	/// A relay over unreachable streamable HTTP targets with the given request timeouts
+	fn relay_over(targets: &[(&str, Option<Duration>)]) -> Relay {
+		let addr: std::net::SocketAddr = "127.0.0.1:1".parse().unwrap();
+		let targets = targets
+			.iter()
+			.map(|(name, timeout)| {
+				Arc::new(McpTarget {
+					name: strng::new(name),
+					spec: McpTargetSpec::Mcp(StreamableHTTPTargetSpec {
+						backend: SimpleBackendReference::InlineBackend(Target::Address(addr)),
+						path: "/mcp".to_string(),
+					}),
+					backend_policies: BackendPolicies {
+						http: timeout.map(|timeout| crate::types::backend::HTTP {
+							request_timeout: Some(timeout),
+							..Default::default()
+						}),
+						..Default::default()
+					},
+					backend: Some(SimpleBackend::Opaque(
+						ResourceName::new(strng::new(name), "".into()),
+						Target::Address(addr),
+					)),
+					always_use_prefix: false,
+				})
+			})
+			.collect();
+		Relay::new(
+			McpBackendGroup {
+				targets,
+				stateful: false,
+			},
+			McpAuthorizationSet::new(RuleSets::from(Vec::new())),
+			PolicyClient {
+				inputs: setup_proxy_test("{}").unwrap().inputs(),
+			},
+		)
+		.unwrap()
+	}
+
+	#[tokio::test(start_paused = true)]
+	async fn test_fanout_opens_upstreams_concurrently() {
+		let relay = relay_over(&[("a", None), ("b", None), ("c", None)]);
+		let started = tokio::time::Instant::now();
+		let opened = relay
+			.open_fanout(|_, _| async {
+				tokio::time::sleep(Duration::from_secs(10)).await;
+				Ok(mergestream::Messages::empty())
+			})
+			.await
+			.unwrap();
+
+		// Three upstreams taking 10s each are done in 10s, not 30s
+		assert_eq!(opened.len(), 3);
+		assert_eq!(started.elapsed(), Duration::from_secs(10));
+	}
+
+	#[tokio::test(start_paused = true)]
+	async fn test_fanout_upstream_bounded_by_request_timeout() {
+		let relay = relay_over(&[("fast", None), ("slow", Some(Duration::from_secs(2)))]);
+		let started = tokio::time::Instant::now();
+		let err = relay
+			.open_fanout(|name, _| async move {
+				if name == "slow" {
+					std::future::pending::<()>().await;
+				}
+				Ok(mergestream::Messages::empty())
+			})
+			.await
+			.unwrap_err();
+		assert!(
+			matches!(err, UpstreamError::Timeout(ref name) if name == "slow"),
+			"{err}"
+		);
+		assert_eq!(started.elapsed(), Duration::from_secs(2));
+
+		// Without a request timeout, an upstream is bounded by the default
+		let relay = relay_over(&[("slow", None)]);
+		let started = tokio::time::Instant::now();
+		let err = relay
+			.open_fanout(|_, _| async {
+				std::future::pending::<()>().await;
+				Ok(mergestream::Messages::empty())
+			})
+			.await
+			.unwrap_err();
+		assert!(matches!(err, UpstreamError::Timeout(_)), "{err}");
+		assert_eq!(started.elapsed(), DEFAULT_FANOUT_UPSTREAM_TIMEOUT);
+	}
+
+	/// The backend a call to `tool_name` resolves to against `registry`
+	fn resolved_target(
+		relay: &Relay,
+		registry: Option<&Arc<CompiledRegistry>>,
+		tool_name: &str,
+	) -> String {
+		match relay
+			.resolve_tool_call_with(registry, tool_name, serde_json::json!({}), None)
+			.unwrap()
+		{
+			ResolvedToolCall::Backend { target, .. } => target,
+			_ => panic!("'{tool_name}' should resolve to a backend"),
+		}
+	}
+
+	#[test]
+	fn test_invoker_keeps_registry_snapshot() {
+		let store = RegistryStoreRef::new(RegistryStore::new());
+		store
+			.update(Registry::with_tool_definitions(vec![
+				ToolDefinition::source("lookup", "one", "lookup"),
+			]))
+			.unwrap();
+		let relay = Arc::new(relay_over(&[]).with_registry(store.clone()));
+		let invoker = RelayToolInvoker::new(relay.clone(), IncomingRequestContext::empty());
+
+		// A reload mid-composition doesn't change what the composition's calls resolve to
+		store
+			.update(Registry::with_tool_definitions(vec![
+				ToolDefinition::source("lookup", "two", "lookup"),
+			]))
+			.unwrap();
+		assert_eq!(
+			resolved_target(&relay, invoker.registry.as_ref(), "lookup"),
+			"one"
+		);
+		assert_eq!(
+			relay.tool_targets(invoker.registry.as_deref(), "lookup"),
+			["one"]
+		);
+
+		// New invocations see the reloaded registry
+		assert_eq!(
+			resolved_target(&relay, relay.registry_snapshot().as_ref(), "lookup"),
+			"two"
+		);
+	}
+
+	#[test]
+	fn test_strict_args_refused_before_backend_is_listed() {
+		use crate::mcp::registry::ToolImplementation;
+
+		let mut tool = ToolDefinition::source("forecast", "weather", "get_forecast");
+		if let ToolImplementation::Source(source) = &mut tool.implementation {
+			*source = source
+				.clone()
+				.with_hidden_fields(vec!["api_key".to_string()])
+				.with_strict_args();
+		}
+		let store = RegistryStoreRef::new(RegistryStore::new());
+		store
+			.update(Registry::with_tool_definitions(vec![tool]))
+			.unwrap();
+		let relay = relay_over(&[]).with_registry(store.clone());
+		let snapshot = relay.registry_snapshot();
+		let resolve = |args: serde_json::Value| {
+			relay.resolve_tool_call_with(snapshot.as_ref(), "forecast", args, None)
+		};
+
+		// Nothing has listed the backend's tools, so even a guessed parameter that isn't hidden
+		// can't be checked and the call is refused
+		let err = resolve(serde_json::json!({"city": "Oslo", "admin": true})).unwrap_err();
+		assert!(matches!(err, UpstreamError::InvalidRequest(_)), "{err}");
+		assert!(err.to_string().contains("couldn't be listed"), "{err}");
+
+		// Once a list has shown the backend's schema, listed arguments go through
+		let schema = serde_json::json!({
+			"type": "object",
+			"properties": {"city": {"type": "string"}, "api_key": {"type": "string"}}
+		});
+		store.schema_drift().observe(
+			snapshot.as_ref().unwrap(),
+			&[(
+				"weather".to_string(),
+				rmcp::model::Tool::new(
+					"get_forecast",
+					"Get the forecast",
+					Arc::new(schema.as_object().unwrap().clone()),
+				),
+			)],
+		);
+		let err = resolve(serde_json::json!({"city": "Oslo", "admin": true})).unwrap_err();
+		assert!(
+			matches!(
+				err,
+				UpstreamError::InvalidArguments { ref problems, .. } if problems[0].pointer == "/admin"
+			),
+			"{err}"
+		);
+		assert!(resolve(serde_json::json!({"city": "Oslo"})).is_ok());
+	}
+
+	#[tokio::test]
+	async fn test_strict_args_backend_listed_before_refusing() {
+		use crate::mcp::registry::ToolImplementation;
+		use crate::mcp::tools_cache::ToolListCache;
+
+		let mut tool = ToolDefinition::source("forecast", "weather", "get_forecast");
+		if let ToolImplementation::Source(source) = &mut tool.implementation {
+			*source = source.clone().with_strict_args();
+		}
+		let store = RegistryStoreRef::new(RegistryStore::new());
+		store
+			.update(Registry::with_tool_definitions(vec![tool]))
+			.unwrap();
+		let cache = ToolListCacheScope {
+			cache: Arc::new(ToolListCache::new()),
+			backend: ResourceName::new(strng::new("mcp"), "".into()),
+			ttl: Duration::from_secs(60),
+		};
+		let relay = relay_over(&[])
+			.with_registry(store.clone())
+			.with_tools_cache(cache.clone());
+		let snapshot = relay.registry_snapshot();
+		let ctx = IncomingRequestContext::empty();
+		let resolve = |args: serde_json::Value| {
+			relay.resolve_tool_call_with(snapshot.as_ref(), "forecast", args, None)
+		};
+
+		// A backend that can't be listed leaves the call refused
+		relay
+			.learn_backend_schema(snapshot.as_ref(), "forecast", &ctx)
+			.await;
+		let err = resolve(serde_json::json!({"city": "Oslo"})).unwrap_err();
+		assert!(err.to_string().contains("couldn't be listed"), "{err}");
+
+		// Otherwise the gateway lists it itself, here from the tools cache, before checking
+		let schema = serde_json::json!({
+			"type": "object",
+			"properties": {"city": {"type": "string"}}
+		});
+		cache.insert(
+			&strng::new("weather"),
+			vec![rmcp::model::Tool::new(
+				"get_forecast",
+				"Get the forecast",
+				Arc::new(schema.as_object().unwrap().clone()),
+			)],
+		);
+		relay
+			.learn_backend_schema(snapshot.as_ref(), "forecast", &ctx)
+			.await;
+		assert!(store.schema_drift().backend_schema("weather", "get_forecast").is_some());
+		assert!(resolve(serde_json::json!({"city": "Oslo"})).is_ok());
+		let err = resolve(serde_json::json!({"city": "Oslo", "admin": true})).unwrap_err();
+		assert!(matches!(err, UpstreamError::InvalidArguments { .. }), "{err}");
+	}
+
+	#[derive(Debug)]
+	struct DenyAll;
+
+	#[async_trait::async_trait]
+	impl crate::mcp::registry::Authorizer for DenyAll {
+		async fn authorize(
+			&self,
+			_: &crate::mcp::registry::AuthorizationRequest,
+		) -> Result<crate::mcp::registry::AuthorizationDecision, crate::mcp::registry::AuthorizerError>
+		{
+			Ok(crate::mcp::registry::AuthorizationDecision::deny(Some(
+				"not for agents".to_string(),
+			)))
+		}
+	}
+
+	#[tokio::test]
+	async fn test_invoker_authorizes_composition_steps() {
+		let authorizer = crate::mcp::registry::ToolCallAuthorizer::new(Arc::new(DenyAll));
+		let store = RegistryStoreRef::new(RegistryStore::new().with_authorizer(Arc::new(authorizer)));
+		store
+			.update(Registry::with_tool_definitions(vec![
+				ToolDefinition::source("lookup", "one", "lookup"),
+			]))
+			.unwrap();
+		let relay = Arc::new(relay_over(&[]).with_registry(store));
+		let invoker = RelayToolInvoker::new(relay, IncomingRequestContext::empty());
+
+		// Denied before the call reaches the (missing) backend
+		let err = invoker
+			.invoke("lookup", serde_json::json!({}))
+			.await
+			.unwrap_err();
+		assert!(err.to_string().contains("not for agents"), "{err}");
+	}
+
+	#[tokio::test(start_paused = true)]
+	async fn test_transport_retry_retries_transport_failures() {
+		use std::sync::atomic::{AtomicU32, Ordering};
+
+		let retry = TransportRetry {
+			max_attempts: 3,
+			backoff_ms: 100,
+		};
+		let calls = AtomicU32::new(0);
+		let calls = &calls;
+		let start = tokio::time::Instant::now();
+		let res: Result<(), _> = with_transport_retry(Some(&retry), "backend", move || async move {
+			calls.fetch_add(1, Ordering::SeqCst);
+			Err(UpstreamError::Send)
+		})
+		.await;
+		assert!(matches!(res, Err(UpstreamError::Send)));
+		assert_eq!(calls.load(Ordering::SeqCst), retry.attempts());
+		assert_eq!(start.elapsed(), retry.backoff() * 2);
+
+		// A later attempt that succeeds is returned
+		calls.store(0, Ordering::SeqCst);
+		let res = with_transport_retry(Some(&retry), "backend", move || async move {
+			match calls.fetch_add(1, Ordering::SeqCst) {
+				0 => Err(UpstreamError::Recv),
+				n => Ok(n),
+			}
+		})
+		.await;
+		assert_eq!(res.unwrap(), 1);
+	}
+
+	#[tokio::test(start_paused = true)]
+	async fn test_transport_retry_skips_other_failures() {
+		use std::sync::atomic::{AtomicU32, Ordering};
+
+		let retry = TransportRetry {
+			max_attempts: 3,
+			backoff_ms: 100,
+		};
+		let calls = AtomicU32::new(0);
+		let calls = &calls;
+		let failures: [fn() -> UpstreamError; 2] = [
+			|| UpstreamError::InvalidRequest("bad arguments".to_string()),
+			|| {
+				let resp = ::http::Response::builder()
+					.status(::http::StatusCode::SERVICE_UNAVAILABLE)
+					.body(crate::http::Body::empty())
+					.unwrap();
+				UpstreamError::Http(crate::mcp::ClientError::Status(Box::new(resp)))
+			},
+		];
+		for failure in failures {
+			calls.store(0, Ordering::SeqCst);
+			let res: Result<(), _> = with_transport_retry(Some(&retry), "backend", move || async move {
+				calls.fetch_add(1, Ordering::SeqCst);
+				Err(failure())
+			})
+			.await;
+			assert!(res.is_err());
+			assert_eq!(calls.load(Ordering::SeqCst), 1);
+		}
+
+		// Without a policy, even a transport failure runs once
+		calls.store(0, Ordering::SeqCst);
+		let start = tokio::time::Instant::now();
+		let res: Result<(), _> = with_transport_retry(None, "backend", move || async move {
+			calls.fetch_add(1, Ordering::SeqCst);
+			Err(UpstreamError::Send)
+		})
+		.await;
+		assert!(matches!(res, Err(UpstreamError::Send)));
+		assert_eq!(calls.load(Ordering::SeqCst), 1);
+		assert_eq!(start.elapsed(), Duration::ZERO);
+	}
+}
@@ -24,6 +24,25 @@ impl Messages {
 	pub fn from_result<T: Into<ServerResult>>(id: RequestId, result: T) -> Self {
 		Self::from(ServerJsonRpcMessage::response(result.into(), id))
 	}
+
+	/// from_messages returns a stream of `messages`, in order.
+	pub fn from_messages(messages: Vec<ServerJsonRpcMessage>) -> Self {
+		Messages(futures_util::stream::iter(messages.into_iter().map(Ok)).boxed())
+	}
+
+	/// inspect calls `f` with each message as it passes through the stream.
+	pub fn inspect(self, mut f: impl FnMut(&ServerJsonRpcMessage) + Send + 'static) -> Self {
+		Messages(
+			self
+				.0
+				.inspect(move |msg| {
+					if let Ok(msg) = msg {
+						f(msg)
+					}
+				})
+				.boxed(),
+		)
+	}
 }
 
 impl Stream for Messages {
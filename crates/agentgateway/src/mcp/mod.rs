@@ -1,18 +1,23 @@
+mod circuit_breaker;
+mod composition_tracing;
 mod handler;
 mod mergestream;
 mod rbac;
 pub mod registry;
 mod router;
 pub mod saga;
+mod sampling;
 mod session;
 mod sse;
 mod streamablehttp;
+mod tools_cache;
 mod upstream;
 
 use std::fmt::{Display, Error, Write};
 use std::sync::Arc;
 
 use axum_core::BoxError;
+pub use circuit_breaker::{UpstreamHealth, UpstreamStatus};
 use prometheus_client::encoding::{EncodeLabelValue, LabelValueEncoder};
 pub use rbac::{McpAuthorization, McpAuthorizationSet, ResourceId, ResourceType};
 pub use router::App;
@@ -22,6 +27,10 @@ use thiserror::Error;
 #[path = "mcp_tests.rs"]
 mod tests;
 
+#[cfg(test)]
+#[path = "registry_tests.rs"]
+mod registry_tests;
+
 #[derive(Error, Debug)]
 pub enum ClientError {
 	#[error("http request failed with code: {}", .0.status())]
@@ -69,4 +78,8 @@ pub struct MCPInfo {
 	pub target_name: Option<String>,
 	pub resource: Option<MCPOperation>,
 	pub session_id: Option<String>,
+	/// Execution labels the caller attached via `_meta`, as `name=value` pairs
+	pub labels: Option<String>,
+	/// Id of the composition execution the call started
+	pub execution_id: Option<String>,
 }
@@ -0,0 +1,358 @@
+// Blue/green registry activation
+//
+// By default every registry that loads successfully goes live at once. With manual
+// promotion, a reloaded registry is staged as a candidate alongside the active one instead:
+// operators can review what it would change and run smoke tests against it, and it only
+// replaces the active registry when promoted through the admin server. Smoke tests call
+// compositions of the candidate with canned backend responses, so a registry that isn't live
+// yet never reaches real backends.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use parking_lot::Mutex;
+use serde::Serialize;
+use serde_json::Value;
+use tracing::info;
+
+use super::changelog::RegistryDiff;
+use super::compiled::CompiledRegistry;
+use super::executor::{CompositionExecutor, ExecutionError, ToolInvoker};
+use super::snapshot::RegistrySnapshot;
+use super::stats::ReloadTrigger;
+
+/// A composition call made against a candidate registry before it can be promoted
+#[derive(Debug, Clone, PartialEq)]
+pub struct SmokeTest {
+	/// Composition to call
+	pub tool: String,
+	pub arguments: Value,
+	/// Canned results of the tools the composition calls, by tool name; calls of any other
+	/// tool fail
+	pub responses: HashMap<String, Value>,
+	/// Value the result must match; objects match results holding at least their fields
+	pub expect: Option<Value>,
+}
+
+/// Outcome of one smoke test
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SmokeTestResult {
+	pub tool: String,
+	pub passed: bool,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub error: Option<String>,
+}
+
+impl SmokeTest {
+	/// Call the composition in `registry`, answering its tool calls from `responses`
+	pub async fn run(&self, registry: Arc<CompiledRegistry>) -> SmokeTestResult {
+		let invoker = Arc::new(CannedInvoker {
+			responses: self.responses.clone(),
+		});
+		let executor = CompositionExecutor::new(registry, invoker);
+		let error = match executor.execute(&self.tool, self.arguments.clone()).await {
+			Ok(result) => self
+				.expect
+				.as_ref()
+				.filter(|expected| !matches_expected(&result, expected))
+				.map(|expected| format!("result {result} does not match expected {expected}")),
+			Err(e) => Some(e.to_string()),
+		};
+		SmokeTestResult {
+			tool: self.tool.clone(),
+			passed: error.is_none(),
+			error,
+		}
+	}
+}
+
+/// Whether `actual` matches `expected`, ignoring object fields `expected` doesn't list
+fn matches_expected(actual: &Value, expected: &Value) -> bool {
+	match (actual, expected) {
+		(Value::Object(actual), Value::Object(expected)) => expected
+			.iter()
+			.all(|(key, e)| actual.get(key).is_some_and(|a| matches_expected(a, e))),
+		_ => actual == expected,
+	}
+}
+
+struct CannedInvoker {
+	responses: HashMap<String, Value>,
+}
+
+#[async_trait::async_trait]
+impl ToolInvoker for CannedInvoker {
+	async fn invoke(&self, tool_name: &str, _args: Value) -> Result<Value, ExecutionError> {
+		self
+			.responses
+			.get(tool_name)
+			.cloned()
+			.ok_or_else(|| ExecutionError::ToolNotFound(format!("{tool_name} (no smoke test response)")))
+	}
+}
+
+/// A registry staged for promotion
+#[derive(Debug)]
+pub struct Candidate {
+	/// Distinguishes successive candidates, so a promotion can't pick up one staged after
+	/// it was reviewed
+	pub id: u64,
+	pub registry: Arc<CompiledRegistry>,
+	/// The reload that staged it
+	pub trigger: ReloadTrigger,
+	/// Where it was loaded from, if it came from a configured source
+	pub source: Option<String>,
+	/// When it was staged (milliseconds since the Unix epoch)
+	pub staged_at_ms: u64,
+	pub validation_errors: usize,
+	pub validation_warnings: usize,
+	/// Persisted as the last known good registry once promoted
+	pub(crate) snapshot: Option<RegistrySnapshot>,
+	/// Outcome of the latest smoke test run against it
+	smoke_tests: Mutex<Option<Vec<SmokeTestResult>>>,
+}
+
+impl Candidate {
+	/// Outcome of the latest smoke test run, if any has run
+	pub fn smoke_test_results(&self) -> Option<Vec<SmokeTestResult>> {
+		self.smoke_tests.lock().clone()
+	}
+
+	/// Summarize the candidate and what promoting it over `active` would change
+	pub fn summary(&self, active: Option<&CompiledRegistry>) -> CandidateSummary {
+		CandidateSummary {
+			id: self.id,
+			trigger: self.trigger,
+			source: self.source.clone(),
+			staged_at_ms: self.staged_at_ms,
+			tools: self.registry.len(),
+			compositions: self.registry.composition_count(),
+			validation_errors: self.validation_errors,
+			validation_warnings: self.validation_warnings,
+			changes: RegistryDiff::between(active, &self.registry),
+			smoke_tests: self.smoke_test_results(),
+		}
+	}
+}
+
+/// A candidate registry, as reported by the admin server
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CandidateSummary {
+	pub id: u64,
+	pub trigger: ReloadTrigger,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub source: Option<String>,
+	pub staged_at_ms: u64,
+	pub tools: usize,
+	pub compositions: usize,
+	pub validation_errors: usize,
+	pub validation_warnings: usize,
+	/// What promoting it would change in the active registry
+	pub changes: RegistryDiff,
+	/// Outcome of the latest smoke test run, if any has run
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub smoke_tests: Option<Vec<SmokeTestResult>>,
+}
+
+/// A candidate registry and what's needed to build one
+#[derive(Debug, Clone)]
+pub struct StagedRegistry {
+	pub registry: Arc<CompiledRegistry>,
+	pub trigger: ReloadTrigger,
+	pub source: Option<String>,
+	pub validation_errors: usize,
+	pub validation_warnings: usize,
+	pub snapshot: Option<RegistrySnapshot>,
+}
+
+/// Why a candidate couldn't be promoted
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+pub enum PromotionError {
+	#[error("no candidate registry is staged")]
+	NoCandidate,
+	#[error("candidate {requested} was replaced by candidate {staged}")]
+	Superseded { requested: u64, staged: u64 },
+	#[error("{} of {} smoke tests failed", .0.iter().filter(|r| !r.passed).count(), .0.len())]
+	SmokeTestsFailed(Vec<SmokeTestResult>),
+}
+
+/// Candidate registry of a store with manual promotion, and the smoke tests gating it
+#[derive(Debug, Default)]
+pub struct ManualPromotion {
+	smoke_tests: Vec<SmokeTest>,
+	candidate: Mutex<Option<Arc<Candidate>>>,
+	next_id: AtomicU64,
+}
+
+impl ManualPromotion {
+	pub fn new(smoke_tests: Vec<SmokeTest>) -> Self {
+		Self {
+			smoke_tests,
+			..Default::default()
+		}
+	}
+
+	pub fn smoke_tests(&self) -> &[SmokeTest] {
+		&self.smoke_tests
+	}
+
+	/// Stage a registry, replacing any candidate staged before it; returns its id
+	pub fn stage(&self, staged: StagedRegistry) -> u64 {
+		let id = self.next_id.fetch_add(1, Ordering::Relaxed) + 1;
+		let candidate = Arc::new(Candidate {
+			id,
+			registry: staged.registry,
+			trigger: staged.trigger,
+			source: staged.source,
+			staged_at_ms: SystemTime::now()
+				.duration_since(UNIX_EPOCH)
+				.map(|d| d.as_millis() as u64)
+				.unwrap_or_default(),
+			validation_errors: staged.validation_errors,
+			validation_warnings: staged.validation_warnings,
+			snapshot: staged.snapshot,
+			smoke_tests: Mutex::new(None),
+		});
+		if let Some(replaced) = self.candidate.lock().replace(candidate) {
+			info!(target: "virtual_tools", replaced = replaced.id, candidate = id, "Replaced candidate registry");
+		}
+		id
+	}
+
+	/// The staged candidate, if any
+	pub fn candidate(&self) -> Option<Arc<Candidate>> {
+		self.candidate.lock().clone()
+	}
+
+	/// Drop the staged candidate, returning it
+	pub fn discard(&self) -> Option<Arc<Candidate>> {
+		self.candidate.lock().take()
+	}
+
+	/// Run every smoke test against a candidate, recording the outcome on it
+	pub async fn test(&self, candidate: &Candidate) -> Vec<SmokeTestResult> {
+		let mut results = Vec::with_capacity(self.smoke_tests.len());
+		for test in &self.smoke_tests {
+			results.push(test.run(candidate.registry.clone()).await);
+		}
+		*candidate.smoke_tests.lock() = Some(results.clone());
+		results
+	}
+
+	/// Smoke test the candidate `id` (the staged one if `None`) and, if every test passes,
+	/// take it for activation, unless another candidate was staged in the meantime
+	pub async fn promote(&self, id: Option<u64>) -> Result<Arc<Candidate>, PromotionError> {
+		let candidate = self.candidate().ok_or(PromotionError::NoCandidate)?;
+		if let Some(requested) = id
+			&& requested != candidate.id
+		{
+			return Err(PromotionError::Superseded {
+				requested,
+				staged: candidate.id,
+			});
+		}
+		let results = self.test(&candidate).await;
+		if results.iter().any(|r| !r.passed) {
+			return Err(PromotionError::SmokeTestsFailed(results));
+		}
+		let mut staged = self.candidate.lock();
+		match staged.as_ref() {
+			Some(current) if current.id == candidate.id => Ok(staged.take().expect("checked above")),
+			Some(current) => Err(PromotionError::Superseded {
+				requested: candidate.id,
+				staged: current.id,
+			}),
+			None => Err(PromotionError::NoCandidate),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use serde_json::json;
+
+	use super::*;
+	use crate::mcp::registry::patterns::{
+		PatternSpec, PipelineSpec, PipelineStep, StepOperation, ToolCall,
+	};
+	use crate::mcp::registry::types::{Registry, ToolDefinition};
+
+	fn staged(backend_tool: &str) -> StagedRegistry {
+		let composition = ToolDefinition::composition(
+			"lookup",
+			PatternSpec::Pipeline(PipelineSpec {
+				steps: vec![PipelineStep {
+					id: "search".to_string(),
+					operation: StepOperation::Tool(ToolCall {
+						name: backend_tool.to_string(),
+					}),
+					input: None,
+					session_set: None,
+					retry: None,
+					cache: None,
+				}],
+			}),
+		);
+		let registry =
+			CompiledRegistry::compile(Registry::with_tool_definitions(vec![composition])).unwrap();
+		StagedRegistry {
+			registry: Arc::new(registry),
+			trigger: ReloadTrigger::Poll,
+			source: None,
+			validation_errors: 0,
+			validation_warnings: 0,
+			snapshot: None,
+		}
+	}
+
+	fn smoke_test() -> SmokeTest {
+		SmokeTest {
+			tool: "lookup".to_string(),
+			arguments: json!({"q": "mcp"}),
+			responses: HashMap::from([("search".to_string(), json!({"hits": 3, "took": 12}))]),
+			expect: Some(json!({"hits": 3})),
+		}
+	}
+
+	#[tokio::test]
+	async fn test_promotion_requires_passing_smoke_tests() {
+		let promotion = ManualPromotion::new(vec![smoke_test()]);
+		assert_eq!(
+			promotion.promote(None).await.unwrap_err(),
+			PromotionError::NoCandidate
+		);
+
+		// The candidate calls a tool the smoke test has no response for
+		let broken = promotion.stage(staged("serach"));
+		let err = promotion.promote(Some(broken)).await.unwrap_err();
+		assert_eq!(err.to_string(), "1 of 1 smoke tests failed");
+		let results = promotion.candidate().unwrap().smoke_test_results().unwrap();
+		assert!(!results[0].passed);
+
+		let fixed = promotion.stage(staged("search"));
+		assert_eq!(
+			promotion.promote(Some(broken)).await.unwrap_err(),
+			PromotionError::Superseded {
+				requested: broken,
+				staged: fixed
+			}
+		);
+		let promoted = promotion.promote(Some(fixed)).await.unwrap();
+		assert_eq!(promoted.id, fixed);
+		assert!(promoted.smoke_test_results().unwrap()[0].passed);
+		assert!(promotion.candidate().is_none());
+	}
+
+	#[test]
+	fn test_matches_expected_ignores_unlisted_fields() {
+		let result = json!({"hits": 3, "meta": {"took": 12, "shard": 1}});
+		assert!(matches_expected(&result, &json!({"meta": {"took": 12}})));
+		assert!(!matches_expected(&result, &json!({"meta": {"took": 13}})));
+		assert!(!matches_expected(&result, &json!({"missing": null})));
+		assert!(matches_expected(&json!([1, 2]), &json!([1, 2])));
+	}
+}
@@ -0,0 +1,649 @@
+// Payload archival for composition executions
+//
+// Stores the full (unredacted) input and output of selected compositions in a filesystem
+// directory or an S3-compatible bucket, for compliance and for building offline evaluation
+// datasets. Archiving happens off the request path: a write that fails is logged and counted,
+// never surfaced to the caller, and records arriving while too many writes are pending are
+// dropped rather than queued without bound.
+
+use std::collections::HashMap;
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde::Serialize;
+use serde_json::Value;
+use tokio::sync::Semaphore;
+use tracing::{debug, warn};
+
+use super::executor::{ExecutionId, ExecutionLabels};
+
+/// How often expired archives are deleted
+const RETENTION_INTERVAL: Duration = Duration::from_secs(3600);
+
+/// Background writes in flight at once; records beyond this are dropped
+const MAX_PENDING_WRITES: usize = 64;
+
+/// Error type for archive writes
+#[derive(Debug, thiserror::Error)]
+pub enum ArchiveError {
+	#[error("archive io error: {0}")]
+	Io(#[from] std::io::Error),
+	#[error("archive upload failed: {0}")]
+	Upload(String),
+	#[error("invalid archive key: {0}")]
+	InvalidKey(String),
+}
+
+/// One archived composition execution
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ArchiveRecord {
+	pub composition: String,
+	/// Unique id of the execution
+	pub execution: ExecutionId,
+	/// Trace id of the composition span, when traced
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub trace_id: Option<String>,
+	/// Labels the caller attached to the execution
+	#[serde(skip_serializing_if = "ExecutionLabels::is_empty")]
+	pub labels: ExecutionLabels,
+	/// Start time (milliseconds since the Unix epoch)
+	pub started_at_ms: u64,
+	pub duration_ms: u64,
+	pub input: Value,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub output: Option<Value>,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub error: Option<String>,
+}
+
+impl ArchiveRecord {
+	/// Object key: `<composition>/<startedAtMs>-<execution id>.json`, with the composition
+	/// name encoded as a single path segment
+	pub fn key(&self) -> String {
+		format!(
+			"{}/{}-{}.json",
+			key_segment(&self.composition),
+			self.started_at_ms,
+			self.execution
+		)
+	}
+}
+
+/// Percent-encode every byte other than ASCII letters, digits, `-` and `_`, so a name can't
+/// be `.`, `..` or contain a path separator
+fn key_segment(name: &str) -> String {
+	let mut segment = String::with_capacity(name.len());
+	for byte in name.bytes() {
+		if byte.is_ascii_alphanumeric() || byte == b'-' || byte == b'_' {
+			segment.push(byte as char);
+		} else {
+			segment.push_str(&format!("%{byte:02X}"));
+		}
+	}
+	segment
+}
+
+/// Destination of archived records
+#[async_trait::async_trait]
+pub trait ArchiveSink: Send + Sync + std::fmt::Debug {
+	/// Store one serialized record under `key`
+	async fn put(&self, key: &str, body: Vec<u8>) -> Result<(), ArchiveError>;
+
+	/// Delete records written before `cutoff`, returning how many were deleted
+	async fn expire(&self, cutoff: SystemTime) -> Result<usize, ArchiveError>;
+
+	/// Check that records can be stored; sinks that can't check without writing a record
+	/// report success
+	async fn check(&self) -> Result<(), ArchiveError> {
+		Ok(())
+	}
+}
+
+/// Archives records as JSON files below a directory
+#[derive(Debug, Clone)]
+pub struct FilesystemSink {
+	root: PathBuf,
+}
+
+impl FilesystemSink {
+	pub fn new(root: impl Into<PathBuf>) -> Self {
+		Self { root: root.into() }
+	}
+}
+
+#[async_trait::async_trait]
+impl ArchiveSink for FilesystemSink {
+	async fn put(&self, key: &str, body: Vec<u8>) -> Result<(), ArchiveError> {
+		// Only plain relative components, so the key can't resolve outside the root
+		let relative = Path::new(key);
+		if key.is_empty()
+			|| relative
+				.components()
+				.any(|c| !matches!(c, Component::Normal(_)))
+		{
+			return Err(ArchiveError::InvalidKey(key.to_string()));
+		}
+		let path = self.root.join(relative);
+		if let Some(parent) = path.parent() {
+			tokio::fs::create_dir_all(parent).await?;
+		}
+		tokio::fs::write(path, body).await?;
+		Ok(())
+	}
+
+	async fn check(&self) -> Result<(), ArchiveError> {
+		tokio::fs::create_dir_all(&self.root).await?;
+		let probe = self.root.join(".ready");
+		tokio::fs::write(&probe, b"").await?;
+		tokio::fs::remove_file(&probe).await?;
+		Ok(())
+	}
+
+	async fn expire(&self, cutoff: SystemTime) -> Result<usize, ArchiveError> {
+		let root = self.root.clone();
+		tokio::task::spawn_blocking(move || expire_dir(&root, cutoff))
+			.await
+			.map_err(|e| ArchiveError::Io(std::io::Error::other(e)))?
+			.map_err(Into::into)
+	}
+}
+
+fn expire_dir(dir: &Path, cutoff: SystemTime) -> std::io::Result<usize> {
+	let entries = match std::fs::read_dir(dir) {
+		Ok(entries) => entries,
+		Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(0),
+		Err(e) => return Err(e),
+	};
+	let mut expired = 0;
+	for entry in entries {
+		let entry = entry?;
+		let metadata = entry.metadata()?;
+		if metadata.is_dir() {
+			expired += expire_dir(&entry.path(), cutoff)?;
+		} else if metadata.modified()? < cutoff {
+			std::fs::remove_file(entry.path())?;
+			expired += 1;
+		}
+	}
+	Ok(expired)
+}
+
+/// Archives records as objects in an S3-compatible bucket (path-style addressing)
+///
+/// S3 has no per-object expiry, so retention is delegated to the bucket: objects are tagged
+/// `retention-days=<n>` for a lifecycle rule to act on. Uploads use the same HTTP client as
+/// registry fetching, so they require the 'testing' feature too.
+#[cfg(feature = "testing")]
+#[derive(Debug, Clone)]
+pub struct S3Sink {
+	client: reqwest::Client,
+	endpoint: String,
+	bucket: String,
+	region: String,
+	prefix: String,
+	credentials: aws_credential_types::Credentials,
+	retention_days: Option<u64>,
+}
+
+#[cfg(feature = "testing")]
+impl S3Sink {
+	pub fn new(
+		endpoint: &str,
+		bucket: &str,
+		region: &str,
+		credentials: aws_credential_types::Credentials,
+	) -> Self {
+		Self {
+			client: reqwest::Client::new(),
+			endpoint: endpoint.trim_end_matches('/').to_string(),
+			bucket: bucket.to_string(),
+			region: region.to_string(),
+			prefix: String::new(),
+			credentials,
+			retention_days: None,
+		}
+	}
+
+	/// Builder: prepend `prefix` to every object key
+	pub fn with_prefix(mut self, prefix: &str) -> Self {
+		self.prefix = prefix.to_string();
+		self
+	}
+
+	/// Builder: tag objects with the retention lifecycle rules should enforce
+	pub fn with_retention(mut self, retention: Duration) -> Self {
+		self.retention_days = Some(retention.as_secs().div_ceil(86_400).max(1));
+		self
+	}
+
+	fn sign(
+		&self,
+		url: &str,
+		headers: &[(&str, String)],
+		body: &[u8],
+	) -> anyhow::Result<Vec<(String, String)>> {
+		use aws_sigv4::http_request::{
+			PayloadChecksumKind, PercentEncodingMode, SignableBody, SignableRequest, SigningSettings,
+			UriPathNormalizationMode, sign,
+		};
+		use aws_sigv4::sign::v4::SigningParams;
+
+		let identity = self.credentials.clone().into();
+		let mut settings = SigningSettings::default();
+		settings.payload_checksum_kind = PayloadChecksumKind::XAmzSha256;
+		settings.percent_encoding_mode = PercentEncodingMode::Single;
+		settings.uri_path_normalization_mode = UriPathNormalizationMode::Disabled;
+		let params = SigningParams::builder()
+			.identity(&identity)
+			.region(&self.region)
+			.name("s3")
+			.time(SystemTime::now())
+			.settings(settings)
+			.build()?
+			.into();
+		let request = SignableRequest::new(
+			"PUT",
+			url,
+			headers.iter().map(|(k, v)| (*k, v.as_str())),
+			SignableBody::Bytes(body),
+		)?;
+		let (instructions, _) = sign(request, &params)?.into_parts();
+		Ok(
+			instructions
+				.headers()
+				.map(|(k, v)| (k.to_string(), v.to_string()))
+				.collect(),
+		)
+	}
+}
+
+#[cfg(feature = "testing")]
+#[async_trait::async_trait]
+impl ArchiveSink for S3Sink {
+	async fn put(&self, key: &str, body: Vec<u8>) -> Result<(), ArchiveError> {
+		let url = format!("{}/{}/{}{key}", self.endpoint, self.bucket, self.prefix);
+		let mut headers = vec![("content-type", "application/json".to_string())];
+		if let Some(days) = self.retention_days {
+			headers.push(("x-amz-tagging", format!("retention-days={days}")));
+		}
+		let signed = self
+			.sign(&url, &headers, &body)
+			.map_err(|e| ArchiveError::Upload(format!("signing: {e}")))?;
+
+		let mut request = self.client.put(&url).body(body);
+		for (name, value) in headers
+			.iter()
+			.map(|(k, v)| (k.to_string(), v.clone()))
+			.chain(signed)
+		{
+			request = request.header(name, value);
+		}
+		let response = request
+			.send()
+			.await
+			.map_err(|e| ArchiveError::Upload(e.to_string()))?;
+		if !response.status().is_success() {
+			return Err(ArchiveError::Upload(format!(
+				"PUT {url} returned {}",
+				response.status()
+			)));
+		}
+		Ok(())
+	}
+
+	async fn expire(&self, _cutoff: SystemTime) -> Result<usize, ArchiveError> {
+		// Left to the bucket's lifecycle rules
+		Ok(0)
+	}
+}
+
+/// Counters of archive writes
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ArchiveStats {
+	pub archived: u64,
+	pub failed: u64,
+	/// Records not written because too many writes were pending
+	pub dropped: u64,
+	pub expired: u64,
+}
+
+/// Selects executions to archive and writes them to a sink in the background
+#[derive(Debug)]
+pub struct PayloadArchiver {
+	sink: Arc<dyn ArchiveSink>,
+	/// Fraction of executions archived, by composition name (`*` for any other composition)
+	sample_rates: HashMap<String, f64>,
+	retention: Option<Duration>,
+	/// Permits for background writes, bounding the spawned tasks
+	pending: Arc<Semaphore>,
+	archived: AtomicU64,
+	failed: AtomicU64,
+	dropped: AtomicU64,
+	expired: AtomicU64,
+}
+
+impl PayloadArchiver {
+	/// Archive every execution of every composition to `sink`
+	pub fn new(sink: Arc<dyn ArchiveSink>) -> Self {
+		Self {
+			sink,
+			sample_rates: HashMap::from([("*".to_string(), 1.0)]),
+			retention: None,
+			pending: Arc::new(Semaphore::new(MAX_PENDING_WRITES)),
+			archived: AtomicU64::new(0),
+			failed: AtomicU64::new(0),
+			dropped: AtomicU64::new(0),
+			expired: AtomicU64::new(0),
+		}
+	}
+
+	/// Builder: only archive the listed compositions, each at its sample rate (0.0 - 1.0);
+	/// `*` matches compositions not listed
+	pub fn with_sample_rates(mut self, rates: HashMap<String, f64>) -> Self {
+		self.sample_rates = rates;
+		self
+	}
+
+	/// Builder: delete archives older than `retention`
+	pub fn with_retention(mut self, retention: Duration) -> Self {
+		self.retention = Some(retention);
+		self
+	}
+
+	/// Check that the sink can store records
+	pub async fn check(&self) -> Result<(), ArchiveError> {
+		self.sink.check().await
+	}
+
+	/// Whether to archive this execution of `composition`
+	pub fn should_archive(&self, composition: &str) -> bool {
+		let rate = self
+			.sample_rates
+			.get(composition)
+			.or_else(|| self.sample_rates.get("*"))
+			.copied()
+			.unwrap_or(0.0);
+		rate >= 1.0 || (rate > 0.0 && rand::random::<f64>() < rate)
+	}
+
+	/// Write `record` to the sink without waiting for it, or drop it if too many writes are
+	/// already pending
+	pub fn archive(self: &Arc<Self>, record: ArchiveRecord) {
+		let Ok(permit) = self.pending.clone().try_acquire_owned() else {
+			self.dropped.fetch_add(1, Ordering::Relaxed);
+			warn!(target: "virtual_tools", composition = %record.composition, "archive writes backed up, dropped composition payload");
+			return;
+		};
+		let archiver = self.clone();
+		tokio::spawn(async move {
+			archiver.write(record).await;
+			drop(permit);
+		});
+	}
+
+	/// Write `record` to the sink
+	pub async fn write(&self, record: ArchiveRecord) {
+		let key = record.key();
+		let result = match serde_json::to_vec(&record) {
+			Ok(body) => self.sink.put(&key, body).await,
+			Err(e) => Err(ArchiveError::Upload(e.to_string())),
+		};
+		match result {
+			Ok(()) => {
+				self.archived.fetch_add(1, Ordering::Relaxed);
+				debug!(target: "virtual_tools", key, "archived composition payload");
+			},
+			Err(e) => {
+				self.failed.fetch_add(1, Ordering::Relaxed);
+				warn!(target: "virtual_tools", key, error = %e, "failed to archive composition payload");
+			},
+		}
+	}
+
+	/// Delete archives past their retention, if one is set
+	pub async fn enforce_retention(&self) -> Result<usize, ArchiveError> {
+		let Some(retention) = self.retention else {
+			return Ok(0);
+		};
+		let cutoff = SystemTime::now()
+			.checked_sub(retention)
+			.unwrap_or(UNIX_EPOCH);
+		let expired = self.sink.expire(cutoff).await?;
+		self.expired.fetch_add(expired as u64, Ordering::Relaxed);
+		Ok(expired)
+	}
+
+	/// Enforce the retention periodically, until the archiver is dropped elsewhere
+	pub fn spawn_retention(self: &Arc<Self>) {
+		if self.retention.is_none() {
+			return;
+		}
+		let archiver = Arc::downgrade(self);
+		tokio::spawn(async move {
+			let mut interval = tokio::time::interval(RETENTION_INTERVAL);
+			loop {
+				interval.tick().await;
+				let Some(archiver) = archiver.upgrade() else {
+					return;
+				};
+				if let Err(e) = archiver.enforce_retention().await {
+					warn!(target: "virtual_tools", error = %e, "failed to expire composition archives");
+				}
+			}
+		});
+	}
+
+	pub fn stats(&self) -> ArchiveStats {
+		ArchiveStats {
+			archived: self.archived.load(Ordering::Relaxed),
+			failed: self.failed.load(Ordering::Relaxed),
+			dropped: self.dropped.load(Ordering::Relaxed),
+			expired: self.expired.load(Ordering::Relaxed),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use serde_json::json;
+
+	use super::*;
+
+	fn record(composition: &str, trace_id: Option<&str>) -> ArchiveRecord {
+		ArchiveRecord {
+			composition: composition.to_string(),
+			execution: "5f0c2b1e-8d4a-4c3e-9b7a-2e6d1f0a9c84".parse().unwrap(),
+			trace_id: trace_id.map(str::to_string),
+			labels: ExecutionLabels::default(),
+			started_at_ms: 1_700_000_000_000,
+			duration_ms: 12,
+			input: json!({"query": "rust", "token": "kept"}),
+			output: Some(json!({"results": []})),
+			error: None,
+		}
+	}
+
+	#[test]
+	fn test_keys_use_execution_id() {
+		assert_eq!(
+			record("search", Some("4bf92f3577b34da6a3ce929d0e0e4736")).key(),
+			"search/1700000000000-5f0c2b1e-8d4a-4c3e-9b7a-2e6d1f0a9c84.json"
+		);
+	}
+
+	#[tokio::test]
+	async fn test_keys_stay_below_the_root() {
+		let root = tempfile::tempdir().unwrap();
+		let archive = root.path().join("archive");
+		let sink = FilesystemSink::new(&archive);
+		for key in [
+			"../escaped.json",
+			"/tmp/escaped.json",
+			"a/../../escaped.json",
+			"",
+		] {
+			assert!(
+				matches!(
+					sink.put(key, b"{}".to_vec()).await,
+					Err(ArchiveError::InvalidKey(_))
+				),
+				"{key}"
+			);
+		}
+
+		// Hostile composition names are encoded into a single segment
+		let mut hostile = record("../../etc", None);
+		assert_eq!(
+			hostile.key(),
+			"%2E%2E%2F%2E%2E%2Fetc/1700000000000-5f0c2b1e-8d4a-4c3e-9b7a-2e6d1f0a9c84.json"
+		);
+		hostile.composition = "/abs".to_string();
+		sink.put(&hostile.key(), b"{}".to_vec()).await.unwrap();
+		assert!(archive.join(hostile.key()).exists());
+		assert_eq!(std::fs::read_dir(root.path()).unwrap().count(), 1);
+	}
+
+	#[tokio::test]
+	async fn test_writes_beyond_the_pending_limit_are_dropped() {
+		let archiver = Arc::new(PayloadArchiver::new(Arc::new(RecordingSink::default())));
+		let held = archiver
+			.pending
+			.clone()
+			.acquire_many_owned(MAX_PENDING_WRITES as u32)
+			.await
+			.unwrap();
+		archiver.archive(record("search", None));
+		assert_eq!(archiver.stats().dropped, 1);
+		drop(held);
+		archiver.archive(record("search", None));
+		assert_eq!(archiver.stats().dropped, 1);
+	}
+
+	#[test]
+	fn test_sampling_by_composition() {
+		let sink = Arc::new(FilesystemSink::new("/nonexistent"));
+		let archiver = PayloadArchiver::new(sink.clone());
+		assert!(archiver.should_archive("anything"));
+
+		let archiver = PayloadArchiver::new(sink).with_sample_rates(HashMap::from([
+			("orders".to_string(), 1.0),
+			("search".to_string(), 0.0),
+		]));
+		assert!(archiver.should_archive("orders"));
+		assert!(!archiver.should_archive("search"));
+		assert!(!archiver.should_archive("unlisted"));
+	}
+
+	#[tokio::test]
+	async fn test_filesystem_sink_writes_and_expires() {
+		let root = tempfile::tempdir().unwrap();
+		let archiver = PayloadArchiver::new(Arc::new(FilesystemSink::new(root.path())))
+			.with_retention(Duration::from_secs(60));
+		let record = record("search", None);
+		archiver.write(record.clone()).await;
+		assert_eq!(archiver.stats().archived, 1);
+
+		let written = std::fs::read(root.path().join(record.key())).unwrap();
+		let written: Value = serde_json::from_slice(&written).unwrap();
+		// Archives keep the full payload
+		assert_eq!(written["input"]["token"], "kept");
+
+		// Nothing is old enough to expire yet; a zero retention expires everything
+		assert_eq!(archiver.enforce_retention().await.unwrap(), 0);
+		let sink = FilesystemSink::new(root.path());
+		let cutoff = SystemTime::now() + Duration::from_secs(1);
+		assert_eq!(sink.expire(cutoff).await.unwrap(), 1);
+	}
+
+	#[cfg(feature = "testing")]
+	#[tokio::test]
+	async fn test_s3_sink_uploads_signed_objects() {
+		use wiremock::matchers::{header, header_exists, method, path};
+		use wiremock::{Mock, MockServer, ResponseTemplate};
+
+		let server = MockServer::start().await;
+		let record = record("search", None);
+		Mock::given(method("PUT"))
+			.and(path(format!("/archive/evals/{}", record.key())))
+			.and(header("x-amz-tagging", "retention-days=30"))
+			.and(header_exists("authorization"))
+			.respond_with(ResponseTemplate::new(200))
+			.expect(1)
+			.mount(&server)
+			.await;
+
+		let credentials = aws_credential_types::Credentials::new("key", "secret", None, None, "test");
+		let sink = S3Sink::new(&server.uri(), "archive", "us-east-1", credentials)
+			.with_prefix("evals/")
+			.with_retention(Duration::from_secs(30 * 86_400));
+		let archiver = PayloadArchiver::new(Arc::new(sink));
+		archiver.write(record).await;
+		assert_eq!(archiver.stats().archived, 1);
+	}
+
+	#[derive(Debug, Default)]
+	struct RecordingSink {
+		puts: parking_lot::Mutex<Vec<(String, Value)>>,
+	}
+
+	#[async_trait::async_trait]
+	impl ArchiveSink for RecordingSink {
+		async fn put(&self, key: &str, body: Vec<u8>) -> Result<(), ArchiveError> {
+			let body = serde_json::from_slice(&body).unwrap();
+			self.puts.lock().push((key.to_string(), body));
+			Ok(())
+		}
+
+		async fn expire(&self, _cutoff: SystemTime) -> Result<usize, ArchiveError> {
+			Ok(0)
+		}
+	}
+
+	#[tokio::test]
+	async fn test_executor_archives_selected_compositions() {
+		use crate::mcp::registry::CompiledRegistry;
+		use crate::mcp::registry::executor::{CompositionExecutor, MockToolInvoker};
+		use crate::mcp::registry::patterns::{MapEachSpec, PatternSpec};
+		use crate::mcp::registry::types::{Registry, ToolDefinition};
+
+		let tool = |name: &str| {
+			ToolDefinition::composition(name, PatternSpec::MapEach(MapEachSpec::tool("lookup")))
+		};
+		let registry = Registry::with_tool_definitions(vec![tool("archived"), tool("skipped")]);
+		let compiled = Arc::new(CompiledRegistry::compile(registry).unwrap());
+		let invoker = Arc::new(MockToolInvoker::new().with_response("lookup", json!("ok")));
+		let sink = Arc::new(RecordingSink::default());
+		let archiver = Arc::new(
+			PayloadArchiver::new(sink.clone())
+				.with_sample_rates(HashMap::from([("archived".to_string(), 1.0)])),
+		);
+		let execution_id = ExecutionId::new();
+		let executor = CompositionExecutor::new(compiled, invoker)
+			.with_archiver(archiver.clone())
+			.with_execution_id(execution_id)
+			.with_trace_id("trace-1")
+			.with_labels(ExecutionLabels::from_iter([("project", "search")]));
+
+		executor.execute("archived", json!([1])).await.unwrap();
+		executor.execute("skipped", json!([2])).await.unwrap();
+		for _ in 0..100 {
+			if archiver.stats().archived > 0 {
+				break;
+			}
+			tokio::time::sleep(Duration::from_millis(5)).await;
+		}
+
+		let puts = sink.puts.lock();
+		assert_eq!(puts.len(), 1);
+		assert!(puts[0].0.starts_with("archived/"));
+		assert!(puts[0].0.ends_with(&format!("-{execution_id}.json")));
+		assert_eq!(puts[0].1["traceId"], "trace-1");
+		assert_eq!(puts[0].1["input"], json!([1]));
+		assert_eq!(puts[0].1["output"], json!(["ok"]));
+		assert_eq!(puts[0].1["labels"], json!({"project": "search"}));
+	}
+}
@@ -0,0 +1,316 @@
+// Argument checks for tool calls made by compositions
+//
+// A composition step binds its arguments from the composition's input and earlier results.
+// A binding mistake (a misspelled field, a string where an array belongs) otherwise shows up
+// as a backend rejection that doesn't say which step sent what. When a step calls a tool the
+// registry knows an input schema for, the arguments are checked against it first, so the
+// mistake is reported at the step boundary.
+//
+// Callers' arguments to virtual tools and compositions are checked against the schema the
+// gateway lists for the tool the same way. Every mismatch is collected, so an agent sees all
+// of them in one error and can fix its arguments in a single retry.
+//
+// Only the parts of JSON Schema that registry schemas use are checked: `type`, `enum`,
+// `required`, `properties`, `additionalProperties: false` and `items`. Anything else is
+// accepted.
+
+use serde::Serialize;
+use serde_json::Value;
+
+use super::validation::escape_pointer_token;
+
+/// What to do with arguments that don't match the called tool's input schema
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ArgumentCheckPolicy {
+	/// Don't check arguments
+	Off,
+	/// Log mismatches and make the call anyway
+	#[default]
+	Warn,
+	/// Fail the step instead of making the call
+	Enforce,
+}
+
+/// One place where arguments don't match a schema
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ArgumentProblem {
+	/// JSON pointer to the offending value, or to the object missing a field
+	pub pointer: String,
+	pub message: String,
+}
+
+impl std::fmt::Display for ArgumentProblem {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		write!(f, "{}: {}", self.pointer, self.message)
+	}
+}
+
+/// Where `args` don't match `schema`, as `<JSON pointer>: <problem>` messages
+pub fn check_arguments(schema: &Value, args: &Value) -> Vec<String> {
+	argument_problems(schema, args)
+		.iter()
+		.map(ToString::to_string)
+		.collect()
+}
+
+/// Every place `args` don't match `schema`
+pub fn argument_problems(schema: &Value, args: &Value) -> Vec<ArgumentProblem> {
+	let mut problems = Vec::new();
+	check("", schema, args, &mut problems);
+	problems
+}
+
+/// Convert strings that spell out a number or boolean where `schema` expects one, as agents
+/// often quote them (`"days": "3"`); strings that don't parse are left for the check to report
+pub fn coerce_arguments(schema: &Value, args: &mut Value) {
+	if let Value::String(s) = &*args
+		&& let Some(expected) = schema.get("type")
+		&& !matches_type(args, expected)
+		&& let Some(coerced) = coerce_scalar(s, expected)
+	{
+		*args = coerced;
+		return;
+	}
+	match args {
+		Value::Object(fields) => {
+			let Some(properties) = schema.get("properties").and_then(Value::as_object) else {
+				return;
+			};
+			for (name, field) in fields.iter_mut() {
+				if let Some(property) = properties.get(name) {
+					coerce_arguments(property, field);
+				}
+			}
+		},
+		Value::Array(items) => {
+			if let Some(item_schema) = schema.get("items").filter(|s| s.is_object()) {
+				for item in items {
+					coerce_arguments(item_schema, item);
+				}
+			}
+		},
+		_ => {},
+	}
+}
+
+/// `text` as the first type in `expected` it parses as
+fn coerce_scalar(text: &str, expected: &Value) -> Option<Value> {
+	let names: Vec<&str> = match expected {
+		Value::String(name) => vec![name.as_str()],
+		Value::Array(names) => names.iter().filter_map(Value::as_str).collect(),
+		_ => return None,
+	};
+	let text = text.trim();
+	names.into_iter().find_map(|name| match name {
+		"integer" => text.parse::<i64>().ok().map(Value::from),
+		"number" => text
+			.parse::<i64>()
+			.ok()
+			.map(Value::from)
+			.or_else(|| serde_json::Number::from_f64(text.parse().ok()?).map(Value::Number)),
+		"boolean" => text.parse::<bool>().ok().map(Value::Bool),
+		_ => None,
+	})
+}
+
+fn check(path: &str, schema: &Value, value: &Value, problems: &mut Vec<ArgumentProblem>) {
+	let at = if path.is_empty() { "/" } else { path };
+	let mut problem = |pointer: &str, message: String| {
+		problems.push(ArgumentProblem {
+			pointer: pointer.to_string(),
+			message,
+		})
+	};
+	if let Some(expected) = schema.get("type")
+		&& !matches_type(value, expected)
+	{
+		problem(
+			at,
+			format!(
+				"expected {}, got {}",
+				type_names(expected),
+				type_name(value)
+			),
+		);
+		return;
+	}
+	if let Some(allowed) = schema.get("enum").and_then(Value::as_array)
+		&& !allowed.contains(value)
+	{
+		problem(
+			at,
+			format!("{value} is not one of {}", Value::Array(allowed.clone())),
+		);
+	}
+	match value {
+		Value::Object(fields) => {
+			for name in schema
+				.get("required")
+				.and_then(Value::as_array)
+				.into_iter()
+				.flatten()
+				.filter_map(Value::as_str)
+			{
+				if !fields.contains_key(name) {
+					problem(at, format!("missing required field '{name}'"));
+				}
+			}
+			let properties = schema.get("properties").and_then(Value::as_object);
+			let closed = schema.get("additionalProperties") == Some(&Value::Bool(false));
+			for (name, field) in fields {
+				let child = format!("{}/{}", path, escape_pointer_token(name));
+				match properties.and_then(|p| p.get(name)) {
+					Some(property) => check(&child, property, field, problems),
+					None if closed => problems.push(ArgumentProblem {
+						pointer: child,
+						message: "unknown field".to_string(),
+					}),
+					None => {},
+				}
+			}
+		},
+		Value::Array(items) => {
+			if let Some(item_schema) = schema.get("items").filter(|s| s.is_object()) {
+				for (i, item) in items.iter().enumerate() {
+					check(&format!("{path}/{i}"), item_schema, item, problems);
+				}
+			}
+		},
+		_ => {},
+	}
+}
+
+/// Whether a value satisfies a JSON Schema `type` (a name or a list of names)
+pub(super) fn matches_type(value: &Value, expected: &Value) -> bool {
+	let matches_name = |name: &str| match name {
+		"string" => value.is_string(),
+		"number" => value.is_number(),
+		"integer" => value.is_i64() || value.is_u64(),
+		"boolean" => value.is_boolean(),
+		"array" => value.is_array(),
+		"object" => value.is_object(),
+		"null" => value.is_null(),
+		_ => true,
+	};
+	match expected {
+		Value::String(name) => matches_name(name),
+		Value::Array(names) => names.iter().filter_map(Value::as_str).any(matches_name),
+		_ => true,
+	}
+}
+
+fn type_names(expected: &Value) -> String {
+	match expected {
+		Value::Array(names) => names
+			.iter()
+			.filter_map(Value::as_str)
+			.collect::<Vec<_>>()
+			.join(" or "),
+		other => other.as_str().unwrap_or_default().to_string(),
+	}
+}
+
+fn type_name(value: &Value) -> &'static str {
+	match value {
+		Value::Null => "null",
+		Value::Bool(_) => "boolean",
+		Value::Number(_) => "number",
+		Value::String(_) => "string",
+		Value::Array(_) => "array",
+		Value::Object(_) => "object",
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use serde_json::json;
+
+	use super::*;
+
+	#[test]
+	fn test_check_arguments() {
+		let schema = json!({
+			"type": "object",
+			"properties": {
+				"city": {"type": "string"},
+				"days": {"type": "integer"},
+				"units": {"enum": ["metric", "imperial"]},
+				"ids": {"type": "array", "items": {"type": "string"}},
+				"filters": {
+					"type": "object",
+					"properties": {"lang": {"type": ["string", "null"]}},
+					"additionalProperties": false
+				}
+			},
+			"required": ["city"]
+		});
+
+		assert!(check_arguments(&schema, &json!({"city": "Oslo", "days": 3})).is_empty());
+		assert!(check_arguments(&schema, &json!({"city": "Oslo", "extra": true})).is_empty());
+		assert_eq!(
+			check_arguments(
+				&schema,
+				&json!({
+					"days": 2.5,
+					"units": "kelvin",
+					"ids": ["a", 7],
+					"filters": {"lang": null, "langauge": "en"}
+				})
+			),
+			vec![
+				"/: missing required field 'city'",
+				"/days: expected integer, got number",
+				"/units: \"kelvin\" is not one of [\"metric\",\"imperial\"]",
+				"/ids/1: expected string, got number",
+				"/filters/langauge: unknown field",
+			]
+		);
+		assert_eq!(
+			check_arguments(&schema, &json!(["Oslo"])),
+			vec!["/: expected object, got array"]
+		);
+	}
+
+	#[test]
+	fn test_coerce_arguments() {
+		let schema = json!({
+			"type": "object",
+			"properties": {
+				"days": {"type": "integer"},
+				"radius": {"type": "number"},
+				"metric": {"type": "boolean"},
+				"limit": {"type": ["integer", "null"]},
+				"city": {"type": "string"},
+				"ids": {"type": "array", "items": {"type": "integer"}}
+			}
+		});
+		let mut args = json!({
+			"days": "3",
+			"radius": " 2.5 ",
+			"metric": "true",
+			"limit": "10",
+			"city": "42",
+			"ids": ["1", "two"]
+		});
+		coerce_arguments(&schema, &mut args);
+		assert_eq!(
+			args,
+			json!({
+				"days": 3,
+				"radius": 2.5,
+				"metric": true,
+				"limit": 10,
+				"city": "42",
+				"ids": [1, "two"]
+			})
+		);
+		// What didn't convert is left for the check, which reports it with everything else
+		assert_eq!(
+			argument_problems(&schema, &args),
+			vec![ArgumentProblem {
+				pointer: "/ids/1".to_string(),
+				message: "expected integer, got string".to_string(),
+			}]
+		);
+	}
+}
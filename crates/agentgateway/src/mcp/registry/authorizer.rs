@@ -0,0 +1,387 @@
+// External authorization of tool calls
+//
+// Lets a central policy service decide, call by call, whether a caller may invoke a tool,
+// so tool usage can be governed without redeploying the gateway. The gateway POSTs an
+// OPA-style `{"input": ...}` document describing the caller and the call, and accepts either
+// `{"result": true}` or `{"result": {"allow": true, "reason": "..."}}` back. That makes an
+// OPA server (`/v1/data/<package>/<rule>`) and a purpose-built ext_authz-style service
+// interchangeable behind the same configuration. Decisions are requested through the
+// gateway's own upstream client, so DNS, pooling and TLS trust match its other calls.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use aws_lc_rs::digest;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use tracing::warn;
+
+use crate::client::Client;
+
+/// Default time allowed for an authorization decision
+pub const DEFAULT_AUTHORIZER_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// Largest decision body read from the policy service
+const MAX_DECISION_BYTES: usize = 64 * 1024;
+
+/// Error type for authorizer calls
+#[derive(Debug, thiserror::Error)]
+pub enum AuthorizerError {
+	#[error("authorizer unavailable: {0}")]
+	Unavailable(String),
+	#[error("invalid authorizer response: {0}")]
+	InvalidResponse(String),
+}
+
+/// What the authorizer is asked about a tool call
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AuthorizationRequest {
+	/// Tool name the caller invoked
+	pub tool: String,
+	/// Target the call resolved to (`_composition` for compositions)
+	pub target: String,
+	/// Tool invoked on the target
+	pub backend_tool: String,
+	/// Hex SHA-256 of the compact JSON arguments the call executes with, so policies can pin
+	/// or deny exact calls without the gateway forwarding payloads
+	pub args_sha256: String,
+	/// `sub` claim of the caller's verified JWT
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub subject: Option<String>,
+	/// Verified JWT claims of the caller
+	#[serde(skip_serializing_if = "Map::is_empty")]
+	pub claims: Map<String, Value>,
+}
+
+impl AuthorizationRequest {
+	pub fn new(
+		tool: &str,
+		target: &str,
+		backend_tool: &str,
+		args: &Value,
+		claims: Option<&Map<String, Value>>,
+	) -> Self {
+		let claims = claims.cloned().unwrap_or_default();
+		Self {
+			tool: tool.to_string(),
+			target: target.to_string(),
+			backend_tool: backend_tool.to_string(),
+			args_sha256: args_sha256(args),
+			subject: claims
+				.get("sub")
+				.and_then(Value::as_str)
+				.map(str::to_string),
+			claims,
+		}
+	}
+}
+
+fn args_sha256(args: &Value) -> String {
+	let bytes = serde_json::to_vec(args).unwrap_or_default();
+	hex::encode(digest::digest(&digest::SHA256, &bytes))
+}
+
+/// The authorizer's verdict on a tool call
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct AuthorizationDecision {
+	pub allow: bool,
+	/// Explanation returned to the caller on denial
+	#[serde(default)]
+	pub reason: Option<String>,
+}
+
+impl AuthorizationDecision {
+	pub fn allow() -> Self {
+		Self {
+			allow: true,
+			reason: None,
+		}
+	}
+
+	pub fn deny(reason: Option<String>) -> Self {
+		Self {
+			allow: false,
+			reason,
+		}
+	}
+
+	/// Parse an authorizer response body
+	///
+	/// A response without a `result` (OPA's answer for an undefined rule) is a denial.
+	pub fn from_response(body: &[u8]) -> Result<Self, AuthorizerError> {
+		#[derive(Deserialize)]
+		#[serde(untagged)]
+		enum Verdict {
+			Bool(bool),
+			Decision(AuthorizationDecision),
+		}
+		#[derive(Deserialize)]
+		struct Response {
+			#[serde(default)]
+			result: Option<Verdict>,
+		}
+
+		let response: Response =
+			serde_json::from_slice(body).map_err(|e| AuthorizerError::InvalidResponse(e.to_string()))?;
+		Ok(match response.result {
+			Some(Verdict::Bool(allow)) => Self {
+				allow,
+				reason: None,
+			},
+			Some(Verdict::Decision(decision)) => decision,
+			None => Self::deny(None),
+		})
+	}
+}
+
+/// Source of authorization decisions
+#[async_trait::async_trait]
+pub trait Authorizer: Send + Sync + std::fmt::Debug {
+	async fn authorize(
+		&self,
+		request: &AuthorizationRequest,
+	) -> Result<AuthorizationDecision, AuthorizerError>;
+}
+
+/// Asks an HTTP policy service, such as an OPA server, for decisions
+#[derive(Debug, Clone)]
+pub struct HttpAuthorizer {
+	client: Client,
+	url: http::Uri,
+	bearer: Option<String>,
+}
+
+impl HttpAuthorizer {
+	pub fn new(client: Client, url: http::Uri) -> Self {
+		Self {
+			client,
+			url,
+			bearer: None,
+		}
+	}
+
+	/// Builder: authenticate to the policy service with a bearer token
+	pub fn with_bearer(mut self, token: &str) -> Self {
+		self.bearer = Some(token.to_string());
+		self
+	}
+}
+
+#[async_trait::async_trait]
+impl Authorizer for HttpAuthorizer {
+	async fn authorize(
+		&self,
+		request: &AuthorizationRequest,
+	) -> Result<AuthorizationDecision, AuthorizerError> {
+		let unavailable = |e: &dyn std::fmt::Display| AuthorizerError::Unavailable(e.to_string());
+		let body =
+			serde_json::to_vec(&serde_json::json!({ "input": request })).map_err(|e| unavailable(&e))?;
+		let mut http_request = http::Request::builder()
+			.method(http::Method::POST)
+			.uri(self.url.clone())
+			.header(http::header::CONTENT_TYPE, "application/json");
+		if let Some(token) = &self.bearer {
+			http_request = http_request.header(http::header::AUTHORIZATION, format!("Bearer {token}"));
+		}
+		let http_request = http_request
+			.body(crate::http::Body::from(body))
+			.map_err(|e| unavailable(&e))?;
+		let response = self
+			.client
+			.simple_call(http_request)
+			.await
+			.map_err(|e| unavailable(&e))?;
+		if !response.status().is_success() {
+			return Err(AuthorizerError::Unavailable(format!(
+				"status {}",
+				response.status()
+			)));
+		}
+		let body = crate::http::read_body_with_limit(response.into_body(), MAX_DECISION_BYTES)
+			.await
+			.map_err(|e| unavailable(&e))?;
+		AuthorizationDecision::from_response(&body)
+	}
+}
+
+/// Gate in front of every tool call, applying a timeout and failure mode to an [`Authorizer`]
+#[derive(Debug, Clone)]
+pub struct ToolCallAuthorizer {
+	authorizer: Arc<dyn Authorizer>,
+	timeout: Duration,
+	fail_open: bool,
+}
+
+impl ToolCallAuthorizer {
+	pub fn new(authorizer: Arc<dyn Authorizer>) -> Self {
+		Self {
+			authorizer,
+			timeout: DEFAULT_AUTHORIZER_TIMEOUT,
+			fail_open: false,
+		}
+	}
+
+	/// Builder: time allowed for a decision before the failure mode applies
+	pub fn with_timeout(mut self, timeout: Duration) -> Self {
+		self.timeout = timeout;
+		self
+	}
+
+	/// Builder: allow calls when the authorizer fails or times out, instead of denying them
+	pub fn with_fail_open(mut self, fail_open: bool) -> Self {
+		self.fail_open = fail_open;
+		self
+	}
+
+	/// Decide whether a tool call may proceed
+	pub async fn check(&self, request: &AuthorizationRequest) -> AuthorizationDecision {
+		let error = match tokio::time::timeout(self.timeout, self.authorizer.authorize(request)).await {
+			Ok(Ok(decision)) => return decision,
+			Ok(Err(e)) => e.to_string(),
+			Err(_) => format!("no decision within {:?}", self.timeout),
+		};
+		warn!(
+			target: "virtual_tools",
+			tool = %request.tool,
+			fail_open = self.fail_open,
+			"tool call authorizer failed: {error}"
+		);
+		if self.fail_open {
+			AuthorizationDecision::allow()
+		} else {
+			AuthorizationDecision::deny(None)
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use serde_json::json;
+
+	#[derive(Debug)]
+	struct Fixed(Option<AuthorizationDecision>, Duration);
+
+	#[async_trait::async_trait]
+	impl Authorizer for Fixed {
+		async fn authorize(
+			&self,
+			_: &AuthorizationRequest,
+		) -> Result<AuthorizationDecision, AuthorizerError> {
+			tokio::time::sleep(self.1).await;
+			self
+				.0
+				.clone()
+				.ok_or_else(|| AuthorizerError::Unavailable("connection refused".to_string()))
+		}
+	}
+
+	#[test]
+	fn test_request() {
+		let claims = json!({"sub": "agent-7", "team": "search"});
+		let request = AuthorizationRequest::new(
+			"search",
+			"backend",
+			"web_search",
+			&json!({"q": "rust"}),
+			claims.as_object(),
+		);
+		assert_eq!(request.subject.as_deref(), Some("agent-7"));
+		assert_eq!(request.args_sha256.len(), 64);
+
+		let anonymous =
+			|args: Value| AuthorizationRequest::new("search", "backend", "web_search", &args, None);
+		assert_eq!(
+			request.args_sha256,
+			anonymous(json!({"q": "rust"})).args_sha256
+		);
+		assert_ne!(
+			request.args_sha256,
+			anonymous(json!({"q": "go"})).args_sha256
+		);
+
+		let anonymous = serde_json::to_value(anonymous(json!({}))).unwrap();
+		assert!(anonymous.get("subject").is_none());
+		assert!(anonymous.get("claims").is_none());
+		assert_eq!(anonymous["backendTool"], "web_search");
+	}
+
+	#[test]
+	fn test_decision_from_response() {
+		let parse = |body: Value| AuthorizationDecision::from_response(body.to_string().as_bytes());
+		assert_eq!(
+			parse(json!({"result": true})).unwrap(),
+			AuthorizationDecision::allow()
+		);
+		assert_eq!(
+			parse(json!({"result": {"allow": false, "reason": "outside business hours"}})).unwrap(),
+			AuthorizationDecision::deny(Some("outside business hours".to_string()))
+		);
+		// Undefined OPA rule
+		assert_eq!(parse(json!({})).unwrap(), AuthorizationDecision::deny(None));
+		assert!(parse(json!({"result": "yes"})).is_err());
+	}
+
+	#[tokio::test]
+	async fn test_failure_mode() {
+		let request = AuthorizationRequest::new("search", "backend", "web_search", &json!({}), None);
+		let deny = AuthorizationDecision::deny(Some("no".to_string()));
+
+		let authorizer = ToolCallAuthorizer::new(Arc::new(Fixed(Some(deny.clone()), Duration::ZERO)));
+		assert_eq!(authorizer.check(&request).await, deny);
+
+		for fail_open in [false, true] {
+			let failing =
+				ToolCallAuthorizer::new(Arc::new(Fixed(None, Duration::ZERO))).with_fail_open(fail_open);
+			assert_eq!(failing.check(&request).await.allow, fail_open);
+
+			let slow = ToolCallAuthorizer::new(Arc::new(Fixed(
+				Some(AuthorizationDecision::allow()),
+				Duration::from_secs(5),
+			)))
+			.with_timeout(Duration::from_millis(10))
+			.with_fail_open(fail_open);
+			assert_eq!(slow.check(&request).await.allow, fail_open);
+		}
+	}
+	#[tokio::test]
+	async fn test_http_authorizer() {
+		use wiremock::matchers::{body_partial_json, header, method, path};
+		use wiremock::{Mock, MockServer, ResponseTemplate};
+
+		let server = MockServer::start().await;
+		Mock::given(method("POST"))
+			.and(path("/v1/data/tools/allow"))
+			.and(header("authorization", "Bearer secret"))
+			.and(body_partial_json(json!({"input": {"tool": "search"}})))
+			.respond_with(
+				ResponseTemplate::new(200)
+					.set_body_json(json!({"result": {"allow": false, "reason": "quota exhausted"}})),
+			)
+			.mount(&server)
+			.await;
+
+		let request = AuthorizationRequest::new("search", "backend", "web_search", &json!({}), None);
+		let client = crate::test_helpers::proxymock::setup_proxy_test("{}")
+			.unwrap()
+			.inputs()
+			.upstream
+			.clone();
+		let url: http::Uri = format!("{}/v1/data/tools/allow", server.uri())
+			.parse()
+			.unwrap();
+		let authorizer = HttpAuthorizer::new(client.clone(), url.clone()).with_bearer("secret");
+		assert_eq!(
+			authorizer.authorize(&request).await.unwrap(),
+			AuthorizationDecision::deny(Some("quota exhausted".to_string()))
+		);
+
+		// Unmatched requests get wiremock's 404
+		let unauthenticated = HttpAuthorizer::new(client, url);
+		assert!(matches!(
+			unauthenticated.authorize(&request).await,
+			Err(AuthorizerError::Unavailable(_))
+		));
+	}
+}
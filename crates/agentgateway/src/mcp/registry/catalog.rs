@@ -0,0 +1,321 @@
+// Tool documentation for agents
+//
+// Whenever a registry is loaded, a `describe_tool` meta-tool is listed next to its tools, so
+// agents can look up a tool's full documentation at runtime instead of every ListTools entry
+// carrying it. The documentation is assembled from the registry definition: tags and
+// deprecation notices come from the `tags` and `deprecated` metadata keys, and any other
+// metadata is passed through as-is.
+
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+use rmcp::model::{Tool, ToolAnnotations as McpToolAnnotations};
+use serde::Serialize;
+use serde_json::{Map, Value, json};
+
+use super::compiled::{CompiledImplementation, CompiledRegistry, CompiledTool};
+use super::executor::envelope_output_schema;
+use super::stats::ToolSummary;
+use super::types::ToolAnnotations;
+
+/// Name of the documentation meta-tool (a registry tool of the same name takes precedence)
+pub const DESCRIBE_TOOL: &str = "describe_tool";
+
+/// Metadata keys with a dedicated place in the documentation
+const TAGS_KEY: &str = "tags";
+const DEPRECATED_KEY: &str = "deprecated";
+
+/// Full documentation of a registry tool
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ToolDoc {
+	#[serde(flatten)]
+	pub summary: ToolSummary,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub description: Option<String>,
+	/// Per-locale description overrides
+	#[serde(skip_serializing_if = "BTreeMap::is_empty")]
+	pub descriptions: BTreeMap<String, String>,
+	/// Omitted for virtual tools that don't override their backend tool's schema
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub input_schema: Option<Value>,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub output_schema: Option<Value>,
+	/// Backend tool fields hidden from callers (operator documentation only, never returned
+	/// to agents)
+	#[serde(skip)]
+	pub hidden_fields: Vec<String>,
+	/// Backend tool fields the gateway fills in (operator documentation only)
+	#[serde(skip)]
+	pub injected_fields: Vec<String>,
+	#[serde(skip_serializing_if = "Vec::is_empty")]
+	pub tags: Vec<String>,
+	#[serde(skip_serializing_if = "Vec::is_empty")]
+	pub examples: Vec<Value>,
+	/// Behavior hints declared in the registry
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub annotations: Option<ToolAnnotations>,
+	/// Deprecation notice
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub deprecated: Option<String>,
+	#[serde(skip_serializing_if = "Map::is_empty")]
+	pub metadata: Map<String, Value>,
+}
+
+impl From<&CompiledTool> for ToolDoc {
+	fn from(tool: &CompiledTool) -> Self {
+		let def = &tool.def;
+		let mut metadata: Map<String, Value> = def
+			.metadata
+			.iter()
+			.map(|(k, v)| (k.clone(), v.clone()))
+			.collect();
+		let tags = match metadata.remove(TAGS_KEY) {
+			Some(Value::Array(tags)) => tags
+				.into_iter()
+				.filter_map(|t| t.as_str().map(str::to_string))
+				.collect(),
+			Some(Value::String(tag)) => vec![tag],
+			_ => Vec::new(),
+		};
+		let examples = def
+			.examples
+			.iter()
+			.filter_map(|e| serde_json::to_value(e).ok())
+			.collect();
+		let deprecated = match metadata.remove(DEPRECATED_KEY) {
+			Some(Value::String(notice)) => Some(notice),
+			Some(Value::Bool(true)) => Some("deprecated".to_string()),
+			_ => None,
+		};
+
+		let (input_schema, output_schema, hidden_fields, mut injected_fields) = match &tool.compiled {
+			CompiledImplementation::Source(source) => (
+				def.input_schema.clone(),
+				def.output_schema.clone(),
+				source.source.hide_fields.clone(),
+				source.source.defaults.keys().cloned().collect(),
+			),
+			CompiledImplementation::Composition(composition) => {
+				let declared = composition.output_schema.as_ref();
+				let output_schema = if def.result_envelope {
+					Some(envelope_output_schema(declared))
+				} else {
+					declared.cloned()
+				};
+				(
+					composition.input_schema.clone(),
+					output_schema,
+					Vec::new(),
+					Vec::new(),
+				)
+			},
+		};
+		injected_fields.sort();
+
+		Self {
+			summary: ToolSummary::from(tool),
+			description: def.description.clone(),
+			descriptions: def
+				.descriptions
+				.iter()
+				.map(|(k, v)| (k.clone(), v.clone()))
+				.collect(),
+			input_schema,
+			output_schema,
+			hidden_fields,
+			injected_fields,
+			tags,
+			examples,
+			annotations: def.annotations.clone(),
+			deprecated,
+			metadata,
+		}
+	}
+}
+
+/// Definition of the describe tool, as listed to clients
+pub fn describe_tool_definition() -> Tool {
+	let input_schema = json!({
+		"type": "object",
+		"properties": {
+			"name": {
+				"type": "string",
+				"description": "Tool to describe. Omit to list every registry tool with its description."
+			}
+		}
+	});
+	Tool {
+		name: Cow::Borrowed(DESCRIBE_TOOL),
+		title: None,
+		description: Some(Cow::Borrowed(
+			"Describe a tool: its full description, input and output schemas, tags, examples and \
+			deprecation notice.",
+		)),
+		input_schema: Arc::new(input_schema.as_object().cloned().unwrap_or_default()),
+		output_schema: None,
+		annotations: Some(McpToolAnnotations::new().read_only(true).open_world(false)),
+		icons: None,
+		meta: None,
+	}
+}
+
+/// Answer a describe call: the documentation of the `name`d tool, or a catalog of every
+/// tool when no name is given
+///
+/// Only tools `visible` allows are described; others look like they don't exist, as they do
+/// in tools/list.
+pub fn describe(
+	registry: &CompiledRegistry,
+	args: &Value,
+	visible: impl Fn(&CompiledTool) -> bool,
+) -> Result<Value, String> {
+	let name = match args.get("name") {
+		None | Some(Value::Null) => return Ok(catalog(registry, visible)),
+		Some(Value::String(name)) => name,
+		Some(_) => return Err("'name' must be a string".to_string()),
+	};
+	let tool = registry
+		.get_tool(name)
+		.filter(|tool| visible(tool))
+		.ok_or_else(|| format!("'{name}' is not a registry tool; see tools/list for its schema"))?;
+	serde_json::to_value(ToolDoc::from(tool.as_ref())).map_err(|e| e.to_string())
+}
+
+fn catalog(registry: &CompiledRegistry, visible: impl Fn(&CompiledTool) -> bool) -> Value {
+	let mut tools: Vec<_> = registry
+		.tools()
+		.filter(|tool| visible(tool))
+		.map(|tool| {
+			let doc = ToolDoc::from(tool.as_ref());
+			let mut entry = json!({"name": doc.summary.name, "type": doc.summary.kind});
+			if let Some(description) = doc.description {
+				entry["description"] = description.into();
+			}
+			if !doc.tags.is_empty() {
+				entry["tags"] = doc.tags.into();
+			}
+			if let Some(deprecated) = doc.deprecated {
+				entry["deprecated"] = deprecated.into();
+			}
+			entry
+		})
+		.collect();
+	tools.sort_by(|a, b| a["name"].as_str().cmp(&b["name"].as_str()));
+	json!({ "tools": tools })
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::mcp::registry::types::Registry;
+
+	fn registry() -> CompiledRegistry {
+		let registry: Registry = serde_json::from_value(json!({
+			"schemaVersion": "1.0",
+			"tools": [
+				{
+					"name": "search",
+					"description": "Search the web",
+					"descriptions": {"fr": "Rechercher sur le web"},
+					"source": {
+						"target": "backend",
+						"tool": "web_search",
+						"defaults": {"api_key": "${SEARCH_KEY}", "region": "us"},
+						"hideFields": ["api_key", "region"]
+					},
+					"version": "2.1.0",
+					"metadata": {
+						"owner": "search-team",
+						"tags": ["web", "search"],
+						"deprecated": "use search_v3"
+					},
+					"examples": [{"input": {"query": "rust async"}}]
+				},
+				{
+					"name": "research",
+					"description": "Search then summarize",
+					"spec": {
+						"pipeline": {
+							"steps": [{"id": "s", "operation": {"tool": {"name": "search"}}}]
+						}
+					},
+					"resultEnvelope": true,
+					"annotations": {"readOnlyHint": true},
+					"examples": [{"input": {"query": "mcp"}, "output": {"summary": "..."}}]
+				}
+			]
+		}))
+		.unwrap();
+		CompiledRegistry::compile(registry).unwrap()
+	}
+
+	#[test]
+	fn test_describe_tool() {
+		let registry = registry();
+		let doc = describe(&registry, &json!({"name": "search"}), |_| true).unwrap();
+		assert_eq!(doc["type"], "source");
+		assert_eq!(doc["sources"], json!(["backend/web_search"]));
+		assert_eq!(doc["version"], "2.1.0");
+		assert_eq!(doc["descriptions"], json!({"fr": "Rechercher sur le web"}));
+		assert_eq!(doc["tags"], json!(["web", "search"]));
+		assert_eq!(doc["examples"], json!([{"input": {"query": "rust async"}}]));
+		assert_eq!(doc["deprecated"], "use search_v3");
+		assert_eq!(doc["metadata"], json!({"owner": "search-team"}));
+		// Hidden and injected fields, let alone their values, are not disclosed to agents
+		assert!(doc.get("hiddenFields").is_none());
+		assert!(doc.get("injectedFields").is_none());
+		assert!(!doc.to_string().contains("api_key"));
+		assert!(!doc.to_string().contains("SEARCH_KEY"));
+
+		let doc = describe(&registry, &json!({"name": "research"}), |_| true).unwrap();
+		assert_eq!(doc["type"], "pipeline");
+		assert!(doc["outputSchema"]["properties"]["data"].is_object());
+		assert_eq!(doc["annotations"], json!({"readOnlyHint": true}));
+		assert_eq!(
+			doc["examples"],
+			json!([{"input": {"query": "mcp"}, "output": {"summary": "..."}}])
+		);
+
+		assert!(describe(&registry, &json!({"name": "backend_fetch"}), |_| true).is_err());
+		assert!(describe(&registry, &json!({"name": 1}), |_| true).is_err());
+	}
+
+	#[test]
+	fn test_describe_hidden_tool() {
+		let registry = registry();
+		let visible = |tool: &CompiledTool| tool.def.name != "search";
+		let hidden = describe(&registry, &json!({"name": "search"}), visible).unwrap_err();
+		let missing = describe(&registry, &json!({"name": "backend_fetch"}), visible).unwrap_err();
+		assert_eq!(
+			hidden.replace("search", "backend_fetch"),
+			missing,
+			"a hidden tool looks like a missing one"
+		);
+		assert_eq!(
+			describe(&registry, &json!({}), visible).unwrap(),
+			json!({"tools": [
+				{"name": "research", "type": "pipeline", "description": "Search then summarize"}
+			]})
+		);
+	}
+
+	#[test]
+	fn test_catalog() {
+		let catalog = describe(&registry(), &json!({}), |_| true).unwrap();
+		assert_eq!(
+			catalog,
+			json!({"tools": [
+				{"name": "research", "type": "pipeline", "description": "Search then summarize"},
+				{
+					"name": "search",
+					"type": "source",
+					"description": "Search the web",
+					"tags": ["web", "search"],
+					"deprecated": "use search_v3"
+				}
+			]})
+		);
+	}
+}
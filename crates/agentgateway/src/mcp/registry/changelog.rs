@@ -0,0 +1,294 @@
+// Changelog of registry reloads
+//
+// When errors start right after a reload, the first question is what the reload changed.
+// Each successful reload is compared with the registry it replaced, tool by tool: tools that
+// appeared, tools that went away, and for tools in both, every field whose value changed,
+// addressed by a JSON pointer into the tool's definition. Reloads that changed something are
+// logged and kept in a bounded history for the admin server.
+
+use std::collections::{BTreeSet, VecDeque};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use parking_lot::Mutex;
+use serde::Serialize;
+use serde_json::Value;
+use tracing::{debug, info};
+
+use super::compiled::CompiledRegistry;
+use super::stats::ReloadTrigger;
+use super::validation::escape_pointer_token;
+
+/// Default number of registry changes kept in the changelog
+pub const DEFAULT_CHANGELOG_SIZE: usize = 16;
+
+/// A changed field of a tool definition
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FieldChange {
+	/// JSON pointer into the tool definition
+	pub path: String,
+	/// Value before the reload, absent if the field was added
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub before: Option<Value>,
+	/// Value after the reload, absent if the field was removed
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub after: Option<Value>,
+}
+
+/// A tool whose definition a reload changed
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ToolChange {
+	pub name: String,
+	pub fields: Vec<FieldChange>,
+}
+
+/// What one reload changed, tools sorted by name
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RegistryDiff {
+	pub added: Vec<String>,
+	pub removed: Vec<String>,
+	pub modified: Vec<ToolChange>,
+}
+
+impl RegistryDiff {
+	/// Compare the tools of two registries; no previous registry means every tool is new
+	pub fn between(previous: Option<&CompiledRegistry>, current: &CompiledRegistry) -> Self {
+		let definition = |registry: &CompiledRegistry, name: &str| {
+			registry
+				.get_tool(name)
+				.and_then(|t| serde_json::to_value(&t.def).ok())
+				.unwrap_or_default()
+		};
+		let names = |registry: &CompiledRegistry| -> BTreeSet<String> {
+			registry.tools().map(|t| t.def.name.clone()).collect()
+		};
+		let after = names(current);
+		let before = previous.map(names).unwrap_or_default();
+
+		let mut diff = Self {
+			added: after.difference(&before).cloned().collect(),
+			removed: before.difference(&after).cloned().collect(),
+			modified: Vec::new(),
+		};
+		if let Some(previous) = previous {
+			for name in before.intersection(&after) {
+				let mut fields = Vec::new();
+				diff_values(
+					String::new(),
+					&definition(previous, name),
+					&definition(current, name),
+					&mut fields,
+				);
+				if !fields.is_empty() {
+					diff.modified.push(ToolChange {
+						name: name.clone(),
+						fields,
+					});
+				}
+			}
+		}
+		diff
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.added.is_empty() && self.removed.is_empty() && self.modified.is_empty()
+	}
+}
+
+/// Descend into objects, and arrays that kept their length, to the values that differ
+fn diff_values(path: String, before: &Value, after: &Value, changes: &mut Vec<FieldChange>) {
+	match (before, after) {
+		_ if before == after => {},
+		(Value::Object(b), Value::Object(a)) => {
+			let keys: BTreeSet<&String> = b.keys().chain(a.keys()).collect();
+			for key in keys {
+				let child = format!("{}/{}", path, escape_pointer_token(key));
+				match (b.get(key), a.get(key)) {
+					(Some(bv), Some(av)) => diff_values(child, bv, av, changes),
+					(bv, av) => changes.push(FieldChange {
+						path: child,
+						before: bv.cloned(),
+						after: av.cloned(),
+					}),
+				}
+			}
+		},
+		(Value::Array(b), Value::Array(a)) if b.len() == a.len() => {
+			for (i, (bv, av)) in b.iter().zip(a).enumerate() {
+				diff_values(format!("{}/{}", path, i), bv, av, changes);
+			}
+		},
+		_ => changes.push(FieldChange {
+			path,
+			before: Some(before.clone()),
+			after: Some(after.clone()),
+		}),
+	}
+}
+
+/// A reload that changed the registry
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RegistryChange {
+	/// Reload time (milliseconds since the Unix epoch)
+	pub at_ms: u64,
+	pub trigger: ReloadTrigger,
+	#[serde(flatten)]
+	pub diff: RegistryDiff,
+}
+
+/// Recent registry changes, newest first, kept across reloads
+#[derive(Debug)]
+pub struct RegistryChangelog {
+	capacity: usize,
+	changes: Mutex<VecDeque<RegistryChange>>,
+}
+
+impl Default for RegistryChangelog {
+	fn default() -> Self {
+		Self::new(DEFAULT_CHANGELOG_SIZE)
+	}
+}
+
+impl RegistryChangelog {
+	/// Create a changelog keeping the last `capacity` changes (0 only logs them)
+	pub fn new(capacity: usize) -> Self {
+		Self {
+			capacity,
+			changes: Mutex::new(VecDeque::new()),
+		}
+	}
+
+	/// Log what a reload changed and keep it, unless it changed nothing
+	pub fn record(&self, trigger: ReloadTrigger, diff: RegistryDiff) {
+		if diff.is_empty() {
+			debug!(target: "virtual_tools", trigger = trigger.as_str(), "Registry reload changed nothing");
+			return;
+		}
+		info!(
+			target: "virtual_tools",
+			trigger = trigger.as_str(),
+			added = ?diff.added,
+			removed = ?diff.removed,
+			modified = ?diff.modified.iter().map(|t| t.name.as_str()).collect::<Vec<_>>(),
+			"Registry tools changed"
+		);
+		for tool in &diff.modified {
+			for field in &tool.fields {
+				debug!(
+					target: "virtual_tools",
+					tool = %tool.name,
+					path = %field.path,
+					before = ?field.before,
+					after = ?field.after,
+					"Registry tool field changed"
+				);
+			}
+		}
+		if self.capacity == 0 {
+			return;
+		}
+		let at_ms = SystemTime::now()
+			.duration_since(UNIX_EPOCH)
+			.map(|d| d.as_millis() as u64)
+			.unwrap_or_default();
+		let mut changes = self.changes.lock();
+		if changes.len() == self.capacity {
+			changes.pop_back();
+		}
+		changes.push_front(RegistryChange {
+			at_ms,
+			trigger,
+			diff,
+		});
+	}
+
+	/// Up to `limit` recent changes, newest first
+	pub fn recent(&self, limit: usize) -> Vec<RegistryChange> {
+		self.changes.lock().iter().take(limit).cloned().collect()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use serde_json::json;
+
+	use super::*;
+	use crate::mcp::registry::Registry;
+
+	fn compile(tools: Value) -> CompiledRegistry {
+		let registry: Registry =
+			serde_json::from_value(json!({"schemaVersion": "1.0", "tools": tools})).unwrap();
+		CompiledRegistry::compile(registry).unwrap()
+	}
+
+	#[test]
+	fn test_diff_between_registries() {
+		let before = compile(json!([
+			{"name": "get_weather", "source": {"target": "weather", "tool": "fetch", "defaults": {"units": "metric"}}},
+			{"name": "get_alerts", "source": {"target": "weather", "tool": "alerts"}},
+			{"name": "search", "description": "Search", "source": {"target": "web", "tool": "search"}},
+		]));
+		let after = compile(json!([
+			{"name": "get_weather", "source": {"target": "weather", "tool": "fetch", "defaults": {"units": "imperial", "days": 3}}},
+			{"name": "get_forecast", "source": {"target": "weather", "tool": "forecast"}},
+			{"name": "search", "description": "Search", "source": {"target": "web", "tool": "search"}},
+		]));
+
+		let diff = RegistryDiff::between(Some(&before), &after);
+		assert_eq!(diff.added, vec!["get_forecast"]);
+		assert_eq!(diff.removed, vec!["get_alerts"]);
+		assert_eq!(diff.modified.len(), 1);
+		assert_eq!(diff.modified[0].name, "get_weather");
+		assert_eq!(
+			diff.modified[0].fields,
+			vec![
+				FieldChange {
+					path: "/source/defaults/days".to_string(),
+					before: None,
+					after: Some(json!(3)),
+				},
+				FieldChange {
+					path: "/source/defaults/units".to_string(),
+					before: Some(json!("metric")),
+					after: Some(json!("imperial")),
+				},
+			]
+		);
+
+		assert!(RegistryDiff::between(Some(&after), &after).is_empty());
+		let initial = RegistryDiff::between(None, &after);
+		assert_eq!(initial.added, vec!["get_forecast", "get_weather", "search"]);
+		assert!(initial.modified.is_empty());
+	}
+
+	#[test]
+	fn test_changelog_keeps_recent_changes() {
+		let changelog = RegistryChangelog::new(2);
+		let added = |name: &str| RegistryDiff {
+			added: vec![name.to_string()],
+			..Default::default()
+		};
+		changelog.record(ReloadTrigger::Initial, added("a"));
+		changelog.record(ReloadTrigger::Poll, RegistryDiff::default());
+		changelog.record(ReloadTrigger::Poll, added("b"));
+		changelog.record(ReloadTrigger::FileWatch, added("c"));
+
+		let recent = changelog.recent(10);
+		assert_eq!(
+			recent
+				.iter()
+				.map(|c| c.diff.added[0].as_str())
+				.collect::<Vec<_>>(),
+			vec!["c", "b"]
+		);
+		assert_eq!(recent[0].trigger, ReloadTrigger::FileWatch);
+		assert_eq!(changelog.recent(1).len(), 1);
+
+		let json = serde_json::to_value(&recent[0]).unwrap();
+		assert_eq!(json["trigger"], "file_watch");
+		assert_eq!(json["added"], json!(["c"]));
+	}
+}
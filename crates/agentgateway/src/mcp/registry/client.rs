@@ -1,12 +1,16 @@
 // Registry client for fetching registry from file or HTTP sources
 
 use std::path::PathBuf;
+use std::sync::Arc;
 use std::time::Duration;
 
+use parking_lot::Mutex;
 use tracing::info;
 
 use super::error::RegistryError;
+use super::signature::{SignatureVerifier, signature_path};
 use super::types::Registry;
+use super::validation::find_unknown_fields;
 
 /// Source for registry data
 #[derive(Debug, Clone)]
@@ -20,6 +24,40 @@ pub enum RegistrySource {
 	},
 }
 
+/// A registry as fetched from its source, with the document it was parsed from
+#[derive(Debug, Clone)]
+pub struct FetchedRegistry {
+	pub registry: Registry,
+	/// The document exactly as the source served it, which snapshots persist verbatim
+	pub document: String,
+}
+
+impl FetchedRegistry {
+	/// A registry handed over already parsed, e.g. through the API, documented by its JSON
+	/// serialization
+	pub fn from_registry(registry: Registry) -> Result<Self, RegistryError> {
+		let document = serde_json::to_string(&registry)?;
+		Ok(Self { registry, document })
+	}
+}
+
+impl std::fmt::Display for RegistrySource {
+	/// Source location without credentials or query parameters, for logs and metrics
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			RegistrySource::File(path) => write!(f, "file://{}", path.display()),
+			RegistrySource::Http { url, .. } => {
+				let scheme = url.scheme_str().unwrap_or("http");
+				let host = url.host().unwrap_or_default();
+				match url.port_u16() {
+					Some(port) => write!(f, "{scheme}://{host}:{port}{}", url.path()),
+					None => write!(f, "{scheme}://{host}{}", url.path()),
+				}
+			},
+		}
+	}
+}
+
 /// Authentication configuration for HTTP sources
 #[derive(Debug, Clone)]
 pub enum AuthConfig {
@@ -45,11 +83,71 @@ impl AuthConfig {
 	}
 }
 
+/// TLS settings for HTTPS sources, all PEM encoded
+#[derive(Debug, Clone, Default)]
+pub struct TlsConfig {
+	/// Client certificate chain presented for mTLS
+	pub cert: Option<Vec<u8>>,
+	/// Private key of `cert`
+	pub key: Option<Vec<u8>>,
+	/// CA bundle trusted instead of the system roots
+	pub root: Option<Vec<u8>>,
+}
+
+/// Cache validators of the last document fetched from an HTTP source
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CacheValidators {
+	pub etag: Option<String>,
+	pub last_modified: Option<String>,
+}
+
+impl CacheValidators {
+	/// Read the validators a response carries
+	pub fn from_headers(headers: &http::HeaderMap) -> Self {
+		let header = |name| {
+			headers
+				.get(name)
+				.and_then(|v| v.to_str().ok())
+				.map(str::to_string)
+		};
+		Self {
+			etag: header(http::header::ETAG),
+			last_modified: header(http::header::LAST_MODIFIED),
+		}
+	}
+
+	/// Headers that let the source answer 304 Not Modified if the document hasn't changed
+	pub fn conditional_headers(&self) -> impl Iterator<Item = (http::HeaderName, &str)> {
+		[
+			(http::header::IF_NONE_MATCH, self.etag.as_deref()),
+			(
+				http::header::IF_MODIFIED_SINCE,
+				self.last_modified.as_deref(),
+			),
+		]
+		.into_iter()
+		.filter_map(|(name, value)| Some((name, value?)))
+	}
+}
+
 /// Client for fetching registry data
 #[derive(Debug, Clone)]
 pub struct RegistryClient {
 	source: RegistrySource,
 	refresh_interval: Duration,
+	/// Longest interval polling stretches to while an HTTP source is unchanged (optional)
+	max_refresh_interval: Option<Duration>,
+	/// Reject registry documents containing unrecognized fields
+	strict: bool,
+	/// Require documents to carry a detached signature by a trusted key (optional)
+	verifier: Option<SignatureVerifier>,
+	/// Validators of the last document fetched, shared by clones of the client
+	validators: Arc<Mutex<CacheValidators>>,
+	/// Custom TLS settings for HTTPS sources (optional)
+	tls: Option<TlsConfig>,
+	/// HTTP client for HTTP sources, reused across polls
+	#[cfg(feature = "testing")]
+	http: reqwest::Client,
 }
 
 impl RegistryClient {
@@ -58,9 +156,55 @@ impl RegistryClient {
 		Self {
 			source,
 			refresh_interval,
+			max_refresh_interval: None,
+			strict: false,
+			verifier: None,
+			validators: Default::default(),
+			tls: None,
+			#[cfg(feature = "testing")]
+			http: reqwest::Client::new(),
 		}
 	}
 
+	/// Builder: let the polling interval stretch up to `max` while the source is unchanged
+	pub fn with_max_refresh_interval(mut self, max: Duration) -> Result<Self, RegistryError> {
+		if max < self.refresh_interval {
+			return Err(RegistryError::InvalidSource(format!(
+				"max refresh interval {:?} is shorter than the refresh interval {:?}",
+				max, self.refresh_interval
+			)));
+		}
+		self.max_refresh_interval = Some(max);
+		Ok(self)
+	}
+
+	/// Builder: enable strict parsing (unknown fields become errors)
+	pub fn with_strict(mut self, strict: bool) -> Self {
+		self.strict = strict;
+		self
+	}
+
+	/// Builder: reject documents whose detached signature (`<source>.sig`) doesn't verify
+	pub fn with_signature_verifier(mut self, verifier: SignatureVerifier) -> Self {
+		self.verifier = Some(verifier);
+		self
+	}
+
+	/// Builder: connect to HTTPS sources with a custom CA bundle and/or a client certificate
+	pub fn with_tls(mut self, tls: TlsConfig) -> Result<Self, RegistryError> {
+		if tls.cert.is_some() != tls.key.is_some() {
+			return Err(RegistryError::InvalidSource(
+				"TLS client certificate and key must be set together".to_string(),
+			));
+		}
+		#[cfg(feature = "testing")]
+		{
+			self.http = http_client(&tls)?;
+		}
+		self.tls = Some(tls);
+		Ok(self)
+	}
+
 	/// Create a registry client from a source URI string
 	pub fn from_uri(
 		uri: &str,
@@ -95,64 +239,101 @@ impl RegistryClient {
 		self.refresh_interval
 	}
 
+	/// Get the longest interval polling stretches to, if polling adapts
+	pub fn max_refresh_interval(&self) -> Option<Duration> {
+		self.max_refresh_interval
+	}
+
+	/// Check if strict parsing is enabled
+	pub fn is_strict(&self) -> bool {
+		self.strict
+	}
+
+	/// Get the custom TLS settings for HTTPS sources
+	pub fn tls(&self) -> Option<&TlsConfig> {
+		self.tls.as_ref()
+	}
+
+	/// Get the cache validators of the last document fetched from an HTTP source
+	pub fn cache_validators(&self) -> CacheValidators {
+		self.validators.lock().clone()
+	}
+
 	/// Fetch the registry from the configured source
-	pub async fn fetch(&self) -> Result<Registry, RegistryError> {
+	pub async fn fetch(&self) -> Result<FetchedRegistry, RegistryError> {
 		match &self.source {
 			RegistrySource::File(path) => self.fetch_from_file(path).await,
-			RegistrySource::Http { url, auth } => self.fetch_from_http(url, auth.as_ref()).await,
+			RegistrySource::Http { url, auth } => self
+				.fetch_from_http(url, auth.as_ref(), false)
+				.await?
+				.ok_or_else(|| RegistryError::FetchError("unexpected 304 Not Modified".to_string())),
+		}
+	}
+
+	/// Fetch the registry, or `None` if an HTTP source reports it unchanged since the last
+	/// fetch
+	///
+	/// HTTP requests carry `If-None-Match` / `If-Modified-Since` from the last response, so
+	/// polling a large registry that hasn't changed costs neither a download nor a reparse.
+	pub async fn fetch_if_changed(&self) -> Result<Option<FetchedRegistry>, RegistryError> {
+		match &self.source {
+			RegistrySource::File(path) => self.fetch_from_file(path).await.map(Some),
+			RegistrySource::Http { url, auth } => self.fetch_from_http(url, auth.as_ref(), true).await,
 		}
 	}
 
 	/// Fetch registry from a local file
-	async fn fetch_from_file(&self, path: &PathBuf) -> Result<Registry, RegistryError> {
+	async fn fetch_from_file(&self, path: &PathBuf) -> Result<FetchedRegistry, RegistryError> {
 		info!(target: "virtual_tools", "Loading registry from file: {}", path.display());
 		let content = fs_err::tokio::read_to_string(path).await?;
-		let registry: Registry = serde_json::from_str(&content)?;
+		if let Some(verifier) = &self.verifier {
+			let signature = fs_err::tokio::read_to_string(signature_path(path)).await?;
+			verifier.verify(content.as_bytes(), &signature)?;
+		}
+		let registry = parse_registry(&content, self.strict)?;
 		info!(target: "virtual_tools", "Loaded {} tools from registry file", registry.len());
-		Ok(registry)
+		Ok(FetchedRegistry {
+			registry,
+			document: content,
+		})
 	}
 
-	/// Fetch registry from HTTP(S) URL
+	/// Fetch registry from HTTP(S) URL, conditionally on it having changed if `conditional`
 	#[cfg(feature = "testing")]
 	async fn fetch_from_http(
 		&self,
 		url: &http::Uri,
 		auth: Option<&AuthConfig>,
-	) -> Result<Registry, RegistryError> {
+		conditional: bool,
+	) -> Result<Option<FetchedRegistry>, RegistryError> {
 		info!(target: "virtual_tools", "Fetching registry from HTTP: {}", url);
 
-		// Build the request
-		let client = reqwest::Client::new();
-		let mut request = client.get(url.to_string());
-
-		// Add authentication if configured
-		if let Some(auth_config) = auth {
-			request = request.header("Authorization", auth_config.to_header_value());
+		let client = &self.http;
+		let validators = conditional.then(|| self.cache_validators());
+		let response = get(client, url, auth, validators.as_ref()).await?;
+		if response.status() == http::StatusCode::NOT_MODIFIED {
+			info!(target: "virtual_tools", "Registry not modified since the last fetch");
+			return Ok(None);
 		}
-
-		// Execute the request
-		let response = request
-			.send()
-			.await
-			.map_err(|e| RegistryError::FetchError(format!("HTTP request failed: {}", e)))?;
-
-		// Check status
-		if !response.status().is_success() {
-			return Err(RegistryError::FetchError(format!(
-				"HTTP request failed with status: {}",
-				response.status()
-			)));
+		let validators = CacheValidators::from_headers(response.headers());
+		let body = read_body(response).await?;
+
+		// Verify the document before anything in it is trusted
+		if let Some(verifier) = &self.verifier {
+			let signature_url = super::signature::signature_url(url)?;
+			let signature = read_body(get(client, &signature_url, auth, None).await?).await?;
+			verifier.verify(&body, &String::from_utf8_lossy(&signature))?;
 		}
 
-		// Parse response body
-		let body = response
-			.text()
-			.await
-			.map_err(|e| RegistryError::FetchError(format!("Failed to read response body: {}", e)))?;
-
-		let registry: Registry = serde_json::from_str(&body)?;
+		let body = String::from_utf8(body)
+			.map_err(|e| RegistryError::FetchError(format!("Registry is not valid UTF-8: {}", e)))?;
+		let registry = parse_registry(&body, self.strict)?;
 		info!(target: "virtual_tools", "Fetched {} tools from registry URL", registry.len());
-		Ok(registry)
+		*self.validators.lock() = validators;
+		Ok(Some(FetchedRegistry {
+			registry,
+			document: body,
+		}))
 	}
 
 	/// Fetch registry from HTTP(S) URL (stub when testing feature is not enabled)
@@ -161,7 +342,8 @@ impl RegistryClient {
 		&self,
 		url: &http::Uri,
 		_auth: Option<&AuthConfig>,
-	) -> Result<Registry, RegistryError> {
+		_conditional: bool,
+	) -> Result<Option<FetchedRegistry>, RegistryError> {
 		Err(RegistryError::FetchError(format!(
 			"HTTP registry fetching requires the 'testing' feature: {}",
 			url
@@ -182,6 +364,86 @@ impl RegistryClient {
 	}
 }
 
+/// Build an HTTP client on the gateway's backend TLS stack
+#[cfg(feature = "testing")]
+fn http_client(tls: &TlsConfig) -> Result<reqwest::Client, RegistryError> {
+	let backend_tls = crate::http::backendtls::ResolvedBackendTLS {
+		cert: tls.cert.clone(),
+		key: tls.key.clone(),
+		root: tls.root.clone(),
+		..Default::default()
+	}
+	.try_into()
+	.map_err(|e| RegistryError::InvalidSource(format!("invalid TLS configuration: {e}")))?;
+	reqwest::Client::builder()
+		.use_preconfigured_tls(Arc::unwrap_or_clone(backend_tls.base_config().config))
+		.build()
+		.map_err(|e| RegistryError::InvalidSource(format!("failed to build HTTP client: {e}")))
+}
+
+/// GET a URL from an HTTP source, conditionally if `validators` are given
+///
+/// Returns the response if it succeeded or, for a conditional request, was 304 Not Modified.
+#[cfg(feature = "testing")]
+async fn get(
+	client: &reqwest::Client,
+	url: &http::Uri,
+	auth: Option<&AuthConfig>,
+	validators: Option<&CacheValidators>,
+) -> Result<reqwest::Response, RegistryError> {
+	let mut request = client.get(url.to_string());
+
+	// Add authentication if configured
+	if let Some(auth_config) = auth {
+		request = request.header("Authorization", auth_config.to_header_value());
+	}
+	for (name, value) in validators.into_iter().flat_map(|v| v.conditional_headers()) {
+		request = request.header(name, value);
+	}
+
+	let response = request
+		.send()
+		.await
+		.map_err(|e| RegistryError::FetchError(format!("HTTP request failed: {}", e)))?;
+
+	let not_modified = validators.is_some() && response.status() == http::StatusCode::NOT_MODIFIED;
+	if !response.status().is_success() && !not_modified {
+		return Err(RegistryError::FetchError(format!(
+			"HTTP request for {} failed with status: {}",
+			url.path(),
+			response.status()
+		)));
+	}
+	Ok(response)
+}
+
+#[cfg(feature = "testing")]
+async fn read_body(response: reqwest::Response) -> Result<Vec<u8>, RegistryError> {
+	let body = response
+		.bytes()
+		.await
+		.map_err(|e| RegistryError::FetchError(format!("Failed to read response body: {}", e)))?;
+	Ok(body.to_vec())
+}
+
+/// Parse a registry document
+///
+/// In strict mode, fields the registry model does not recognize (e.g. a typo
+/// like `hideFeilds`) are reported as errors instead of being silently ignored.
+pub fn parse_registry(content: &str, strict: bool) -> Result<Registry, RegistryError> {
+	if !strict {
+		return Ok(serde_json::from_str(content)?);
+	}
+
+	let raw: serde_json::Value = serde_json::from_str(content)?;
+	let (registry, unknown) = find_unknown_fields(&raw)?;
+	if !unknown.is_empty() {
+		let messages: Vec<String> = unknown.iter().map(|e| e.to_string()).collect();
+		return Err(RegistryError::SchemaValidation(messages.join("; ")));
+	}
+	Ok(registry)
+}
+
 /// Parse a duration string like "5m", "30s", "1h"
 pub fn parse_duration(s: &str) -> Result<Duration, RegistryError> {
 	let s = s.trim();
@@ -229,6 +491,149 @@ pub fn parse_duration(s: &str) -> Result<Duration, RegistryError> {
 mod tests {
 	use super::*;
 
+	#[test]
+	fn test_tls_requires_cert_and_key() {
+		let client = || {
+			RegistryClient::from_uri(
+				"https://registry.internal/tools.json",
+				Duration::from_secs(60),
+				None,
+			)
+			.unwrap()
+		};
+		let cert_only = TlsConfig {
+			cert: Some(b"cert".to_vec()),
+			..Default::default()
+		};
+		assert!(matches!(
+			client().with_tls(cert_only),
+			Err(RegistryError::InvalidSource(_))
+		));
+	}
+
+	#[cfg(feature = "testing")]
+	#[tokio::test]
+	async fn test_https_source_uses_configured_certificates() {
+		use rcgen::{BasicConstraints, CertificateParams, IsCa, Issuer, KeyPair};
+		use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+		use crate::types::agent::{parse_cert, parse_key};
+
+		// One CA issues both the server's and the client's certificates
+		let ca_key = KeyPair::generate().unwrap();
+		let mut ca_params = CertificateParams::new(Vec::<String>::new()).unwrap();
+		ca_params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+		let ca_pem = ca_params.self_signed(&ca_key).unwrap().pem();
+		let issuer = Issuer::new(ca_params, ca_key);
+		let leaf = |name: &str| {
+			let key = KeyPair::generate().unwrap();
+			let cert = CertificateParams::new(vec![name.to_string()])
+				.unwrap()
+				.signed_by(&key, &issuer)
+				.unwrap();
+			(cert.pem(), key.serialize_pem())
+		};
+		let (server_cert, server_key) = leaf("127.0.0.1");
+		let (client_cert, client_key) = leaf("registry-client");
+
+		// An HTTPS server that only talks to clients with a certificate from the CA
+		let mut roots = rustls::RootCertStore::empty();
+		roots.add_parsable_certificates(parse_cert(ca_pem.as_bytes()).unwrap());
+		let provider = crate::transport::tls::provider();
+		let verifier = rustls::server::WebPkiClientVerifier::builder_with_provider(
+			Arc::new(roots),
+			provider.clone(),
+		)
+		.build()
+		.unwrap();
+		let config = rustls::ServerConfig::builder_with_provider(provider)
+			.with_protocol_versions(crate::transport::tls::ALL_TLS_VERSIONS)
+			.unwrap()
+			.with_client_cert_verifier(verifier)
+			.with_single_cert(
+				parse_cert(server_cert.as_bytes()).unwrap(),
+				parse_key(server_key.as_bytes()).unwrap(),
+			)
+			.unwrap();
+		let acceptor = tokio_rustls::TlsAcceptor::from(Arc::new(config));
+		let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+		let url = format!("https://{}/tools.json", listener.local_addr().unwrap());
+		tokio::spawn(async move {
+			while let Ok((stream, _)) = listener.accept().await {
+				let acceptor = acceptor.clone();
+				tokio::spawn(async move {
+					let Ok(mut tls) = acceptor.accept(stream).await else {
+						return;
+					};
+					let mut request = Vec::new();
+					let mut buf = [0u8; 1024];
+					while !request.ends_with(b"\r\n\r\n") {
+						match tls.read(&mut buf).await {
+							Ok(0) | Err(_) => return,
+							Ok(n) => request.extend_from_slice(&buf[..n]),
+						}
+					}
+					let body = r#"{"schemaVersion": "1.0", "tools": []}"#;
+					let response = format!(
+						"HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{body}",
+						body.len()
+					);
+					let _ = tls.write_all(response.as_bytes()).await;
+					let _ = tls.shutdown().await;
+				});
+			}
+		});
+
+		let fetch = |tls: TlsConfig| {
+			let client = RegistryClient::from_uri(&url, Duration::from_secs(60), None)
+				.unwrap()
+				.with_tls(tls)
+				.unwrap();
+			async move { client.fetch().await }
+		};
+		let trusted = TlsConfig {
+			cert: Some(client_cert.into_bytes()),
+			key: Some(client_key.into_bytes()),
+			root: Some(ca_pem.into_bytes()),
+		};
+		let fetched = fetch(trusted.clone()).await.unwrap();
+		assert!(fetched.registry.tools.is_empty());
+
+		// Without the CA bundle the server isn't trusted...
+		let no_root = TlsConfig {
+			root: None,
+			..trusted.clone()
+		};
+		assert!(fetch(no_root).await.is_err());
+		// ...and without the client certificate the server refuses the client
+		let no_cert = TlsConfig {
+			cert: None,
+			key: None,
+			..trusted
+		};
+		assert!(fetch(no_cert).await.is_err());
+	}
+
+	#[test]
+	fn test_cache_validators() {
+		let mut headers = http::HeaderMap::new();
+		headers.insert(http::header::ETAG, "\"v42\"".parse().unwrap());
+		let validators = CacheValidators::from_headers(&headers);
+		assert_eq!(validators.etag.as_deref(), Some("\"v42\""));
+		assert_eq!(
+			validators.conditional_headers().collect::<Vec<_>>(),
+			vec![(http::header::IF_NONE_MATCH, "\"v42\"")]
+		);
+
+		headers.insert(
+			http::header::LAST_MODIFIED,
+			"Wed, 21 Oct 2026 07:28:00 GMT".parse().unwrap(),
+		);
+		let validators = CacheValidators::from_headers(&headers);
+		assert_eq!(validators.conditional_headers().count(), 2);
+		assert_eq!(CacheValidators::default().conditional_headers().count(), 0);
+	}
+
 	#[test]
 	fn test_parse_duration() {
 		assert_eq!(parse_duration("5s").unwrap(), Duration::from_secs(5));
@@ -275,6 +680,20 @@ mod tests {
 		assert_eq!(client.file_path(), None);
 	}
 
+	#[test]
+	fn test_source_display_omits_query() {
+		let client = RegistryClient::from_uri(
+			"https://example.com:8443/registry.json?token=secret",
+			Duration::from_secs(300),
+			None,
+		)
+		.unwrap();
+		assert_eq!(
+			client.source().to_string(),
+			"https://example.com:8443/registry.json"
+		);
+	}
+
 	#[test]
 	fn test_from_uri_invalid() {
 		assert!(
@@ -287,6 +706,193 @@ mod tests {
 		);
 	}
 
+	#[test]
+	fn test_parse_registry_lenient_ignores_unknown_fields() {
+		let json = r#"{
+			"tools": [
+				{
+					"name": "get_weather",
+					"source": { "target": "weather", "tool": "fetch_weather", "hideFeilds": ["key"] }
+				}
+			]
+		}"#;
+
+		let registry = parse_registry(json, false).unwrap();
+		assert_eq!(registry.len(), 1);
+	}
+
+	#[test]
+	fn test_parse_registry_strict_rejects_unknown_fields() {
+		let json = r#"{
+			"tools": [
+				{
+					"name": "get_weather",
+					"source": { "target": "weather", "tool": "fetch_weather", "hideFeilds": ["key"] }
+				}
+			]
+		}"#;
+
+		let err = parse_registry(json, true).unwrap_err().to_string();
+		assert!(err.contains("/tools/0/source/hideFeilds"), "{err}");
+	}
+
+	#[test]
+	fn test_parse_registry_strict_nested_pattern() {
+		let json = r#"{
+			"tools": [
+				{
+					"name": "pipeline",
+					"spec": {
+						"pipeline": {
+							"steps": [
+								{
+									"id": "search",
+									"operation": { "tool": { "name": "web_search", "timeoutMs": 5 } }
+								}
+							]
+						}
+					}
+				}
+			]
+		}"#;
+
+		let err = parse_registry(json, true).unwrap_err().to_string();
+		assert!(
+			err.contains("/tools/0/spec/pipeline/steps/0/operation/tool/timeoutMs"),
+			"{err}"
+		);
+	}
+
+	#[test]
+	fn test_parse_registry_strict_accepts_known_fields() {
+		let json = r#"{
+			"schemaVersion": "1.0",
+			"tools": [
+				{
+					"name": "get_weather",
+					"description": null,
+					"source": {
+						"target": "weather",
+						"tool": "fetch_weather",
+						"defaults": { "units": { "anything": "goes" } },
+						"hideFields": ["key"]
+					},
+					"metadata": { "owner": "team-a" }
+				},
+				{
+					"name": "research_pipeline",
+					"spec": {
+						"pipeline": {
+							"steps": [
+								{
+									"id": "search",
+									"operation": { "tool": { "name": "web_search" } }
+								}
+							]
+						}
+					}
+				}
+			]
+		}"#;
+
+		let registry = parse_registry(json, true).unwrap();
+		assert_eq!(registry.len(), 2);
+	}
+
+	#[test]
+	fn test_parse_registry_strict_accepts_explicit_defaults() {
+		// Fields holding their defaults are left out when the registry is serialized back
+		let json = r#"{
+			"defaults": {},
+			"targets": {},
+			"tools": [
+				{
+					"name": "get_weather",
+					"source": {
+						"target": "weather",
+						"tool": "fetch_weather",
+						"fallbackTargets": [],
+						"callerDefaults": {},
+						"strictArgs": false
+					},
+					"outputTransform": { "mappings": {}, "variants": [] },
+					"priority": "normal"
+				},
+				{
+					"name": "cached_search",
+					"spec": {
+						"cache": {
+							"keyPaths": ["$.query"],
+							"inner": { "tool": { "name": "search" } },
+							"store": "results",
+							"ttlSeconds": 60,
+							"negative": { "ttlSeconds": 10, "errors": [] }
+						}
+					}
+				},
+				{
+					"name": "submit_order",
+					"spec": {
+						"deadLetter": {
+							"inner": { "tool": { "name": "submit" } },
+							"deadLetterTool": "park_order",
+							"sensitiveFields": []
+						}
+					}
+				}
+			]
+		}"#;
+
+		let registry = parse_registry(json, true).unwrap();
+		assert_eq!(registry.len(), 3);
+
+		let misspelled = json.replace("\"strictArgs\"", "\"stirctArgs\"");
+		let err = parse_registry(&misspelled, true).unwrap_err().to_string();
+		assert!(err.contains("/tools/0/source/stirctArgs"), "{err}");
+	}
+
+	#[test]
+	fn test_parse_registry_strict_flattened_fields() {
+		// Tools and scatter targets flatten what they invoke into their own fields
+		let json = r#"{
+			"tools": [
+				{
+					"name": "fan_out",
+					"priority": "normal",
+					"spec": {
+						"scatterGather": {
+							"targets": [{ "tool": "search", "weight": null }],
+							"aggregation": { "ops": [] }
+						}
+					}
+				}
+			]
+		}"#;
+		parse_registry(json, true).unwrap();
+
+		let misspelled = json
+			.replace("\"priority\"", "\"priorty\"")
+			.replace("\"weight\": null", "\"wieght\": 2");
+		let err = parse_registry(&misspelled, true).unwrap_err().to_string();
+		assert!(err.contains("/tools/0/priorty"), "{err}");
+		assert!(
+			err.contains("/tools/0/spec/scatterGather/targets/0/wieght"),
+			"{err}"
+		);
+	}
+
+	#[test]
+	fn test_from_uri_strict() {
+		let client = RegistryClient::from_uri(
+			"file:///path/to/registry.json",
+			Duration::from_secs(300),
+			None,
+		)
+		.unwrap();
+		assert!(!client.is_strict());
+		assert!(client.with_strict(true).is_strict());
+	}
+
 	#[test]
 	fn test_auth_config_bearer() {
 		let auth = AuthConfig::Bearer("my-token".to_string());
@@ -4,17 +4,25 @@
 // Uses two-pass compilation for order-independent reference resolution.
 
 use std::borrow::Cow;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::sync::Arc;
 
-use rmcp::model::Tool;
+use once_cell::sync::Lazy;
+use rmcp::model::{Tool, ToolAnnotations as McpToolAnnotations};
 use serde_json_path::JsonPath;
 
+use super::arguments::{ArgumentCheckPolicy, ArgumentProblem};
+use super::env_policy::EnvVarPolicy;
 use super::error::RegistryError;
-use super::patterns::{FieldSource, PatternSpec};
+use super::executor::{ExecutionError, FilterExecutor, ScriptExecutor, envelope_output_schema};
+use super::json_limits::JsonLimits;
+use super::locale::select_localized;
+use super::patterns::{CoerceType, FieldPredicate, FieldSource, PatternSpec, PredicateValue};
 use super::types::{
-	OutputTransform, Registry, SourceTool, ToolDefinition, ToolImplementation, VirtualToolDef,
+	CostModel, OutputTransform, Registry, SourceTool, TargetPolicy, ToolAnnotations, ToolDefinition,
+	ToolImplementation, VirtualToolDef,
 };
+use super::validation::escape_pointer_token;
 
 /// Maximum depth for reference resolution (safety limit)
 const MAX_REFERENCE_DEPTH: usize = 100;
@@ -24,8 +32,26 @@ const MAX_REFERENCE_DEPTH: usize = 100;
 pub struct CompiledRegistry {
 	/// Tool name -> compiled tool
 	tools_by_name: HashMap<String, Arc<CompiledTool>>,
-	/// (target, source_tool) -> virtual tool names (for reverse lookup, source tools only)
-	tools_by_source: HashMap<(String, String), Vec<String>>,
+	/// target -> source_tool -> virtual tool names (for reverse lookup, source tools only).
+	/// Nested so lookups can borrow both keys instead of allocating a tuple.
+	tools_by_source: HashMap<String, HashMap<String, Vec<String>>>,
+	/// target -> headers and `_meta` entries attached to every call to it
+	targets: HashMap<String, CompiledTargetPolicy>,
+	/// Bounds on backend responses parsed for output transforms
+	json_limits: JsonLimits,
+	/// What compositions do with tool arguments that don't match the tool's input schema
+	argument_checks: ArgumentCheckPolicy,
+	/// What happens to callers' arguments that don't match the tool's input schema
+	input_checks: ArgumentCheckPolicy,
+	/// Whether callers must confirm calls of tools annotated destructive
+	confirm_destructive: bool,
+}
+
+/// Compiled per-target headers and `_meta` entries, with environment variables resolved
+#[derive(Debug, Default)]
+pub struct CompiledTargetPolicy {
+	headers: Vec<(http::HeaderName, String)>,
+	meta: serde_json::Map<String, serde_json::Value>,
 }
 
 /// A compiled tool - either a source-based tool or a composition
@@ -66,6 +92,12 @@ pub struct CompiledComposition {
 	pub output_transform: Option<CompiledOutputTransform>,
 	/// Resolved tool references (name -> index in registry)
 	pub resolved_references: Vec<String>,
+	/// Input schema advertised to clients: the declared one, or one inferred from the
+	/// input paths the pattern reads
+	pub input_schema: Option<serde_json::Value>,
+	/// Output schema advertised to clients: the declared one, or one derived from the
+	/// output transform
+	pub output_schema: Option<serde_json::Value>,
 }
 
 /// Compiled output transform with pre-compiled JSONPath expressions
@@ -73,6 +105,37 @@ pub struct CompiledComposition {
 pub struct CompiledOutputTransform {
 	/// Field name -> compiled field source
 	pub fields: HashMap<String, CompiledFieldSource>,
+	/// Output fields in the order they are produced, with their array item projections
+	layout: Vec<TransformEntry>,
+	/// Variants tried in order before `fields`
+	variants: Vec<CompiledVariant>,
+	discriminator: Option<String>,
+}
+
+/// An output field and the `field[*].item` / `field[N].item` mappings projected onto its
+/// array elements
+#[derive(Debug)]
+struct TransformEntry {
+	name: String,
+	/// Key of the field's source in `fields`, e.g. `orgs[*].repos`
+	key: String,
+	projection: Option<Projection>,
+}
+
+#[derive(Debug)]
+struct Projection {
+	selector: ItemSelector,
+	/// Fields of each projected element, which may project further (`orgs[*].repos[*].name`)
+	entries: Vec<TransformEntry>,
+}
+
+/// Which elements of an array a projection applies to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ItemSelector {
+	/// `[*]`: every element, producing an array
+	Each,
+	/// `[N]`: a single element, producing an object (negative indexes count from the end)
+	Index(i64),
 }
 
 /// Compiled field source
@@ -93,7 +156,8 @@ pub enum CompiledFieldSource {
 	/// Template interpolation
 	Template {
 		template: String,
-		vars: HashMap<String, JsonPath>,
+		/// (`{name}` placeholder, path) pairs
+		vars: Vec<(String, JsonPath)>,
 	},
 	/// Concatenation
 	Concat {
@@ -102,6 +166,36 @@ pub enum CompiledFieldSource {
 	},
 	/// Nested mapping
 	Nested(Box<CompiledOutputTransform>),
+	/// Source chosen by a predicate
+	Conditional(Box<CompiledConditional>),
+	/// Type coercion of another source
+	Coerce {
+		source: Box<CompiledFieldSource>,
+		as_type: CoerceType,
+	},
+}
+
+/// Compiled conditional field source
+#[derive(Debug)]
+pub struct CompiledConditional {
+	when: CompiledPredicate,
+	then: CompiledFieldSource,
+	fallback: Option<CompiledFieldSource>,
+}
+
+/// Field predicate with a pre-compiled JSONPath
+#[derive(Debug)]
+struct CompiledPredicate {
+	field: JsonPath,
+	op: String,
+	value: PredicateValue,
+}
+
+/// Compiled output variant
+#[derive(Debug)]
+struct CompiledVariant {
+	when: CompiledPredicate,
+	transform: CompiledOutputTransform,
 }
 
 // =============================================================================
@@ -132,27 +226,54 @@ impl CompiledRegistry {
 	/// Pass 1: Index all tools by name (order-independent)
 	/// Pass 2: Compile each tool, resolving references
 	pub fn compile(registry: Registry) -> Result<Self, RegistryError> {
+		Self::compile_with_env_policy(registry, &EnvVarPolicy::Unrestricted)
+	}
+
+	/// Compile a registry, rejecting references to environment variables `env_policy`
+	/// doesn't allow
+	pub fn compile_with_env_policy(
+		registry: Registry,
+		env_policy: &EnvVarPolicy,
+	) -> Result<Self, RegistryError> {
+		if let Some(reason) = registry
+			.defaults
+			.resilience
+			.as_ref()
+			.and_then(|r| r.unsupported())
+		{
+			return Err(RegistryError::CompilationError(format!(
+				"defaults: resilience {}",
+				reason
+			)));
+		}
+
 		// Pass 1: Index all definitions by name
 		let mut defs_by_name: HashMap<String, ToolDefinition> = HashMap::new();
-		for tool_def in registry.tools {
+		for mut tool_def in registry.tools {
 			if defs_by_name.contains_key(&tool_def.name) {
 				return Err(RegistryError::DuplicateToolName(tool_def.name.clone()));
 			}
+			registry.defaults.apply(&mut tool_def);
+			expand_resilience(&mut tool_def)?;
+			validate_cost(&tool_def)?;
+			check_tool_env_vars(&tool_def, env_policy)?;
 			defs_by_name.insert(tool_def.name.clone(), tool_def);
 		}
+		check_composition_cycles(&defs_by_name)?;
 
 		// Pass 2: Compile each tool
 		let mut tools_by_name: HashMap<String, Arc<CompiledTool>> = HashMap::new();
-		let mut tools_by_source: HashMap<(String, String), Vec<String>> = HashMap::new();
+		let mut tools_by_source: HashMap<String, HashMap<String, Vec<String>>> = HashMap::new();
 
 		for (name, def) in &defs_by_name {
 			let compiled = CompiledTool::compile(def, &defs_by_name, 0)?;
 
 			// Index source-based tools by their source for reverse lookup
 			if let ToolImplementation::Source(ref source) = def.implementation {
-				let source_key = (source.target.clone(), source.tool.clone());
 				tools_by_source
-					.entry(source_key)
+					.entry(source.target.clone())
+					.or_default()
+					.entry(source.tool.clone())
 					.or_default()
 					.push(name.clone());
 			}
@@ -160,9 +281,23 @@ impl CompiledRegistry {
 			tools_by_name.insert(name.clone(), Arc::new(compiled));
 		}
 
+		let targets = registry
+			.targets
+			.iter()
+			.map(|(target, policy)| {
+				check_target_env_vars(target, policy, env_policy)?;
+				Ok((target.clone(), CompiledTargetPolicy::compile(policy)?))
+			})
+			.collect::<Result<_, RegistryError>>()?;
+
 		Ok(Self {
 			tools_by_name,
 			tools_by_source,
+			targets,
+			json_limits: JsonLimits::default(),
+			argument_checks: ArgumentCheckPolicy::default(),
+			input_checks: ArgumentCheckPolicy::default(),
+			confirm_destructive: false,
 		})
 	}
 
@@ -171,14 +306,80 @@ impl CompiledRegistry {
 		Self {
 			tools_by_name: HashMap::new(),
 			tools_by_source: HashMap::new(),
+			targets: HashMap::new(),
+			json_limits: JsonLimits::default(),
+			argument_checks: ArgumentCheckPolicy::default(),
+			input_checks: ArgumentCheckPolicy::default(),
+			confirm_destructive: false,
 		}
 	}
 
+	/// Builder: bound the backend responses parsed for output transforms
+	pub fn with_json_limits(mut self, limits: JsonLimits) -> Self {
+		self.json_limits = limits;
+		self
+	}
+
+	/// Bounds on backend responses parsed for output transforms
+	pub fn json_limits(&self) -> JsonLimits {
+		self.json_limits
+	}
+
+	/// Builder: choose what compositions do with arguments that don't match a tool's input
+	/// schema
+	pub fn with_argument_checks(mut self, policy: ArgumentCheckPolicy) -> Self {
+		self.argument_checks = policy;
+		self
+	}
+
+	/// What compositions do with arguments that don't match a tool's input schema
+	pub fn argument_checks(&self) -> ArgumentCheckPolicy {
+		self.argument_checks
+	}
+
+	/// Builder: choose what happens to callers' arguments that don't match the input schema
+	/// listed for the tool they call
+	pub fn with_input_checks(mut self, policy: ArgumentCheckPolicy) -> Self {
+		self.input_checks = policy;
+		self
+	}
+
+	/// What happens to callers' arguments that don't match the tool's input schema
+	pub fn input_checks(&self) -> ArgumentCheckPolicy {
+		self.input_checks
+	}
+
+	/// Builder: require callers to confirm calls of tools annotated destructive
+	pub fn with_destructive_confirmation(mut self, confirm: bool) -> Self {
+		self.confirm_destructive = confirm;
+		self
+	}
+
+	/// Whether a call of the named tool must be confirmed by the caller
+	pub fn requires_confirmation(&self, name: &str) -> bool {
+		self.confirm_destructive
+			&& self
+				.get_tool(name)
+				.and_then(|t| t.def.annotations.as_ref())
+				.is_some_and(|a| a.is_destructive())
+	}
+
+	/// Headers and `_meta` entries configured for calls to a target
+	pub fn target_policy(&self, target: &str) -> Option<&CompiledTargetPolicy> {
+		self.targets.get(target)
+	}
+
 	/// Look up tool by name
 	pub fn get_tool(&self, name: &str) -> Option<&Arc<CompiledTool>> {
 		self.tools_by_name.get(name)
 	}
 
+	/// A tool's description in the locale best matching an Accept-Language value, if the
+	/// tool defines one
+	pub fn localized_description(&self, name: &str, accept_language: &str) -> Option<&str> {
+		select_localized(&self.get_tool(name)?.def.descriptions, accept_language)
+	}
+
 	/// Check if a tool is a composition
 	pub fn is_composition(&self, name: &str) -> bool {
 		self
@@ -199,16 +400,15 @@ impl CompiledRegistry {
 
 	/// Check if a backend tool is virtualized
 	pub fn is_virtualized(&self, target: &str, tool: &str) -> bool {
-		self
-			.tools_by_source
-			.contains_key(&(target.to_string(), tool.to_string()))
+		self.get_virtual_names(target, tool).is_some()
 	}
 
 	/// Get virtual tool names for a given source tool
 	pub fn get_virtual_names(&self, target: &str, tool: &str) -> Option<&Vec<String>> {
 		self
 			.tools_by_source
-			.get(&(target.to_string(), tool.to_string()))
+			.get(target)
+			.and_then(|tools| tools.get(tool))
 	}
 
 	/// Transform backend tool list to virtual tool list
@@ -217,34 +417,32 @@ impl CompiledRegistry {
 	/// non-virtualized tools unchanged. Compositions are not affected by this.
 	pub fn transform_tools(&self, backend_tools: Vec<(String, Tool)>) -> Vec<(String, Tool)> {
 		let mut result = Vec::new();
-		let mut virtualized_sources: std::collections::HashSet<(String, String)> =
-			std::collections::HashSet::new();
 
 		// First, add all virtual tools that have matching sources
-		for ((target, source_tool), virtual_names) in &self.tools_by_source {
-			// Find the source tool in backend_tools
-			let source = backend_tools
-				.iter()
-				.find(|(t, tool)| t == target && tool.name.as_ref() == source_tool);
-
-			if let Some((_, source_tool_def)) = source {
-				virtualized_sources.insert((target.clone(), source_tool.clone()));
-
-				// Create virtual tools from this source
-				for vname in virtual_names {
-					if let Some(compiled) = self.tools_by_name.get(vname) {
-						if let Some(virtual_tool) = compiled.create_virtual_tool(source_tool_def) {
-							result.push((target.clone(), virtual_tool));
+		for (target, source_tools) in &self.tools_by_source {
+			for (source_tool, virtual_names) in source_tools {
+				// Find the source tool in backend_tools
+				let source = backend_tools
+					.iter()
+					.find(|(t, tool)| t == target && tool.name.as_ref() == source_tool);
+
+				if let Some((_, source_tool_def)) = source {
+					// Create virtual tools from this source
+					for vname in virtual_names {
+						if let Some(compiled) = self.tools_by_name.get(vname) {
+							if let Some(virtual_tool) = compiled.create_virtual_tool(source_tool_def) {
+								result.push((target.clone(), virtual_tool));
+							}
 						}
 					}
 				}
 			}
 		}
 
-		// Pass through non-virtualized tools
+		// Pass through non-virtualized tools (any source present in backend_tools was
+		// virtualized above)
 		for (target, tool) in backend_tools {
-			let source_key = (target.clone(), tool.name.to_string());
-			if !virtualized_sources.contains(&source_key) {
+			if !self.is_virtualized(&target, &tool.name) {
 				result.push((target, tool));
 			}
 		}
@@ -252,27 +450,37 @@ impl CompiledRegistry {
 		// Add compositions as synthetic tools
 		for (name, compiled) in &self.tools_by_name {
 			if compiled.is_composition() {
-				let output_schema = compiled
-					.def
-					.output_schema
-					.as_ref()
-					.and_then(|v| v.as_object().cloned())
-					.map(Arc::new);
+				let declared = compiled
+					.composition_info()
+					.and_then(|c| c.output_schema.as_ref());
+				let output_schema = if compiled.def.result_envelope {
+					envelope_output_schema(declared)
+						.as_object()
+						.cloned()
+						.map(Arc::new)
+				} else {
+					declared.and_then(|v| v.as_object().cloned()).map(Arc::new)
+				};
 
 				let composition_tool = Tool {
 					name: Cow::Owned(name.clone()),
 					title: None,
 					description: compiled.def.description.clone().map(Cow::Owned),
 					input_schema: Arc::new(
-						compiled
-							.def
-							.input_schema
-							.clone()
-							.and_then(|v| v.as_object().cloned())
-							.unwrap_or_default(),
+						compiled.with_examples(
+							compiled
+								.composition_info()
+								.and_then(|c| c.input_schema.as_ref())
+								.and_then(|v| v.as_object().cloned())
+								.unwrap_or_default(),
+						),
 					),
 					output_schema,
-					annotations: None,
+					annotations: compiled
+						.def
+						.annotations
+						.as_ref()
+						.map(|a| merge_annotations(a, None)),
 					icons: None,
 					meta: None,
 				};
@@ -319,7 +527,7 @@ impl CompiledRegistry {
 			.get_tool(virtual_name)
 			.ok_or_else(|| RegistryError::tool_not_found(virtual_name))?;
 
-		tool.transform_output(response)
+		tool.transform_output_with(response, &self.json_limits)
 	}
 
 	/// Get all tool names
@@ -327,11 +535,25 @@ impl CompiledRegistry {
 		self.tools_by_name.keys()
 	}
 
+	/// Iterate over all compiled tools
+	pub fn tools(&self) -> impl Iterator<Item = &Arc<CompiledTool>> {
+		self.tools_by_name.values()
+	}
+
 	/// Get number of tools
 	pub fn len(&self) -> usize {
 		self.tools_by_name.len()
 	}
 
+	/// Get number of compositions
+	pub fn composition_count(&self) -> usize {
+		self
+			.tools_by_name
+			.values()
+			.filter(|t| t.is_composition())
+			.count()
+	}
+
 	/// Check if registry is empty
 	pub fn is_empty(&self) -> bool {
 		self.tools_by_name.is_empty()
@@ -353,8 +575,46 @@ impl CompiledTool {
 			return Err(RegistryError::ReferenceDepthExceeded(def.name.clone()));
 		}
 
+		if let Some(idempotency) = &def.idempotency {
+			if matches!(def.implementation, ToolImplementation::Source(_)) {
+				return Err(RegistryError::CompilationError(format!(
+					"tool '{}': idempotency is only supported on compositions",
+					def.name
+				)));
+			}
+			if idempotency.ttl_seconds == 0 {
+				return Err(RegistryError::CompilationError(format!(
+					"tool '{}': idempotency ttlSeconds must be positive",
+					def.name
+				)));
+			}
+		}
+
 		let compiled = match &def.implementation {
 			ToolImplementation::Source(source) => {
+				if let Some(ref pagination) = source.pagination {
+					pagination
+						.validate()
+						.map_err(|e| RegistryError::CompilationError(format!("tool '{}': {e}", def.name)))?;
+				}
+				if let Some(ref polling) = source.job_polling {
+					if source.pagination.is_some() {
+						return Err(RegistryError::CompilationError(format!(
+							"tool '{}': jobPolling and pagination can't be combined",
+							def.name
+						)));
+					}
+					polling
+						.validate()
+						.map_err(|e| RegistryError::CompilationError(format!("tool '{}': {e}", def.name)))?;
+				}
+				let mut targets = HashSet::new();
+				if let Some(target) = source.targets().find(|target| !targets.insert(*target)) {
+					return Err(RegistryError::CompilationError(format!(
+						"tool '{}': target '{}' is listed more than once in its failover group",
+						def.name, target
+					)));
+				}
 				let output_transform = if let Some(ref transform) = def.output_transform {
 					Some(CompiledOutputTransform::compile(transform)?)
 				} else {
@@ -368,6 +628,21 @@ impl CompiledTool {
 				})
 			},
 			ToolImplementation::Spec(spec) => {
+				check_step_bindings(spec)
+					.map_err(|e| RegistryError::CompilationError(format!("tool '{}': {e}", def.name)))?;
+				check_predicates(spec)
+					.map_err(|e| RegistryError::CompilationError(format!("tool '{}': {e}", def.name)))?;
+				for call in spec.composition_calls() {
+					let problem = match all_defs.get(&call.name).map(|d| &d.implementation) {
+						Some(ToolImplementation::Spec(_)) => continue,
+						Some(ToolImplementation::Source(_)) => "is a virtual tool; call it with a tool step",
+						None => "is not in the registry",
+					};
+					return Err(RegistryError::CompilationError(format!(
+						"tool '{}' calls composition '{}', which {problem}",
+						def.name, call.name
+					)));
+				}
 				let output_transform = if let Some(ref transform) = def.output_transform {
 					Some(CompiledOutputTransform::compile(transform)?)
 				} else {
@@ -388,10 +663,30 @@ impl CompiledTool {
 					}
 				}
 
+				let input_schema = def
+					.input_schema
+					.clone()
+					.or_else(|| infer_input_schema(spec));
+				let output_schema = def.output_schema.clone().or_else(|| {
+					output_transform
+						.as_ref()
+						.map(CompiledOutputTransform::schema)
+				});
+
+				let mut spec = spec.clone();
+				for script in spec.scripts_mut() {
+					let module = ScriptExecutor::compile(script).map_err(|e| {
+						RegistryError::CompilationError(format!("tool '{}': script: {e}", def.name))
+					})?;
+					script.module = Some(Arc::new(module));
+				}
+
 				CompiledImplementation::Composition(CompiledComposition {
-					spec: spec.clone(),
+					spec,
 					output_transform,
 					resolved_references,
+					input_schema,
+					output_schema,
 				})
 			},
 		};
@@ -435,6 +730,15 @@ impl CompiledTool {
 		}
 	}
 
+	/// Input schema known without listing the backend: a composition's, or a virtual tool's
+	/// `inputSchema` override
+	pub fn known_input_schema(&self) -> Option<&serde_json::Value> {
+		match &self.compiled {
+			CompiledImplementation::Source(_) => self.def.input_schema.as_ref(),
+			CompiledImplementation::Composition(c) => c.input_schema.as_ref(),
+		}
+	}
+
 	/// Create a virtual tool from a source tool definition (for source-based tools only)
 	pub fn create_virtual_tool(&self, source: &Tool) -> Option<Tool> {
 		let source_tool = self.source_info()?;
@@ -457,9 +761,14 @@ impl CompiledTool {
 				.clone()
 				.map(Cow::Owned)
 				.or_else(|| source.description.clone()),
-			input_schema: self.compute_effective_schema(source, source_tool),
+			input_schema: Arc::new(
+				self.with_examples(self.compute_effective_schema(source, source_tool)),
+			),
 			output_schema,
-			annotations: source.annotations.clone(),
+			annotations: match &self.def.annotations {
+				Some(annotations) => Some(merge_annotations(annotations, source.annotations.as_ref())),
+				None => source.annotations.clone(),
+			},
 			icons: source.icons.clone(),
 			meta: source.meta.clone(),
 		})
@@ -470,11 +779,11 @@ impl CompiledTool {
 		&self,
 		source: &Tool,
 		source_tool: &CompiledSourceTool,
-	) -> Arc<serde_json::Map<String, serde_json::Value>> {
+	) -> serde_json::Map<String, serde_json::Value> {
 		// If we have a complete override schema, use it
 		if let Some(ref override_schema) = self.def.input_schema {
 			if let Some(obj) = override_schema.as_object() {
-				return Arc::new(obj.clone());
+				return obj.clone();
 			}
 		}
 
@@ -503,20 +812,91 @@ impl CompiledTool {
 			}
 		}
 
-		Arc::new(schema)
+		schema
+	}
+
+	/// Arguments a strict virtual tool refuses: hidden fields, and fields its effective input
+	/// schema doesn't list
+	///
+	/// The effective schema is the definition's `inputSchema`, or else `backend_schema` (the
+	/// source tool's, from the last list) minus hidden fields. Both kinds are reported as
+	/// unknown fields, so an agent can't tell a hidden field from a misspelled one.
+	///
+	/// Returns `None` for a strict tool without an effective schema, as before the backend has
+	/// been listed: what it accepts isn't known, so nothing can be let through.
+	pub fn disallowed_arguments(
+		&self,
+		args: &serde_json::Value,
+		backend_schema: Option<&serde_json::Map<String, serde_json::Value>>,
+	) -> Option<Vec<ArgumentProblem>> {
+		let Some(source) = self
+			.source_info()
+			.map(|s| &s.source)
+			.filter(|s| s.strict_args)
+		else {
+			return Some(Vec::new());
+		};
+		let schema = self
+			.def
+			.input_schema
+			.as_ref()
+			.and_then(|s| s.as_object())
+			.or(backend_schema)?;
+		let Some(fields) = args.as_object() else {
+			return Some(Vec::new());
+		};
+		let properties = schema.get("properties").and_then(|p| p.as_object());
+		let problems = fields
+			.keys()
+			.filter(|name| {
+				source.hide_fields.contains(*name) || !properties.is_some_and(|p| p.contains_key(*name))
+			})
+			.map(|name| ArgumentProblem {
+				pointer: format!("/{}", escape_pointer_token(name)),
+				message: "unknown field".to_string(),
+			})
+			.collect();
+		Some(problems)
+	}
+
+	/// Advertise the definition's example inputs in an input schema, unless it declares its own
+	fn with_examples(
+		&self,
+		mut schema: serde_json::Map<String, serde_json::Value>,
+	) -> serde_json::Map<String, serde_json::Value> {
+		if !self.def.examples.is_empty() && !schema.contains_key("examples") {
+			let inputs = self.def.examples.iter().map(|e| e.input.clone()).collect();
+			schema.insert("examples".to_string(), serde_json::Value::Array(inputs));
+		}
+		schema
 	}
 
 	/// Inject default values into arguments
 	pub fn inject_defaults(
+		&self,
+		args: serde_json::Value,
+	) -> Result<serde_json::Value, RegistryError> {
+		self.inject_defaults_for(args, None)
+	}
+
+	/// Inject default values into the arguments of a call from `caller` (a JWT subject)
+	///
+	/// The caller's own defaults win over the tool's, and arguments the caller passes win
+	/// over both.
+	pub fn inject_defaults_for(
 		&self,
 		mut args: serde_json::Value,
+		caller: Option<&str>,
 	) -> Result<serde_json::Value, RegistryError> {
-		let defaults = match &self.compiled {
-			CompiledImplementation::Source(s) => &s.source.defaults,
+		let (defaults, caller_defaults) = match &self.compiled {
+			CompiledImplementation::Source(s) => (
+				&s.source.defaults,
+				caller.and_then(|c| s.source.caller_defaults.get(c)),
+			),
 			CompiledImplementation::Composition(_) => return Ok(args), // No defaults for compositions
 		};
 
-		if defaults.is_empty() {
+		if defaults.is_empty() && caller_defaults.is_none() {
 			return Ok(args);
 		}
 
@@ -524,14 +904,14 @@ impl CompiledTool {
 			.as_object_mut()
 			.ok_or_else(|| RegistryError::SchemaValidation("arguments must be an object".into()))?;
 
-		for (key, value) in defaults {
+		for (key, value) in caller_defaults.into_iter().flatten().chain(defaults) {
 			// Don't override if already provided
 			if obj.contains_key(key) {
 				continue;
 			}
 
 			// Resolve environment variables in string values
-			let resolved_value = resolve_env_vars(value)?;
+			let resolved_value = resolve_env_vars(value, false)?;
 			obj.insert(key.clone(), resolved_value);
 		}
 
@@ -542,6 +922,15 @@ impl CompiledTool {
 	pub fn transform_output(
 		&self,
 		response: serde_json::Value,
+	) -> Result<serde_json::Value, RegistryError> {
+		self.transform_output_with(response, &JsonLimits::default())
+	}
+
+	/// Transform output using the output transform, parsing text responses within `limits`
+	pub fn transform_output_with(
+		&self,
+		response: serde_json::Value,
+		limits: &JsonLimits,
 	) -> Result<serde_json::Value, RegistryError> {
 		let transform = match &self.compiled {
 			CompiledImplementation::Source(s) => s.output_transform.as_ref(),
@@ -553,9 +942,9 @@ impl CompiledTool {
 		};
 
 		// Extract JSON if embedded in text
-		let json_response = extract_json_from_response(&response)?;
+		let json_response = extract_json_from_response(&response, limits)?;
 
-		transform.apply(&json_response)
+		transform.apply(json_response.as_ref())
 	}
 
 	/// Check if this tool has an output transform defined
@@ -583,83 +972,287 @@ impl CompiledTool {
 impl CompiledOutputTransform {
 	/// Compile an output transform
 	pub fn compile(transform: &OutputTransform) -> Result<Self, RegistryError> {
+		let mut compiled = Self::compile_mappings(&transform.mappings)?;
+		for variant in &transform.variants {
+			compiled.variants.push(CompiledVariant {
+				when: CompiledPredicate::compile(&variant.when)?,
+				transform: Self::compile_mappings(&variant.mappings)?,
+			});
+		}
+		if let Some(discriminator) = &transform.discriminator {
+			compiled.check_discriminator(discriminator)?;
+			compiled.discriminator = Some(discriminator.clone());
+		}
+		Ok(compiled)
+	}
+
+	/// Check that each branch maps the discriminator to its own string literal
+	fn check_discriminator(&self, discriminator: &str) -> Result<(), RegistryError> {
+		if self.variants.is_empty() {
+			return Err(RegistryError::CompilationError(format!(
+				"output discriminator '{discriminator}' is set, but the transform has no variants"
+			)));
+		}
+		let mut seen = Vec::new();
+		for (i, branch) in self.branches().enumerate() {
+			let value = match branch.fields.get(discriminator) {
+				Some(CompiledFieldSource::Literal(serde_json::Value::String(value))) => value,
+				_ => {
+					let branch = if i == 0 && !self.fields.is_empty() {
+						"mappings".to_string()
+					} else {
+						format!("variant {}", i - usize::from(!self.fields.is_empty()))
+					};
+					return Err(RegistryError::CompilationError(format!(
+						"{branch} must map output discriminator '{discriminator}' to a string literal"
+					)));
+				},
+			};
+			if seen.contains(&value) {
+				return Err(RegistryError::CompilationError(format!(
+					"output discriminator value '{value}' is used by more than one variant"
+				)));
+			}
+			seen.push(value);
+		}
+		Ok(())
+	}
+
+	/// The base mappings (unless empty) followed by each variant's
+	fn branches(&self) -> impl Iterator<Item = &CompiledOutputTransform> {
+		(!self.fields.is_empty())
+			.then_some(self)
+			.into_iter()
+			.chain(self.variants.iter().map(|v| &v.transform))
+	}
+
+	fn compile_mappings(mappings: &HashMap<String, FieldSource>) -> Result<Self, RegistryError> {
 		let mut fields = HashMap::new();
 
-		for (name, source) in &transform.mappings {
+		for (name, source) in mappings {
 			let compiled = CompiledFieldSource::compile(source)?;
 			fields.insert(name.clone(), compiled);
 		}
 
-		Ok(Self { fields })
+		// Split base fields from array item mappings (e.g., "orgs" vs "orgs[*].repos" vs
+		// "orgs[*].repos[*].name") once, rather than on every application
+		let mut layout: Vec<TransformEntry> = Vec::new();
+		let mut names: Vec<&String> = fields.keys().collect();
+		names.sort();
+		for field_name in names {
+			let mut entries = &mut layout;
+			let mut rest = field_name.as_str();
+			loop {
+				let (name, selector, item) = match split_item_mapping(rest) {
+					Some((name, selector, item)) => (name, Some(selector), item),
+					None => (rest, None, ""),
+				};
+				// Source key of this level: everything of the field name up to its name
+				let key_len = field_name.len() - rest.len() + name.len();
+				let idx = match entries.iter().position(|e| e.name == name) {
+					Some(idx) => idx,
+					None => {
+						entries.push(TransformEntry {
+							name: name.to_string(),
+							key: field_name[..key_len].to_string(),
+							projection: None,
+						});
+						entries.len() - 1
+					},
+				};
+				let Some(selector) = selector else {
+					break;
+				};
+				let projection = entries[idx].projection.get_or_insert(Projection {
+					selector,
+					entries: Vec::new(),
+				});
+				if projection.selector != selector {
+					return Err(RegistryError::CompilationError(format!(
+						"output field '{}' mixes [*] and [N] item mappings",
+						&field_name[..key_len]
+					)));
+				}
+				entries = &mut projection.entries;
+				rest = item;
+			}
+		}
+
+		Ok(Self {
+			fields,
+			layout,
+			variants: Vec::new(),
+			discriminator: None,
+		})
 	}
 
 	/// Apply the transform to a JSON value
 	///
-	/// Handles array item mappings like `repos[*].name` which project fields onto array items.
+	/// Handles array item mappings like `repos[*].name` which project fields onto array items,
+	/// `orgs[*].repos[*].name` which project through nested arrays, and `repos[0].name` which
+	/// projects a single element. When the transform has variants, the first one whose
+	/// predicate matches `input` is applied instead of the base mappings.
 	pub fn apply(&self, input: &serde_json::Value) -> Result<serde_json::Value, RegistryError> {
-		let mut result = serde_json::Map::new();
-
-		// Separate base fields from array item mappings (e.g., "repos" vs "repos[*].name")
-		let mut base_fields: HashMap<&str, &CompiledFieldSource> = HashMap::new();
-		let mut array_item_mappings: HashMap<&str, Vec<(&str, &CompiledFieldSource)>> = HashMap::new();
-
-		for (field_name, field_source) in &self.fields {
-			if let Some(bracket_pos) = field_name.find("[*].") {
-				// This is an array item mapping like "repos[*].name"
-				let base_array = &field_name[..bracket_pos];
-				let item_field = &field_name[bracket_pos + 4..]; // Skip "[*]."
-				array_item_mappings
-					.entry(base_array)
-					.or_default()
-					.push((item_field, field_source));
-			} else {
-				base_fields.insert(field_name.as_str(), field_source);
-			}
+		if let Some(variant) = self.variants.iter().find(|v| v.when.matches(input)) {
+			return variant.transform.apply(input);
+		}
+		if !self.variants.is_empty() && self.fields.is_empty() {
+			return Err(RegistryError::SchemaValidation(
+				"result matches no output variant".to_string(),
+			));
 		}
+		self.apply_entries(&self.layout, input)
+	}
 
-		// Process base fields first
-		for (field_name, field_source) in &base_fields {
-			let value = field_source.extract(input)?;
-
-			// Check if this base field has array item mappings
-			if let Some(item_mappings) = array_item_mappings.get(*field_name) {
-				// Transform each item in the array
-				if let serde_json::Value::Array(items) = value {
-					let transformed: Result<Vec<serde_json::Value>, RegistryError> = items
-						.iter()
-						.map(|item| {
-							let mut obj = serde_json::Map::new();
-							for (item_field_name, item_field_source) in item_mappings {
-								let item_value = item_field_source.extract(item)?;
-								obj.insert((*item_field_name).to_string(), item_value);
-							}
-							Ok(serde_json::Value::Object(obj))
-						})
-						.collect();
-					result.insert(
-						(*field_name).to_string(),
-						serde_json::Value::Array(transformed?),
-					);
+	fn apply_entries(
+		&self,
+		entries: &[TransformEntry],
+		input: &serde_json::Value,
+	) -> Result<serde_json::Value, RegistryError> {
+		let mut result = serde_json::Map::with_capacity(entries.len());
+
+		for entry in entries {
+			let Some(source) = self.fields.get(&entry.key) else {
+				// Array item mappings without a base array definition - skip with null
+				result.insert(entry.name.clone(), serde_json::Value::Null);
+				continue;
+			};
+			// Conditional sources may omit the field
+			let Some(value) = source.extract_optional(input)? else {
+				continue;
+			};
+			let value = match &entry.projection {
+				Some(projection) => self.project(projection, value)?,
+				None => value,
+			};
+			result.insert(entry.name.clone(), value);
+		}
+
+		Ok(serde_json::Value::Object(result))
+	}
+
+	fn project(
+		&self,
+		projection: &Projection,
+		value: serde_json::Value,
+	) -> Result<serde_json::Value, RegistryError> {
+		match (projection.selector, value) {
+			// Transform each item in the array
+			(ItemSelector::Each, serde_json::Value::Array(items)) => Ok(serde_json::Value::Array(
+				items
+					.iter()
+					.map(|item| self.apply_entries(&projection.entries, item))
+					.collect::<Result<_, _>>()?,
+			)),
+			// A wildcard path matching a single element is still projected as a one-element array
+			(ItemSelector::Each, item @ serde_json::Value::Object(_)) => {
+				Ok(serde_json::Value::Array(vec![
+					self.apply_entries(&projection.entries, &item)?,
+				]))
+			},
+			(ItemSelector::Index(index), serde_json::Value::Array(items)) => {
+				let index = if index < 0 {
+					items.len().checked_sub(index.unsigned_abs() as usize)
 				} else {
-					// Not an array, just insert as-is
-					result.insert((*field_name).to_string(), value);
+					Some(index as usize)
+				};
+				match index.and_then(|i| items.get(i)) {
+					Some(item) => self.apply_entries(&projection.entries, item),
+					None => Ok(serde_json::Value::Null),
 				}
-			} else {
-				result.insert((*field_name).to_string(), value);
-			}
+			},
+			(_, value) => Ok(value),
 		}
+	}
 
-		// Handle array item mappings for arrays that weren't explicitly defined as base fields
-		// (this would be an error case, but handle gracefully)
-		for (base_array, _) in &array_item_mappings {
-			if !base_fields.contains_key(*base_array) && !result.contains_key(*base_array) {
-				// Array item mappings without a base array definition - skip with null
-				result.insert((*base_array).to_string(), serde_json::Value::Null);
+	/// JSON Schema of the transform's output, derived from its field names and sources
+	///
+	/// Path sources can produce anything, so their fields accept any value; literal, template,
+	/// concat, coerce and nested sources contribute their types. Fields a conditional source
+	/// may omit are not required. A transform with variants produces a `oneOf` of its
+	/// branches, with an OpenAPI-style `discriminator` when one is set.
+	pub fn schema(&self) -> serde_json::Value {
+		if self.variants.is_empty() {
+			return self.entries_schema(&self.layout);
+		}
+		let branches: Vec<_> = self
+			.branches()
+			.map(|branch| {
+				let mut schema = branch.entries_schema(&branch.layout);
+				if let Some(discriminator) = &self.discriminator
+					&& let Some(CompiledFieldSource::Literal(value)) = branch.fields.get(discriminator)
+				{
+					schema["properties"][discriminator] = serde_json::json!({"const": value});
+				}
+				schema
+			})
+			.collect();
+		let mut schema = serde_json::json!({"type": "object", "oneOf": branches});
+		if let Some(discriminator) = &self.discriminator {
+			schema["discriminator"] = serde_json::json!({"propertyName": discriminator});
+		}
+		schema
+	}
+
+	fn entries_schema(&self, entries: &[TransformEntry]) -> serde_json::Value {
+		let mut properties = serde_json::Map::with_capacity(entries.len());
+		let mut required = Vec::new();
+		for entry in entries {
+			let source = self.fields.get(&entry.key);
+			let schema = match &entry.projection {
+				// Values that aren't arrays (or objects, for [*]) pass through projections
+				// unchanged; null is the one expected when the base field is missing
+				Some(Projection {
+					selector: ItemSelector::Each,
+					entries,
+				}) => serde_json::json!({
+					"type": ["array", "null"],
+					"items": self.entries_schema(entries),
+				}),
+				Some(Projection {
+					selector: ItemSelector::Index(_),
+					entries,
+				}) => {
+					let mut schema = self.entries_schema(entries);
+					schema["type"] = serde_json::json!(["object", "null"]);
+					schema
+				},
+				None => source
+					.map(CompiledFieldSource::schema)
+					.unwrap_or_else(|| serde_json::json!({})),
+			};
+			if !source.is_some_and(CompiledFieldSource::may_omit) {
+				required.push(serde_json::Value::String(entry.name.clone()));
 			}
+			properties.insert(entry.name.clone(), schema);
+		}
+		let mut schema = serde_json::json!({"type": "object", "properties": properties});
+		if !required.is_empty() {
+			schema["required"] = serde_json::Value::Array(required);
 		}
+		schema
+	}
+}
 
-		Ok(serde_json::Value::Object(result))
+/// Split an array item mapping like `repos[*].name` or `repos[0].name` into the array field,
+/// its selector and the item field, at the first selector
+fn split_item_mapping(field_name: &str) -> Option<(&str, ItemSelector, &str)> {
+	let mut search = 0;
+	while let Some(open) = field_name[search..].find('[').map(|i| i + search) {
+		let after = &field_name[open + 1..];
+		if let Some(close) = after.find("].") {
+			let inner = &after[..close];
+			let selector = match inner {
+				"*" => Some(ItemSelector::Each),
+				_ => inner.parse().ok().map(ItemSelector::Index),
+			};
+			if let Some(selector) = selector {
+				return Some((&field_name[..open], selector, &after[close + 2..]));
+			}
+		}
+		search = open + 1;
 	}
+	None
 }
 
 impl CompiledFieldSource {
@@ -687,11 +1280,11 @@ impl CompiledFieldSource {
 				Ok(CompiledFieldSource::Coalesce { paths, originals })
 			},
 			FieldSource::Template(t) => {
-				let mut vars = HashMap::new();
+				let mut vars = Vec::with_capacity(t.vars.len());
 				for (name, path) in &t.vars {
 					let jsonpath = JsonPath::parse(path)
 						.map_err(|e| RegistryError::invalid_jsonpath(path, e.to_string()))?;
-					vars.insert(name.clone(), jsonpath);
+					vars.push((format!("{{{}}}", name), jsonpath));
 				}
 				Ok(CompiledFieldSource::Template {
 					template: t.template.clone(),
@@ -711,11 +1304,90 @@ impl CompiledFieldSource {
 				})
 			},
 			FieldSource::Nested(nested) => {
-				let compiled = CompiledOutputTransform::compile(&OutputTransform {
-					mappings: nested.mappings.clone(),
-				})?;
+				let compiled =
+					CompiledOutputTransform::compile(&OutputTransform::new(nested.mappings.clone()))?;
 				Ok(CompiledFieldSource::Nested(Box::new(compiled)))
 			},
+			FieldSource::Conditional(c) => Ok(CompiledFieldSource::Conditional(Box::new(
+				CompiledConditional {
+					when: CompiledPredicate::compile(&c.when)?,
+					then: CompiledFieldSource::compile(&c.then)?,
+					fallback: c
+						.fallback
+						.as_ref()
+						.map(CompiledFieldSource::compile)
+						.transpose()?,
+				},
+			))),
+			FieldSource::Coerce(c) => Ok(CompiledFieldSource::Coerce {
+				source: Box::new(CompiledFieldSource::compile(&c.source)?),
+				as_type: c.as_type,
+			}),
+		}
+	}
+
+	/// JSON Schema of the values this source produces
+	pub fn schema(&self) -> serde_json::Value {
+		match self {
+			CompiledFieldSource::Path { .. } | CompiledFieldSource::Coalesce { .. } => {
+				serde_json::json!({})
+			},
+			CompiledFieldSource::Literal(value) => serde_json::json!({"type": json_type_name(value)}),
+			CompiledFieldSource::Template { .. } | CompiledFieldSource::Concat { .. } => {
+				serde_json::json!({"type": "string"})
+			},
+			CompiledFieldSource::Nested(nested) => nested.schema(),
+			CompiledFieldSource::Conditional(c) => {
+				let then = c.then.schema();
+				match &c.fallback {
+					Some(fallback) if fallback.schema() != then => serde_json::json!({}),
+					_ => then,
+				}
+			},
+			CompiledFieldSource::Coerce { source, as_type } => {
+				let scalar = serde_json::json!({"type": [as_type, "null"]});
+				// Arrays are coerced element-wise
+				match source.schema().get("type").and_then(|t| t.as_str()) {
+					Some("string" | "number" | "integer" | "boolean" | "null") => scalar,
+					_ => serde_json::json!({
+						"anyOf": [scalar.clone(), {"type": "array", "items": scalar}]
+					}),
+				}
+			},
+		}
+	}
+
+	/// Whether the source can leave its field out of the output
+	fn may_omit(&self) -> bool {
+		match self {
+			CompiledFieldSource::Conditional(c) => {
+				c.then.may_omit()
+					|| c
+						.fallback
+						.as_ref()
+						.is_none_or(CompiledFieldSource::may_omit)
+			},
+			CompiledFieldSource::Coerce { source, .. } => source.may_omit(),
+			_ => false,
+		}
+	}
+
+	/// Extract a value from input, or `None` if a conditional source omits the field
+	pub fn extract_optional(
+		&self,
+		input: &serde_json::Value,
+	) -> Result<Option<serde_json::Value>, RegistryError> {
+		match self {
+			CompiledFieldSource::Conditional(c) => match c.branch(input) {
+				Some(source) => source.extract_optional(input),
+				None => Ok(None),
+			},
+			CompiledFieldSource::Coerce { source, as_type } => Ok(
+				source
+					.extract_optional(input)?
+					.map(|value| as_type.coerce(value)),
+			),
+			source => source.extract(input).map(Some),
 		}
 	}
 
@@ -724,11 +1396,10 @@ impl CompiledFieldSource {
 		match self {
 			CompiledFieldSource::Path { jsonpath, .. } => {
 				let nodes = jsonpath.query(input);
-				let values: Vec<_> = nodes.iter().map(|v| (*v).clone()).collect();
-				Ok(match values.len() {
+				Ok(match nodes.len() {
 					0 => serde_json::Value::Null,
-					1 => values.into_iter().next().unwrap(),
-					_ => serde_json::Value::Array(values),
+					1 => nodes.first().cloned().unwrap_or_default(),
+					_ => serde_json::Value::Array(nodes.iter().map(|v| (*v).clone()).collect()),
 				})
 			},
 			CompiledFieldSource::Literal(value) => Ok(value.clone()),
@@ -743,15 +1414,9 @@ impl CompiledFieldSource {
 				}
 				Ok(serde_json::Value::Null)
 			},
-			CompiledFieldSource::Template { template, vars } => {
-				let mut result = template.clone();
-				for (name, path) in vars {
-					let nodes = path.query(input);
-					let value = nodes.iter().next().and_then(|v| v.as_str()).unwrap_or("");
-					result = result.replace(&format!("{{{}}}", name), value);
-				}
-				Ok(serde_json::Value::String(result))
-			},
+			CompiledFieldSource::Template { template, vars } => Ok(serde_json::Value::String(
+				render_template(template, vars, input),
+			)),
 			CompiledFieldSource::Concat { paths, separator } => {
 				let mut parts = Vec::new();
 				for path in paths {
@@ -765,68 +1430,667 @@ impl CompiledFieldSource {
 				Ok(serde_json::Value::String(parts.join(separator)))
 			},
 			CompiledFieldSource::Nested(transform) => transform.apply(input),
+			CompiledFieldSource::Conditional(c) => match c.branch(input) {
+				Some(source) => source.extract(input),
+				None => Ok(serde_json::Value::Null),
+			},
+			CompiledFieldSource::Coerce { source, as_type } => Ok(as_type.coerce(source.extract(input)?)),
 		}
 	}
 }
 
-// =============================================================================
-// Helper Functions
-// =============================================================================
+impl CompiledConditional {
+	/// The source to use for `input`, if any
+	fn branch(&self, input: &serde_json::Value) -> Option<&CompiledFieldSource> {
+		if self.when.matches(input) {
+			Some(&self.then)
+		} else {
+			self.fallback.as_ref()
+		}
+	}
+}
 
-/// Resolve ${ENV_VAR} patterns in a JSON value
-fn resolve_env_vars(value: &serde_json::Value) -> Result<serde_json::Value, RegistryError> {
-	match value {
-		serde_json::Value::String(s) => {
-			let resolved = resolve_env_string(s)?;
-			Ok(serde_json::Value::String(resolved))
-		},
-		serde_json::Value::Object(obj) => {
-			let mut new_obj = serde_json::Map::new();
-			for (k, v) in obj {
-				new_obj.insert(k.clone(), resolve_env_vars(v)?);
-			}
-			Ok(serde_json::Value::Object(new_obj))
-		},
-		serde_json::Value::Array(arr) => {
-			let new_arr: Result<Vec<_>, _> = arr.iter().map(resolve_env_vars).collect();
-			Ok(serde_json::Value::Array(new_arr?))
-		},
-		other => Ok(other.clone()),
+impl CompiledPredicate {
+	fn compile(predicate: &FieldPredicate) -> Result<Self, RegistryError> {
+		if !FilterExecutor::is_supported_op(&predicate.op) {
+			return Err(RegistryError::CompilationError(format!(
+				"unknown predicate operator '{}'",
+				predicate.op
+			)));
+		}
+		let field = JsonPath::parse(&predicate.field)
+			.map_err(|e| RegistryError::invalid_jsonpath(&predicate.field, e.to_string()))?;
+		Ok(Self {
+			field,
+			op: predicate.op.clone(),
+			value: predicate.value.clone(),
+		})
+	}
+
+	fn matches(&self, input: &serde_json::Value) -> bool {
+		let nodes = self.field.query(input);
+		FilterExecutor::evaluate_condition(&self.op, nodes.first(), &self.value).unwrap_or(false)
 	}
 }
 
-/// Resolve ${ENV_VAR} patterns in a string
-fn resolve_env_string(s: &str) -> Result<String, RegistryError> {
-	let mut result = s.to_string();
-	let re = regex::Regex::new(r"\$\{([^}]+)\}").expect("valid regex");
+// =============================================================================
+// CompiledTargetPolicy Implementation
+// =============================================================================
 
-	for cap in re.captures_iter(s) {
-		let var_name = &cap[1];
-		let value = std::env::var(var_name).map_err(|_| RegistryError::EnvVarNotFound {
-			name: var_name.to_string(),
-		})?;
-		result = result.replace(&cap[0], &value);
+impl CompiledTargetPolicy {
+	/// Compile a target policy, validating header names and resolving environment variables
+	pub fn compile(policy: &TargetPolicy) -> Result<Self, RegistryError> {
+		let mut headers = Vec::with_capacity(policy.headers.len());
+		for (name, value) in &policy.headers {
+			let name = http::HeaderName::try_from(name.as_str()).map_err(|e| {
+				RegistryError::CompilationError(format!("invalid target header '{name}': {e}"))
+			})?;
+			headers.push((name, resolve_env_string(value, true)?));
+		}
+		let meta = policy
+			.meta
+			.iter()
+			.map(|(k, v)| Ok((k.clone(), resolve_env_vars(v, true)?)))
+			.collect::<Result<_, RegistryError>>()?;
+		Ok(Self { headers, meta })
 	}
 
-	Ok(result)
-}
+	/// Headers to set, with `${claims.<name>}` placeholders filled from the caller's claims
+	pub fn headers<'a>(
+		&'a self,
+		claims: Option<&'a serde_json::Map<String, serde_json::Value>>,
+	) -> impl Iterator<Item = (&'a http::HeaderName, Cow<'a, str>)> + 'a {
+		self
+			.headers
+			.iter()
+			.map(move |(name, value)| (name, render_claims(value, claims)))
+	}
 
-/// Extract JSON from response (handles JSON embedded in text)
-fn extract_json_from_response(
-	response: &serde_json::Value,
-) -> Result<serde_json::Value, RegistryError> {
-	match response {
-		serde_json::Value::Object(_) | serde_json::Value::Array(_) => Ok(response.clone()),
-		serde_json::Value::String(s) => {
-			if let Ok(json) = serde_json::from_str(s) {
-				return Ok(json);
-			}
-			if let Some(json) = find_json_in_text(s) {
-				return Ok(json);
-			}
-			Ok(response.clone())
-		},
-		other => Ok(other.clone()),
+	/// `_meta` entries to merge, with `${claims.<name>}` placeholders filled from the caller's
+	/// claims
+	pub fn meta(
+		&self,
+		claims: Option<&serde_json::Map<String, serde_json::Value>>,
+	) -> serde_json::Map<String, serde_json::Value> {
+		self
+			.meta
+			.iter()
+			.map(|(k, v)| (k.clone(), render_claims_value(v, claims)))
+			.collect()
+	}
+
+	/// Check if the policy sets no `_meta` entries
+	pub fn has_meta(&self) -> bool {
+		!self.meta.is_empty()
+	}
+}
+
+const CLAIMS_PLACEHOLDER: &str = "${claims.";
+
+/// Replace `${claims.<name>}` placeholders with the caller's claims (missing claims render empty)
+fn render_claims<'a>(
+	template: &'a str,
+	claims: Option<&serde_json::Map<String, serde_json::Value>>,
+) -> Cow<'a, str> {
+	if !template.contains(CLAIMS_PLACEHOLDER) {
+		return Cow::Borrowed(template);
+	}
+	let mut out = String::with_capacity(template.len());
+	let mut rest = template;
+	while let Some(start) = rest.find(CLAIMS_PLACEHOLDER) {
+		out.push_str(&rest[..start]);
+		let after = &rest[start + CLAIMS_PLACEHOLDER.len()..];
+		let Some(end) = after.find('}') else {
+			out.push_str(&rest[start..]);
+			rest = "";
+			break;
+		};
+		match claims.and_then(|c| c.get(&after[..end])) {
+			Some(serde_json::Value::String(s)) => out.push_str(s),
+			Some(serde_json::Value::Null) | None => {},
+			Some(v) => out.push_str(&v.to_string()),
+		}
+		rest = &after[end + 1..];
+	}
+	out.push_str(rest);
+	Cow::Owned(out)
+}
+
+fn render_claims_value(
+	value: &serde_json::Value,
+	claims: Option<&serde_json::Map<String, serde_json::Value>>,
+) -> serde_json::Value {
+	match value {
+		serde_json::Value::String(s) => {
+			serde_json::Value::String(render_claims(s, claims).into_owned())
+		},
+		serde_json::Value::Array(items) => items
+			.iter()
+			.map(|v| render_claims_value(v, claims))
+			.collect(),
+		serde_json::Value::Object(map) => serde_json::Value::Object(
+			map
+				.iter()
+				.map(|(k, v)| (k.clone(), render_claims_value(v, claims)))
+				.collect(),
+		),
+		other => other.clone(),
+	}
+}
+
+// =============================================================================
+// Helper Functions
+// =============================================================================
+
+/// MCP annotations from a tool's registry hints, falling back to `base` for hints left unset
+fn merge_annotations(
+	annotations: &ToolAnnotations,
+	base: Option<&McpToolAnnotations>,
+) -> McpToolAnnotations {
+	let base = base.cloned().unwrap_or_default();
+	McpToolAnnotations {
+		read_only_hint: annotations.read_only_hint.or(base.read_only_hint),
+		destructive_hint: annotations.destructive_hint.or(base.destructive_hint),
+		idempotent_hint: annotations.idempotent_hint.or(base.idempotent_hint),
+		open_world_hint: annotations.open_world_hint.or(base.open_world_hint),
+		..base
+	}
+}
+
+/// Expand a composition's resilience shorthand into wrapper patterns around its spec
+fn expand_resilience(def: &mut ToolDefinition) -> Result<(), RegistryError> {
+	let Some(resilience) = def.resilience.take() else {
+		return Ok(());
+	};
+	match &mut def.implementation {
+		ToolImplementation::Spec(spec) => {
+			if let Some(reason) = resilience.unsupported() {
+				return Err(RegistryError::CompilationError(format!(
+					"tool '{}': resilience {}",
+					def.name, reason
+				)));
+			}
+			let inner = std::mem::replace(
+				spec,
+				PatternSpec::Pipeline(super::patterns::PipelineSpec { steps: vec![] }),
+			);
+			*spec = resilience.expand(&def.name, inner);
+			Ok(())
+		},
+		ToolImplementation::Source(_) => Err(RegistryError::CompilationError(format!(
+			"tool '{}': resilience applies to compositions; use transportRetry on source tools",
+			def.name
+		))),
+	}
+}
+
+/// Check a tool's cost model: source tools only, non-negative figures and a valid JSONPath
+fn validate_cost(def: &ToolDefinition) -> Result<(), RegistryError> {
+	let Some(cost) = &def.cost else {
+		return Ok(());
+	};
+	if matches!(def.implementation, ToolImplementation::Spec(_)) {
+		return Err(RegistryError::CompilationError(format!(
+			"tool '{}': cost applies to source tools; a composition costs the sum of its tool calls",
+			def.name
+		)));
+	}
+	let figures = match cost {
+		CostModel::Static(cost) => vec![*cost],
+		CostModel::Bytes(b) => vec![b.per_call, b.per_kib_in, b.per_kib_out],
+		CostModel::Reported(r) => {
+			JsonPath::parse(&r.path)
+				.map_err(|e| RegistryError::invalid_jsonpath(&r.path, e.to_string()))?;
+			vec![r.fallback]
+		},
+	};
+	if figures.iter().any(|f| !f.is_finite() || *f < 0.0) {
+		return Err(RegistryError::CompilationError(format!(
+			"tool '{}': cost must be a non-negative number",
+			def.name
+		)));
+	}
+	Ok(())
+}
+
+/// Reject compositions that end up calling themselves, through explicit composition calls or
+/// tool steps naming a composition
+///
+/// Nothing bounds the depth of nested executions, so such a composition would recurse until
+/// the call fails.
+fn check_composition_cycles(defs: &HashMap<String, ToolDefinition>) -> Result<(), RegistryError> {
+	fn calls<'a>(defs: &'a HashMap<String, ToolDefinition>, name: &str) -> Vec<&'a str> {
+		let Some(ToolImplementation::Spec(spec)) = defs.get(name).map(|d| &d.implementation) else {
+			return Vec::new();
+		};
+		let mut calls: Vec<&str> = spec
+			.referenced_tools()
+			.into_iter()
+			.filter(|callee| {
+				matches!(
+					defs.get(*callee).map(|d| &d.implementation),
+					Some(ToolImplementation::Spec(_))
+				)
+			})
+			.collect();
+		calls.sort_unstable();
+		calls.dedup();
+		calls
+	}
+
+	/// Depth-first search from `name`; `path` holds the compositions being visited
+	fn visit<'a>(
+		defs: &'a HashMap<String, ToolDefinition>,
+		name: &'a str,
+		path: &mut Vec<&'a str>,
+		done: &mut HashSet<&'a str>,
+	) -> Result<(), RegistryError> {
+		if let Some(start) = path.iter().position(|n| *n == name) {
+			let mut cycle = path[start..].to_vec();
+			cycle.push(name);
+			return Err(RegistryError::CompilationError(format!(
+				"composition cycle: {}",
+				cycle.join(" -> ")
+			)));
+		}
+		if !done.insert(name) {
+			return Ok(());
+		}
+		path.push(name);
+		for callee in calls(defs, name) {
+			visit(defs, callee, path, done)?;
+		}
+		path.pop();
+		Ok(())
+	}
+
+	let mut names: Vec<&str> = defs.keys().map(String::as_str).collect();
+	names.sort_unstable();
+	let mut done = HashSet::new();
+	for name in names {
+		visit(defs, name, &mut Vec::new(), &mut done)?;
+	}
+	Ok(())
+}
+
+/// Check the step bindings of every pipeline in a composition, including nested ones, the
+/// target sampling of every scatter-gather, and the bounds of durations and percentages
+fn check_step_bindings(spec: &PatternSpec) -> Result<(), String> {
+	for pattern in spec.patterns() {
+		match pattern {
+			PatternSpec::Pipeline(pipeline) => pipeline.check_bindings()?,
+			PatternSpec::ScatterGather(scatter) => scatter.check_sampling()?,
+			_ => {},
+		}
+		pattern.check_bounds()?;
+	}
+	Ok(())
+}
+
+/// Check the predicates and JSONPaths of every schema map, filter and assert step in a
+/// composition, including nested ones, so an unknown operator fails the registry rather
+/// than each call
+fn check_predicates(spec: &PatternSpec) -> Result<(), String> {
+	for pattern in spec.patterns() {
+		match pattern {
+			PatternSpec::SchemaMap(schema_map) => {
+				for (field, source) in &schema_map.mappings {
+					CompiledFieldSource::compile(source)
+						.map_err(|e| format!("schemaMap field '{field}': {e}"))?;
+				}
+			},
+			PatternSpec::Filter(filter) => {
+				CompiledPredicate::compile(&filter.predicate).map_err(|e| format!("filter: {e}"))?;
+			},
+			PatternSpec::Cache(cache) => {
+				// The cache compares stored results for equality only
+				for (name, predicate) in [("cacheIf", &cache.cache_if), ("staleIf", &cache.stale_if)] {
+					let Some(predicate) = predicate else {
+						continue;
+					};
+					if predicate.op != "eq" {
+						return Err(format!(
+							"cache {name}: only the eq operator is supported, got '{}'",
+							predicate.op
+						));
+					}
+					check_cache_path(&predicate.field).map_err(|e| format!("cache {name}: {e}"))?;
+				}
+				for path in &cache.key_paths {
+					check_cache_path(path).map_err(|e| format!("cache keyPaths: {e}"))?;
+				}
+				if let Some(kind) = cache
+					.negative
+					.iter()
+					.flat_map(|n| &n.errors)
+					.find(|kind| !ExecutionError::KINDS.contains(&kind.as_str()))
+				{
+					return Err(format!(
+						"cache negative.errors: unknown error kind '{kind}'"
+					));
+				}
+			},
+			_ => {},
+		}
+		for assert in pattern.assertions() {
+			CompiledPredicate::compile(&assert.predicate).map_err(|e| format!("assert: {e}"))?;
+		}
+	}
+	Ok(())
+}
+
+/// Cache paths are JSONPath when they start with `$`, and dot-separated otherwise
+fn check_cache_path(path: &str) -> Result<(), String> {
+	if path.starts_with('$') {
+		JsonPath::parse(path).map_err(|e| format!("invalid JSONPath '{path}': {e}"))?;
+	}
+	Ok(())
+}
+
+/// Infer a best-effort input schema from the JSONPaths a composition reads from its input
+///
+/// `$.query` and `$.filters.lang` become (nested) object properties and `$.ids[*]` an array.
+/// Types and required fields can't be known from paths alone, so properties accept any value.
+/// Returns None when the pattern reads no named input fields.
+fn infer_input_schema(spec: &PatternSpec) -> Option<serde_json::Value> {
+	let mut root = InferredSchema::default();
+	for path in spec.input_paths() {
+		root.insert(&input_path_segments(path));
+	}
+	if root.properties.is_empty() {
+		return None;
+	}
+	let mut schema = root.to_schema();
+	schema["type"] = serde_json::json!("object");
+	Some(schema)
+}
+
+/// Shape of the input implied by the paths read from it
+#[derive(Default)]
+struct InferredSchema {
+	properties: BTreeMap<String, InferredSchema>,
+	items: Option<Box<InferredSchema>>,
+}
+
+enum InputPathSegment<'a> {
+	Field(&'a str),
+	Item,
+}
+
+impl InferredSchema {
+	fn insert(&mut self, segments: &[InputPathSegment]) {
+		match segments.split_first() {
+			None => {},
+			Some((InputPathSegment::Field(name), rest)) => self
+				.properties
+				.entry(name.to_string())
+				.or_default()
+				.insert(rest),
+			Some((InputPathSegment::Item, rest)) => self.items.get_or_insert_default().insert(rest),
+		}
+	}
+
+	fn to_schema(&self) -> serde_json::Value {
+		if let Some(items) = &self.items {
+			return serde_json::json!({"type": "array", "items": items.to_schema()});
+		}
+		if self.properties.is_empty() {
+			return serde_json::json!({});
+		}
+		let properties: serde_json::Map<_, _> = self
+			.properties
+			.iter()
+			.map(|(name, schema)| (name.clone(), schema.to_schema()))
+			.collect();
+		serde_json::json!({"type": "object", "properties": properties})
+	}
+}
+
+/// Split a JSONPath into field and array item segments, stopping at syntax that doesn't
+/// name a fixed location (recursive descent, wildcards on objects)
+fn input_path_segments(path: &str) -> Vec<InputPathSegment<'_>> {
+	let mut segments = Vec::new();
+	let Some(mut rest) = path.trim().strip_prefix('$') else {
+		return segments;
+	};
+	loop {
+		if rest.starts_with("..") {
+			break;
+		}
+		if let Some(r) = rest.strip_prefix('.') {
+			let end = r.find(['.', '[']).unwrap_or(r.len());
+			let name = &r[..end];
+			if name.is_empty() || name == "*" {
+				break;
+			}
+			segments.push(InputPathSegment::Field(name));
+			rest = &r[end..];
+		} else if let Some(r) = rest.strip_prefix('[') {
+			let Some(end) = r.find(']') else {
+				break;
+			};
+			let selector = r[..end].trim();
+			let quoted = ['\'', '"']
+				.into_iter()
+				.find_map(|q| selector.strip_prefix(q)?.strip_suffix(q));
+			segments.push(match quoted {
+				Some(name) => InputPathSegment::Field(name),
+				None => InputPathSegment::Item,
+			});
+			rest = &r[end + 1..];
+		} else {
+			break;
+		}
+	}
+	segments
+}
+
+/// JSON Schema type name of a value
+fn json_type_name(value: &serde_json::Value) -> &'static str {
+	match value {
+		serde_json::Value::Null => "null",
+		serde_json::Value::Bool(_) => "boolean",
+		serde_json::Value::Number(n) if n.is_f64() => "number",
+		serde_json::Value::Number(_) => "integer",
+		serde_json::Value::String(_) => "string",
+		serde_json::Value::Array(_) => "array",
+		serde_json::Value::Object(_) => "object",
+	}
+}
+
+/// `${ENV_VAR}` placeholder
+static ENV_VAR: Lazy<regex::Regex> =
+	Lazy::new(|| regex::Regex::new(r"\$\{([^}]+)\}").expect("valid regex"));
+
+/// Names of the environment variables a string references
+///
+/// With `keep_claims`, `${claims.<name>}` placeholders aren't environment variables.
+fn env_var_refs(s: &str, keep_claims: bool) -> impl Iterator<Item = &str> {
+	ENV_VAR
+		.captures_iter(s)
+		.filter(move |cap| !(keep_claims && cap[0].starts_with(CLAIMS_PLACEHOLDER)))
+		.filter_map(|cap| cap.get(1))
+		.map(|name| name.as_str())
+}
+
+/// Reject environment variable references in a value that `policy` doesn't allow
+fn check_env_vars(
+	value: &serde_json::Value,
+	keep_claims: bool,
+	policy: &EnvVarPolicy,
+	location: &str,
+) -> Result<(), RegistryError> {
+	match value {
+		serde_json::Value::String(s) => check_env_string(s, keep_claims, policy, location),
+		serde_json::Value::Object(obj) => obj
+			.values()
+			.try_for_each(|v| check_env_vars(v, keep_claims, policy, location)),
+		serde_json::Value::Array(arr) => arr
+			.iter()
+			.try_for_each(|v| check_env_vars(v, keep_claims, policy, location)),
+		_ => Ok(()),
+	}
+}
+
+fn check_env_string(
+	s: &str,
+	keep_claims: bool,
+	policy: &EnvVarPolicy,
+	location: &str,
+) -> Result<(), RegistryError> {
+	match env_var_refs(s, keep_claims).find(|name| !policy.allows(name)) {
+		Some(name) => Err(RegistryError::EnvVarNotAllowed {
+			name: name.to_string(),
+			location: location.to_string(),
+		}),
+		None => Ok(()),
+	}
+}
+
+/// Check the environment variables a tool's source defaults, including every caller's
+/// defaults, reference against `policy`
+fn check_tool_env_vars(def: &ToolDefinition, policy: &EnvVarPolicy) -> Result<(), RegistryError> {
+	let ToolImplementation::Source(source) = &def.implementation else {
+		return Ok(());
+	};
+	let location = format!("tool '{}'", def.name);
+	for value in source.defaults.values() {
+		check_env_vars(value, false, policy, &location)?;
+	}
+	for (caller, defaults) in &source.caller_defaults {
+		let location = format!("tool '{}' (caller '{}')", def.name, caller);
+		for value in defaults.values() {
+			check_env_vars(value, false, policy, &location)?;
+		}
+	}
+	Ok(())
+}
+
+/// Check the environment variables a target policy references against `policy`
+fn check_target_env_vars(
+	target: &str,
+	target_policy: &TargetPolicy,
+	policy: &EnvVarPolicy,
+) -> Result<(), RegistryError> {
+	let location = format!("target '{target}'");
+	for value in target_policy.headers.values() {
+		check_env_string(value, true, policy, &location)?;
+	}
+	target_policy
+		.meta
+		.values()
+		.try_for_each(|v| check_env_vars(v, true, policy, &location))
+}
+
+/// Resolve ${ENV_VAR} patterns in a JSON value
+fn resolve_env_vars(
+	value: &serde_json::Value,
+	keep_claims: bool,
+) -> Result<serde_json::Value, RegistryError> {
+	match value {
+		serde_json::Value::String(s) => {
+			let resolved = resolve_env_string(s, keep_claims)?;
+			Ok(serde_json::Value::String(resolved))
+		},
+		serde_json::Value::Object(obj) => {
+			let mut new_obj = serde_json::Map::new();
+			for (k, v) in obj {
+				new_obj.insert(k.clone(), resolve_env_vars(v, keep_claims)?);
+			}
+			Ok(serde_json::Value::Object(new_obj))
+		},
+		serde_json::Value::Array(arr) => {
+			let new_arr: Result<Vec<_>, _> = arr
+				.iter()
+				.map(|v| resolve_env_vars(v, keep_claims))
+				.collect();
+			Ok(serde_json::Value::Array(new_arr?))
+		},
+		other => Ok(other.clone()),
+	}
+}
+
+/// Resolve ${ENV_VAR} patterns in a string.
+///
+/// With `keep_claims`, `${claims.<name>}` placeholders are left for per-request rendering.
+fn resolve_env_string(s: &str, keep_claims: bool) -> Result<String, RegistryError> {
+	// Most defaults are plain strings; skip the copy and regex scan for them
+	if !s.contains("${") {
+		return Ok(s.to_string());
+	}
+
+	let mut result = s.to_string();
+	for cap in ENV_VAR.captures_iter(s) {
+		let var_name = &cap[1];
+		if keep_claims && cap[0].starts_with(CLAIMS_PLACEHOLDER) {
+			continue;
+		}
+		let value = std::env::var(var_name).map_err(|_| RegistryError::EnvVarNotFound {
+			name: var_name.to_string(),
+		})?;
+		result = result.replace(&cap[0], &value);
+	}
+
+	Ok(result)
+}
+
+/// Render a template in a single pass over the template text.
+///
+/// Substituted values come from backend responses, so they are copied verbatim rather than
+/// scanned again for placeholders.
+fn render_template(
+	template: &str,
+	vars: &[(String, JsonPath)],
+	input: &serde_json::Value,
+) -> String {
+	// Only look this far ahead for a closing brace, so stray braces stay linear
+	let max_placeholder = vars.iter().map(|(p, _)| p.len()).max().unwrap_or(0);
+
+	let mut result = String::with_capacity(template.len());
+	let mut rest = template;
+	while let Some(start) = rest.find('{') {
+		result.push_str(&rest[..start]);
+		rest = &rest[start..];
+		let window = &rest.as_bytes()[..rest.len().min(max_placeholder)];
+		let var = window
+			.iter()
+			.position(|b| *b == b'}')
+			.and_then(|end| vars.iter().find(|(p, _)| p.as_str() == &rest[..=end]));
+		match var {
+			Some((placeholder, path)) => {
+				let nodes = path.query(input);
+				result.push_str(nodes.first().and_then(|v| v.as_str()).unwrap_or(""));
+				rest = &rest[placeholder.len()..];
+			},
+			None => {
+				result.push('{');
+				rest = &rest[1..];
+			},
+		}
+	}
+	result.push_str(rest);
+	result
+}
+
+/// Extract JSON from response (handles JSON embedded in text)
+fn extract_json_from_response<'a>(
+	response: &'a serde_json::Value,
+	limits: &JsonLimits,
+) -> Result<Cow<'a, serde_json::Value>, RegistryError> {
+	match response {
+		// Structured responses are used in place; cloning large documents dominated transform cost
+		serde_json::Value::Object(_) | serde_json::Value::Array(_) => Ok(Cow::Borrowed(response)),
+		serde_json::Value::String(s) => {
+			if let Some(json) = limits.parse(s)? {
+				return Ok(Cow::Owned(json));
+			}
+			if let Some(json) = find_json_in_text(s) {
+				limits.check_depth(&json)?;
+				return Ok(Cow::Owned(json));
+			}
+			Ok(Cow::Borrowed(response))
+		},
+		other => Ok(Cow::Borrowed(other)),
 	}
 }
 
@@ -927,8 +2191,10 @@ mod tests {
 	use serde_json::json;
 
 	use super::*;
+	use crate::mcp::registry::json_limits::JsonLimitError;
 	use crate::mcp::registry::patterns::{
-		AggregationOp, AggregationStrategy, PipelineSpec, PipelineStep, ScatterGatherSpec,
+		AggregationOp, AggregationStrategy, CompositionCall, ConstructBinding, DataBinding,
+		InputBinding, LiteralValue, MapEachSpec, PipelineSpec, PipelineStep, ScatterGatherSpec,
 		ScatterTarget, StepOperation, ToolCall,
 	};
 	use crate::mcp::registry::types::OutputField;
@@ -991,6 +2257,9 @@ mod tests {
 						name: "web_search".to_string(),
 					}),
 					input: None,
+					session_set: None,
+					retry: None,
+					cache: None,
 				}],
 			}),
 		);
@@ -1109,6 +2378,175 @@ mod tests {
 		assert!(props.get("debug_mode").is_none());
 	}
 
+	#[test]
+	fn test_strict_args_refuse_unlisted_fields() {
+		let def = ToolDefinition::source("get_weather", "weather", "fetch_weather");
+		let mut strict = def.clone();
+		if let ToolImplementation::Source(source) = &mut strict.implementation {
+			*source = source
+				.clone()
+				.with_hidden_fields(vec!["debug_mode".to_string()])
+				.with_strict_args();
+		}
+		let compiled = CompiledTool::compile(&strict, &HashMap::new(), 0).unwrap();
+		let source = create_source_tool("fetch_weather", "Weather");
+		let args = json!({"city": "Oslo", "debug_mode": true, "admin": true});
+		let pointers = |problems: Vec<ArgumentProblem>| {
+			let mut pointers: Vec<_> = problems.into_iter().map(|p| p.pointer).collect();
+			pointers.sort();
+			pointers
+		};
+
+		// Before the backend is listed nothing is known to be allowed
+		assert_eq!(compiled.disallowed_arguments(&args, None), None);
+		assert_eq!(
+			pointers(
+				compiled
+					.disallowed_arguments(&args, Some(&source.input_schema))
+					.unwrap()
+			),
+			vec!["/admin", "/debug_mode"]
+		);
+		assert_eq!(
+			compiled.disallowed_arguments(
+				&json!({"city": "Oslo", "units": "metric"}),
+				Some(&source.input_schema)
+			),
+			Some(vec![])
+		);
+
+		// Tools that aren't strict forward everything
+		let compiled = CompiledTool::compile(&def, &HashMap::new(), 0).unwrap();
+		assert_eq!(compiled.disallowed_arguments(&args, None), Some(vec![]));
+	}
+
+	#[test]
+	fn test_examples_in_schema() {
+		let json = r#"{
+			"schemaVersion": "1.0",
+			"tools": [
+				{
+					"name": "get_weather",
+					"source": {"target": "weather", "tool": "fetch_weather"},
+					"examples": [
+						{"description": "Metric units", "input": {"city": "Paris", "units": "metric"}},
+						{"input": {"city": "Seattle"}, "output": {"temp": 12}}
+					]
+				},
+				{
+					"name": "forecast",
+					"spec": {"pipeline": {"steps": [
+						{"id": "w", "operation": {"tool": {"name": "get_weather"}}}
+					]}},
+					"inputSchema": {"type": "object", "examples": [{"city": "Oslo"}]},
+					"examples": [{"input": {"city": "Rome"}}]
+				}
+			]
+		}"#;
+		let registry: Registry = serde_json::from_str(json).unwrap();
+		let compiled = CompiledRegistry::compile(registry).unwrap();
+
+		let source = create_source_tool("fetch_weather", "Weather");
+		let result = compiled.transform_tools(vec![("weather".to_string(), source)]);
+		let schema = |name: &str| {
+			let (_, tool) = result.iter().find(|(_, t)| t.name == name).unwrap();
+			tool.input_schema.get("examples").cloned()
+		};
+		assert_eq!(
+			schema("get_weather"),
+			Some(json!([{"city": "Paris", "units": "metric"}, {"city": "Seattle"}]))
+		);
+		// Examples declared in the schema itself win
+		assert_eq!(schema("forecast"), Some(json!([{"city": "Oslo"}])));
+	}
+
+	#[test]
+	fn test_annotations() {
+		let json = r#"{
+			"schemaVersion": "1.0",
+			"tools": [
+				{
+					"name": "drop_table",
+					"source": {"target": "db", "tool": "drop"},
+					"annotations": {"destructiveHint": true, "idempotentHint": true}
+				},
+				{
+					"name": "get_weather",
+					"source": {"target": "weather", "tool": "fetch_weather"}
+				},
+				{
+					"name": "forecast",
+					"spec": {"pipeline": {"steps": [
+						{"id": "w", "operation": {"tool": {"name": "get_weather"}}}
+					]}},
+					"annotations": {"readOnlyHint": true}
+				}
+			]
+		}"#;
+		let registry: Registry = serde_json::from_str(json).unwrap();
+		let compiled = CompiledRegistry::compile(registry).unwrap();
+
+		let mut drop = create_source_tool("drop", "Drop a table");
+		drop.annotations = Some(
+			McpToolAnnotations::new()
+				.destructive(false)
+				.open_world(false),
+		);
+		let mut weather = create_source_tool("fetch_weather", "Weather");
+		weather.annotations = Some(McpToolAnnotations::new().read_only(true));
+		let result = compiled.transform_tools(vec![
+			("db".to_string(), drop),
+			("weather".to_string(), weather),
+		]);
+		let annotations = |name: &str| {
+			let (_, tool) = result.iter().find(|(_, t)| t.name == name).unwrap();
+			tool.annotations.clone()
+		};
+		// Registry hints win, unset ones come from the backend tool
+		assert_eq!(
+			annotations("drop_table"),
+			Some(
+				McpToolAnnotations::new()
+					.destructive(true)
+					.idempotent(true)
+					.open_world(false)
+			)
+		);
+		assert_eq!(
+			annotations("get_weather"),
+			Some(McpToolAnnotations::new().read_only(true))
+		);
+		assert_eq!(
+			annotations("forecast"),
+			Some(McpToolAnnotations::new().read_only(true))
+		);
+
+		// Confirmation is only required when switched on, and only for destructive tools
+		assert!(!compiled.requires_confirmation("drop_table"));
+		let compiled = compiled.with_destructive_confirmation(true);
+		assert!(compiled.requires_confirmation("drop_table"));
+		assert!(!compiled.requires_confirmation("get_weather"));
+		assert!(!compiled.requires_confirmation("forecast"));
+		assert!(!compiled.requires_confirmation("missing"));
+	}
+
+	#[test]
+	fn test_localized_description() {
+		let mut tool = ToolDefinition::source("get_weather", "weather", "fetch_weather")
+			.with_description("Get the weather");
+		tool
+			.descriptions
+			.insert("fr".to_string(), "Obtenir la météo".to_string());
+		let compiled = CompiledRegistry::compile(Registry::with_tool_definitions(vec![tool])).unwrap();
+
+		assert_eq!(
+			compiled.localized_description("get_weather", "fr-FR, en;q=0.8"),
+			Some("Obtenir la météo")
+		);
+		assert_eq!(compiled.localized_description("get_weather", "de"), None);
+		assert_eq!(compiled.localized_description("unknown", "fr"), None);
+	}
+
 	#[test]
 	fn test_inject_defaults() {
 		let tool = VirtualToolDef::new("get_weather", "weather", "fetch_weather")
@@ -1142,6 +2580,40 @@ mod tests {
 		assert_eq!(result["units"], "imperial");
 	}
 
+	#[test]
+	fn test_inject_caller_defaults() {
+		let mut def = ToolDefinition::source("get_weather", "weather", "fetch_weather");
+		let source = def
+			.source_tool()
+			.unwrap()
+			.clone()
+			.with_default("units", json!("metric"))
+			.with_caller_default("billing-bot", "units", json!("imperial"))
+			.with_caller_default("billing-bot", "region", json!("eu"));
+		def.implementation = ToolImplementation::Source(source);
+		let compiled = CompiledTool::compile(&def, &HashMap::new(), 0).unwrap();
+
+		let args = json!({"city": "Paris"});
+		assert_eq!(
+			compiled
+				.inject_defaults_for(args.clone(), Some("billing-bot"))
+				.unwrap(),
+			json!({"city": "Paris", "units": "imperial", "region": "eu"})
+		);
+		assert_eq!(
+			compiled
+				.inject_defaults_for(args.clone(), Some("support-bot"))
+				.unwrap(),
+			json!({"city": "Paris", "units": "metric"})
+		);
+		assert_eq!(
+			compiled
+				.inject_defaults_for(json!({"region": "us"}), Some("billing-bot"))
+				.unwrap(),
+			json!({"region": "us", "units": "imperial"})
+		);
+	}
+
 	#[test]
 	fn test_inject_defaults_with_env_var() {
 		unsafe {
@@ -1213,6 +2685,43 @@ mod tests {
 		assert_eq!(result, response);
 	}
 
+	#[test]
+	fn test_output_transform_response_limits() {
+		let mut props = HashMap::new();
+		props.insert("temp".to_string(), OutputField::new("number", "$.temp"));
+		let output_schema = super::super::types::OutputSchema::new(props);
+		let tool = VirtualToolDef::new("test", "backend", "tool").with_output_schema(output_schema);
+		let compiled =
+			CompiledTool::compile(&ToolDefinition::from_legacy(tool), &HashMap::new(), 0).unwrap();
+		let limits = JsonLimits {
+			max_bytes: 64,
+			max_depth: 3,
+		};
+
+		let text = json!(r#"{"temp": 72.5}"#);
+		let result = compiled.transform_output_with(text, &limits).unwrap();
+		assert_eq!(result["temp"], 72.5);
+
+		let deep = json!(r#"{"temp": [[[1]]]}"#);
+		let err = compiled.transform_output_with(deep, &limits).unwrap_err();
+		assert!(err.to_string().contains("nested deeper"), "{err}");
+
+		// Embedded JSON is bounded too
+		let deep = json!(r#"Result: {"temp": [[[1]]]}"#);
+		assert!(compiled.transform_output_with(deep, &limits).is_err());
+
+		let large = json!(format!(r#"{{"temp": 1, "pad": "{}"}}"#, "x".repeat(64)));
+		let err = compiled.transform_output_with(large, &limits).unwrap_err();
+		assert!(matches!(
+			err,
+			RegistryError::ResponseLimit(JsonLimitError::TooLarge { limit: 64, .. })
+		));
+
+		// Structured responses were already parsed by the backend client
+		let structured = json!({"temp": [[[1]]]});
+		assert!(compiled.transform_output_with(structured, &limits).is_ok());
+	}
+
 	#[test]
 	fn test_output_transform_array_item_mapping() {
 		// Test the repos[*].name pattern for transforming array items
@@ -1274,7 +2783,480 @@ mod tests {
 	}
 
 	#[test]
-	fn test_output_transform_coalesce() {
+	fn test_output_transform_array_item_mapping_without_base() {
+		let json = r#"{
+			"name": "search_repos",
+			"source": { "target": "github", "tool": "search_repositories" },
+			"outputTransform": {
+				"mappings": {
+					"total": { "path": "$.total_count" },
+					"repos[*].name": { "path": "$.full_name" }
+				}
+			}
+		}"#;
+
+		let def: ToolDefinition = serde_json::from_str(json).unwrap();
+		let compiled = CompiledTool::compile(&def, &HashMap::new(), 0).unwrap();
+
+		let result = compiled
+			.transform_output(json!({"total_count": 1, "items": [{"full_name": "a/b"}]}))
+			.unwrap();
+		assert_eq!(result, json!({"repos": null, "total": 1}));
+	}
+
+	#[test]
+	fn test_output_transform_nested_array_item_mapping() {
+		let transform: OutputTransform = serde_json::from_value(json!({
+			"mappings": {
+				"orgs": {"path": "$.data.organizations[*]"},
+				"orgs[*].name": {"path": "$.login"},
+				"orgs[*].repos": {"path": "$.repositories[*]"},
+				"orgs[*].repos[*].name": {"path": "$.full_name"},
+				"orgs[*].latest": {"path": "$.repositories"},
+				"orgs[*].latest[-1].name": {"path": "$.full_name"},
+				"first": {"path": "$.data.organizations"},
+				"first[0].name": {"path": "$.login"},
+				"missing": {"path": "$.data.organizations"},
+				"missing[5].name": {"path": "$.login"}
+			}
+		}))
+		.unwrap();
+		let transform = CompiledOutputTransform::compile(&transform).unwrap();
+
+		let result = transform
+			.apply(&json!({"data": {"organizations": [
+				{"login": "acme", "repositories": [
+					{"full_name": "acme/api", "stars": 5},
+					{"full_name": "acme/web", "stars": 3}
+				]},
+				{"login": "solo", "repositories": [{"full_name": "solo/only"}]}
+			]}}))
+			.unwrap();
+
+		assert_eq!(
+			result,
+			json!({
+				"orgs": [
+					{
+						"name": "acme",
+						"repos": [{"name": "acme/api"}, {"name": "acme/web"}],
+						"latest": {"name": "acme/web"}
+					},
+					{
+						// A single match is still projected as an array
+						"name": "solo",
+						"repos": [{"name": "solo/only"}],
+						"latest": {"name": "solo/only"}
+					}
+				],
+				"first": {"name": "acme"},
+				"missing": null
+			})
+		);
+	}
+
+	#[test]
+	fn test_output_transform_conditional_fields() {
+		let transform: OutputTransform = serde_json::from_value(json!({
+			"mappings": {
+				"status": {"conditional": {
+					"when": {"field": "$.error", "op": "eq", "value": {"nullValue": true}},
+					"then": {"literal": {"stringValue": "ok"}},
+					"fallback": {"literal": {"stringValue": "failed"}}
+				}},
+				"error": {"conditional": {
+					"when": {"field": "$.error.code", "op": "gte", "value": {"numberValue": 400}},
+					"then": {"path": "$.error.message"}
+				}},
+				"items": {"conditional": {
+					"when": {"field": "$.data", "op": "ne", "value": {"nullValue": true}},
+					"then": {"path": "$.data[*]"}
+				}},
+				"items[*].id": {"path": "$.id"}
+			}
+		}))
+		.unwrap();
+		let transform = CompiledOutputTransform::compile(&transform).unwrap();
+
+		assert_eq!(
+			transform
+				.apply(&json!({"data": [{"id": 1, "x": true}, {"id": 2}]}))
+				.unwrap(),
+			json!({"status": "ok", "items": [{"id": 1}, {"id": 2}]})
+		);
+		assert_eq!(
+			transform
+				.apply(&json!({"error": {"code": 503, "message": "unavailable"}}))
+				.unwrap(),
+			json!({"status": "failed", "error": "unavailable"})
+		);
+		// A non-numeric code doesn't match `gte` rather than failing the transform
+		assert_eq!(
+			transform
+				.apply(&json!({"error": {"code": "E_TIMEOUT"}}))
+				.unwrap(),
+			json!({"status": "failed"})
+		);
+	}
+
+	#[test]
+	fn test_output_transform_schema() {
+		let transform: OutputTransform = serde_json::from_value(json!({
+			"mappings": {
+				"status": {"conditional": {
+					"when": {"field": "$.error", "op": "eq", "value": {"nullValue": true}},
+					"then": {"literal": {"stringValue": "ok"}},
+					"fallback": {"literal": {"stringValue": "failed"}}
+				}},
+				"error": {"conditional": {
+					"when": {"field": "$.error.code", "op": "gte", "value": {"numberValue": 400}},
+					"then": {"path": "$.error.message"}
+				}},
+				"summary": {"template": {"template": "{n} results", "vars": {"n": "$.count"}}},
+				"count": {"coerce": {"source": {"path": "$.count"}, "as": "number"}},
+				"meta": {"nested": {"mappings": {"version": {"literal": {"numberValue": 2}}}}},
+				"items": {"path": "$.data"},
+				"items[*].id": {"path": "$.id"}
+			}
+		}))
+		.unwrap();
+		let transform = CompiledOutputTransform::compile(&transform).unwrap();
+
+		let count = json!({"type": ["number", "null"]});
+		assert_eq!(
+			transform.schema(),
+			json!({
+				"type": "object",
+				"properties": {
+					"count": {"anyOf": [count, {"type": "array", "items": count}]},
+					"error": {},
+					"items": {
+						"type": ["array", "null"],
+						"items": {"type": "object", "properties": {"id": {}}, "required": ["id"]}
+					},
+					"meta": {
+						"type": "object",
+						"properties": {"version": {"type": "number"}},
+						"required": ["version"]
+					},
+					"status": {"type": "string"},
+					"summary": {"type": "string"}
+				},
+				"required": ["count", "items", "meta", "status", "summary"]
+			})
+		);
+	}
+
+	#[test]
+	fn test_output_transform_coerce() {
+		let transform: OutputTransform = serde_json::from_value(json!({
+			"mappings": {
+				"price": {"coerce": {"source": {"path": "$.price"}, "as": "number"}},
+				"inStock": {"coerce": {"source": {"path": "$.in_stock"}, "as": "boolean"}},
+				"sizes": {"coerce": {"source": {"path": "$.sizes[*]"}, "as": "number"}},
+				"sku": {"coerce": {"source": {"path": "$.sku"}, "as": "string"}},
+				"discount": {"coerce": {
+					"source": {"conditional": {
+						"when": {"field": "$.discount", "op": "ne", "value": {"nullValue": true}},
+						"then": {"path": "$.discount"}
+					}},
+					"as": "number"
+				}}
+			}
+		}))
+		.unwrap();
+		let transform = CompiledOutputTransform::compile(&transform).unwrap();
+
+		let result = transform
+			.apply(&json!({
+				"price": "1,299.99",
+				"in_stock": "Y",
+				"sizes": ["8", "9.5", 10],
+				"sku": 12345
+			}))
+			.unwrap();
+		// Unparseable values become null; an omitted conditional stays omitted
+		assert_eq!(
+			result,
+			json!({"price": 1299.99, "inStock": null, "sizes": [8, 9.5, 10], "sku": "12345"})
+		);
+	}
+
+	#[test]
+	fn test_output_transform_rejects_unknown_predicate_operator() {
+		let transform: OutputTransform = serde_json::from_value(json!({
+			"mappings": {
+				"x": {"conditional": {
+					"when": {"field": "$.a", "op": "matches", "value": {"stringValue": "b"}},
+					"then": {"path": "$.a"}
+				}}
+			}
+		}))
+		.unwrap();
+		assert!(matches!(
+			CompiledOutputTransform::compile(&transform),
+			Err(RegistryError::CompilationError(msg)) if msg.contains("matches")
+		));
+	}
+
+	#[test]
+	fn test_schema_map_rejects_unknown_predicate_operator() {
+		let composition = ToolDefinition::composition(
+			"normalize",
+			serde_json::from_value(json!({"schemaMap": {"mappings": {
+				"x": {"conditional": {
+					"when": {"field": "$.a", "op": "matches", "value": {"stringValue": "b"}},
+					"then": {"path": "$.a"}
+				}}
+			}}}))
+			.unwrap(),
+		);
+		let err = CompiledRegistry::compile(Registry::with_tool_definitions(vec![composition]))
+			.unwrap_err()
+			.to_string();
+		assert!(err.contains("'x'") && err.contains("matches"), "{err}");
+	}
+
+	#[test]
+	fn test_assert_rejects_unknown_predicate_operator() {
+		let composition = ToolDefinition::composition(
+			"checked",
+			serde_json::from_value(json!({"pipeline": {"steps": [
+				{"id": "check", "operation": {"assert": {
+					"predicate": {"field": "$.items", "op": "nonEmpty", "value": {"nullValue": true}}
+				}}}
+			]}}))
+			.unwrap(),
+		);
+		let err = CompiledRegistry::compile(Registry::with_tool_definitions(vec![composition]))
+			.unwrap_err()
+			.to_string();
+		assert!(err.contains("assert") && err.contains("nonEmpty"), "{err}");
+	}
+
+	#[test]
+	fn test_cache_rejects_non_equality_predicates() {
+		let cached = |stale_if: serde_json::Value| {
+			ToolDefinition::composition(
+				"cached",
+				serde_json::from_value(json!({"cache": {
+					"keyPaths": ["$.query"],
+					"inner": {"tool": {"name": "search"}},
+					"store": "results",
+					"ttlSeconds": 60,
+					"staleIf": stale_if
+				}}))
+				.unwrap(),
+			)
+		};
+		let compile = |tool| {
+			let search = ToolDefinition::source("search", "backend", "search");
+			CompiledRegistry::compile(Registry::with_tool_definitions(vec![search, tool]))
+		};
+
+		let err = compile(cached(
+			json!({"field": "$.count", "op": "lt", "value": {"numberValue": 1}}),
+		))
+		.unwrap_err()
+		.to_string();
+		assert!(
+			err.contains("staleIf: only the eq operator is supported, got 'lt'"),
+			"{err}"
+		);
+
+		let err = compile(cached(
+			json!({"field": "$.[count", "op": "eq", "value": {"numberValue": 0}}),
+		))
+		.unwrap_err()
+		.to_string();
+		assert!(err.contains("invalid JSONPath"), "{err}");
+
+		compile(cached(
+			json!({"field": "$.results", "op": "eq", "value": {"listValue": []}}),
+		))
+		.unwrap();
+	}
+
+	#[test]
+	fn test_cache_rejects_unknown_error_kinds() {
+		let cached = |errors: Vec<&str>| {
+			let composition = ToolDefinition::composition(
+				"cached",
+				serde_json::from_value(json!({"cache": {
+					"keyPaths": ["$.query"],
+					"inner": {"tool": {"name": "search"}},
+					"store": "results",
+					"ttlSeconds": 60,
+					"negative": {"ttlSeconds": 10, "errors": errors}
+				}}))
+				.unwrap(),
+			);
+			CompiledRegistry::compile(Registry::with_tool_definitions(vec![composition]))
+		};
+
+		let err = cached(vec!["not found"]).unwrap_err().to_string();
+		assert!(err.contains("unknown error kind 'not found'"), "{err}");
+		cached(vec!["tool_not_found", "invalid_input"]).unwrap();
+	}
+
+	#[test]
+	fn test_output_transform_variants() {
+		let transform: OutputTransform = serde_json::from_value(json!({
+			"mappings": {
+				"status": {"literal": {"stringValue": "ok"}},
+				"items": {"path": "$.data"}
+			},
+			"variants": [
+				{
+					"when": {"field": "$.error", "op": "ne", "value": {"nullValue": true}},
+					"mappings": {
+						"status": {"literal": {"stringValue": "error"}},
+						"message": {"path": "$.error.message"}
+					}
+				},
+				{
+					"when": {"field": "$.truncated", "op": "eq", "value": {"boolValue": true}},
+					"mappings": {
+						"status": {"literal": {"stringValue": "partial"}},
+						"items": {"path": "$.data"},
+						"cursor": {"path": "$.next"}
+					}
+				}
+			],
+			"discriminator": "status"
+		}))
+		.unwrap();
+		let transform = CompiledOutputTransform::compile(&transform).unwrap();
+
+		assert_eq!(
+			transform.apply(&json!({"data": [1, 2]})).unwrap(),
+			json!({"status": "ok", "items": [1, 2]})
+		);
+		assert_eq!(
+			transform
+				.apply(&json!({"error": {"message": "rate limited"}, "data": null}))
+				.unwrap(),
+			json!({"status": "error", "message": "rate limited"})
+		);
+		assert_eq!(
+			transform
+				.apply(&json!({"data": [1], "truncated": true, "next": "c2"}))
+				.unwrap(),
+			json!({"status": "partial", "items": [1], "cursor": "c2"})
+		);
+
+		assert_eq!(
+			transform.schema(),
+			json!({
+				"type": "object",
+				"oneOf": [
+					{
+						"type": "object",
+						"properties": {"items": {}, "status": {"const": "ok"}},
+						"required": ["items", "status"]
+					},
+					{
+						"type": "object",
+						"properties": {"message": {}, "status": {"const": "error"}},
+						"required": ["message", "status"]
+					},
+					{
+						"type": "object",
+						"properties": {"cursor": {}, "items": {}, "status": {"const": "partial"}},
+						"required": ["cursor", "items", "status"]
+					}
+				],
+				"discriminator": {"propertyName": "status"}
+			})
+		);
+	}
+
+	#[test]
+	fn test_output_transform_variants_without_base_mappings() {
+		let transform = OutputTransform::empty().with_variant(
+			FieldPredicate::eq("$.kind", "user"),
+			HashMap::from([("name".to_string(), FieldSource::Path("$.login".to_string()))]),
+		);
+		let transform = CompiledOutputTransform::compile(&transform).unwrap();
+		assert_eq!(
+			transform
+				.apply(&json!({"kind": "user", "login": "octocat"}))
+				.unwrap(),
+			json!({"name": "octocat"})
+		);
+		assert!(matches!(
+			transform.apply(&json!({"kind": "bot"})),
+			Err(RegistryError::SchemaValidation(_))
+		));
+	}
+
+	#[test]
+	fn test_output_transform_rejects_bad_discriminator() {
+		let status = |value: &str| {
+			HashMap::from([(
+				"status".to_string(),
+				FieldSource::Literal(LiteralValue::StringValue(value.to_string())),
+			)])
+		};
+		let error = |transform: OutputTransform| match CompiledOutputTransform::compile(&transform) {
+			Err(RegistryError::CompilationError(msg)) => msg,
+			other => panic!("expected a compilation error, got {other:?}"),
+		};
+
+		// No variants to tell apart
+		let msg = error(OutputTransform::new(status("ok")).with_discriminator("status"));
+		assert!(msg.contains("no variants"));
+
+		// A branch that doesn't set the discriminator
+		let transform = OutputTransform::new(status("ok"))
+			.with_variant(FieldPredicate::eq("$.failed", true), HashMap::new())
+			.with_discriminator("status");
+		assert!(error(transform).contains("variant 0 must map"));
+
+		// Two branches with the same value
+		let transform = OutputTransform::new(status("ok"))
+			.with_variant(FieldPredicate::eq("$.failed", true), status("ok"))
+			.with_discriminator("status");
+		assert!(error(transform).contains("more than one variant"));
+	}
+
+	#[test]
+	fn test_output_transform_rejects_mixed_item_selectors() {
+		let transform: OutputTransform = serde_json::from_value(json!({
+			"mappings": {
+				"repos": {"path": "$.items"},
+				"repos[*].name": {"path": "$.name"},
+				"repos[0].id": {"path": "$.id"}
+			}
+		}))
+		.unwrap();
+		assert!(matches!(
+			CompiledOutputTransform::compile(&transform),
+			Err(RegistryError::CompilationError(msg)) if msg.contains("'repos'")
+		));
+	}
+
+	#[test]
+	fn test_split_item_mapping() {
+		assert_eq!(
+			split_item_mapping("orgs[*].repos[*].name"),
+			Some(("orgs", ItemSelector::Each, "repos[*].name"))
+		);
+		assert_eq!(
+			split_item_mapping("repos[-1].name"),
+			Some(("repos", ItemSelector::Index(-1), "name"))
+		);
+		// Brackets that aren't selectors are part of the field name
+		assert_eq!(
+			split_item_mapping("a[x].b[2].c"),
+			Some(("a[x].b", ItemSelector::Index(2), "c"))
+		);
+		assert_eq!(split_item_mapping("repos[*]"), None);
+		assert_eq!(split_item_mapping("owner.login"), None);
+	}
+
+	#[test]
+	fn test_output_transform_coalesce() {
 		let json = r#"{
 			"name": "test",
 			"source": { "target": "backend", "tool": "tool" },
@@ -1398,15 +3380,13 @@ mod tests {
 		let composition = ToolDefinition::composition(
 			"pipeline",
 			PatternSpec::ScatterGather(ScatterGatherSpec {
-				targets: vec![
-					ScatterTarget::Tool("tool_a".to_string()),
-					ScatterTarget::Tool("tool_b".to_string()),
-				],
+				targets: vec![ScatterTarget::tool("tool_a"), ScatterTarget::tool("tool_b")],
 				aggregation: AggregationStrategy {
 					ops: vec![AggregationOp::Flatten(true)],
 				},
 				timeout_ms: None,
 				fail_fast: false,
+				sample: None,
 			}),
 		);
 
@@ -1420,6 +3400,240 @@ mod tests {
 		assert!(comp.resolved_references.contains(&"tool_b".to_string()));
 	}
 
+	#[test]
+	fn test_composition_calls_are_resolved() {
+		let call = |caller: &str, callee: &str| {
+			ToolDefinition::composition(
+				caller,
+				PatternSpec::Pipeline(PipelineSpec {
+					steps: vec![PipelineStep {
+						id: "call".to_string(),
+						operation: StepOperation::Composition(CompositionCall::new(callee)),
+						input: None,
+						session_set: None,
+						retry: None,
+						cache: None,
+					}],
+				}),
+			)
+		};
+		let compile = |tools| CompiledRegistry::compile(Registry::with_tool_definitions(tools));
+
+		let compiled = compile(vec![
+			call("outer", "inner"),
+			ToolDefinition::composition("inner", PatternSpec::MapEach(MapEachSpec::tool("fetch"))),
+		])
+		.unwrap();
+		let outer = compiled.get_tool("outer").unwrap();
+		assert_eq!(
+			outer.composition_info().unwrap().resolved_references,
+			vec!["inner".to_string()]
+		);
+
+		let err = compile(vec![call("outer", "missing")]).unwrap_err();
+		assert!(
+			err
+				.to_string()
+				.contains("tool 'outer' calls composition 'missing', which is not in the registry")
+		);
+		let err = compile(vec![
+			call("outer", "search"),
+			ToolDefinition::source("search", "backend", "search"),
+		])
+		.unwrap_err();
+		assert!(
+			err
+				.to_string()
+				.contains("'search', which is a virtual tool")
+		);
+
+		// Cycles are rejected, including ones closed by a tool step naming a composition
+		let err = compile(vec![call("a", "b"), call("b", "a")]).unwrap_err();
+		assert!(err.to_string().contains("composition cycle: a -> b -> a"));
+		let err = compile(vec![call("a", "a")]).unwrap_err();
+		assert!(err.to_string().contains("composition cycle: a -> a"));
+		let b = ToolDefinition::composition(
+			"b",
+			PatternSpec::Pipeline(PipelineSpec {
+				steps: vec![PipelineStep {
+					id: "back".to_string(),
+					operation: StepOperation::Tool(ToolCall {
+						name: "a".to_string(),
+					}),
+					input: None,
+					session_set: None,
+					retry: None,
+					cache: None,
+				}],
+			}),
+		);
+		let err = compile(vec![call("a", "b"), b]).unwrap_err();
+		assert!(err.to_string().contains("composition cycle: a -> b -> a"));
+	}
+
+	#[test]
+	fn test_script_modules_compiled_with_registry() {
+		use base64::Engine as _;
+		let wasm =
+			|wat: &str| base64::engine::general_purpose::STANDARD.encode(wat::parse_str(wat).unwrap());
+		let registry = |wasm: String| {
+			serde_json::from_value::<Registry>(json!({
+				"schemaVersion": "1.0",
+				"tools": [{
+					"name": "reshape",
+					"spec": {"pipeline": {"steps": [{"id": "outer", "operation": {"pattern": {
+						"pipeline": {"steps": [{"id": "run", "operation": {"script": {"wasm": wasm}}}]}
+					}}}]}}
+				}]
+			}))
+			.unwrap()
+		};
+
+		let valid = wasm(
+			r#"(module (memory (export "memory") 1)
+				(func (export "alloc") (param i32) (result i32) (local.get 0))
+				(func (export "transform") (param i32 i32) (result i64) (i64.const 0)))"#,
+		);
+		let compiled = CompiledRegistry::compile(registry(valid)).unwrap();
+		let mut spec = compiled
+			.get_tool("reshape")
+			.unwrap()
+			.composition_info()
+			.unwrap()
+			.spec
+			.clone();
+		assert!(spec.scripts_mut()[0].module.is_some());
+
+		// A bad module fails the load rather than its first call
+		let err = CompiledRegistry::compile(registry(wasm(r#"(module (memory (export "memory") 1))"#)))
+			.unwrap_err();
+		assert_eq!(
+			err.to_string(),
+			"compilation error: tool 'reshape': script: module does not export 'alloc(i32) -> i32'"
+		);
+		let err = CompiledRegistry::compile(registry("not base64!".to_string())).unwrap_err();
+		assert!(err.to_string().contains("not valid base64"), "{err}");
+	}
+
+	#[test]
+	fn test_composition_input_schema_inferred_from_paths() {
+		let step = |id: &str, input: Option<DataBinding>| PipelineStep {
+			id: id.to_string(),
+			operation: StepOperation::Tool(ToolCall {
+				name: "web_search".to_string(),
+			}),
+			input,
+			session_set: None,
+			retry: None,
+			cache: None,
+		};
+		let input = |path: &str| {
+			DataBinding::Input(InputBinding {
+				path: path.to_string(),
+			})
+		};
+		let spec = PatternSpec::Pipeline(PipelineSpec {
+			steps: vec![
+				step(
+					"search",
+					Some(DataBinding::Construct(ConstructBinding {
+						fields: HashMap::from([
+							("q".to_string(), input("$.query")),
+							("lang".to_string(), input("$.filters['lang']")),
+						]),
+					})),
+				),
+				step("fetch", Some(input("$.urls[*].href"))),
+				step("ignored", Some(input("$..anything"))),
+			],
+		});
+
+		let mut declared = ToolDefinition::composition("declared", spec.clone());
+		declared.input_schema = Some(json!({"type": "object", "required": ["query"]}));
+		let registry = Registry::with_tool_definitions(vec![
+			ToolDefinition::composition("inferred", spec),
+			declared,
+		]);
+		let compiled = CompiledRegistry::compile(registry).unwrap();
+
+		let inferred = compiled
+			.get_tool("inferred")
+			.unwrap()
+			.composition_info()
+			.unwrap();
+		assert_eq!(
+			inferred.input_schema,
+			Some(json!({
+				"type": "object",
+				"properties": {
+					"filters": {"type": "object", "properties": {"lang": {}}},
+					"query": {},
+					"urls": {
+						"type": "array",
+						"items": {"type": "object", "properties": {"href": {}}}
+					}
+				}
+			}))
+		);
+		let declared = compiled
+			.get_tool("declared")
+			.unwrap()
+			.composition_info()
+			.unwrap();
+		assert_eq!(
+			declared.input_schema,
+			Some(json!({"type": "object", "required": ["query"]}))
+		);
+
+		// Patterns that don't read named input fields advertise no schema
+		let passthrough = ToolDefinition::composition(
+			"passthrough",
+			PatternSpec::Pipeline(PipelineSpec {
+				steps: vec![step("search", None)],
+			}),
+		);
+		let compiled =
+			CompiledRegistry::compile(Registry::with_tool_definitions(vec![passthrough])).unwrap();
+		let tool = compiled.get_tool("passthrough").unwrap();
+		assert!(tool.composition_info().unwrap().input_schema.is_none());
+	}
+
+	#[test]
+	fn test_composition_output_schema_derived_from_transform() {
+		let spec = PatternSpec::Pipeline(PipelineSpec { steps: vec![] });
+		let transform: OutputTransform = serde_json::from_value(json!({
+			"mappings": {"title": {"path": "$.name"}, "source": {"literal": {"stringValue": "web"}}}
+		}))
+		.unwrap();
+		let mut derived =
+			ToolDefinition::composition("derived", spec.clone()).with_output_transform(transform.clone());
+		derived.result_envelope = true;
+		let declared = ToolDefinition::composition("declared", spec)
+			.with_output_transform(transform)
+			.with_output_schema(json!({"type": "object"}));
+		let compiled =
+			CompiledRegistry::compile(Registry::with_tool_definitions(vec![derived, declared])).unwrap();
+
+		let tools: HashMap<_, _> = compiled
+			.transform_tools(vec![])
+			.into_iter()
+			.map(|(_, tool)| (tool.name.to_string(), tool))
+			.collect();
+		let data = json!({
+			"type": "object",
+			"properties": {"source": {"type": "string"}, "title": {}},
+			"required": ["source", "title"]
+		});
+		assert_eq!(
+			serde_json::Value::Object(tools["derived"].output_schema.as_deref().unwrap().clone()),
+			envelope_output_schema(Some(&data))
+		);
+		assert_eq!(
+			serde_json::Value::Object(tools["declared"].output_schema.as_deref().unwrap().clone()),
+			json!({"type": "object"})
+		);
+	}
+
 	#[test]
 	fn test_extract_json_from_text() {
 		let text = r#"Here is the result: {"temperature": 72.5, "city": "Seattle"} and some more text"#;
@@ -1437,4 +3651,738 @@ mod tests {
 		assert!(json.is_array());
 		assert_eq!(json.as_array().unwrap().len(), 3);
 	}
+
+	#[test]
+	fn test_registry_defaults_fill_unset_policies() {
+		use crate::mcp::registry::types::{RegistryDefaults, TransportRetry};
+
+		let mut opted_out = ToolDefinition::source("opted_out", "weather", "alerts");
+		if let ToolImplementation::Source(source) = &mut opted_out.implementation {
+			source.transport_retry = Some(TransportRetry {
+				max_attempts: 1,
+				backoff_ms: 0,
+			});
+		}
+		let registry = Registry::with_tool_definitions(vec![
+			ToolDefinition::source("forecast", "weather", "get_forecast"),
+			opted_out,
+		])
+		.with_defaults(RegistryDefaults {
+			transport_retry: Some(TransportRetry {
+				max_attempts: 3,
+				backoff_ms: 50,
+			}),
+			..Default::default()
+		});
+		let compiled = CompiledRegistry::compile(registry).unwrap();
+
+		let retry = |name: &str| {
+			compiled
+				.get_tool(name)
+				.and_then(|t| t.source_info())
+				.and_then(|s| s.source.transport_retry.clone())
+				.map(|r| r.attempts())
+		};
+		assert_eq!(retry("forecast"), Some(3));
+		assert_eq!(retry("opted_out"), Some(1));
+	}
+
+	#[test]
+	fn test_registry_defaults_resilience_wraps_compositions() {
+		use crate::mcp::registry::patterns::{CircuitBreakerShorthand, Resilience};
+		use crate::mcp::registry::types::RegistryDefaults;
+
+		let composition = |name: &str| {
+			ToolDefinition::composition(
+				name,
+				PatternSpec::Pipeline(PipelineSpec {
+					steps: vec![PipelineStep {
+						id: "search".to_string(),
+						operation: StepOperation::Tool(ToolCall {
+							name: "search_api".to_string(),
+						}),
+						input: None,
+						session_set: None,
+						retry: None,
+						cache: None,
+					}],
+				}),
+			)
+		};
+		let mut opted_out = composition("opted_out");
+		opted_out.resilience = Some(Resilience::default());
+		let defaults = |resilience: Resilience| RegistryDefaults {
+			resilience: Some(resilience),
+			..Default::default()
+		};
+		let registry = Registry::with_tool_definitions(vec![
+			composition("wrapped"),
+			opted_out,
+			ToolDefinition::source("forecast", "weather", "get_forecast"),
+		])
+		.with_defaults(defaults(Resilience {
+			retries: Some(1),
+			..Default::default()
+		}));
+		let compiled = CompiledRegistry::compile(registry).unwrap();
+
+		let outer_step = |name: &str| {
+			let comp = compiled.get_tool(name).unwrap().composition_info().unwrap();
+			match &comp.spec {
+				PatternSpec::Pipeline(p) => p.steps[0].clone(),
+				other => panic!("expected pipeline, got {other:?}"),
+			}
+		};
+		let wrapped = outer_step("wrapped");
+		assert_eq!(wrapped.id, "wrapped");
+		assert_eq!(wrapped.retry.map(|r| r.max_attempts), Some(2));
+		let opted_out = outer_step("opted_out");
+		assert_eq!(opted_out.id, "search");
+		assert!(opted_out.retry.is_none());
+		let forecast = compiled.get_tool("forecast").unwrap();
+		assert!(forecast.source_info().is_some());
+
+		// Defaults the executor can't honor fail compilation rather than being dropped
+		let breaker = defaults(Resilience {
+			circuit_breaker: Some(CircuitBreakerShorthand {
+				store: "circuits".to_string(),
+				failure_threshold: 5,
+				failure_window_seconds: 60,
+				reset_timeout_seconds: 30,
+			}),
+			..Default::default()
+		});
+		let registry =
+			Registry::with_tool_definitions(vec![composition("wrapped")]).with_defaults(breaker);
+		let err = CompiledRegistry::compile(registry).unwrap_err().to_string();
+		assert!(err.contains("defaults: resilience circuitBreaker"), "{err}");
+	}
+
+	#[test]
+	fn test_invalid_pagination_rejected() {
+		let registry: Registry = serde_json::from_value(serde_json::json!({
+			"schemaVersion": "1.0",
+			"tools": [{
+				"name": "list_issues",
+				"source": {
+					"target": "tracker",
+					"tool": "issues",
+					"pagination": {
+						"cursorField": "after",
+						"cursorPath": "$.pageInfo.endCursor",
+						"itemsPath": "$.nodes[*]"
+					}
+				}
+			}]
+		}))
+		.unwrap();
+		let err = CompiledRegistry::compile(registry).unwrap_err();
+		assert!(err.to_string().contains("itemsPath"));
+	}
+
+	#[test]
+	fn test_failover_targets() {
+		let registry: Registry = serde_json::from_value(serde_json::json!({
+			"schemaVersion": "1.0",
+			"tools": [{
+				"name": "search",
+				"source": {
+					"target": "search-primary",
+					"fallbackTargets": ["search-replica", "search-vendor"],
+					"tool": "web_search"
+				}
+			}]
+		}))
+		.unwrap();
+		let compiled = CompiledRegistry::compile(registry).unwrap();
+		let source = &compiled
+			.get_tool("search")
+			.unwrap()
+			.source_info()
+			.unwrap()
+			.source;
+		assert_eq!(
+			source.targets().collect::<Vec<_>>(),
+			vec!["search-primary", "search-replica", "search-vendor"]
+		);
+
+		let mut def = ToolDefinition::source("search", "search-primary", "web_search");
+		if let ToolImplementation::Source(source) = &mut def.implementation {
+			*source = source.clone().with_fallback_targets(vec![
+				"search-replica".to_string(),
+				"search-primary".to_string(),
+			]);
+		}
+		let registry = Registry::with_tool_definitions(vec![def]);
+		let err = CompiledRegistry::compile(registry).unwrap_err();
+		assert!(
+			err
+				.to_string()
+				.contains("target 'search-primary' is listed more than once")
+		);
+	}
+
+	#[test]
+	fn test_nested_pipeline_bindings_checked() {
+		let registry: Registry = serde_json::from_value(serde_json::json!({
+			"schemaVersion": "1.0",
+			"tools": [{
+				"name": "research",
+				"spec": {"pipeline": {"steps": [
+					{"id": "fetch", "operation": {"tool": {"name": "fetch"}}},
+					{"id": "analyze", "operation": {"pattern": {"pipeline": {"steps": [
+						{"id": "search", "operation": {"tool": {"name": "search"}}},
+						{
+							"id": "summarize",
+							"operation": {"tool": {"name": "summarize"}},
+							"input": {"step": {"stepId": "fetch", "path": "$"}}
+						}
+					]}}}}
+				]}}
+			}]
+		}))
+		.unwrap();
+		let err = CompiledRegistry::compile(registry).unwrap_err();
+		assert_eq!(
+			err.to_string(),
+			"compilation error: tool 'research': step 'summarize' binds to unknown step 'fetch'"
+		);
+	}
+
+	#[test]
+	fn test_patch_payloads_are_not_pipelines() {
+		let registry: Registry = serde_json::from_value(serde_json::json!({
+			"schemaVersion": "1.0",
+			"tools": [{
+				"name": "template",
+				"spec": {"pipeline": {"steps": [{
+					"id": "fill",
+					"operation": {"patch": {"mergePatch": {"pipeline": {"steps": [
+						{"id": "draft", "operation": {"tool": {"name": "write"}}},
+						{
+							"id": "review",
+							"operation": {"tool": {"name": "review"}},
+							"input": {"step": {"stepId": "publish", "path": "$"}}
+						}
+					]}}}}
+				}]}}
+			}]
+		}))
+		.unwrap();
+		CompiledRegistry::compile(registry).unwrap();
+	}
+
+	#[test]
+	fn test_duration_and_percentage_bounds_checked() {
+		let compile = |spec: serde_json::Value| {
+			let registry: Registry = serde_json::from_value(serde_json::json!({
+				"schemaVersion": "1.0",
+				"tools": [{"name": "slow", "spec": spec}]
+			}))
+			.unwrap();
+			CompiledRegistry::compile(registry).map(|_| ())
+		};
+		let inner = serde_json::json!({"tool": {"name": "fetch"}});
+
+		compile(serde_json::json!({"timeout": {"inner": inner, "durationMs": "2m"}})).unwrap();
+		let err =
+			compile(serde_json::json!({"timeout": {"inner": inner, "durationMs": 0}})).unwrap_err();
+		assert_eq!(
+			err.to_string(),
+			"compilation error: tool 'slow': durationMs must be greater than 0, got 0ms"
+		);
+
+		let retry = |jitter: serde_json::Value| {
+			serde_json::json!({"retry": {
+				"inner": inner,
+				"maxAttempts": 3,
+				"backoff": {"fixed": {"delayMs": "500ms"}},
+				"jitter": jitter
+			}})
+		};
+		compile(retry(serde_json::json!("20%"))).unwrap();
+		let err = compile(retry(serde_json::json!("150%"))).unwrap_err();
+		assert!(err.to_string().contains("jitter must be between 0 and 1"));
+	}
+
+	#[test]
+	fn test_bounds_skip_patch_payloads() {
+		let registry: Registry = serde_json::from_value(serde_json::json!({
+			"schemaVersion": "1.0",
+			"tools": [{
+				"name": "tune",
+				"spec": {"pipeline": {"steps": [{
+					"id": "defaults",
+					"operation": {"patch": {"mergePatch": {
+						"timeoutMs": 0,
+						"windowMs": 0,
+						"threshold": 5,
+						"jitter": 2
+					}}}
+				}]}}
+			}]
+		}))
+		.unwrap();
+		CompiledRegistry::compile(registry).unwrap();
+
+		// Bounds still apply to sampling steps, which aren't patterns of their own
+		let registry: Registry = serde_json::from_value(serde_json::json!({
+			"schemaVersion": "1.0",
+			"tools": [{
+				"name": "judge",
+				"spec": {"pipeline": {"steps": [{
+					"id": "ask",
+					"operation": {"sample": {"prompt": "rate this", "timeoutMs": 0}}
+				}]}}
+			}]
+		}))
+		.unwrap();
+		let err = CompiledRegistry::compile(registry).unwrap_err();
+		assert_eq!(
+			err.to_string(),
+			"compilation error: tool 'judge': timeoutMs must be greater than 0, got 0ms"
+		);
+	}
+
+	#[test]
+	fn test_env_policy_rejects_unlisted_vars() {
+		let registry = |defaults: serde_json::Value, headers: serde_json::Value| -> Registry {
+			serde_json::from_value(serde_json::json!({
+				"schemaVersion": "1.0",
+				"tools": [{
+					"name": "forecast",
+					"source": {"target": "weather", "tool": "get_forecast", "defaults": defaults}
+				}],
+				"targets": {"weather": {"headers": headers}}
+			}))
+			.unwrap()
+		};
+		let policy = EnvVarPolicy::Allowlist {
+			prefixes: vec!["REGISTRY_".to_string()],
+			names: vec!["WEATHER_API_KEY".to_string()],
+		};
+
+		unsafe {
+			std::env::set_var("REGISTRY_TEST_TOKEN_COMPILED", "tok");
+		}
+		// Defaults are only resolved per call, but their variables are checked at compile time
+		let allowed = registry(
+			serde_json::json!({"auth": {"key": "${WEATHER_API_KEY}"}}),
+			serde_json::json!({"Authorization": "${REGISTRY_TEST_TOKEN_COMPILED} ${claims.sub}"}),
+		);
+		assert!(CompiledRegistry::compile_with_env_policy(allowed, &policy).is_ok());
+
+		let err = CompiledRegistry::compile_with_env_policy(
+			registry(
+				serde_json::json!({"keys": ["${WEATHER_API_KEY}", "${AWS_SECRET_ACCESS_KEY}"]}),
+				serde_json::json!({}),
+			),
+			&policy,
+		)
+		.unwrap_err();
+		assert!(matches!(
+			err,
+			RegistryError::EnvVarNotAllowed { ref name, ref location }
+				if name == "AWS_SECRET_ACCESS_KEY" && location == "tool 'forecast'"
+		));
+
+		let leaky = || {
+			registry(
+				serde_json::json!({}),
+				serde_json::json!({"X-Leak": "${PATH}"}),
+			)
+		};
+		let err = CompiledRegistry::compile_with_env_policy(leaky(), &policy).unwrap_err();
+		assert_eq!(
+			err.to_string(),
+			"target 'weather' references environment variable 'PATH', which is not allowed"
+		);
+		assert!(CompiledRegistry::compile(leaky()).is_ok());
+	}
+
+	#[test]
+	fn test_env_policy_checks_caller_defaults() {
+		let registry = |caller_defaults: serde_json::Value| -> Registry {
+			serde_json::from_value(serde_json::json!({
+				"schemaVersion": "1.0",
+				"tools": [{
+					"name": "forecast",
+					"source": {
+						"target": "weather",
+						"tool": "get_forecast",
+						"defaults": {"region": "us"},
+						"callerDefaults": caller_defaults
+					}
+				}]
+			}))
+			.unwrap()
+		};
+		let policy = EnvVarPolicy::Allowlist {
+			prefixes: vec!["REGISTRY_".to_string()],
+			names: vec![],
+		};
+
+		let allowed = registry(serde_json::json!({
+			"billing-bot": {"region": "eu", "token": "${REGISTRY_BILLING_TOKEN}"}
+		}));
+		assert!(CompiledRegistry::compile_with_env_policy(allowed, &policy).is_ok());
+
+		// A caller's defaults are resolved per call like the tool's, so they're held to the
+		// same allowlist at load
+		let leaky = registry(serde_json::json!({
+			"billing-bot": {"region": "eu"},
+			"exfil-bot": {"note": {"key": "${AWS_SECRET_ACCESS_KEY}"}}
+		}));
+		let err = CompiledRegistry::compile_with_env_policy(leaky, &policy).unwrap_err();
+		assert_eq!(
+			err.to_string(),
+			"tool 'forecast' (caller 'exfil-bot') references environment variable \
+			 'AWS_SECRET_ACCESS_KEY', which is not allowed"
+		);
+	}
+
+	#[test]
+	fn test_target_policy_resolves_env_and_claims() {
+		use crate::mcp::registry::types::TargetPolicy;
+
+		unsafe {
+			std::env::set_var("TEST_TARGET_TOKEN_COMPILED", "tok123");
+		}
+		let mut registry = Registry::new();
+		registry.targets.insert(
+			"weather".to_string(),
+			TargetPolicy {
+				headers: HashMap::from([
+					(
+						"Authorization".to_string(),
+						"Bearer ${TEST_TARGET_TOKEN_COMPILED}".to_string(),
+					),
+					("X-Tenant".to_string(), "${claims.tenant}".to_string()),
+				]),
+				meta: serde_json::Map::from_iter([(
+					"caller".to_string(),
+					json!({"sub": "${claims.sub}", "tags": ["${claims.missing}"]}),
+				)]),
+			},
+		);
+		let compiled = CompiledRegistry::compile(registry).unwrap();
+		assert!(compiled.target_policy("other").is_none());
+		let policy = compiled.target_policy("weather").unwrap();
+
+		let claims = serde_json::Map::from_iter([
+			("tenant".to_string(), json!("acme")),
+			("sub".to_string(), json!("user-1")),
+		]);
+		let headers: HashMap<String, String> = policy
+			.headers(Some(&claims))
+			.map(|(k, v)| (k.to_string(), v.into_owned()))
+			.collect();
+		assert_eq!(headers["authorization"], "Bearer tok123");
+		assert_eq!(headers["x-tenant"], "acme");
+
+		assert!(policy.has_meta());
+		assert_eq!(
+			json!(policy.meta(Some(&claims))),
+			json!({"caller": {"sub": "user-1", "tags": [""]}})
+		);
+	}
+
+	#[test]
+	fn test_target_policy_rejects_invalid_header_name() {
+		use crate::mcp::registry::types::TargetPolicy;
+
+		let mut registry = Registry::new();
+		registry.targets.insert(
+			"weather".to_string(),
+			TargetPolicy {
+				headers: HashMap::from([("bad header".to_string(), "x".to_string())]),
+				..Default::default()
+			},
+		);
+		assert!(matches!(
+			CompiledRegistry::compile(registry),
+			Err(RegistryError::CompilationError(msg)) if msg.contains("bad header")
+		));
+	}
+
+	#[test]
+	fn test_idempotency_is_for_compositions() {
+		use crate::mcp::registry::patterns::OnDuplicate;
+		use crate::mcp::registry::types::Idempotency;
+
+		let idempotency = |ttl_seconds| Idempotency {
+			ttl_seconds,
+			on_duplicate: OnDuplicate::Cached,
+		};
+		let compile =
+			|tool: ToolDefinition| CompiledRegistry::compile(Registry::with_tool_definitions(vec![tool]));
+		let pipeline = PatternSpec::Pipeline(PipelineSpec { steps: vec![] });
+
+		assert!(
+			compile(ToolDefinition::composition("c", pipeline.clone()).with_idempotency(idempotency(60)))
+				.is_ok()
+		);
+		assert!(matches!(
+			compile(ToolDefinition::source("s", "backend", "tool").with_idempotency(idempotency(60))),
+			Err(RegistryError::CompilationError(msg)) if msg.contains("only supported on compositions")
+		));
+		assert!(matches!(
+			compile(ToolDefinition::composition("c", pipeline).with_idempotency(idempotency(0))),
+			Err(RegistryError::CompilationError(msg)) if msg.contains("ttlSeconds")
+		));
+	}
+
+	#[test]
+	fn test_render_claims() {
+		let claims = serde_json::Map::from_iter([
+			("org".to_string(), json!("acme")),
+			("level".to_string(), json!(3)),
+		]);
+		assert!(matches!(
+			render_claims("static", Some(&claims)),
+			Cow::Borrowed("static")
+		));
+		assert_eq!(
+			render_claims("${claims.org}/${claims.level}", Some(&claims)),
+			"acme/3"
+		);
+		assert_eq!(render_claims("id=${claims.org}", None), "id=");
+		assert_eq!(
+			render_claims("a ${claims.org", Some(&claims)),
+			"a ${claims.org"
+		);
+	}
+
+	#[test]
+	fn test_resilience_shorthand_expands_at_compile_time() {
+		use crate::mcp::registry::patterns::{CircuitBreakerShorthand, Resilience};
+
+		let mut composition = ToolDefinition::composition(
+			"pipeline",
+			PatternSpec::Pipeline(PipelineSpec {
+				steps: vec![PipelineStep {
+					id: "search".to_string(),
+					operation: StepOperation::Tool(ToolCall {
+						name: "search_api".to_string(),
+					}),
+					input: None,
+					session_set: None,
+					retry: None,
+					cache: None,
+				}],
+			}),
+		);
+		composition.resilience = Some(Resilience {
+			timeout_ms: Some(10_000),
+			retries: Some(2),
+			..Default::default()
+		});
+		let compiled =
+			CompiledRegistry::compile(Registry::with_tool_definitions(vec![composition])).unwrap();
+
+		let comp = compiled
+			.get_tool("pipeline")
+			.unwrap()
+			.composition_info()
+			.unwrap();
+		assert!(matches!(comp.spec, PatternSpec::Pipeline(_)));
+		assert!(!comp.spec.is_stateful_unimplemented());
+		assert_eq!(comp.resolved_references, vec!["search_api".to_string()]);
+
+		// Settings the executor can't honor fail compilation instead of every call
+		let mut breaker = ToolDefinition::composition(
+			"guarded",
+			PatternSpec::Pipeline(PipelineSpec { steps: vec![] }),
+		);
+		breaker.resilience = Some(Resilience {
+			circuit_breaker: Some(CircuitBreakerShorthand {
+				store: "circuits".to_string(),
+				failure_threshold: 5,
+				failure_window_seconds: 60,
+				reset_timeout_seconds: 30,
+			}),
+			..Default::default()
+		});
+		let err = CompiledRegistry::compile(Registry::with_tool_definitions(vec![breaker]))
+			.unwrap_err()
+			.to_string();
+		assert!(err.contains("circuitBreaker"), "{err}");
+
+		let mut source = ToolDefinition::source("forecast", "weather", "get_forecast");
+		source.resilience = Some(Resilience {
+			retries: Some(1),
+			..Default::default()
+		});
+		assert!(matches!(
+			CompiledRegistry::compile(Registry::with_tool_definitions(vec![source])),
+			Err(RegistryError::CompilationError(_))
+		));
+	}
+
+	#[test]
+	fn test_cost_model_validation() {
+		use crate::mcp::registry::types::{CostModel, ReportedCost};
+
+		let compile = |cost: CostModel, def: ToolDefinition| {
+			let mut def = def;
+			def.cost = Some(cost);
+			CompiledRegistry::compile(Registry::with_tool_definitions(vec![def]))
+		};
+		let source = || ToolDefinition::source("llm", "openai", "complete");
+		let reported = |path: &str| {
+			CostModel::Reported(ReportedCost {
+				path: path.to_string(),
+				fallback: 0.0,
+			})
+		};
+
+		assert!(compile(reported("$.usage.cost"), source()).is_ok());
+		assert!(matches!(
+			compile(reported("usage.cost"), source()),
+			Err(RegistryError::InvalidJsonPath { .. })
+		));
+		assert!(matches!(
+			compile(CostModel::Static(-1.0), source()),
+			Err(RegistryError::CompilationError(_))
+		));
+		let composition = ToolDefinition::composition(
+			"pipeline",
+			PatternSpec::Pipeline(PipelineSpec { steps: vec![] }),
+		);
+		assert!(matches!(
+			compile(CostModel::Static(1.0), composition),
+			Err(RegistryError::CompilationError(_))
+		));
+	}
+
+	#[test]
+	fn test_template_does_not_expand_substituted_values() {
+		let transform: OutputTransform = serde_json::from_value(json!({
+			"mappings": {
+				"label": {"template": {
+					"template": "{a}-{b} {unknown} {",
+					"vars": {"a": "$.a", "b": "$.b"}
+				}}
+			}
+		}))
+		.unwrap();
+		let compiled = CompiledOutputTransform::compile(&transform).unwrap();
+
+		let result = compiled.apply(&json!({"a": "{b}", "b": "x"})).unwrap();
+		assert_eq!(result["label"], "{b}-x {unknown} {");
+	}
+
+	#[test]
+	fn test_find_json_in_text_adversarial_inputs() {
+		// Unbalanced and deeply nested inputs must fail fast rather than panic or overflow
+		let unclosed = "{".repeat(100_000);
+		assert!(find_json_in_text(&unclosed).is_none());
+		let deep = format!("{}{}", "[".repeat(100_000), "]".repeat(100_000));
+		assert!(find_json_in_text(&deep).is_none());
+		let unterminated = format!("{{\"{}", "\\\"".repeat(50_000));
+		assert!(find_json_in_text(&unterminated).is_none());
+	}
+
+	mod proptests {
+		use proptest::prelude::*;
+		use serde_json::{Value, json};
+
+		use super::super::*;
+
+		fn arb_json() -> impl Strategy<Value = Value> {
+			let leaf = prop_oneof![
+				Just(Value::Null),
+				any::<bool>().prop_map(Value::Bool),
+				any::<i64>().prop_map(|n| json!(n)),
+				".*".prop_map(Value::String),
+				"[{}\\[\\]\"\\\\a-c:, ]{0,16}".prop_map(Value::String),
+			];
+			leaf.prop_recursive(4, 64, 8, |inner| {
+				prop_oneof![
+					prop::collection::vec(inner.clone(), 0..8).prop_map(Value::Array),
+					prop::collection::btree_map("[a-c]{1,3}", inner, 0..8)
+						.prop_map(|m| Value::Object(m.into_iter().collect())),
+				]
+			})
+		}
+
+		/// Text made of the characters that drive the brace scanner
+		fn arb_json_like_text() -> impl Strategy<Value = String> {
+			"[{}\\[\\]\"\\\\a:, ]{0,256}"
+		}
+
+		fn transform() -> CompiledOutputTransform {
+			let transform: OutputTransform = serde_json::from_value(json!({
+				"mappings": {
+					"first": {"path": "$.a"},
+					"all": {"path": "$..b"},
+					"any": {"coalesce": {"paths": ["$.a", "$.b", "$[0]"]}},
+					"label": {"template": {
+						"template": "{a}/{b}/{a}{",
+						"vars": {"a": "$.a", "b": "$..c"}
+					}},
+					"joined": {"concat": {"paths": ["$.a", "$.b"], "separator": "-"}},
+					"items": {"path": "$.b"},
+					"items[*].name": {"path": "$.a"},
+					"orphan[*].name": {"path": "$.c"},
+					"nested": {"nested": {"mappings": {"inner": {"path": "$.a.b"}}}}
+				}
+			}))
+			.unwrap();
+			CompiledOutputTransform::compile(&transform).unwrap()
+		}
+
+		proptest! {
+			#[test]
+			fn apply_handles_arbitrary_json(input in arb_json()) {
+				let transform = transform();
+				let result = transform.apply(&input).unwrap();
+				let keys: Vec<&String> = result.as_object().unwrap().keys().collect();
+				prop_assert_eq!(keys.len(), 8);
+			}
+
+			#[test]
+			fn apply_handles_text_responses(text in arb_json_like_text()) {
+				let text = Value::String(text);
+				let response = extract_json_from_response(&text, &JsonLimits::default()).unwrap();
+				transform().apply(response.as_ref()).unwrap();
+			}
+
+			#[test]
+			fn find_json_in_text_never_panics(text in any::<String>()) {
+				find_json_in_text(&text);
+			}
+
+			#[test]
+			fn find_json_in_text_never_panics_on_json_like_text(text in arb_json_like_text()) {
+				find_json_in_text(&text);
+			}
+
+			#[test]
+			fn find_json_in_text_finds_embedded_json(
+				prefix in "[^\\[{]{0,32}",
+				value in arb_json(),
+				suffix in ".{0,32}",
+			) {
+				// Objects are preferred over arrays, so an array is only found when no object is
+				let text = format!("{prefix}{value}{suffix}");
+				prop_assume!(value.is_object() || (value.is_array() && !text.contains('{')));
+				prop_assert_eq!(find_json_in_text(&text), Some(value));
+			}
+
+			#[test]
+			fn template_substitutes_values_verbatim(a in ".*", b in ".*") {
+				let transform: OutputTransform = serde_json::from_value(json!({
+					"mappings": {"label": {"template": {
+						"template": "{a}-{b}",
+						"vars": {"a": "$.a", "b": "$.b"}
+					}}}
+				}))
+				.unwrap();
+				let compiled = CompiledOutputTransform::compile(&transform).unwrap();
+				let result = compiled.apply(&json!({"a": a, "b": b})).unwrap();
+				prop_assert_eq!(&result["label"], &json!(format!("{a}-{b}")));
+			}
+		}
+	}
 }
@@ -0,0 +1,75 @@
+// Confirmation of destructive tool calls
+//
+// With `confirmDestructive` on, a client has to confirm each call of a tool the registry
+// annotates `destructiveHint: true` by setting `agentgateway.dev/confirmed: true` in the
+// call's `_meta`. Unconfirmed calls are refused with an error saying how to confirm, so an
+// agent can't delete or overwrite anything without its client asking first. Only the tool
+// the client calls is checked: the steps of a composition run as part of the composition,
+// which is confirmed by annotating the composition itself.
+
+use serde_json::{Map, Value};
+
+use super::executor::ExecutionError;
+
+/// `_meta` key a caller sets to `true` to confirm a destructive call
+pub const CONFIRMED_META_KEY: &str = "agentgateway.dev/confirmed";
+
+/// Whether a request's `_meta` confirms the call
+pub fn confirmed_in_meta(meta: &Map<String, Value>) -> Result<bool, ExecutionError> {
+	match meta.get(CONFIRMED_META_KEY) {
+		None => Ok(false),
+		Some(Value::Bool(confirmed)) => Ok(*confirmed),
+		Some(_) => Err(ExecutionError::InvalidInput(format!(
+			"{CONFIRMED_META_KEY}: expected a boolean"
+		))),
+	}
+}
+
+/// Refuse a call of a destructive tool unless `meta` confirms it
+pub fn check_confirmation(
+	tool: &str,
+	meta: Option<&Map<String, Value>>,
+) -> Result<(), ExecutionError> {
+	if meta.map(confirmed_in_meta).transpose()?.unwrap_or(false) {
+		return Ok(());
+	}
+	Err(ExecutionError::InvalidInput(format!(
+		"tool {tool} is destructive and must be confirmed: set {CONFIRMED_META_KEY} to true in \
+		 the call's _meta"
+	)))
+}
+
+#[cfg(test)]
+mod tests {
+	use serde_json::json;
+
+	use super::*;
+
+	fn meta(value: Value) -> Map<String, Value> {
+		value.as_object().cloned().unwrap()
+	}
+
+	#[test]
+	fn test_check_confirmation() {
+		assert!(check_confirmation("drop_table", None).is_err());
+		assert!(check_confirmation("drop_table", Some(&meta(json!({})))).is_err());
+		let refused = check_confirmation(
+			"drop_table",
+			Some(&meta(json!({CONFIRMED_META_KEY: false}))),
+		)
+		.unwrap_err();
+		assert!(refused.to_string().contains(CONFIRMED_META_KEY));
+		assert!(
+			check_confirmation("drop_table", Some(&meta(json!({CONFIRMED_META_KEY: true})))).is_ok()
+		);
+		assert!(
+			check_confirmation(
+				"drop_table",
+				Some(&meta(json!({CONFIRMED_META_KEY: "yes"})))
+			)
+			.unwrap_err()
+			.to_string()
+			.contains("expected a boolean")
+		);
+	}
+}
@@ -0,0 +1,322 @@
+// Duplicate-call suppression
+//
+// Agents stuck in a retry loop send the same call over and over, often before the first one
+// has returned. A tool with a `debounce` window answers a repeat of an identical call (same
+// caller, tool and arguments) with the earlier call's result instead of invoking it again:
+// repeats that arrive while the call is in flight wait for it, and repeats within the window
+// after it succeeded get its result. A failure is only shared with the repeats that were
+// already waiting, so a retry after an error does reach the backend. A call every caller gave
+// up on, or one still pending past its window plus `PENDING_TIMEOUT`, is forgotten so that
+// hung or abandoned calls don't pile up. At most `max_calls` calls are tracked; a new call
+// beyond that runs without being debounced, rather than pushing out one that repeats may be
+// waiting for.
+
+use std::collections::HashMap;
+use std::future::Future;
+use std::time::{Duration, Instant};
+
+use futures::FutureExt;
+use futures::future::{BoxFuture, Shared};
+use parking_lot::Mutex;
+use serde_json::Value;
+use tracing::debug;
+
+/// What makes two calls identical
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct CallKey {
+	caller: String,
+	tool: String,
+	args: String,
+}
+
+impl CallKey {
+	pub fn new(caller: impl Into<String>, tool: impl Into<String>, args: &Value) -> Self {
+		Self {
+			caller: caller.into(),
+			tool: tool.into(),
+			args: canonical(args).to_string(),
+		}
+	}
+}
+
+/// `value` with object keys sorted, so arguments sent in a different key order match
+fn canonical(value: &Value) -> Value {
+	match value {
+		Value::Object(fields) => {
+			let mut sorted: Vec<_> = fields.iter().collect();
+			sorted.sort_by(|(a, _), (b, _)| a.cmp(b));
+			Value::Object(
+				sorted
+					.into_iter()
+					.map(|(key, value)| (key.clone(), canonical(value)))
+					.collect(),
+			)
+		},
+		Value::Array(items) => Value::Array(items.iter().map(canonical).collect()),
+		other => other.clone(),
+	}
+}
+
+/// How much longer than its window a call may stay pending before repeats stop waiting for it
+const PENDING_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// Default for the most calls tracked at once
+pub const DEFAULT_MAX_DEBOUNCED_CALLS: usize = 10_000;
+
+/// Outcome of a call, with the time it completed
+type SharedCall<T, E> = Shared<BoxFuture<'static, Result<(T, Instant), E>>>;
+
+struct Entry<T, E> {
+	call: SharedCall<T, E>,
+	window: Duration,
+	started: Instant,
+}
+
+impl<T: Clone, E: Clone> Entry<T, E> {
+	/// Whether a repeat of the call should still get this entry's result
+	fn is_live(&self) -> bool {
+		match self.call.peek() {
+			// A pending call held only by this entry has no caller left to drive it
+			None => {
+				self.call.strong_count() > Some(1) && self.started.elapsed() < self.window + PENDING_TIMEOUT
+			},
+			Some(Ok((_, completed))) => completed.elapsed() < self.window,
+			Some(Err(_)) => false,
+		}
+	}
+}
+
+/// Calls in flight or recently completed, keyed by caller, tool and arguments
+///
+/// `E` is shared between every caller of a call, so it must be cheap to clone.
+pub struct CallDebouncer<T, E> {
+	calls: Mutex<HashMap<CallKey, Entry<T, E>>>,
+	max_calls: usize,
+}
+
+impl<T, E> std::fmt::Debug for CallDebouncer<T, E> {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		f.debug_struct("CallDebouncer")
+			.field("len", &self.calls.lock().len())
+			.field("max_calls", &self.max_calls)
+			.finish()
+	}
+}
+
+impl<T, E> Default for CallDebouncer<T, E> {
+	fn default() -> Self {
+		Self::with_max_calls(DEFAULT_MAX_DEBOUNCED_CALLS)
+	}
+}
+
+impl<T, E> CallDebouncer<T, E> {
+	/// Create a debouncer tracking at most `max_calls` calls
+	pub fn with_max_calls(max_calls: usize) -> Self {
+		Self {
+			calls: Mutex::new(HashMap::new()),
+			max_calls,
+		}
+	}
+}
+
+impl<T, E> CallDebouncer<T, E>
+where
+	T: Clone + Send + Sync + 'static,
+	E: Clone + Send + Sync + 'static,
+{
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Run `call`, unless an identical call is in flight or succeeded within `window`
+	///
+	/// A repeat gets the earlier call's outcome, and `call` is dropped without being run.
+	pub async fn call<F>(&self, key: CallKey, window: Duration, call: F) -> Result<T, E>
+	where
+		F: Future<Output = Result<T, E>> + Send + 'static,
+	{
+		let shared = {
+			let mut calls = self.calls.lock();
+			calls.retain(|_, entry| entry.is_live());
+			match calls.get(&key) {
+				Some(entry) => {
+					debug!(target: "virtual_tools", tool = %key.tool, "suppressed duplicate call");
+					Ok(entry.call.clone())
+				},
+				None if calls.len() >= self.max_calls => Err(call),
+				None => {
+					let shared = call
+						.map(|result| result.map(|value| (value, Instant::now())))
+						.boxed()
+						.shared();
+					calls.insert(
+						key,
+						Entry {
+							call: shared.clone(),
+							window,
+							started: Instant::now(),
+						},
+					);
+					Ok(shared)
+				},
+			}
+		};
+		match shared {
+			Ok(shared) => shared.await.map(|(value, _)| value),
+			// Too many calls are tracked already, so this one isn't
+			Err(call) => call.await,
+		}
+	}
+
+	/// Number of calls in flight or within their window
+	pub fn len(&self) -> usize {
+		let mut calls = self.calls.lock();
+		calls.retain(|_, entry| entry.is_live());
+		calls.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.len() == 0
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::sync::Arc;
+	use std::sync::atomic::{AtomicU32, Ordering};
+
+	use serde_json::json;
+
+	use super::*;
+
+	fn counted(
+		calls: &Arc<AtomicU32>,
+		result: Result<u32, String>,
+	) -> BoxFuture<'static, Result<u32, String>> {
+		let calls = calls.clone();
+		async move {
+			let n = calls.fetch_add(1, Ordering::SeqCst) + 1;
+			tokio::time::sleep(Duration::from_millis(20)).await;
+			result.map(|v| v + n)
+		}
+		.boxed()
+	}
+
+	#[tokio::test]
+	async fn test_repeats_share_one_call() {
+		let debouncer = CallDebouncer::new();
+		let calls = Arc::new(AtomicU32::new(0));
+		let window = Duration::from_secs(60);
+		let key = || CallKey::new("agent-7", "search", &json!({"q": "mcp"}));
+
+		// Concurrent repeats wait for the call in flight
+		let (a, b) = tokio::join!(
+			debouncer.call(key(), window, counted(&calls, Ok(10))),
+			debouncer.call(key(), window, counted(&calls, Ok(20))),
+		);
+		assert_eq!((a, b), (Ok(11), Ok(11)));
+		// Later repeats within the window get the same result
+		let c = debouncer.call(key(), window, counted(&calls, Ok(30))).await;
+		assert_eq!(c, Ok(11));
+		assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+		// Another caller, tool or argument is a different call
+		let other = CallKey::new("agent-8", "search", &json!({"q": "mcp"}));
+		let d = debouncer.call(other, window, counted(&calls, Ok(40))).await;
+		assert_eq!(d, Ok(42));
+		let other = CallKey::new("agent-7", "search", &json!({"q": "a2a"}));
+		let e = debouncer.call(other, window, counted(&calls, Ok(50))).await;
+		assert_eq!(e, Ok(53));
+		assert_eq!(debouncer.len(), 3);
+	}
+
+	#[tokio::test]
+	async fn test_argument_order_is_ignored() {
+		let debouncer = CallDebouncer::new();
+		let calls = Arc::new(AtomicU32::new(0));
+		let window = Duration::from_secs(60);
+		let key = |args: &str| CallKey::new("agent-7", "search", &serde_json::from_str(args).unwrap());
+
+		let a = key(r#"{"q": "mcp", "filter": {"lang": "en", "tags": [{"b": 1, "a": 2}]}}"#);
+		let b = key(r#"{"filter": {"tags": [{"a": 2, "b": 1}], "lang": "en"}, "q": "mcp"}"#);
+		assert_eq!(a, b);
+		let first = debouncer.call(a, window, counted(&calls, Ok(10))).await;
+		let repeat = debouncer.call(b, window, counted(&calls, Ok(20))).await;
+		assert_eq!((first, repeat), (Ok(11), Ok(11)));
+		assert_eq!(calls.load(Ordering::SeqCst), 1);
+	}
+
+	#[tokio::test]
+	async fn test_failures_and_expired_results_are_retried() {
+		let debouncer = CallDebouncer::new();
+		let calls = Arc::new(AtomicU32::new(0));
+		let key = || CallKey::new("session-1", "publish", &json!({}));
+		let window = Duration::from_secs(60);
+
+		let (a, b) = tokio::join!(
+			debouncer.call(key(), window, counted(&calls, Err("down".into()))),
+			debouncer.call(key(), window, counted(&calls, Ok(0))),
+		);
+		assert_eq!(a, Err("down".to_string()));
+		assert_eq!(b, Err("down".to_string()));
+		let retry = debouncer
+			.call(key(), Duration::ZERO, counted(&calls, Ok(0)))
+			.await;
+		assert_eq!(retry, Ok(2));
+
+		// A zero window only suppresses repeats while the call is in flight
+		let again = debouncer
+			.call(key(), Duration::ZERO, counted(&calls, Ok(0)))
+			.await;
+		assert_eq!(again, Ok(3));
+		assert!(debouncer.is_empty());
+	}
+
+	#[tokio::test]
+	async fn test_calls_beyond_the_cap_are_not_debounced() {
+		let debouncer = CallDebouncer::with_max_calls(1);
+		let calls = Arc::new(AtomicU32::new(0));
+		let window = Duration::from_secs(60);
+		let key = |q: &str| CallKey::new("agent-7", "search", &json!({ "q": q }));
+
+		let first = debouncer
+			.call(key("a"), window, counted(&calls, Ok(0)))
+			.await;
+		assert_eq!(first, Ok(1));
+		// The tracked call keeps its place, and a new one runs every time
+		let b = debouncer
+			.call(key("b"), window, counted(&calls, Ok(0)))
+			.await;
+		let b_again = debouncer
+			.call(key("b"), window, counted(&calls, Ok(0)))
+			.await;
+		assert_eq!((b, b_again), (Ok(2), Ok(3)));
+		let repeat = debouncer
+			.call(key("a"), window, counted(&calls, Ok(0)))
+			.await;
+		assert_eq!(repeat, Ok(1));
+		assert_eq!(debouncer.len(), 1);
+	}
+
+	#[tokio::test]
+	async fn test_abandoned_calls_are_evicted() {
+		let debouncer = CallDebouncer::new();
+		let calls = Arc::new(AtomicU32::new(0));
+		let key = || CallKey::new("agent-7", "export", &json!({}));
+		let window = Duration::from_secs(60);
+
+		// The caller gives up on a call that never returns
+		let hung = debouncer.call(key(), window, futures::future::pending());
+		assert!(
+			tokio::time::timeout(Duration::from_millis(10), hung)
+				.await
+				.is_err()
+		);
+		assert!(debouncer.is_empty());
+
+		// A repeat runs the call rather than waiting on the abandoned one
+		let retry = debouncer.call(key(), window, counted(&calls, Ok(0))).await;
+		assert_eq!(retry, Ok(1));
+		assert_eq!(debouncer.len(), 1);
+	}
+}
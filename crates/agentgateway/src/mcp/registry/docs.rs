@@ -0,0 +1,846 @@
+// Reference documentation for a registry
+//
+// Renders a page per tool for a platform docs site: description, schemas, a mermaid diagram of
+// a composition's steps, the tools it calls and is called by, and version information. The
+// content comes from the same `ToolDoc` the `describe_tool` meta-tool returns, so the docs
+// site and agents see the same documentation. Only the docs site, which is for operators,
+// lists the backend fields a tool hides or fills in.
+//
+// Pages are built as a list of blocks and rendered as Markdown or as a standalone HTML page.
+// Diagrams are mermaid source either way; the HTML page loads mermaid to draw them.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write;
+use std::str::FromStr;
+
+use serde_json::Value;
+
+use super::catalog::ToolDoc;
+use super::compiled::{CompiledRegistry, CompiledTool};
+use super::patterns::{MapEachInner, PatternSpec, ScatterOperation, StepOperation};
+use super::types::ToolAnnotations;
+
+/// Output format of generated documentation
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DocsFormat {
+	#[default]
+	Markdown,
+	Html,
+}
+
+impl DocsFormat {
+	pub fn content_type(self) -> &'static str {
+		match self {
+			DocsFormat::Markdown => "text/markdown; charset=utf-8",
+			DocsFormat::Html => "text/html; charset=utf-8",
+		}
+	}
+}
+
+impl FromStr for DocsFormat {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		match s {
+			"markdown" | "md" => Ok(DocsFormat::Markdown),
+			"html" => Ok(DocsFormat::Html),
+			other => Err(format!(
+				"unknown docs format '{other}', expected 'markdown' or 'html'"
+			)),
+		}
+	}
+}
+
+/// Documentation of every tool in `registry`, with an index, sorted by name
+pub fn render_docs(registry: &CompiledRegistry, format: DocsFormat) -> String {
+	let used_by = used_by(registry);
+	let mut tools: Vec<&CompiledTool> = registry.tools().map(|t| t.as_ref()).collect();
+	tools.sort_by(|a, b| a.def.name.cmp(&b.def.name));
+
+	let mut blocks = vec![
+		Block::Heading(1, "Tool Reference".to_string()),
+		Block::Paragraph(vec![Inline::Text(format!(
+			"{} tools, generated from the registry.",
+			tools.len()
+		))]),
+	];
+	blocks.push(Block::List(
+		tools
+			.iter()
+			.map(|tool| {
+				let mut item = vec![
+					Inline::Link(tool.def.name.clone()),
+					Inline::Text(format!(" ({})", ToolDoc::from(*tool).summary.kind)),
+				];
+				if let Some(line) = tool
+					.def
+					.description
+					.as_deref()
+					.and_then(|d| d.lines().next())
+				{
+					item.push(Inline::Text(format!(": {line}")));
+				}
+				item
+			})
+			.collect(),
+	));
+	for tool in tools {
+		blocks.extend(tool_blocks(registry, tool, &used_by));
+	}
+	render(&blocks, "Tool Reference", format)
+}
+
+/// Documentation of one tool, if `registry` has it
+pub fn render_tool_docs(
+	registry: &CompiledRegistry,
+	name: &str,
+	format: DocsFormat,
+) -> Option<String> {
+	let tool = registry.get_tool(name)?;
+	let blocks = tool_blocks(registry, tool, &used_by(registry));
+	Some(render(&blocks, name, format))
+}
+
+/// Mermaid flowchart of a composition's steps, from its input to its output
+///
+/// Steps that run a nested pattern are drawn as a subgraph named after the step, and calls to
+/// registry tools link to their documentation.
+pub fn composition_diagram(registry: &CompiledRegistry, spec: &PatternSpec) -> String {
+	let mut diagram = Diagram::new(registry);
+	let input = diagram.node(Shape::Terminal, "input");
+	let exits = diagram.pattern(spec, &[input]);
+	let output = diagram.node(Shape::Terminal, "output");
+	diagram.edges(&exits, &output, None);
+	diagram.finish()
+}
+
+/// Compositions calling each tool
+fn used_by(registry: &CompiledRegistry) -> BTreeMap<&str, BTreeSet<&str>> {
+	let mut used_by: BTreeMap<&str, BTreeSet<&str>> = BTreeMap::new();
+	for tool in registry.tools() {
+		for reference in tool
+			.composition_info()
+			.into_iter()
+			.flat_map(|c| &c.resolved_references)
+		{
+			used_by
+				.entry(reference.as_str())
+				.or_default()
+				.insert(tool.def.name.as_str());
+		}
+	}
+	used_by
+}
+
+fn tool_blocks(
+	registry: &CompiledRegistry,
+	tool: &CompiledTool,
+	used_by: &BTreeMap<&str, BTreeSet<&str>>,
+) -> Vec<Block> {
+	let doc = ToolDoc::from(tool);
+	let mut blocks = vec![Block::Heading(2, doc.summary.name.clone())];
+	if let Some(notice) = &doc.deprecated {
+		blocks.push(Block::Notice(format!("Deprecated: {notice}")));
+	}
+	if let Some(description) = &doc.description {
+		blocks.push(Block::Paragraph(vec![Inline::Text(description.clone())]));
+	}
+
+	let mut fields = vec![("Type", vec![Inline::Code(doc.summary.kind.to_string())])];
+	if let Some(version) = &doc.summary.version {
+		fields.push(("Version", vec![Inline::Code(version.clone())]));
+	}
+	if let Some(source) = tool.source_info() {
+		fields.push((
+			"Backend",
+			vec![Inline::Code(format!(
+				"{}/{}",
+				source.source.target, source.source.tool
+			))],
+		));
+	}
+	if !doc.tags.is_empty() {
+		fields.push(("Tags", codes(&doc.tags)));
+	}
+	if let Some(hints) = doc.annotations.as_ref().map(hints)
+		&& !hints.is_empty()
+	{
+		fields.push(("Hints", vec![Inline::Text(hints.join(", "))]));
+	}
+	if !doc.hidden_fields.is_empty() {
+		fields.push(("Hidden fields", codes(&doc.hidden_fields)));
+	}
+	if !doc.injected_fields.is_empty() {
+		fields.push(("Injected fields", codes(&doc.injected_fields)));
+	}
+	if !doc.descriptions.is_empty() {
+		fields.push((
+			"Locales",
+			codes(&doc.descriptions.keys().cloned().collect::<Vec<_>>()),
+		));
+	}
+	blocks.push(Block::Fields(fields));
+
+	for (title, schema) in [
+		("Input schema", &doc.input_schema),
+		("Output schema", &doc.output_schema),
+	] {
+		if let Some(schema) = schema {
+			blocks.push(Block::Heading(3, title.to_string()));
+			blocks.push(Block::Code("json", pretty(schema)));
+		}
+	}
+
+	if let Some(composition) = tool.composition_info() {
+		blocks.push(Block::Heading(3, "Composition".to_string()));
+		blocks.push(Block::Code(
+			"mermaid",
+			composition_diagram(registry, &composition.spec),
+		));
+	}
+
+	let calls: BTreeSet<&str> = tool
+		.composition_info()
+		.into_iter()
+		.flat_map(|c| c.resolved_references.iter().map(String::as_str))
+		.collect();
+	let callers = used_by.get(doc.summary.name.as_str());
+	if !calls.is_empty() || callers.is_some() {
+		let mut dependencies = Vec::new();
+		if !calls.is_empty() {
+			dependencies.push(("Calls", links(registry, calls.iter().copied())));
+		}
+		if let Some(callers) = callers {
+			dependencies.push(("Used by", links(registry, callers.iter().copied())));
+		}
+		blocks.push(Block::Heading(3, "Dependencies".to_string()));
+		blocks.push(Block::Fields(dependencies));
+	}
+
+	if !doc.examples.is_empty() {
+		blocks.push(Block::Heading(3, "Examples".to_string()));
+		for example in &doc.examples {
+			if let Some(description) = example.get("description").and_then(Value::as_str) {
+				blocks.push(Block::Paragraph(vec![Inline::Text(
+					description.to_string(),
+				)]));
+			}
+			let example = match example {
+				Value::Object(example) if example.contains_key("input") => {
+					let mut example = example.clone();
+					example.remove("description");
+					Value::Object(example)
+				},
+				other => other.clone(),
+			};
+			blocks.push(Block::Code("json", pretty(&example)));
+		}
+	}
+	blocks
+}
+
+fn hints(annotations: &ToolAnnotations) -> Vec<&'static str> {
+	[
+		(annotations.read_only_hint, "read-only"),
+		(annotations.destructive_hint, "destructive"),
+		(annotations.idempotent_hint, "idempotent"),
+		(annotations.open_world_hint, "open world"),
+	]
+	.into_iter()
+	.filter_map(|(hint, name)| (hint == Some(true)).then_some(name))
+	.collect()
+}
+
+fn codes(values: &[String]) -> Vec<Inline> {
+	separated(values.iter().map(|v| Inline::Code(v.clone())))
+}
+
+/// Links to the registry tools among `names`, and the other names as code
+fn links<'a>(registry: &CompiledRegistry, names: impl Iterator<Item = &'a str>) -> Vec<Inline> {
+	separated(names.map(|name| match registry.get_tool(name) {
+		Some(_) => Inline::Link(name.to_string()),
+		None => Inline::Code(name.to_string()),
+	}))
+}
+
+fn separated(items: impl Iterator<Item = Inline>) -> Vec<Inline> {
+	let mut inlines = Vec::new();
+	for item in items {
+		if !inlines.is_empty() {
+			inlines.push(Inline::Text(", ".to_string()));
+		}
+		inlines.push(item);
+	}
+	inlines
+}
+
+fn pretty(value: &Value) -> String {
+	serde_json::to_string_pretty(value).unwrap_or_else(|_| value.to_string())
+}
+
+/// Anchor of a tool's heading, as GitHub and most Markdown renderers derive it
+fn anchor(name: &str) -> String {
+	name
+		.to_lowercase()
+		.chars()
+		.filter_map(|c| match c {
+			' ' => Some('-'),
+			c if c.is_alphanumeric() || c == '-' || c == '_' => Some(c),
+			_ => None,
+		})
+		.collect()
+}
+
+enum Block {
+	Heading(u8, String),
+	Paragraph(Vec<Inline>),
+	/// Name/value pairs
+	Fields(Vec<(&'static str, Vec<Inline>)>),
+	/// Fenced code with its language
+	Code(&'static str, String),
+	List(Vec<Vec<Inline>>),
+	Notice(String),
+}
+
+enum Inline {
+	Text(String),
+	Code(String),
+	/// Link to a tool's documentation
+	Link(String),
+}
+
+fn render(blocks: &[Block], title: &str, format: DocsFormat) -> String {
+	match format {
+		DocsFormat::Markdown => markdown(blocks),
+		DocsFormat::Html => html(blocks, title),
+	}
+}
+
+fn markdown(blocks: &[Block]) -> String {
+	let inlines = |inlines: &[Inline]| -> String {
+		inlines
+			.iter()
+			.map(|inline| match inline {
+				Inline::Text(text) => text.clone(),
+				Inline::Code(code) => code_span(code),
+				Inline::Link(name) => format!("[{}](#{})", code_span(name), anchor(name)),
+			})
+			.collect()
+	};
+	let mut out = String::new();
+	for block in blocks {
+		match block {
+			Block::Heading(level, text) => {
+				let _ = writeln!(out, "{} {}\n", "#".repeat(*level as usize), text);
+			},
+			Block::Paragraph(text) => {
+				let _ = writeln!(out, "{}\n", inlines(text));
+			},
+			Block::Fields(fields) => {
+				out.push_str("| | |\n|---|---|\n");
+				for (name, value) in fields {
+					let value = inlines(value).replace('|', "\\|").replace('\n', " ");
+					let _ = writeln!(out, "| {name} | {value} |");
+				}
+				out.push('\n');
+			},
+			Block::Code(lang, code) => {
+				let _ = writeln!(out, "```{lang}\n{code}\n```\n");
+			},
+			Block::List(items) => {
+				for item in items {
+					let _ = writeln!(out, "- {}", inlines(item));
+				}
+				out.push('\n');
+			},
+			Block::Notice(text) => {
+				let _ = writeln!(out, "> **{text}**\n");
+			},
+		}
+	}
+	out
+}
+
+/// A code span that holds `code` even if it contains backticks
+fn code_span(code: &str) -> String {
+	if code.contains('`') {
+		format!("`` {code} ``")
+	} else {
+		format!("`{code}`")
+	}
+}
+
+const MERMAID_SCRIPT: &str = r#"<script type="module">
+import mermaid from "https://cdn.jsdelivr.net/npm/mermaid@11/dist/mermaid.esm.min.mjs";
+mermaid.initialize({ startOnLoad: true });
+</script>"#;
+
+fn html(blocks: &[Block], title: &str) -> String {
+	let inlines = |inlines: &[Inline]| -> String {
+		inlines
+			.iter()
+			.map(|inline| match inline {
+				Inline::Text(text) => escape(text),
+				Inline::Code(code) => format!("<code>{}</code>", escape(code)),
+				Inline::Link(name) => format!(
+					"<a href=\"#{}\"><code>{}</code></a>",
+					escape(&anchor(name)),
+					escape(name)
+				),
+			})
+			.collect()
+	};
+	let mut body = String::new();
+	let mut has_diagram = false;
+	for block in blocks {
+		match block {
+			// Tool headings are link targets
+			Block::Heading(2, text) => {
+				let _ = writeln!(
+					body,
+					"<h2 id=\"{}\">{}</h2>",
+					escape(&anchor(text)),
+					escape(text)
+				);
+			},
+			Block::Heading(level, text) => {
+				let _ = writeln!(body, "<h{level}>{}</h{level}>", escape(text));
+			},
+			Block::Paragraph(text) => {
+				let _ = writeln!(body, "<p>{}</p>", inlines(text));
+			},
+			Block::Fields(fields) => {
+				body.push_str("<table>\n");
+				for (name, value) in fields {
+					let _ = writeln!(body, "<tr><th>{name}</th><td>{}</td></tr>", inlines(value));
+				}
+				body.push_str("</table>\n");
+			},
+			Block::Code("mermaid", code) => {
+				has_diagram = true;
+				let _ = writeln!(body, "<pre class=\"mermaid\">\n{}\n</pre>", escape(code));
+			},
+			Block::Code(lang, code) => {
+				let _ = writeln!(
+					body,
+					"<pre><code class=\"language-{lang}\">{}</code></pre>",
+					escape(code)
+				);
+			},
+			Block::List(items) => {
+				body.push_str("<ul>\n");
+				for item in items {
+					let _ = writeln!(body, "<li>{}</li>", inlines(item));
+				}
+				body.push_str("</ul>\n");
+			},
+			Block::Notice(text) => {
+				let _ = writeln!(
+					body,
+					"<blockquote><strong>{}</strong></blockquote>",
+					escape(text)
+				);
+			},
+		}
+	}
+	format!(
+		"<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n</head>\n<body>\n{}{}</body>\n</html>\n",
+		escape(title),
+		body,
+		if has_diagram {
+			format!("{MERMAID_SCRIPT}\n")
+		} else {
+			String::new()
+		}
+	)
+}
+
+fn escape(text: &str) -> String {
+	let mut escaped = String::with_capacity(text.len());
+	for c in text.chars() {
+		match c {
+			'&' => escaped.push_str("&amp;"),
+			'<' => escaped.push_str("&lt;"),
+			'>' => escaped.push_str("&gt;"),
+			'"' => escaped.push_str("&quot;"),
+			c => escaped.push(c),
+		}
+	}
+	escaped
+}
+
+#[derive(Clone, Copy)]
+enum Shape {
+	/// Composition input and output
+	Terminal,
+	/// Call to a virtual tool or backend
+	Tool,
+	/// Call to another composition
+	Composition,
+	/// A pattern's own processing (fan-in, retry, filter, ...)
+	Control,
+}
+
+/// Mermaid flowchart under construction
+///
+/// Edges are kept apart from nodes and written last, since mermaid moves a node into the
+/// subgraph of the first line that mentions it.
+struct Diagram<'a> {
+	registry: &'a CompiledRegistry,
+	nodes: Vec<String>,
+	edges: Vec<String>,
+	links: Vec<String>,
+	count: usize,
+	depth: usize,
+}
+
+impl<'a> Diagram<'a> {
+	fn new(registry: &'a CompiledRegistry) -> Self {
+		Self {
+			registry,
+			nodes: Vec::new(),
+			edges: Vec::new(),
+			links: Vec::new(),
+			count: 0,
+			depth: 1,
+		}
+	}
+
+	fn finish(self) -> String {
+		let mut out = "flowchart TD".to_string();
+		for line in self.nodes.iter().chain(&self.edges).chain(&self.links) {
+			out.push('\n');
+			out.push_str(line);
+		}
+		out
+	}
+
+	fn id(&mut self) -> String {
+		self.count += 1;
+		format!("n{}", self.count)
+	}
+
+	fn node(&mut self, shape: Shape, label: &str) -> String {
+		let id = self.id();
+		let (open, close) = match shape {
+			Shape::Terminal => ("([", "])"),
+			Shape::Tool => ("[", "]"),
+			Shape::Composition => ("[[", "]]"),
+			Shape::Control => ("{{", "}}"),
+		};
+		let line = format!("{id}{open}\"{}\"{close}", label.replace('"', "#quot;"));
+		self
+			.nodes
+			.push(format!("{}{line}", "  ".repeat(self.depth)));
+		id
+	}
+
+	fn edges(&mut self, from: &[String], to: &str, label: Option<&str>) {
+		for from in from {
+			self.edges.push(match label {
+				Some(label) => format!("  {from} -.->|\"{}\"| {to}", label.replace('"', "#quot;")),
+				None => format!("  {from} --> {to}"),
+			});
+		}
+	}
+
+	/// A pattern's own node, reached from `from`
+	fn control(&mut self, label: &str, from: &[String]) -> String {
+		let id = self.node(Shape::Control, label);
+		self.edges(from, &id, None);
+		id
+	}
+
+	/// A call to `name`, labeled `label`, reached from `from`
+	fn call(&mut self, name: &str, label: &str, from: &[String]) -> Vec<String> {
+		let tool = self.registry.get_tool(name);
+		let shape = match tool {
+			Some(tool) if tool.composition_info().is_some() => Shape::Composition,
+			_ => Shape::Tool,
+		};
+		let id = self.node(shape, label);
+		self.edges(from, &id, None);
+		if tool.is_some() {
+			self
+				.links
+				.push(format!("  click {id} href \"#{}\"", anchor(name)));
+		}
+		vec![id]
+	}
+
+	/// Start of a path taken instead of the main one, such as a timeout's fallback
+	fn junction(&mut self, from: &str, when: &str) -> [String; 1] {
+		let id = self.id();
+		self
+			.nodes
+			.push(format!("{}{id}((\" \"))", "  ".repeat(self.depth)));
+		self.edges(&[from.to_string()], &id, Some(when));
+		[id]
+	}
+
+	fn subgraph(&mut self, label: &str, f: impl FnOnce(&mut Self) -> Vec<String>) -> Vec<String> {
+		let id = self.id();
+		self.nodes.push(format!(
+			"{}subgraph {id} [\"{}\"]",
+			"  ".repeat(self.depth),
+			label.replace('"', "#quot;")
+		));
+		self.depth += 1;
+		let exits = f(self);
+		self.depth -= 1;
+		self.nodes.push(format!("{}end", "  ".repeat(self.depth)));
+		exits
+	}
+
+	/// Draw `spec` reached from `from`, returning the nodes its result comes from
+	fn pattern(&mut self, spec: &PatternSpec, from: &[String]) -> Vec<String> {
+		match spec {
+			PatternSpec::Pipeline(p) => p.steps.iter().fold(from.to_vec(), |from, step| {
+				self.step(&step.id, &step.operation, &from)
+			}),
+			PatternSpec::ScatterGather(sg) => {
+				let mut exits = Vec::new();
+				for target in &sg.targets {
+					exits.extend(match &target.operation {
+						ScatterOperation::Tool(name) => self.call(name, name, from),
+						ScatterOperation::Pattern(p) => self.pattern(p, from),
+					});
+				}
+				vec![self.control("gather", &exits)]
+			},
+			PatternSpec::MapEach(me) => {
+				let each = [self.control("for each item", from)];
+				match &me.inner {
+					MapEachInner::Tool(name) => self.call(name, name, &each),
+					MapEachInner::Pattern(p) => self.pattern(p, &each),
+				}
+			},
+			PatternSpec::Retry(r) => {
+				let retry = self.control(&format!("retry, up to {} attempts", r.max_attempts), from);
+				self.operation(&r.inner, &[retry])
+			},
+			PatternSpec::Timeout(t) => {
+				let timeout = self.control(&format!("timeout {}ms", t.duration_ms), from);
+				let mut exits = self.operation(&t.inner, std::slice::from_ref(&timeout));
+				if let Some(fallback) = &t.fallback {
+					let start = self.junction(&timeout, "on timeout");
+					exits.extend(self.operation(fallback, &start));
+				}
+				exits
+			},
+			PatternSpec::CircuitBreaker(cb) => {
+				let breaker = self.control("circuit breaker", from);
+				let mut exits = self.operation(&cb.inner, std::slice::from_ref(&breaker));
+				if let Some(fallback) = &cb.fallback {
+					let start = self.junction(&breaker, "when open");
+					exits.extend(self.operation(fallback, &start));
+				}
+				exits
+			},
+			PatternSpec::DeadLetter(dl) => {
+				let dead_letter = self.control("dead letter", from);
+				let exits = self.operation(&dl.inner, std::slice::from_ref(&dead_letter));
+				let failed = self.junction(&dead_letter, "on failure");
+				self.call(&dl.dead_letter_tool, &dl.dead_letter_tool, &failed);
+				exits
+			},
+			PatternSpec::Cache(c) => {
+				let cache = self.control("cache", from);
+				self.operation(&c.inner, &[cache])
+			},
+			PatternSpec::Idempotent(i) => {
+				let idempotent = self.control("idempotent", from);
+				self.operation(&i.inner, &[idempotent])
+			},
+			PatternSpec::ClaimCheck(cc) => {
+				let claim_check = self.control("claim check", from);
+				self.operation(&cc.inner, &[claim_check])
+			},
+			PatternSpec::Throttle(t) => {
+				let throttle = self.control(&format!("throttle, {} per {}ms", t.rate, t.window_ms), from);
+				self.operation(&t.inner, &[throttle])
+			},
+			// Patterns without an inner operation: their own node, then any tools they call
+			other => {
+				let node = [self.control(other.pattern_name(), from)];
+				let tools = other.referenced_tools();
+				if tools.is_empty() {
+					return node.to_vec();
+				}
+				tools
+					.into_iter()
+					.flat_map(|name| self.call(name, name, &node))
+					.collect()
+			},
+		}
+	}
+
+	/// A wrapped operation, such as a retry's inner operation
+	fn operation(&mut self, op: &StepOperation, from: &[String]) -> Vec<String> {
+		match op {
+			StepOperation::Tool(tc) => self.call(&tc.name, &tc.name, from),
+			StepOperation::Composition(cc) => self.call(&cc.name, &cc.name, from),
+			StepOperation::Pattern(p) => self.pattern(p, from),
+			other => vec![self.control(operation_kind(other), from)],
+		}
+	}
+
+	/// A pipeline step, labeled with its id
+	fn step(&mut self, id: &str, op: &StepOperation, from: &[String]) -> Vec<String> {
+		match op {
+			StepOperation::Tool(tc) if tc.name == id => self.call(&tc.name, id, from),
+			StepOperation::Tool(tc) => self.call(&tc.name, &format!("{id}: {}", tc.name), from),
+			StepOperation::Composition(cc) if cc.name == id => self.call(&cc.name, id, from),
+			StepOperation::Composition(cc) => self.call(&cc.name, &format!("{id}: {}", cc.name), from),
+			StepOperation::Pattern(p) => self.subgraph(id, |d| d.pattern(p, from)),
+			other => vec![self.control(&format!("{id}: {}", operation_kind(other)), from)],
+		}
+	}
+}
+
+fn operation_kind(op: &StepOperation) -> &'static str {
+	match op {
+		StepOperation::Tool(_) => "tool",
+		StepOperation::Pattern(_) => "pattern",
+		StepOperation::Composition(_) => "composition",
+		StepOperation::Assert(_) => "assert",
+		StepOperation::Script(_) => "script",
+		StepOperation::Sample(_) => "sample",
+		StepOperation::Patch(_) => "patch",
+		StepOperation::Constant(_) => "constant",
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use serde_json::json;
+
+	use super::*;
+	use crate::mcp::registry::types::Registry;
+
+	fn registry() -> CompiledRegistry {
+		let registry: Registry = serde_json::from_value(json!({
+			"schemaVersion": "1.0",
+			"tools": [
+				{
+					"name": "search",
+					"description": "Search the web",
+					"source": {
+						"target": "backend",
+						"tool": "web_search",
+						"defaults": {"api_key": "${SEARCH_KEY}"},
+						"hideFields": ["api_key"]
+					},
+					"version": "2.1.0",
+					"metadata": {"tags": ["web"], "deprecated": "use search_v3"}
+				},
+				{
+					"name": "cached_search",
+					"source": {"target": "cache", "tool": "lookup"}
+				},
+				{
+					"name": "research",
+					"description": "Search then <summarize>",
+					"spec": {
+						"pipeline": {
+							"steps": [
+								{"id": "find", "operation": {"tool": {"name": "search"}}},
+								{"id": "search", "operation": {"pattern": {"timeout": {
+									"inner": {"tool": {"name": "search"}},
+									"durationMs": 500,
+									"fallback": {"tool": {"name": "cached_search"}}
+								}}}}
+							]
+						}
+					},
+					"annotations": {"readOnlyHint": true},
+					"examples": [{"description": "Research a topic", "input": {"query": "mcp"}}]
+				}
+			]
+		}))
+		.unwrap();
+		CompiledRegistry::compile(registry).unwrap()
+	}
+
+	#[test]
+	fn test_composition_diagram() {
+		let registry = registry();
+		let research = registry.get_tool("research").unwrap();
+		let diagram = composition_diagram(&registry, &research.composition_info().unwrap().spec);
+		assert_eq!(
+			diagram,
+			r##"flowchart TD
+  n1(["input"])
+  n2["find: search"]
+  subgraph n3 ["search"]
+    n4{{"timeout 500ms"}}
+    n5["search"]
+    n6((" "))
+    n7["cached_search"]
+  end
+  n8(["output"])
+  n1 --> n2
+  n2 --> n4
+  n4 --> n5
+  n4 -.->|"on timeout"| n6
+  n6 --> n7
+  n5 --> n8
+  n7 --> n8
+  click n2 href "#search"
+  click n5 href "#search"
+  click n7 href "#cached_search""##
+		);
+	}
+
+	#[test]
+	fn test_markdown_docs() {
+		let registry = registry();
+		let docs = render_docs(&registry, DocsFormat::Markdown);
+		assert!(docs.starts_with("# Tool Reference\n\n3 tools, generated from the registry."));
+		// Index, sorted by name
+		assert!(docs.contains(
+			"- [`cached_search`](#cached_search) (source)\n- [`research`](#research) (pipeline): Search then <summarize>\n- [`search`](#search) (source): Search the web\n"
+		));
+		assert!(docs.contains("## search\n\n> **Deprecated: use search_v3**\n\nSearch the web\n"));
+		assert!(
+			docs
+				.contains("| Version | `2.1.0` |\n| Backend | `backend/web_search` |\n| Tags | `web` |\n")
+		);
+		assert!(docs.contains("| Hidden fields | `api_key` |\n| Injected fields | `api_key` |\n"));
+		assert!(docs.contains("| Used by | [`research`](#research) |"));
+		assert!(docs.contains("| Calls | [`cached_search`](#cached_search), [`search`](#search) |"));
+		assert!(docs.contains("| Hints | read-only |"));
+		assert!(docs.contains("### Composition\n\n```mermaid\nflowchart TD\n"));
+		assert!(docs.contains("### Examples\n\nResearch a topic\n\n```json\n{\n  \"input\": {\n    \"query\": \"mcp\"\n  }\n}\n```"));
+		// Injected values are never included
+		assert!(!docs.contains("SEARCH_KEY"));
+
+		let docs = render_tool_docs(&registry, "cached_search", DocsFormat::Markdown).unwrap();
+		assert!(docs.starts_with("## cached_search\n"));
+		assert!(render_tool_docs(&registry, "missing", DocsFormat::Markdown).is_none());
+	}
+
+	#[test]
+	fn test_html_docs() {
+		let registry = registry();
+		let docs = render_docs(&registry, DocsFormat::Html);
+		assert!(docs.starts_with("<!DOCTYPE html>"));
+		assert!(
+			docs.contains("<h2 id=\"research\">research</h2>\n<p>Search then &lt;summarize&gt;</p>")
+		);
+		assert!(docs.contains("<pre class=\"mermaid\">\nflowchart TD\n"));
+		assert!(docs.contains("mermaid.initialize"));
+
+		// No diagrams, no script
+		let docs = render_tool_docs(&registry, "search", DocsFormat::Html).unwrap();
+		assert!(docs.contains("<a href=\"#research\"><code>research</code></a>"));
+		assert!(!docs.contains("mermaid"));
+	}
+
+	#[test]
+	fn test_docs_format() {
+		assert_eq!("md".parse(), Ok(DocsFormat::Markdown));
+		assert_eq!("html".parse(), Ok(DocsFormat::Html));
+		assert!("pdf".parse::<DocsFormat>().is_err());
+	}
+}
@@ -0,0 +1,318 @@
+// Backend tool schema drift detection for virtual tools
+//
+// A virtual tool is written against the backend tool schema of the day: it hides some
+// fields and fills others with defaults. When the backend later changes that schema (e.g. a
+// hidden field becomes required) calls start failing with backend errors agents can't act
+// on. The tracker compares each listed backend schema with the virtual tools built on it and
+// reports the mismatches once, optionally withdrawing the affected virtual tools.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use parking_lot::Mutex;
+use rmcp::model::{JsonObject, Tool};
+use serde::Serialize;
+use serde_json::Value;
+use tracing::{info, warn};
+
+use super::arguments::matches_type;
+use super::compiled::{CompiledRegistry, CompiledTool};
+
+/// What to do with a virtual tool whose backend schema changed incompatibly
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SchemaDriftPolicy {
+	/// Log the incompatibility and keep serving the tool
+	#[default]
+	Warn,
+	/// Log the incompatibility and withdraw the tool until the schemas match again
+	Disable,
+}
+
+/// A mismatch between a virtual tool and its backend tool's schema
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DriftIssue {
+	pub message: String,
+	/// Whether calls through the virtual tool are expected to fail
+	pub breaking: bool,
+}
+
+/// Backend schemas seen at list time and the drift found against them, kept across reloads
+#[derive(Debug, Default)]
+pub struct SchemaDriftTracker {
+	policy: SchemaDriftPolicy,
+	inner: Mutex<DriftInner>,
+}
+
+#[derive(Debug, Default)]
+struct DriftInner {
+	/// (target, backend tool) -> input schema from the last list
+	seen: HashMap<(String, String), Arc<JsonObject>>,
+	/// Virtual tool -> issues reported for it
+	issues: HashMap<String, Vec<DriftIssue>>,
+	/// Virtual tool -> why it is withdrawn
+	disabled: HashMap<String, String>,
+}
+
+impl SchemaDriftTracker {
+	pub fn new(policy: SchemaDriftPolicy) -> Self {
+		Self {
+			policy,
+			inner: Mutex::default(),
+		}
+	}
+
+	/// Check the virtual tools built on `backend_tools` against their current schemas
+	///
+	/// Issues are logged when they first appear or change, not on every list.
+	pub fn observe(&self, registry: &CompiledRegistry, backend_tools: &[(String, Tool)]) {
+		let mut inner = self.inner.lock();
+		for (target, tool) in backend_tools {
+			let Some(virtual_names) = registry.get_virtual_names(target, tool.name.as_ref()) else {
+				continue;
+			};
+			let key = (target.clone(), tool.name.to_string());
+			if let Some(previous) = inner.seen.insert(key, tool.input_schema.clone())
+				&& previous != tool.input_schema
+			{
+				info!(
+					target: "virtual_tools",
+					backend_target = %target,
+					backend_tool = %tool.name,
+					"backend tool schema changed"
+				);
+			}
+			for name in virtual_names {
+				if let Some(compiled) = registry.get_tool(name) {
+					let issues = check_compatibility(compiled, &tool.input_schema);
+					inner.report(name, issues, self.policy);
+				}
+			}
+		}
+	}
+
+	/// Input schema of a backend tool from the last list, if it has been listed
+	pub fn backend_schema(&self, target: &str, tool: &str) -> Option<Arc<JsonObject>> {
+		let key = (target.to_string(), tool.to_string());
+		self.inner.lock().seen.get(&key).cloned()
+	}
+
+	/// Why a virtual tool is withdrawn, if it is
+	pub fn disabled_reason(&self, name: &str) -> Option<String> {
+		self.inner.lock().disabled.get(name).cloned()
+	}
+
+	pub fn is_disabled(&self, name: &str) -> bool {
+		self.inner.lock().disabled.contains_key(name)
+	}
+
+	/// Current issues per virtual tool
+	pub fn issues(&self) -> HashMap<String, Vec<DriftIssue>> {
+		self.inner.lock().issues.clone()
+	}
+}
+
+impl DriftInner {
+	fn report(&mut self, name: &str, issues: Vec<DriftIssue>, policy: SchemaDriftPolicy) {
+		match self.issues.get(name) {
+			Some(previous) if *previous == issues => return,
+			None if issues.is_empty() => return,
+			_ => {},
+		}
+		if issues.is_empty() {
+			info!(target: "virtual_tools", virtual_tool = name, "virtual tool matches its backend schema again");
+			self.issues.remove(name);
+			self.disabled.remove(name);
+			return;
+		}
+
+		for issue in &issues {
+			warn!(
+				target: "virtual_tools",
+				virtual_tool = name,
+				breaking = issue.breaking,
+				"backend schema drift: {}",
+				issue.message
+			);
+		}
+		let breaking: Vec<_> = issues
+			.iter()
+			.filter(|i| i.breaking)
+			.map(|i| i.message.as_str())
+			.collect();
+		if policy == SchemaDriftPolicy::Disable && !breaking.is_empty() {
+			warn!(target: "virtual_tools", virtual_tool = name, "disabling virtual tool until its backend schema is compatible");
+			self.disabled.insert(name.to_string(), breaking.join("; "));
+		} else {
+			self.disabled.remove(name);
+		}
+		self.issues.insert(name.to_string(), issues);
+	}
+}
+
+/// Compare a source-based virtual tool's hidden fields and defaults with the backend schema
+pub fn check_compatibility(tool: &CompiledTool, backend_schema: &JsonObject) -> Vec<DriftIssue> {
+	let Some(source) = tool.source_info().map(|s| &s.source) else {
+		return Vec::new();
+	};
+	let properties = backend_schema.get("properties").and_then(Value::as_object);
+	let required: Vec<&str> = backend_schema
+		.get("required")
+		.and_then(Value::as_array)
+		.map(|r| r.iter().filter_map(Value::as_str).collect())
+		.unwrap_or_default();
+	let backend = format!("{}/{}", source.target, source.tool);
+
+	let mut issues = Vec::new();
+	for field in &source.hide_fields {
+		if required.contains(&field.as_str()) && !source.defaults.contains_key(field) {
+			issues.push(DriftIssue {
+				message: format!("hidden field '{field}' is required by {backend} but has no default"),
+				breaking: true,
+			});
+		}
+	}
+
+	let mut defaults: Vec<_> = source.defaults.iter().collect();
+	defaults.sort_by_key(|(field, _)| field.as_str());
+	for (field, value) in defaults {
+		let Some(properties) = properties else {
+			continue;
+		};
+		let Some(property) = properties.get(field) else {
+			issues.push(DriftIssue {
+				message: format!("default '{field}' is no longer accepted by {backend}"),
+				breaking: false,
+			});
+			continue;
+		};
+		if let Some(expected) = property.get("type")
+			&& !matches_type(value, expected)
+		{
+			issues.push(DriftIssue {
+				message: format!("default '{field}' does not match type {expected} expected by {backend}"),
+				breaking: true,
+			});
+		}
+	}
+	issues
+}
+
+#[cfg(test)]
+mod tests {
+	use serde_json::json;
+
+	use super::*;
+	use crate::mcp::registry::types::{Registry, ToolDefinition, ToolImplementation};
+
+	fn registry() -> CompiledRegistry {
+		let mut forecast = ToolDefinition::source("forecast", "weather", "get_forecast");
+		if let ToolImplementation::Source(source) = &mut forecast.implementation {
+			source.hide_fields = vec!["api_key".to_string(), "debug".to_string()];
+			source.defaults.insert("units".to_string(), json!("metric"));
+		}
+		CompiledRegistry::compile(Registry::with_tool_definitions(vec![forecast])).unwrap()
+	}
+
+	fn backend(schema: Value) -> Vec<(String, Tool)> {
+		vec![(
+			"weather".to_string(),
+			Tool::new(
+				"get_forecast",
+				"Get the forecast",
+				Arc::new(schema.as_object().unwrap().clone()),
+			),
+		)]
+	}
+
+	#[test]
+	fn test_compatible_schema_has_no_issues() {
+		let registry = registry();
+		let schema = json!({
+			"type": "object",
+			"properties": {
+				"city": {"type": "string"},
+				"units": {"type": "string"},
+				"api_key": {"type": "string"}
+			},
+			"required": ["city"]
+		});
+		let tool = registry.get_tool("forecast").unwrap();
+		assert!(check_compatibility(tool, schema.as_object().unwrap()).is_empty());
+	}
+
+	#[test]
+	fn test_incompatible_changes_are_reported() {
+		let registry = registry();
+		let schema = json!({
+			"type": "object",
+			"properties": {
+				"city": {"type": "string"},
+				"api_key": {"type": "string"}
+			},
+			"required": ["city", "api_key"]
+		});
+		let tool = registry.get_tool("forecast").unwrap();
+		let issues = check_compatibility(tool, schema.as_object().unwrap());
+		assert_eq!(
+			issues,
+			vec![
+				DriftIssue {
+					message: "hidden field 'api_key' is required by weather/get_forecast but has no default"
+						.to_string(),
+					breaking: true,
+				},
+				DriftIssue {
+					message: "default 'units' is no longer accepted by weather/get_forecast".to_string(),
+					breaking: false,
+				},
+			]
+		);
+
+		let schema = json!({"properties": {"units": {"type": ["integer", "null"]}}});
+		let issues = check_compatibility(tool, schema.as_object().unwrap());
+		assert_eq!(issues.len(), 1);
+		assert!(issues[0].breaking);
+	}
+
+	#[test]
+	fn test_disable_policy_withdraws_until_compatible() {
+		let registry = registry();
+		let tracker = SchemaDriftTracker::new(SchemaDriftPolicy::Disable);
+
+		tracker.observe(
+			&registry,
+			&backend(json!({"properties": {"units": {"type": "string"}}})),
+		);
+		assert!(!tracker.is_disabled("forecast"));
+
+		tracker.observe(
+			&registry,
+			&backend(json!({
+				"properties": {"units": {"type": "string"}, "debug": {"type": "boolean"}},
+				"required": ["debug"]
+			})),
+		);
+		assert!(
+			tracker
+				.disabled_reason("forecast")
+				.is_some_and(|r| r.contains("'debug' is required"))
+		);
+		assert_eq!(tracker.issues()["forecast"].len(), 1);
+
+		tracker.observe(
+			&registry,
+			&backend(json!({"properties": {"units": {"type": "string"}}})),
+		);
+		assert!(!tracker.is_disabled("forecast"));
+		assert!(tracker.issues().is_empty());
+	}
+
+	#[test]
+	fn test_warn_policy_keeps_tool() {
+		let registry = registry();
+		let tracker = SchemaDriftTracker::default();
+		tracker.observe(&registry, &backend(json!({"required": ["api_key"]})));
+		assert!(!tracker.is_disabled("forecast"));
+		assert_eq!(tracker.issues()["forecast"].len(), 1);
+	}
+}
@@ -0,0 +1,53 @@
+// Environment variables available to registry entries
+//
+// Source defaults and target policies can reference process environment variables as
+// `${VAR}`, which is how registry authors inject API keys without writing them into the
+// registry. Left unrestricted, any author can also reference secrets meant for the gateway
+// itself, such as AWS credentials, and have them sent to a backend they control. An
+// allowlist limits references to the variables an operator has set aside for the registry;
+// a registry referencing anything else fails to compile.
+
+/// Which environment variables registry entries may reference
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum EnvVarPolicy {
+	/// Any variable (the default)
+	#[default]
+	Unrestricted,
+	/// Only variables starting with one of `prefixes`, or listed in `names`
+	Allowlist {
+		prefixes: Vec<String>,
+		names: Vec<String>,
+	},
+}
+
+impl EnvVarPolicy {
+	/// Whether registry entries may reference the variable `name`
+	pub fn allows(&self, name: &str) -> bool {
+		match self {
+			Self::Unrestricted => true,
+			Self::Allowlist { prefixes, names } => {
+				prefixes.iter().any(|p| name.starts_with(p.as_str())) || names.iter().any(|n| n == name)
+			},
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_allows() {
+		assert!(EnvVarPolicy::Unrestricted.allows("AWS_SECRET_ACCESS_KEY"));
+
+		let policy = EnvVarPolicy::Allowlist {
+			prefixes: vec!["REGISTRY_".to_string()],
+			names: vec!["WEATHER_API_KEY".to_string()],
+		};
+		assert!(policy.allows("REGISTRY_GITHUB_TOKEN"));
+		assert!(policy.allows("WEATHER_API_KEY"));
+		assert!(!policy.allows("AWS_SECRET_ACCESS_KEY"));
+		assert!(!policy.allows("WEATHER_API_KEY_2"));
+		assert!(!policy.allows("REGISTRY"));
+	}
+}
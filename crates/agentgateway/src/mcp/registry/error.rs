@@ -2,6 +2,8 @@
 
 use thiserror::Error;
 
+use super::json_limits::JsonLimitError;
+
 /// Errors that can occur during registry operations
 #[derive(Error, Debug)]
 pub enum RegistryError {
@@ -33,9 +35,15 @@ pub enum RegistryError {
 	#[error("environment variable '{name}' not found")]
 	EnvVarNotFound { name: String },
 
+	#[error("{location} references environment variable '{name}', which is not allowed")]
+	EnvVarNotAllowed { name: String, location: String },
+
 	#[error("invalid registry source URI: {0}")]
 	InvalidSource(String),
 
+	#[error("invalid registry proto: {0}")]
+	InvalidProto(String),
+
 	#[error("schema validation error: {0}")]
 	SchemaValidation(String),
 
@@ -53,6 +61,15 @@ pub enum RegistryError {
 
 	#[error("unknown tool reference: '{0}'")]
 	UnknownToolReference(String),
+
+	#[error("corrupt registry snapshot: {0}")]
+	CorruptSnapshot(String),
+
+	#[error("registry signature verification failed: {0}")]
+	InvalidSignature(String),
+
+	#[error("backend response rejected: {0}")]
+	ResponseLimit(#[from] JsonLimitError),
 }
 
 impl RegistryError {
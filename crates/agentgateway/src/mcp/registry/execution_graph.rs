@@ -4,7 +4,7 @@
 // the flow of data through a composition's operations.
 
 use super::patterns::{
-	AggregationStrategy, DataBinding, FilterSpec, MapEachInner, PatternSpec, SchemaMapSpec,
+	AggregationStrategy, DataBinding, FilterSpec, MapEachInner, PatternSpec, RankSpec, SchemaMapSpec,
 };
 
 /// An execution graph representing a compiled composition
@@ -58,6 +58,9 @@ pub enum NodeOperation {
 	/// MapEach: apply operation to each array element
 	MapEach { inner: MapEachInner },
 
+	/// Rank: score, sort and limit array elements
+	Rank(RankSpec),
+
 	/// Input: the composition's input
 	Input,
 
@@ -83,6 +86,18 @@ pub enum StepOperationNode {
 	Tool { name: String },
 	/// Inline pattern
 	Pattern(Box<PatternSpec>),
+	/// Call to another composition
+	Composition { name: String },
+	/// Assertion over the step input
+	Assert,
+	/// Sandboxed script over the step input
+	Script,
+	/// Completion from the client's LLM
+	Sample,
+	/// JSON Patch or merge patch over the step input
+	Patch,
+	/// Fixed value
+	Constant,
 }
 
 /// A target in scatter-gather
@@ -163,6 +178,14 @@ impl ExecutionGraph {
 								name: tc.name.clone(),
 							},
 							super::patterns::StepOperation::Pattern(p) => StepOperationNode::Pattern(p.clone()),
+							super::patterns::StepOperation::Composition(cc) => StepOperationNode::Composition {
+								name: cc.name.clone(),
+							},
+							super::patterns::StepOperation::Assert(_) => StepOperationNode::Assert,
+							super::patterns::StepOperation::Script(_) => StepOperationNode::Script,
+							super::patterns::StepOperation::Sample(_) => StepOperationNode::Sample,
+							super::patterns::StepOperation::Patch(_) => StepOperationNode::Patch,
+							super::patterns::StepOperation::Constant(_) => StepOperationNode::Constant,
 						},
 						input: s.input.clone(),
 					})
@@ -173,9 +196,9 @@ impl ExecutionGraph {
 				let targets = sg
 					.targets
 					.iter()
-					.map(|t| match t {
-						super::patterns::ScatterTarget::Tool(name) => ScatterTargetNode::Tool(name.clone()),
-						super::patterns::ScatterTarget::Pattern(p) => ScatterTargetNode::Pattern(p.clone()),
+					.map(|t| match &t.operation {
+						super::patterns::ScatterOperation::Tool(name) => ScatterTargetNode::Tool(name.clone()),
+						super::patterns::ScatterOperation::Pattern(p) => ScatterTargetNode::Pattern(p.clone()),
 					})
 					.collect();
 				NodeOperation::ScatterGather {
@@ -190,14 +213,16 @@ impl ExecutionGraph {
 			PatternSpec::MapEach(me) => NodeOperation::MapEach {
 				inner: me.inner.clone(),
 			},
+			PatternSpec::Rank(r) => NodeOperation::Rank(r.clone()),
+			PatternSpec::Cache(_) | PatternSpec::DeadLetter(_) => {
+				NodeOperation::Pattern(Box::new(spec.clone()))
+			},
 
 			// Stateful patterns - wrap as Pattern for now (execution will error at runtime)
 			PatternSpec::Retry(_)
 			| PatternSpec::Timeout(_)
-			| PatternSpec::Cache(_)
 			| PatternSpec::Idempotent(_)
 			| PatternSpec::CircuitBreaker(_)
-			| PatternSpec::DeadLetter(_)
 			| PatternSpec::Saga(_)
 			| PatternSpec::ClaimCheck(_)
 			| PatternSpec::Throttle(_) => NodeOperation::Pattern(Box::new(spec.clone())),
@@ -238,11 +263,18 @@ impl ExecutionGraph {
 			NodeOperation::Pipeline { steps } => {
 				for step in steps {
 					match &step.operation {
-						StepOperationNode::Tool { name } => refs.push(name.clone()),
+						StepOperationNode::Tool { name } | StepOperationNode::Composition { name } => {
+							refs.push(name.clone())
+						},
 						StepOperationNode::Pattern(p) => {
 							let inner_op = Self::pattern_to_operation(p);
 							Self::collect_tool_refs(&inner_op, refs);
 						},
+						StepOperationNode::Assert
+						| StepOperationNode::Script
+						| StepOperationNode::Sample
+						| StepOperationNode::Patch
+						| StepOperationNode::Constant => {},
 					}
 				}
 			},
@@ -268,6 +300,7 @@ impl ExecutionGraph {
 				let inner_op = Self::pattern_to_operation(p);
 				Self::collect_tool_refs(&inner_op, refs);
 			},
+			NodeOperation::Rank(r) => refs.extend(r.referenced_tools().into_iter().map(String::from)),
 			NodeOperation::Filter(_)
 			| NodeOperation::SchemaMap(_)
 			| NodeOperation::Input
@@ -294,6 +327,9 @@ mod tests {
 						name: "search".to_string(),
 					}),
 					input: None,
+					session_set: None,
+					retry: None,
+					cache: None,
 				},
 				PipelineStep {
 					id: "step2".to_string(),
@@ -301,6 +337,9 @@ mod tests {
 						name: "summarize".to_string(),
 					}),
 					input: None,
+					session_set: None,
+					retry: None,
+					cache: None,
 				},
 			],
 		});
@@ -320,14 +359,15 @@ mod tests {
 	fn test_build_scatter_gather_graph() {
 		let spec = PatternSpec::ScatterGather(ScatterGatherSpec {
 			targets: vec![
-				super::super::patterns::ScatterTarget::Tool("tool_a".to_string()),
-				super::super::patterns::ScatterTarget::Tool("tool_b".to_string()),
+				super::super::patterns::ScatterTarget::tool("tool_a"),
+				super::super::patterns::ScatterTarget::tool("tool_b"),
 			],
 			aggregation: AggregationStrategy {
 				ops: vec![AggregationOp::Flatten(true)],
 			},
 			timeout_ms: Some(5000),
 			fail_fast: false,
+			sample: None,
 		});
 
 		let graph = ExecutionGraph::from_pattern(&spec);
@@ -362,6 +402,8 @@ mod tests {
 	fn test_build_map_each_graph() {
 		let spec = PatternSpec::MapEach(super::super::patterns::MapEachSpec {
 			inner: MapEachInner::Tool("fetch".to_string()),
+			max_items: None,
+			stream: None,
 		});
 
 		let graph = ExecutionGraph::from_pattern(&spec);
@@ -0,0 +1,291 @@
+// Gateway-hosted utility tools
+//
+// A few tools implemented in-process under the reserved `_builtin` target, for glue logic
+// and testing inside compositions without standing up an MCP server. Compositions call them
+// as `_builtin_<tool>` (e.g. `_builtin_echo`); virtual tools can also use `_builtin` as their
+// source target to rename them or add defaults.
+
+use std::time::Duration;
+
+use rand::seq::IndexedRandom;
+use serde_json::{Map, Value, json};
+
+use super::ExecutionError;
+use crate::mcp::registry::compiled::CompiledOutputTransform;
+use crate::mcp::registry::types::OutputTransform;
+
+/// Reserved target name of the built-in tools
+pub const BUILTIN_TARGET: &str = "_builtin";
+
+/// Names of the built-in tools
+pub const BUILTIN_TOOLS: &[&str] = &["echo", "transform", "json_diff", "sleep", "random"];
+
+/// Longest delay the `sleep` tool accepts
+pub const MAX_SLEEP_MS: u64 = 60_000;
+
+/// The built-in tool a composition tool name refers to (`_builtin_echo` -> `echo`)
+pub fn builtin_tool(name: &str) -> Option<&str> {
+	name
+		.strip_prefix(BUILTIN_TARGET)?
+		.strip_prefix('_')
+		.filter(|tool| BUILTIN_TOOLS.contains(tool))
+}
+
+/// Invoke a built-in tool
+///
+/// - `echo`: returns its arguments
+/// - `transform {input, mappings}`: applies output transform mappings to `input`
+/// - `json_diff {left, right}`: lists the paths where two values differ
+/// - `sleep {ms, value?}`: waits, then returns `value` (or `{sleptMs}`)
+/// - `random {min?, max?}` or `random {choices}`: a random integer (default 0-100, inclusive)
+///   or element, as `{value}`
+pub async fn invoke_builtin(tool: &str, args: Value) -> Result<Value, ExecutionError> {
+	match tool {
+		"echo" => Ok(args),
+		"transform" => transform(args),
+		"json_diff" => {
+			let changes = diff(
+				args.get("left").unwrap_or(&Value::Null),
+				args.get("right").unwrap_or(&Value::Null),
+			);
+			Ok(json!({"equal": changes.is_empty(), "changes": changes}))
+		},
+		"sleep" => {
+			let ms = args.get("ms").and_then(Value::as_u64).ok_or_else(|| {
+				ExecutionError::InvalidInput("sleep requires a non-negative integer 'ms'".to_string())
+			})?;
+			if ms > MAX_SLEEP_MS {
+				return Err(ExecutionError::InvalidInput(format!(
+					"sleep of {ms}ms exceeds the {MAX_SLEEP_MS}ms maximum"
+				)));
+			}
+			tokio::time::sleep(Duration::from_millis(ms)).await;
+			Ok(match args.get("value") {
+				Some(value) => value.clone(),
+				None => json!({"sleptMs": ms}),
+			})
+		},
+		"random" => random(&args).map(|value| json!({"value": value})),
+		other => Err(ExecutionError::ToolNotFound(format!(
+			"{BUILTIN_TARGET}_{other}"
+		))),
+	}
+}
+
+fn transform(args: Value) -> Result<Value, ExecutionError> {
+	let Value::Object(mut args) = args else {
+		return Err(ExecutionError::InvalidInput(
+			"transform requires an object with 'input' and 'mappings'".to_string(),
+		));
+	};
+	let mappings = args
+		.remove("mappings")
+		.ok_or_else(|| ExecutionError::InvalidInput("transform requires 'mappings'".to_string()))?;
+	let transform: OutputTransform = serde_json::from_value(json!({"mappings": mappings}))
+		.map_err(|e| ExecutionError::InvalidInput(format!("invalid transform mappings: {e}")))?;
+	let compiled = CompiledOutputTransform::compile(&transform)
+		.map_err(|e| ExecutionError::InvalidInput(e.to_string()))?;
+	compiled
+		.apply(args.get("input").unwrap_or(&Value::Null))
+		.map_err(|e| ExecutionError::ToolExecutionFailed(e.to_string()))
+}
+
+fn random(args: &Value) -> Result<Value, ExecutionError> {
+	if let Some(choices) = args.get("choices") {
+		let choices = choices.as_array().ok_or_else(|| {
+			ExecutionError::InvalidInput("random 'choices' must be an array".to_string())
+		})?;
+		return choices
+			.choose(&mut rand::rng())
+			.cloned()
+			.ok_or_else(|| ExecutionError::InvalidInput("random 'choices' is empty".to_string()));
+	}
+	let bound = |name: &str, default: i64| match args.get(name) {
+		None => Ok(default),
+		Some(v) => v
+			.as_i64()
+			.ok_or_else(|| ExecutionError::InvalidInput(format!("random '{name}' must be an integer"))),
+	};
+	let (min, max) = (bound("min", 0)?, bound("max", 100)?);
+	if min > max {
+		return Err(ExecutionError::InvalidInput(format!(
+			"random 'min' ({min}) is greater than 'max' ({max})"
+		)));
+	}
+	Ok(rand::random_range(min..=max).into())
+}
+
+/// Paths where `left` and `right` differ, descending into objects and arrays
+fn diff(left: &Value, right: &Value) -> Vec<Value> {
+	let mut changes = Vec::new();
+	diff_at("$", left, right, &mut changes);
+	changes
+}
+
+fn diff_at(path: &str, left: &Value, right: &Value, changes: &mut Vec<Value>) {
+	match (left, right) {
+		(Value::Object(l), Value::Object(r)) => {
+			for (key, lv) in l {
+				let child = child_path(path, key);
+				match r.get(key) {
+					Some(rv) => diff_at(&child, lv, rv, changes),
+					None => changes.push(change(child, "removed", Some(lv), None)),
+				}
+			}
+			for (key, rv) in r.iter().filter(|(key, _)| !l.contains_key(*key)) {
+				changes.push(change(child_path(path, key), "added", None, Some(rv)));
+			}
+		},
+		(Value::Array(l), Value::Array(r)) => {
+			for i in 0..l.len().max(r.len()) {
+				let child = format!("{path}[{i}]");
+				match (l.get(i), r.get(i)) {
+					(Some(lv), Some(rv)) => diff_at(&child, lv, rv, changes),
+					(Some(lv), None) => changes.push(change(child, "removed", Some(lv), None)),
+					(None, rv) => changes.push(change(child, "added", None, rv)),
+				}
+			}
+		},
+		_ if left != right => {
+			changes.push(change(path.to_string(), "changed", Some(left), Some(right)))
+		},
+		_ => {},
+	}
+}
+
+fn child_path(path: &str, key: &str) -> String {
+	let plain = !key.is_empty()
+		&& !key.starts_with(|c: char| c.is_ascii_digit())
+		&& key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+	if plain {
+		format!("{path}.{key}")
+	} else {
+		format!(
+			"{path}['{}']",
+			key.replace('\\', "\\\\").replace('\'', "\\'")
+		)
+	}
+}
+
+fn change(path: String, op: &str, left: Option<&Value>, right: Option<&Value>) -> Value {
+	let mut change = Map::new();
+	change.insert("path".to_string(), path.into());
+	change.insert("op".to_string(), op.into());
+	if let Some(left) = left {
+		change.insert("left".to_string(), left.clone());
+	}
+	if let Some(right) = right {
+		change.insert("right".to_string(), right.clone());
+	}
+	Value::Object(change)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_builtin_tool_names() {
+		assert_eq!(builtin_tool("_builtin_echo"), Some("echo"));
+		assert_eq!(builtin_tool("_builtin_json_diff"), Some("json_diff"));
+		assert_eq!(builtin_tool("_builtin_unknown"), None);
+		assert_eq!(builtin_tool("echo"), None);
+	}
+
+	#[tokio::test]
+	async fn test_echo_and_transform() {
+		let args = json!({"a": [1, 2]});
+		assert_eq!(invoke_builtin("echo", args.clone()).await.unwrap(), args);
+
+		let result = invoke_builtin(
+			"transform",
+			json!({
+				"input": {"user": {"name": "ada"}, "items": [1, 2, 3]},
+				"mappings": {
+					"name": {"path": "$.user.name"},
+					"greeting": {"template": {"template": "hi {n}", "vars": {"n": "$.user.name"}}}
+				}
+			}),
+		)
+		.await
+		.unwrap();
+		assert_eq!(result, json!({"name": "ada", "greeting": "hi ada"}));
+
+		let err = invoke_builtin("transform", json!({"input": {}}))
+			.await
+			.unwrap_err();
+		assert!(matches!(err, ExecutionError::InvalidInput(_)));
+	}
+
+	#[tokio::test]
+	async fn test_json_diff() {
+		let result = invoke_builtin(
+			"json_diff",
+			json!({
+				"left": {"a": 1, "b": {"c": [1, 2]}, "gone": true},
+				"right": {"a": 2, "b": {"c": [1]}, "new key": null}
+			}),
+		)
+		.await
+		.unwrap();
+		assert_eq!(
+			result,
+			json!({
+				"equal": false,
+				"changes": [
+					{"path": "$.a", "op": "changed", "left": 1, "right": 2},
+					{"path": "$.b.c[1]", "op": "removed", "left": 2},
+					{"path": "$.gone", "op": "removed", "left": true},
+					{"path": "$['new key']", "op": "added", "right": null}
+				]
+			})
+		);
+
+		let same = invoke_builtin("json_diff", json!({"left": [1], "right": [1]}))
+			.await
+			.unwrap();
+		assert_eq!(same, json!({"equal": true, "changes": []}));
+	}
+
+	#[tokio::test(start_paused = true)]
+	async fn test_sleep() {
+		let result = invoke_builtin("sleep", json!({"ms": 500, "value": {"ok": true}}))
+			.await
+			.unwrap();
+		assert_eq!(result, json!({"ok": true}));
+		assert_eq!(
+			invoke_builtin("sleep", json!({"ms": 5})).await.unwrap(),
+			json!({"sleptMs": 5})
+		);
+		assert!(
+			invoke_builtin("sleep", json!({"ms": MAX_SLEEP_MS + 1}))
+				.await
+				.is_err()
+		);
+	}
+
+	#[tokio::test]
+	async fn test_random() {
+		for _ in 0..20 {
+			let value = invoke_builtin("random", json!({"min": 3, "max": 5}))
+				.await
+				.unwrap()["value"]
+				.as_i64()
+				.unwrap();
+			assert!((3..=5).contains(&value));
+		}
+		let picked = invoke_builtin("random", json!({"choices": ["x"]}))
+			.await
+			.unwrap();
+		assert_eq!(picked, json!({"value": "x"}));
+		assert!(
+			invoke_builtin("random", json!({"min": 2, "max": 1}))
+				.await
+				.is_err()
+		);
+		assert!(matches!(
+			invoke_builtin("missing", json!({})).await,
+			Err(ExecutionError::ToolNotFound(name)) if name == "_builtin_missing"
+		));
+	}
+}
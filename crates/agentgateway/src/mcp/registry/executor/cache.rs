@@ -0,0 +1,491 @@
+// Cache pattern executor
+//
+// Runs the cache pattern on the stateful cache executor. Entries of every composition share
+// one in-memory store, keyed `<composition>/<key>`, which outlives registry reloads so
+// operators can inspect and purge it from the admin API. Inline step caches are sugar over
+// the pattern, keyed `<composition>/<step>/<key>`.
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+
+use serde::Serialize;
+use serde_json::Value;
+
+use super::context::ExecutionContext;
+use super::{CompositionExecutor, ExecutionError, PipelineExecutor};
+use crate::mcp::registry::patterns::{CacheSpec, FieldPredicate, StepCache, StepOperation};
+use crate::stateful::memory::MemoryStore;
+use crate::stateful::{
+	self, CacheError, CachePredicate, CacheStats, CacheStatsSnapshot, InnerFailure, StateStore,
+	is_revalidation_marker,
+};
+
+/// Default for the most results held by the cache of a registry
+pub const DEFAULT_MAX_CACHE_ENTRIES: usize = 10_000;
+
+/// Store and per-composition hit/miss counters behind cache patterns
+#[derive(Debug)]
+pub struct ResultCache {
+	store: Arc<MemoryStore>,
+	stats: parking_lot::Mutex<HashMap<String, CacheStatsSnapshot>>,
+}
+
+impl Default for ResultCache {
+	fn default() -> Self {
+		Self::with_max_entries(DEFAULT_MAX_CACHE_ENTRIES)
+	}
+}
+
+/// Cache counters and live entry count of one composition
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CompositionCacheStats {
+	#[serde(flatten)]
+	pub counters: CacheStatsSnapshot,
+	/// Unexpired entries in the store
+	pub entries: usize,
+}
+
+impl ResultCache {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Create a cache holding at most `max_entries` results, evicting the least recently
+	/// used once full
+	pub fn with_max_entries(max_entries: usize) -> Self {
+		Self {
+			store: Arc::new(MemoryStore::with_max_entries(max_entries)),
+			stats: Default::default(),
+		}
+	}
+
+	/// The store holding cached results
+	pub fn store(&self) -> &Arc<MemoryStore> {
+		&self.store
+	}
+
+	/// Add the outcome of one lookup to a composition's counters
+	fn record(&self, composition: &str, outcome: CacheStatsSnapshot) {
+		let mut stats = self.stats.lock();
+		let counters = stats.entry(composition.to_string()).or_default();
+		counters.hits += outcome.hits;
+		counters.stale_hits += outcome.stale_hits;
+		counters.misses += outcome.misses;
+	}
+
+	/// Counters and entry counts of every composition that has used the cache
+	pub async fn snapshot(&self) -> Result<BTreeMap<String, CompositionCacheStats>, ExecutionError> {
+		let mut snapshot: BTreeMap<String, CompositionCacheStats> = self
+			.stats
+			.lock()
+			.iter()
+			.map(|(name, counters)| {
+				let stats = CompositionCacheStats {
+					counters: *counters,
+					entries: 0,
+				};
+				(name.clone(), stats)
+			})
+			.collect();
+		for key in self.store.keys("").await.map_err(store_error)? {
+			if is_revalidation_marker(&key) {
+				continue;
+			}
+			if let Some((composition, _)) = key.split_once('/') {
+				snapshot.entry(composition.to_string()).or_default().entries += 1;
+			}
+		}
+		Ok(snapshot)
+	}
+
+	/// Delete the entries of `composition` (or of every composition) whose key starts with
+	/// `prefix` (or all of them), returning how many were deleted
+	pub async fn purge(
+		&self,
+		composition: Option<&str>,
+		prefix: Option<&str>,
+	) -> Result<usize, ExecutionError> {
+		let scope = composition.map(|c| format!("{c}/")).unwrap_or_default();
+		let keys = self.store.keys(&scope).await.map_err(store_error)?;
+		let mut purged = 0;
+		for key in keys {
+			if is_revalidation_marker(&key) {
+				continue;
+			}
+			let matches = prefix.is_none_or(|prefix| {
+				key
+					.split_once('/')
+					.is_some_and(|(_, key)| key.starts_with(prefix))
+			});
+			if matches {
+				self.store.delete(&key).await.map_err(store_error)?;
+				purged += 1;
+			}
+		}
+		Ok(purged)
+	}
+}
+
+/// Executor for cache patterns
+pub struct CacheExecutor;
+
+impl CacheExecutor {
+	/// Execute a cache pattern, running the inner operation only on a miss
+	///
+	/// Without a result cache attached to the executor, the inner operation always runs.
+	pub async fn execute(
+		spec: &CacheSpec,
+		input: Value,
+		ctx: &ExecutionContext,
+		executor: &CompositionExecutor,
+	) -> Result<Value, ExecutionError> {
+		Self::execute_scoped(spec, None, input, ctx, executor).await
+	}
+
+	/// Execute a pipeline step's operation through its inline cache
+	pub async fn execute_step(
+		cache: &StepCache,
+		operation: &StepOperation,
+		input: Value,
+		ctx: &ExecutionContext,
+		executor: &CompositionExecutor,
+	) -> Result<Value, ExecutionError> {
+		let spec = cache.cache_spec(operation);
+		let step_id = ctx.step_id.clone().unwrap_or_default();
+		Self::execute_scoped(&spec, Some(&step_id), input, ctx, executor).await
+	}
+
+	/// Execute a cache pattern whose keys are kept under `step`, if given
+	async fn execute_scoped(
+		spec: &CacheSpec,
+		step: Option<&str>,
+		input: Value,
+		ctx: &ExecutionContext,
+		executor: &CompositionExecutor,
+	) -> Result<Value, ExecutionError> {
+		let step_id = ctx.step_id.clone().unwrap_or_else(|| "cache".to_string());
+		let Some(cache) = executor.result_cache.clone() else {
+			return PipelineExecutor::execute_operation(&step_id, &spec.inner, input, ctx, executor)
+				.await;
+		};
+
+		let composition = ctx.composition.clone().unwrap_or_default();
+		let key_prefix = match step {
+			Some(step) => format!("{composition}/{step}/"),
+			None => format!("{composition}/"),
+		};
+		let store_spec = Self::store_spec(spec, key_prefix)?;
+
+		// The inner operation may finish in the background (stale-while-revalidate), so it owns
+		// its context and executor; its error is kept to return it unchanged on a miss
+		let failure = Arc::new(parking_lot::Mutex::new(None));
+		let inner = {
+			let operation = spec.inner.clone();
+			let input = input.clone();
+			let ctx = ctx.child(input.clone());
+			let executor = executor.clone();
+			let failure = failure.clone();
+			async move {
+				PipelineExecutor::execute_operation(&step_id, &operation, input, &ctx, &executor)
+					.await
+					.map_err(|e| {
+						let described = InnerFailure {
+							kind: e.kind().to_string(),
+							message: e.to_string(),
+							transient: e.is_retryable(),
+						};
+						*failure.lock() = Some(e);
+						described
+					})
+			}
+		};
+
+		let outcome = CacheStats::default();
+		let store: Arc<dyn StateStore> = cache.store.clone();
+		let result =
+			stateful::CacheExecutor::execute_with_stats(&store_spec, input, store, &outcome, inner).await;
+		let outcome = outcome.snapshot();
+		cache.record(&composition, outcome);
+		ctx.record_cache_hits(outcome.hits + outcome.stale_hits);
+
+		result.map_err(|e| match e {
+			CacheError::InnerExecution(cached) => {
+				failure
+					.lock()
+					.take()
+					.unwrap_or_else(|| ExecutionError::CachedFailure {
+						kind: cached.kind,
+						message: cached.message,
+						retryable: cached.transient,
+					})
+			},
+			CacheError::KeyDerivation(message) => ExecutionError::InvalidInput(message),
+			e => ExecutionError::Internal(e.to_string()),
+		})
+	}
+
+	/// The stateful cache spec for a composition's cache pattern, storing keys under `key_prefix`
+	fn store_spec(
+		spec: &CacheSpec,
+		key_prefix: String,
+	) -> Result<stateful::CacheSpec, ExecutionError> {
+		let mut store_spec = stateful::CacheSpec::new(spec.key_paths.clone(), spec.ttl_seconds)
+			.with_key_prefix(key_prefix);
+		store_spec.stale_while_revalidate_seconds = spec.stale_while_revalidate_seconds;
+		store_spec.cache_if = spec.cache_if.as_ref().map(predicate).transpose()?;
+		store_spec.stale_if = spec.stale_if.as_ref().map(predicate).transpose()?;
+		store_spec.negative = spec.negative.as_ref().map(|n| stateful::NegativeCacheSpec {
+			ttl_seconds: n.ttl_seconds,
+			errors: n.errors.clone(),
+			empty_results: n.empty_results,
+		});
+		Ok(store_spec)
+	}
+}
+
+/// Cache predicates only compare for equality
+fn predicate(p: &FieldPredicate) -> Result<CachePredicate, ExecutionError> {
+	if p.op != "eq" {
+		return Err(ExecutionError::PredicateError(format!(
+			"cache predicates only support the eq operator, got {}",
+			p.op
+		)));
+	}
+	Ok(CachePredicate {
+		field: p.field.clone(),
+		equals: p.value.to_json_value(),
+	})
+}
+
+fn store_error(e: stateful::StoreError) -> ExecutionError {
+	ExecutionError::Internal(format!("cache store: {e}"))
+}
+
+#[cfg(test)]
+mod tests {
+	use serde_json::json;
+
+	use super::*;
+	use crate::mcp::registry::CompiledRegistry;
+	use crate::mcp::registry::executor::MockToolInvoker;
+	use crate::mcp::registry::patterns::{PatternSpec, StepOperation, ToolCall};
+	use crate::mcp::registry::types::{Registry, ToolDefinition};
+
+	fn cached_search() -> ToolDefinition {
+		ToolDefinition::composition(
+			"cached_search",
+			PatternSpec::Cache(CacheSpec {
+				key_paths: vec!["$.query".to_string()],
+				inner: Box::new(StepOperation::Tool(ToolCall {
+					name: "search".to_string(),
+				})),
+				store: "results".to_string(),
+				ttl_seconds: 60,
+				stale_while_revalidate_seconds: None,
+				cache_if: None,
+				stale_if: None,
+				negative: None,
+			}),
+		)
+	}
+
+	fn setup(cache: Arc<ResultCache>) -> (CompositionExecutor, Arc<MockToolInvoker>) {
+		let registry = Registry::with_tool_definitions(vec![cached_search()]);
+		let compiled = Arc::new(CompiledRegistry::compile(registry).unwrap());
+		let invoker =
+			Arc::new(MockToolInvoker::new().with_response("search", json!({"results": ["axum"]})));
+		let executor = CompositionExecutor::new(compiled, invoker.clone()).with_result_cache(cache);
+		(executor, invoker)
+	}
+
+	#[tokio::test]
+	async fn test_cache_pattern_hits_and_purge() {
+		let cache = Arc::new(ResultCache::new());
+		let (executor, invoker) = setup(cache.clone());
+
+		for query in ["rust", "rust", "go"] {
+			let result = executor
+				.execute("cached_search", json!({"query": query}))
+				.await
+				.unwrap();
+			assert_eq!(result, json!({"results": ["axum"]}));
+		}
+		assert_eq!(invoker.call_count("search"), 2);
+
+		let stats = cache.snapshot().await.unwrap();
+		assert_eq!(
+			stats["cached_search"],
+			CompositionCacheStats {
+				counters: CacheStatsSnapshot {
+					hits: 1,
+					stale_hits: 0,
+					misses: 2,
+				},
+				entries: 2,
+			}
+		);
+
+		// Purge by key prefix, then by composition
+		assert_eq!(cache.purge(None, Some("ru")).await.unwrap(), 1);
+		assert_eq!(cache.purge(Some("other"), None).await.unwrap(), 0);
+		assert_eq!(cache.purge(Some("cached_search"), None).await.unwrap(), 1);
+		executor
+			.execute("cached_search", json!({"query": "rust"}))
+			.await
+			.unwrap();
+		assert_eq!(invoker.call_count("search"), 3);
+	}
+
+	#[tokio::test]
+	async fn test_revalidation_markers_are_not_entries() {
+		let cache = Arc::new(ResultCache::new());
+		let (executor, _) = setup(cache.clone());
+		executor
+			.execute("cached_search", json!({"query": "rust"}))
+			.await
+			.unwrap();
+		let key = cache
+			.store()
+			.keys("cached_search/")
+			.await
+			.unwrap()
+			.remove(0);
+		cache
+			.store()
+			.set(&format!("{key}#revalidating"), b"1".to_vec(), None)
+			.await
+			.unwrap();
+
+		let stats = cache.snapshot().await.unwrap();
+		assert_eq!(stats["cached_search"].entries, 1);
+		assert_eq!(cache.purge(Some("cached_search"), None).await.unwrap(), 1);
+	}
+
+	#[tokio::test]
+	async fn test_step_cache_caches_only_its_step() {
+		let spec: PatternSpec = serde_json::from_value(json!({
+			"pipeline": {
+				"steps": [
+					{"id": "prepare", "operation": {"tool": {"name": "prepare"}}},
+					{
+						"id": "search",
+						"operation": {"tool": {"name": "search"}},
+						"cache": {"ttlSeconds": 60, "keyPaths": ["$.query"]}
+					},
+					{"id": "summarize", "operation": {"tool": {"name": "summarize"}}}
+				]
+			}
+		}))
+		.unwrap();
+		let registry =
+			Registry::with_tool_definitions(vec![ToolDefinition::composition("research", spec)]);
+		let compiled = Arc::new(CompiledRegistry::compile(registry).unwrap());
+		let invoker = Arc::new(
+			MockToolInvoker::new()
+				.with_response("prepare", json!({"query": "rust"}))
+				.with_response("search", json!({"results": ["axum"]}))
+				.with_response("summarize", json!({"summary": "axum"})),
+		);
+		let cache = Arc::new(ResultCache::new());
+		let executor =
+			CompositionExecutor::new(compiled, invoker.clone()).with_result_cache(cache.clone());
+
+		for _ in 0..2 {
+			let result = executor.execute("research", json!({})).await.unwrap();
+			assert_eq!(result, json!({"summary": "axum"}));
+		}
+		assert_eq!(invoker.call_count("prepare"), 2);
+		assert_eq!(invoker.call_count("search"), 1);
+		assert_eq!(invoker.call_count("summarize"), 2);
+
+		// Entries are kept under the step, and purge like any other cache entry
+		assert_eq!(
+			cache.store().keys("").await.unwrap(),
+			vec!["research/search/rust".to_string()]
+		);
+		assert_eq!(
+			cache
+				.purge(Some("research"), Some("search/"))
+				.await
+				.unwrap(),
+			1
+		);
+	}
+
+	#[tokio::test]
+	async fn test_negative_cache_keeps_failure_kind() {
+		let cached = |name: &str, tool: &str, errors: Vec<&str>| {
+			ToolDefinition::composition(
+				name,
+				serde_json::from_value(json!({"cache": {
+					"keyPaths": ["$.query"],
+					"inner": {"tool": {"name": tool}},
+					"store": "results",
+					"ttlSeconds": 60,
+					"negative": {"ttlSeconds": 60, "errors": errors}
+				}}))
+				.unwrap(),
+			)
+		};
+		let registry = Registry::with_tool_definitions(vec![
+			cached("missing_search", "missing", vec![]),
+			cached("flaky_search", "flaky", vec![]),
+			cached("listed_search", "flaky", vec!["tool_execution_failed"]),
+		]);
+		let compiled = Arc::new(CompiledRegistry::compile(registry).unwrap());
+		let invoker = Arc::new(MockToolInvoker::new().with_error("flaky", "connection reset"));
+		let executor = CompositionExecutor::new(compiled, invoker.clone())
+			.with_result_cache(Arc::new(ResultCache::new()));
+		let input = json!({"query": "rust"});
+
+		// A permanent failure is replayed with its kind, message and retryability
+		let first = executor
+			.execute("missing_search", input.clone())
+			.await
+			.unwrap_err();
+		let replayed = executor
+			.execute("missing_search", input.clone())
+			.await
+			.unwrap_err();
+		assert!(matches!(replayed, ExecutionError::CachedFailure { .. }));
+		assert_eq!(replayed.kind(), "tool_not_found");
+		assert_eq!(replayed.to_string(), first.to_string());
+		assert!(!replayed.is_retryable());
+
+		// Transient failures are only cached when their kind is listed
+		for _ in 0..2 {
+			let err = executor
+				.execute("flaky_search", input.clone())
+				.await
+				.unwrap_err();
+			assert!(matches!(err, ExecutionError::ToolExecutionFailed(_)));
+		}
+		assert_eq!(invoker.call_count("flaky"), 2);
+		for _ in 0..2 {
+			let err = executor
+				.execute("listed_search", input.clone())
+				.await
+				.unwrap_err();
+			assert_eq!(err.kind(), "tool_execution_failed");
+			assert!(err.is_retryable());
+		}
+		assert_eq!(invoker.call_count("flaky"), 3);
+	}
+
+	#[tokio::test]
+	async fn test_cache_pattern_without_result_cache_passes_through() {
+		let registry = Registry::with_tool_definitions(vec![cached_search()]);
+		let compiled = Arc::new(CompiledRegistry::compile(registry).unwrap());
+		let invoker = Arc::new(MockToolInvoker::new().with_response("search", json!([])));
+		let executor = CompositionExecutor::new(compiled, invoker.clone());
+
+		for _ in 0..2 {
+			executor
+				.execute("cached_search", json!({"query": "rust"}))
+				.await
+				.unwrap();
+		}
+		assert_eq!(invoker.call_count("search"), 2);
+	}
+}
@@ -2,18 +2,34 @@
 
 use std::collections::HashMap;
 use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
 
 use serde_json::Value;
 use tokio::sync::RwLock;
 
-use super::ToolInvoker;
+use super::cost::{CostLedger, CostSummary};
+use super::envelope::EnvelopeError;
+use super::execution_id::ExecutionId;
+use super::execution_log::StepTiming;
+use super::journal::JournalRecorder;
+use super::labels::ExecutionLabels;
+use super::memory::{MemoryBudget, MemoryLedger};
+use super::session::SessionStore;
+use super::time_travel::CapturedStep;
+use super::verbosity::CompositionVerbosity;
+use super::{ExecutionError, InvocationMeta, ToolInvoker};
+use crate::events::{EventBus, EventKind};
 use crate::mcp::registry::compiled::CompiledRegistry;
 
 /// Execution context passed through composition execution
 pub struct ExecutionContext {
-	/// Original composition input
+	/// Input of the current operation (the composition input, until a nested pattern narrows it)
 	pub input: Value,
 
+	/// Original composition input (shared with child contexts)
+	pub composition_input: Arc<Value>,
+
 	/// Step results (step_id -> result)
 	step_results: Arc<RwLock<HashMap<String, Value>>>,
 
@@ -22,6 +38,51 @@ pub struct ExecutionContext {
 
 	/// Tool invoker for backend calls
 	pub tool_invoker: Arc<dyn ToolInvoker>,
+
+	/// Session-scoped state shared across compositions in the same MCP session
+	pub session: Arc<SessionStore>,
+
+	/// Step timings for the execution log (shared with child contexts)
+	step_timings: Arc<parking_lot::Mutex<Vec<StepTiming>>>,
+
+	/// Failures the execution tolerated (shared with child contexts)
+	errors: Arc<parking_lot::Mutex<Vec<EnvelopeError>>>,
+
+	/// Debug verbosity for this composition execution
+	pub verbosity: CompositionVerbosity,
+
+	/// Name of the composition being executed
+	pub composition: Option<String>,
+
+	/// Id of the execution
+	pub execution_id: ExecutionId,
+
+	/// Pipeline step currently executing (inherited by nested patterns)
+	pub step_id: Option<String>,
+
+	/// Caller-supplied labels for cost attribution (shared with child contexts)
+	pub labels: Arc<ExecutionLabels>,
+
+	/// Payload bytes held by this execution (shared with child contexts)
+	memory: Arc<MemoryLedger>,
+
+	/// Bus that step completions are published to
+	events: Option<EventBus>,
+
+	/// Results served from a cache pattern (shared with child contexts)
+	cache_hits: Arc<AtomicU64>,
+
+	/// Estimated cost of the execution's tool calls (shared with child contexts)
+	cost: Arc<CostLedger>,
+
+	/// Inputs and outputs of top-level pipeline steps, while the execution is captured
+	captured_steps: Option<Arc<parking_lot::Mutex<Vec<CapturedStep>>>>,
+
+	/// Recorder of top-level pipeline steps, while the execution is journaled for a standby
+	journal: Option<Arc<JournalRecorder>>,
+
+	/// Whether pipeline steps report progress to the client (top-level steps only)
+	reports_progress: bool,
 }
 
 impl ExecutionContext {
@@ -32,13 +93,117 @@ impl ExecutionContext {
 		tool_invoker: Arc<dyn ToolInvoker>,
 	) -> Self {
 		Self {
+			composition_input: Arc::new(input.clone()),
 			input,
 			step_results: Arc::new(RwLock::new(HashMap::new())),
 			registry,
 			tool_invoker,
+			session: Arc::new(SessionStore::new()),
+			step_timings: Default::default(),
+			errors: Default::default(),
+			verbosity: CompositionVerbosity::default(),
+			composition: None,
+			execution_id: ExecutionId::new(),
+			step_id: None,
+			labels: Default::default(),
+			memory: Arc::new(MemoryLedger::new(Arc::new(MemoryBudget::default()))),
+			events: None,
+			cache_hits: Default::default(),
+			cost: Default::default(),
+			captured_steps: None,
+			journal: None,
+			reports_progress: false,
 		}
 	}
 
+	/// Builder: attach session-scoped state
+	pub fn with_session(mut self, session: Arc<SessionStore>) -> Self {
+		self.session = session;
+		self
+	}
+
+	/// Builder: set debug verbosity
+	pub fn with_verbosity(mut self, verbosity: CompositionVerbosity) -> Self {
+		self.verbosity = verbosity;
+		self
+	}
+
+	/// Builder: set the name of the composition being executed
+	pub fn with_composition(mut self, name: &str) -> Self {
+		self.composition = Some(name.to_string());
+		self
+	}
+
+	/// Builder: set the id of the execution
+	pub fn with_execution_id(mut self, id: ExecutionId) -> Self {
+		self.execution_id = id;
+		self
+	}
+
+	/// Builder: charge tool calls to the given ledger
+	pub fn with_cost_ledger(mut self, cost: Arc<CostLedger>) -> Self {
+		self.cost = cost;
+		self
+	}
+
+	/// Builder: attach caller-supplied labels
+	pub fn with_labels(mut self, labels: Arc<ExecutionLabels>) -> Self {
+		self.labels = labels;
+		self
+	}
+
+	/// Builder: charge payloads held by this execution against a shared budget
+	pub fn with_memory_budget(mut self, budget: Arc<MemoryBudget>) -> Self {
+		self.memory = Arc::new(MemoryLedger::new(budget));
+		self
+	}
+
+	/// Builder: charge payloads to another execution's ledger (e.g. the caller of a nested
+	/// composition), so they're released with that execution's
+	pub fn with_memory_ledger(mut self, ledger: Arc<MemoryLedger>) -> Self {
+		self.memory = ledger;
+		self
+	}
+
+	/// Ledger this execution's payloads are charged to
+	pub fn memory_ledger(&self) -> Arc<MemoryLedger> {
+		self.memory.clone()
+	}
+
+	/// Builder: publish step completions to `events`
+	pub fn with_events(mut self, events: EventBus) -> Self {
+		self.events = Some(events);
+		self
+	}
+
+	/// Builder: capture the input and output of each top-level pipeline step, after `completed`
+	pub fn with_step_capture(mut self, completed: Vec<CapturedStep>) -> Self {
+		self.captured_steps = Some(Arc::new(parking_lot::Mutex::new(completed)));
+		self
+	}
+
+	/// Builder: journal the output of each top-level pipeline step
+	pub fn with_journal(mut self, journal: Arc<JournalRecorder>) -> Self {
+		self.journal = Some(journal);
+		self
+	}
+
+	/// Builder: report the progress of top-level pipeline steps to the client
+	pub fn with_progress(mut self) -> Self {
+		self.reports_progress = true;
+		self
+	}
+
+	/// Account for a payload held by this execution, failing if the budget is exhausted
+	pub fn charge(&self, value: &Value) -> Result<(), ExecutionError> {
+		self.memory.charge(value)
+	}
+
+	/// Approximate payload bytes held by this execution
+	pub fn memory_held(&self) -> usize {
+		self.memory.held()
+	}
+
 	/// Store a step result
 	pub async fn store_step_result(&self, step_id: &str, result: Value) {
 		self
@@ -57,11 +222,187 @@ impl ExecutionContext {
 	pub fn child(&self, input: Value) -> Self {
 		Self {
 			input,
+			composition_input: self.composition_input.clone(),
 			step_results: Arc::new(RwLock::new(HashMap::new())),
 			registry: self.registry.clone(),
 			tool_invoker: self.tool_invoker.clone(),
+			session: self.session.clone(),
+			step_timings: self.step_timings.clone(),
+			errors: self.errors.clone(),
+			verbosity: self.verbosity,
+			composition: self.composition.clone(),
+			execution_id: self.execution_id,
+			step_id: self.step_id.clone(),
+			labels: self.labels.clone(),
+			memory: self.memory.clone(),
+			events: self.events.clone(),
+			cache_hits: self.cache_hits.clone(),
+			cost: self.cost.clone(),
+			// Steps of nested patterns are part of the step that runs them
+			captured_steps: None,
+			journal: None,
+			reports_progress: false,
 		}
 	}
+
+	/// Create a view of this context for a pipeline step (shares step results)
+	pub fn for_step(&self, step_id: &str) -> Self {
+		Self {
+			input: self.input.clone(),
+			composition_input: self.composition_input.clone(),
+			step_results: self.step_results.clone(),
+			registry: self.registry.clone(),
+			tool_invoker: self.tool_invoker.clone(),
+			session: self.session.clone(),
+			step_timings: self.step_timings.clone(),
+			errors: self.errors.clone(),
+			verbosity: self.verbosity,
+			composition: self.composition.clone(),
+			execution_id: self.execution_id,
+			step_id: Some(step_id.to_string()),
+			labels: self.labels.clone(),
+			memory: self.memory.clone(),
+			events: self.events.clone(),
+			cache_hits: self.cache_hits.clone(),
+			cost: self.cost.clone(),
+			captured_steps: self.captured_steps.clone(),
+			journal: self.journal.clone(),
+			reports_progress: self.reports_progress,
+		}
+	}
+
+	/// Where in the composition this context is, for error messages and logs, e.g.
+	/// `composition research, step summarize`
+	pub fn location(&self) -> String {
+		let composition = self.composition.as_deref().unwrap_or("<unnamed>");
+		match &self.step_id {
+			Some(step) => format!("composition {composition}, step {step}"),
+			None => format!("composition {composition}"),
+		}
+	}
+
+	/// Metadata describing tool calls made from this context
+	pub fn invocation_meta(&self) -> InvocationMeta {
+		InvocationMeta {
+			composition: self.composition.clone(),
+			step_id: self.step_id.clone(),
+			labels: self.labels.clone(),
+		}
+	}
+
+	/// Record how long a step took
+	pub fn record_step_timing(&self, step_id: &str, duration: Duration, ok: bool) {
+		self.step_timings.lock().push(StepTiming {
+			step_id: step_id.to_string(),
+			duration_ms: duration.as_millis() as u64,
+			ok,
+		});
+		if let (Some(events), Some(composition)) = (&self.events, &self.composition) {
+			events.publish(EventKind::CompositionStepCompleted {
+				composition: composition.clone(),
+				execution: self.execution_id,
+				step: step_id.to_string(),
+				duration_ms: duration.as_millis() as u64,
+				ok,
+			});
+		}
+	}
+
+	/// Get recorded step timings
+	pub fn step_timings(&self) -> Vec<StepTiming> {
+		self.step_timings.lock().clone()
+	}
+
+	/// Capture a step's input and outcome, if the execution is captured
+	pub fn capture_step(
+		&self,
+		step_id: &str,
+		input: &Value,
+		result: &Result<Value, ExecutionError>,
+		duration: Duration,
+	) {
+		if let Some(steps) = &self.captured_steps {
+			steps.lock().push(CapturedStep {
+				step_id: step_id.to_string(),
+				input: input.clone(),
+				output: result.as_ref().ok().cloned(),
+				error: result.as_ref().err().map(ToString::to_string),
+				duration_ms: duration.as_millis() as u64,
+				carried_over: false,
+				redacted: false,
+			});
+		}
+	}
+
+	/// Tell the client that `completed` of `total` top-level steps are done, and `step` is
+	/// starting
+	pub async fn report_progress(&self, completed: usize, total: usize, step: &str) {
+		if self.reports_progress {
+			let message = format!("step {} of {total}: {step}", completed + 1);
+			self
+				.tool_invoker
+				.report_progress(completed as u32, total as u32, message)
+				.await;
+		}
+	}
+
+	/// Journal a completed step's output, if the execution is journaled
+	pub async fn journal_step(&self, step_id: &str, output: &Value) {
+		if let Some(journal) = &self.journal {
+			journal.step_completed(step_id, output).await;
+		}
+	}
+
+	/// Get captured steps (empty unless the execution is captured)
+	pub fn captured_steps(&self) -> Vec<CapturedStep> {
+		self
+			.captured_steps
+			.as_ref()
+			.map(|steps| steps.lock().clone())
+			.unwrap_or_default()
+	}
+
+	/// Record a failure the execution tolerated and continued past
+	pub fn record_error(&self, error: &ExecutionError) {
+		self.errors.lock().push(EnvelopeError {
+			step: self.step_id.clone(),
+			message: error.to_string(),
+		});
+	}
+
+	/// Count results served from a cache pattern
+	pub fn record_cache_hits(&self, hits: u64) {
+		self.cache_hits.fetch_add(hits, Ordering::Relaxed);
+	}
+
+	/// Results served from a cache pattern so far
+	pub fn cache_hits(&self) -> u64 {
+		self.cache_hits.load(Ordering::Relaxed)
+	}
+
+	/// Charge a call to a priced tool
+	pub fn record_cost(&self, tool: &str, cost: f64) {
+		self.cost.record(tool, cost);
+	}
+
+	/// Add the cost of a nested composition execution
+	pub fn merge_cost(&self, nested: &CostSummary) {
+		self.cost.merge(nested);
+	}
+
+	/// Estimated cost of the execution so far
+	pub fn cost(&self) -> f64 {
+		self.cost.total()
+	}
+
+	pub fn cost_summary(&self) -> CostSummary {
+		self.cost.summary()
+	}
+
+	/// Get recorded tolerated failures
+	pub fn errors(&self) -> Vec<EnvelopeError> {
+		self.errors.lock().clone()
+	}
 }
 
 #[cfg(test)]
@@ -113,4 +454,29 @@ mod tests {
 		// Child has different input
 		assert_eq!(child_ctx.input["child"], true);
 	}
+
+	#[tokio::test]
+	async fn test_child_context_shares_session() {
+		let registry = Registry::new();
+		let compiled =
+			Arc::new(crate::mcp::registry::compiled::CompiledRegistry::compile(registry).unwrap());
+		let invoker = Arc::new(MockToolInvoker::new());
+		let session = Arc::new(SessionStore::new());
+
+		let parent_ctx =
+			ExecutionContext::new(serde_json::json!({}), compiled, invoker).with_session(session.clone());
+		let child_ctx = parent_ctx.child(serde_json::json!({}));
+
+		child_ctx
+			.session
+			.set("cursor", serde_json::json!(42))
+			.await
+			.unwrap();
+
+		assert_eq!(session.get("cursor").await, Some(serde_json::json!(42)));
+		assert_eq!(
+			parent_ctx.session.get("cursor").await,
+			Some(serde_json::json!(42))
+		);
+	}
 }
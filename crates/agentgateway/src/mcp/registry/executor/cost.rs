@@ -0,0 +1,168 @@
+// Cost estimation for tool calls
+//
+// Tools priced with a `cost` model in the registry are charged on every call a
+// composition makes to them. Costs accumulate per execution, including nested
+// compositions, so budget guards and chargeback can work from estimated cost
+// rather than call counts.
+
+use std::collections::BTreeMap;
+use std::io;
+
+use parking_lot::Mutex;
+use serde::Serialize;
+use serde_json::Value;
+
+use super::path_cache;
+use crate::mcp::registry::types::CostModel;
+
+/// Estimated cost of one call to a tool priced with `model`
+///
+/// `input_bytes` is the serialized size of the call's arguments; only bytes-based models
+/// use it.
+pub fn estimate(model: &CostModel, input_bytes: usize, result: &Value) -> f64 {
+	match model {
+		CostModel::Static(cost) => *cost,
+		CostModel::Bytes(bytes) => {
+			bytes.per_call
+				+ kib(input_bytes) * bytes.per_kib_in
+				+ kib(serialized_len(result)) * bytes.per_kib_out
+		},
+		CostModel::Reported(reported) => path_cache::parse(&reported.path)
+			.ok()
+			.and_then(|path| path.query(result).first().and_then(Value::as_f64))
+			.filter(|cost| cost.is_finite() && *cost >= 0.0)
+			.unwrap_or(reported.fallback),
+	}
+}
+
+fn kib(bytes: usize) -> f64 {
+	bytes as f64 / 1024.0
+}
+
+/// Length of a value's JSON serialization, without allocating it
+pub fn serialized_len(value: &Value) -> usize {
+	struct Count(usize);
+	impl io::Write for Count {
+		fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+			self.0 += buf.len();
+			Ok(buf.len())
+		}
+		fn flush(&mut self) -> io::Result<()> {
+			Ok(())
+		}
+	}
+	let mut count = Count(0);
+	let _ = serde_json::to_writer(&mut count, value);
+	count.0
+}
+
+/// Cost of the calls one execution made to a priced tool
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct ToolCost {
+	pub calls: u64,
+	pub cost: f64,
+}
+
+/// Estimated cost of an execution's tool calls
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct CostSummary {
+	/// Total estimated cost
+	pub total: f64,
+	/// Calls and cost by priced tool (tools without a cost model aren't listed)
+	pub tools: BTreeMap<String, ToolCost>,
+}
+
+impl CostSummary {
+	/// Check if no priced tool was called
+	pub fn is_empty(&self) -> bool {
+		self.tools.is_empty()
+	}
+}
+
+/// Running cost of an execution, shared by its contexts
+#[derive(Debug, Default)]
+pub struct CostLedger(Mutex<CostSummary>);
+
+impl CostLedger {
+	/// Charge one call to `tool`
+	pub fn record(&self, tool: &str, cost: f64) {
+		let mut summary = self.0.lock();
+		summary.total += cost;
+		let entry = summary.tools.entry(tool.to_string()).or_default();
+		entry.calls += 1;
+		entry.cost += cost;
+	}
+
+	/// Add the cost of a nested execution
+	pub fn merge(&self, other: &CostSummary) {
+		let mut summary = self.0.lock();
+		summary.total += other.total;
+		for (tool, cost) in &other.tools {
+			let entry = summary.tools.entry(tool.clone()).or_default();
+			entry.calls += cost.calls;
+			entry.cost += cost.cost;
+		}
+	}
+
+	/// Total cost so far
+	pub fn total(&self) -> f64 {
+		self.0.lock().total
+	}
+
+	pub fn summary(&self) -> CostSummary {
+		self.0.lock().clone()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use serde_json::json;
+
+	use super::*;
+	use crate::mcp::registry::types::{BytesCost, ReportedCost};
+
+	#[test]
+	fn test_estimate() {
+		let result = json!({"usage": {"cost": 0.25}, "text": "x".repeat(2015)});
+		assert_eq!(serialized_len(&result), 2048);
+
+		assert_eq!(estimate(&CostModel::Static(0.5), 0, &result), 0.5);
+
+		let bytes = CostModel::Bytes(BytesCost {
+			per_call: 0.1,
+			per_kib_in: 1.0,
+			per_kib_out: 2.0,
+		});
+		assert_eq!(estimate(&bytes, 512, &result), 0.1 + 0.5 + 4.0);
+
+		let reported = |path: &str| {
+			CostModel::Reported(ReportedCost {
+				path: path.to_string(),
+				fallback: 0.01,
+			})
+		};
+		assert_eq!(estimate(&reported("$.usage.cost"), 0, &result), 0.25);
+		assert_eq!(estimate(&reported("$.usage.tokens"), 0, &result), 0.01);
+	}
+
+	#[test]
+	fn test_ledger_merges_nested_executions() {
+		let ledger = CostLedger::default();
+		ledger.record("search", 1.0);
+
+		let nested = CostLedger::default();
+		nested.record("search", 0.5);
+		nested.record("rerank", 2.0);
+		ledger.merge(&nested.summary());
+
+		let summary = ledger.summary();
+		assert_eq!(summary.total, 3.5);
+		assert_eq!(
+			summary.tools["search"],
+			ToolCost {
+				calls: 2,
+				cost: 1.5
+			}
+		);
+	}
+}
@@ -0,0 +1,633 @@
+// Dead letter pattern executor
+//
+// Retries the inner operation, then hands the failed input to the dead letter tool. Failures
+// are also kept in a bounded in-memory queue, so operators can browse them from the admin API
+// and replay them through their composition once the cause is fixed. The queue holds only the
+// redacted input: a replay runs with the operator's invoker, not the original caller's, and
+// entries whose input lost values to redaction or truncation can't be replayed.
+
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use parking_lot::Mutex;
+use serde::Serialize;
+use serde_json::{Value, json};
+use tracing::warn;
+
+use super::context::ExecutionContext;
+use super::execution_id::ExecutionId;
+use super::execution_log::{redact, redact_fields};
+use super::{CompositionExecutor, ExecutionError, PipelineExecutor};
+use crate::events::EventKind;
+use crate::mcp::registry::patterns::DeadLetterSpec;
+
+/// Default number of dead-lettered payloads retained
+pub const DEFAULT_DEAD_LETTER_CAPACITY: usize = 1000;
+
+/// Shortest redacted value scrubbed from dead letter error text
+const MIN_SCRUBBED_LEN: usize = 4;
+
+/// A dead-lettered composition input
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeadLetterEntry {
+	/// Identifier used to inspect and replay the entry
+	pub id: u64,
+	/// Execution the failure happened in
+	pub execution_id: ExecutionId,
+	/// Composition the failure happened in
+	pub composition: String,
+	/// Step that wrapped the failed operation
+	pub step_id: String,
+	/// Tool the failure was sent to
+	pub dead_letter_tool: String,
+	/// When the last attempt failed (milliseconds since the Unix epoch)
+	pub failed_at_ms: u64,
+	/// Final error message
+	pub error: String,
+	/// Whether the final error is one a retry could fix (e.g. a backend outage)
+	pub retryable: bool,
+	/// Error of each attempt, in order
+	pub attempt_errors: Vec<String>,
+	/// Redacted composition input
+	pub input: Value,
+	/// Whether the input survived redaction intact, so the entry can be replayed
+	pub replayable: bool,
+	/// Replays that failed again
+	pub failed_replays: u32,
+}
+
+/// One page of dead letter entries, newest first
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeadLetterPage {
+	pub entries: Vec<DeadLetterEntry>,
+	/// Entries matching the filter across all pages
+	pub total: usize,
+	/// Offset of the next page, if any
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub next_offset: Option<usize>,
+}
+
+/// Outcome of replaying one entry
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReplayOutcome {
+	pub id: u64,
+	#[serde(flatten)]
+	pub status: ReplayStatus,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "status", rename_all = "camelCase")]
+pub enum ReplayStatus {
+	/// The composition succeeded; the entry was removed
+	Succeeded { result: Value },
+	/// The composition failed again; the entry was kept
+	Failed { error: String },
+	/// No entry with this id (already replayed or evicted)
+	NotFound,
+	/// The entry's input had values redacted, so it can't be replayed as-is
+	Redacted,
+}
+
+/// Bounded queue of dead-lettered payloads; the oldest entries are evicted first
+pub struct DeadLetterQueue {
+	capacity: usize,
+	next_id: AtomicU64,
+	entries: Mutex<VecDeque<DeadLetterEntry>>,
+}
+
+impl std::fmt::Debug for DeadLetterQueue {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		f.debug_struct("DeadLetterQueue")
+			.field("capacity", &self.capacity)
+			.field("len", &self.len())
+			.finish()
+	}
+}
+
+impl Default for DeadLetterQueue {
+	fn default() -> Self {
+		Self::new(DEFAULT_DEAD_LETTER_CAPACITY)
+	}
+}
+
+impl DeadLetterQueue {
+	pub fn new(capacity: usize) -> Self {
+		Self {
+			capacity,
+			next_id: AtomicU64::new(1),
+			entries: Mutex::new(VecDeque::new()),
+		}
+	}
+
+	/// Number of retained entries
+	pub fn len(&self) -> usize {
+		self.entries.lock().len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.len() == 0
+	}
+
+	/// Retain a failure, returning its id
+	fn record(&self, mut entry: DeadLetterEntry) -> u64 {
+		let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+		entry.id = id;
+		let mut entries = self.entries.lock();
+		if entries.len() >= self.capacity {
+			entries.pop_front();
+		}
+		if self.capacity > 0 {
+			entries.push_back(entry);
+		}
+		id
+	}
+
+	/// Entries, newest first, optionally only those of one composition
+	pub fn list(&self, composition: Option<&str>, offset: usize, limit: usize) -> DeadLetterPage {
+		let entries = self.entries.lock();
+		let matching: Vec<&DeadLetterEntry> = entries
+			.iter()
+			.rev()
+			.filter(|e| composition.is_none_or(|c| e.composition == c))
+			.collect();
+		let total = matching.len();
+		let page: Vec<DeadLetterEntry> = matching
+			.into_iter()
+			.skip(offset)
+			.take(limit)
+			.cloned()
+			.collect();
+		let next_offset = (offset + page.len() < total).then_some(offset + page.len());
+		DeadLetterPage {
+			entries: page,
+			total,
+			next_offset,
+		}
+	}
+
+	/// Look up one entry
+	pub fn get(&self, id: u64) -> Option<DeadLetterEntry> {
+		self.entries.lock().iter().find(|e| e.id == id).cloned()
+	}
+
+	/// Entries dead-lettered by one execution, oldest first
+	pub fn for_execution(&self, execution_id: ExecutionId) -> Vec<DeadLetterEntry> {
+		self
+			.entries
+			.lock()
+			.iter()
+			.filter(|e| e.execution_id == execution_id)
+			.cloned()
+			.collect()
+	}
+
+	/// Discard entries, returning how many were removed
+	pub fn remove(&self, ids: &[u64]) -> usize {
+		let mut entries = self.entries.lock();
+		let before = entries.len();
+		entries.retain(|e| !ids.contains(&e.id));
+		before - entries.len()
+	}
+
+	/// Run entries through their composition again, one at a time, with `executor`
+	///
+	/// The executor's invoker is the operator's, not the original caller's. Dead letter
+	/// patterns don't capture failures during a replay; a failed replay keeps its entry.
+	pub async fn replay(&self, ids: &[u64], executor: CompositionExecutor) -> Vec<ReplayOutcome> {
+		let executor = executor.for_dead_letter_replay();
+		let mut outcomes = Vec::with_capacity(ids.len());
+		for &id in ids {
+			let found = self
+				.entries
+				.lock()
+				.iter()
+				.find(|e| e.id == id)
+				.map(|e| (e.composition.clone(), e.input.clone(), e.replayable));
+			let status = match found {
+				None => ReplayStatus::NotFound,
+				Some((_, _, false)) => ReplayStatus::Redacted,
+				Some((composition, input, true)) => {
+					self.replay_one(id, &composition, input, &executor).await
+				},
+			};
+			outcomes.push(ReplayOutcome { id, status });
+		}
+		outcomes
+	}
+
+	async fn replay_one(
+		&self,
+		id: u64,
+		composition: &str,
+		input: Value,
+		executor: &CompositionExecutor,
+	) -> ReplayStatus {
+		match executor.execute(composition, input).await {
+			Ok(result) => {
+				self.remove(&[id]);
+				ReplayStatus::Succeeded { result }
+			},
+			Err(e) => {
+				let error = e.to_string();
+				if let Some(entry) = self.entries.lock().iter_mut().find(|entry| entry.id == id) {
+					entry.failed_replays += 1;
+					entry.error = error.clone();
+					entry.retryable = e.is_retryable();
+				}
+				ReplayStatus::Failed { error }
+			},
+		}
+	}
+}
+
+/// Executor for dead letter patterns
+pub struct DeadLetterExecutor;
+
+impl DeadLetterExecutor {
+	/// Execute a dead letter pattern
+	///
+	/// Runs the inner operation up to `max_attempts` times. When every attempt fails, the input
+	/// and error are sent to the dead letter tool and retained in the executor's dead letter
+	/// queue, then the error is returned if `rethrow` is set, or null otherwise.
+	pub async fn execute(
+		spec: &DeadLetterSpec,
+		input: Value,
+		ctx: &ExecutionContext,
+		executor: &CompositionExecutor,
+	) -> Result<Value, ExecutionError> {
+		let step_id = ctx
+			.step_id
+			.clone()
+			.unwrap_or_else(|| "dead_letter".to_string());
+		let attempts = spec.max_attempts.max(1);
+		let mut errors = Vec::new();
+		for attempt in 1..=attempts {
+			match PipelineExecutor::execute_operation(&step_id, &spec.inner, input.clone(), ctx, executor)
+				.await
+			{
+				Ok(result) => return Ok(result),
+				Err(e) => errors.push(e),
+			}
+			if attempt < attempts
+				&& let Some(backoff) = &spec.backoff
+			{
+				tokio::time::sleep(backoff.delay(attempt)).await;
+			}
+		}
+		let error = errors.pop().expect("at least one attempt");
+		// Replays report their failure to the operator instead of dead-lettering again
+		if executor.dead_letter_replay {
+			return Err(error);
+		}
+
+		let composition = ctx.composition.clone().unwrap_or_default();
+		let failed_at_ms = SystemTime::now()
+			.duration_since(UNIX_EPOCH)
+			.unwrap_or_default()
+			.as_millis() as u64;
+		let mut payload = json!({
+			"composition": composition,
+			"execution_id": ctx.execution_id,
+			"step": step_id,
+			"attempts": attempts,
+			"failed_at_ms": failed_at_ms,
+		});
+		Self::add_failure(&mut payload, spec, &input, &error, ctx, executor).await;
+		if let Err(e) = executor
+			.execute_tool(&spec.dead_letter_tool, payload, ctx)
+			.await
+		{
+			warn!(
+				target: "virtual_tools",
+				composition = %composition,
+				step = %step_id,
+				tool = %spec.dead_letter_tool,
+				error = %e,
+				"failed to send to dead letter tool"
+			);
+		}
+
+		if let Some(queue) = &executor.dead_letters {
+			let mut attempt_errors: Vec<String> = errors.iter().map(ToString::to_string).collect();
+			attempt_errors.push(error.to_string());
+			let input = redact(&redact_fields(
+				&ctx.composition_input,
+				&spec.sensitive_fields,
+				&mut Vec::new(),
+			));
+			let entry = DeadLetterEntry {
+				id: 0,
+				execution_id: ctx.execution_id,
+				composition,
+				step_id,
+				dead_letter_tool: spec.dead_letter_tool.clone(),
+				failed_at_ms,
+				error: error.to_string(),
+				retryable: error.is_retryable(),
+				attempt_errors,
+				replayable: input == *ctx.composition_input,
+				input,
+				failed_replays: 0,
+			};
+			queue.record(entry);
+		}
+		if let Some(events) = &executor.events {
+			events.publish(EventKind::DeadLetterEmitted {
+				error: Some(error.to_string()),
+				attempts: u8::try_from(attempts).unwrap_or(u8::MAX),
+			});
+		}
+
+		if spec.rethrow {
+			Err(error)
+		} else {
+			Ok(Value::Null)
+		}
+	}
+
+	/// Add the redacted input and error to a dead letter payload
+	///
+	/// Values of sensitive fields are replaced in the input and scrubbed from the error text.
+	/// An input over `max_payload_bytes` is stored with the claim check tool and sent as
+	/// `original_input_claim`, or left out without one.
+	async fn add_failure(
+		payload: &mut Value,
+		spec: &DeadLetterSpec,
+		input: &Value,
+		error: &ExecutionError,
+		ctx: &ExecutionContext,
+		executor: &CompositionExecutor,
+	) {
+		let mut secrets = Vec::new();
+		let original_input = redact_fields(input, &spec.sensitive_fields, &mut secrets);
+		let mut error = error.to_string();
+		// Short values would scrub unrelated text
+		for secret in secrets.iter().filter(|s| s.len() >= MIN_SCRUBBED_LEN) {
+			error = error.replace(secret.as_str(), "[REDACTED]");
+		}
+
+		let Some(max_bytes) = spec.max_payload_bytes.map(|b| b as usize) else {
+			payload["original_input"] = original_input;
+			payload["error"] = Value::String(error);
+			return;
+		};
+		if error.len() > max_bytes {
+			let mut end = max_bytes;
+			while !error.is_char_boundary(end) {
+				end -= 1;
+			}
+			error = format!("{}...[truncated]", &error[..end]);
+		}
+		payload["error"] = Value::String(error);
+
+		let input_bytes = original_input.to_string().len();
+		if input_bytes <= max_bytes {
+			payload["original_input"] = original_input;
+			return;
+		}
+		payload["original_input"] = Value::Null;
+		payload["original_input_bytes"] = json!(input_bytes);
+		let Some(claim_check_tool) = &spec.claim_check_tool else {
+			return;
+		};
+		let stored = json!({
+			"composition": payload["composition"],
+			"execution_id": payload["execution_id"],
+			"step": payload["step"],
+			"payload": original_input,
+		});
+		match executor.execute_tool(claim_check_tool, stored, ctx).await {
+			Ok(claim) => payload["original_input_claim"] = claim,
+			Err(e) => warn!(
+				target: "virtual_tools",
+				step = %payload["step"],
+				tool = %claim_check_tool,
+				error = %e,
+				"failed to store oversized dead letter input; sending without it"
+			),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::sync::Arc;
+
+	use super::*;
+	use crate::mcp::registry::CompiledRegistry;
+	use crate::mcp::registry::executor::MockToolInvoker;
+	use crate::mcp::registry::patterns::{PatternSpec, StepOperation, ToolCall};
+	use crate::mcp::registry::types::{Registry, ToolDefinition, ToolImplementation};
+
+	fn guarded_order() -> ToolDefinition {
+		ToolDefinition::composition(
+			"place_order",
+			PatternSpec::DeadLetter(DeadLetterSpec {
+				inner: Box::new(StepOperation::Tool(ToolCall {
+					name: "create_order".to_string(),
+				})),
+				dead_letter_tool: "park_order".to_string(),
+				max_attempts: 2,
+				backoff: None,
+				rethrow: false,
+				sensitive_fields: vec![],
+				max_payload_bytes: None,
+				claim_check_tool: None,
+			}),
+		)
+	}
+
+	fn compiled() -> Arc<CompiledRegistry> {
+		let registry = Registry::with_tool_definitions(vec![guarded_order()]);
+		Arc::new(CompiledRegistry::compile(registry).unwrap())
+	}
+
+	#[tokio::test]
+	async fn test_failures_are_dead_lettered() {
+		let queue = Arc::new(DeadLetterQueue::default());
+		let invoker = Arc::new(
+			MockToolInvoker::new()
+				.with_error("create_order", "inventory down")
+				.with_response("park_order", json!({"parked": true})),
+		);
+		let execution_id = ExecutionId::new();
+		let executor = CompositionExecutor::new(compiled(), invoker.clone())
+			.with_dead_letters(queue.clone())
+			.with_execution_id(execution_id);
+
+		let input = json!({"sku": "A1", "token": "secret"});
+		let result = executor.execute("place_order", input).await.unwrap();
+		assert_eq!(result, Value::Null);
+		assert_eq!(invoker.call_count("create_order"), 2);
+		assert_eq!(invoker.call_count("park_order"), 1);
+
+		let page = queue.list(Some("place_order"), 0, 10);
+		assert_eq!(page.total, 1);
+		let entry = &page.entries[0];
+		assert_eq!(entry.attempt_errors.len(), 2);
+		assert!(entry.error.contains("inventory down"));
+		assert_eq!(entry.input["sku"], "A1");
+		assert_ne!(entry.input["token"], "secret");
+		assert!(queue.list(Some("other"), 0, 10).entries.is_empty());
+
+		// The entry and the dead letter payload carry the execution id
+		assert_eq!(entry.execution_id, execution_id);
+		assert_eq!(queue.for_execution(execution_id).len(), 1);
+		assert!(queue.for_execution(ExecutionId::new()).is_empty());
+		let payload = invoker.call_args("park_order").remove(0);
+		assert_eq!(payload["execution_id"], execution_id.to_string());
+	}
+
+	#[tokio::test]
+	async fn test_payload_is_redacted_and_size_limited() {
+		let mut tool = guarded_order();
+		let ToolImplementation::Spec(PatternSpec::DeadLetter(spec)) = &mut tool.implementation else {
+			unreachable!()
+		};
+		spec.max_attempts = 1;
+		spec.sensitive_fields = vec!["card".to_string()];
+		spec.max_payload_bytes = Some(64);
+		spec.claim_check_tool = Some("store_payload".to_string());
+		let compiled =
+			Arc::new(CompiledRegistry::compile(Registry::with_tool_definitions(vec![tool])).unwrap());
+		let invoker = Arc::new(
+			MockToolInvoker::new()
+				.with_error("create_order", "card 4111111111111111 declined")
+				.with_response("park_order", json!({}))
+				.with_response("store_payload", json!({"ref": "claim-1"})),
+		);
+		let executor = CompositionExecutor::new(compiled, invoker.clone());
+
+		// Small input: sent inline, with the card number redacted in the input and error
+		let input = json!({"sku": "A1", "cardNumber": "4111111111111111", "apiKey": "k"});
+		executor.execute("place_order", input).await.unwrap();
+		let payload = invoker.call_args("park_order").remove(0);
+		assert_eq!(payload["original_input"]["sku"], "A1");
+		assert_eq!(payload["original_input"]["cardNumber"], "[REDACTED]");
+		assert_eq!(payload["original_input"]["apiKey"], "[REDACTED]");
+		assert!(
+			payload["error"]
+				.as_str()
+				.unwrap()
+				.contains("card [REDACTED] declined")
+		);
+		assert_eq!(invoker.call_count("store_payload"), 0);
+
+		// Large input: stored with the claim check tool and sent by reference
+		let input = json!({"sku": "A1", "notes": "x".repeat(100), "card": "4111111111111111"});
+		executor.execute("place_order", input).await.unwrap();
+		let payload = invoker.call_args("park_order").remove(1);
+		assert_eq!(payload["original_input"], Value::Null);
+		assert_eq!(payload["original_input_claim"], json!({"ref": "claim-1"}));
+		assert!(payload["original_input_bytes"].as_u64().unwrap() > 64);
+		let stored = invoker.call_args("store_payload").remove(0);
+		assert_eq!(stored["payload"]["card"], "[REDACTED]");
+	}
+
+	#[tokio::test]
+	async fn test_replay_removes_entries_that_succeed() {
+		let queue = Arc::new(DeadLetterQueue::default());
+		let failing = Arc::new(
+			MockToolInvoker::new()
+				.with_error("create_order", "inventory down")
+				.with_response("park_order", json!({})),
+		);
+		let executor = CompositionExecutor::new(compiled(), failing).with_dead_letters(queue.clone());
+		executor
+			.execute("place_order", json!({"sku": "A1"}))
+			.await
+			.unwrap();
+		let id = queue.list(None, 0, 10).entries[0].id;
+
+		// Still failing: the entry is kept and no new one is captured
+		let operator = Arc::new(
+			MockToolInvoker::new()
+				.with_error("create_order", "inventory down")
+				.with_response("park_order", json!({})),
+		);
+		let executor =
+			CompositionExecutor::new(compiled(), operator.clone()).with_dead_letters(queue.clone());
+		let outcomes = queue.replay(&[id, 999], executor).await;
+		assert!(matches!(outcomes[0].status, ReplayStatus::Failed { .. }));
+		assert!(matches!(outcomes[1].status, ReplayStatus::NotFound));
+		assert_eq!(queue.len(), 1);
+		assert_eq!(queue.get(id).unwrap().failed_replays, 1);
+
+		// Fixed backend: the replay succeeds and the entry is gone
+		let fixed = Arc::new(MockToolInvoker::new().with_response("create_order", json!({"id": 7})));
+		let outcomes = queue
+			.replay(&[id], CompositionExecutor::new(compiled(), fixed.clone()))
+			.await;
+		assert!(matches!(
+			&outcomes[0].status,
+			ReplayStatus::Succeeded { result } if result == &json!({"id": 7})
+		));
+		assert!(queue.is_empty());
+		assert_eq!(operator.call_count("park_order"), 0);
+		assert_eq!(fixed.call_args("create_order"), vec![json!({"sku": "A1"})]);
+	}
+
+	#[tokio::test]
+	async fn test_redacted_entries_are_not_replayed() {
+		let queue = Arc::new(DeadLetterQueue::default());
+		let invoker = Arc::new(
+			MockToolInvoker::new()
+				.with_error("create_order", "inventory down")
+				.with_response("park_order", json!({})),
+		);
+		let executor = CompositionExecutor::new(compiled(), invoker).with_dead_letters(queue.clone());
+		executor
+			.execute("place_order", json!({"sku": "A1", "apiKey": "k"}))
+			.await
+			.unwrap();
+		let entry = queue.list(None, 0, 10).entries.remove(0);
+		assert!(!entry.replayable);
+
+		let fixed = Arc::new(MockToolInvoker::new().with_response("create_order", json!({"id": 7})));
+		let outcomes = queue
+			.replay(
+				&[entry.id],
+				CompositionExecutor::new(compiled(), fixed.clone()),
+			)
+			.await;
+		assert!(matches!(outcomes[0].status, ReplayStatus::Redacted));
+		assert_eq!(fixed.call_count("create_order"), 0);
+		assert_eq!(queue.len(), 1);
+	}
+
+	#[test]
+	fn test_queue_pages_and_evicts_oldest() {
+		let queue = DeadLetterQueue::new(3);
+		for n in 0..4 {
+			let entry = DeadLetterEntry {
+				id: 0,
+				execution_id: ExecutionId::new(),
+				composition: "c".to_string(),
+				step_id: "s".to_string(),
+				dead_letter_tool: "t".to_string(),
+				failed_at_ms: n,
+				error: "e".to_string(),
+				retryable: true,
+				attempt_errors: vec![],
+				input: Value::Null,
+				replayable: true,
+				failed_replays: 0,
+			};
+			queue.record(entry);
+		}
+		let first = queue.list(None, 0, 2);
+		assert_eq!(first.total, 3);
+		assert_eq!(
+			first.entries.iter().map(|e| e.id).collect::<Vec<_>>(),
+			vec![4, 3]
+		);
+		assert_eq!(first.next_offset, Some(2));
+		let second = queue.list(None, 2, 2);
+		assert_eq!(second.entries[0].id, 2);
+		assert_eq!(second.next_offset, None);
+	}
+}
@@ -0,0 +1,75 @@
+// Standard result envelope for composition outputs
+
+use serde::Serialize;
+use serde_json::{Value, json};
+
+/// Composition output wrapped as `{data, errors, meta}`, enabled per tool with
+/// `resultEnvelope: true` so agent frameworks can parse every composition the same way
+#[derive(Debug, Clone, Serialize)]
+pub struct ResultEnvelope {
+	/// The composition output (after any output transform)
+	pub data: Value,
+	/// Failures the composition tolerated, e.g. scatter-gather targets that failed
+	/// without `failFast`. Executions that fail outright are still reported as tool errors.
+	pub errors: Vec<EnvelopeError>,
+	pub meta: EnvelopeMeta,
+}
+
+/// A tolerated failure reported in the envelope
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct EnvelopeError {
+	/// Pipeline step the failure occurred in, if any
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub step: Option<String>,
+	pub message: String,
+}
+
+/// Execution metadata reported in the envelope
+#[derive(Debug, Clone, Serialize)]
+pub struct EnvelopeMeta {
+	/// Total execution time
+	pub duration_ms: u64,
+	/// Number of pipeline steps executed
+	pub steps: usize,
+	/// Results served from a cache pattern
+	pub cache_hits: u64,
+	/// Estimated cost of the execution's tool calls
+	pub cost: f64,
+}
+
+impl ResultEnvelope {
+	pub fn into_value(self) -> Value {
+		serde_json::to_value(self).expect("envelope serialization cannot fail")
+	}
+}
+
+/// Output schema advertised for an enveloped composition, wrapping its declared schema
+pub fn envelope_output_schema(data: Option<&Value>) -> Value {
+	json!({
+		"type": "object",
+		"properties": {
+			"data": data.cloned().unwrap_or_else(|| json!({})),
+			"errors": {
+				"type": "array",
+				"items": {
+					"type": "object",
+					"properties": {
+						"step": {"type": "string"},
+						"message": {"type": "string"}
+					},
+					"required": ["message"]
+				}
+			},
+			"meta": {
+				"type": "object",
+				"properties": {
+					"duration_ms": {"type": "integer"},
+					"steps": {"type": "integer"},
+					"cache_hits": {"type": "integer"},
+					"cost": {"type": "number"}
+				}
+			}
+		},
+		"required": ["data", "errors", "meta"]
+	})
+}
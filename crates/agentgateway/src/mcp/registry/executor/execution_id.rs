@@ -0,0 +1,78 @@
+// Execution ids
+//
+// Every composition execution gets a random UUID when it starts. The id is returned to the
+// caller in the result's `_meta` under `agentgateway.dev/executionId`, and is attached to
+// everything the execution leaves behind: log lines, the composition span, the request log,
+// lifecycle events, the execution log, captures, archived payloads and dead letters. Given
+// the id from a caller's report, an operator can find all of them.
+
+use std::fmt;
+use std::str::FromStr;
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use uuid::Uuid;
+
+/// `_meta` key holding the id of the execution that produced a result
+pub const EXECUTION_ID_META_KEY: &str = "agentgateway.dev/executionId";
+
+/// Unique id of one composition execution, serialized as a hyphenated UUID
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ExecutionId(Uuid);
+
+impl ExecutionId {
+	/// A new random id
+	pub fn new() -> Self {
+		Self(Uuid::new_v4())
+	}
+}
+
+impl Default for ExecutionId {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl fmt::Display for ExecutionId {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		self.0.hyphenated().fmt(f)
+	}
+}
+
+impl FromStr for ExecutionId {
+	type Err = uuid::Error;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		Uuid::parse_str(s).map(Self)
+	}
+}
+
+impl Serialize for ExecutionId {
+	fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+		serializer.collect_str(self)
+	}
+}
+
+impl<'de> Deserialize<'de> for ExecutionId {
+	fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+		let s = String::deserialize(deserializer)?;
+		s.parse().map_err(serde::de::Error::custom)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_execution_ids() {
+		let id = ExecutionId::new();
+		assert_ne!(id, ExecutionId::new());
+		assert_eq!(id.to_string().len(), 36);
+		assert_eq!(id.to_string().parse::<ExecutionId>().unwrap(), id);
+		assert_eq!(
+			serde_json::to_value(id).unwrap(),
+			serde_json::Value::String(id.to_string())
+		);
+		assert!("exec-7".parse::<ExecutionId>().is_err());
+	}
+}
@@ -0,0 +1,437 @@
+// Per-composition execution log for debugging
+
+use std::collections::{HashMap, VecDeque};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use parking_lot::Mutex;
+use serde::Serialize;
+use serde_json::Value;
+
+use super::ExecutionError;
+use super::cost::CostSummary;
+use super::execution_id::ExecutionId;
+use super::labels::ExecutionLabels;
+
+/// Default number of executions retained per composition
+pub const DEFAULT_EXECUTION_LOG_CAPACITY: usize = 20;
+
+/// Maximum length of string values kept in recorded inputs/outputs
+const MAX_STRING_LEN: usize = 256;
+
+/// Key words whose values are always redacted (matched case-insensitively against each word
+/// of a key, e.g. `clientSecret` and `db_password_hash`)
+const SENSITIVE_KEYS: &[&str] = &[
+	"password",
+	"passwords",
+	"secret",
+	"secrets",
+	"authorization",
+	"apikey",
+	"cookie",
+	"cookies",
+	"credential",
+	"credentials",
+];
+
+/// Key words whose values are redacted when they end a key: `access_token` holds a token,
+/// `max_tokens` and `token_count` don't
+const SENSITIVE_LAST_KEYS: &[&str] = &["token"];
+
+/// Maximum number of redacted strings collected for scrubbing from other text
+const MAX_REDACTED_STRINGS: usize = 32;
+
+/// Timing for a single composition step
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StepTiming {
+	/// Step identifier
+	pub step_id: String,
+	/// Time spent executing the step
+	pub duration_ms: u64,
+	/// Whether the step succeeded
+	pub ok: bool,
+}
+
+/// A single recorded composition execution
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExecutionRecord {
+	/// Unique id of the execution
+	pub execution_id: ExecutionId,
+	/// Start time (milliseconds since the Unix epoch)
+	pub started_at_ms: u64,
+	/// Total execution time
+	pub duration_ms: u64,
+	/// Redacted composition input
+	pub input: Value,
+	/// Redacted composition output (on success)
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub output: Option<Value>,
+	/// Error message (on failure)
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub error: Option<String>,
+	/// Step timings in completion order
+	pub steps: Vec<StepTiming>,
+	/// Labels the caller attached to the execution
+	#[serde(skip_serializing_if = "ExecutionLabels::is_empty")]
+	pub labels: ExecutionLabels,
+	/// Estimated cost of the execution's priced tool calls
+	#[serde(skip_serializing_if = "CostSummary::is_empty")]
+	pub cost: CostSummary,
+}
+
+impl ExecutionRecord {
+	/// Build a record from an execution outcome, redacting input and output
+	pub fn new(
+		execution_id: ExecutionId,
+		input: &Value,
+		result: &Result<Value, ExecutionError>,
+		duration: Duration,
+		steps: Vec<StepTiming>,
+	) -> Self {
+		let started_at_ms = SystemTime::now()
+			.checked_sub(duration)
+			.and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+			.map(|d| d.as_millis() as u64)
+			.unwrap_or_default();
+		let (output, error) = match result {
+			Ok(v) => (Some(redact(v)), None),
+			Err(e) => (None, Some(e.to_string())),
+		};
+		Self {
+			execution_id,
+			started_at_ms,
+			duration_ms: duration.as_millis() as u64,
+			input: redact(input),
+			output,
+			error,
+			steps,
+			labels: ExecutionLabels::default(),
+			cost: CostSummary::default(),
+		}
+	}
+
+	/// Builder: attach the estimated cost of the execution
+	pub fn with_cost(mut self, cost: CostSummary) -> Self {
+		self.cost = cost;
+		self
+	}
+
+	/// Builder: attribute the execution to caller-supplied labels
+	pub fn with_labels(mut self, labels: &ExecutionLabels) -> Self {
+		self.labels = labels.clone();
+		self
+	}
+}
+
+/// In-memory ring buffer of recent executions, bounded per composition
+///
+/// Lets operators inspect what the last N invocations of a composition actually did
+/// without enabling verbose logging globally.
+#[derive(Debug)]
+pub struct ExecutionLog {
+	capacity: usize,
+	entries: Mutex<HashMap<String, VecDeque<ExecutionRecord>>>,
+}
+
+impl Default for ExecutionLog {
+	fn default() -> Self {
+		Self::new(DEFAULT_EXECUTION_LOG_CAPACITY)
+	}
+}
+
+impl ExecutionLog {
+	/// Create a log retaining up to `capacity` executions per composition (0 disables recording)
+	pub fn new(capacity: usize) -> Self {
+		Self {
+			capacity,
+			entries: Mutex::new(HashMap::new()),
+		}
+	}
+
+	/// Maximum number of executions retained per composition
+	pub fn capacity(&self) -> usize {
+		self.capacity
+	}
+
+	/// Check if recording is enabled
+	pub fn is_enabled(&self) -> bool {
+		self.capacity > 0
+	}
+
+	/// Record an execution, evicting the oldest entry once the composition is at capacity
+	pub fn record(&self, composition: &str, record: ExecutionRecord) {
+		if !self.is_enabled() {
+			return;
+		}
+		let mut entries = self.entries.lock();
+		let buffer = entries.entry(composition.to_string()).or_default();
+		if buffer.len() >= self.capacity {
+			buffer.pop_front();
+		}
+		buffer.push_back(record);
+	}
+
+	/// Recent executions of a composition, newest first
+	pub fn recent(&self, composition: &str) -> Vec<ExecutionRecord> {
+		self
+			.entries
+			.lock()
+			.get(composition)
+			.map(|b| b.iter().rev().cloned().collect())
+			.unwrap_or_default()
+	}
+
+	/// Recent executions of every composition, newest first
+	pub fn snapshot(&self) -> HashMap<String, Vec<ExecutionRecord>> {
+		self
+			.entries
+			.lock()
+			.iter()
+			.map(|(name, b)| (name.clone(), b.iter().rev().cloned().collect()))
+			.collect()
+	}
+
+	/// The recorded execution with the given id, and the composition it ran
+	pub fn find(&self, execution_id: ExecutionId) -> Option<(String, ExecutionRecord)> {
+		self.entries.lock().iter().find_map(|(name, b)| {
+			b.iter()
+				.find(|r| r.execution_id == execution_id)
+				.map(|r| (name.clone(), r.clone()))
+		})
+	}
+
+	/// Drop all recorded executions
+	pub fn clear(&self) {
+		self.entries.lock().clear();
+	}
+}
+
+/// Redact sensitive fields and truncate long strings
+pub(super) fn redact(value: &Value) -> Value {
+	match value {
+		Value::Object(map) => Value::Object(
+			map
+				.iter()
+				.map(|(k, v)| {
+					if is_sensitive(k, &[]) {
+						(k.clone(), Value::String("[REDACTED]".to_string()))
+					} else {
+						(k.clone(), redact(v))
+					}
+				})
+				.collect(),
+		),
+		Value::Array(items) => Value::Array(items.iter().map(redact).collect()),
+		Value::String(s) if s.len() > MAX_STRING_LEN => {
+			let mut end = MAX_STRING_LEN;
+			while !s.is_char_boundary(end) {
+				end -= 1;
+			}
+			Value::String(format!("{}...[truncated]", &s[..end]))
+		},
+		other => other.clone(),
+	}
+}
+
+/// Redact sensitive fields, and fields whose key contains one of `extra_keys`, keeping other
+/// strings whole; redacted strings are appended to `secrets`, up to `MAX_REDACTED_STRINGS`
+pub(super) fn redact_fields(
+	value: &Value,
+	extra_keys: &[String],
+	secrets: &mut Vec<String>,
+) -> Value {
+	match value {
+		Value::Object(map) => Value::Object(
+			map
+				.iter()
+				.map(|(k, v)| {
+					if is_sensitive(k, extra_keys) {
+						collect_strings(v, secrets);
+						(k.clone(), Value::String("[REDACTED]".to_string()))
+					} else {
+						(k.clone(), redact_fields(v, extra_keys, secrets))
+					}
+				})
+				.collect(),
+		),
+		Value::Array(items) => Value::Array(
+			items
+				.iter()
+				.map(|v| redact_fields(v, extra_keys, secrets))
+				.collect(),
+		),
+		other => other.clone(),
+	}
+}
+
+/// Whether the value of `key` is redacted
+///
+/// Keys are split into words at `_`, `-`, `.` and camelCase humps, and a word matches a
+/// sensitive key word it ends with (`accesstoken`); the words run together are matched too
+/// (`api_key`). Extra keys match as case-insensitive substrings.
+fn is_sensitive(key: &str, extra_keys: &[String]) -> bool {
+	let words = key_words(key);
+	let joined = words.concat();
+	let last = words.last().map(String::as_str).unwrap_or_default();
+	SENSITIVE_KEYS
+		.iter()
+		.any(|s| joined.ends_with(s) || words.iter().any(|w| w.ends_with(s)))
+		|| SENSITIVE_LAST_KEYS.iter().any(|s| last.ends_with(s))
+		|| extra_keys
+			.iter()
+			.any(|s| key.to_ascii_lowercase().contains(&s.to_ascii_lowercase()))
+}
+
+/// Lowercased words of a key
+fn key_words(key: &str) -> Vec<String> {
+	let mut words = Vec::new();
+	let mut word = String::new();
+	let mut prev_lower = false;
+	for c in key.chars() {
+		if !c.is_alphanumeric() || (c.is_uppercase() && prev_lower) {
+			if !word.is_empty() {
+				words.push(std::mem::take(&mut word));
+			}
+		}
+		if c.is_alphanumeric() {
+			word.extend(c.to_lowercase());
+		}
+		prev_lower = c.is_lowercase() || c.is_ascii_digit();
+	}
+	if !word.is_empty() {
+		words.push(word);
+	}
+	words
+}
+
+fn collect_strings(value: &Value, strings: &mut Vec<String>) {
+	if strings.len() >= MAX_REDACTED_STRINGS {
+		return;
+	}
+	match value {
+		Value::String(s) if !s.is_empty() => strings.push(s.clone()),
+		Value::Object(map) => map.values().for_each(|v| collect_strings(v, strings)),
+		Value::Array(items) => items.iter().for_each(|v| collect_strings(v, strings)),
+		_ => {},
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn record(n: u64) -> ExecutionRecord {
+		ExecutionRecord::new(
+			ExecutionId::new(),
+			&serde_json::json!({ "n": n }),
+			&Ok(serde_json::json!({})),
+			Duration::from_millis(n),
+			vec![],
+		)
+	}
+
+	#[test]
+	fn test_ring_buffer_evicts_oldest() {
+		let log = ExecutionLog::new(2);
+		log.record("pipeline", record(1));
+		log.record("pipeline", record(2));
+		log.record("pipeline", record(3));
+
+		let recent = log.recent("pipeline");
+		assert_eq!(recent.len(), 2);
+		assert_eq!(recent[0].input["n"], 3);
+		assert_eq!(recent[1].input["n"], 2);
+		assert!(log.recent("other").is_empty());
+	}
+
+	#[test]
+	fn test_find_by_execution_id() {
+		let log = ExecutionLog::new(2);
+		let rec = record(1);
+		let id = rec.execution_id;
+		log.record("pipeline", rec);
+		log.record("other", record(2));
+
+		let (name, found) = log.find(id).unwrap();
+		assert_eq!(name, "pipeline");
+		assert_eq!(found.input["n"], 1);
+		assert!(log.find(ExecutionId::new()).is_none());
+	}
+
+	#[test]
+	fn test_zero_capacity_disables_recording() {
+		let log = ExecutionLog::new(0);
+		log.record("pipeline", record(1));
+		assert!(log.snapshot().is_empty());
+	}
+
+	#[test]
+	fn test_record_redacts_input_and_output() {
+		let long = "x".repeat(MAX_STRING_LEN + 10);
+		let rec = ExecutionRecord::new(
+			ExecutionId::new(),
+			&serde_json::json!({ "query": "q", "apiKey": "k", "nested": { "Authorization": "Bearer t" } }),
+			&Ok(serde_json::json!({ "body": long })),
+			Duration::from_millis(5),
+			vec![],
+		);
+
+		assert_eq!(rec.input["query"], "q");
+		assert_eq!(rec.input["apiKey"], "[REDACTED]");
+		assert_eq!(rec.input["nested"]["Authorization"], "[REDACTED]");
+		let body = rec.output.unwrap()["body"].as_str().unwrap().to_string();
+		assert!(body.ends_with("...[truncated]"));
+		assert_eq!(rec.duration_ms, 5);
+	}
+
+	#[test]
+	fn test_sensitive_keys_match_words() {
+		for key in [
+			"password",
+			"access_token",
+			"refreshToken",
+			"X-Auth-Token",
+			"api_key",
+			"apiKey",
+			"clientSecret",
+			"Authorization",
+		] {
+			assert!(is_sensitive(key, &[]), "{key}");
+		}
+		for key in [
+			"max_tokens",
+			"maxTokens",
+			"tokenizer",
+			"token_count",
+			"keyword",
+		] {
+			assert!(!is_sensitive(key, &[]), "{key}");
+		}
+		assert!(is_sensitive("ssn_last4", &["ssn".to_string()]));
+	}
+
+	#[test]
+	fn test_redacted_strings_are_capped() {
+		let input = serde_json::json!({
+			"secrets": (0..100).map(|i| format!("s{i}")).collect::<Vec<_>>()
+		});
+		let mut secrets = Vec::new();
+		let redacted = redact_fields(&input, &[], &mut secrets);
+		assert_eq!(redacted["secrets"], "[REDACTED]");
+		assert_eq!(secrets.len(), MAX_REDACTED_STRINGS);
+	}
+
+	#[test]
+	fn test_record_error() {
+		let rec = ExecutionRecord::new(
+			ExecutionId::new(),
+			&serde_json::json!({}),
+			&Err(ExecutionError::ToolNotFound("missing".to_string())),
+			Duration::from_millis(1),
+			vec![],
+		);
+		assert!(rec.output.is_none());
+		assert_eq!(rec.error.as_deref(), Some("tool not found: missing"));
+	}
+}
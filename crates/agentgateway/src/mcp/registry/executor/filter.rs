@@ -1,9 +1,8 @@
 // Filter pattern executor
 
 use serde_json::Value;
-use serde_json_path::JsonPath;
 
-use super::ExecutionError;
+use super::{ExecutionError, path_cache, value_type_name};
 use crate::mcp::registry::patterns::{FilterSpec, PredicateValue};
 
 /// Executor for filter patterns
@@ -14,11 +13,10 @@ impl FilterExecutor {
 	pub async fn execute(spec: &FilterSpec, input: Value) -> Result<Value, ExecutionError> {
 		let arr = input.as_array().ok_or_else(|| ExecutionError::TypeError {
 			expected: "array".to_string(),
-			actual: Self::value_type_name(&input),
+			actual: value_type_name(&input),
 		})?;
 
-		let jsonpath = JsonPath::parse(&spec.predicate.field)
-			.map_err(|e| ExecutionError::JsonPathError(format!("{}: {}", spec.predicate.field, e)))?;
+		let jsonpath = path_cache::parse(&spec.predicate.field).map_err(|e| e.in_field("predicate"))?;
 
 		let mut result = Vec::new();
 
@@ -34,6 +32,30 @@ impl FilterExecutor {
 		Ok(Value::Array(result))
 	}
 
+	/// Check if `op` is a supported predicate operator
+	pub(crate) fn is_supported_op(op: &str) -> bool {
+		matches!(
+			op,
+			"eq" | "ne" | "gt" | "gte" | "lt" | "lte" | "contains" | "in"
+		)
+	}
+
+	/// Evaluate a predicate for a conditional mapping, where a field of the wrong type for the
+	/// operator is a non-match rather than an error
+	pub(crate) fn evaluate_condition(
+		op: &str,
+		field_value: Option<&Value>,
+		predicate_value: &PredicateValue,
+	) -> Result<bool, ExecutionError> {
+		if !Self::is_supported_op(op) {
+			return Err(ExecutionError::PredicateError(format!(
+				"unknown operator: {}",
+				op
+			)));
+		}
+		Ok(Self::evaluate_predicate(op, field_value, predicate_value).unwrap_or(false))
+	}
+
 	/// Evaluate a predicate
 	fn evaluate_predicate(
 		op: &str,
@@ -110,18 +132,6 @@ impl FilterExecutor {
 
 		Ok(list.iter().any(|item| item == field_val))
 	}
-
-	fn value_type_name(value: &Value) -> String {
-		match value {
-			Value::Null => "null",
-			Value::Bool(_) => "boolean",
-			Value::Number(_) => "number",
-			Value::String(_) => "string",
-			Value::Array(_) => "array",
-			Value::Object(_) => "object",
-		}
-		.to_string()
-	}
 }
 
 #[cfg(test)]
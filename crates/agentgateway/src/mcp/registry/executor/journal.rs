@@ -0,0 +1,405 @@
+// Warm standby of in-flight compositions
+//
+// With an execution journal, a gateway writes each pipeline composition it runs to a directory
+// shared with a standby instance (such as a replicated volume): the caller, the input, and the
+// output of every top-level step as it completes. Finished executions are removed. When the
+// primary fails, an operator takes its executions over on the standby. A caller retrying a call
+// that was in flight, with the same arguments, then has its execution resumed after the last
+// completed step instead of started over, so the tool calls already made aren't repeated.
+//
+// Executions are matched to retries by caller: the JWT subject, or the MCP session of anonymous
+// callers, which doesn't survive a failover. Taking over while the primary still runs would let
+// both instances continue the same execution, so the primary must be fenced off first.
+
+use std::collections::HashSet;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tracing::warn;
+
+use super::execution_id::ExecutionId;
+use super::execution_log::redact;
+
+/// How often journaled executions past their retention are deleted
+const RETENTION_INTERVAL: Duration = Duration::from_secs(3600);
+
+/// Default time a journaled execution is kept for a retry to resume it
+pub const DEFAULT_JOURNAL_RETENTION: Duration = Duration::from_secs(24 * 3600);
+
+/// A pipeline execution in flight, as journaled by the instance running it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JournaledExecution {
+	pub execution_id: ExecutionId,
+	pub composition: String,
+	/// Caller the execution can only be resumed for
+	pub caller: String,
+	/// Instance running the execution, or that took it over
+	pub owner: String,
+	/// Start time (milliseconds since the Unix epoch)
+	pub started_at_ms: u64,
+	pub input: Value,
+	/// Top-level steps completed so far, in execution order
+	pub completed: Vec<JournaledStep>,
+}
+
+/// Output of one completed pipeline step
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JournaledStep {
+	pub step_id: String,
+	pub output: Value,
+}
+
+impl JournaledExecution {
+	/// This execution with its payloads redacted, for display
+	pub fn redacted(&self) -> Self {
+		Self {
+			input: redact(&self.input),
+			completed: self
+				.completed
+				.iter()
+				.map(|step| JournaledStep {
+					step_id: step.step_id.clone(),
+					output: redact(&step.output),
+				})
+				.collect(),
+			..self.clone()
+		}
+	}
+}
+
+/// Journal of in-flight pipeline executions, in a directory shared with a standby
+#[derive(Debug)]
+pub struct ExecutionJournal {
+	root: PathBuf,
+	node: String,
+	retention: Duration,
+	/// Executions running on this instance
+	running: Mutex<HashSet<ExecutionId>>,
+	/// Executions taken over, waiting for their callers to retry
+	adoptable: Mutex<Vec<JournaledExecution>>,
+}
+
+impl ExecutionJournal {
+	/// Journal to `root` as instance `node`
+	pub fn new(root: impl Into<PathBuf>, node: impl Into<String>) -> Self {
+		Self {
+			root: root.into(),
+			node: node.into(),
+			retention: DEFAULT_JOURNAL_RETENTION,
+			running: Default::default(),
+			adoptable: Default::default(),
+		}
+	}
+
+	/// Builder: keep journaled executions for `retention` after they start
+	pub fn with_retention(mut self, retention: Duration) -> Self {
+		self.retention = retention;
+		self
+	}
+
+	/// Name of this instance
+	pub fn node(&self) -> &str {
+		&self.node
+	}
+
+	/// Check that executions can be journaled, by writing and removing a probe file
+	pub async fn check_writable(&self) -> io::Result<()> {
+		tokio::fs::create_dir_all(&self.root).await?;
+		let probe = self.root.join(format!(".ready-{}", self.node));
+		tokio::fs::write(&probe, b"").await?;
+		tokio::fs::remove_file(&probe).await
+	}
+
+	/// Start journaling an execution, returning the recorder of its steps
+	pub(super) async fn begin(self: &Arc<Self>, execution: JournaledExecution) -> JournalRecorder {
+		self.running.lock().insert(execution.execution_id);
+		let recorder = JournalRecorder {
+			journal: self.clone(),
+			execution: tokio::sync::Mutex::new(execution),
+		};
+		recorder.write().await;
+		recorder
+	}
+
+	/// Stop journaling a finished execution
+	pub(super) async fn finish(&self, execution_id: ExecutionId) {
+		self.running.lock().remove(&execution_id);
+		match tokio::fs::remove_file(self.path(execution_id)).await {
+			Ok(()) => {},
+			Err(e) if e.kind() == io::ErrorKind::NotFound => {},
+			Err(e) => {
+				warn!(target: "virtual_tools", execution_id = %execution_id, error = %e, "failed to remove journaled execution");
+			},
+		}
+	}
+
+	/// Journaled executions, oldest first
+	pub async fn list(&self) -> io::Result<Vec<JournaledExecution>> {
+		let root = self.root.clone();
+		let mut executions = tokio::task::spawn_blocking(move || read_dir(&root))
+			.await
+			.map_err(io::Error::other)??;
+		executions.sort_by_key(|e| e.started_at_ms);
+		Ok(executions)
+	}
+
+	/// Take over every journaled execution this instance isn't running, so retries of their
+	/// calls resume them here; returns how many were taken over
+	pub async fn take_over(&self) -> io::Result<usize> {
+		let mut taken = Vec::new();
+		for mut execution in self.list().await? {
+			if self.running.lock().contains(&execution.execution_id) {
+				continue;
+			}
+			execution.owner = self.node.clone();
+			self.write(&execution).await?;
+			taken.push(execution);
+		}
+		let count = taken.len();
+		let mut adoptable = self.adoptable.lock();
+		adoptable.retain(|e| !taken.iter().any(|t| t.execution_id == e.execution_id));
+		adoptable.extend(taken);
+		Ok(count)
+	}
+
+	/// Claim the taken-over execution of `composition` by `caller` with `input`, if there is one
+	pub fn adopt(
+		&self,
+		composition: &str,
+		caller: &str,
+		input: &Value,
+	) -> Option<JournaledExecution> {
+		let mut adoptable = self.adoptable.lock();
+		let index = adoptable
+			.iter()
+			.position(|e| e.composition == composition && e.caller == caller && &e.input == input)?;
+		let execution = adoptable.swap_remove(index);
+		self.running.lock().insert(execution.execution_id);
+		Some(execution)
+	}
+
+	/// Delete journaled executions of this instance that started longer than the retention
+	/// ago, returning how many were deleted
+	///
+	/// Executions owned by another instance sharing the directory are left to it, unless this
+	/// instance took them over, so a standby doesn't expire its primary's long-running ones.
+	pub async fn enforce_retention(&self) -> io::Result<usize> {
+		let cutoff = now_ms().saturating_sub(self.retention.as_millis() as u64);
+		let adoptable: HashSet<ExecutionId> = self
+			.adoptable
+			.lock()
+			.iter()
+			.map(|e| e.execution_id)
+			.collect();
+		let mut expired = 0;
+		for execution in self.list().await? {
+			let owned = execution.owner == self.node || adoptable.contains(&execution.execution_id);
+			if !owned
+				|| execution.started_at_ms >= cutoff
+				|| self.running.lock().contains(&execution.execution_id)
+			{
+				continue;
+			}
+			self.finish(execution.execution_id).await;
+			expired += 1;
+		}
+		self.adoptable.lock().retain(|e| e.started_at_ms >= cutoff);
+		Ok(expired)
+	}
+
+	/// Enforce the retention periodically, until the journal is dropped elsewhere
+	pub fn spawn_retention(self: &Arc<Self>) {
+		let journal = Arc::downgrade(self);
+		tokio::spawn(async move {
+			let mut interval = tokio::time::interval(RETENTION_INTERVAL);
+			loop {
+				interval.tick().await;
+				let Some(journal) = journal.upgrade() else {
+					return;
+				};
+				if let Err(e) = journal.enforce_retention().await {
+					warn!(target: "virtual_tools", error = %e, "failed to expire journaled executions");
+				}
+			}
+		});
+	}
+
+	fn path(&self, execution_id: ExecutionId) -> PathBuf {
+		self.root.join(format!("{execution_id}.json"))
+	}
+
+	/// Replace the journaled state of an execution, atomically for readers of the directory
+	async fn write(&self, execution: &JournaledExecution) -> io::Result<()> {
+		let body = serde_json::to_vec(execution).map_err(io::Error::other)?;
+		let path = self.path(execution.execution_id);
+		let staged = path.with_extension("json.tmp");
+		tokio::fs::create_dir_all(&self.root).await?;
+		tokio::fs::write(&staged, body).await?;
+		tokio::fs::rename(&staged, &path).await
+	}
+}
+
+/// Records the steps of one journaled execution as they complete
+#[derive(Debug)]
+pub struct JournalRecorder {
+	journal: Arc<ExecutionJournal>,
+	execution: tokio::sync::Mutex<JournaledExecution>,
+}
+
+impl JournalRecorder {
+	/// Journal the output of a completed top-level step
+	pub async fn step_completed(&self, step_id: &str, output: &Value) {
+		let mut execution = self.execution.lock().await;
+		execution.completed.push(JournaledStep {
+			step_id: step_id.to_string(),
+			output: output.clone(),
+		});
+		if let Err(e) = self.journal.write(&execution).await {
+			warn!(target: "virtual_tools", execution_id = %execution.execution_id, error = %e, "failed to journal composition step");
+		}
+	}
+
+	async fn write(&self) {
+		let execution = self.execution.lock().await;
+		if let Err(e) = self.journal.write(&execution).await {
+			warn!(target: "virtual_tools", execution_id = %execution.execution_id, error = %e, "failed to journal composition execution");
+		}
+	}
+}
+
+fn read_dir(root: &Path) -> io::Result<Vec<JournaledExecution>> {
+	let entries = match std::fs::read_dir(root) {
+		Ok(entries) => entries,
+		Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+		Err(e) => return Err(e),
+	};
+	let mut executions = Vec::new();
+	for entry in entries {
+		let path = entry?.path();
+		if path.extension().is_none_or(|ext| ext != "json") {
+			continue;
+		}
+		// An execution finishing concurrently removes its file
+		let body = match std::fs::read(&path) {
+			Ok(body) => body,
+			Err(e) if e.kind() == io::ErrorKind::NotFound => continue,
+			Err(e) => return Err(e),
+		};
+		match serde_json::from_slice(&body) {
+			Ok(execution) => executions.push(execution),
+			Err(e) => {
+				warn!(target: "virtual_tools", path = %path.display(), error = %e, "skipping unreadable journaled execution");
+			},
+		}
+	}
+	Ok(executions)
+}
+
+pub(super) fn now_ms() -> u64 {
+	SystemTime::now()
+		.duration_since(UNIX_EPOCH)
+		.map(|d| d.as_millis() as u64)
+		.unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+	use serde_json::json;
+
+	use super::*;
+	use crate::mcp::registry::executor::testing::research_pipeline;
+	use crate::mcp::registry::executor::{CompositionExecutor, MockToolInvoker};
+
+	#[tokio::test]
+	async fn test_standby_resumes_after_last_completed_step() {
+		let dir = tempfile::tempdir().unwrap();
+		let primary = Arc::new(ExecutionJournal::new(dir.path(), "primary"));
+		let standby = Arc::new(ExecutionJournal::new(dir.path(), "standby"));
+		let input = json!({"q": "mcp"});
+
+		// The primary fails while summarize is in flight
+		let invoker = Arc::new(
+			MockToolInvoker::new()
+				.with_response("search", json!({"hits": 3}))
+				.with_latency("summarize", Duration::from_secs(3600)),
+		);
+		let execution_id = ExecutionId::new();
+		let executor = CompositionExecutor::new(research_pipeline(), invoker.clone())
+			.with_execution_id(execution_id)
+			.with_journal(primary.clone(), "sub:alice");
+		let run = executor.execute("research", input.clone());
+		assert!(
+			tokio::time::timeout(Duration::from_millis(100), run)
+				.await
+				.is_err()
+		);
+		let journaled = standby.list().await.unwrap();
+		assert_eq!(journaled.len(), 1);
+		assert_eq!(journaled[0].owner, "primary");
+		assert_eq!(journaled[0].completed[0].step_id, "search");
+
+		// Nothing resumes before the takeover, nor for other callers or arguments
+		assert!(standby.adopt("research", "sub:alice", &input).is_none());
+		assert_eq!(standby.take_over().await.unwrap(), 1);
+		assert!(standby.adopt("research", "sub:bob", &input).is_none());
+		assert!(
+			standby
+				.adopt("research", "sub:alice", &json!({"q": "rust"}))
+				.is_none()
+		);
+		let adopted = standby.adopt("research", "sub:alice", &input).unwrap();
+		assert_eq!(adopted.execution_id, execution_id);
+		assert_eq!(adopted.owner, "standby");
+
+		let invoker = Arc::new(
+			MockToolInvoker::new()
+				.with_response("search", json!({"hits": 0}))
+				.with_response("summarize", json!({"summary": "short"}))
+				.with_response("publish", json!({"url": "https://example.com"})),
+		);
+		let executor = CompositionExecutor::new(research_pipeline(), invoker.clone())
+			.with_execution_id(adopted.execution_id)
+			.with_journal(standby.clone(), "sub:alice");
+		let (result, _) = executor.resume_journaled(adopted).await;
+		assert_eq!(result.unwrap(), json!({"url": "https://example.com"}));
+		invoker.assert_not_called("search");
+		assert_eq!(invoker.call_args("summarize"), [json!({"hits": 3})]);
+		// Finished executions leave the journal
+		assert!(standby.list().await.unwrap().is_empty());
+	}
+
+	#[tokio::test]
+	async fn test_retention() {
+		let dir = tempfile::tempdir().unwrap();
+		let primary =
+			Arc::new(ExecutionJournal::new(dir.path(), "primary").with_retention(Duration::ZERO));
+		let standby =
+			Arc::new(ExecutionJournal::new(dir.path(), "standby").with_retention(Duration::ZERO));
+		let execution = JournaledExecution {
+			execution_id: ExecutionId::new(),
+			composition: "research".to_string(),
+			caller: "sub:alice".to_string(),
+			owner: "primary".to_string(),
+			started_at_ms: now_ms() - 1,
+			input: json!({"api_key": "k"}),
+			completed: vec![],
+		};
+		primary.write(&execution).await.unwrap();
+		assert_ne!(
+			primary.list().await.unwrap()[0].redacted().input["api_key"],
+			"k"
+		);
+		// The standby leaves the primary's executions to it
+		assert_eq!(standby.enforce_retention().await.unwrap(), 0);
+		assert_eq!(standby.list().await.unwrap().len(), 1);
+		assert_eq!(primary.enforce_retention().await.unwrap(), 1);
+		assert!(primary.list().await.unwrap().is_empty());
+	}
+}
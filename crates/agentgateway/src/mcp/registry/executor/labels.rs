@@ -0,0 +1,148 @@
+// Execution labels for cost attribution
+//
+// Callers tag a tool call with opaque labels (project, ticket id, ...) under the
+// `agentgateway.dev/labels` key of its `_meta`. The labels follow the execution
+// through every step, so metrics, logs and archives can be broken down per
+// initiative for chargeback.
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use super::ExecutionError;
+
+/// `_meta` key holding a call's labels
+pub const LABELS_META_KEY: &str = "agentgateway.dev/labels";
+
+/// Most labels one call may carry, bounding metric cardinality
+pub const MAX_LABELS: usize = 8;
+
+/// Longest label name or value, in bytes
+pub const MAX_LABEL_LEN: usize = 64;
+
+/// Caller-supplied labels attached to a composition execution
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct ExecutionLabels(BTreeMap<String, String>);
+
+impl ExecutionLabels {
+	/// Read the labels from a request's `_meta`, if it has any
+	///
+	/// Labels must be an object of string values with at most [`MAX_LABELS`]
+	/// entries. Names may only contain ASCII letters, digits, `_`, `-` and `.`.
+	pub fn from_meta(meta: &Map<String, Value>) -> Result<Self, ExecutionError> {
+		let Some(labels) = meta.get(LABELS_META_KEY) else {
+			return Ok(Self::default());
+		};
+		let invalid = |msg: String| ExecutionError::InvalidInput(format!("{LABELS_META_KEY}: {msg}"));
+		let Value::Object(labels) = labels else {
+			return Err(invalid("expected an object".to_string()));
+		};
+		if labels.len() > MAX_LABELS {
+			return Err(invalid(format!("at most {MAX_LABELS} labels are allowed")));
+		}
+		let mut out = BTreeMap::new();
+		for (name, value) in labels {
+			let valid_name = !name.is_empty()
+				&& name.len() <= MAX_LABEL_LEN
+				&& name
+					.bytes()
+					.all(|b| b.is_ascii_alphanumeric() || matches!(b, b'_' | b'-' | b'.'));
+			if !valid_name {
+				return Err(invalid(format!("invalid label name '{name}'")));
+			}
+			let Value::String(value) = value else {
+				return Err(invalid(format!("label '{name}' must be a string")));
+			};
+			if value.len() > MAX_LABEL_LEN {
+				return Err(invalid(format!(
+					"label '{name}' is longer than {MAX_LABEL_LEN} bytes"
+				)));
+			}
+			out.insert(name.clone(), value.clone());
+		}
+		Ok(Self(out))
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.0.is_empty()
+	}
+
+	pub fn get(&self, name: &str) -> Option<&str> {
+		self.0.get(name).map(String::as_str)
+	}
+
+	/// Labels in name order
+	pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+		self.0.iter().map(|(k, v)| (k.as_str(), v.as_str()))
+	}
+}
+
+impl std::fmt::Display for ExecutionLabels {
+	/// `name=value` pairs separated by commas, e.g. `project=search,ticket=OPS-12`
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		for (i, (name, value)) in self.iter().enumerate() {
+			if i > 0 {
+				f.write_str(",")?;
+			}
+			write!(f, "{name}={value}")?;
+		}
+		Ok(())
+	}
+}
+
+impl<K: Into<String>, V: Into<String>> FromIterator<(K, V)> for ExecutionLabels {
+	fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+		Self(
+			iter
+				.into_iter()
+				.map(|(k, v)| (k.into(), v.into()))
+				.collect(),
+		)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use serde_json::json;
+
+	use super::*;
+
+	fn meta(labels: Value) -> Map<String, Value> {
+		let Value::Object(meta) = json!({ LABELS_META_KEY: labels }) else {
+			unreachable!()
+		};
+		meta
+	}
+
+	#[test]
+	fn test_from_meta() {
+		let labels =
+			ExecutionLabels::from_meta(&meta(json!({"project": "search", "ticket": "OPS-12"}))).unwrap();
+		assert_eq!(labels.get("project"), Some("search"));
+		assert_eq!(labels.to_string(), "project=search,ticket=OPS-12");
+
+		assert!(ExecutionLabels::from_meta(&Map::new()).unwrap().is_empty());
+	}
+
+	#[test]
+	fn test_from_meta_rejects_invalid_labels() {
+		for labels in [
+			json!("project=search"),
+			json!({"project": 7}),
+			json!({"bad name": "x"}),
+			json!({"project": "x".repeat(MAX_LABEL_LEN + 1)}),
+			Value::Object(
+				(0..=MAX_LABELS)
+					.map(|i| (format!("l{i}"), json!("v")))
+					.collect(),
+			),
+		] {
+			assert!(matches!(
+				ExecutionLabels::from_meta(&meta(labels)),
+				Err(ExecutionError::InvalidInput(_))
+			));
+		}
+	}
+}
@@ -1,10 +1,11 @@
 // Map Each pattern executor
 
-use serde_json::Value;
+use futures::{Stream, StreamExt};
+use serde_json::{Value, json};
 
 use super::context::ExecutionContext;
-use super::{CompositionExecutor, ExecutionError};
-use crate::mcp::registry::patterns::{MapEachInner, MapEachSpec};
+use super::{CompositionExecutor, ExecutionError, value_type_name};
+use crate::mcp::registry::patterns::{MapEachInner, MapEachSpec, MapEachStream};
 
 /// Executor for map-each patterns
 pub struct MapEachExecutor;
@@ -19,8 +20,23 @@ impl MapEachExecutor {
 	) -> Result<Value, ExecutionError> {
 		let arr = input.as_array().ok_or_else(|| ExecutionError::TypeError {
 			expected: "array".to_string(),
-			actual: Self::value_type_name(&input),
+			actual: value_type_name(&input),
 		})?;
+		if let Some(max_items) = spec.max_items
+			&& arr.len() > max_items as usize
+		{
+			return Err(ExecutionError::InvalidInput(format!(
+				"mapEach input has {} items, more than maxItems ({})",
+				arr.len(),
+				max_items
+			)));
+		}
+
+		if let Some(stream) = &spec.stream
+			&& ctx.tool_invoker.can_emit_partial()
+		{
+			return Self::execute_streaming(&spec.inner, stream, arr, ctx, executor).await;
+		}
 
 		let mut results = Vec::with_capacity(arr.len());
 
@@ -32,6 +48,48 @@ impl MapEachExecutor {
 		Ok(Value::Array(results))
 	}
 
+	/// Run up to `stream.concurrency()` elements at once, emitting each result to the client
+	/// as `{"index", "item"}` instead of collecting them; returns `{"streamed": count}`
+	///
+	/// Elements are only started as earlier results are emitted, so a client that reads
+	/// slowly holds back the backend calls rather than the gateway buffering results.
+	async fn execute_streaming(
+		inner: &MapEachInner,
+		stream: &MapEachStream,
+		arr: &[Value],
+		ctx: &ExecutionContext,
+		executor: &CompositionExecutor,
+	) -> Result<Value, ExecutionError> {
+		let results =
+			futures::stream::iter(arr.iter().cloned().enumerate()).map(|(index, item)| async move {
+				Self::execute_inner(inner, item, ctx, executor)
+					.await
+					.map(|result| (index, result))
+			});
+		if stream.unordered {
+			Self::emit_all(results.buffer_unordered(stream.concurrency()), ctx).await
+		} else {
+			Self::emit_all(results.buffered(stream.concurrency()), ctx).await
+		}
+	}
+
+	async fn emit_all(
+		results: impl Stream<Item = Result<(usize, Value), ExecutionError>>,
+		ctx: &ExecutionContext,
+	) -> Result<Value, ExecutionError> {
+		let mut results = std::pin::pin!(results);
+		let mut streamed = 0;
+		while let Some(result) = results.next().await {
+			let (index, item) = result?;
+			ctx
+				.tool_invoker
+				.emit_partial(json!({"index": index, "item": item}))
+				.await?;
+			streamed += 1;
+		}
+		Ok(json!({ "streamed": streamed }))
+	}
+
 	/// Execute the inner operation for one element
 	async fn execute_inner(
 		inner: &MapEachInner,
@@ -43,22 +101,12 @@ impl MapEachExecutor {
 			MapEachInner::Tool(name) => executor.execute_tool(name, item, ctx).await,
 			MapEachInner::Pattern(pattern) => {
 				let child_ctx = ctx.child(item.clone());
-				executor.execute_pattern(pattern, item, &child_ctx).await
+				let result = executor.execute_pattern(pattern, item, &child_ctx).await?;
+				ctx.charge(&result)?;
+				Ok(result)
 			},
 		}
 	}
-
-	fn value_type_name(value: &Value) -> String {
-		match value {
-			Value::Null => "null",
-			Value::Bool(_) => "boolean",
-			Value::Number(_) => "number",
-			Value::String(_) => "string",
-			Value::Array(_) => "array",
-			Value::Object(_) => "object",
-		}
-		.to_string()
-	}
 }
 
 #[cfg(test)]
@@ -94,6 +142,8 @@ mod tests {
 
 		let spec = MapEachSpec {
 			inner: MapEachInner::Tool("process".to_string()),
+			max_items: None,
+			stream: None,
 		};
 
 		let input = json!([{"id": 1}, {"id": 2}, {"id": 3}]);
@@ -123,6 +173,8 @@ mod tests {
 
 		let spec = MapEachSpec {
 			inner: MapEachInner::Pattern(Box::new(inner_pattern)),
+			max_items: None,
+			stream: None,
 		};
 
 		let input = json!([
@@ -149,6 +201,8 @@ mod tests {
 
 		let spec = MapEachSpec {
 			inner: MapEachInner::Tool("tool".to_string()),
+			max_items: None,
+			stream: None,
 		};
 
 		let input = json!({"not": "an array"});
@@ -168,6 +222,8 @@ mod tests {
 
 		let spec = MapEachSpec {
 			inner: MapEachInner::Tool("tool".to_string()),
+			max_items: None,
+			stream: None,
 		};
 
 		let input = json!([]);
@@ -177,4 +233,51 @@ mod tests {
 		let arr = result.unwrap();
 		assert!(arr.as_array().unwrap().is_empty());
 	}
+
+	#[tokio::test]
+	async fn test_map_each_stream() {
+		let compiled = Arc::new(CompiledRegistry::compile(Registry::new()).unwrap());
+		let invoker = Arc::new(
+			MockToolInvoker::new()
+				.with_script("process", [Ok(json!("a")), Ok(json!("b")), Ok(json!("c"))])
+				.with_partials(),
+		);
+		let ctx = ExecutionContext::new(json!({}), compiled.clone(), invoker.clone());
+		let executor = CompositionExecutor::new(compiled, invoker.clone());
+		let spec = MapEachSpec::tool("process").with_stream(MapEachStream::default());
+
+		let result = MapEachExecutor::execute(&spec, json!([1, 2, 3]), &ctx, &executor).await;
+		assert_eq!(result.unwrap(), json!({"streamed": 3}));
+		assert_eq!(
+			invoker.partials(),
+			vec![
+				json!({"index": 0, "item": "a"}),
+				json!({"index": 1, "item": "b"}),
+				json!({"index": 2, "item": "c"}),
+			]
+		);
+
+		// Without a client stream the array is returned as usual
+		let (ctx, executor) = setup_context_and_executor(
+			MockToolInvoker::new().with_response("process", json!({"processed": true})),
+		);
+		let result = MapEachExecutor::execute(&spec, json!([1, 2]), &ctx, &executor).await;
+		assert_eq!(result.unwrap().as_array().unwrap().len(), 2);
+	}
+
+	#[tokio::test]
+	async fn test_map_each_max_items() {
+		let invoker = MockToolInvoker::new().with_response("process", json!({"processed": true}));
+		let (ctx, executor) = setup_context_and_executor(invoker);
+		let spec = MapEachSpec::tool("process").with_max_items(2);
+
+		let result = MapEachExecutor::execute(&spec, json!([1, 2]), &ctx, &executor).await;
+		assert_eq!(result.unwrap().as_array().unwrap().len(), 2);
+
+		let result = MapEachExecutor::execute(&spec, json!([1, 2, 3]), &ctx, &executor).await;
+		assert!(matches!(
+			result.unwrap_err(),
+			ExecutionError::InvalidInput(msg) if msg.contains("more than maxItems (2)")
+		));
+	}
 }
@@ -0,0 +1,141 @@
+// Memory accounting for in-flight composition payloads
+
+use std::mem::size_of;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use serde_json::Value;
+
+use super::ExecutionError;
+
+/// Global cap on the payload bytes held by in-flight composition executions
+#[derive(Debug, Default)]
+pub struct MemoryBudget {
+	/// Maximum bytes across all executions (None tracks usage without a limit)
+	limit: Option<usize>,
+	used: AtomicUsize,
+}
+
+impl MemoryBudget {
+	/// Create a budget capped at `limit` bytes
+	pub fn new(limit: Option<usize>) -> Self {
+		Self {
+			limit,
+			used: AtomicUsize::new(0),
+		}
+	}
+
+	/// Configured limit, if any
+	pub fn limit(&self) -> Option<usize> {
+		self.limit
+	}
+
+	/// Bytes currently held by in-flight executions
+	pub fn used(&self) -> usize {
+		self.used.load(Ordering::Relaxed)
+	}
+
+	/// Reserve bytes, failing if the reservation would exceed the limit
+	fn reserve(&self, bytes: usize) -> Result<(), ExecutionError> {
+		let Some(limit) = self.limit else {
+			self.used.fetch_add(bytes, Ordering::Relaxed);
+			return Ok(());
+		};
+		self
+			.used
+			.fetch_update(Ordering::AcqRel, Ordering::Acquire, |used| {
+				used.checked_add(bytes).filter(|total| *total <= limit)
+			})
+			.map(|_| ())
+			.map_err(|_| ExecutionError::MemoryLimitExceeded { limit })
+	}
+
+	fn release(&self, bytes: usize) {
+		self.used.fetch_sub(bytes, Ordering::AcqRel);
+	}
+}
+
+/// Bytes charged by a single composition execution, returned to the budget on drop
+#[derive(Debug)]
+pub struct MemoryLedger {
+	budget: Arc<MemoryBudget>,
+	held: AtomicUsize,
+}
+
+impl MemoryLedger {
+	/// Create an empty ledger drawing from `budget`
+	pub fn new(budget: Arc<MemoryBudget>) -> Self {
+		Self {
+			budget,
+			held: AtomicUsize::new(0),
+		}
+	}
+
+	/// Charge the approximate size of a value held by the execution
+	pub fn charge(&self, value: &Value) -> Result<(), ExecutionError> {
+		let bytes = approx_size(value);
+		self.budget.reserve(bytes)?;
+		self.held.fetch_add(bytes, Ordering::Relaxed);
+		Ok(())
+	}
+
+	/// Bytes charged so far
+	pub fn held(&self) -> usize {
+		self.held.load(Ordering::Relaxed)
+	}
+}
+
+impl Drop for MemoryLedger {
+	fn drop(&mut self) {
+		self.budget.release(*self.held.get_mut());
+	}
+}
+
+/// Approximate heap footprint of a JSON value
+pub fn approx_size(value: &Value) -> usize {
+	size_of::<Value>()
+		+ match value {
+			Value::String(s) => s.len(),
+			Value::Array(items) => items.iter().map(approx_size).sum(),
+			Value::Object(map) => map.iter().map(|(k, v)| k.len() + approx_size(v)).sum(),
+			Value::Null | Value::Bool(_) | Value::Number(_) => 0,
+		}
+}
+
+#[cfg(test)]
+mod tests {
+	use serde_json::json;
+
+	use super::*;
+
+	#[test]
+	fn test_ledger_releases_on_drop() {
+		let budget = Arc::new(MemoryBudget::new(None));
+		let doc = json!({"body": "x".repeat(1000)});
+		{
+			let ledger = MemoryLedger::new(budget.clone());
+			ledger.charge(&doc).unwrap();
+			assert_eq!(ledger.held(), approx_size(&doc));
+			assert_eq!(budget.used(), approx_size(&doc));
+		}
+		assert_eq!(budget.used(), 0);
+	}
+
+	#[test]
+	fn test_limit_rejects_charge() {
+		let doc = json!({"body": "x".repeat(1000)});
+		let budget = Arc::new(MemoryBudget::new(Some(approx_size(&doc) + 10)));
+		let first = MemoryLedger::new(budget.clone());
+		first.charge(&doc).unwrap();
+
+		let second = MemoryLedger::new(budget.clone());
+		assert!(matches!(
+			second.charge(&doc),
+			Err(ExecutionError::MemoryLimitExceeded { .. })
+		));
+		assert_eq!(second.held(), 0);
+
+		drop(first);
+		second.charge(&doc).unwrap();
+	}
+}
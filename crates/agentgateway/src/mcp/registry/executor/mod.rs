@@ -1,36 +1,104 @@
 // Composition Executor Module
 //
 // Executes tool compositions at runtime, handling:
-// - Pattern execution (pipeline, scatter-gather, filter, schema-map, map-each)
+// - Pattern execution (pipeline, scatter-gather, filter, schema-map, map-each, rank, cache)
 // - Tool invocation via backend pool
 // - Result aggregation and transformation
 // - Tracing and observability
 
-use tracing::debug;
+use tracing::{Instrument, Span, debug, info, info_span, warn};
 
+mod builtins;
+mod cache;
 mod context;
+mod cost;
+mod dead_letter;
+mod envelope;
+mod execution_id;
+mod execution_log;
 mod filter;
+mod journal;
+mod labels;
 mod map_each;
+mod memory;
+mod patch;
+mod path_cache;
 mod pipeline;
+mod rank;
+mod retry;
+mod sampling;
 mod scatter_gather;
 mod schema_map;
+mod script;
+mod session;
+#[cfg(any(test, feature = "testing"))]
+mod testing;
 mod throttle;
+mod time_travel;
+mod timeout;
+mod token_budget;
+mod verbosity;
+mod worker_pool;
 
+pub use builtins::{BUILTIN_TARGET, BUILTIN_TOOLS, builtin_tool, invoke_builtin};
+pub use cache::{CacheExecutor, CompositionCacheStats, DEFAULT_MAX_CACHE_ENTRIES, ResultCache};
 pub use context::ExecutionContext;
+pub use cost::{CostLedger, CostSummary, ToolCost};
+pub use dead_letter::{
+	DEFAULT_DEAD_LETTER_CAPACITY, DeadLetterEntry, DeadLetterExecutor, DeadLetterPage,
+	DeadLetterQueue, ReplayOutcome, ReplayStatus,
+};
+pub use envelope::{EnvelopeError, EnvelopeMeta, ResultEnvelope, envelope_output_schema};
+pub use execution_id::{EXECUTION_ID_META_KEY, ExecutionId};
+pub use execution_log::{
+	DEFAULT_EXECUTION_LOG_CAPACITY, ExecutionLog, ExecutionRecord, StepTiming,
+};
 pub use filter::FilterExecutor;
+pub use journal::{
+	DEFAULT_JOURNAL_RETENTION, ExecutionJournal, JournalRecorder, JournaledExecution, JournaledStep,
+};
+pub use labels::{ExecutionLabels, LABELS_META_KEY, MAX_LABEL_LEN, MAX_LABELS};
 pub use map_each::MapEachExecutor;
+pub use memory::{MemoryBudget, MemoryLedger, approx_size};
+pub use patch::PatchExecutor;
 pub use pipeline::PipelineExecutor;
+pub use rank::RankExecutor;
+pub use sampling::SampleExecutor;
 pub use scatter_gather::ScatterGatherExecutor;
 pub use schema_map::SchemaMapExecutor;
-pub use throttle::{RateLimiterRegistry, SharedRateLimiterRegistry, ThrottleExecutor};
+pub use script::ScriptExecutor;
+pub use session::SessionStore;
+#[cfg(any(test, feature = "testing"))]
+pub use testing::{MockCall, MockToolInvoker, RegistryAwareInvoker};
+pub use throttle::{
+	DEFAULT_MAX_RATE_LIMITERS, RateLimiterRegistry, SharedRateLimiterRegistry, ThrottleExecutor,
+};
+pub use time_travel::{
+	CapturedStep, DEFAULT_CAPTURE_CAPACITY, ExecutionCapture, ExecutionCaptures, RerunError,
+	ResumePoint,
+};
+pub use timeout::TimeoutExecutor;
+pub use token_budget::{CharTokenizer, Tokenizer, WordTokenizer, apply_token_budget};
+pub use verbosity::{
+	CompositionVerbosity, VERBOSITY_HEADER, VerbosityOverride, VerbosityOverrides,
+};
+pub use worker_pool::{
+	DEFAULT_MAX_BATCH_WORKERS, DEFAULT_MAX_WORKERS, ExecutionPriority, WorkerPermit, WorkerPool,
+};
 
 use std::sync::Arc;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
 
+use rmcp::model::{CreateMessageRequestParam, CreateMessageResult};
 use serde_json::Value;
 use thiserror::Error;
 
+use super::archive::{ArchiveRecord, PayloadArchiver};
+use super::arguments::{ArgumentCheckPolicy, check_arguments};
 use super::compiled::{CompiledComposition, CompiledRegistry, CompiledTool};
 use super::patterns::PatternSpec;
+use super::types::CostModel;
+use crate::events::{EventBus, EventKind};
 
 /// Errors that can occur during composition execution
 #[derive(Error, Debug)]
@@ -53,8 +121,17 @@ pub enum ExecutionError {
 	#[error("all scatter-gather targets failed")]
 	AllTargetsFailed,
 
-	#[error("JSONPath evaluation failed: {0}")]
-	JsonPathError(String),
+	/// A JSONPath failed to parse or evaluate; `field` and `location` say where it is used
+	#[error(
+		"JSONPath evaluation failed for '{path}'{}: {message}",
+		jsonpath_context(.field.as_deref(), .location.as_deref())
+	)]
+	JsonPathError {
+		path: String,
+		message: String,
+		field: Option<String>,
+		location: Option<String>,
+	},
 
 	#[error("predicate evaluation failed: {0}")]
 	PredicateError(String),
@@ -67,14 +144,229 @@ pub enum ExecutionError {
 
 	#[error("stateful pattern not implemented: {pattern}. {details}")]
 	StatefulPatternNotImplemented { pattern: String, details: String },
+
+	#[error("memory limit exceeded: in-flight compositions would hold more than {limit} bytes")]
+	MemoryLimitExceeded { limit: usize },
+
+	#[error("session state is full: {0}")]
+	SessionStateFull(String),
+
+	#[error("assertion failed in step {step}: {message}")]
+	AssertionFailed { step: String, message: String },
+
+	#[error("script failed in step {step}: {message}")]
+	ScriptFailed { step: String, message: String },
+
+	#[error("patch failed in step {step}: {message}")]
+	PatchFailed { step: String, message: String },
+
+	#[error("arguments for tool {tool} in step {step} don't match its input schema: {message}")]
+	InvalidArguments {
+		step: String,
+		tool: String,
+		message: String,
+	},
+
+	#[error("sampling unavailable: {0}")]
+	SamplingUnavailable(String),
+
+	#[error("upstream for tool {0} is unavailable: its circuit breaker is open")]
+	UpstreamUnavailable(String),
+
+	/// The call was lost to a transient upstream failure, such as a backend process that
+	/// exited and is being restarted, and is safe to retry
+	#[error("transient upstream failure: {0}")]
+	Transient(String),
+
+	/// A failure replayed from a cache pattern's negative cache, keeping the original error's
+	/// message, kind and retryability
+	#[error("{message}")]
+	CachedFailure {
+		kind: String,
+		message: String,
+		retryable: bool,
+	},
+}
+
+impl ExecutionError {
+	/// Whether the failure may go away on another attempt, rather than being caused by the
+	/// input or the composition itself
+	pub fn is_retryable(&self) -> bool {
+		matches!(
+			self,
+			ExecutionError::ToolExecutionFailed(_)
+				| ExecutionError::Transient(_)
+				| ExecutionError::PatternExecutionFailed(_)
+				| ExecutionError::Timeout(_)
+				| ExecutionError::AllTargetsFailed
+				| ExecutionError::Internal(_)
+				| ExecutionError::CachedFailure {
+					retryable: true,
+					..
+				}
+		)
+	}
+
+	/// Every kind [`ExecutionError::kind`] returns
+	pub const KINDS: &[&str] = &[
+		"tool_not_found",
+		"tool_execution_failed",
+		"pattern_execution_failed",
+		"invalid_input",
+		"timeout",
+		"all_targets_failed",
+		"jsonpath",
+		"predicate",
+		"type",
+		"internal",
+		"stateful_pattern_not_implemented",
+		"memory_limit_exceeded",
+		"session_state_full",
+		"assertion_failed",
+		"script_failed",
+		"patch_failed",
+		"invalid_arguments",
+		"sampling_unavailable",
+		"upstream_unavailable",
+		"transient",
+	];
+
+	/// Stable name of the kind of failure, e.g. to choose which failures to cache
+	pub fn kind(&self) -> &str {
+		match self {
+			ExecutionError::ToolNotFound(_) => "tool_not_found",
+			ExecutionError::ToolExecutionFailed(_) => "tool_execution_failed",
+			ExecutionError::PatternExecutionFailed(_) => "pattern_execution_failed",
+			ExecutionError::InvalidInput(_) => "invalid_input",
+			ExecutionError::Timeout(_) => "timeout",
+			ExecutionError::AllTargetsFailed => "all_targets_failed",
+			ExecutionError::JsonPathError { .. } => "jsonpath",
+			ExecutionError::PredicateError(_) => "predicate",
+			ExecutionError::TypeError { .. } => "type",
+			ExecutionError::Internal(_) => "internal",
+			ExecutionError::StatefulPatternNotImplemented { .. } => "stateful_pattern_not_implemented",
+			ExecutionError::MemoryLimitExceeded { .. } => "memory_limit_exceeded",
+			ExecutionError::SessionStateFull(_) => "session_state_full",
+			ExecutionError::AssertionFailed { .. } => "assertion_failed",
+			ExecutionError::ScriptFailed { .. } => "script_failed",
+			ExecutionError::PatchFailed { .. } => "patch_failed",
+			ExecutionError::InvalidArguments { .. } => "invalid_arguments",
+			ExecutionError::SamplingUnavailable(_) => "sampling_unavailable",
+			ExecutionError::UpstreamUnavailable(_) => "upstream_unavailable",
+			ExecutionError::Transient(_) => "transient",
+			ExecutionError::CachedFailure { kind, .. } => kind,
+		}
+	}
+
+	/// A JSONPath error for `path`, without context yet
+	pub fn jsonpath(path: impl Into<String>, message: impl Into<String>) -> Self {
+		ExecutionError::JsonPathError {
+			path: path.into(),
+			message: message.into(),
+			field: None,
+			location: None,
+		}
+	}
+
+	/// Name the field a JSONPath error's path is used for, or the field enclosing the one
+	/// already named (`name.inner`)
+	pub fn in_field(mut self, name: &str) -> Self {
+		if let ExecutionError::JsonPathError { field, .. } = &mut self {
+			*field = Some(match field.take() {
+				Some(inner) => format!("{name}.{inner}"),
+				None => name.to_string(),
+			});
+		}
+		self
+	}
+
+	/// Say where in a composition a JSONPath error happened, unless already said
+	pub fn at(mut self, describe: impl FnOnce() -> String) -> Self {
+		if let ExecutionError::JsonPathError { location, .. } = &mut self
+			&& location.is_none()
+		{
+			*location = Some(describe());
+		}
+		self
+	}
+}
+
+/// ` in <location>, field <field>`, for the parts known
+fn jsonpath_context(field: Option<&str>, location: Option<&str>) -> String {
+	match (location, field) {
+		(Some(location), Some(field)) => format!(" in {location}, field {field}"),
+		(Some(location), None) => format!(" in {location}"),
+		(None, Some(field)) => format!(" in field {field}"),
+		(None, None) => String::new(),
+	}
+}
+
+/// JSON type name of `value`, for type errors
+pub(crate) fn value_type_name(value: &Value) -> String {
+	match value {
+		Value::Null => "null",
+		Value::Bool(_) => "boolean",
+		Value::Number(_) => "number",
+		Value::String(_) => "string",
+		Value::Array(_) => "array",
+		Value::Object(_) => "object",
+	}
+	.to_string()
 }
 
 /// Composition executor - executes tool compositions
+#[derive(Clone)]
 pub struct CompositionExecutor {
 	/// Compiled registry for tool lookups
 	registry: Arc<CompiledRegistry>,
 	/// Tool invocation callback
 	tool_invoker: Arc<dyn ToolInvoker>,
+	/// Session-scoped state shared with every composition this executor runs
+	session: Arc<SessionStore>,
+	/// Recent-execution log (optional)
+	execution_log: Option<Arc<ExecutionLog>>,
+	/// Runtime per-tool debug verbosity (optional)
+	verbosity_overrides: Option<Arc<VerbosityOverrides>>,
+	/// Verbosity a trusted caller asked for, ahead of the per-tool overrides (optional)
+	verbosity: Option<CompositionVerbosity>,
+	/// Global budget for in-flight payload bytes (optional)
+	memory_budget: Option<Arc<MemoryBudget>>,
+	/// Bus that execution lifecycle events are published to (optional)
+	events: Option<EventBus>,
+	/// Store behind cache patterns (optional; without it cache patterns don't cache)
+	result_cache: Option<Arc<ResultCache>>,
+	/// Queue dead letter patterns retain failures in (optional)
+	dead_letters: Option<Arc<DeadLetterQueue>>,
+	/// Whether this executor replays a dead-lettered input
+	dead_letter_replay: bool,
+	/// Store that executions at capture verbosity are retained in (optional)
+	captures: Option<Arc<ExecutionCaptures>>,
+	/// Sink that full composition payloads are archived to (optional)
+	archiver: Option<Arc<PayloadArchiver>>,
+	/// Trace id of the call this executor serves, for correlating archived payloads and
+	/// debug output
+	trace_id: Option<String>,
+	/// Span id of the composition span, for correlating debug output
+	span_id: Option<String>,
+	/// Labels attributing executions to a caller's project, ticket, etc.
+	labels: Arc<ExecutionLabels>,
+	/// Id the caller generated for the execution (optional; otherwise each execution gets
+	/// a new one)
+	execution_id: Option<ExecutionId>,
+	/// Journal that pipeline executions are written to for a standby, with the caller they
+	/// can be resumed for (optional)
+	journal: Option<(Arc<ExecutionJournal>, String)>,
+}
+
+/// Where in a composition a tool invocation originates
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct InvocationMeta {
+	/// Composition making the call
+	pub composition: Option<String>,
+	/// Pipeline step making the call
+	pub step_id: Option<String>,
+	/// Labels the caller attached to the execution
+	pub labels: Arc<ExecutionLabels>,
 }
 
 /// Trait for invoking tools (abstraction over actual backend calls)
@@ -82,6 +374,63 @@ pub struct CompositionExecutor {
 pub trait ToolInvoker: Send + Sync {
 	/// Invoke a tool by name with the given arguments
 	async fn invoke(&self, tool_name: &str, args: Value) -> Result<Value, ExecutionError>;
+
+	/// Invoke a tool, with metadata about the composition step making the call
+	///
+	/// Invokers that call real backends use this to correlate backend requests with the
+	/// composition; the default ignores the metadata.
+	async fn invoke_with_meta(
+		&self,
+		tool_name: &str,
+		args: Value,
+		_meta: &InvocationMeta,
+	) -> Result<Value, ExecutionError> {
+		self.invoke(tool_name, args).await
+	}
+
+	/// Ask the connected client's LLM for a completion (MCP sampling)
+	///
+	/// The default has no client to ask.
+	async fn create_message(
+		&self,
+		_request: CreateMessageRequestParam,
+	) -> Result<CreateMessageResult, ExecutionError> {
+		Err(ExecutionError::SamplingUnavailable(
+			"no client connection to sample from".to_string(),
+		))
+	}
+
+	/// Whether the client is connected over a stream that [`emit_partial`] can send to
+	///
+	/// [`emit_partial`]: ToolInvoker::emit_partial
+	fn can_emit_partial(&self) -> bool {
+		false
+	}
+
+	/// Send part of the composition's result to the client ahead of the final result
+	///
+	/// Waits while the client's stream is full, so a slow client slows the execution down
+	/// rather than piling up results. The default has no stream to send to.
+	async fn emit_partial(&self, _partial: Value) -> Result<(), ExecutionError> {
+		Err(ExecutionError::PatternExecutionFailed(
+			"no client stream to emit partial results to".to_string(),
+		))
+	}
+
+	/// Tell the client how far a composition has got: `completed` of `total` steps are done,
+	/// and `message` names the one starting
+	///
+	/// Only sent to clients that asked for progress with a progress token. The default has no
+	/// client to tell.
+	async fn report_progress(&self, _completed: u32, _total: u32, _message: String) {}
+
+	/// Whether calls to the upstream behind a tool are currently let through, so patterns
+	/// can skip upstreams known to be down instead of waiting on them
+	///
+	/// The default knows nothing about upstream health.
+	fn is_available(&self, _tool_name: &str) -> bool {
+		true
+	}
 }
 
 impl CompositionExecutor {
@@ -90,52 +439,516 @@ impl CompositionExecutor {
 		Self {
 			registry,
 			tool_invoker,
+			session: Arc::new(SessionStore::new()),
+			execution_log: None,
+			verbosity_overrides: None,
+			verbosity: None,
+			memory_budget: None,
+			events: None,
+			result_cache: None,
+			dead_letters: None,
+			dead_letter_replay: false,
+			captures: None,
+			archiver: None,
+			trace_id: None,
+			span_id: None,
+			labels: Default::default(),
+			execution_id: None,
+			journal: None,
 		}
 	}
 
+	/// Builder: attach session-scoped state (e.g. owned by the MCP session)
+	pub fn with_session(mut self, session: Arc<SessionStore>) -> Self {
+		self.session = session;
+		self
+	}
+
+	/// Builder: record executions into the given log
+	pub fn with_execution_log(mut self, log: Arc<ExecutionLog>) -> Self {
+		self.execution_log = Some(log);
+		self
+	}
+
+	/// Builder: consult runtime verbosity overrides for each composition
+	pub fn with_verbosity_overrides(mut self, overrides: Arc<VerbosityOverrides>) -> Self {
+		self.verbosity_overrides = Some(overrides);
+		self
+	}
+
+	/// Builder: run every composition at the verbosity a trusted caller asked for
+	pub fn with_verbosity(mut self, verbosity: Option<CompositionVerbosity>) -> Self {
+		self.verbosity = verbosity;
+		self
+	}
+
+	/// Builder: charge in-flight payloads against a budget shared with other executors
+	pub fn with_memory_budget(mut self, budget: Arc<MemoryBudget>) -> Self {
+		self.memory_budget = Some(budget);
+		self
+	}
+
+	/// Builder: publish execution lifecycle events to the given event bus
+	pub fn with_events(mut self, events: EventBus) -> Self {
+		self.events = Some(events);
+		self
+	}
+
+	/// Builder: keep cache pattern results in the given cache
+	pub fn with_result_cache(mut self, cache: Arc<ResultCache>) -> Self {
+		self.result_cache = Some(cache);
+		self
+	}
+
+	/// Builder: retain dead-lettered failures in the given queue
+	pub fn with_dead_letters(mut self, queue: Arc<DeadLetterQueue>) -> Self {
+		self.dead_letters = Some(queue);
+		self
+	}
+
+	/// Builder: return failures from dead letter patterns instead of dead-lettering them again
+	pub fn for_dead_letter_replay(mut self) -> Self {
+		self.dead_letter_replay = true;
+		self
+	}
+
+	/// Builder: retain executions at capture verbosity, and re-runs, in the given store
+	pub fn with_captures(mut self, captures: Arc<ExecutionCaptures>) -> Self {
+		self.captures = Some(captures);
+		self
+	}
+
+	/// Builder: archive the payloads of executions the archiver selects
+	pub fn with_archiver(mut self, archiver: Arc<PayloadArchiver>) -> Self {
+		self.archiver = Some(archiver);
+		self
+	}
+
+	/// Builder: attribute executions to caller-supplied labels
+	pub fn with_labels(mut self, labels: ExecutionLabels) -> Self {
+		self.labels = Arc::new(labels);
+		self
+	}
+
+	/// Builder: record this trace id with archived payloads and debug output
+	pub fn with_trace_id(mut self, trace_id: impl Into<String>) -> Self {
+		self.trace_id = Some(trace_id.into());
+		self
+	}
+
+	/// Builder: tag debug output with the composition span's id
+	pub fn with_span_id(mut self, span_id: impl Into<String>) -> Self {
+		self.span_id = Some(span_id.into());
+		self
+	}
+
+	/// Builder: run the execution under an id the caller generated, so the caller can report
+	/// it before the execution finishes. Only for executors that run a single execution.
+	pub fn with_execution_id(mut self, id: ExecutionId) -> Self {
+		self.execution_id = Some(id);
+		self
+	}
+
+	/// Builder: journal pipeline executions on behalf of `caller`, so a standby can resume
+	/// them; only executions with an id set by [`Self::with_execution_id`] are journaled
+	pub fn with_journal(mut self, journal: Arc<ExecutionJournal>, caller: impl Into<String>) -> Self {
+		self.journal = Some((journal, caller.into()));
+		self
+	}
+
+	/// Resolve the debug verbosity for a composition
+	fn verbosity_for(&self, composition_name: &str) -> CompositionVerbosity {
+		self
+			.verbosity
+			.or_else(|| {
+				self
+					.verbosity_overrides
+					.as_ref()
+					.and_then(|o| o.get(composition_name))
+			})
+			.unwrap_or_default()
+	}
+
+	/// Span that a composition's debug output is recorded under
+	///
+	/// It carries the execution id and the trace and span ids of the MCP call, so log lines
+	/// can be joined with the trace. No span is created unless verbosity is raised for the
+	/// composition.
+	fn debug_span(&self, composition_name: &str, execution: ExecutionId) -> Span {
+		if self.verbosity_for(composition_name) == CompositionVerbosity::Off {
+			return Span::none();
+		}
+		info_span!(
+			target: "virtual_tools",
+			"composition",
+			name = %composition_name,
+			execution_id = %execution,
+			trace_id = self.trace_id.as_deref(),
+			span_id = self.span_id.as_deref(),
+		)
+	}
+
 	/// Execute a composition by name
 	pub async fn execute(
 		&self,
 		composition_name: &str,
 		input: Value,
 	) -> Result<Value, ExecutionError> {
-		debug!(target: "virtual_tools", composition = %composition_name, "executing composition");
+		self.execute_metered(composition_name, input).await.0
+	}
+
+	/// Execute a composition by name, also returning the estimated cost of its tool calls
+	pub async fn execute_metered(
+		&self,
+		composition_name: &str,
+		input: Value,
+	) -> (Result<Value, ExecutionError>, CostSummary) {
+		self.execute_from(composition_name, input, None).await
+	}
+
+	/// Re-run a captured execution of a pipeline composition from a step
+	pub async fn resume(
+		&self,
+		composition_name: &str,
+		input: Value,
+		resume: ResumePoint,
+	) -> Result<Value, ExecutionError> {
+		self
+			.execute_from(composition_name, input, Some(&resume))
+			.await
+			.0
+	}
 
-		let tool = self
+	/// Resume a journaled execution taken over from another instance, after its last
+	/// completed step
+	pub async fn resume_journaled(
+		&self,
+		execution: JournaledExecution,
+	) -> (Result<Value, ExecutionError>, CostSummary) {
+		let pipeline = self
 			.registry
-			.get_tool(composition_name)
-			.ok_or_else(|| ExecutionError::ToolNotFound(composition_name.to_string()))?;
+			.get_tool(&execution.composition)
+			.and_then(|tool| tool.composition_info())
+			.and_then(|composition| match &composition.spec {
+				PatternSpec::Pipeline(p) => Some(p),
+				_ => None,
+			});
+		// A step that completed just before the failure may not have been journaled yet, and
+		// runs again
+		let from_step = pipeline.and_then(|p| {
+			p.steps
+				.iter()
+				.find(|step| !execution.completed.iter().any(|c| c.step_id == step.id))
+				.or(p.steps.last())
+				.map(|step| step.id.clone())
+		});
+		let Some(from_step) = from_step else {
+			return self
+				.execute_from(&execution.composition, execution.input, None)
+				.await;
+		};
+		let resume = ResumePoint {
+			from_step,
+			completed: execution
+				.completed
+				.into_iter()
+				.map(|step| CapturedStep {
+					step_id: step.step_id,
+					input: Value::Null,
+					output: Some(step.output),
+					error: None,
+					duration_ms: 0,
+					carried_over: true,
+					redacted: false,
+				})
+				.collect(),
+			capture_id: None,
+			replay_of: None,
+		};
+		self
+			.execute_from(&execution.composition, execution.input, Some(&resume))
+			.await
+	}
+
+	/// Execute a composition by name, from the start or from a resume point
+	async fn execute_from(
+		&self,
+		composition_name: &str,
+		input: Value,
+		resume: Option<&ResumePoint>,
+	) -> (Result<Value, ExecutionError>, CostSummary) {
+		let execution = self.execution_id.unwrap_or_default();
+		debug!(
+			target: "virtual_tools",
+			composition = %composition_name,
+			execution_id = %execution,
+			"executing composition"
+		);
+
+		let cost = Arc::new(CostLedger::default());
+		let result: Result<Value, ExecutionError> = async {
+			let tool = self
+				.registry
+				.get_tool(composition_name)
+				.ok_or_else(|| ExecutionError::ToolNotFound(composition_name.to_string()))?;
 
-		let composition = tool.composition_info().ok_or_else(|| {
-			ExecutionError::InvalidInput(format!("{} is not a composition", composition_name))
-		})?;
+			let composition = tool.composition_info().ok_or_else(|| {
+				ExecutionError::InvalidInput(format!("{} is not a composition", composition_name))
+			})?;
 
-		self.execute_composition(tool, composition, input).await
+			self
+				.execute_composition(
+					tool,
+					composition,
+					input,
+					execution,
+					cost.clone(),
+					None,
+					resume,
+				)
+				.instrument(self.debug_span(composition_name, execution))
+				.await
+		}
+		.await;
+		(result, cost.summary())
 	}
 
-	/// Execute a compiled composition
+	/// Execute a compiled composition as execution `execution`, charging its tool calls to
+	/// `cost`
+	///
+	/// A nested composition charges its payloads to its caller's `memory` ledger instead of
+	/// being admitted on its own; the caller already holds its input.
+	#[allow(clippy::too_many_arguments)]
 	async fn execute_composition(
 		&self,
-		_tool: &CompiledTool,
+		tool: &CompiledTool,
 		composition: &CompiledComposition,
 		input: Value,
+		execution: ExecutionId,
+		cost: Arc<CostLedger>,
+		memory: Option<Arc<MemoryLedger>>,
+		resume: Option<&ResumePoint>,
 	) -> Result<Value, ExecutionError> {
-		let ctx = ExecutionContext::new(
+		let started = Instant::now();
+		let name = &tool.def.name;
+		let verbosity = self.verbosity_for(name);
+		let mut ctx = ExecutionContext::new(
 			input.clone(),
 			self.registry.clone(),
 			self.tool_invoker.clone(),
-		);
+		)
+		.with_session(self.session.clone())
+		.with_verbosity(verbosity)
+		.with_composition(name)
+		.with_execution_id(execution)
+		.with_labels(self.labels.clone())
+		.with_cost_ledger(cost);
+		let nested = memory.is_some();
+		match (memory, &self.memory_budget) {
+			(Some(ledger), _) => ctx = ctx.with_memory_ledger(ledger),
+			(None, Some(budget)) => ctx = ctx.with_memory_budget(budget.clone()),
+			(None, None) => {},
+		}
+		// Only the top-level execution carries the executor's id; nested compositions are part
+		// of the step that runs them
+		let top_level = self.execution_id == Some(execution);
+		if top_level {
+			ctx = ctx.with_progress();
+		}
+		let journal = self
+			.journal
+			.as_ref()
+			.filter(|_| top_level && matches!(composition.spec, PatternSpec::Pipeline(_)));
+		if let Some((journal, caller)) = journal {
+			let completed = resume
+				.map(|r| {
+					r.completed
+						.iter()
+						.filter_map(|step| {
+							Some(JournaledStep {
+								step_id: step.step_id.clone(),
+								output: step.output.clone()?,
+							})
+						})
+						.collect()
+				})
+				.unwrap_or_default();
+			let recorder = journal
+				.begin(JournaledExecution {
+					execution_id: execution,
+					composition: name.clone(),
+					caller: caller.clone(),
+					owner: journal.node().to_string(),
+					started_at_ms: journal::now_ms(),
+					input: input.clone(),
+					completed,
+				})
+				.await;
+			ctx = ctx.with_journal(Arc::new(recorder));
+		}
+		// Re-runs are always captured, so their outcome can be inspected
+		let rerun = resume.is_some_and(|r| r.replay_of.is_some());
+		let captures = self
+			.captures
+			.as_ref()
+			.filter(|_| rerun || verbosity >= CompositionVerbosity::Capture);
+		if captures.is_some() {
+			let completed = resume.map(|r| r.completed.clone()).unwrap_or_default();
+			ctx = ctx.with_step_capture(completed);
+		}
+		// Only pay for lifecycle events while someone is watching
+		let events = self.events.as_ref().filter(|e| e.subscriber_count() > 0);
+		// Sample for archival up front, so the start time is only taken when needed
+		let archive = self
+			.archiver
+			.as_ref()
+			.filter(|a| a.should_archive(name))
+			.map(|a| (a, SystemTime::now()));
+		if let Some(events) = events {
+			ctx = ctx.with_events(events.clone());
+			events.publish(EventKind::CompositionStarted {
+				composition: name.clone(),
+				execution,
+			});
+		}
 
-		let result = self.execute_pattern(&composition.spec, input, &ctx).await?;
+		if verbosity >= CompositionVerbosity::Full {
+			info!(
+				target: "virtual_tools",
+				composition = %name,
+				labels = %ctx.labels,
+				input = %execution_log::redact(&ctx.input),
+				"composition started"
+			);
+		}
 
-		// Apply output transform if present
-		if let Some(ref transform) = composition.output_transform {
-			transform
-				.apply(&result)
-				.map_err(|e| ExecutionError::PatternExecutionFailed(e.to_string()))
-		} else {
-			Ok(result)
+		// Admit the execution only if its input fits in the memory budget
+		let admitted = if nested { Ok(()) } else { ctx.charge(&input) };
+		let result = match (admitted, resume, &composition.spec) {
+			(Err(e), _, _) => Err(e),
+			(Ok(()), None, spec) => self.execute_pattern(spec, input, &ctx).await,
+			(Ok(()), Some(resume), PatternSpec::Pipeline(p)) => {
+				PipelineExecutor::resume(p, input, resume, &ctx, self).await
+			},
+			(Ok(()), Some(_), _) => Err(ExecutionError::InvalidInput(format!(
+				"{name} is not a pipeline; only pipelines can be resumed from a step"
+			))),
+		};
+		let result = result.map_err(|e| e.at(|| ctx.location()));
+		let result = result.and_then(|result| {
+			// Apply output transform if present
+			if let Some(ref transform) = composition.output_transform {
+				transform
+					.apply(&result)
+					.map_err(|e| ExecutionError::PatternExecutionFailed(e.to_string()))
+			} else {
+				Ok(result)
+			}
+		});
+
+		let elapsed = started.elapsed();
+		if let Some((journal, _)) = journal {
+			journal.finish(execution).await;
+		}
+		if verbosity >= CompositionVerbosity::Summary {
+			let output = match &result {
+				Ok(v) if verbosity >= CompositionVerbosity::Full => execution_log::redact(v).to_string(),
+				Ok(_) => String::new(),
+				Err(e) => e.to_string(),
+			};
+			info!(
+				target: "virtual_tools",
+				composition = %name,
+				labels = %ctx.labels,
+				duration_ms = elapsed.as_millis() as u64,
+				ok = result.is_ok(),
+				output = %output,
+				"composition completed"
+			);
+		}
+
+		if let Some(ref log) = self.execution_log {
+			log.record(
+				name,
+				ExecutionRecord::new(execution, &ctx.input, &result, elapsed, ctx.step_timings())
+					.with_labels(&ctx.labels)
+					.with_cost(ctx.cost_summary()),
+			);
+		}
+
+		if let Some(captures) = captures {
+			captures.record(ExecutionCapture {
+				id: resume
+					.and_then(|r| r.capture_id)
+					.unwrap_or_else(|| captures.next_id()),
+				execution_id: execution,
+				composition: name.clone(),
+				started_at_ms: SystemTime::now()
+					.checked_sub(elapsed)
+					.and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+					.unwrap_or_default()
+					.as_millis() as u64,
+				duration_ms: elapsed.as_millis() as u64,
+				input: ctx.input.clone(),
+				replayable: true,
+				steps: ctx.captured_steps(),
+				output: result.as_ref().ok().cloned(),
+				error: result.as_ref().err().map(ToString::to_string),
+				replay_of: resume.and_then(|r| r.replay_of),
+			});
+		}
+
+		if let Some((archiver, started_at)) = archive {
+			archiver.archive(ArchiveRecord {
+				composition: name.clone(),
+				execution,
+				trace_id: self.trace_id.clone(),
+				labels: (*ctx.labels).clone(),
+				started_at_ms: started_at
+					.duration_since(UNIX_EPOCH)
+					.unwrap_or_default()
+					.as_millis() as u64,
+				duration_ms: elapsed.as_millis() as u64,
+				input: ctx.input.clone(),
+				output: result.as_ref().ok().cloned(),
+				error: result.as_ref().err().map(ToString::to_string),
+			});
+		}
+
+		if let Some(events) = events {
+			let composition = name.clone();
+			let duration_ms = elapsed.as_millis() as u64;
+			events.publish(match &result {
+				Ok(_) => EventKind::CompositionFinished {
+					composition,
+					execution,
+					duration_ms,
+				},
+				Err(e) => EventKind::CompositionFailed {
+					composition,
+					execution,
+					error: e.to_string(),
+					duration_ms,
+				},
+			});
 		}
+
+		if !tool.def.result_envelope {
+			return result;
+		}
+		result.map(|data| {
+			ResultEnvelope {
+				data,
+				errors: ctx.errors(),
+				meta: EnvelopeMeta {
+					duration_ms: elapsed.as_millis() as u64,
+					steps: ctx.step_timings().len(),
+					cache_hits: ctx.cache_hits(),
+					cost: ctx.cost(),
+				},
+			}
+			.into_value()
+		})
 	}
 
 	/// Execute a pattern
@@ -157,6 +970,10 @@ impl CompositionExecutor {
 				PatternSpec::Filter(f) => FilterExecutor::execute(f, input).await,
 				PatternSpec::SchemaMap(sm) => SchemaMapExecutor::execute(sm, input).await,
 				PatternSpec::MapEach(me) => MapEachExecutor::execute(me, input, ctx, self).await,
+				PatternSpec::Rank(r) => RankExecutor::execute(r, input, ctx, self).await,
+				PatternSpec::Cache(c) => CacheExecutor::execute(c, input, ctx, self).await,
+				PatternSpec::DeadLetter(dl) => DeadLetterExecutor::execute(dl, input, ctx, self).await,
+				PatternSpec::Timeout(t) => TimeoutExecutor::execute(t, input, ctx, self).await,
 
 				// Stateful patterns (IR defined, runtime not yet implemented)
 				PatternSpec::Retry(_) => Err(ExecutionError::StatefulPatternNotImplemented {
@@ -165,18 +982,6 @@ impl CompositionExecutor {
 						Configure a state store backend (e.g., Redis, in-memory) and implement RetryExecutor to enable this pattern."
 						.to_string(),
 				}),
-				PatternSpec::Timeout(_) => Err(ExecutionError::StatefulPatternNotImplemented {
-					pattern: "timeout".to_string(),
-					details: "The timeout pattern requires async cancellation support. \
-						Implement TimeoutExecutor with tokio::time::timeout to enable this pattern."
-						.to_string(),
-				}),
-				PatternSpec::Cache(_) => Err(ExecutionError::StatefulPatternNotImplemented {
-					pattern: "cache".to_string(),
-					details: "The cache pattern requires a cache store backend (e.g., Redis, in-memory LRU). \
-						Configure a cache store and implement CacheExecutor to enable read-through caching."
-						.to_string(),
-				}),
 				PatternSpec::Idempotent(_) => Err(ExecutionError::StatefulPatternNotImplemented {
 					pattern: "idempotent".to_string(),
 					details: "The idempotent pattern requires a store for tracking processed request keys. \
@@ -189,12 +994,6 @@ impl CompositionExecutor {
 						Configure a store backend and implement CircuitBreakerExecutor to enable fail-fast behavior with automatic recovery."
 						.to_string(),
 				}),
-				PatternSpec::DeadLetter(_) => Err(ExecutionError::StatefulPatternNotImplemented {
-					pattern: "dead_letter".to_string(),
-					details: "The dead letter pattern requires a queue or storage backend for capturing failed messages. \
-						Configure a dead letter tool and implement DeadLetterExecutor to enable failure capture for later processing."
-						.to_string(),
-				}),
 				PatternSpec::Saga(_) => Err(ExecutionError::StatefulPatternNotImplemented {
 					pattern: "saga".to_string(),
 					details: "The saga pattern requires a store for tracking saga state and enabling recovery. \
@@ -262,6 +1061,41 @@ impl CompositionExecutor {
 		})
 	}
 
+	/// Check a step's arguments against the input schema of the tool it calls
+	fn check_arguments(
+		&self,
+		tool: &str,
+		schema: &Value,
+		args: &Value,
+		ctx: &ExecutionContext,
+	) -> Result<(), ExecutionError> {
+		let policy = self.registry.argument_checks();
+		if policy == ArgumentCheckPolicy::Off {
+			return Ok(());
+		}
+		let problems = check_arguments(schema, args);
+		if problems.is_empty() {
+			return Ok(());
+		}
+		let step = ctx.step_id.as_deref().unwrap_or("(none)");
+		let message = problems.join("; ");
+		if policy == ArgumentCheckPolicy::Warn {
+			warn!(
+				target: "virtual_tools",
+				composition = ctx.composition.as_deref().unwrap_or_default(),
+				step,
+				tool,
+				"arguments don't match the tool's input schema: {message}"
+			);
+			return Ok(());
+		}
+		Err(ExecutionError::InvalidArguments {
+			step: step.to_string(),
+			tool: tool.to_string(),
+			message,
+		})
+	}
+
 	/// Execute a tool by name
 	///
 	/// This function uses Box::pin to handle async recursion when compositions
@@ -274,63 +1108,58 @@ impl CompositionExecutor {
 	) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<Value, ExecutionError>> + Send + 'a>>
 	{
 		Box::pin(async move {
-			// First, check if it's a composition in the registry
-			if let Some(tool) = self.registry.get_tool(name)
+			// First, check if it's a composition in the registry, otherwise invoke via the tool invoker
+			let tool = self.registry.get_tool(name);
+			if let Some(schema) = tool.and_then(|t| t.known_input_schema()) {
+				self.check_arguments(name, schema, &args, ctx)?;
+			}
+			let result = if let Some(tool) = tool
 				&& let Some(composition) = tool.composition_info()
 			{
-				return self.execute_composition(tool, composition, args).await;
-			}
-
-			// Otherwise, invoke via the tool invoker
-			ctx.tool_invoker.invoke(name, args).await
+				// The nested execution has its own total, which the caller's then includes
+				let nested = Arc::new(CostLedger::default());
+				let result = self
+					.execute_composition(
+						tool,
+						composition,
+						args,
+						ExecutionId::new(),
+						nested.clone(),
+						Some(ctx.memory_ledger()),
+						None,
+					)
+					.await;
+				ctx.merge_cost(&nested.summary());
+				// Its payloads were charged to this execution as it ran
+				result?
+			} else {
+				let model = tool.and_then(|t| t.def.cost.as_ref());
+				let input_bytes = match model {
+					Some(CostModel::Bytes(_)) => cost::serialized_len(&args),
+					_ => 0,
+				};
+				let result = ctx
+					.tool_invoker
+					.invoke_with_meta(name, args, &ctx.invocation_meta())
+					.await?;
+				if let Some(model) = model {
+					ctx.record_cost(name, cost::estimate(model, input_bytes, &result));
+				}
+				// The result is held by this execution until it completes
+				ctx.charge(&result)?;
+				result
+			};
+			Ok(result)
 		})
 	}
 }
 
-/// Mock tool invoker for testing
-#[cfg(test)]
-pub struct MockToolInvoker {
-	responses: std::sync::Mutex<std::collections::HashMap<String, Value>>,
-}
-
-#[cfg(test)]
-impl MockToolInvoker {
-	pub fn new() -> Self {
-		Self {
-			responses: std::sync::Mutex::new(std::collections::HashMap::new()),
-		}
-	}
-
-	pub fn with_response(self, tool_name: &str, response: Value) -> Self {
-		self
-			.responses
-			.lock()
-			.unwrap()
-			.insert(tool_name.to_string(), response);
-		self
-	}
-}
-
-#[cfg(test)]
-#[async_trait::async_trait]
-impl ToolInvoker for MockToolInvoker {
-	async fn invoke(&self, tool_name: &str, _args: Value) -> Result<Value, ExecutionError> {
-		self
-			.responses
-			.lock()
-			.unwrap()
-			.get(tool_name)
-			.cloned()
-			.ok_or_else(|| ExecutionError::ToolNotFound(tool_name.to_string()))
-	}
-}
-
 #[cfg(test)]
 mod tests {
 	use super::*;
 	use crate::mcp::registry::patterns::{
-		BackoffStrategy, ExponentialBackoff, PipelineSpec, PipelineStep, RetrySpec, StepOperation,
-		ToolCall,
+		BackoffStrategy, ExponentialBackoff, PipelineSpec, PipelineStep, RetrySpec, SessionBinding,
+		StepOperation, ToolCall,
 	};
 	use crate::mcp::registry::types::{Registry, ToolDefinition};
 
@@ -346,6 +1175,9 @@ mod tests {
 						name: "echo".to_string(),
 					}),
 					input: None,
+					session_set: None,
+					retry: None,
+					cache: None,
 				}],
 			}),
 		);
@@ -365,6 +1197,535 @@ mod tests {
 		assert_eq!(result.unwrap()["echoed"], true);
 	}
 
+	#[tokio::test]
+	async fn test_execute_passes_invocation_meta() {
+		let composition = ToolDefinition::composition(
+			"test_pipeline",
+			PatternSpec::Pipeline(PipelineSpec {
+				steps: vec![PipelineStep {
+					id: "step1".to_string(),
+					operation: StepOperation::Tool(ToolCall {
+						name: "echo".to_string(),
+					}),
+					input: None,
+					session_set: None,
+					retry: None,
+					cache: None,
+				}],
+			}),
+		);
+
+		let registry = Registry::with_tool_definitions(vec![composition]);
+		let compiled = CompiledRegistry::compile(registry).unwrap();
+		let invoker = Arc::new(MockToolInvoker::new().with_response("echo", serde_json::json!({})));
+		let labels = ExecutionLabels::from_iter([("project", "search"), ("ticket", "OPS-12")]);
+		let log = Arc::new(ExecutionLog::default());
+		let executor = CompositionExecutor::new(Arc::new(compiled), invoker.clone())
+			.with_labels(labels.clone())
+			.with_execution_log(log.clone());
+
+		executor
+			.execute("test_pipeline", serde_json::json!({}))
+			.await
+			.unwrap();
+
+		assert_eq!(
+			invoker.calls(),
+			vec![(
+				"echo".to_string(),
+				InvocationMeta {
+					composition: Some("test_pipeline".to_string()),
+					step_id: Some("step1".to_string()),
+					labels: Arc::new(labels.clone()),
+				}
+			)]
+		);
+		assert_eq!(log.recent("test_pipeline")[0].labels, labels);
+	}
+
+	#[tokio::test]
+	async fn test_execute_shares_session_state() {
+		let composition = ToolDefinition::composition(
+			"paginate",
+			PatternSpec::Pipeline(PipelineSpec {
+				steps: vec![PipelineStep {
+					id: "list".to_string(),
+					operation: StepOperation::Tool(ToolCall {
+						name: "list_items".to_string(),
+					}),
+					input: None,
+					session_set: Some(SessionBinding {
+						key: "cursor".to_string(),
+						path: "$.next".to_string(),
+					}),
+					retry: None,
+					cache: None,
+				}],
+			}),
+		);
+
+		let registry = Registry::with_tool_definitions(vec![composition]);
+		let compiled = CompiledRegistry::compile(registry).unwrap();
+		let invoker =
+			MockToolInvoker::new().with_response("list_items", serde_json::json!({"next": "abc"}));
+		let session = Arc::new(SessionStore::new());
+
+		let executor =
+			CompositionExecutor::new(Arc::new(compiled), Arc::new(invoker)).with_session(session.clone());
+
+		executor
+			.execute("paginate", serde_json::json!({}))
+			.await
+			.unwrap();
+
+		assert_eq!(session.get("cursor").await, Some(serde_json::json!("abc")));
+	}
+
+	#[tokio::test]
+	async fn test_execute_enforces_memory_budget() {
+		let composition = ToolDefinition::composition(
+			"fetch",
+			PatternSpec::Pipeline(PipelineSpec {
+				steps: vec![PipelineStep {
+					id: "fetch".to_string(),
+					operation: StepOperation::Tool(ToolCall {
+						name: "fetch_doc".to_string(),
+					}),
+					input: None,
+					session_set: None,
+					retry: None,
+					cache: None,
+				}],
+			}),
+		);
+
+		let registry = Registry::with_tool_definitions(vec![composition]);
+		let compiled = CompiledRegistry::compile(registry).unwrap();
+		let doc = serde_json::json!({"body": "x".repeat(4096)});
+		let invoker = MockToolInvoker::new().with_response("fetch_doc", doc.clone());
+		let budget = Arc::new(MemoryBudget::new(Some(approx_size(&doc))));
+
+		let executor = CompositionExecutor::new(Arc::new(compiled), Arc::new(invoker))
+			.with_memory_budget(budget.clone());
+
+		// The input fits, but the fetched document pushes the execution over the limit
+		let result = executor.execute("fetch", serde_json::json!({})).await;
+		assert!(matches!(
+			result,
+			Err(ExecutionError::MemoryLimitExceeded { .. })
+		));
+		// Everything charged by the failed execution is released
+		assert_eq!(budget.used(), 0);
+	}
+
+	#[tokio::test]
+	async fn test_nested_composition_charges_payloads_once() {
+		let step = |id: &str, tool: &str| PipelineStep {
+			id: id.to_string(),
+			operation: StepOperation::Tool(ToolCall {
+				name: tool.to_string(),
+			}),
+			input: None,
+			session_set: None,
+			retry: None,
+			cache: None,
+		};
+		let inner = ToolDefinition::composition(
+			"inner",
+			PatternSpec::Pipeline(PipelineSpec {
+				steps: vec![step("fetch", "fetch_doc")],
+			}),
+		);
+		let outer = ToolDefinition::composition(
+			"outer",
+			PatternSpec::Pipeline(PipelineSpec {
+				steps: vec![step("call", "inner")],
+			}),
+		);
+
+		let registry = Registry::with_tool_definitions(vec![inner, outer]);
+		let compiled = CompiledRegistry::compile(registry).unwrap();
+		let doc = serde_json::json!({"body": "x".repeat(4096)});
+		let invoker = MockToolInvoker::new().with_response("fetch_doc", doc.clone());
+		let input = serde_json::json!({});
+		let budget = Arc::new(MemoryBudget::new(Some(
+			approx_size(&doc) + approx_size(&input),
+		)));
+
+		let executor = CompositionExecutor::new(Arc::new(compiled), Arc::new(invoker))
+			.with_memory_budget(budget.clone());
+
+		// The fetched document is held once, not again by the outer composition
+		let result = executor.execute("outer", input).await.unwrap();
+		assert_eq!(result, doc);
+		assert_eq!(budget.used(), 0);
+	}
+
+	#[tokio::test]
+	async fn test_execute_wraps_result_envelope() {
+		use crate::mcp::registry::patterns::{AggregationStrategy, ScatterGatherSpec, ScatterTarget};
+
+		let scatter = PatternSpec::ScatterGather(ScatterGatherSpec {
+			targets: vec![
+				ScatterTarget::tool("search_a"),
+				ScatterTarget::tool("search_b"),
+			],
+			aggregation: AggregationStrategy { ops: vec![] },
+			timeout_ms: None,
+			fail_fast: false,
+			sample: None,
+		});
+		let mut composition = ToolDefinition::composition(
+			"search",
+			PatternSpec::Pipeline(PipelineSpec {
+				steps: vec![PipelineStep {
+					id: "gather".to_string(),
+					operation: StepOperation::Pattern(Box::new(scatter)),
+					input: None,
+					session_set: None,
+					retry: None,
+					cache: None,
+				}],
+			}),
+		);
+		composition.result_envelope = true;
+
+		let registry = Registry::with_tool_definitions(vec![composition]);
+		let compiled = CompiledRegistry::compile(registry).unwrap();
+		let invoker = MockToolInvoker::new()
+			.with_response("search_a", serde_json::json!({"hits": 1}))
+			.with_error("search_b", "backend unavailable");
+		let executor = CompositionExecutor::new(Arc::new(compiled), Arc::new(invoker));
+
+		let result = executor
+			.execute("search", serde_json::json!({}))
+			.await
+			.unwrap();
+		assert_eq!(result["data"], serde_json::json!([{"hits": 1}]));
+		assert_eq!(
+			result["errors"],
+			serde_json::json!([{
+				"step": "gather",
+				"message": "tool execution failed: backend unavailable"
+			}])
+		);
+		assert_eq!(result["meta"]["steps"], 1);
+		assert_eq!(result["meta"]["cache_hits"], 0);
+		assert!(result["meta"]["duration_ms"].is_u64());
+	}
+
+	#[tokio::test]
+	async fn test_execute_metered_accumulates_tool_costs() {
+		use crate::mcp::registry::types::{CostModel, ReportedCost};
+
+		let step = |id: &str, tool: &str| PipelineStep {
+			id: id.to_string(),
+			operation: StepOperation::Tool(ToolCall {
+				name: tool.to_string(),
+			}),
+			input: None,
+			session_set: None,
+			retry: None,
+			cache: None,
+		};
+		let mut search = ToolDefinition::source("search", "web", "search");
+		search.cost = Some(CostModel::Static(0.5));
+		let mut summarize = ToolDefinition::source("summarize", "llm", "complete");
+		summarize.cost = Some(CostModel::Reported(ReportedCost {
+			path: "$.usage.cost".to_string(),
+			fallback: 0.0,
+		}));
+		let inner = ToolDefinition::composition(
+			"inner",
+			PatternSpec::Pipeline(PipelineSpec {
+				steps: vec![step("search", "search")],
+			}),
+		);
+		let mut outer = ToolDefinition::composition(
+			"outer",
+			PatternSpec::Pipeline(PipelineSpec {
+				steps: vec![
+					step("search", "search"),
+					step("nested", "inner"),
+					step("summarize", "summarize"),
+					step("lookup", "unpriced"),
+				],
+			}),
+		);
+		outer.result_envelope = true;
+
+		let registry = Registry::with_tool_definitions(vec![search, summarize, inner, outer]);
+		let compiled = CompiledRegistry::compile(registry).unwrap();
+		let invoker = MockToolInvoker::new()
+			.with_response("search", serde_json::json!({"hits": 3}))
+			.with_response("summarize", serde_json::json!({"usage": {"cost": 2.0}}))
+			.with_response("unpriced", serde_json::json!({}));
+		let executor = CompositionExecutor::new(Arc::new(compiled), Arc::new(invoker));
+
+		let (result, cost) = executor
+			.execute_metered("outer", serde_json::json!({}))
+			.await;
+		assert_eq!(result.unwrap()["meta"]["cost"], 3.0);
+		assert_eq!(cost.total, 3.0);
+		assert_eq!(cost.tools["search"].calls, 2);
+		assert_eq!(cost.tools["summarize"].cost, 2.0);
+		assert!(!cost.tools.contains_key("unpriced"));
+	}
+
+	#[tokio::test]
+	async fn test_progress_is_reported_for_top_level_steps() {
+		let step = |id: &str, tool: &str| PipelineStep {
+			id: id.to_string(),
+			operation: StepOperation::Tool(ToolCall {
+				name: tool.to_string(),
+			}),
+			input: None,
+			session_set: None,
+			retry: None,
+			cache: None,
+		};
+		let inner = ToolDefinition::composition(
+			"inner",
+			PatternSpec::Pipeline(PipelineSpec {
+				steps: vec![step("search", "search")],
+			}),
+		);
+		let outer = ToolDefinition::composition(
+			"outer",
+			PatternSpec::Pipeline(PipelineSpec {
+				steps: vec![step("search", "search"), step("nested", "inner")],
+			}),
+		);
+		let registry = Registry::with_tool_definitions(vec![inner, outer]);
+		let compiled = Arc::new(CompiledRegistry::compile(registry).unwrap());
+		let invoker =
+			Arc::new(MockToolInvoker::new().with_response("search", serde_json::json!({"hits": 3})));
+
+		let executor = CompositionExecutor::new(compiled.clone(), invoker.clone())
+			.with_execution_id(ExecutionId::new());
+		executor
+			.execute("outer", serde_json::json!({}))
+			.await
+			.unwrap();
+		// The nested composition's step is part of the step that runs it
+		assert_eq!(
+			invoker.progress(),
+			[
+				(0, 2, "step 1 of 2: search".to_string()),
+				(1, 2, "step 2 of 2: nested".to_string()),
+			]
+		);
+	}
+
+	#[tokio::test]
+	async fn test_step_arguments_are_checked_against_tool_schema() {
+		let mut forecast = ToolDefinition::source("forecast", "weather", "get_forecast");
+		forecast.input_schema = Some(serde_json::json!({
+			"type": "object",
+			"properties": {"city": {"type": "string"}},
+			"required": ["city"]
+		}));
+		let plan = ToolDefinition::composition(
+			"plan",
+			PatternSpec::Pipeline(PipelineSpec {
+				steps: vec![PipelineStep {
+					id: "lookup".to_string(),
+					operation: StepOperation::Tool(ToolCall {
+						name: "forecast".to_string(),
+					}),
+					input: None,
+					session_set: None,
+					retry: None,
+					cache: None,
+				}],
+			}),
+		);
+		let compiled = || {
+			CompiledRegistry::compile(Registry::with_tool_definitions(vec![
+				forecast.clone(),
+				plan.clone(),
+			]))
+			.unwrap()
+		};
+		let invoker = Arc::new(MockToolInvoker::new().with_response("forecast", serde_json::json!({})));
+		let input = serde_json::json!({"town": "Oslo"});
+
+		// Warn (the default) logs the mismatch and makes the call
+		let executor = CompositionExecutor::new(Arc::new(compiled()), invoker.clone());
+		assert!(executor.execute("plan", input.clone()).await.is_ok());
+		assert_eq!(invoker.calls().len(), 1);
+
+		let registry = compiled().with_argument_checks(ArgumentCheckPolicy::Enforce);
+		let executor = CompositionExecutor::new(Arc::new(registry), invoker.clone());
+		let err = executor.execute("plan", input).await.unwrap_err();
+		assert!(matches!(
+			&err,
+			ExecutionError::InvalidArguments { step, tool, .. } if step == "lookup" && tool == "forecast"
+		));
+		assert!(err.to_string().contains("missing required field 'city'"));
+		assert_eq!(invoker.calls().len(), 1);
+
+		let executor = CompositionExecutor::new(Arc::new(compiled()), invoker.clone());
+		assert!(
+			executor
+				.execute("plan", serde_json::json!({"city": "Oslo"}))
+				.await
+				.is_ok()
+		);
+	}
+
+	#[tokio::test]
+	async fn test_execute_records_execution_log() {
+		let composition = ToolDefinition::composition(
+			"research_pipeline",
+			PatternSpec::Pipeline(PipelineSpec {
+				steps: vec![
+					PipelineStep {
+						id: "search".to_string(),
+						operation: StepOperation::Tool(ToolCall {
+							name: "search".to_string(),
+						}),
+						input: None,
+						session_set: None,
+						retry: None,
+						cache: None,
+					},
+					PipelineStep {
+						id: "summarize".to_string(),
+						operation: StepOperation::Tool(ToolCall {
+							name: "summarize".to_string(),
+						}),
+						input: None,
+						session_set: None,
+						retry: None,
+						cache: None,
+					},
+				],
+			}),
+		);
+
+		let registry = Registry::with_tool_definitions(vec![composition]);
+		let compiled = CompiledRegistry::compile(registry).unwrap();
+		let invoker = MockToolInvoker::new().with_response("search", serde_json::json!({"hits": 3}));
+		let log = Arc::new(ExecutionLog::new(5));
+		let events = EventBus::new(4);
+		let mut rx = events.subscribe();
+
+		let executor = CompositionExecutor::new(Arc::new(compiled), Arc::new(invoker))
+			.with_execution_log(log.clone())
+			.with_events(events);
+
+		let result = executor
+			.execute(
+				"research_pipeline",
+				serde_json::json!({"query": "rust", "token": "s3cret"}),
+			)
+			.await;
+		assert!(result.is_err());
+
+		let recent = log.recent("research_pipeline");
+		assert_eq!(recent.len(), 1);
+		let rec = &recent[0];
+		assert_eq!(rec.input["query"], "rust");
+		assert_eq!(rec.input["token"], "[REDACTED]");
+		assert!(rec.error.as_deref().unwrap().contains("summarize"));
+		let steps: Vec<_> = rec
+			.steps
+			.iter()
+			.map(|s| (s.step_id.as_str(), s.ok))
+			.collect();
+		assert_eq!(steps, vec![("search", true), ("summarize", false)]);
+
+		let events: Vec<_> = std::iter::from_fn(|| rx.try_recv().ok()).collect();
+		let names: Vec<_> = events.iter().map(|e| e.kind.name()).collect();
+		assert_eq!(
+			names,
+			vec![
+				"composition_started",
+				"composition_step_completed",
+				"composition_step_completed",
+				"composition_failed"
+			]
+		);
+		assert!(matches!(
+			&events[3].kind,
+			EventKind::CompositionFailed { composition, error, .. }
+				if composition == "research_pipeline" && error.contains("summarize")
+		));
+	}
+
+	#[tokio::test]
+	async fn test_verbosity_override_applies_to_composition() {
+		let registry = Registry::new();
+		let compiled = CompiledRegistry::compile(registry).unwrap();
+		let overrides = Arc::new(VerbosityOverrides::new());
+		let executor = CompositionExecutor::new(Arc::new(compiled), Arc::new(MockToolInvoker::new()))
+			.with_verbosity_overrides(overrides.clone());
+
+		assert_eq!(
+			executor.verbosity_for("research_pipeline"),
+			CompositionVerbosity::Off
+		);
+		overrides
+			.set(
+				"research_pipeline",
+				CompositionVerbosity::Steps,
+				std::time::Duration::from_secs(60),
+			)
+			.unwrap();
+		assert_eq!(
+			executor.verbosity_for("research_pipeline"),
+			CompositionVerbosity::Steps
+		);
+		assert_eq!(executor.verbosity_for("other"), CompositionVerbosity::Off);
+	}
+
+	#[tokio::test]
+	async fn test_jsonpath_errors_name_field_and_location() {
+		let input = serde_json::from_value(serde_json::json!({"construct": {"fields": {
+			"query": {"input": {"path": "$.[bad"}}
+		}}}))
+		.unwrap();
+		let composition = ToolDefinition::composition(
+			"lookup",
+			PatternSpec::Pipeline(PipelineSpec {
+				steps: vec![PipelineStep {
+					id: "search".to_string(),
+					operation: StepOperation::Tool(ToolCall {
+						name: "echo".to_string(),
+					}),
+					input: Some(input),
+					session_set: None,
+					retry: None,
+					cache: None,
+				}],
+			}),
+		);
+		let compiled =
+			CompiledRegistry::compile(Registry::with_tool_definitions(vec![composition])).unwrap();
+		let executor = CompositionExecutor::new(Arc::new(compiled), Arc::new(MockToolInvoker::new()));
+
+		let err = executor
+			.execute("lookup", serde_json::json!({}))
+			.await
+			.unwrap_err();
+		let ExecutionError::JsonPathError {
+			path,
+			field,
+			location,
+			..
+		} = &err
+		else {
+			panic!("expected a JSONPath error, got {err}");
+		};
+		assert_eq!(path, "$.[bad");
+		assert_eq!(field.as_deref(), Some("input.query"));
+		assert_eq!(location.as_deref(), Some("composition lookup, step search"));
+		let message = err.to_string();
+		assert!(message.starts_with("JSONPath evaluation failed for '$.[bad' in composition lookup"));
+		assert!(message.contains("step search, field input.query: "));
+	}
+
 	#[tokio::test]
 	async fn test_execute_nonexistent_composition() {
 		let registry = Registry::new();
@@ -427,4 +1788,58 @@ mod tests {
 			),
 		}
 	}
+
+	#[tokio::test]
+	async fn test_execute_resilience_shorthand() {
+		let registry: Registry = serde_json::from_value(serde_json::json!({
+			"tools": [
+				{
+					"name": "search",
+					"spec": {"pipeline": {"steps": [
+						{"id": "fetch", "operation": {"tool": {"name": "search_api"}}}
+					]}},
+					"resilience": {"retries": 2, "backoff": {"fixed": {"delayMs": 1}}}
+				},
+				{
+					"name": "report",
+					"spec": {"pipeline": {"steps": [
+						{"id": "render", "operation": {"tool": {"name": "slow_api"}}}
+					]}},
+					"resilience": {"timeoutMs": 20, "retries": 1}
+				}
+			]
+		}))
+		.unwrap();
+		let compiled = Arc::new(CompiledRegistry::compile(registry).unwrap());
+		let invoker = Arc::new(
+			MockToolInvoker::new()
+				.with_script(
+					"search_api",
+					[
+						Err("upstream unavailable".to_string()),
+						Err("upstream unavailable".to_string()),
+					],
+				)
+				.with_response("search_api", serde_json::json!({"hits": 3}))
+				.with_response("slow_api", serde_json::json!({}))
+				.with_latency("slow_api", std::time::Duration::from_secs(5)),
+		);
+		let executor = CompositionExecutor::new(compiled, invoker.clone());
+
+		// Two failed attempts are retried
+		let result = executor
+			.execute("search", serde_json::json!({"q": "rust"}))
+			.await
+			.unwrap();
+		assert_eq!(result["hits"], 3);
+		invoker.assert_called("search_api", 3);
+
+		// Each attempt times out on its own
+		let err = executor
+			.execute("report", serde_json::json!({}))
+			.await
+			.unwrap_err();
+		assert!(matches!(err, ExecutionError::Timeout(20)), "{err:?}");
+		invoker.assert_called("slow_api", 2);
+	}
 }
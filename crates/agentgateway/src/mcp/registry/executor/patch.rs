@@ -0,0 +1,390 @@
+// Patch step executor
+//
+// Applies an RFC 6902 JSON Patch or an RFC 7386 merge patch to the step input. Placeholders
+// in the patch are filled from the spec's vars, evaluated against the unpatched input.
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+
+use serde_json::{Map, Value};
+
+use super::{ExecutionError, path_cache};
+use crate::mcp::registry::patterns::{JsonPatchOp, JsonPatchOperation, PatchSpec};
+use crate::mcp::registry::validation::escape_pointer_token;
+
+/// Executor for patch steps
+pub struct PatchExecutor;
+
+impl PatchExecutor {
+	/// Apply a patch to the step input
+	pub fn execute(step_id: &str, spec: &PatchSpec, input: Value) -> Result<Value, ExecutionError> {
+		let failed = |message: String| ExecutionError::PatchFailed {
+			step: step_id.to_string(),
+			message,
+		};
+		let vars = Self::resolve_vars(&spec.vars, &input)?;
+		match (&spec.json_patch, &spec.merge_patch) {
+			(Some(ops), None) => {
+				let mut doc = input;
+				for (i, op) in ops.iter().enumerate() {
+					let op = fill_operation(op, &vars);
+					apply_operation(&mut doc, &op).map_err(|e| failed(format!("operation {i}: {e}")))?;
+				}
+				Ok(doc)
+			},
+			(None, Some(patch)) => {
+				let mut doc = input;
+				merge(&mut doc, &fill(patch, &vars));
+				Ok(doc)
+			},
+			_ => Err(failed(
+				"exactly one of jsonPatch and mergePatch must be set".to_string(),
+			)),
+		}
+	}
+
+	fn resolve_vars(
+		vars: &HashMap<String, String>,
+		input: &Value,
+	) -> Result<HashMap<String, Value>, ExecutionError> {
+		vars
+			.iter()
+			.map(|(name, path)| {
+				let value = path_cache::parse(path)?
+					.query(input)
+					.first()
+					.cloned()
+					.unwrap_or(Value::Null);
+				Ok((name.clone(), value))
+			})
+			.collect()
+	}
+}
+
+fn fill_operation(op: &JsonPatchOperation, vars: &HashMap<String, Value>) -> JsonPatchOperation {
+	JsonPatchOperation {
+		op: op.op,
+		path: interpolate(&op.path, vars, escape_pointer_token),
+		from: op
+			.from
+			.as_deref()
+			.map(|from| interpolate(from, vars, escape_pointer_token)),
+		value: op.value.as_ref().map(|value| fill(value, vars)),
+	}
+}
+
+/// Fill placeholders in every string of a patch value
+fn fill(value: &Value, vars: &HashMap<String, Value>) -> Value {
+	match value {
+		Value::String(s) => match s
+			.strip_prefix('{')
+			.and_then(|s| s.strip_suffix('}'))
+			.and_then(|name| vars.get(name))
+		{
+			Some(whole) => whole.clone(),
+			None => Value::String(interpolate(s, vars, Cow::Borrowed)),
+		},
+		Value::Array(items) => Value::Array(items.iter().map(|v| fill(v, vars)).collect()),
+		Value::Object(map) => Value::Object(
+			map
+				.iter()
+				.map(|(k, v)| (k.clone(), fill(v, vars)))
+				.collect(),
+		),
+		other => other.clone(),
+	}
+}
+
+/// Replace `{name}` placeholders with the variables' text passed through `escape`, in a
+/// single pass so values containing braces are left alone
+fn interpolate(
+	template: &str,
+	vars: &HashMap<String, Value>,
+	escape: impl Fn(&str) -> Cow<'_, str>,
+) -> String {
+	let mut out = String::with_capacity(template.len());
+	let mut rest = template;
+	while let Some(start) = rest.find('{') {
+		out.push_str(&rest[..start]);
+		let tail = &rest[start..];
+		match tail
+			.find('}')
+			.and_then(|end| Some((end, vars.get(&tail[1..end])?)))
+		{
+			Some((end, value)) => {
+				match value {
+					Value::String(s) => out.push_str(&escape(s)),
+					other => out.push_str(&escape(&other.to_string())),
+				}
+				rest = &tail[end + 1..];
+			},
+			None => {
+				out.push('{');
+				rest = &tail[1..];
+			},
+		}
+	}
+	out.push_str(rest);
+	out
+}
+
+/// Apply a single RFC 6902 operation
+fn apply_operation(doc: &mut Value, op: &JsonPatchOperation) -> Result<(), String> {
+	let value = || {
+		op.value
+			.clone()
+			.ok_or_else(|| format!("'{}' needs a value", op_name(op.op)))
+	};
+	let from = || {
+		op.from
+			.as_deref()
+			.ok_or_else(|| format!("'{}' needs a from pointer", op_name(op.op)))
+	};
+	match op.op {
+		JsonPatchOp::Add => add(doc, &op.path, value()?),
+		JsonPatchOp::Remove => remove(doc, &op.path).map(drop),
+		JsonPatchOp::Replace => {
+			let target = doc
+				.pointer_mut(&op.path)
+				.ok_or_else(|| format!("path '{}' does not exist", op.path))?;
+			*target = value()?;
+			Ok(())
+		},
+		JsonPatchOp::Move => {
+			let from = from()?;
+			if op.path != from && op.path.starts_with(&format!("{from}/")) {
+				return Err(format!(
+					"cannot move '{from}' into its own child '{}'",
+					op.path
+				));
+			}
+			let moved = remove(doc, from)?;
+			add(doc, &op.path, moved)
+		},
+		JsonPatchOp::Copy => {
+			let from = from()?;
+			let copied = doc
+				.pointer(from)
+				.cloned()
+				.ok_or_else(|| format!("path '{from}' does not exist"))?;
+			add(doc, &op.path, copied)
+		},
+		JsonPatchOp::Test => {
+			let expected = value()?;
+			match doc.pointer(&op.path) {
+				Some(actual) if *actual == expected => Ok(()),
+				Some(actual) => Err(format!(
+					"test failed: '{}' is {actual}, expected {expected}",
+					op.path
+				)),
+				None => Err(format!("test failed: path '{}' does not exist", op.path)),
+			}
+		},
+	}
+}
+
+fn op_name(op: JsonPatchOp) -> &'static str {
+	match op {
+		JsonPatchOp::Add => "add",
+		JsonPatchOp::Remove => "remove",
+		JsonPatchOp::Replace => "replace",
+		JsonPatchOp::Move => "move",
+		JsonPatchOp::Copy => "copy",
+		JsonPatchOp::Test => "test",
+	}
+}
+
+/// Split a JSON Pointer into its parent pointer and unescaped last token
+fn split_pointer(path: &str) -> Result<(&str, String), String> {
+	let (parent, last) = path
+		.rsplit_once('/')
+		.ok_or_else(|| format!("'{path}' is not a JSON Pointer"))?;
+	Ok((parent, last.replace("~1", "/").replace("~0", "~")))
+}
+
+fn add(doc: &mut Value, path: &str, value: Value) -> Result<(), String> {
+	if path.is_empty() {
+		*doc = value;
+		return Ok(());
+	}
+	let (parent, key) = split_pointer(path)?;
+	match doc.pointer_mut(parent) {
+		Some(Value::Object(map)) => {
+			map.insert(key, value);
+			Ok(())
+		},
+		Some(Value::Array(items)) => {
+			let index = if key == "-" {
+				items.len()
+			} else {
+				array_index(&key, items.len() + 1)?
+			};
+			items.insert(index, value);
+			Ok(())
+		},
+		Some(_) => Err(format!("parent of '{path}' is not an object or array")),
+		None => Err(format!("parent of '{path}' does not exist")),
+	}
+}
+
+fn remove(doc: &mut Value, path: &str) -> Result<Value, String> {
+	let (parent, key) = split_pointer(path)?;
+	let removed = match doc.pointer_mut(parent) {
+		Some(Value::Object(map)) => map.remove(&key),
+		Some(Value::Array(items)) => {
+			let index = array_index(&key, items.len())?;
+			Some(items.remove(index))
+		},
+		_ => None,
+	};
+	removed.ok_or_else(|| format!("path '{path}' does not exist"))
+}
+
+/// Parse an array index token, which must be below `bound`
+fn array_index(token: &str, bound: usize) -> Result<usize, String> {
+	let index = token
+		.parse::<usize>()
+		.ok()
+		.filter(|_| token == "0" || !token.starts_with('0'))
+		.ok_or_else(|| format!("'{token}' is not an array index"))?;
+	if index >= bound {
+		return Err(format!("array index {index} is out of bounds"));
+	}
+	Ok(index)
+}
+
+/// Apply an RFC 7386 merge patch
+fn merge(target: &mut Value, patch: &Value) {
+	let Value::Object(patch) = patch else {
+		*target = patch.clone();
+		return;
+	};
+	if !target.is_object() {
+		*target = Value::Object(Map::new());
+	}
+	let Value::Object(map) = target else {
+		unreachable!()
+	};
+	for (key, value) in patch {
+		if value.is_null() {
+			map.remove(key);
+		} else {
+			merge(map.entry(key.clone()).or_insert(Value::Null), value);
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use serde_json::json;
+
+	use super::*;
+
+	fn spec(json: Value) -> PatchSpec {
+		serde_json::from_value(json).unwrap()
+	}
+
+	#[test]
+	fn test_json_patch_operations() {
+		let input = json!({"a": {"b": 1, "c": [1, 2]}, "d": "x"});
+		let patch = spec(json!({"jsonPatch": [
+			{"op": "test", "path": "/d", "value": "x"},
+			{"op": "add", "path": "/a/c/-", "value": 3},
+			{"op": "add", "path": "/a/c/0", "value": 0},
+			{"op": "replace", "path": "/a/b", "value": null},
+			{"op": "copy", "from": "/a/c", "path": "/e"},
+			{"op": "move", "from": "/d", "path": "/a/d"},
+			{"op": "remove", "path": "/a/c/1"}
+		]}));
+		let output = PatchExecutor::execute("edit", &patch, input).unwrap();
+		assert_eq!(
+			output,
+			json!({"a": {"b": null, "c": [0, 2, 3], "d": "x"}, "e": [0, 1, 2, 3]})
+		);
+
+		let failing = spec(json!({"jsonPatch": [
+			{"op": "remove", "path": "/a"},
+			{"op": "test", "path": "/d", "value": "y"}
+		]}));
+		let err = PatchExecutor::execute("edit", &failing, json!({"a": 1, "d": "x"})).unwrap_err();
+		assert!(
+			matches!(&err, ExecutionError::PatchFailed { step, message }
+				if step == "edit" && message.starts_with("operation 1: test failed")),
+			"{err}"
+		);
+		assert!(!err.is_retryable());
+
+		let into_child = spec(json!({"jsonPatch": [{"op": "move", "from": "/a", "path": "/a/b"}]}));
+		assert!(PatchExecutor::execute("edit", &into_child, json!({"a": {}})).is_err());
+	}
+
+	#[test]
+	fn test_merge_patch() {
+		let input = json!({"title": "Hello", "author": {"name": "A", "email": "a@x"}, "tags": ["x"]});
+		let patch = spec(json!({"mergePatch": {
+			"title": "Bye",
+			"author": {"email": null},
+			"tags": ["y"],
+			"meta": {"draft": true}
+		}}));
+		let output = PatchExecutor::execute("edit", &patch, input).unwrap();
+		assert_eq!(
+			output,
+			json!({"title": "Bye", "author": {"name": "A"}, "tags": ["y"], "meta": {"draft": true}})
+		);
+
+		let both = spec(json!({"jsonPatch": [], "mergePatch": {}}));
+		assert!(PatchExecutor::execute("edit", &both, json!({})).is_err());
+	}
+
+	#[test]
+	fn test_placeholders_filled_from_input() {
+		let input = json!({"user": {"login": "octo", "id": 7}, "items": {"octo": {"seen": false}}});
+		let patch = spec(json!({
+			"jsonPatch": [
+				{"op": "replace", "path": "/items/{login}/seen", "value": true},
+				{"op": "add", "path": "/owner", "value": {"id": "{id}", "label": "user {login} #{id}"}}
+			],
+			"vars": {"login": "$.user.login", "id": "$.user.id"}
+		}));
+		let output = PatchExecutor::execute("edit", &patch, input).unwrap();
+		assert_eq!(output["items"]["octo"]["seen"], json!(true));
+		assert_eq!(output["owner"], json!({"id": 7, "label": "user octo #7"}));
+
+		let merge = spec(json!({
+			"mergePatch": {"assignee": "{login}", "note": "{unknown}"},
+			"vars": {"login": "$.user.login"}
+		}));
+		let output =
+			PatchExecutor::execute("edit", &merge, json!({"user": {"login": "octo"}})).unwrap();
+		assert_eq!(output["assignee"], json!("octo"));
+		assert_eq!(output["note"], json!("{unknown}"));
+	}
+
+	#[test]
+	fn test_placeholders_in_pointers_are_escaped() {
+		let input = json!({
+			"key": "a/b~c",
+			"items": {"a/b~c": {"seen": false}, "a": {"b": {"seen": false}}}
+		});
+		let patch = spec(json!({
+			"jsonPatch": [
+				{"op": "replace", "path": "/items/{key}/seen", "value": true},
+				{"op": "copy", "from": "/items/{key}", "path": "/copied"}
+			],
+			"vars": {"key": "$.key"}
+		}));
+		let output = PatchExecutor::execute("edit", &patch, input).unwrap();
+		assert_eq!(output["items"]["a/b~c"]["seen"], json!(true));
+		assert_eq!(output["items"]["a"]["b"]["seen"], json!(false));
+		assert_eq!(output["copied"], json!({"seen": true}));
+
+		// Values outside pointers are filled in as they are
+		let merge = spec(json!({
+			"mergePatch": {"note": "key {key}"},
+			"vars": {"key": "$.key"}
+		}));
+		let output = PatchExecutor::execute("edit", &merge, json!({"key": "a/b~c"})).unwrap();
+		assert_eq!(output["note"], json!("key a/b~c"));
+	}
+}
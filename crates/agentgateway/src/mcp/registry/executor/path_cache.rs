@@ -0,0 +1,51 @@
+// Process-wide cache of parsed JSONPath expressions
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use once_cell::sync::Lazy;
+use parking_lot::RwLock;
+use serde_json_path::JsonPath;
+
+use super::ExecutionError;
+
+/// Maximum number of distinct expressions kept; the cache is reset once it fills up
+const MAX_ENTRIES: usize = 1024;
+
+static CACHE: Lazy<RwLock<HashMap<String, Arc<JsonPath>>>> = Lazy::new(Default::default);
+
+/// Parse a JSONPath expression, reusing the parsed form across executions.
+///
+/// Pattern specs keep their paths as strings, so without this every execution of a
+/// filter, binding or schema map re-parses the same expressions.
+pub(crate) fn parse(path: &str) -> Result<Arc<JsonPath>, ExecutionError> {
+	if let Some(parsed) = CACHE.read().get(path) {
+		return Ok(parsed.clone());
+	}
+
+	let parsed =
+		Arc::new(JsonPath::parse(path).map_err(|e| ExecutionError::jsonpath(path, e.to_string()))?);
+	let mut cache = CACHE.write();
+	if cache.len() >= MAX_ENTRIES {
+		cache.clear();
+	}
+	cache.insert(path.to_string(), parsed.clone());
+	Ok(parsed)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_parse_reuses_expression() {
+		let first = parse("$.items[*].id").unwrap();
+		let second = parse("$.items[*].id").unwrap();
+		assert!(Arc::ptr_eq(&first, &second));
+
+		assert!(matches!(
+			parse("$.[invalid"),
+			Err(ExecutionError::JsonPathError { .. })
+		));
+	}
+}
@@ -1,11 +1,23 @@
 // Pipeline pattern executor
 
+use std::collections::HashMap;
+use std::time::Instant;
+
 use serde_json::Value;
-use serde_json_path::JsonPath;
+use tracing::{debug, info, warn};
 
 use super::context::ExecutionContext;
-use super::{CompositionExecutor, ExecutionError};
-use crate::mcp::registry::patterns::{DataBinding, PipelineSpec, StepOperation};
+use super::retry::with_step_retry;
+use super::time_travel::ResumePoint;
+use super::verbosity::CompositionVerbosity;
+use super::{
+	CacheExecutor, CompositionExecutor, ExecutionError, FilterExecutor, PatchExecutor,
+	SampleExecutor, SchemaMapExecutor, ScriptExecutor, path_cache,
+};
+use crate::mcp::registry::patterns::{
+	AssertSeverity, AssertSpec, CompositionCall, DataBinding, PipelineSpec, PipelineStep,
+	StepOperation,
+};
 
 /// Executor for pipeline patterns
 pub struct PipelineExecutor;
@@ -18,64 +30,285 @@ impl PipelineExecutor {
 		ctx: &ExecutionContext,
 		executor: &CompositionExecutor,
 	) -> Result<Value, ExecutionError> {
+		Self::execute_steps(&spec.steps, 0, input.clone(), &input, ctx, executor).await
+	}
+
+	/// Execute a pipeline from a step, taking the outputs of the steps before it as given
+	pub async fn resume(
+		spec: &PipelineSpec,
+		input: Value,
+		resume: &ResumePoint,
+		ctx: &ExecutionContext,
+		executor: &CompositionExecutor,
+	) -> Result<Value, ExecutionError> {
+		let from = spec
+			.steps
+			.iter()
+			.position(|step| step.id == resume.from_step)
+			.ok_or_else(|| {
+				ExecutionError::InvalidInput(format!("no pipeline step '{}'", resume.from_step))
+			})?;
 		let mut current_result = input.clone();
+		for step in &spec.steps[..from] {
+			let output = resume
+				.completed
+				.iter()
+				.find(|s| s.step_id == step.id)
+				.and_then(|s| s.output.clone())
+				.ok_or_else(|| {
+					ExecutionError::InvalidInput(format!("step '{}' has no output to resume from", step.id))
+				})?;
+			ctx.store_step_result(&step.id, output.clone()).await;
+			current_result = output;
+		}
+		Self::execute_steps(&spec.steps, from, current_result, &input, ctx, executor).await
+	}
+
+	/// Execute the steps from index `from` in order, starting from `current_result`
+	async fn execute_steps(
+		steps: &[PipelineStep],
+		from: usize,
+		mut current_result: Value,
+		input: &Value,
+		ctx: &ExecutionContext,
+		executor: &CompositionExecutor,
+	) -> Result<Value, ExecutionError> {
+		for (index, step) in steps.iter().enumerate().skip(from) {
+			ctx.report_progress(index, steps.len(), &step.id).await;
 
-		for step in &spec.steps {
 			// Resolve input for this step
+			let step_ctx = ctx.for_step(&step.id);
 			let step_input = if let Some(ref binding) = step.input {
-				Self::resolve_binding(binding, &input, ctx).await?
+				Self::resolve_binding(binding, input, &step_ctx)
+					.await
+					.map_err(|e| e.at(|| step_ctx.location()))?
 			} else {
 				// Default: use previous step's output (or composition input for first step)
 				current_result.clone()
 			};
 
 			// Execute the step operation
-			let result = match &step.operation {
-				StepOperation::Tool(tc) => executor.execute_tool(&tc.name, step_input, ctx).await?,
-				StepOperation::Pattern(pattern) => {
-					let child_ctx = ctx.child(step_input.clone());
-					executor
-						.execute_pattern(pattern, step_input, &child_ctx)
-						.await?
-				},
-			};
+			let started = Instant::now();
+			let result = with_step_retry(step.retry.as_ref(), &step.id, || async {
+				match &step.cache {
+					Some(cache) => {
+						CacheExecutor::execute_step(
+							cache,
+							&step.operation,
+							step_input.clone(),
+							&step_ctx,
+							executor,
+						)
+						.await
+					},
+					None => {
+						Self::execute_operation(
+							&step.id,
+							&step.operation,
+							step_input.clone(),
+							&step_ctx,
+							executor,
+						)
+						.await
+					},
+				}
+			})
+			.await
+			.map_err(|e| e.at(|| step_ctx.location()));
+			let elapsed = started.elapsed();
+			ctx.record_step_timing(&step.id, elapsed, result.is_ok());
+			ctx.capture_step(&step.id, &step_input, &result, elapsed);
+			if ctx.verbosity >= CompositionVerbosity::Steps {
+				info!(
+					target: "virtual_tools",
+					step = %step.id,
+					duration_ms = elapsed.as_millis() as u64,
+					ok = result.is_ok(),
+					"composition step completed"
+				);
+			}
+			let result = result?;
+			ctx.journal_step(&step.id, &result).await;
 
 			// Store result for potential reference by later steps
 			ctx.store_step_result(&step.id, result.clone()).await;
+			if let Some(ref set) = step.session_set {
+				let value = Self::apply_jsonpath(&set.path, &result)
+					.map_err(|e| e.in_field("sessionSet").at(|| step_ctx.location()))?;
+				ctx.session.set(&set.key, value).await?;
+			}
 			current_result = result;
 		}
 
 		Ok(current_result)
 	}
 
+	/// Execute a step's operation once
+	pub(super) async fn execute_operation(
+		step_id: &str,
+		operation: &StepOperation,
+		input: Value,
+		ctx: &ExecutionContext,
+		executor: &CompositionExecutor,
+	) -> Result<Value, ExecutionError> {
+		match operation {
+			StepOperation::Tool(tc) => executor.execute_tool(&tc.name, input, ctx).await,
+			StepOperation::Pattern(pattern) => {
+				let child_ctx = ctx.child(input.clone());
+				executor.execute_pattern(pattern, input, &child_ctx).await
+			},
+			StepOperation::Composition(call) => Self::call_composition(call, input, ctx, executor).await,
+			StepOperation::Assert(assert) => Self::check_assertion(step_id, assert, input, ctx),
+			StepOperation::Script(script) => ScriptExecutor::execute(step_id, script, input).await,
+			StepOperation::Sample(sample) => SampleExecutor::execute(sample, input, ctx).await,
+			StepOperation::Patch(patch) => PatchExecutor::execute(step_id, patch, input),
+			StepOperation::Constant(value) => {
+				ctx.charge(value)?;
+				Ok(value.clone())
+			},
+		}
+	}
+
+	/// Run another composition, with its arguments and result remapped as the call specifies
+	async fn call_composition(
+		call: &CompositionCall,
+		input: Value,
+		ctx: &ExecutionContext,
+		executor: &CompositionExecutor,
+	) -> Result<Value, ExecutionError> {
+		let args = match &call.input {
+			Some(mappings) => {
+				SchemaMapExecutor::map_fields(mappings, &input).map_err(|e| e.in_field("input"))?
+			},
+			None => input,
+		};
+		let result = executor.execute_tool(&call.name, args, ctx).await?;
+		match &call.output {
+			Some(mappings) => {
+				SchemaMapExecutor::map_fields(mappings, &result).map_err(|e| e.in_field("output"))
+			},
+			None => Ok(result),
+		}
+	}
+
+	/// Pass `value` through if the assertion holds; otherwise fail, or warn and continue
+	fn check_assertion(
+		step_id: &str,
+		spec: &AssertSpec,
+		value: Value,
+		ctx: &ExecutionContext,
+	) -> Result<Value, ExecutionError> {
+		let predicate = &spec.predicate;
+		let nodes = path_cache::parse(&predicate.field)
+			.map_err(|e| e.in_field("assert.predicate"))?
+			.query(&value);
+		if FilterExecutor::evaluate_condition(&predicate.op, nodes.first(), &predicate.value)? {
+			return Ok(value);
+		}
+
+		let error = ExecutionError::AssertionFailed {
+			step: step_id.to_string(),
+			message: spec.message.clone().unwrap_or_else(|| {
+				format!(
+					"expected {} {} {}",
+					predicate.field,
+					predicate.op,
+					predicate.value.to_json_value()
+				)
+			}),
+		};
+		match spec.severity {
+			AssertSeverity::Fail => Err(error),
+			AssertSeverity::Warn => {
+				warn!(target: "virtual_tools", step = step_id, "{error}");
+				ctx.record_error(&error);
+				Ok(value)
+			},
+		}
+	}
+
 	/// Resolve a data binding to a value
 	async fn resolve_binding(
 		binding: &DataBinding,
 		input: &Value,
 		ctx: &ExecutionContext,
+	) -> Result<Value, ExecutionError> {
+		Self::resolve_field(binding, "input", input, ctx).await
+	}
+
+	/// Resolve the binding of `field` (e.g. `input.query`) to a value
+	async fn resolve_field(
+		binding: &DataBinding,
+		field: &str,
+		input: &Value,
+		ctx: &ExecutionContext,
 	) -> Result<Value, ExecutionError> {
 		match binding {
-			DataBinding::Input(ib) => Self::apply_jsonpath(&ib.path, input),
+			DataBinding::Input(ib) => Self::bind_path(&ib.path, input, field, ctx),
 			DataBinding::Step(sb) => {
 				let step_result = ctx
 					.get_step_result(&sb.step_id)
 					.await
 					.ok_or_else(|| ExecutionError::InvalidInput(format!("step {} not found", sb.step_id)))?;
-				Self::apply_jsonpath(&sb.path, &step_result)
+				Self::bind_path(&sb.path, &step_result, field, ctx)
 			},
 			DataBinding::Constant(value) => Ok(value.clone()),
+			DataBinding::Session(sb) => {
+				let value = ctx.session.get(&sb.key).await.unwrap_or(Value::Null);
+				Self::bind_path(&sb.path, &value, field, ctx)
+			},
 			DataBinding::Construct(cb) => {
 				// Build an object by resolving each field's binding
 				let mut obj = serde_json::Map::new();
 				for (field_name, field_binding) in &cb.fields {
-					let field_value = Box::pin(Self::resolve_binding(field_binding, input, ctx)).await?;
+					let nested = format!("{field}.{field_name}");
+					let field_value =
+						Box::pin(Self::resolve_field(field_binding, &nested, input, ctx)).await?;
 					obj.insert(field_name.clone(), field_value);
 				}
 				Ok(Value::Object(obj))
 			},
+			DataBinding::Template(tb) => {
+				let mut values = HashMap::new();
+				for (name, var_binding) in &tb.vars {
+					let nested = format!("{field}.{name}");
+					let value = Box::pin(Self::resolve_field(var_binding, &nested, input, ctx)).await?;
+					values.insert(name.as_str(), value);
+				}
+				Ok(Value::String(tb.render(&values)))
+			},
 		}
 	}
 
+	/// Apply the JSONPath a binding of `field` reads, logging paths that match nothing
+	fn bind_path(
+		path: &str,
+		value: &Value,
+		field: &str,
+		ctx: &ExecutionContext,
+	) -> Result<Value, ExecutionError> {
+		if path == "$" {
+			return Ok(value.clone());
+		}
+		let nodes = path_cache::parse(path)
+			.map_err(|e| e.in_field(field))?
+			.query(value);
+		if nodes.is_empty() {
+			debug!(
+				target: "virtual_tools",
+				location = %ctx.location(),
+				field,
+				path,
+				"JSONPath matched nothing; binding null"
+			);
+		}
+		Ok(match nodes.len() {
+			0 => Value::Null,
+			1 => nodes.first().cloned().unwrap_or_default(),
+			_ => Value::Array(nodes.iter().map(|v| (*v).clone()).collect()),
+		})
+	}
+
 	/// Apply a JSONPath to extract a value
 	fn apply_jsonpath(path: &str, value: &Value) -> Result<Value, ExecutionError> {
 		// Handle root path specially
@@ -83,8 +316,7 @@ impl PipelineExecutor {
 			return Ok(value.clone());
 		}
 
-		let jsonpath = JsonPath::parse(path)
-			.map_err(|e| ExecutionError::JsonPathError(format!("{}: {}", path, e)))?;
+		let jsonpath = path_cache::parse(path)?;
 
 		let nodes = jsonpath.query(value);
 		let results: Vec<_> = nodes.iter().map(|v| (*v).clone()).collect();
@@ -102,7 +334,10 @@ mod tests {
 	use super::*;
 	use crate::mcp::registry::CompiledRegistry;
 	use crate::mcp::registry::executor::MockToolInvoker;
-	use crate::mcp::registry::patterns::{InputBinding, PipelineStep, StepBinding, ToolCall};
+	use crate::mcp::registry::patterns::{
+		FieldPredicate, InputBinding, PipelineStep, PredicateValue, SessionBinding, StepBinding,
+		StepRetry, ToolCall,
+	};
 	use crate::mcp::registry::types::Registry;
 	use std::sync::Arc;
 
@@ -135,6 +370,9 @@ mod tests {
 						name: "step1_tool".to_string(),
 					}),
 					input: None,
+					session_set: None,
+					retry: None,
+					cache: None,
 				},
 				PipelineStep {
 					id: "s2".to_string(),
@@ -142,6 +380,9 @@ mod tests {
 						name: "step2_tool".to_string(),
 					}),
 					input: None,
+					session_set: None,
+					retry: None,
+					cache: None,
 				},
 			],
 		};
@@ -168,6 +409,9 @@ mod tests {
 				input: Some(DataBinding::Input(InputBinding {
 					path: "$.query".to_string(),
 				})),
+				session_set: None,
+				retry: None,
+				cache: None,
 			}],
 		};
 
@@ -193,6 +437,9 @@ mod tests {
 						name: "search".to_string(),
 					}),
 					input: None,
+					session_set: None,
+					retry: None,
+					cache: None,
 				},
 				PipelineStep {
 					id: "process".to_string(),
@@ -203,6 +450,9 @@ mod tests {
 						step_id: "search".to_string(),
 						path: "$.results".to_string(),
 					})),
+					session_set: None,
+					retry: None,
+					cache: None,
 				},
 			],
 		};
@@ -213,6 +463,304 @@ mod tests {
 		assert_eq!(result.unwrap()["processed"], true);
 	}
 
+	#[tokio::test]
+	async fn test_pipeline_assert_step() {
+		let invoker = MockToolInvoker::new()
+			.with_response("search", serde_json::json!({"results": []}))
+			.with_response("summarize", serde_json::json!({"summary": "nothing"}));
+		let (ctx, executor) = setup_context_and_executor(invoker);
+
+		let spec = |severity: AssertSeverity| PipelineSpec {
+			steps: vec![
+				PipelineStep {
+					id: "search".to_string(),
+					operation: StepOperation::Tool(ToolCall {
+						name: "search".to_string(),
+					}),
+					input: None,
+					session_set: None,
+					retry: None,
+					cache: None,
+				},
+				PipelineStep {
+					id: "has_results".to_string(),
+					operation: StepOperation::Assert(AssertSpec {
+						predicate: FieldPredicate::new("$.results[0]", "ne", PredicateValue::NullValue(true)),
+						message: Some("search returned no results".to_string()),
+						severity,
+					}),
+					input: None,
+					session_set: None,
+					retry: None,
+					cache: None,
+				},
+				PipelineStep {
+					id: "summarize".to_string(),
+					operation: StepOperation::Tool(ToolCall {
+						name: "summarize".to_string(),
+					}),
+					input: None,
+					session_set: None,
+					retry: None,
+					cache: None,
+				},
+			],
+		};
+
+		let result = PipelineExecutor::execute(
+			&spec(AssertSeverity::Fail),
+			serde_json::json!({}),
+			&ctx,
+			&executor,
+		)
+		.await;
+		assert_eq!(
+			result.unwrap_err().to_string(),
+			"assertion failed in step has_results: search returned no results"
+		);
+		assert!(ctx.get_step_result("summarize").await.is_none());
+
+		// A warning is reported and the value continues to the next step
+		let result = PipelineExecutor::execute(
+			&spec(AssertSeverity::Warn),
+			serde_json::json!({}),
+			&ctx,
+			&executor,
+		)
+		.await;
+		assert_eq!(result.unwrap()["summary"], "nothing");
+		let errors = ctx.errors();
+		assert_eq!(errors.len(), 1);
+		assert_eq!(errors[0].step.as_deref(), Some("has_results"));
+	}
+
+	#[tokio::test]
+	async fn test_pipeline_constant_step() {
+		let invoker =
+			MockToolInvoker::new().with_response("search", serde_json::json!({"results": [1, 2]}));
+		let (ctx, executor) = setup_context_and_executor(invoker);
+
+		let step = |id: &str, operation: StepOperation, input: Option<DataBinding>| PipelineStep {
+			id: id.to_string(),
+			operation,
+			input,
+			session_set: None,
+			retry: None,
+			cache: None,
+		};
+		let spec = PipelineSpec {
+			steps: vec![
+				step(
+					"defaults",
+					StepOperation::Constant(serde_json::json!({"limit": 10, "sort": "recent"})),
+					None,
+				),
+				step(
+					"search",
+					StepOperation::Tool(ToolCall {
+						name: "search".to_string(),
+					}),
+					None,
+				),
+				// The step input is ignored
+				step(
+					"status",
+					StepOperation::Constant(serde_json::json!("done")),
+					Some(DataBinding::Step(StepBinding {
+						step_id: "search".to_string(),
+						path: "$".to_string(),
+					})),
+				),
+			],
+		};
+
+		let result = PipelineExecutor::execute(&spec, serde_json::json!({}), &ctx, &executor).await;
+		assert_eq!(result.unwrap(), serde_json::json!("done"));
+		assert_eq!(ctx.get_step_result("defaults").await.unwrap()["limit"], 10);
+		assert_eq!(
+			ctx.get_step_result("search").await.unwrap()["results"][1],
+			2
+		);
+	}
+
+	#[tokio::test]
+	async fn test_pipeline_composition_call() {
+		let registry: Registry = serde_json::from_value(serde_json::json!({
+			"tools": [
+				{
+					"name": "forecast",
+					"spec": {"pipeline": {"steps": [
+						{"id": "fetch", "operation": {"tool": {"name": "get_weather"}}}
+					]}}
+				},
+				{
+					"name": "plan_trip",
+					"spec": {"pipeline": {"steps": [
+						{"id": "weather", "operation": {"composition": {
+							"name": "forecast",
+							"input": {"city": {"path": "$.destination"}},
+							"output": {"temperature": {"path": "$.temp"}}
+						}}}
+					]}}
+				}
+			]
+		}))
+		.unwrap();
+		let compiled = Arc::new(CompiledRegistry::compile(registry).unwrap());
+		let invoker = Arc::new(
+			MockToolInvoker::new()
+				.with_response("get_weather", serde_json::json!({"temp": 12, "wind": 30})),
+		);
+		let executor = CompositionExecutor::new(compiled, invoker.clone());
+
+		let result = executor
+			.execute(
+				"plan_trip",
+				serde_json::json!({"destination": "Paris", "days": 3}),
+			)
+			.await
+			.unwrap();
+		assert_eq!(result, serde_json::json!({"temperature": 12}));
+		assert_eq!(
+			invoker.call_args("get_weather"),
+			vec![serde_json::json!({"city": "Paris"})]
+		);
+	}
+
+	#[tokio::test]
+	async fn test_pipeline_step_retry() {
+		let invoker = MockToolInvoker::new()
+			.with_script(
+				"fetch",
+				[
+					Err("upstream unavailable".to_string()),
+					Err("upstream unavailable".to_string()),
+				],
+			)
+			.with_response("fetch", serde_json::json!({"page": 1}));
+		let invoker = Arc::new(invoker);
+		let compiled = Arc::new(CompiledRegistry::compile(Registry::new()).unwrap());
+		let ctx = ExecutionContext::new(serde_json::json!({}), compiled.clone(), invoker.clone());
+		let executor = CompositionExecutor::new(compiled, invoker.clone());
+
+		let spec = |max_attempts| PipelineSpec {
+			steps: vec![PipelineStep {
+				id: "fetch".to_string(),
+				operation: StepOperation::Tool(ToolCall {
+					name: "fetch".to_string(),
+				}),
+				input: None,
+				session_set: None,
+				retry: Some(StepRetry {
+					max_attempts,
+					backoff_ms: 1,
+				}),
+				cache: None,
+			}],
+		};
+
+		let result = PipelineExecutor::execute(&spec(2), serde_json::json!({}), &ctx, &executor).await;
+		assert!(result.is_err());
+		invoker.assert_called("fetch", 2);
+
+		// The scripted failures are used up; a third failure would have been retried too
+		let result = PipelineExecutor::execute(&spec(3), serde_json::json!({}), &ctx, &executor).await;
+		assert_eq!(result.unwrap()["page"], 1);
+		invoker.assert_called("fetch", 3);
+	}
+
+	#[tokio::test]
+	async fn test_pipeline_session_get_and_set() {
+		let invoker =
+			MockToolInvoker::new().with_response("list_items", serde_json::json!({"next": "page-2"}));
+
+		let (ctx, executor) = setup_context_and_executor(invoker);
+		ctx
+			.session
+			.set("cursor", serde_json::json!({"page": 1}))
+			.await
+			.unwrap();
+
+		let binding = DataBinding::Session(SessionBinding {
+			key: "cursor".to_string(),
+			path: "$.page".to_string(),
+		});
+		let resolved = PipelineExecutor::resolve_binding(&binding, &serde_json::json!({}), &ctx)
+			.await
+			.unwrap();
+		assert_eq!(resolved, serde_json::json!(1));
+
+		let spec = PipelineSpec {
+			steps: vec![PipelineStep {
+				id: "list".to_string(),
+				operation: StepOperation::Tool(ToolCall {
+					name: "list_items".to_string(),
+				}),
+				input: Some(binding),
+				session_set: Some(SessionBinding {
+					key: "cursor".to_string(),
+					path: "$.next".to_string(),
+				}),
+				retry: None,
+				cache: None,
+			}],
+		};
+
+		PipelineExecutor::execute(&spec, serde_json::json!({}), &ctx, &executor)
+			.await
+			.unwrap();
+
+		assert_eq!(
+			ctx.session.get("cursor").await,
+			Some(serde_json::json!("page-2"))
+		);
+	}
+
+	#[tokio::test]
+	async fn test_template_and_construct_bindings() {
+		let (ctx, _executor) = setup_context_and_executor(MockToolInvoker::new());
+		ctx
+			.store_step_result("search", serde_json::json!({"hits": 3, "top": "MCP spec"}))
+			.await;
+
+		// A saga-style output combining a step output, the input and computed text
+		let binding: DataBinding = serde_json::from_value(serde_json::json!({"construct": {"fields": {
+			"summary": {"template": {
+				"template": "{hits} results for '{query}', best: {top} {missing}",
+				"vars": {
+					"hits": {"step": {"stepId": "search", "path": "$.hits"}},
+					"query": {"input": {"path": "$.query"}},
+					"top": {"step": {"stepId": "search", "path": "$.top"}}
+				}
+			}},
+			"raw": {"step": {"stepId": "search", "path": "$"}}
+		}}}))
+		.unwrap();
+		let resolved =
+			PipelineExecutor::resolve_binding(&binding, &serde_json::json!({"query": "mcp"}), &ctx)
+				.await
+				.unwrap();
+		assert_eq!(
+			resolved["summary"],
+			"3 results for 'mcp', best: MCP spec {missing}"
+		);
+		assert_eq!(resolved["raw"]["hits"], 3);
+	}
+
+	#[tokio::test]
+	async fn test_session_binding_missing_key_is_null() {
+		let (ctx, _executor) = setup_context_and_executor(MockToolInvoker::new());
+
+		let binding = DataBinding::Session(SessionBinding {
+			key: "missing".to_string(),
+			path: "$".to_string(),
+		});
+		let resolved = PipelineExecutor::resolve_binding(&binding, &serde_json::json!({}), &ctx)
+			.await
+			.unwrap();
+		assert_eq!(resolved, Value::Null);
+	}
+
 	#[tokio::test]
 	async fn test_apply_jsonpath() {
 		let value = serde_json::json!({
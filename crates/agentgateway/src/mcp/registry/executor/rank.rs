@@ -0,0 +1,309 @@
+// Rank pattern executor
+
+use serde_json::{Value, json};
+
+use super::context::ExecutionContext;
+use super::{CompositionExecutor, ExecutionError, SampleExecutor, path_cache, value_type_name};
+use crate::mcp::registry::patterns::{
+	EmbeddingScorer, LlmScorer, RankScorer, RankSpec, SampleSpec, WeightedField,
+};
+
+/// Executor for rank patterns
+pub struct RankExecutor;
+
+impl RankExecutor {
+	/// Execute a rank pattern
+	pub async fn execute(
+		spec: &RankSpec,
+		input: Value,
+		ctx: &ExecutionContext,
+		executor: &CompositionExecutor,
+	) -> Result<Value, ExecutionError> {
+		let items = match &spec.items_path {
+			Some(path) => first_match(path, &input)
+				.map_err(|e| e.in_field("itemsPath"))?
+				.cloned()
+				.unwrap_or(Value::Null),
+			None => input.clone(),
+		};
+		let Value::Array(items) = items else {
+			return Err(ExecutionError::TypeError {
+				expected: "array".to_string(),
+				actual: value_type_name(&items),
+			});
+		};
+		if items.is_empty() {
+			return Ok(Value::Array(items));
+		}
+
+		let scores = match &spec.scorer {
+			RankScorer::Fields(fields) => Self::field_scores(fields, &items)?,
+			RankScorer::Embedding(embedding) => {
+				Self::embedding_scores(embedding, &input, &items, ctx, executor).await?
+			},
+			RankScorer::Llm(llm) => Self::llm_scores(llm, &input, &items, ctx).await?,
+		};
+
+		let mut ranked: Vec<(f64, Value)> = scores
+			.into_iter()
+			.zip(items)
+			.filter(|(score, _)| spec.min_score.is_none_or(|min| *score >= min))
+			.collect();
+		// Stable, so equal scores keep their input order
+		if spec.ascending {
+			ranked.sort_by(|(a, _), (b, _)| a.total_cmp(b));
+		} else {
+			ranked.sort_by(|(a, _), (b, _)| b.total_cmp(a));
+		}
+		if let Some(limit) = spec.limit {
+			ranked.truncate(limit);
+		}
+
+		Ok(Value::Array(
+			ranked
+				.into_iter()
+				.map(|(score, mut item)| {
+					if let (Some(field), Value::Object(obj)) = (&spec.score_field, &mut item) {
+						obj.insert(field.clone(), json!(score));
+					}
+					item
+				})
+				.collect(),
+		))
+	}
+
+	fn field_scores(fields: &[WeightedField], items: &[Value]) -> Result<Vec<f64>, ExecutionError> {
+		let paths = fields
+			.iter()
+			.map(|f| {
+				let path = path_cache::parse(&f.path).map_err(|e| e.in_field("fields"))?;
+				Ok((path, f.weight))
+			})
+			.collect::<Result<Vec<_>, ExecutionError>>()?;
+		Ok(
+			items
+				.iter()
+				.map(|item| {
+					paths
+						.iter()
+						.map(|(path, weight)| {
+							let value = path.query(item).first().and_then(Value::as_f64);
+							value.unwrap_or(0.0) * weight
+						})
+						.sum()
+				})
+				.collect(),
+		)
+	}
+
+	async fn embedding_scores(
+		scorer: &EmbeddingScorer,
+		input: &Value,
+		items: &[Value],
+		ctx: &ExecutionContext,
+		executor: &CompositionExecutor,
+	) -> Result<Vec<f64>, ExecutionError> {
+		let query = first_match(&scorer.query_path, input)
+			.map_err(|e| e.in_field("queryPath"))?
+			.map(text)
+			.ok_or_else(|| ExecutionError::InvalidInput(format!("no query at {}", scorer.query_path)))?;
+		let content = path_cache::parse(&scorer.content_path).map_err(|e| e.in_field("contentPath"))?;
+		let texts: Vec<String> = std::iter::once(query)
+			.chain(
+				items
+					.iter()
+					.map(|item| content.query(item).first().map(text).unwrap_or_default()),
+			)
+			.collect();
+
+		let response = executor
+			.execute_tool(&scorer.embedder, json!({"input": texts}), ctx)
+			.await?;
+		let vectors = embeddings(&response).filter(|v| v.len() == texts.len());
+		let Some(vectors) = vectors else {
+			return Err(ExecutionError::ToolExecutionFailed(format!(
+				"embedder '{}' did not return {} vectors",
+				scorer.embedder,
+				texts.len()
+			)));
+		};
+		let (query, items) = vectors.split_first().expect("at least the query vector");
+		Ok(items.iter().map(|v| cosine_similarity(query, v)).collect())
+	}
+
+	async fn llm_scores(
+		scorer: &LlmScorer,
+		input: &Value,
+		items: &[Value],
+		ctx: &ExecutionContext,
+	) -> Result<Vec<f64>, ExecutionError> {
+		let mut request = json!({"items": items});
+		if let Some(path) = &scorer.query_path {
+			request["query"] = first_match(path, input)
+				.map_err(|e| e.in_field("queryPath"))?
+				.cloned()
+				.unwrap_or(Value::Null);
+		}
+		let sample = SampleSpec {
+			prompt: format!(
+				"{}\n\nScore each element of `items` from 0 (irrelevant) to 10 (best match). \
+				 Reply with only a JSON array of {} numbers, one per element, in order.",
+				scorer.prompt,
+				items.len()
+			),
+			system_prompt: None,
+			max_tokens: scorer.max_tokens,
+			temperature: Some(0.0),
+			model_hints: scorer.model_hints.clone(),
+			json: true,
+			timeout_ms: scorer.timeout_ms,
+		};
+		let response = SampleExecutor::execute(&sample, request, ctx).await?;
+		response
+			.as_array()
+			.filter(|scores| scores.len() == items.len())
+			.and_then(|scores| scores.iter().map(Value::as_f64).collect::<Option<Vec<_>>>())
+			.ok_or_else(|| {
+				ExecutionError::ToolExecutionFailed(format!(
+					"LLM scorer did not return {} numeric scores: {response}",
+					items.len()
+				))
+			})
+	}
+}
+
+fn first_match<'a>(path: &str, value: &'a Value) -> Result<Option<&'a Value>, ExecutionError> {
+	Ok(path_cache::parse(path)?.query(value).first())
+}
+
+fn text(value: &Value) -> String {
+	match value {
+		Value::String(s) => s.clone(),
+		other => other.to_string(),
+	}
+}
+
+/// Vectors from an embedder response, in any of the accepted shapes
+fn embeddings(response: &Value) -> Option<Vec<Vec<f64>>> {
+	let vectors: Vec<&Value> = match response {
+		Value::Array(vectors) => vectors.iter().collect(),
+		Value::Object(obj) => match (obj.get("embeddings"), obj.get("data")) {
+			(Some(Value::Array(vectors)), _) => vectors.iter().collect(),
+			(_, Some(Value::Array(data))) => data
+				.iter()
+				.map(|d| d.get("embedding"))
+				.collect::<Option<_>>()?,
+			_ => return None,
+		},
+		_ => return None,
+	};
+	vectors
+		.into_iter()
+		.map(|v| v.as_array()?.iter().map(Value::as_f64).collect())
+		.collect()
+}
+
+fn cosine_similarity(a: &[f64], b: &[f64]) -> f64 {
+	let dot: f64 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+	let norm = |v: &[f64]| v.iter().map(|x| x * x).sum::<f64>().sqrt();
+	let denominator = norm(a) * norm(b);
+	if denominator == 0.0 {
+		0.0
+	} else {
+		dot / denominator
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::sync::Arc;
+
+	use super::*;
+	use crate::mcp::registry::CompiledRegistry;
+	use crate::mcp::registry::executor::MockToolInvoker;
+
+	fn setup(invoker: MockToolInvoker) -> (ExecutionContext, CompositionExecutor) {
+		let registry = Arc::new(CompiledRegistry::empty());
+		let invoker = Arc::new(invoker);
+		let ctx = ExecutionContext::new(json!({}), registry.clone(), invoker.clone());
+		(ctx, CompositionExecutor::new(registry, invoker))
+	}
+
+	fn spec(value: Value) -> RankSpec {
+		serde_json::from_value(value).unwrap()
+	}
+
+	#[tokio::test]
+	async fn test_rank_by_fields() {
+		let (ctx, executor) = setup(MockToolInvoker::new());
+		let spec = spec(json!({
+			"scorer": {"fields": [{"path": "$.stars", "weight": 0.01}, {"path": "$.relevance"}]},
+			"limit": 2,
+			"minScore": 1.0,
+			"scoreField": "_score"
+		}));
+		let input = json!([
+			{"id": "a", "stars": 100, "relevance": 0.5},
+			{"id": "b", "stars": 50, "relevance": 2.0},
+			{"id": "c", "relevance": 0.1},
+			{"id": "d", "stars": "many", "relevance": 1.5}
+		]);
+		let result = RankExecutor::execute(&spec, input, &ctx, &executor)
+			.await
+			.unwrap();
+		assert_eq!(
+			result,
+			json!([
+				{"id": "b", "stars": 50, "relevance": 2.0, "_score": 2.5},
+				{"id": "a", "stars": 100, "relevance": 0.5, "_score": 1.5}
+			])
+		);
+
+		let err = RankExecutor::execute(&spec, json!({"not": "an array"}), &ctx, &executor)
+			.await
+			.unwrap_err();
+		assert!(matches!(err, ExecutionError::TypeError { .. }));
+	}
+
+	#[tokio::test]
+	async fn test_rank_by_embedding_similarity() {
+		let invoker = MockToolInvoker::new().with_response(
+			"embed",
+			json!({"data": [
+				{"embedding": [1.0, 0.0]},
+				{"embedding": [0.0, 1.0]},
+				{"embedding": [0.9, 0.1]}
+			]}),
+		);
+		let (ctx, executor) = setup(invoker);
+		let spec = spec(json!({
+			"itemsPath": "$.results",
+			"scorer": {"embedding": {"embedder": "embed", "queryPath": "$.query", "contentPath": "$.title"}}
+		}));
+		let input = json!({
+			"query": "rust web frameworks",
+			"results": [{"title": "gardening"}, {"title": "axum"}]
+		});
+		let result = RankExecutor::execute(&spec, input, &ctx, &executor)
+			.await
+			.unwrap();
+		assert_eq!(result, json!([{"title": "axum"}, {"title": "gardening"}]));
+	}
+
+	#[tokio::test]
+	async fn test_rank_by_llm() {
+		let (ctx, executor) = setup(MockToolInvoker::new().with_sampling_response("[2, 9, 5]"));
+		let spec = spec(json!({"scorer": {"llm": {"prompt": "Relevance to 'rust'"}}}));
+		let result = RankExecutor::execute(&spec, json!(["go", "rust", "c"]), &ctx, &executor)
+			.await
+			.unwrap();
+		assert_eq!(result, json!(["rust", "c", "go"]));
+
+		// A reply that doesn't score every element fails the step
+		let result = RankExecutor::execute(&spec, json!(["go", "rust"]), &ctx, &executor).await;
+		assert!(matches!(
+			result,
+			Err(ExecutionError::ToolExecutionFailed(_))
+		));
+	}
+}
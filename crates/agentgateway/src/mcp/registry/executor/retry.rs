@@ -0,0 +1,96 @@
+// Inline retries for pipeline steps and scatter-gather targets
+
+use std::future::Future;
+use std::time::Duration;
+
+use serde_json::Value;
+use tracing::warn;
+
+use super::ExecutionError;
+use crate::mcp::registry::patterns::StepRetry;
+
+/// Run `call` until it succeeds, fails with an error a retry wouldn't fix, or `retry` runs
+/// out of attempts
+pub(crate) async fn with_step_retry<F, Fut>(
+	retry: Option<&StepRetry>,
+	step: &str,
+	mut call: F,
+) -> Result<Value, ExecutionError>
+where
+	F: FnMut() -> Fut,
+	Fut: Future<Output = Result<Value, ExecutionError>>,
+{
+	let attempts = retry.map_or(1, |r| r.max_attempts.max(1));
+	let mut attempt = 1;
+	loop {
+		match call().await {
+			Err(e) if e.is_retryable() && attempt < attempts => {
+				warn!(
+					target: "virtual_tools",
+					step,
+					attempt,
+					error = %e,
+					"composition step failed, retrying"
+				);
+				if let Some(retry) = retry
+					&& retry.backoff_ms > 0
+				{
+					tokio::time::sleep(Duration::from_millis(retry.backoff_ms)).await;
+				}
+				attempt += 1;
+			},
+			res => return res,
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::sync::atomic::{AtomicU32, Ordering};
+
+	use super::*;
+
+	#[tokio::test]
+	async fn test_retries_until_success() {
+		let calls = AtomicU32::new(0);
+		let retry = StepRetry {
+			max_attempts: 3,
+			backoff_ms: 0,
+		};
+		let result = with_step_retry(Some(&retry), "fetch", || async {
+			match calls.fetch_add(1, Ordering::SeqCst) {
+				0 => Err(ExecutionError::ToolExecutionFailed("503".to_string())),
+				1 => Err(ExecutionError::Transient("process restarting".to_string())),
+				_ => Ok(Value::Bool(true)),
+			}
+		})
+		.await;
+		assert_eq!(result.unwrap(), Value::Bool(true));
+		assert_eq!(calls.load(Ordering::SeqCst), 3);
+	}
+
+	#[tokio::test]
+	async fn test_gives_up_after_max_attempts_or_deterministic_errors() {
+		let retry = StepRetry {
+			max_attempts: 2,
+			backoff_ms: 0,
+		};
+		let calls = AtomicU32::new(0);
+		let result = with_step_retry(Some(&retry), "fetch", || async {
+			calls.fetch_add(1, Ordering::SeqCst);
+			Err(ExecutionError::Timeout(100))
+		})
+		.await;
+		assert!(matches!(result, Err(ExecutionError::Timeout(100))));
+		assert_eq!(calls.load(Ordering::SeqCst), 2);
+
+		let calls = AtomicU32::new(0);
+		let result = with_step_retry(Some(&retry), "fetch", || async {
+			calls.fetch_add(1, Ordering::SeqCst);
+			Err(ExecutionError::ToolNotFound("fetch".to_string()))
+		})
+		.await;
+		assert!(matches!(result, Err(ExecutionError::ToolNotFound(_))));
+		assert_eq!(calls.load(Ordering::SeqCst), 1);
+	}
+}
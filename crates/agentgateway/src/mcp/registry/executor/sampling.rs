@@ -0,0 +1,161 @@
+// Sample step executor
+//
+// Turns a step's prompt and input into an MCP sampling request and sends it to the connected
+// client through the tool invoker, so compositions can use the client's model for judgment
+// calls without the gateway holding LLM credentials.
+
+use std::time::Duration;
+
+use rmcp::model::{
+	Content, CreateMessageRequestParam, CreateMessageResult, ModelHint, ModelPreferences, Role,
+	SamplingMessage,
+};
+use serde_json::{Value, json};
+
+use super::ExecutionError;
+use super::context::ExecutionContext;
+use crate::mcp::registry::patterns::SampleSpec;
+
+/// Executor for sample steps
+pub struct SampleExecutor;
+
+impl SampleExecutor {
+	/// Ask the client's model about the step input
+	pub async fn execute(
+		spec: &SampleSpec,
+		input: Value,
+		ctx: &ExecutionContext,
+	) -> Result<Value, ExecutionError> {
+		let request = Self::request(spec, &input);
+		let result = tokio::time::timeout(
+			Duration::from_millis(spec.timeout_ms.into()),
+			ctx.tool_invoker.create_message(request),
+		)
+		.await
+		.map_err(|_| ExecutionError::Timeout(spec.timeout_ms))??;
+		Self::output(spec, result)
+	}
+
+	/// Build the sampling request for a step input
+	pub fn request(spec: &SampleSpec, input: &Value) -> CreateMessageRequestParam {
+		let input = serde_json::to_string_pretty(input).unwrap_or_default();
+		let model_preferences = (!spec.model_hints.is_empty()).then(|| ModelPreferences {
+			hints: Some(
+				spec
+					.model_hints
+					.iter()
+					.map(|name| ModelHint {
+						name: Some(name.clone()),
+					})
+					.collect(),
+			),
+			cost_priority: None,
+			speed_priority: None,
+			intelligence_priority: None,
+		});
+		CreateMessageRequestParam {
+			messages: vec![SamplingMessage {
+				role: Role::User,
+				content: Content::text(format!("{}\n\nInput:\n{input}", spec.prompt)),
+			}],
+			model_preferences,
+			system_prompt: spec.system_prompt.clone(),
+			include_context: None,
+			temperature: spec.temperature,
+			max_tokens: spec.max_tokens,
+			stop_sequences: None,
+			metadata: None,
+		}
+	}
+
+	fn output(spec: &SampleSpec, result: CreateMessageResult) -> Result<Value, ExecutionError> {
+		let text = result
+			.message
+			.content
+			.as_text()
+			.map(|t| t.text.as_str())
+			.ok_or_else(|| {
+				ExecutionError::ToolExecutionFailed("sampling response has no text content".to_string())
+			})?;
+		if !spec.json {
+			return Ok(json!({"text": text, "model": result.model}));
+		}
+		// Models often wrap JSON answers in a Markdown code fence
+		let body = text.trim();
+		let body = body
+			.strip_prefix("```json")
+			.or_else(|| body.strip_prefix("```"))
+			.and_then(|b| b.strip_suffix("```"))
+			.unwrap_or(body);
+		serde_json::from_str(body).map_err(|e| {
+			ExecutionError::ToolExecutionFailed(format!("sampling response is not valid JSON: {e}"))
+		})
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::sync::Arc;
+
+	use super::*;
+	use crate::mcp::registry::compiled::CompiledRegistry;
+	use crate::mcp::registry::executor::MockToolInvoker;
+
+	fn spec(json: bool) -> SampleSpec {
+		serde_json::from_value(json!({
+			"prompt": "Return the most relevant result",
+			"modelHints": ["claude"],
+			"json": json,
+			"timeoutMs": 1000
+		}))
+		.unwrap()
+	}
+
+	#[test]
+	fn test_request_includes_prompt_and_input() {
+		let request = SampleExecutor::request(&spec(false), &json!({"hits": [1]}));
+		let text = request.messages[0].content.as_text().unwrap().text.clone();
+		assert!(text.starts_with("Return the most relevant result"));
+		assert!(text.contains("\"hits\""));
+		assert_eq!(request.max_tokens, 1024);
+		let hints = request.model_preferences.unwrap().hints.unwrap();
+		assert_eq!(hints[0].name.as_deref(), Some("claude"));
+	}
+
+	#[tokio::test]
+	async fn test_sample_returns_text_or_json() {
+		let invoker =
+			Arc::new(MockToolInvoker::new().with_sampling_response("```json\n{\"best\": 2}\n```"));
+		let ctx = ExecutionContext::new(
+			json!({}),
+			Arc::new(CompiledRegistry::empty()),
+			invoker.clone(),
+		);
+
+		let result = SampleExecutor::execute(&spec(true), json!([1, 2]), &ctx)
+			.await
+			.unwrap();
+		assert_eq!(result, json!({"best": 2}));
+
+		let result = SampleExecutor::execute(&spec(false), json!([1, 2]), &ctx)
+			.await
+			.unwrap();
+		assert_eq!(result["text"], json!("```json\n{\"best\": 2}\n```"));
+		assert_eq!(result["model"], json!("mock"));
+		assert_eq!(invoker.sampling_requests().len(), 2);
+	}
+
+	#[tokio::test]
+	async fn test_sample_without_client_support() {
+		let ctx = ExecutionContext::new(
+			json!({}),
+			Arc::new(CompiledRegistry::empty()),
+			Arc::new(MockToolInvoker::new()),
+		);
+		let err = SampleExecutor::execute(&spec(false), json!({}), &ctx)
+			.await
+			.unwrap_err();
+		assert!(matches!(err, ExecutionError::SamplingUnavailable(_)));
+		assert!(!err.is_retryable());
+	}
+}
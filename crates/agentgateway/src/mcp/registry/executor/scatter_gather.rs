@@ -3,13 +3,19 @@
 use std::time::Duration;
 
 use futures::future::join_all;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
 use serde_json::Value;
-use serde_json_path::JsonPath;
 use tokio::time::timeout;
+use tracing::debug;
 
 use super::context::ExecutionContext;
-use super::{CompositionExecutor, ExecutionError};
-use crate::mcp::registry::patterns::{AggregationOp, ScatterGatherSpec, ScatterTarget};
+use super::retry::with_step_retry;
+use super::token_budget::apply_token_budget;
+use super::{CompositionExecutor, ExecutionError, path_cache, value_type_name};
+use crate::mcp::registry::patterns::{
+	AggregationOp, ScatterGatherSpec, ScatterOperation, ScatterTarget, TargetSample, TokenBudgetOp,
+};
 
 /// Executor for scatter-gather patterns
 pub struct ScatterGatherExecutor;
@@ -22,10 +28,22 @@ impl ScatterGatherExecutor {
 		ctx: &ExecutionContext,
 		executor: &CompositionExecutor,
 	) -> Result<Value, ExecutionError> {
-		// Create futures for all targets
-		let futures: Vec<_> = spec
-			.targets
-			.iter()
+		// Create futures for all targets, or the sampled ones
+		let targets: Vec<&ScatterTarget> = match &spec.sample {
+			Some(sample) => {
+				let selected = sample_targets(&spec.targets, sample);
+				debug!(
+					target: "virtual_tools",
+					location = %ctx.location(),
+					selected = ?selected,
+					"Sampled scatter-gather targets"
+				);
+				selected.into_iter().map(|i| &spec.targets[i]).collect()
+			},
+			None => spec.targets.iter().collect(),
+		};
+		let futures: Vec<_> = targets
+			.into_iter()
 			.map(|target| Self::execute_target(target, input.clone(), ctx, executor))
 			.collect();
 
@@ -50,6 +68,9 @@ impl ScatterGatherExecutor {
 		if successes.is_empty() {
 			return Err(ExecutionError::AllTargetsFailed);
 		}
+		for e in failures.iter().filter_map(|r| r.as_ref().err()) {
+			ctx.record_error(e);
+		}
 
 		// Extract successful results
 		let values: Vec<Value> = successes.into_iter().map(|r| r.unwrap()).collect();
@@ -65,13 +86,27 @@ impl ScatterGatherExecutor {
 		ctx: &ExecutionContext,
 		executor: &CompositionExecutor,
 	) -> Result<Value, ExecutionError> {
-		match target {
-			ScatterTarget::Tool(name) => executor.execute_tool(name, input, ctx).await,
-			ScatterTarget::Pattern(pattern) => {
-				let child_ctx = ctx.child(input.clone());
-				executor.execute_pattern(pattern, input, &child_ctx).await
-			},
+		let label = match &target.operation {
+			ScatterOperation::Tool(name) => name.as_str(),
+			ScatterOperation::Pattern(pattern) => pattern.pattern_name(),
+		};
+		// Skip a target whose upstream is known to be down rather than waiting out its failure
+		if let ScatterOperation::Tool(name) = &target.operation
+			&& !ctx.tool_invoker.is_available(name)
+		{
+			return Err(ExecutionError::UpstreamUnavailable(name.clone()));
 		}
+		with_step_retry(target.retry.as_ref(), label, || async {
+			let input = input.clone();
+			match &target.operation {
+				ScatterOperation::Tool(name) => executor.execute_tool(name, input, ctx).await,
+				ScatterOperation::Pattern(pattern) => {
+					let child_ctx = ctx.child(input.clone());
+					executor.execute_pattern(pattern, input, &child_ctx).await
+				},
+			}
+		})
+		.await
 	}
 
 	/// Apply aggregation operations to results
@@ -86,6 +121,7 @@ impl ScatterGatherExecutor {
 				AggregationOp::Limit(limit) => Self::limit(&result, limit.count as usize)?,
 				AggregationOp::Concat(_) => result, // Already an array, no change
 				AggregationOp::Merge(_) => Self::merge(&mut values)?,
+				AggregationOp::TokenBudget(budget) => Self::token_budget(&result, budget)?,
 			};
 		}
 
@@ -118,8 +154,7 @@ impl ScatterGatherExecutor {
 			actual: value_type_name(value),
 		})?;
 
-		let jsonpath = JsonPath::parse(field)
-			.map_err(|e| ExecutionError::JsonPathError(format!("{}: {}", field, e)))?;
+		let jsonpath = path_cache::parse(field).map_err(|e| e.in_field("sort"))?;
 
 		let mut items: Vec<_> = arr.to_vec();
 
@@ -143,8 +178,7 @@ impl ScatterGatherExecutor {
 			actual: value_type_name(value),
 		})?;
 
-		let jsonpath = JsonPath::parse(field)
-			.map_err(|e| ExecutionError::JsonPathError(format!("{}: {}", field, e)))?;
+		let jsonpath = path_cache::parse(field).map_err(|e| e.in_field("dedupe"))?;
 
 		let mut seen = std::collections::HashSet::new();
 		let mut result = Vec::new();
@@ -173,6 +207,17 @@ impl ScatterGatherExecutor {
 		Ok(Value::Array(arr.iter().take(count).cloned().collect()))
 	}
 
+	/// Keep the leading items that fit a token budget
+	fn token_budget(value: &Value, op: &TokenBudgetOp) -> Result<Value, ExecutionError> {
+		let arr = value.as_array().ok_or_else(|| ExecutionError::TypeError {
+			expected: "array".to_string(),
+			actual: value_type_name(value),
+		})?;
+
+		let kept = apply_token_budget(arr, op, op.tokenizer.tokenizer())?;
+		Ok(Value::Array(kept))
+	}
+
 	/// Merge objects
 	fn merge(values: &mut Vec<Value>) -> Result<Value, ExecutionError> {
 		let mut result = serde_json::Map::new();
@@ -189,17 +234,35 @@ impl ScatterGatherExecutor {
 	}
 }
 
-/// Get type name for error messages
-fn value_type_name(value: &Value) -> String {
-	match value {
-		Value::Null => "null",
-		Value::Bool(_) => "boolean",
-		Value::Number(_) => "number",
-		Value::String(_) => "string",
-		Value::Array(_) => "array",
-		Value::Object(_) => "object",
-	}
-	.to_string()
+/// Indices of the targets a sampled scatter-gather invokes, in declaration order
+///
+/// The rest are drawn by weighted sampling without replacement (Efraimidis-Spirakis): each
+/// gets the key `u^(1/weight)` for a uniform random `u`, and the largest keys win.
+fn sample_targets(targets: &[ScatterTarget], sample: &TargetSample) -> Vec<usize> {
+	let weight = |i: usize| targets[i].weight.unwrap_or(1.0);
+	let mut by_weight: Vec<usize> = (0..targets.len()).collect();
+	// Stable, so ties keep declaration order
+	by_weight.sort_by(|&a, &b| weight(b).total_cmp(&weight(a)));
+	let (always, rest) = by_weight.split_at((sample.always as usize).min(targets.len()));
+
+	let mut rng = match sample.seed {
+		Some(seed) => StdRng::seed_from_u64(seed),
+		None => StdRng::from_rng(&mut rand::rng()),
+	};
+	let mut keyed: Vec<(f64, usize)> = rest
+		.iter()
+		.filter(|&&i| weight(i) > 0.0)
+		.map(|&i| (rng.random::<f64>().powf(1.0 / weight(i)), i))
+		.collect();
+	keyed.sort_by(|a, b| b.0.total_cmp(&a.0));
+
+	let mut selected: Vec<usize> = always
+		.iter()
+		.copied()
+		.chain(keyed.into_iter().take(sample.draw as usize).map(|(_, i)| i))
+		.collect();
+	selected.sort_unstable();
+	selected
 }
 
 /// Compare two optional JSON values
@@ -226,7 +289,7 @@ mod tests {
 	use super::*;
 	use crate::mcp::registry::CompiledRegistry;
 	use crate::mcp::registry::executor::MockToolInvoker;
-	use crate::mcp::registry::patterns::{AggregationStrategy, DedupeOp, LimitOp, SortOp};
+	use crate::mcp::registry::patterns::{AggregationStrategy, DedupeOp, LimitOp, SortOp, StepRetry};
 	use crate::mcp::registry::types::Registry;
 	use serde_json::json;
 	use std::sync::Arc;
@@ -254,12 +317,13 @@ mod tests {
 
 		let spec = ScatterGatherSpec {
 			targets: vec![
-				ScatterTarget::Tool("search_a".to_string()),
-				ScatterTarget::Tool("search_b".to_string()),
+				ScatterTarget::tool("search_a"),
+				ScatterTarget::tool("search_b"),
 			],
 			aggregation: AggregationStrategy { ops: vec![] },
 			timeout_ms: None,
 			fail_fast: false,
+			sample: None,
 		};
 
 		let result = ScatterGatherExecutor::execute(&spec, json!({}), &ctx, &executor).await;
@@ -270,6 +334,147 @@ mod tests {
 		assert_eq!(arr.as_array().unwrap().len(), 2);
 	}
 
+	#[tokio::test]
+	async fn test_scatter_gather_target_retry() {
+		let invoker = MockToolInvoker::new()
+			.with_script("flaky", [Err("connection reset".to_string())])
+			.with_response("flaky", json!({"source": "flaky"}));
+		let (ctx, executor) = setup_context_and_executor(invoker);
+
+		let spec = ScatterGatherSpec {
+			targets: vec![ScatterTarget::tool("flaky").with_retry(StepRetry {
+				max_attempts: 2,
+				backoff_ms: 0,
+			})],
+			aggregation: AggregationStrategy { ops: vec![] },
+			timeout_ms: None,
+			fail_fast: true,
+			sample: None,
+		};
+
+		let result = ScatterGatherExecutor::execute(&spec, json!({}), &ctx, &executor).await;
+		assert_eq!(result.unwrap(), json!([{"source": "flaky"}]));
+		assert!(ctx.errors().is_empty());
+	}
+
+	#[tokio::test]
+	async fn test_scatter_gather_skips_unavailable_upstreams() {
+		let invoker = Arc::new(
+			MockToolInvoker::new()
+				.with_response("search_a", json!({"source": "a"}))
+				.with_response("search_b", json!({"source": "b"}))
+				.with_unavailable("search_b"),
+		);
+		let compiled = Arc::new(CompiledRegistry::compile(Registry::new()).unwrap());
+		let ctx = ExecutionContext::new(json!({}), compiled.clone(), invoker.clone());
+		let executor = CompositionExecutor::new(compiled, invoker.clone());
+
+		let mut spec = ScatterGatherSpec {
+			targets: vec![
+				ScatterTarget::tool("search_a"),
+				ScatterTarget::tool("search_b").with_retry(StepRetry {
+					max_attempts: 3,
+					backoff_ms: 0,
+				}),
+			],
+			aggregation: AggregationStrategy { ops: vec![] },
+			timeout_ms: None,
+			fail_fast: false,
+			sample: None,
+		};
+
+		let result = ScatterGatherExecutor::execute(&spec, json!({}), &ctx, &executor).await;
+		assert_eq!(result.unwrap(), json!([{"source": "a"}]));
+		invoker.assert_not_called("search_b");
+		assert!(ctx.errors()[0].message.contains("circuit breaker is open"));
+
+		spec.fail_fast = true;
+		let result = ScatterGatherExecutor::execute(&spec, json!({}), &ctx, &executor).await;
+		assert!(matches!(
+			result,
+			Err(ExecutionError::UpstreamUnavailable(name)) if name == "search_b"
+		));
+	}
+
+	#[tokio::test]
+	async fn test_scatter_gather_samples_targets() {
+		let tail = ["tail_c", "tail_d", "tail_e"];
+		let invoker = tail
+			.iter()
+			.chain(&["premium_a", "premium_b", "unweighted"])
+			.fold(MockToolInvoker::new(), |invoker, name| {
+				invoker.with_response(name, json!({"source": name}))
+			});
+		let invoker = Arc::new(invoker);
+		let compiled = Arc::new(CompiledRegistry::compile(Registry::new()).unwrap());
+		let ctx = ExecutionContext::new(json!({}), compiled.clone(), invoker.clone());
+		let executor = CompositionExecutor::new(compiled, invoker.clone());
+
+		let mut targets: Vec<_> = tail.iter().map(|name| ScatterTarget::tool(*name)).collect();
+		targets.push(ScatterTarget::tool("unweighted").with_weight(0.0));
+		targets.push(ScatterTarget::tool("premium_a").with_weight(5.0));
+		targets.push(ScatterTarget::tool("premium_b").with_weight(4.0));
+		let spec = ScatterGatherSpec {
+			targets,
+			aggregation: AggregationStrategy { ops: vec![] },
+			timeout_ms: None,
+			fail_fast: false,
+			sample: Some(TargetSample {
+				always: 2,
+				draw: 1,
+				seed: Some(7),
+			}),
+		};
+
+		let first = ScatterGatherExecutor::execute(&spec, json!({}), &ctx, &executor)
+			.await
+			.unwrap();
+		let first = first.as_array().unwrap();
+		assert_eq!(first.len(), 3);
+		assert!(tail.contains(&first[0]["source"].as_str().unwrap()));
+		assert_eq!(
+			first[1..],
+			[
+				json!({"source": "premium_a"}),
+				json!({"source": "premium_b"})
+			]
+		);
+		invoker.assert_not_called("unweighted");
+
+		// A seed makes the draw the same on every call
+		let again = ScatterGatherExecutor::execute(&spec, json!({}), &ctx, &executor)
+			.await
+			.unwrap();
+		assert_eq!(again.as_array().unwrap(), first);
+	}
+
+	#[test]
+	fn test_sample_targets_follows_weights() {
+		let targets = [
+			ScatterTarget::tool("heavy").with_weight(9.0),
+			ScatterTarget::tool("light"),
+		];
+		let heavy = (0..1000)
+			.filter(|&seed| {
+				let sample = TargetSample {
+					always: 0,
+					draw: 1,
+					seed: Some(seed),
+				};
+				sample_targets(&targets, &sample) == [0]
+			})
+			.count();
+		assert!((850..=950).contains(&heavy), "heavy drawn {heavy} times");
+
+		// Asking for more targets than there are invokes each once
+		let sample = TargetSample {
+			always: 1,
+			draw: 5,
+			seed: None,
+		};
+		assert_eq!(sample_targets(&targets, &sample), [0, 1]);
+	}
+
 	#[tokio::test]
 	async fn test_flatten() {
 		let value = json!([[1, 2], [3, 4], [5]]);
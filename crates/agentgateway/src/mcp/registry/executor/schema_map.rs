@@ -3,9 +3,9 @@
 use std::collections::HashMap;
 
 use serde_json::Value;
-use serde_json_path::JsonPath;
+use tracing::debug;
 
-use super::ExecutionError;
+use super::{ExecutionError, FilterExecutor, path_cache};
 use crate::mcp::registry::patterns::{FieldSource, SchemaMapSpec};
 
 /// Executor for schema-map patterns
@@ -14,33 +14,66 @@ pub struct SchemaMapExecutor;
 impl SchemaMapExecutor {
 	/// Execute a schema-map pattern
 	pub async fn execute(spec: &SchemaMapSpec, input: Value) -> Result<Value, ExecutionError> {
-		let mut result = serde_json::Map::new();
+		Self::map_fields(&spec.mappings, &input)
+	}
 
-		for (field_name, source) in &spec.mappings {
-			let value = Self::extract_field_source(source, &input)?;
-			result.insert(field_name.clone(), value);
+	/// Build the output object for a set of mappings
+	pub(super) fn map_fields(
+		mappings: &HashMap<String, FieldSource>,
+		input: &Value,
+	) -> Result<Value, ExecutionError> {
+		let mut result = serde_json::Map::with_capacity(mappings.len());
+
+		for (field_name, source) in mappings {
+			let value = Self::extract_field_source(source, input).map_err(|e| e.in_field(field_name))?;
+			if let (FieldSource::Path(path), Some(Value::Null)) = (source, &value) {
+				debug!(
+					target: "virtual_tools",
+					field = %field_name,
+					path = %path,
+					"schemaMap JSONPath matched nothing or null; field null"
+				);
+			}
+			if let Some(value) = value {
+				result.insert(field_name.clone(), value);
+			}
 		}
 
 		Ok(Value::Object(result))
 	}
 
-	/// Extract a value from a field source
-	fn extract_field_source(source: &FieldSource, input: &Value) -> Result<Value, ExecutionError> {
-		match source {
+	/// Extract a value from a field source, or `None` if a conditional source omits the field
+	fn extract_field_source(
+		source: &FieldSource,
+		input: &Value,
+	) -> Result<Option<Value>, ExecutionError> {
+		let value = match source {
 			FieldSource::Path(path) => Self::extract_path(path, input),
 			FieldSource::Literal(lit) => Ok(lit.to_json_value()),
 			FieldSource::Coalesce(c) => Self::coalesce(&c.paths, input),
 			FieldSource::Template(t) => Self::template(&t.template, &t.vars, input),
 			FieldSource::Concat(c) => Self::concat(&c.paths, c.separator.as_deref(), input),
-			FieldSource::Nested(nested) => {
-				let nested_spec = SchemaMapSpec {
-					mappings: nested.mappings.clone(),
+			FieldSource::Nested(nested) => Self::map_fields(&nested.mappings, input),
+			FieldSource::Conditional(c) => {
+				let jsonpath = path_cache::parse(&c.when.field).map_err(|e| e.in_field("when"))?;
+				let query_result = jsonpath.query(input);
+				let field_value = query_result.iter().next().copied();
+				let branch = if FilterExecutor::evaluate_condition(&c.when.op, field_value, &c.when.value)?
+				{
+					Some(&c.then)
+				} else {
+					c.fallback.as_ref()
+				};
+				return match branch {
+					Some(source) => Self::extract_field_source(source, input),
+					None => Ok(None),
 				};
-				Box::pin(Self::execute(&nested_spec, input.clone()))
-					.now_or_never()
-					.unwrap()
 			},
-		}
+			FieldSource::Coerce(c) => {
+				return Ok(Self::extract_field_source(&c.source, input)?.map(|v| c.as_type.coerce(v)));
+			},
+		};
+		value.map(Some)
 	}
 
 	/// Extract value using JSONPath
@@ -50,16 +83,13 @@ impl SchemaMapExecutor {
 			return Ok(input.clone());
 		}
 
-		let jsonpath = JsonPath::parse(path)
-			.map_err(|e| ExecutionError::JsonPathError(format!("{}: {}", path, e)))?;
+		let jsonpath = path_cache::parse(path)?;
 
 		let nodes = jsonpath.query(input);
-		let results: Vec<_> = nodes.iter().map(|v| (*v).clone()).collect();
-
-		Ok(match results.len() {
+		Ok(match nodes.len() {
 			0 => Value::Null,
-			1 => results.into_iter().next().unwrap(),
-			_ => Value::Array(results),
+			1 => nodes.first().cloned().unwrap_or_default(),
+			_ => Value::Array(nodes.iter().map(|v| (*v).clone()).collect()),
 		})
 	}
 
@@ -119,25 +149,6 @@ impl SchemaMapExecutor {
 	}
 }
 
-// Helper trait for sync execution of async in nested case
-trait NowOrNever {
-	type Output;
-	fn now_or_never(self) -> Option<Self::Output>;
-}
-
-impl<F: std::future::Future> NowOrNever for F {
-	type Output = F::Output;
-	fn now_or_never(self) -> Option<Self::Output> {
-		let waker = futures::task::noop_waker();
-		let mut cx = std::task::Context::from_waker(&waker);
-		let mut pinned = std::pin::pin!(self);
-		match pinned.as_mut().poll(&mut cx) {
-			std::task::Poll::Ready(result) => Some(result),
-			std::task::Poll::Pending => None,
-		}
-	}
-}
-
 #[cfg(test)]
 mod tests {
 	use super::*;
@@ -319,4 +330,37 @@ mod tests {
 		assert_eq!(result["author_info"]["name"], "Jane Doe");
 		assert_eq!(result["author_info"]["affiliation"], "University");
 	}
+
+	#[tokio::test]
+	async fn test_schema_map_conditional() {
+		let spec: SchemaMapSpec = serde_json::from_value(json!({
+			"mappings": {
+				"ok": {"conditional": {
+					"when": {"field": "$.error", "op": "eq", "value": {"nullValue": true}},
+					"then": {"literal": {"boolValue": true}},
+					"fallback": {"literal": {"boolValue": false}}
+				}},
+				"message": {"conditional": {
+					"when": {"field": "$.error", "op": "ne", "value": {"nullValue": true}},
+					"then": {"path": "$.error.message"}
+				}},
+				"count": {"conditional": {
+					"when": {"field": "$.items", "op": "gt", "value": {"numberValue": 0}},
+					"then": {"path": "$.items"}
+				}}
+			}
+		}))
+		.unwrap();
+
+		let result = SchemaMapExecutor::execute(&spec, json!({"items": 3}))
+			.await
+			.unwrap();
+		assert_eq!(result, json!({"ok": true, "count": 3}));
+
+		// The error shape maps its message; `gt` on a missing field doesn't match
+		let result = SchemaMapExecutor::execute(&spec, json!({"error": {"message": "boom"}}))
+			.await
+			.unwrap();
+		assert_eq!(result, json!({"ok": false, "message": "boom"}));
+	}
 }
@@ -0,0 +1,303 @@
+// Script step executor
+//
+// Runs a pipeline step's WebAssembly module with wasmi. The module gets no imports, a fuel
+// budget bounds its CPU time and a store limiter bounds its memory. Modules are decoded and
+// validated when the registry is compiled; scripts run on the blocking pool so a long one
+// doesn't stall other compositions. A run that outlives its timeout fails the step at once,
+// though its thread keeps going until the fuel runs out, which the fuel ceiling bounds.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use base64::Engine as _;
+use base64::engine::general_purpose::STANDARD;
+use once_cell::sync::Lazy;
+use serde_json::Value;
+use wasmi::core::{TrapCode, ValType};
+use wasmi::{Config, Engine, ExternType, Linker, Module, Store, StoreLimits, StoreLimitsBuilder};
+
+use super::ExecutionError;
+use crate::mcp::registry::patterns::ScriptSpec;
+
+static ENGINE: Lazy<Engine> = Lazy::new(|| {
+	let mut config = Config::default();
+	config.consume_fuel(true);
+	Engine::new(&config)
+});
+
+/// Executor for script steps
+pub struct ScriptExecutor;
+
+impl ScriptExecutor {
+	/// Run a script over the step input
+	pub async fn execute(
+		step_id: &str,
+		spec: &ScriptSpec,
+		input: Value,
+	) -> Result<Value, ExecutionError> {
+		let spec = spec.clone();
+		let timeout = spec.limits.timeout_ms;
+		let step = step_id.to_string();
+		let task = tokio::task::spawn_blocking(move || Self::run(&spec, &input));
+		match tokio::time::timeout(Duration::from_millis(timeout), task).await {
+			Ok(result) => result
+				.map_err(|e| ExecutionError::Internal(format!("script task failed: {e}")))?
+				.map_err(|message| ExecutionError::ScriptFailed { step, message }),
+			Err(_) => Err(ExecutionError::ScriptFailed {
+				step,
+				message: format!("exceeded its time limit of {timeout}ms"),
+			}),
+		}
+	}
+
+	/// Check a script's limits and decode and validate its module, which must import nothing
+	/// and export the memory and functions the executor calls
+	pub fn compile(spec: &ScriptSpec) -> Result<Module, String> {
+		spec.limits.check()?;
+		let wasm = STANDARD
+			.decode(&spec.wasm)
+			.map_err(|e| format!("module is not valid base64: {e}"))?;
+		let module = Module::new(&ENGINE, &wasm).map_err(|e| format!("invalid module: {e}"))?;
+		if let Some(import) = module.imports().next() {
+			return Err(format!(
+				"module imports '{}.{}', but scripts get no imports",
+				import.module(),
+				import.name()
+			));
+		}
+		let export = |name: &str| {
+			module
+				.exports()
+				.find(|export| export.name() == name)
+				.map(|export| export.ty().clone())
+		};
+		let exports_func = |name: &str, params: &[ValType], results: &[ValType]| matches!(export(name), Some(ExternType::Func(f)) if f.params() == params && f.results() == results);
+		if !matches!(export("memory"), Some(ExternType::Memory(_))) {
+			return Err("module does not export 'memory'".to_string());
+		}
+		if !exports_func("alloc", &[ValType::I32], &[ValType::I32]) {
+			return Err("module does not export 'alloc(i32) -> i32'".to_string());
+		}
+		if !exports_func(
+			&spec.entrypoint,
+			&[ValType::I32, ValType::I32],
+			&[ValType::I64],
+		) {
+			return Err(format!(
+				"module does not export '{}(i32, i32) -> i64'",
+				spec.entrypoint
+			));
+		}
+		Ok(module)
+	}
+
+	fn run(spec: &ScriptSpec, input: &Value) -> Result<Value, String> {
+		let limits = spec.limits;
+		let module = match &spec.module {
+			Some(module) => module.clone(),
+			None => Arc::new(Self::compile(spec)?),
+		};
+
+		let store_limits = StoreLimitsBuilder::new()
+			.memory_size(usize::try_from(limits.memory_bytes).unwrap_or(usize::MAX))
+			.instances(1)
+			.build();
+		let mut store = Store::new(&ENGINE, store_limits);
+		store.limiter(|limits: &mut StoreLimits| limits);
+		store.set_fuel(limits.fuel).map_err(|e| e.to_string())?;
+
+		let instance = Linker::<StoreLimits>::new(&ENGINE)
+			.instantiate(&mut store, &module)
+			.and_then(|pre| pre.start(&mut store))
+			.map_err(|e| format!("instantiation failed: {e}"))?;
+		let memory = instance
+			.get_memory(&store, "memory")
+			.ok_or("module does not export 'memory'")?;
+		let alloc = instance
+			.get_typed_func::<i32, i32>(&store, "alloc")
+			.map_err(|e| format!("module does not export 'alloc(i32) -> i32': {e}"))?;
+		let entrypoint = instance
+			.get_typed_func::<(i32, i32), i64>(&store, &spec.entrypoint)
+			.map_err(|e| {
+				format!(
+					"module does not export '{}(i32, i32) -> i64': {e}",
+					spec.entrypoint
+				)
+			})?;
+
+		let trap = |e: wasmi::Error| match e.as_trap_code() {
+			Some(TrapCode::OutOfFuel) => format!("exceeded its fuel limit of {}", limits.fuel),
+			_ => e.to_string(),
+		};
+		let input = serde_json::to_vec(input).map_err(|e| e.to_string())?;
+		let len = i32::try_from(input.len()).map_err(|_| "input is too large".to_string())?;
+		let ptr = alloc.call(&mut store, len).map_err(trap)?;
+		memory
+			.write(&mut store, ptr as u32 as usize, &input)
+			.map_err(|e| format!("cannot write input at {ptr}: {e}"))?;
+
+		let packed = entrypoint.call(&mut store, (ptr, len)).map_err(trap)? as u64;
+		let (out_ptr, out_len) = ((packed >> 32) as usize, (packed & 0xffff_ffff) as usize);
+		if out_len as u64 > limits.output_bytes {
+			return Err(format!(
+				"output of {out_len} bytes exceeds the {} byte limit",
+				limits.output_bytes
+			));
+		}
+		let mut output = vec![0; out_len];
+		memory
+			.read(&store, out_ptr, &mut output)
+			.map_err(|e| format!("cannot read output at {out_ptr}: {e}"))?;
+		serde_json::from_slice(&output).map_err(|e| format!("output is not valid JSON: {e}"))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use serde_json::json;
+
+	use super::*;
+	use crate::mcp::registry::patterns::ScriptLimits;
+
+	/// Bump allocator shared by the test modules
+	const PRELUDE: &str = r#"
+		(memory (export "memory") 1)
+		(global $next (mut i32) (i32.const 1024))
+		(func (export "alloc") (param $len i32) (result i32)
+			(local $ptr i32)
+			(local.set $ptr (global.get $next))
+			(global.set $next (i32.add (global.get $next) (local.get $len)))
+			(local.get $ptr))
+	"#;
+
+	fn script(body: &str) -> ScriptSpec {
+		let wasm = wat::parse_str(format!("(module {PRELUDE} {body})")).unwrap();
+		ScriptSpec {
+			wasm: STANDARD.encode(wasm),
+			entrypoint: "transform".to_string(),
+			limits: ScriptLimits::default(),
+			module: None,
+		}
+	}
+
+	/// Returns the input unchanged
+	const IDENTITY: &str = r#"
+		(func (export "transform") (param $ptr i32) (param $len i32) (result i64)
+			(i64.or
+				(i64.shl (i64.extend_i32_u (local.get $ptr)) (i64.const 32))
+				(i64.extend_i32_u (local.get $len))))
+	"#;
+
+	#[tokio::test]
+	async fn test_script_transforms_input() {
+		let input = json!({"items": [1, 2, 3]});
+		let result = ScriptExecutor::execute("copy", &script(IDENTITY), input.clone())
+			.await
+			.unwrap();
+		assert_eq!(result, input);
+
+		// Returns a constant from a data segment
+		let constant = script(
+			r#"(data (i32.const 16) "[true]")
+			(func (export "transform") (param i32 i32) (result i64)
+				(i64.or (i64.shl (i64.const 16) (i64.const 32)) (i64.const 6)))"#,
+		);
+		let result = ScriptExecutor::execute("constant", &constant, json!({}))
+			.await
+			.unwrap();
+		assert_eq!(result, json!([true]));
+	}
+
+	#[tokio::test]
+	async fn test_script_limits() {
+		let spin = script(
+			r#"(func (export "transform") (param i32 i32) (result i64)
+				(loop $forever (br $forever))
+				(i64.const 0))"#,
+		);
+		let err = ScriptExecutor::execute("spin", &spin, json!({}))
+			.await
+			.unwrap_err();
+		assert!(
+			matches!(&err, ExecutionError::ScriptFailed { step, message }
+				if step == "spin" && message.contains("fuel limit")),
+			"{err}"
+		);
+
+		let mut small = script(IDENTITY);
+		small.limits.output_bytes = 4;
+		let err = ScriptExecutor::execute("copy", &small, json!({"big": true}))
+			.await
+			.unwrap_err();
+		assert!(
+			err.to_string().contains("exceeds the 4 byte limit"),
+			"{err}"
+		);
+
+		// A module declaring more memory than allowed can't be instantiated
+		let mut hungry = script(IDENTITY);
+		hungry.limits.memory_bytes = 1024;
+		assert!(
+			ScriptExecutor::execute("copy", &hungry, json!({}))
+				.await
+				.is_err()
+		);
+
+		// The wall clock bounds a run whose fuel would last much longer
+		let mut slow = spin.clone();
+		slow.limits.fuel = 100_000_000;
+		slow.limits.timeout_ms = 10;
+		let err = ScriptExecutor::execute("spin", &slow, json!({}))
+			.await
+			.unwrap_err();
+		assert!(err.to_string().contains("time limit of 10ms"), "{err}");
+
+		// Limits above the ceiling fail the load
+		let mut greedy = script(IDENTITY);
+		greedy.limits.fuel = ScriptLimits::MAX.fuel + 1;
+		assert_eq!(
+			ScriptExecutor::compile(&greedy).unwrap_err(),
+			format!(
+				"fuel of {} exceeds the maximum of {}",
+				ScriptLimits::MAX.fuel + 1,
+				ScriptLimits::MAX.fuel
+			)
+		);
+	}
+
+	#[tokio::test]
+	async fn test_script_has_no_host_access() {
+		let wasm = wat::parse_str(
+			r#"(module (import "env" "read_file" (func (param i32)))
+				(memory (export "memory") 1))"#,
+		)
+		.unwrap();
+		let spec = ScriptSpec {
+			wasm: STANDARD.encode(wasm),
+			entrypoint: "transform".to_string(),
+			limits: ScriptLimits::default(),
+			module: None,
+		};
+		let err = ScriptExecutor::execute("io", &spec, json!({}))
+			.await
+			.unwrap_err();
+		assert!(err.to_string().contains("scripts get no imports"), "{err}");
+	}
+
+	#[tokio::test]
+	async fn test_script_runs_compiled_module() {
+		let mut spec = script(IDENTITY);
+		spec.module = Some(Arc::new(ScriptExecutor::compile(&spec).unwrap()));
+		// The compiled module is used as is, without decoding the source again
+		spec.wasm = String::new();
+		let result = ScriptExecutor::execute("copy", &spec, json!([1]))
+			.await
+			.unwrap();
+		assert_eq!(result, json!([1]));
+
+		let mut renamed = script(IDENTITY);
+		renamed.entrypoint = "run".to_string();
+		let err = ScriptExecutor::compile(&renamed).unwrap_err();
+		assert_eq!(err, "module does not export 'run(i32, i32) -> i64'");
+	}
+}
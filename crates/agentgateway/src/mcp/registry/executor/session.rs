@@ -0,0 +1,148 @@
+// Session-scoped composition state
+
+use std::collections::HashMap;
+
+use serde_json::Value;
+use tokio::sync::RwLock;
+
+use super::ExecutionError;
+
+/// Default maximum number of keys one session can hold
+pub const DEFAULT_MAX_SESSION_KEYS: usize = 64;
+
+/// Default maximum total size of one session's values, as serialized JSON
+pub const DEFAULT_MAX_SESSION_BYTES: usize = 1024 * 1024;
+
+/// Key/value state shared by all compositions executed within one MCP session
+///
+/// Lets multi-turn agent flows stash cursors or partial results between tool calls
+/// without a backend datastore. State lives in memory for the lifetime of the
+/// session; it is not carried across session resumption, so a session resumed on
+/// another gateway instance (or after a restart) starts with an empty store.
+///
+/// The store is bounded by key count and total value size. A write that would go over
+/// either limit fails instead of evicting state a later turn may depend on; overwriting
+/// or removing keys frees room.
+#[derive(Debug)]
+pub struct SessionStore {
+	values: RwLock<HashMap<String, (Value, usize)>>,
+	max_keys: usize,
+	max_bytes: usize,
+}
+
+impl Default for SessionStore {
+	fn default() -> Self {
+		Self::with_limits(DEFAULT_MAX_SESSION_KEYS, DEFAULT_MAX_SESSION_BYTES)
+	}
+}
+
+impl SessionStore {
+	/// Create an empty session store with the default limits
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Create an empty session store holding at most `max_keys` keys and `max_bytes`
+	/// bytes of values
+	pub fn with_limits(max_keys: usize, max_bytes: usize) -> Self {
+		Self {
+			values: RwLock::new(HashMap::new()),
+			max_keys,
+			max_bytes,
+		}
+	}
+
+	/// Get a value by key
+	pub async fn get(&self, key: &str) -> Option<Value> {
+		self.values.read().await.get(key).map(|(v, _)| v.clone())
+	}
+
+	/// Set a value, returning the previous value if any
+	pub async fn set(&self, key: &str, value: Value) -> Result<Option<Value>, ExecutionError> {
+		let size = key.len() + serde_json::to_vec(&value).map_or(0, |b| b.len());
+		let mut values = self.values.write().await;
+		let replaced = values.get(key).map_or(0, |(_, s)| *s);
+		if replaced == 0 && values.len() >= self.max_keys {
+			return Err(ExecutionError::SessionStateFull(format!(
+				"cannot set '{}': the session already holds {} keys",
+				key, self.max_keys
+			)));
+		}
+		let used: usize = values.values().map(|(_, s)| s).sum();
+		if used - replaced + size > self.max_bytes {
+			return Err(ExecutionError::SessionStateFull(format!(
+				"cannot set '{}': the session's values would exceed {} bytes",
+				key, self.max_bytes
+			)));
+		}
+		let previous = values.insert(key.to_string(), (value, size));
+		Ok(previous.map(|(v, _)| v))
+	}
+
+	/// Remove a value by key
+	pub async fn remove(&self, key: &str) -> Option<Value> {
+		self.values.write().await.remove(key).map(|(v, _)| v)
+	}
+
+	/// Number of stored keys
+	pub async fn len(&self) -> usize {
+		self.values.read().await.len()
+	}
+
+	/// Check if the store is empty
+	pub async fn is_empty(&self) -> bool {
+		self.values.read().await.is_empty()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[tokio::test]
+	async fn test_get_set_remove() {
+		let store = SessionStore::new();
+		assert!(store.is_empty().await);
+		assert!(store.get("cursor").await.is_none());
+
+		assert!(
+			store
+				.set("cursor", serde_json::json!("abc"))
+				.await
+				.unwrap()
+				.is_none()
+		);
+		assert_eq!(store.get("cursor").await, Some(serde_json::json!("abc")));
+
+		let prev = store.set("cursor", serde_json::json!("def")).await.unwrap();
+		assert_eq!(prev, Some(serde_json::json!("abc")));
+		assert_eq!(store.len().await, 1);
+
+		assert_eq!(store.remove("cursor").await, Some(serde_json::json!("def")));
+		assert!(store.is_empty().await);
+	}
+
+	#[tokio::test]
+	async fn test_limits() {
+		let store = SessionStore::with_limits(2, 32);
+		store.set("a", serde_json::json!(1)).await.unwrap();
+		store.set("b", serde_json::json!(2)).await.unwrap();
+
+		// A new key over the key limit fails; existing keys can still be overwritten
+		let err = store.set("c", serde_json::json!(3)).await.unwrap_err();
+		assert!(err.to_string().contains("2 keys"), "{err}");
+		store.set("b", serde_json::json!("two")).await.unwrap();
+
+		// So can a value over the size limit, leaving the old value in place
+		let err = store
+			.set("b", serde_json::json!("x".repeat(32)))
+			.await
+			.unwrap_err();
+		assert!(err.to_string().contains("32 bytes"), "{err}");
+		assert_eq!(store.get("b").await, Some(serde_json::json!("two")));
+
+		store.remove("a").await;
+		store.set("c", serde_json::json!(3)).await.unwrap();
+		assert_eq!(store.len().await, 2);
+	}
+}
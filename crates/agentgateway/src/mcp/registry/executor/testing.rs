@@ -0,0 +1,484 @@
+// Test doubles for composition authors
+//
+// Available to this crate's tests and, through the `testing` feature, to downstream crates
+// that embed agentgateway and want to unit-test their registries without real backends.
+
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use rmcp::model::{Content, CreateMessageRequestParam, CreateMessageResult, Role, SamplingMessage};
+use serde_json::Value;
+
+use super::{ExecutionError, InvocationMeta, ToolInvoker};
+use crate::mcp::registry::compiled::CompiledRegistry;
+use crate::mcp::registry::error::RegistryError;
+
+/// A single invocation seen by [`MockToolInvoker`]
+#[derive(Debug, Clone)]
+pub struct MockCall {
+	pub tool: String,
+	pub args: Value,
+	pub meta: InvocationMeta,
+}
+
+/// Tool invoker returning canned responses, for testing compositions without backends.
+///
+/// Each tool answers from its script first (one entry per call), then from its fixed
+/// response or error. Tools with neither fail with [`ExecutionError::ToolNotFound`].
+///
+/// ```ignore
+/// let invoker = MockToolInvoker::new()
+///     .with_script("search", [Err("rate limited".to_string()), Ok(json!({"hits": []}))])
+///     .with_latency("search", Duration::from_millis(50));
+/// // ... execute the composition ...
+/// invoker.assert_called("search", 2);
+/// ```
+#[derive(Default)]
+pub struct MockToolInvoker {
+	responses: Mutex<HashMap<String, Result<Value, String>>>,
+	scripts: Mutex<HashMap<String, VecDeque<Result<Value, String>>>>,
+	latency: Mutex<HashMap<String, Duration>>,
+	unavailable: Mutex<HashSet<String>>,
+	calls: Mutex<Vec<MockCall>>,
+	sampling_response: Mutex<Option<String>>,
+	sampling_requests: Mutex<Vec<CreateMessageRequestParam>>,
+	/// Partial results emitted so far, if the mock accepts them
+	partials: Mutex<Option<Vec<Value>>>,
+	progress: Mutex<Vec<(u32, u32, String)>>,
+}
+
+impl MockToolInvoker {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Answer sampling requests with `text`, as if the client's model wrote it; without
+	/// this, sampling fails as it does for clients without the sampling capability
+	pub fn with_sampling_response(self, text: &str) -> Self {
+		*self.sampling_response.lock().unwrap() = Some(text.to_string());
+		self
+	}
+
+	/// Sampling requests received, in order
+	pub fn sampling_requests(&self) -> Vec<CreateMessageRequestParam> {
+		self.sampling_requests.lock().unwrap().clone()
+	}
+
+	/// Accept partial results, as a client connected over a stream does
+	pub fn with_partials(self) -> Self {
+		*self.partials.lock().unwrap() = Some(Vec::new());
+		self
+	}
+
+	/// Partial results emitted so far, in order
+	pub fn partials(&self) -> Vec<Value> {
+		self.partials.lock().unwrap().clone().unwrap_or_default()
+	}
+
+	/// Progress reported so far, as `(completed, total, message)` in order
+	pub fn progress(&self) -> Vec<(u32, u32, String)> {
+		self.progress.lock().unwrap().clone()
+	}
+
+	/// Answer every call to `tool_name` with `response`
+	pub fn with_response(self, tool_name: &str, response: Value) -> Self {
+		self
+			.responses
+			.lock()
+			.unwrap()
+			.insert(tool_name.to_string(), Ok(response));
+		self
+	}
+
+	/// Fail every call to `tool_name` with [`ExecutionError::ToolExecutionFailed`]
+	pub fn with_error(self, tool_name: &str, message: &str) -> Self {
+		self
+			.responses
+			.lock()
+			.unwrap()
+			.insert(tool_name.to_string(), Err(message.to_string()));
+		self
+	}
+
+	/// Answer successive calls to `tool_name` from `script`, e.g. to fail twice and then
+	/// succeed when testing retries. Once the script runs out, the fixed response applies.
+	pub fn with_script(
+		self,
+		tool_name: &str,
+		script: impl IntoIterator<Item = Result<Value, String>>,
+	) -> Self {
+		self
+			.scripts
+			.lock()
+			.unwrap()
+			.entry(tool_name.to_string())
+			.or_default()
+			.extend(script);
+		self
+	}
+
+	/// Delay every call to `tool_name` by `latency` before answering
+	pub fn with_latency(self, tool_name: &str, latency: Duration) -> Self {
+		self
+			.latency
+			.lock()
+			.unwrap()
+			.insert(tool_name.to_string(), latency);
+		self
+	}
+
+	/// Report `tool_name`'s upstream as down, as an open circuit breaker would
+	pub fn with_unavailable(self, tool_name: &str) -> Self {
+		self
+			.unavailable
+			.lock()
+			.unwrap()
+			.insert(tool_name.to_string());
+		self
+	}
+
+	/// Tool invocations seen so far, with their metadata
+	pub fn calls(&self) -> Vec<(String, InvocationMeta)> {
+		self
+			.calls
+			.lock()
+			.unwrap()
+			.iter()
+			.map(|c| (c.tool.clone(), c.meta.clone()))
+			.collect()
+	}
+
+	/// Every invocation seen so far, including arguments
+	pub fn recorded_calls(&self) -> Vec<MockCall> {
+		self.calls.lock().unwrap().clone()
+	}
+
+	/// Arguments of each call to `tool_name`, in call order
+	pub fn call_args(&self, tool_name: &str) -> Vec<Value> {
+		self
+			.calls
+			.lock()
+			.unwrap()
+			.iter()
+			.filter(|c| c.tool == tool_name)
+			.map(|c| c.args.clone())
+			.collect()
+	}
+
+	/// Number of calls made to `tool_name`
+	pub fn call_count(&self, tool_name: &str) -> usize {
+		self
+			.calls
+			.lock()
+			.unwrap()
+			.iter()
+			.filter(|c| c.tool == tool_name)
+			.count()
+	}
+
+	/// Panic unless `tool_name` was called exactly `times` times
+	#[track_caller]
+	pub fn assert_called(&self, tool_name: &str, times: usize) {
+		let count = self.call_count(tool_name);
+		assert_eq!(
+			count, times,
+			"expected {tool_name} to be called {times} time(s), but it was called {count} time(s)"
+		);
+	}
+
+	/// Panic if `tool_name` was called
+	#[track_caller]
+	pub fn assert_not_called(&self, tool_name: &str) {
+		self.assert_called(tool_name, 0);
+	}
+
+	fn next_response(&self, tool_name: &str) -> Result<Value, ExecutionError> {
+		let scripted = self
+			.scripts
+			.lock()
+			.unwrap()
+			.get_mut(tool_name)
+			.and_then(VecDeque::pop_front);
+		let response = match scripted {
+			Some(response) => response,
+			None => self
+				.responses
+				.lock()
+				.unwrap()
+				.get(tool_name)
+				.cloned()
+				.ok_or_else(|| ExecutionError::ToolNotFound(tool_name.to_string()))?,
+		};
+		response.map_err(ExecutionError::ToolExecutionFailed)
+	}
+}
+
+#[async_trait::async_trait]
+impl ToolInvoker for MockToolInvoker {
+	async fn invoke(&self, tool_name: &str, args: Value) -> Result<Value, ExecutionError> {
+		self
+			.invoke_with_meta(tool_name, args, &InvocationMeta::default())
+			.await
+	}
+
+	async fn invoke_with_meta(
+		&self,
+		tool_name: &str,
+		args: Value,
+		meta: &InvocationMeta,
+	) -> Result<Value, ExecutionError> {
+		self.calls.lock().unwrap().push(MockCall {
+			tool: tool_name.to_string(),
+			args,
+			meta: meta.clone(),
+		});
+		let latency = self.latency.lock().unwrap().get(tool_name).copied();
+		if let Some(latency) = latency {
+			tokio::time::sleep(latency).await;
+		}
+		self.next_response(tool_name)
+	}
+
+	fn is_available(&self, tool_name: &str) -> bool {
+		!self.unavailable.lock().unwrap().contains(tool_name)
+	}
+
+	fn can_emit_partial(&self) -> bool {
+		self.partials.lock().unwrap().is_some()
+	}
+
+	async fn emit_partial(&self, partial: Value) -> Result<(), ExecutionError> {
+		match self.partials.lock().unwrap().as_mut() {
+			Some(partials) => {
+				partials.push(partial);
+				Ok(())
+			},
+			None => Err(ExecutionError::PatternExecutionFailed(
+				"mock does not accept partial results".to_string(),
+			)),
+		}
+	}
+
+	async fn report_progress(&self, completed: u32, total: u32, message: String) {
+		self
+			.progress
+			.lock()
+			.unwrap()
+			.push((completed, total, message));
+	}
+
+	async fn create_message(
+		&self,
+		request: CreateMessageRequestParam,
+	) -> Result<CreateMessageResult, ExecutionError> {
+		self.sampling_requests.lock().unwrap().push(request);
+		let text = self
+			.sampling_response
+			.lock()
+			.unwrap()
+			.clone()
+			.ok_or_else(|| {
+				ExecutionError::SamplingUnavailable("mock has no sampling response".to_string())
+			})?;
+		Ok(CreateMessageResult {
+			model: "mock".to_string(),
+			stop_reason: Some(CreateMessageResult::STOP_REASON_END_TURN.to_string()),
+			message: SamplingMessage {
+				role: Role::Assistant,
+				content: Content::text(text),
+			},
+		})
+	}
+}
+
+/// Tool invoker that resolves virtual tools against a registry the way the gateway does.
+///
+/// Source-based virtual tools get their defaults injected and are forwarded to the inner
+/// invoker as `<target>_<tool>`, the name the gateway exposes the backend tool under; the
+/// backend response then goes through the virtual tool's output transform. Other names are
+/// forwarded unchanged. Pair it with a [`MockToolInvoker`] keyed by backend tool names to
+/// test a registry's virtual tools and compositions end to end.
+pub struct RegistryAwareInvoker {
+	registry: Arc<CompiledRegistry>,
+	inner: Arc<dyn ToolInvoker>,
+}
+
+impl RegistryAwareInvoker {
+	pub fn new(registry: Arc<CompiledRegistry>, inner: Arc<dyn ToolInvoker>) -> Self {
+		Self { registry, inner }
+	}
+}
+
+#[async_trait::async_trait]
+impl ToolInvoker for RegistryAwareInvoker {
+	async fn invoke(&self, tool_name: &str, args: Value) -> Result<Value, ExecutionError> {
+		self
+			.invoke_with_meta(tool_name, args, &InvocationMeta::default())
+			.await
+	}
+
+	async fn invoke_with_meta(
+		&self,
+		tool_name: &str,
+		args: Value,
+		meta: &InvocationMeta,
+	) -> Result<Value, ExecutionError> {
+		if self.registry.get_tool(tool_name).is_none() {
+			return self.inner.invoke_with_meta(tool_name, args, meta).await;
+		}
+
+		let (target, backend_tool, args) =
+			self
+				.registry
+				.prepare_call_args(tool_name, args)
+				.map_err(|e| match e {
+					RegistryError::CompositionRequiresExecutor(name) => {
+						ExecutionError::ToolExecutionFailed(format!(
+							"Nested composition '{}' not supported - compositions can only call backend tools",
+							name
+						))
+					},
+					e => ExecutionError::ToolExecutionFailed(e.to_string()),
+				})?;
+		let result = self
+			.inner
+			.invoke_with_meta(&format!("{target}_{backend_tool}"), args, meta)
+			.await?;
+		self
+			.registry
+			.transform_output(tool_name, result)
+			.map_err(|e| ExecutionError::ToolExecutionFailed(e.to_string()))
+	}
+
+	async fn create_message(
+		&self,
+		request: CreateMessageRequestParam,
+	) -> Result<CreateMessageResult, ExecutionError> {
+		self.inner.create_message(request).await
+	}
+
+	fn can_emit_partial(&self) -> bool {
+		self.inner.can_emit_partial()
+	}
+
+	async fn emit_partial(&self, partial: Value) -> Result<(), ExecutionError> {
+		self.inner.emit_partial(partial).await
+	}
+
+	fn is_available(&self, tool_name: &str) -> bool {
+		match self
+			.registry
+			.get_tool(tool_name)
+			.and_then(|tool| tool.source_info())
+		{
+			Some(info) => self
+				.inner
+				.is_available(&format!("{}_{}", info.source.target, info.source.tool)),
+			None => self.inner.is_available(tool_name),
+		}
+	}
+}
+
+/// A `research` pipeline calling `search`, `summarize` and `publish` in turn, for tests that
+/// step through or resume a multi-step execution
+#[cfg(test)]
+pub(crate) fn research_pipeline() -> Arc<CompiledRegistry> {
+	let registry: crate::mcp::registry::types::Registry = serde_json::from_value(serde_json::json!({
+		"schemaVersion": "1.0",
+		"tools": [{
+			"name": "research",
+			"spec": {
+				"pipeline": {
+					"steps": [
+						{"id": "search", "operation": {"tool": {"name": "search"}}},
+						{"id": "summarize", "operation": {"tool": {"name": "summarize"}}},
+						{"id": "publish", "operation": {"tool": {"name": "publish"}}}
+					]
+				}
+			}
+		}]
+	}))
+	.unwrap();
+	Arc::new(CompiledRegistry::compile(registry).unwrap())
+}
+
+#[cfg(test)]
+mod tests {
+	use serde_json::json;
+
+	use super::*;
+
+	#[tokio::test]
+	async fn test_script_then_fixed_response() {
+		let invoker = MockToolInvoker::new()
+			.with_response("search", json!({"hits": 1}))
+			.with_script("search", [Err("rate limited".to_string())]);
+
+		assert!(matches!(
+			invoker.invoke("search", json!({"q": "a"})).await,
+			Err(ExecutionError::ToolExecutionFailed(msg)) if msg == "rate limited"
+		));
+		assert_eq!(
+			invoker.invoke("search", json!({"q": "b"})).await.unwrap(),
+			json!({"hits": 1})
+		);
+		assert!(matches!(
+			invoker.invoke("missing", json!({})).await,
+			Err(ExecutionError::ToolNotFound(_))
+		));
+
+		invoker.assert_called("search", 2);
+		invoker.assert_not_called("fetch");
+		assert_eq!(
+			invoker.call_args("search"),
+			vec![json!({"q": "a"}), json!({"q": "b"})]
+		);
+	}
+
+	#[tokio::test(start_paused = true)]
+	async fn test_latency() {
+		let invoker = MockToolInvoker::new()
+			.with_error("slow", "boom")
+			.with_latency("slow", Duration::from_secs(5));
+
+		let start = tokio::time::Instant::now();
+		assert!(invoker.invoke("slow", json!({})).await.is_err());
+		assert!(start.elapsed() >= Duration::from_secs(5));
+	}
+
+	#[tokio::test]
+	async fn test_registry_aware_invoker_resolves_virtual_tools() {
+		use crate::mcp::registry::types::{Registry, ToolDefinition, ToolImplementation};
+
+		let mut forecast = ToolDefinition::source("forecast", "weather", "get_forecast");
+		if let ToolImplementation::Source(source) = &mut forecast.implementation {
+			source.defaults.insert("units".to_string(), json!("metric"));
+		}
+		let registry = Registry::with_tool_definitions(vec![forecast]);
+		let registry = Arc::new(CompiledRegistry::compile(registry).unwrap());
+
+		let backend = Arc::new(
+			MockToolInvoker::new()
+				.with_response("weather_get_forecast", json!({"temp": 20}))
+				.with_response("echo", json!({"echoed": true})),
+		);
+		let invoker = RegistryAwareInvoker::new(registry, backend.clone());
+
+		assert_eq!(
+			invoker
+				.invoke("forecast", json!({"city": "Paris"}))
+				.await
+				.unwrap(),
+			json!({"temp": 20})
+		);
+		assert_eq!(
+			backend.call_args("weather_get_forecast"),
+			vec![json!({"city": "Paris", "units": "metric"})]
+		);
+
+		// Names the registry doesn't know pass straight through
+		invoker.invoke("echo", json!({})).await.unwrap();
+		backend.assert_called("echo", 1);
+	}
+}
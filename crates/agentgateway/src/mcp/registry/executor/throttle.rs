@@ -5,14 +5,29 @@
 // - TokenBucket: Allows bursts up to bucket capacity
 // - FixedWindow: Simple window-based counting
 // - LeakyBucket: Smooths out request rate
+//
+// Limiter state is dropped once a key has been idle for a whole window, which every strategy
+// treats the same as a key never seen, and the least recently used keys are evicted beyond
+// a maximum count. The buckets are exported in state snapshots through `StateStore`, with
+// their times relative to the export.
+//
+// Gateways in several regions can share buckets through an eventually consistent view of a
+// `TieredStore`: each check starts from the latest bucket any gateway shared and writes its
+// own back. Limits are then approximate across gateways, and a store that can't be reached
+// leaves each gateway enforcing them on its own.
 
 use super::ExecutionError;
 use crate::mcp::registry::patterns::{ThrottleSpec, ThrottleStrategy};
+use crate::stateful::{
+	BoundedMap, EvictionStats, StateStore, StateStoreExt, StoreError, TieredStore,
+};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
 use serde_json::Value;
-use std::collections::HashMap;
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 use tokio::sync::Mutex;
+use tracing::warn;
 
 /// In-memory rate limiter state for single-instance throttling
 #[derive(Debug, Default)]
@@ -27,10 +42,62 @@ pub struct RateLimiterState {
 	leaky_bucket: Option<(f64, Instant)>,
 }
 
+/// A limiter's state as carried in a state snapshot, with times as millis before the export
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct LimiterRecord {
+	#[serde(default, skip_serializing_if = "Vec::is_empty")]
+	sliding_window: Vec<u64>,
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	token_bucket: Option<(f64, u64)>,
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	fixed_window: Option<(u32, u64)>,
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	leaky_bucket: Option<(f64, u64)>,
+}
+
+impl RateLimiterState {
+	fn to_record(&self, now: Instant) -> LimiterRecord {
+		let ago = |at: Instant| now.saturating_duration_since(at).as_millis() as u64;
+		LimiterRecord {
+			sliding_window: self
+				.sliding_window_timestamps
+				.iter()
+				.map(|t| ago(*t))
+				.collect(),
+			token_bucket: self.token_bucket.map(|(tokens, at)| (tokens, ago(at))),
+			fixed_window: self.fixed_window.map(|(count, at)| (count, ago(at))),
+			leaky_bucket: self.leaky_bucket.map(|(level, at)| (level, ago(at))),
+		}
+	}
+
+	fn from_record(record: LimiterRecord, now: Instant) -> Self {
+		// Times from before this process started are clamped to now
+		let at = |ms: u64| now.checked_sub(Duration::from_millis(ms)).unwrap_or(now);
+		Self {
+			sliding_window_timestamps: record.sliding_window.into_iter().map(at).collect(),
+			token_bucket: record.token_bucket.map(|(tokens, ms)| (tokens, at(ms))),
+			fixed_window: record.fixed_window.map(|(count, ms)| (count, at(ms))),
+			leaky_bucket: record.leaky_bucket.map(|(level, ms)| (level, at(ms))),
+		}
+	}
+}
+
+/// Default for the most keys a rate limiter registry tracks at once
+pub const DEFAULT_MAX_RATE_LIMITERS: usize = 10_000;
+
 /// Global rate limiter registry for in-memory throttling
-#[derive(Debug, Default)]
+#[derive(Debug)]
 pub struct RateLimiterRegistry {
-	limiters: HashMap<String, RateLimiterState>,
+	limiters: BoundedMap<String, RateLimiterState>,
+	/// Store the buckets are shared through with other gateways
+	shared: Option<TieredStore>,
+}
+
+impl Default for RateLimiterRegistry {
+	fn default() -> Self {
+		Self::with_max_limiters(DEFAULT_MAX_RATE_LIMITERS)
+	}
 }
 
 impl RateLimiterRegistry {
@@ -38,14 +105,93 @@ impl RateLimiterRegistry {
 		Self::default()
 	}
 
-	pub fn get_or_create(&mut self, key: &str) -> &mut RateLimiterState {
-		self.limiters.entry(key.to_string()).or_default()
+	/// Create a registry tracking at most `max_limiters` keys
+	pub fn with_max_limiters(max_limiters: usize) -> Self {
+		Self {
+			limiters: BoundedMap::new(Some(max_limiters)),
+			shared: None,
+		}
+	}
+
+	/// Also share buckets through `shared`, so gateways sharing it enforce limits together
+	pub fn with_shared(mut self, shared: TieredStore) -> Self {
+		self.shared = Some(shared);
+		self
+	}
+
+	/// Get the state of `key`, dropped once the key is idle for `window`
+	pub fn get_or_create(&mut self, key: &str, window: Duration) -> &mut RateLimiterState {
+		if self.limiters.get(key).is_none() {
+			self
+				.limiters
+				.insert(key.to_string(), RateLimiterState::default(), None);
+		}
+		self.limiters.set_ttl(key, Some(window));
+		self.limiters.get_mut(key).expect("inserted above")
+	}
+
+	/// Keys tracked and how many expired or were evicted so far
+	pub fn eviction_stats(&self) -> EvictionStats {
+		self.limiters.stats()
 	}
 }
 
 /// Shared rate limiter registry wrapped in Arc<Mutex<>>
 pub type SharedRateLimiterRegistry = Arc<Mutex<RateLimiterRegistry>>;
 
+#[async_trait]
+impl StateStore for Mutex<RateLimiterRegistry> {
+	async fn get(&self, key: &str) -> Result<Option<Vec<u8>>, StoreError> {
+		Ok(self.get_with_ttl(key).await?.map(|(value, _)| value))
+	}
+
+	async fn set(&self, key: &str, value: Vec<u8>, ttl: Option<Duration>) -> Result<(), StoreError> {
+		let record: LimiterRecord =
+			serde_json::from_slice(&value).map_err(|e| StoreError::Serialization(e.to_string()))?;
+		let state = RateLimiterState::from_record(record, Instant::now());
+		self
+			.lock()
+			.await
+			.limiters
+			.insert(key.to_string(), state, ttl);
+		Ok(())
+	}
+
+	async fn delete(&self, key: &str) -> Result<(), StoreError> {
+		self.lock().await.limiters.remove(key);
+		Ok(())
+	}
+
+	async fn get_with_ttl(
+		&self,
+		key: &str,
+	) -> Result<Option<(Vec<u8>, Option<Duration>)>, StoreError> {
+		let registry = self.lock().await;
+		let Some((state, expires_at)) = registry.limiters.peek_with_expiry(key) else {
+			return Ok(None);
+		};
+		let now = Instant::now();
+		let value = serde_json::to_vec(&state.to_record(now))
+			.map_err(|e| StoreError::Serialization(e.to_string()))?;
+		Ok(Some((
+			value,
+			expires_at.map(|at| at.saturating_duration_since(now)),
+		)))
+	}
+
+	async fn keys(&self, prefix: &str) -> Result<Vec<String>, StoreError> {
+		let registry = self.lock().await;
+		Ok(
+			registry
+				.limiters
+				.iter()
+				.filter(|(key, _)| key.starts_with(prefix))
+				.map(|(key, _)| key.clone())
+				.collect(),
+		)
+	}
+}
+
 pub struct ThrottleExecutor;
 
 impl ThrottleExecutor {
@@ -57,17 +203,36 @@ impl ThrottleExecutor {
 		key: &str,
 	) -> Result<bool, ExecutionError> {
 		let mut registry = registry.lock().await;
-		let state = registry.get_or_create(key);
-		let now = Instant::now();
 		let window = Duration::from_millis(spec.window_ms as u64);
+		let shared = registry.shared.clone();
+		let shared_key = format!("throttle/{key}");
+		if let Some(shared) = &shared {
+			match shared.get_json::<LimiterRecord>(&shared_key).await {
+				Ok(Some(record)) => {
+					let state = RateLimiterState::from_record(record, Instant::now());
+					registry.limiters.insert(key.to_string(), state, None);
+				},
+				Ok(None) => {},
+				Err(error) => warn!(key, %error, "failed to read shared throttle bucket"),
+			}
+		}
+		let state = registry.get_or_create(key, window);
+		let now = Instant::now();
 		let rate = spec.rate;
 
-		match spec.strategy {
+		let allowed = match spec.strategy {
 			ThrottleStrategy::SlidingWindow => Self::check_sliding_window(state, now, window, rate),
 			ThrottleStrategy::TokenBucket => Self::check_token_bucket(state, now, window, rate),
 			ThrottleStrategy::FixedWindow => Self::check_fixed_window(state, now, window, rate),
 			ThrottleStrategy::LeakyBucket => Self::check_leaky_bucket(state, now, window, rate),
+		}?;
+		if let Some(shared) = &shared {
+			let record = state.to_record(now);
+			if let Err(error) = shared.set_json(&shared_key, &record, Some(window)).await {
+				warn!(key, %error, "failed to share throttle bucket");
+			}
 		}
+		Ok(allowed)
 	}
 
 	fn check_sliding_window(
@@ -178,6 +343,8 @@ impl ThrottleExecutor {
 mod tests {
 	use super::*;
 	use crate::mcp::registry::patterns::{OnExceeded, StepOperation, ToolCall};
+	use crate::stateful::TieredStoreConfig;
+	use crate::stateful::memory::MemoryStore;
 	use tokio::time::sleep;
 
 	fn create_test_spec(
@@ -406,4 +573,83 @@ mod tests {
 			.unwrap();
 		assert!(allowed, "key2 should have separate limit");
 	}
+
+	#[tokio::test]
+	async fn test_idle_and_excess_keys_are_dropped() {
+		let spec = create_test_spec(1, 20, ThrottleStrategy::FixedWindow, OnExceeded::Reject);
+		let registry = Arc::new(Mutex::new(RateLimiterRegistry::with_max_limiters(2)));
+
+		for key in ["key1", "key2", "key3"] {
+			assert!(
+				ThrottleExecutor::check_rate_limit(&spec, &registry, key)
+					.await
+					.unwrap()
+			);
+		}
+		assert_eq!(registry.lock().await.eviction_stats().evicted, 1);
+
+		// Idle for a whole window, the remaining keys expire
+		tokio::time::sleep(Duration::from_millis(30)).await;
+		assert!(
+			ThrottleExecutor::check_rate_limit(&spec, &registry, "key4")
+				.await
+				.unwrap()
+		);
+		let stats = registry.lock().await.eviction_stats();
+		assert_eq!((stats.entries, stats.expired, stats.evicted), (1, 2, 1));
+	}
+
+	#[tokio::test]
+	async fn test_buckets_move_between_registries() {
+		let spec = create_test_spec(2, 60_000, ThrottleStrategy::TokenBucket, OnExceeded::Reject);
+		let blue = create_registry();
+		for _ in 0..2 {
+			assert!(
+				ThrottleExecutor::check_rate_limit(&spec, &blue, "agent-7")
+					.await
+					.unwrap()
+			);
+		}
+
+		let green = create_registry();
+		for key in blue.keys("").await.unwrap() {
+			let (value, ttl) = blue.get_with_ttl(&key).await.unwrap().unwrap();
+			green.set(&key, value, ttl).await.unwrap();
+		}
+		// The drained bucket stays drained on the new instance
+		assert!(
+			!ThrottleExecutor::check_rate_limit(&spec, &green, "agent-7")
+				.await
+				.unwrap()
+		);
+	}
+
+	#[tokio::test]
+	async fn test_gateways_sharing_a_store_share_buckets() {
+		let spec = create_test_spec(2, 60_000, ThrottleStrategy::FixedWindow, OnExceeded::Reject);
+		let remote = Arc::new(MemoryStore::new());
+		let gateway = || {
+			let tiered = TieredStore::new(remote.clone(), TieredStoreConfig::default());
+			let registry = RateLimiterRegistry::new().with_shared(tiered.for_pattern("throttle"));
+			(tiered, Arc::new(Mutex::new(registry)))
+		};
+		let (us_store, us) = gateway();
+		let (_, eu) = gateway();
+
+		for _ in 0..2 {
+			assert!(
+				ThrottleExecutor::check_rate_limit(&spec, &us, "agent-7")
+					.await
+					.unwrap()
+			);
+		}
+		// Once the window replicates, the other gateway finds it used up
+		us_store.flush().await;
+		assert!(
+			!ThrottleExecutor::check_rate_limit(&spec, &eu, "agent-7")
+				.await
+				.unwrap()
+		);
+		assert_eq!(us_store.replication_failures(), 0);
+	}
 }
@@ -0,0 +1,451 @@
+// Time-travel debugging for compositions
+//
+// While a composition's verbosity is raised to `capture`, each execution is retained together
+// with the input and output of every top-level pipeline step. A developer can then re-run a
+// captured execution from any step, optionally replacing the outputs of the steps before it,
+// to try out a fix without reproducing the original call. Captures hold only redacted payloads,
+// and a re-run runs with the operator's invoker, not the original caller's: an input that lost
+// values to redaction can't be re-run, and a redacted step output must be replaced to be reused.
+
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use parking_lot::Mutex;
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+use super::CompositionExecutor;
+use super::execution_id::ExecutionId;
+use super::execution_log::redact;
+
+/// Default number of captured executions retained
+pub const DEFAULT_CAPTURE_CAPACITY: usize = 20;
+
+/// Input and outcome of one captured pipeline step
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CapturedStep {
+	pub step_id: String,
+	pub input: Value,
+	/// Step output (on success)
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub output: Option<Value>,
+	/// Error message (on failure)
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub error: Option<String>,
+	pub duration_ms: u64,
+	/// Whether a re-run took the output from its original capture rather than running the step
+	#[serde(skip_serializing_if = "std::ops::Not::not")]
+	pub carried_over: bool,
+	/// Whether the output lost values to redaction, so a re-run can't carry it over as-is
+	#[serde(skip_serializing_if = "std::ops::Not::not")]
+	pub redacted: bool,
+}
+
+/// A captured composition execution
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExecutionCapture {
+	/// Identifier used to inspect and re-run the capture
+	pub id: u64,
+	/// Unique id of the captured execution
+	pub execution_id: ExecutionId,
+	pub composition: String,
+	/// Start time (milliseconds since the Unix epoch)
+	pub started_at_ms: u64,
+	pub duration_ms: u64,
+	/// Redacted composition input
+	pub input: Value,
+	/// Whether the input survived redaction intact, so the capture can be re-run
+	pub replayable: bool,
+	/// Top-level pipeline steps, in execution order
+	pub steps: Vec<CapturedStep>,
+	/// Composition output (on success)
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub output: Option<Value>,
+	/// Error message (on failure)
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub error: Option<String>,
+	/// Capture this execution re-ran, if it is a re-run
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub replay_of: Option<u64>,
+}
+
+impl ExecutionCapture {
+	fn redacted(self) -> Self {
+		let input = redact(&self.input);
+		Self {
+			replayable: self.replayable && input == self.input,
+			input,
+			steps: self
+				.steps
+				.into_iter()
+				.map(|step| {
+					let output = step.output.as_ref().map(redact);
+					CapturedStep {
+						input: redact(&step.input),
+						redacted: step.redacted || output != step.output,
+						output,
+						..step
+					}
+				})
+				.collect(),
+			output: self.output.as_ref().map(redact),
+			..self
+		}
+	}
+}
+
+/// Where a re-run picks a captured execution back up
+#[derive(Debug, Clone)]
+pub struct ResumePoint {
+	/// Step the re-run starts at
+	pub from_step: String,
+	/// Steps before it, whose outputs the re-run starts from
+	pub completed: Vec<CapturedStep>,
+	/// Id the re-run's own capture is recorded under, when re-running a capture
+	pub(super) capture_id: Option<u64>,
+	/// Capture being re-run, if any
+	pub(super) replay_of: Option<u64>,
+}
+
+/// Why a capture couldn't be re-run
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum RerunError {
+	#[error("no captured execution {0}")]
+	NotFound(u64),
+	#[error("step '{step}' does not run before '{from_step}', so its output can't be replaced")]
+	UnknownOverride { step: String, from_step: String },
+	#[error("the input of captured execution {0} had values redacted, so it can't be re-run")]
+	RedactedInput(u64),
+	#[error("the output of step '{0}' had values redacted; replace it to re-run from a later step")]
+	RedactedOutput(String),
+}
+
+/// Bounded store of captured executions; the oldest are evicted first
+pub struct ExecutionCaptures {
+	capacity: usize,
+	next_id: AtomicU64,
+	captures: Mutex<VecDeque<ExecutionCapture>>,
+}
+
+impl std::fmt::Debug for ExecutionCaptures {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		f.debug_struct("ExecutionCaptures")
+			.field("capacity", &self.capacity)
+			.field("len", &self.len())
+			.finish()
+	}
+}
+
+impl Default for ExecutionCaptures {
+	fn default() -> Self {
+		Self::new(DEFAULT_CAPTURE_CAPACITY)
+	}
+}
+
+impl ExecutionCaptures {
+	pub fn new(capacity: usize) -> Self {
+		Self {
+			capacity,
+			next_id: AtomicU64::new(1),
+			captures: Mutex::new(VecDeque::new()),
+		}
+	}
+
+	/// Number of retained captures
+	pub fn len(&self) -> usize {
+		self.captures.lock().len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.len() == 0
+	}
+
+	/// Allocate the id of the next capture
+	pub(super) fn next_id(&self) -> u64 {
+		self.next_id.fetch_add(1, Ordering::Relaxed)
+	}
+
+	/// Retain a capture, redacted
+	pub(super) fn record(&self, capture: ExecutionCapture) {
+		let capture = capture.redacted();
+		let mut captures = self.captures.lock();
+		if captures.len() >= self.capacity {
+			captures.pop_front();
+		}
+		if self.capacity > 0 {
+			captures.push_back(capture);
+		}
+	}
+
+	/// Captures, newest first, optionally only those of one composition
+	pub fn list(&self, composition: Option<&str>) -> Vec<ExecutionCapture> {
+		self
+			.captures
+			.lock()
+			.iter()
+			.rev()
+			.filter(|c| composition.is_none_or(|name| c.composition == name))
+			.cloned()
+			.collect()
+	}
+
+	/// Look up one capture
+	pub fn get(&self, id: u64) -> Option<ExecutionCapture> {
+		self.captures.lock().iter().find(|c| c.id == id).cloned()
+	}
+
+	/// Look up the capture of an execution by its execution id
+	pub fn find_execution(&self, execution_id: ExecutionId) -> Option<ExecutionCapture> {
+		self
+			.captures
+			.lock()
+			.iter()
+			.find(|c| c.execution_id == execution_id)
+			.cloned()
+	}
+
+	/// Re-run a captured execution from `from_step` with `executor`, returning the re-run's own
+	/// capture
+	///
+	/// The steps before `from_step` are not executed again: their captured outputs are used,
+	/// except where `overrides` (step id to output) replaces them. The executor's invoker is the
+	/// operator's, not the original caller's, and it must record into this store.
+	pub async fn rerun(
+		&self,
+		id: u64,
+		from_step: &str,
+		overrides: Map<String, Value>,
+		executor: CompositionExecutor,
+	) -> Result<ExecutionCapture, RerunError> {
+		let capture = self.get(id).ok_or(RerunError::NotFound(id))?;
+		if !capture.replayable {
+			return Err(RerunError::RedactedInput(id));
+		}
+
+		let mut completed: Vec<CapturedStep> = capture
+			.steps
+			.into_iter()
+			.take_while(|step| step.step_id != from_step)
+			.map(|step| CapturedStep {
+				carried_over: true,
+				duration_ms: 0,
+				..step
+			})
+			.collect();
+		for (step_id, output) in overrides {
+			let Some(step) = completed.iter_mut().find(|s| s.step_id == step_id) else {
+				return Err(RerunError::UnknownOverride {
+					step: step_id,
+					from_step: from_step.to_string(),
+				});
+			};
+			step.output = Some(output);
+			step.error = None;
+			step.redacted = false;
+		}
+		if let Some(step) = completed.iter().find(|s| s.redacted) {
+			return Err(RerunError::RedactedOutput(step.step_id.clone()));
+		}
+
+		let capture_id = self.next_id();
+		let resume = ResumePoint {
+			from_step: from_step.to_string(),
+			completed,
+			capture_id: Some(capture_id),
+			replay_of: Some(id),
+		};
+		// The outcome is part of the re-run's capture
+		let _ = executor
+			.resume(&capture.composition, capture.input, resume)
+			.await;
+		self.get(capture_id).ok_or(RerunError::NotFound(capture_id))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::sync::Arc;
+
+	use serde_json::json;
+
+	use super::*;
+	use crate::mcp::registry::executor::testing::research_pipeline;
+	use crate::mcp::registry::executor::{CompositionVerbosity, MockToolInvoker, VerbosityOverrides};
+
+	#[tokio::test]
+	async fn test_capture_and_rerun_from_step() {
+		let registry = research_pipeline();
+		let captures = Arc::new(ExecutionCaptures::default());
+		let overrides = Arc::new(VerbosityOverrides::new());
+		let invoker = Arc::new(
+			MockToolInvoker::new()
+				.with_response("search", json!({"hits": 3, "api_key": "k"}))
+				.with_response("summarize", json!({"summary": "short"}))
+				.with_error("publish", "publisher down"),
+		);
+		let executor = CompositionExecutor::new(registry.clone(), invoker.clone())
+			.with_verbosity_overrides(overrides.clone())
+			.with_captures(captures.clone());
+
+		// Nothing is captured below the capture level
+		overrides
+			.set(
+				"research",
+				CompositionVerbosity::Full,
+				std::time::Duration::from_secs(60),
+			)
+			.unwrap();
+		let result = executor.execute("research", json!({"q": "mcp"})).await;
+		assert!(result.is_err());
+		assert!(captures.is_empty());
+
+		overrides
+			.set(
+				"research",
+				CompositionVerbosity::Capture,
+				std::time::Duration::from_secs(60),
+			)
+			.unwrap();
+		let result = executor.execute("research", json!({"q": "mcp"})).await;
+		assert!(result.is_err());
+		let capture = captures.list(Some("research")).remove(0);
+		let steps: Vec<_> = capture.steps.iter().map(|s| s.step_id.as_str()).collect();
+		assert_eq!(steps, ["search", "summarize", "publish"]);
+		assert_eq!(capture.steps[1].input["hits"], 3);
+		assert_eq!(capture.steps[1].output, Some(json!({"summary": "short"})));
+		let error = capture.steps[2].error.as_deref().unwrap();
+		assert!(error.contains("publisher down"));
+		// Captures only hold redacted payloads
+		assert_ne!(capture.steps[0].output.as_ref().unwrap()["api_key"], "k");
+		assert!(capture.replayable);
+		assert!(capture.steps[0].redacted);
+		assert!(!capture.steps[1].redacted);
+
+		// Re-run from summarize with a replaced search output; publish is fixed by then
+		let fixed = Arc::new(
+			MockToolInvoker::new()
+				.with_response("summarize", json!({"summary": "long"}))
+				.with_response("publish", json!({"published": true})),
+		);
+		let rerun = captures
+			.rerun(
+				capture.id,
+				"summarize",
+				Map::from_iter([("search".to_string(), json!({"hits": 7}))]),
+				CompositionExecutor::new(registry.clone(), fixed.clone()).with_captures(captures.clone()),
+			)
+			.await
+			.unwrap();
+		assert_eq!(rerun.replay_of, Some(capture.id));
+		assert_ne!(rerun.execution_id, capture.execution_id);
+		let found = captures.find_execution(capture.execution_id).unwrap();
+		assert_eq!(found.id, capture.id);
+		assert_eq!(rerun.output, Some(json!({"published": true})));
+		assert!(rerun.steps[0].carried_over);
+		assert_eq!(rerun.steps[1].input, json!({"hits": 7}));
+		assert!(!rerun.steps[1].carried_over);
+		let called: Vec<_> = fixed.calls().into_iter().map(|(tool, _)| tool).collect();
+		assert_eq!(called, ["summarize", "publish"]);
+
+		// Only earlier steps can be overridden, and only pipelines resumed from a known step
+		assert_eq!(
+			captures
+				.rerun(
+					capture.id,
+					"summarize",
+					Map::from_iter([("publish".to_string(), json!({}))]),
+					CompositionExecutor::new(registry.clone(), fixed.clone()),
+				)
+				.await
+				.unwrap_err(),
+			RerunError::UnknownOverride {
+				step: "publish".to_string(),
+				from_step: "summarize".to_string()
+			}
+		);
+		let unknown = captures
+			.rerun(
+				capture.id,
+				"review",
+				Map::from_iter([("search".to_string(), json!({"hits": 7}))]),
+				CompositionExecutor::new(registry.clone(), fixed.clone()).with_captures(captures.clone()),
+			)
+			.await
+			.unwrap();
+		assert!(unknown.error.unwrap().contains("review"));
+		assert!(matches!(
+			captures
+				.rerun(
+					999,
+					"summarize",
+					Map::new(),
+					CompositionExecutor::new(registry.clone(), fixed.clone()),
+				)
+				.await,
+			Err(RerunError::NotFound(999))
+		));
+	}
+
+	#[tokio::test]
+	async fn test_redacted_captures_are_not_rerun_as_is() {
+		let registry = research_pipeline();
+		let captures = Arc::new(ExecutionCaptures::default());
+		let overrides = Arc::new(VerbosityOverrides::new());
+		overrides
+			.set(
+				"research",
+				CompositionVerbosity::Capture,
+				std::time::Duration::from_secs(60),
+			)
+			.unwrap();
+		let invoker = Arc::new(
+			MockToolInvoker::new()
+				.with_response("search", json!({"hits": 3, "api_key": "k"}))
+				.with_response("summarize", json!({"summary": "short"}))
+				.with_response("publish", json!({"published": true})),
+		);
+		let executor = || {
+			CompositionExecutor::new(registry.clone(), invoker.clone())
+				.with_verbosity_overrides(overrides.clone())
+				.with_captures(captures.clone())
+		};
+
+		// A redacted step output must be replaced before a later step can start from it
+		executor()
+			.execute("research", json!({"q": "mcp"}))
+			.await
+			.unwrap();
+		let capture = captures.list(None).remove(0);
+		assert_eq!(
+			captures
+				.rerun(capture.id, "publish", Map::new(), executor())
+				.await
+				.unwrap_err(),
+			RerunError::RedactedOutput("search".to_string())
+		);
+		// Steps from the re-run point on run again, so their outputs don't matter
+		let rerun = captures
+			.rerun(capture.id, "search", Map::new(), executor())
+			.await
+			.unwrap();
+		assert_eq!(rerun.output, Some(json!({"published": true})));
+
+		// An input that lost values to redaction can't be re-run at all
+		executor()
+			.execute("research", json!({"q": "mcp", "password": "hunter2"}))
+			.await
+			.unwrap();
+		let capture = captures.list(None).remove(0);
+		assert!(!capture.replayable);
+		assert_eq!(capture.input["password"], "[REDACTED]");
+		assert_eq!(
+			captures
+				.rerun(capture.id, "search", Map::new(), executor())
+				.await
+				.unwrap_err(),
+			RerunError::RedactedInput(capture.id)
+		);
+	}
+}
@@ -0,0 +1,222 @@
+// Timeout pattern executor
+//
+// Bounds the inner operation by `durationMs`. When it runs over, the inner operation is
+// dropped (cancelling its in-flight calls) and the fallback runs in its place, with the same
+// input the inner operation was given. The fallback is a step operation like any other, so it
+// can be a nested pattern, including another timeout with a fallback of its own.
+
+use std::time::Duration;
+
+use serde_json::Value;
+use tracing::{Instrument, debug, info_span};
+
+use super::context::ExecutionContext;
+use super::{CompositionExecutor, ExecutionError, PipelineExecutor};
+use crate::mcp::registry::patterns::TimeoutSpec;
+
+/// Executor for timeout patterns
+pub struct TimeoutExecutor;
+
+impl TimeoutExecutor {
+	/// Execute a timeout pattern
+	///
+	/// Returns the inner operation's result (or error) if it finishes within `durationMs`.
+	/// Otherwise runs the fallback if there is one, or fails with `message`, or with a timeout
+	/// error. Only running over triggers the fallback; an inner operation that fails in time
+	/// fails the pattern.
+	pub async fn execute(
+		spec: &TimeoutSpec,
+		input: Value,
+		ctx: &ExecutionContext,
+		executor: &CompositionExecutor,
+	) -> Result<Value, ExecutionError> {
+		let step_id = ctx.step_id.clone().unwrap_or_else(|| "timeout".to_string());
+		let inner =
+			PipelineExecutor::execute_operation(&step_id, &spec.inner, input.clone(), ctx, executor);
+		let Ok(result) =
+			tokio::time::timeout(Duration::from_millis(spec.duration_ms.into()), inner).await
+		else {
+			return Self::timed_out(spec, &step_id, input, ctx, executor).await;
+		};
+		result
+	}
+
+	async fn timed_out(
+		spec: &TimeoutSpec,
+		step_id: &str,
+		input: Value,
+		ctx: &ExecutionContext,
+		executor: &CompositionExecutor,
+	) -> Result<Value, ExecutionError> {
+		let error = ExecutionError::Timeout(spec.duration_ms);
+		let Some(fallback) = &spec.fallback else {
+			return Err(match &spec.message {
+				Some(message) => ExecutionError::PatternExecutionFailed(format!("{message} ({error})")),
+				None => error,
+			});
+		};
+		debug!(
+			target: "virtual_tools",
+			step = %step_id,
+			duration_ms = spec.duration_ms,
+			"timeout elapsed, running fallback"
+		);
+		// The fallback's result stands in for the inner one; the timeout is still reported
+		ctx.record_error(&error);
+		let span = info_span!(
+			target: "virtual_tools",
+			"timeout_fallback",
+			step = %step_id,
+			duration_ms = spec.duration_ms,
+		);
+		PipelineExecutor::execute_operation(step_id, fallback, input, ctx, executor)
+			.instrument(span)
+			.await
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::sync::Arc;
+
+	use serde_json::json;
+
+	use super::*;
+	use crate::mcp::registry::CompiledRegistry;
+	use crate::mcp::registry::executor::MockToolInvoker;
+	use crate::mcp::registry::patterns::{PatternSpec, StepOperation, ToolCall};
+	use crate::mcp::registry::types::{Registry, ToolDefinition};
+
+	fn tool(name: &str) -> Box<StepOperation> {
+		Box::new(StepOperation::Tool(ToolCall {
+			name: name.to_string(),
+		}))
+	}
+
+	fn timeout(
+		inner: Box<StepOperation>,
+		duration_ms: u32,
+		fallback: Option<Box<StepOperation>>,
+	) -> TimeoutSpec {
+		TimeoutSpec {
+			inner,
+			duration_ms,
+			fallback,
+			message: None,
+		}
+	}
+
+	fn executor(spec: TimeoutSpec, invoker: Arc<MockToolInvoker>) -> CompositionExecutor {
+		let registry = Registry::with_tool_definitions(vec![ToolDefinition::composition(
+			"lookup",
+			PatternSpec::Timeout(spec),
+		)]);
+		let compiled = Arc::new(CompiledRegistry::compile(registry).unwrap());
+		CompositionExecutor::new(compiled, invoker)
+	}
+
+	#[tokio::test]
+	async fn test_inner_result_within_timeout() {
+		let invoker = Arc::new(
+			MockToolInvoker::new()
+				.with_response("primary", json!({"source": "primary"}))
+				.with_response("cached", json!({"source": "cached"})),
+		);
+		let spec = timeout(tool("primary"), 1000, Some(tool("cached")));
+		let result = executor(spec, invoker.clone())
+			.execute("lookup", json!({"q": "x"}))
+			.await
+			.unwrap();
+		assert_eq!(result, json!({"source": "primary"}));
+		invoker.assert_not_called("cached");
+	}
+
+	#[tokio::test]
+	async fn test_fallback_runs_with_original_input() {
+		let invoker = Arc::new(
+			MockToolInvoker::new()
+				.with_response("primary", json!({"source": "primary"}))
+				.with_latency("primary", Duration::from_secs(5))
+				.with_response("cached", json!({"source": "cached"})),
+		);
+		let spec = timeout(tool("primary"), 20, Some(tool("cached")));
+		let result = executor(spec, invoker.clone())
+			.execute("lookup", json!({"q": "x"}))
+			.await
+			.unwrap();
+		assert_eq!(result, json!({"source": "cached"}));
+		assert_eq!(invoker.call_args("cached"), vec![json!({"q": "x"})]);
+	}
+
+	#[tokio::test]
+	async fn test_timeout_without_fallback() {
+		let invoker = Arc::new(
+			MockToolInvoker::new()
+				.with_response("primary", json!({}))
+				.with_latency("primary", Duration::from_secs(5)),
+		);
+		let spec = timeout(tool("primary"), 20, None);
+		let err = executor(spec, invoker.clone())
+			.execute("lookup", json!({}))
+			.await
+			.unwrap_err();
+		assert!(matches!(err, ExecutionError::Timeout(20)));
+
+		let spec = TimeoutSpec {
+			message: Some("lookup service is slow".to_string()),
+			..timeout(tool("primary"), 20, None)
+		};
+		let err = executor(spec, invoker)
+			.execute("lookup", json!({}))
+			.await
+			.unwrap_err();
+		assert_eq!(
+			err.to_string(),
+			"pattern execution failed: lookup service is slow (timeout after 20ms)"
+		);
+	}
+
+	#[tokio::test]
+	async fn test_inner_failure_does_not_fall_back() {
+		let invoker = Arc::new(
+			MockToolInvoker::new()
+				.with_error("primary", "backend exploded")
+				.with_response("cached", json!({"source": "cached"})),
+		);
+		let spec = timeout(tool("primary"), 1000, Some(tool("cached")));
+		let err = executor(spec, invoker.clone())
+			.execute("lookup", json!({}))
+			.await
+			.unwrap_err();
+		assert!(err.to_string().contains("backend exploded"));
+		invoker.assert_not_called("cached");
+	}
+
+	#[tokio::test]
+	async fn test_nested_fallback() {
+		let invoker = Arc::new(
+			MockToolInvoker::new()
+				.with_response("primary", json!({"source": "primary"}))
+				.with_latency("primary", Duration::from_secs(5))
+				.with_response("replica", json!({"source": "replica"}))
+				.with_latency("replica", Duration::from_secs(5))
+				.with_response("cached", json!({"source": "cached"})),
+		);
+		// The fallback is itself a timeout, whose own fallback answers
+		let replica = timeout(tool("replica"), 20, Some(tool("cached")));
+		let spec = timeout(
+			tool("primary"),
+			20,
+			Some(Box::new(StepOperation::Pattern(Box::new(
+				PatternSpec::Timeout(replica),
+			)))),
+		);
+		let result = executor(spec, invoker.clone())
+			.execute("lookup", json!({"q": "x"}))
+			.await
+			.unwrap();
+		assert_eq!(result, json!({"source": "cached"}));
+		assert_eq!(invoker.call_args("replica"), vec![json!({"q": "x"})]);
+		assert_eq!(invoker.call_args("cached"), vec![json!({"q": "x"})]);
+	}
+}
@@ -0,0 +1,199 @@
+// Token budgeting for aggregated results
+//
+// Counts are estimates: the gateway doesn't know which model reads the result, so the
+// tokenizers here approximate common BPE vocabularies from characters or words rather than
+// encoding the text.
+
+use serde_json::Value;
+use serde_json_path::JsonPath;
+use tracing::debug;
+
+use super::{ExecutionError, path_cache};
+use crate::mcp::registry::patterns::{TokenBudgetOp, TokenizerKind};
+
+/// Estimates the tokens a piece of text takes up in a model's context
+pub trait Tokenizer: Send + Sync {
+	/// Estimated tokens in `text`
+	fn count(&self, text: &str) -> usize;
+
+	/// Longest prefix of `text` estimated to fit in `max_tokens`
+	fn truncate<'a>(&self, text: &'a str, max_tokens: usize) -> &'a str;
+}
+
+/// One token per 4 characters
+pub struct CharTokenizer;
+
+const CHARS_PER_TOKEN: usize = 4;
+
+impl Tokenizer for CharTokenizer {
+	fn count(&self, text: &str) -> usize {
+		text.chars().count().div_ceil(CHARS_PER_TOKEN)
+	}
+
+	fn truncate<'a>(&self, text: &'a str, max_tokens: usize) -> &'a str {
+		let max_chars = max_tokens.saturating_mul(CHARS_PER_TOKEN);
+		match text.char_indices().nth(max_chars) {
+			Some((end, _)) => &text[..end],
+			None => text,
+		}
+	}
+}
+
+/// 4 tokens per 3 words
+pub struct WordTokenizer;
+
+impl Tokenizer for WordTokenizer {
+	fn count(&self, text: &str) -> usize {
+		(text.split_whitespace().count() * 4).div_ceil(3)
+	}
+
+	fn truncate<'a>(&self, text: &'a str, max_tokens: usize) -> &'a str {
+		let max_words = max_tokens.saturating_mul(3) / 4;
+		if max_words == 0 {
+			return "";
+		}
+		match text.split_whitespace().nth(max_words - 1) {
+			Some(word) => {
+				let end = word.as_ptr() as usize - text.as_ptr() as usize + word.len();
+				&text[..end]
+			},
+			None => text,
+		}
+	}
+}
+
+impl TokenizerKind {
+	/// Tokenizer that implements this estimate
+	pub fn tokenizer(&self) -> &'static dyn Tokenizer {
+		match self {
+			TokenizerKind::Chars => &CharTokenizer,
+			TokenizerKind::Words => &WordTokenizer,
+		}
+	}
+}
+
+/// Keep the leading results whose estimated tokens fit the budget of `op`
+pub fn apply_token_budget(
+	arr: &[Value],
+	op: &TokenBudgetOp,
+	tokenizer: &dyn Tokenizer,
+) -> Result<Vec<Value>, ExecutionError> {
+	let text_field = op
+		.text_field
+		.as_deref()
+		.map(path_cache::parse)
+		.transpose()
+		.map_err(|e| e.in_field("textField"))?;
+
+	let budget = op.max_tokens as usize;
+	let mut used = 0;
+	let mut kept = Vec::with_capacity(arr.len());
+	for item in arr {
+		let tokens = match &text_field {
+			Some(path) => text_tokens(path, item, tokenizer),
+			None => tokenizer.count(&item.to_string()),
+		};
+		if used + tokens <= budget {
+			used += tokens;
+			kept.push(item.clone());
+			continue;
+		}
+		if op.truncate
+			&& let Some(path) = &text_field
+			&& let Some(item) = truncate_text(path, item, tokenizer, budget - used)
+		{
+			kept.push(item);
+		}
+		break;
+	}
+
+	if kept.len() < arr.len() {
+		debug!(
+			target: "virtual_tools",
+			max_tokens = op.max_tokens,
+			kept = kept.len(),
+			dropped = arr.len() - kept.len(),
+			"tokenBudget dropped results over budget"
+		);
+	}
+	Ok(kept)
+}
+
+/// Estimated tokens of the text at `path`; a non-string value counts as compact JSON and a
+/// missing one as nothing
+fn text_tokens(path: &JsonPath, item: &Value, tokenizer: &dyn Tokenizer) -> usize {
+	match path.query(item).first() {
+		Some(Value::String(s)) => tokenizer.count(s),
+		Some(Value::Null) | None => 0,
+		Some(other) => tokenizer.count(&other.to_string()),
+	}
+}
+
+/// `item` with the string at `path` shortened to `max_tokens`, or `None` if there's no string
+/// to shorten or no room for any of it
+fn truncate_text(
+	path: &JsonPath,
+	item: &Value,
+	tokenizer: &dyn Tokenizer,
+	max_tokens: usize,
+) -> Option<Value> {
+	let located = path.query_located(item);
+	let node = located.first()?;
+	let prefix = tokenizer.truncate(node.node().as_str()?, max_tokens);
+	if prefix.is_empty() {
+		return None;
+	}
+	let prefix = prefix.to_string();
+	let pointer = node.location().to_json_pointer();
+	let mut item = item.clone();
+	*item.pointer_mut(&pointer)? = Value::String(prefix);
+	Some(item)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use serde_json::json;
+
+	fn op(max_tokens: u32, text_field: Option<&str>, truncate: bool) -> TokenBudgetOp {
+		TokenBudgetOp {
+			max_tokens,
+			text_field: text_field.map(str::to_string),
+			truncate,
+			tokenizer: TokenizerKind::Chars,
+		}
+	}
+
+	#[test]
+	fn test_tokenizers() {
+		assert_eq!(CharTokenizer.count("abcdefghi"), 3);
+		assert_eq!(CharTokenizer.truncate("abcdefghi", 2), "abcdefgh");
+		assert_eq!(CharTokenizer.truncate("héllo", 1), "héll");
+		assert_eq!(WordTokenizer.count("one two three"), 4);
+		assert_eq!(WordTokenizer.truncate("one  two three four", 3), "one  two");
+		assert_eq!(WordTokenizer.truncate("one two", 1), "");
+		assert_eq!(WordTokenizer.truncate("one two", 8), "one two");
+	}
+
+	#[test]
+	fn test_token_budget_drops_trailing_results() {
+		let results = vec![
+			json!({"text": "aaaaaaaa"}),
+			json!({"text": "bbbbbbbb"}),
+			json!({"text": "cccccccc"}),
+		];
+
+		// 2 tokens each; the third no longer fits
+		let kept = apply_token_budget(&results, &op(5, Some("$.text"), false), &CharTokenizer).unwrap();
+		assert_eq!(kept, results[..2]);
+
+		// Truncating keeps what fits of the first one over budget
+		let kept = apply_token_budget(&results, &op(5, Some("$.text"), true), &CharTokenizer).unwrap();
+		assert_eq!(kept[..2], results[..2]);
+		assert_eq!(kept[2], json!({"text": "cccc"}));
+
+		// Without a text field the whole result is counted: {"text":"aaaaaaaa"} is 5 tokens
+		let kept = apply_token_budget(&results, &op(10, None, true), &CharTokenizer).unwrap();
+		assert_eq!(kept.len(), 2);
+	}
+}
@@ -0,0 +1,223 @@
+// Runtime-adjustable composition debug verbosity
+
+use std::collections::{HashMap, HashSet};
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+
+/// Request header a trusted caller sets to raise verbosity for its own composition calls
+pub const VERBOSITY_HEADER: &str = "x-composition-verbosity";
+
+/// How much a composition logs about its own execution
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum CompositionVerbosity {
+	/// No per-execution logging
+	#[default]
+	Off,
+	/// One line per composition execution (name, duration, outcome)
+	Summary,
+	/// Summary plus one line per step
+	Steps,
+	/// Steps plus redacted inputs and outputs
+	Full,
+	/// Full, and each execution is retained with its step inputs and outputs so it can be re-run
+	/// from any step
+	Capture,
+}
+
+impl FromStr for CompositionVerbosity {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		match s.to_ascii_lowercase().as_str() {
+			"off" => Ok(Self::Off),
+			"summary" => Ok(Self::Summary),
+			"steps" => Ok(Self::Steps),
+			"full" => Ok(Self::Full),
+			"capture" => Ok(Self::Capture),
+			other => Err(format!(
+				"unknown verbosity '{other}', expected off|summary|steps|full|capture"
+			)),
+		}
+	}
+}
+
+/// An active verbosity override
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VerbosityOverride {
+	/// Verbosity in effect for the tool
+	pub verbosity: CompositionVerbosity,
+	/// Time remaining before the override reverts
+	pub remaining_secs: u64,
+}
+
+/// Per-tool verbosity overrides that revert automatically after a TTL
+#[derive(Debug, Default)]
+pub struct VerbosityOverrides {
+	entries: Mutex<HashMap<String, (CompositionVerbosity, Instant)>>,
+	/// JWT subjects whose `x-composition-verbosity` header is honored
+	trusted_callers: HashSet<String>,
+}
+
+impl VerbosityOverrides {
+	/// Create an empty set of overrides
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Builder: honor the verbosity header of callers with these JWT subjects
+	pub fn with_trusted_callers(mut self, subjects: impl IntoIterator<Item = String>) -> Self {
+		self.trusted_callers = subjects.into_iter().collect();
+		self
+	}
+
+	/// Override verbosity for a tool until `ttl` elapses
+	pub fn set(
+		&self,
+		tool: &str,
+		verbosity: CompositionVerbosity,
+		ttl: Duration,
+	) -> Result<(), String> {
+		let expires = Instant::now()
+			.checked_add(ttl)
+			.ok_or_else(|| format!("ttl of {}s is too long", ttl.as_secs()))?;
+		self
+			.entries
+			.lock()
+			.insert(tool.to_string(), (verbosity, expires));
+		Ok(())
+	}
+
+	/// Verbosity a caller asked for with the verbosity header, if the caller is trusted
+	///
+	/// Untrusted and anonymous callers' headers are ignored, as are unparseable values.
+	pub fn requested(
+		&self,
+		subject: Option<&str>,
+		header: Option<&http::HeaderValue>,
+	) -> Option<CompositionVerbosity> {
+		if !self.trusted_callers.contains(subject?) {
+			return None;
+		}
+		header?.to_str().ok()?.parse().ok()
+	}
+
+	/// Remove the override for a tool, returning whether one was active
+	pub fn clear(&self, tool: &str) -> bool {
+		self.entries.lock().remove(tool).is_some()
+	}
+
+	/// Remove all overrides
+	pub fn clear_all(&self) {
+		self.entries.lock().clear();
+	}
+
+	/// Get the active override for a tool, dropping it if expired
+	pub fn get(&self, tool: &str) -> Option<CompositionVerbosity> {
+		let mut entries = self.entries.lock();
+		match entries.get(tool) {
+			Some((v, expires)) if *expires > Instant::now() => Some(*v),
+			Some(_) => {
+				entries.remove(tool);
+				None
+			},
+			None => None,
+		}
+	}
+
+	/// Active overrides, with expired entries pruned
+	pub fn snapshot(&self) -> HashMap<String, VerbosityOverride> {
+		let now = Instant::now();
+		let mut entries = self.entries.lock();
+		entries.retain(|_, (_, expires)| *expires > now);
+		entries
+			.iter()
+			.map(|(tool, (verbosity, expires))| {
+				(
+					tool.clone(),
+					VerbosityOverride {
+						verbosity: *verbosity,
+						remaining_secs: expires.duration_since(now).as_secs(),
+					},
+				)
+			})
+			.collect()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_parse_verbosity() {
+		assert_eq!(
+			"Steps".parse::<CompositionVerbosity>().unwrap(),
+			CompositionVerbosity::Steps
+		);
+		assert!("loud".parse::<CompositionVerbosity>().is_err());
+		assert!(CompositionVerbosity::Full > CompositionVerbosity::Summary);
+	}
+
+	#[test]
+	fn test_override_set_and_clear() {
+		let overrides = VerbosityOverrides::new();
+		assert_eq!(overrides.get("research_pipeline"), None);
+
+		overrides
+			.set(
+				"research_pipeline",
+				CompositionVerbosity::Full,
+				Duration::from_secs(60),
+			)
+			.unwrap();
+		assert_eq!(
+			overrides.get("research_pipeline"),
+			Some(CompositionVerbosity::Full)
+		);
+		assert_eq!(overrides.snapshot().len(), 1);
+
+		assert!(overrides.clear("research_pipeline"));
+		assert_eq!(overrides.get("research_pipeline"), None);
+	}
+
+	#[test]
+	fn test_override_expires() {
+		let overrides = VerbosityOverrides::new();
+		overrides
+			.set("chatty", CompositionVerbosity::Steps, Duration::ZERO)
+			.unwrap();
+		assert_eq!(overrides.get("chatty"), None);
+		assert!(overrides.snapshot().is_empty());
+	}
+
+	#[test]
+	fn test_override_ttl_overflow() {
+		let overrides = VerbosityOverrides::new();
+		assert!(
+			overrides
+				.set("chatty", CompositionVerbosity::Steps, Duration::MAX)
+				.is_err()
+		);
+		assert!(overrides.snapshot().is_empty());
+	}
+
+	#[test]
+	fn test_header_honored_for_trusted_callers() {
+		let overrides = VerbosityOverrides::new().with_trusted_callers(["alice".to_string()]);
+		let full = http::HeaderValue::from_static("full");
+		assert_eq!(
+			overrides.requested(Some("alice"), Some(&full)),
+			Some(CompositionVerbosity::Full)
+		);
+		assert_eq!(overrides.requested(Some("mallory"), Some(&full)), None);
+		assert_eq!(overrides.requested(None, Some(&full)), None);
+		assert_eq!(overrides.requested(Some("alice"), None), None);
+		let loud = http::HeaderValue::from_static("loud");
+		assert_eq!(overrides.requested(Some("alice"), Some(&loud)), None);
+	}
+}
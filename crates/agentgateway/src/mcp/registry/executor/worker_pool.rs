@@ -0,0 +1,183 @@
+// Bounded worker pool for composition execution
+
+use std::future::Future;
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+use super::ExecutionError;
+
+/// Default number of compositions executing at once
+pub const DEFAULT_MAX_WORKERS: usize = 64;
+
+/// Default number of workers batch compositions may occupy
+pub const DEFAULT_MAX_BATCH_WORKERS: usize = 32;
+
+/// Lane a composition execution is queued in
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ExecutionPriority {
+	/// Latency-sensitive calls made by agents over MCP
+	#[default]
+	Interactive,
+	/// Scheduled or webhook-triggered executions
+	Batch,
+}
+
+/// Worker pool shared by every composition execution.
+///
+/// Interactive executions may use any worker. Batch executions are additionally limited to
+/// `max_batch_workers`, so the remaining workers are always available to interactive
+/// requests no matter how much batch work is queued.
+#[derive(Debug)]
+pub struct WorkerPool {
+	workers: Arc<Semaphore>,
+	batch_workers: Arc<Semaphore>,
+	max_workers: usize,
+	max_batch_workers: usize,
+}
+
+/// A reserved worker, released on drop
+#[derive(Debug)]
+pub struct WorkerPermit {
+	_worker: OwnedSemaphorePermit,
+	_batch: Option<OwnedSemaphorePermit>,
+}
+
+impl Default for WorkerPool {
+	fn default() -> Self {
+		Self::new(DEFAULT_MAX_WORKERS, DEFAULT_MAX_BATCH_WORKERS)
+	}
+}
+
+impl WorkerPool {
+	/// Create a pool running up to `max_workers` executions, at most `max_batch_workers` of
+	/// them batch. Both limits are at least 1, and the batch limit never exceeds the total.
+	pub fn new(max_workers: usize, max_batch_workers: usize) -> Self {
+		let max_workers = max_workers.max(1);
+		let max_batch_workers = max_batch_workers.clamp(1, max_workers);
+		Self {
+			workers: Arc::new(Semaphore::new(max_workers)),
+			batch_workers: Arc::new(Semaphore::new(max_batch_workers)),
+			max_workers,
+			max_batch_workers,
+		}
+	}
+
+	/// Maximum number of concurrent executions
+	pub fn max_workers(&self) -> usize {
+		self.max_workers
+	}
+
+	/// Maximum number of concurrent batch executions
+	pub fn max_batch_workers(&self) -> usize {
+		self.max_batch_workers
+	}
+
+	/// Wait for a worker in the given lane
+	pub async fn acquire(&self, priority: ExecutionPriority) -> WorkerPermit {
+		// Batch executions take their lane permit first so they queue behind each other
+		// rather than holding workers that interactive executions could use
+		let batch = match priority {
+			ExecutionPriority::Interactive => None,
+			ExecutionPriority::Batch => Some(
+				self
+					.batch_workers
+					.clone()
+					.acquire_owned()
+					.await
+					.expect("worker pool semaphore is never closed"),
+			),
+		};
+		let worker = self
+			.workers
+			.clone()
+			.acquire_owned()
+			.await
+			.expect("worker pool semaphore is never closed");
+		WorkerPermit {
+			_worker: worker,
+			_batch: batch,
+		}
+	}
+
+	/// Run an execution on its own task while holding a worker
+	pub async fn spawn<F, T>(&self, permit: WorkerPermit, fut: F) -> Result<T, ExecutionError>
+	where
+		F: Future<Output = T> + Send + 'static,
+		T: Send + 'static,
+	{
+		tokio::spawn(async move {
+			let _permit = permit;
+			fut.await
+		})
+		.await
+		.map_err(|e| ExecutionError::Internal(format!("composition task panicked: {e}")))
+	}
+
+	/// Wait for a worker in the given lane, then run the execution
+	pub async fn run<F, T>(&self, priority: ExecutionPriority, fut: F) -> Result<T, ExecutionError>
+	where
+		F: Future<Output = T> + Send + 'static,
+		T: Send + 'static,
+	{
+		let permit = self.acquire(priority).await;
+		self.spawn(permit, fut).await
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::time::Duration;
+
+	use super::*;
+
+	#[tokio::test]
+	async fn test_batch_cannot_starve_interactive() {
+		let pool = WorkerPool::new(2, 1);
+		let _batch = pool.acquire(ExecutionPriority::Batch).await;
+
+		// A second batch execution waits for the batch lane...
+		let blocked = tokio::time::timeout(
+			Duration::from_millis(20),
+			pool.acquire(ExecutionPriority::Batch),
+		)
+		.await;
+		assert!(blocked.is_err());
+
+		// ...while an interactive one still gets the reserved worker
+		let interactive = tokio::time::timeout(
+			Duration::from_millis(20),
+			pool.acquire(ExecutionPriority::Interactive),
+		)
+		.await;
+		assert!(interactive.is_ok());
+	}
+
+	#[tokio::test]
+	async fn test_run_releases_worker() {
+		let pool = WorkerPool::new(1, 1);
+		assert_eq!(
+			pool
+				.run(ExecutionPriority::Batch, async { 1 })
+				.await
+				.unwrap(),
+			1
+		);
+		assert_eq!(
+			pool
+				.run(ExecutionPriority::Interactive, async { 2 })
+				.await
+				.unwrap(),
+			2
+		);
+	}
+
+	#[test]
+	fn test_limits_are_clamped() {
+		let pool = WorkerPool::new(0, 10);
+		assert_eq!(pool.max_workers(), 1);
+		assert_eq!(pool.max_batch_workers(), 1);
+	}
+}
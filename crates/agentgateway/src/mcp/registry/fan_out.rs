@@ -0,0 +1,300 @@
+// Worst-case fan-out of compositions
+//
+// A mapEach runs its inner operation once per element of its input, so a mapEach around a
+// wide scatter-gather or another mapEach multiplies the calls one composition makes. Without
+// `maxItems` nothing bounds the input array, and a large upstream result can turn a single
+// call into thousands of backend calls. The estimate here assumes `ASSUMED_ITEMS` elements
+// for every array without a bound, and flags the mapEach patterns it multiplies out at.
+//
+// Only first attempts are counted (retries are ignored), and a call to another composition
+// counts as a single call.
+
+use super::patterns::{MapEachInner, PatternSpec, ScatterOperation, StepOperation};
+
+/// Elements assumed for an input array that `maxItems` doesn't bound
+pub const ASSUMED_ITEMS: u64 = 100;
+
+/// Calls per element at which a mapEach over an unbounded array is flagged
+pub const WIDE_FAN_OUT: u64 = 3;
+
+/// A mapEach whose worst-case call count multiplies out
+#[derive(Debug, Clone, PartialEq)]
+pub struct FanOutWarning {
+	/// JSON pointer to the mapEach pattern
+	pub pointer: String,
+	/// Estimated worst-case tool calls of one run of the mapEach
+	pub calls: u64,
+	pub message: String,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct Estimate {
+	calls: u64,
+	/// Whether a mapEach runs somewhere inside
+	map_each: bool,
+}
+
+impl Estimate {
+	fn calls(calls: u64) -> Self {
+		Self {
+			calls,
+			map_each: false,
+		}
+	}
+
+	fn then(self, other: Estimate) -> Self {
+		Self {
+			calls: self.calls.saturating_add(other.calls),
+			map_each: self.map_each || other.map_each,
+		}
+	}
+}
+
+/// Estimated worst-case tool calls of one run of `spec`
+pub fn estimate(spec: &PatternSpec) -> u64 {
+	pattern(spec, "", &mut Vec::new()).calls
+}
+
+/// Flag each mapEach over an unbounded array whose inner operation fans out widely or runs
+/// another mapEach; `pointer` is the JSON pointer to `spec`
+///
+/// A mapEach inside one that is already flagged isn't flagged again, since the outer
+/// warning's estimate covers it.
+pub fn check(spec: &PatternSpec, pointer: &str) -> Vec<FanOutWarning> {
+	let mut warnings = Vec::new();
+	pattern(spec, pointer, &mut warnings);
+	warnings
+}
+
+fn pattern(spec: &PatternSpec, pointer: &str, warnings: &mut Vec<FanOutWarning>) -> Estimate {
+	match spec {
+		PatternSpec::Pipeline(p) => {
+			p.steps
+				.iter()
+				.enumerate()
+				.fold(Estimate::default(), |acc, (i, step)| {
+					let pointer = format!("{}/pipeline/steps/{}/operation", pointer, i);
+					acc.then(operation(&step.operation, &pointer, warnings))
+				})
+		},
+		PatternSpec::ScatterGather(sg) => {
+			let mut targets: Vec<Estimate> = sg
+				.targets
+				.iter()
+				.enumerate()
+				.map(|(i, target)| match &target.operation {
+					ScatterOperation::Tool(_) => Estimate::calls(1),
+					ScatterOperation::Pattern(p) => {
+						let pointer = format!("{}/scatterGather/targets/{}/pattern", pointer, i);
+						pattern(p, &pointer, warnings)
+					},
+				})
+				.collect();
+			// A sampled scatter-gather invokes at most `always + draw` targets; assume the
+			// costliest ones
+			if let Some(sample) = &sg.sample {
+				targets.sort_by_key(|t| std::cmp::Reverse(t.calls));
+				targets.truncate(sample.always.saturating_add(sample.draw) as usize);
+			}
+			targets
+				.into_iter()
+				.fold(Estimate::default(), Estimate::then)
+		},
+		PatternSpec::MapEach(me) => {
+			let mut inner_warnings = Vec::new();
+			let inner = match &me.inner {
+				MapEachInner::Tool(_) => Estimate::calls(1),
+				MapEachInner::Pattern(p) => {
+					let pointer = format!("{}/mapEach/inner/pattern", pointer);
+					pattern(p, &pointer, &mut inner_warnings)
+				},
+			};
+			let items = me.max_items.map(u64::from).unwrap_or(ASSUMED_ITEMS);
+			let calls = inner.calls.saturating_mul(items);
+			if me.max_items.is_none() && (inner.map_each || inner.calls >= WIDE_FAN_OUT) {
+				let reason = if inner.map_each {
+					"runs another mapEach for each item".to_string()
+				} else {
+					format!("makes {} calls for each item", inner.calls)
+				};
+				warnings.push(FanOutWarning {
+					pointer: format!("{}/mapEach", pointer),
+					calls,
+					message: format!(
+						"mapEach over an array without maxItems {}; assuming {} items per unbounded array, it could make {} tool calls (set maxItems to bound it)",
+						reason, ASSUMED_ITEMS, calls
+					),
+				});
+			} else {
+				warnings.extend(inner_warnings);
+			}
+			Estimate {
+				calls,
+				map_each: true,
+			}
+		},
+		PatternSpec::Filter(_) | PatternSpec::SchemaMap(_) => Estimate::default(),
+		PatternSpec::Retry(r) => operation(&r.inner, &format!("{}/retry/inner", pointer), warnings),
+		PatternSpec::Cache(c) => operation(&c.inner, &format!("{}/cache/inner", pointer), warnings),
+		PatternSpec::Timeout(t) => {
+			let inner = operation(&t.inner, &format!("{}/timeout/inner", pointer), warnings);
+			match &t.fallback {
+				Some(f) => inner.then(operation(
+					f,
+					&format!("{}/timeout/fallback", pointer),
+					warnings,
+				)),
+				None => inner,
+			}
+		},
+		PatternSpec::CircuitBreaker(cb) => {
+			let pointer = format!("{}/circuitBreaker", pointer);
+			let inner = operation(&cb.inner, &format!("{}/inner", pointer), warnings);
+			match &cb.fallback {
+				Some(f) => inner.then(operation(f, &format!("{}/fallback", pointer), warnings)),
+				None => inner,
+			}
+		},
+		PatternSpec::DeadLetter(dl) => {
+			let inner = operation(
+				&dl.inner,
+				&format!("{}/deadLetter/inner", pointer),
+				warnings,
+			);
+			inner.then(Estimate::calls(
+				1 + u64::from(dl.claim_check_tool.is_some()),
+			))
+		},
+		// Every tool the pattern references, called once
+		other => Estimate::calls(other.referenced_tools().len() as u64),
+	}
+}
+
+fn operation(op: &StepOperation, pointer: &str, warnings: &mut Vec<FanOutWarning>) -> Estimate {
+	match op {
+		StepOperation::Tool(_) | StepOperation::Composition(_) => Estimate::calls(1),
+		StepOperation::Pattern(p) => pattern(p, &format!("{}/pattern", pointer), warnings),
+		StepOperation::Assert(_)
+		| StepOperation::Script(_)
+		| StepOperation::Sample(_)
+		| StepOperation::Patch(_)
+		| StepOperation::Constant(_) => Estimate::default(),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::mcp::registry::patterns::{
+		AggregationStrategy, MapEachSpec, PipelineSpec, PipelineStep, ScatterGatherSpec, ScatterTarget,
+		TargetSample, ToolCall,
+	};
+
+	fn scatter(targets: usize) -> PatternSpec {
+		PatternSpec::ScatterGather(ScatterGatherSpec {
+			targets: (0..targets)
+				.map(|i| ScatterTarget::tool(format!("search_{}", i)))
+				.collect(),
+			aggregation: AggregationStrategy::default(),
+			timeout_ms: None,
+			fail_fast: false,
+			sample: None,
+		})
+	}
+
+	fn step(id: &str, operation: StepOperation) -> PipelineStep {
+		PipelineStep {
+			id: id.to_string(),
+			operation,
+			input: None,
+			session_set: None,
+			retry: None,
+			cache: None,
+		}
+	}
+
+	#[test]
+	fn test_wide_scatter_gather_per_item() {
+		let spec = PatternSpec::Pipeline(PipelineSpec {
+			steps: vec![
+				step(
+					"list",
+					StepOperation::Tool(ToolCall {
+						name: "list_documents".to_string(),
+					}),
+				),
+				step(
+					"search",
+					StepOperation::Pattern(Box::new(PatternSpec::MapEach(MapEachSpec::pattern(
+						scatter(4),
+					)))),
+				),
+			],
+		});
+
+		assert_eq!(estimate(&spec), 401);
+		let warnings = check(&spec, "/tools/0/spec");
+		assert_eq!(warnings.len(), 1);
+		assert_eq!(
+			warnings[0].pointer,
+			"/tools/0/spec/pipeline/steps/1/operation/pattern/mapEach"
+		);
+		assert_eq!(warnings[0].calls, 400);
+		assert!(warnings[0].message.contains("makes 4 calls for each item"));
+
+		// A bound removes the warning and scales the estimate
+		let spec = PatternSpec::MapEach(MapEachSpec::pattern(scatter(4)).with_max_items(10));
+		assert_eq!(estimate(&spec), 40);
+		assert!(check(&spec, "/tools/0/spec").is_empty());
+
+		// So does sampling the targets
+		let PatternSpec::ScatterGather(sg) = scatter(4) else {
+			unreachable!()
+		};
+		let sampled = PatternSpec::ScatterGather(sg.with_sample(TargetSample {
+			always: 1,
+			draw: 1,
+			seed: None,
+		}));
+		assert_eq!(
+			estimate(&PatternSpec::MapEach(MapEachSpec::pattern(sampled))),
+			200
+		);
+	}
+
+	#[test]
+	fn test_nested_map_each() {
+		let spec = PatternSpec::MapEach(MapEachSpec::pattern(PatternSpec::MapEach(
+			MapEachSpec::pattern(scatter(5)),
+		)));
+
+		// Only the outermost mapEach is flagged, with the estimate of the whole nest
+		let warnings = check(&spec, "/tools/2/spec");
+		assert_eq!(warnings.len(), 1);
+		assert_eq!(warnings[0].pointer, "/tools/2/spec/mapEach");
+		assert_eq!(warnings[0].calls, 50_000);
+		assert!(warnings[0].message.contains("runs another mapEach"));
+
+		// Bounding the outer array leaves the inner one to be flagged
+		let spec = PatternSpec::MapEach(
+			MapEachSpec::pattern(PatternSpec::MapEach(MapEachSpec::tool("fetch"))).with_max_items(5),
+		);
+		assert!(check(&spec, "").is_empty());
+		let spec = PatternSpec::MapEach(
+			MapEachSpec::pattern(PatternSpec::MapEach(MapEachSpec::pattern(scatter(3))))
+				.with_max_items(5),
+		);
+		let warnings = check(&spec, "");
+		assert_eq!(warnings.len(), 1);
+		assert_eq!(warnings[0].pointer, "/mapEach/inner/pattern/mapEach");
+		assert_eq!(warnings[0].calls, 300);
+	}
+
+	#[test]
+	fn test_narrow_map_each_is_not_flagged() {
+		let spec = PatternSpec::MapEach(MapEachSpec::pattern(scatter(2)));
+		assert_eq!(estimate(&spec), 200);
+		assert!(check(&spec, "").is_empty());
+		assert!(check(&PatternSpec::MapEach(MapEachSpec::tool("fetch")), "").is_empty());
+	}
+}
@@ -0,0 +1,664 @@
+// Caller-supplied idempotency keys
+//
+// Agents retry calls with side effects after timeouts and restarts, and every agent framework
+// hand-rolls its own "don't do this twice". A composition with `idempotency` accepts a key
+// under `agentgateway.dev/idempotencyKey` in the call's `_meta`. The first call with a key
+// runs; a later call from the same caller with the same key is a replay, and gets the first
+// call's result (`onDuplicate: cached`), an empty result (`skip`) or an error (`error`) for
+// the key's TTL. A replay that arrives while the first call is still running is refused
+// rather than run, and a call that fails releases its key so it can be retried under it.
+// Reusing a key with different arguments is an error.
+//
+// Keys are held in a bounded map: once it is full, the least recently used key is evicted
+// to make room, and its call can then run again. Completed keys are exported in state
+// snapshots through `StateStore`, keyed by the JSON array of caller, tool and key; keys of
+// calls still in flight belong to this instance and aren't.
+//
+// Gateways in several regions can share keys through a strongly consistent view of a
+// `TieredStore`. A call then also claims its key there, so only one gateway runs it, and its
+// result is written back for the others to replay. A call that fails releases its shared claim;
+// one that is cancelled leaves it to lapse with the key's TTL.
+
+use std::future::Future;
+use std::time::{Duration, Instant};
+
+use async_trait::async_trait;
+use parking_lot::Mutex;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use tracing::{debug, warn};
+
+use super::executor::ExecutionError;
+use super::patterns::OnDuplicate;
+use super::types::Idempotency;
+use crate::stateful::{
+	BoundedMap, EvictionStats, StateStore, StateStoreExt, StoreError, TieredStore,
+};
+
+/// `_meta` key holding a call's idempotency key
+pub const IDEMPOTENCY_KEY_META_KEY: &str = "agentgateway.dev/idempotencyKey";
+
+/// `_meta` key set to `true` on the results of replays
+pub const IDEMPOTENT_REPLAY_META_KEY: &str = "agentgateway.dev/idempotentReplay";
+
+/// Longest idempotency key, in bytes
+pub const MAX_IDEMPOTENCY_KEY_LEN: usize = 255;
+
+/// Default for the most idempotency keys held at once
+pub const DEFAULT_MAX_IDEMPOTENCY_KEYS: usize = 100_000;
+
+/// Read the idempotency key from a request's `_meta`, if it has one
+pub fn idempotency_key_from_meta(
+	meta: &Map<String, Value>,
+) -> Result<Option<String>, ExecutionError> {
+	let Some(key) = meta.get(IDEMPOTENCY_KEY_META_KEY) else {
+		return Ok(None);
+	};
+	let invalid =
+		|msg: &str| ExecutionError::InvalidInput(format!("{IDEMPOTENCY_KEY_META_KEY}: {msg}"));
+	match key {
+		Value::String(key) if key.is_empty() => Err(invalid("must not be empty")),
+		Value::String(key) if key.len() > MAX_IDEMPOTENCY_KEY_LEN => Err(invalid(&format!(
+			"longer than {MAX_IDEMPOTENCY_KEY_LEN} bytes"
+		))),
+		Value::String(key) => Ok(Some(key.clone())),
+		_ => Err(invalid("expected a string")),
+	}
+}
+
+/// Which calls are replays of each other
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct IdempotencyKey {
+	caller: String,
+	tool: String,
+	key: String,
+}
+
+impl IdempotencyKey {
+	pub fn new(caller: impl Into<String>, tool: impl Into<String>, key: impl Into<String>) -> Self {
+		Self {
+			caller: caller.into(),
+			tool: tool.into(),
+			key: key.into(),
+		}
+	}
+
+	/// The key of this idempotency key in state snapshots
+	fn store_key(&self) -> String {
+		serde_json::to_string(&(&self.caller, &self.tool, &self.key)).expect("strings serialize")
+	}
+
+	/// The key of this idempotency key in a store shared with other gateways
+	fn shared_key(&self) -> String {
+		format!("idempotency/{}", self.store_key())
+	}
+
+	fn from_store_key(key: &str) -> Result<Self, StoreError> {
+		let (caller, tool, key): (String, String, String) = serde_json::from_str(key)
+			.map_err(|e| StoreError::Serialization(format!("idempotency key '{key}': {e}")))?;
+		Ok(Self::new(caller, tool, key))
+	}
+}
+
+/// What a call with an idempotency key got
+#[derive(Debug, Clone, PartialEq)]
+pub enum IdempotentOutcome<T> {
+	/// The call ran
+	Ran(T),
+	/// A replay, answered with the first call's result
+	Replayed(T),
+	/// A replay, answered with nothing (`onDuplicate: skip`)
+	Skipped,
+}
+
+enum State<T> {
+	InFlight,
+	Done(T),
+}
+
+struct Entry<T> {
+	/// Arguments of the first call
+	args: String,
+	state: State<T>,
+}
+
+/// A completed key as carried in a state snapshot
+#[derive(Serialize, Deserialize)]
+struct StoredEntry<T> {
+	args: String,
+	result: T,
+}
+
+/// A key as held in a store shared with other gateways, without a result while in flight
+#[derive(Serialize, Deserialize)]
+struct SharedEntry<T> {
+	args: String,
+	result: Option<T>,
+}
+
+/// Idempotency keys of calls in flight or completed within their TTL
+pub struct IdempotencyStore<T> {
+	entries: Mutex<BoundedMap<IdempotencyKey, Entry<T>>>,
+	/// Store the keys are shared through with other gateways
+	shared: Option<TieredStore>,
+}
+
+impl<T> std::fmt::Debug for IdempotencyStore<T> {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		f.debug_struct("IdempotencyStore")
+			.field("len", &self.entries.lock().len())
+			.finish()
+	}
+}
+
+impl<T> Default for IdempotencyStore<T> {
+	fn default() -> Self {
+		Self {
+			entries: Mutex::new(BoundedMap::new(Some(DEFAULT_MAX_IDEMPOTENCY_KEYS))),
+			shared: None,
+		}
+	}
+}
+
+impl<T: Clone> IdempotencyStore<T> {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Create a store holding at most `max_keys` keys
+	pub fn with_max_keys(max_keys: usize) -> Self {
+		Self {
+			entries: Mutex::new(BoundedMap::new(Some(max_keys))),
+			shared: None,
+		}
+	}
+
+	/// Also claim keys in `shared`, so gateways sharing it run each call once
+	pub fn with_shared(mut self, shared: TieredStore) -> Self {
+		self.shared = Some(shared);
+		self
+	}
+
+	/// The store keys are shared through, if any
+	pub fn shared(&self) -> Option<&TieredStore> {
+		self.shared.as_ref()
+	}
+
+	/// The most keys held at once
+	pub fn max_keys(&self) -> usize {
+		self
+			.entries
+			.lock()
+			.max_entries()
+			.unwrap_or(DEFAULT_MAX_IDEMPOTENCY_KEYS)
+	}
+
+	/// Run `call`, unless it replays an earlier call with the same key
+	///
+	/// Errors of `call` are returned as they are; a refused replay is an error as well.
+	pub async fn call<F>(
+		&self,
+		key: IdempotencyKey,
+		args: &Value,
+		idempotency: &Idempotency,
+		call: F,
+	) -> Result<IdempotentOutcome<T>, String>
+	where
+		F: Future<Output = Result<T, String>>,
+		T: Serialize + DeserializeOwned + Send + Sync,
+	{
+		let args = args.to_string();
+		{
+			let mut entries = self.entries.lock();
+			if let Some(entry) = entries.get(&key) {
+				debug!(target: "virtual_tools", tool = %key.tool, "idempotency key replayed");
+				let result = match &entry.state {
+					State::InFlight => None,
+					State::Done(result) => Some(result),
+				};
+				return replay(&key, entry.args == args, result, idempotency.on_duplicate);
+			}
+			entries.insert(
+				key.clone(),
+				Entry {
+					args: args.clone(),
+					state: State::InFlight,
+				},
+				None,
+			);
+		}
+
+		// Releases the key if the call fails or is cancelled
+		let mut claim = Claim {
+			entries: &self.entries,
+			key: Some(key.clone()),
+		};
+		if let Some(shared) = &self.shared
+			&& let Some(outcome) = claim_shared(shared, &key, &args, idempotency).await?
+		{
+			return Ok(outcome);
+		}
+		let value = match call.await {
+			Ok(value) => value,
+			Err(e) => {
+				if let Some(shared) = &self.shared
+					&& let Err(error) = shared.delete(&key.shared_key()).await
+				{
+					warn!(
+						target: "virtual_tools",
+						tool = %key.tool,
+						%error,
+						"failed to release shared idempotency key"
+					);
+				}
+				return Err(e);
+			},
+		};
+		if let Some(shared) = &self.shared {
+			let entry = SharedEntry {
+				args: args.clone(),
+				result: Some(&value),
+			};
+			if let Err(error) = shared
+				.set_json(&key.shared_key(), &entry, Some(idempotency.ttl()))
+				.await
+			{
+				warn!(
+					target: "virtual_tools",
+					tool = %key.tool,
+					%error,
+					"failed to share idempotent result"
+				);
+			}
+		}
+		if let Some(key) = claim.key.take() {
+			let mut entries = self.entries.lock();
+			if let Some(entry) = entries.get_mut(&key) {
+				entry.state = State::Done(value.clone());
+				entries.set_ttl(&key, Some(idempotency.ttl()));
+			}
+		}
+		Ok(IdempotentOutcome::Ran(value))
+	}
+
+	/// Number of keys in flight or within their TTL
+	pub fn len(&self) -> usize {
+		let mut entries = self.entries.lock();
+		entries.purge_expired();
+		entries.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.len() == 0
+	}
+
+	/// Keys held and how many expired or were evicted so far
+	pub fn eviction_stats(&self) -> EvictionStats {
+		self.entries.lock().stats()
+	}
+}
+
+#[async_trait]
+impl<T> StateStore for IdempotencyStore<T>
+where
+	T: Clone + Serialize + DeserializeOwned + Send + Sync,
+{
+	async fn get(&self, key: &str) -> Result<Option<Vec<u8>>, StoreError> {
+		Ok(self.get_with_ttl(key).await?.map(|(value, _)| value))
+	}
+
+	async fn set(&self, key: &str, value: Vec<u8>, ttl: Option<Duration>) -> Result<(), StoreError> {
+		let key = IdempotencyKey::from_store_key(key)?;
+		let stored: StoredEntry<T> =
+			serde_json::from_slice(&value).map_err(|e| StoreError::Serialization(e.to_string()))?;
+		let entry = Entry {
+			args: stored.args,
+			state: State::Done(stored.result),
+		};
+		self.entries.lock().insert(key, entry, ttl);
+		Ok(())
+	}
+
+	async fn delete(&self, key: &str) -> Result<(), StoreError> {
+		let key = IdempotencyKey::from_store_key(key)?;
+		self.entries.lock().remove(&key);
+		Ok(())
+	}
+
+	async fn get_with_ttl(
+		&self,
+		key: &str,
+	) -> Result<Option<(Vec<u8>, Option<Duration>)>, StoreError> {
+		let key = IdempotencyKey::from_store_key(key)?;
+		let entries = self.entries.lock();
+		let Some((entry, expires_at)) = entries.peek_with_expiry(&key) else {
+			return Ok(None);
+		};
+		let State::Done(result) = &entry.state else {
+			return Ok(None);
+		};
+		let stored = StoredEntry {
+			args: entry.args.clone(),
+			result,
+		};
+		let value =
+			serde_json::to_vec(&stored).map_err(|e| StoreError::Serialization(e.to_string()))?;
+		let ttl = expires_at.map(|at| at.saturating_duration_since(Instant::now()));
+		Ok(Some((value, ttl)))
+	}
+
+	async fn keys(&self, prefix: &str) -> Result<Vec<String>, StoreError> {
+		let entries = self.entries.lock();
+		Ok(
+			entries
+				.iter()
+				.filter(|(_, entry)| matches!(entry.state, State::Done(_)))
+				.map(|(key, _)| key.store_key())
+				.filter(|key| key.starts_with(prefix))
+				.collect(),
+		)
+	}
+}
+
+/// Answer a replay of `key`, given whether it has the first call's arguments and that call's
+/// result, if it completed
+fn replay<T: Clone>(
+	key: &IdempotencyKey,
+	same_args: bool,
+	result: Option<&T>,
+	on_duplicate: OnDuplicate,
+) -> Result<IdempotentOutcome<T>, String> {
+	if !same_args {
+		return Err(format!(
+			"idempotency key '{}' was already used with different arguments",
+			key.key
+		));
+	}
+	match (result, on_duplicate) {
+		(None, _) => Err(format!(
+			"a call with idempotency key '{}' is still in progress",
+			key.key
+		)),
+		(_, OnDuplicate::Error) => Err(format!("idempotency key '{}' was already used", key.key)),
+		(_, OnDuplicate::Skip) => Ok(IdempotentOutcome::Skipped),
+		(Some(result), OnDuplicate::Cached) => Ok(IdempotentOutcome::Replayed(result.clone())),
+	}
+}
+
+/// Claim `key` in the store shared with other gateways, or answer the call as a replay of the
+/// gateway that holds it. The call fails if the shared store can't be reached.
+async fn claim_shared<T>(
+	shared: &TieredStore,
+	key: &IdempotencyKey,
+	args: &str,
+	idempotency: &Idempotency,
+) -> Result<Option<IdempotentOutcome<T>>, String>
+where
+	T: Clone + Serialize + DeserializeOwned + Send,
+{
+	let unavailable = |e: StoreError| format!("idempotency store unavailable: {e}");
+	let shared_key = key.shared_key();
+	let claim = SharedEntry::<T> {
+		args: args.to_string(),
+		result: None,
+	};
+	let claim = serde_json::to_vec(&claim)
+		.map_err(|e| unavailable(StoreError::Serialization(e.to_string())))?;
+	if shared
+		.set_if_absent(&shared_key, claim, Some(idempotency.ttl()))
+		.await
+		.map_err(unavailable)?
+	{
+		return Ok(None);
+	}
+	debug!(
+		target: "virtual_tools",
+		tool = %key.tool,
+		"idempotency key replayed from shared store"
+	);
+	let entry: Option<SharedEntry<T>> = shared.get_json(&shared_key).await.map_err(unavailable)?;
+	// A claim that lapsed since is treated as still running; the retry can claim it
+	let (same_args, result) = match &entry {
+		Some(entry) => (entry.args == args, entry.result.as_ref()),
+		None => (true, None),
+	};
+	replay(key, same_args, result, idempotency.on_duplicate).map(Some)
+}
+
+struct Claim<'a, T> {
+	entries: &'a Mutex<BoundedMap<IdempotencyKey, Entry<T>>>,
+	key: Option<IdempotencyKey>,
+}
+
+impl<T> Drop for Claim<'_, T> {
+	fn drop(&mut self) {
+		if let Some(key) = self.key.take() {
+			self.entries.lock().remove(&key);
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::sync::Arc;
+	use std::sync::atomic::{AtomicU32, Ordering};
+	use std::time::Duration;
+
+	use serde_json::json;
+
+	use super::*;
+	use crate::stateful::TieredStoreConfig;
+	use crate::stateful::memory::MemoryStore;
+
+	fn idempotency(on_duplicate: OnDuplicate) -> Idempotency {
+		Idempotency {
+			ttl_seconds: 60,
+			on_duplicate,
+		}
+	}
+
+	async fn counted(calls: &Arc<AtomicU32>, result: Result<u32, String>) -> Result<u32, String> {
+		let n = calls.fetch_add(1, Ordering::SeqCst) + 1;
+		tokio::time::sleep(Duration::from_millis(20)).await;
+		result.map(|v| v + n)
+	}
+
+	#[tokio::test]
+	async fn test_replays_get_the_first_result() {
+		let store = IdempotencyStore::new();
+		let calls = Arc::new(AtomicU32::new(0));
+		let cached = idempotency(OnDuplicate::Cached);
+		let key = || IdempotencyKey::new("sub:agent-7", "create_ticket", "req-1");
+		let args = json!({"title": "Disk full"});
+
+		// A replay while the first call runs is refused
+		let (a, b) = tokio::join!(
+			store.call(key(), &args, &cached, counted(&calls, Ok(10))),
+			async {
+				tokio::time::sleep(Duration::from_millis(5)).await;
+				store
+					.call(key(), &args, &cached, counted(&calls, Ok(20)))
+					.await
+			},
+		);
+		assert_eq!(a, Ok(IdempotentOutcome::Ran(11)));
+		assert!(b.unwrap_err().contains("still in progress"));
+
+		let c = store
+			.call(key(), &args, &cached, counted(&calls, Ok(30)))
+			.await;
+		assert_eq!(c, Ok(IdempotentOutcome::Replayed(11)));
+		let skip = idempotency(OnDuplicate::Skip);
+		let d = store
+			.call(key(), &args, &skip, counted(&calls, Ok(40)))
+			.await;
+		assert_eq!(d, Ok(IdempotentOutcome::Skipped));
+		let error = idempotency(OnDuplicate::Error);
+		let e = store
+			.call(key(), &args, &error, counted(&calls, Ok(50)))
+			.await;
+		assert!(e.unwrap_err().contains("already used"));
+		assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+		// The same key with other arguments is a mistake, not a replay
+		let f = store
+			.call(
+				key(),
+				&json!({"title": "CPU"}),
+				&cached,
+				counted(&calls, Ok(0)),
+			)
+			.await;
+		assert!(f.unwrap_err().contains("different arguments"));
+
+		// Keys are per caller and tool
+		let other = IdempotencyKey::new("sub:agent-8", "create_ticket", "req-1");
+		let g = store
+			.call(other, &args, &cached, counted(&calls, Ok(60)))
+			.await;
+		assert_eq!(g, Ok(IdempotentOutcome::Ran(62)));
+		assert_eq!(store.len(), 2);
+	}
+
+	#[tokio::test]
+	async fn test_failures_and_expired_keys_run_again() {
+		let store = IdempotencyStore::new();
+		let calls = Arc::new(AtomicU32::new(0));
+		let key = || IdempotencyKey::new("session:1", "publish", "k");
+		let args = json!({});
+		let cached = idempotency(OnDuplicate::Cached);
+
+		let a = store
+			.call(key(), &args, &cached, counted(&calls, Err("down".into())))
+			.await;
+		assert_eq!(a, Err("down".to_string()));
+		assert!(store.is_empty());
+
+		let expiring = Idempotency {
+			ttl_seconds: 0,
+			on_duplicate: OnDuplicate::Cached,
+		};
+		let b = store
+			.call(key(), &args, &expiring, counted(&calls, Ok(0)))
+			.await;
+		assert_eq!(b, Ok(IdempotentOutcome::Ran(2)));
+		let c = store
+			.call(key(), &args, &cached, counted(&calls, Ok(0)))
+			.await;
+		assert_eq!(c, Ok(IdempotentOutcome::Ran(3)));
+	}
+
+	#[tokio::test]
+	async fn test_least_recently_used_keys_are_evicted() {
+		let store = IdempotencyStore::with_max_keys(1);
+		let calls = Arc::new(AtomicU32::new(0));
+		let key = |k: &str| IdempotencyKey::new("session:1", "publish", k);
+		let args = json!({});
+		let cached = idempotency(OnDuplicate::Cached);
+
+		let a = store
+			.call(key("a"), &args, &cached, counted(&calls, Ok(0)))
+			.await;
+		assert_eq!(a, Ok(IdempotentOutcome::Ran(1)));
+		let b = store
+			.call(key("b"), &args, &cached, counted(&calls, Ok(0)))
+			.await;
+		assert_eq!(b, Ok(IdempotentOutcome::Ran(2)));
+		assert_eq!(store.eviction_stats().evicted, 1);
+
+		// "a" was evicted to make room for "b", so it runs again
+		let c = store
+			.call(key("a"), &args, &cached, counted(&calls, Ok(0)))
+			.await;
+		assert_eq!(c, Ok(IdempotentOutcome::Ran(3)));
+	}
+
+	#[tokio::test]
+	async fn test_completed_keys_move_between_stores() {
+		let blue = IdempotencyStore::new();
+		let calls = Arc::new(AtomicU32::new(0));
+		let key = || IdempotencyKey::new("sub:agent-7", "create_ticket", "req/1");
+		let args = json!({"title": "Disk full"});
+		let cached = idempotency(OnDuplicate::Cached);
+		blue
+			.call(key(), &args, &cached, counted(&calls, Ok(10)))
+			.await
+			.unwrap();
+
+		let keys = blue.keys("").await.unwrap();
+		assert_eq!(keys, vec![r#"["sub:agent-7","create_ticket","req/1"]"#]);
+		let (value, ttl) = blue.get_with_ttl(&keys[0]).await.unwrap().unwrap();
+		assert!(ttl.unwrap() <= Duration::from_secs(60));
+
+		let green = IdempotencyStore::new();
+		green.set(&keys[0], value, ttl).await.unwrap();
+		let replay = green
+			.call(key(), &args, &cached, counted(&calls, Ok(20)))
+			.await;
+		assert_eq!(replay, Ok(IdempotentOutcome::Replayed(11)));
+		assert_eq!(calls.load(Ordering::SeqCst), 1);
+	}
+
+	#[tokio::test]
+	async fn test_gateways_sharing_a_store_run_a_key_once() {
+		let remote = Arc::new(MemoryStore::new());
+		let gateway = || {
+			let tiered = TieredStore::new(remote.clone(), TieredStoreConfig::default());
+			IdempotencyStore::new().with_shared(tiered.for_pattern("idempotency"))
+		};
+		let (us, eu) = (gateway(), gateway());
+		let calls = Arc::new(AtomicU32::new(0));
+		let key = |k: &str| IdempotencyKey::new("sub:agent-7", "create_ticket", k);
+		let args = json!({"title": "Disk full"});
+		let cached = idempotency(OnDuplicate::Cached);
+
+		// The other gateway refuses the key while the first call runs, then replays its result
+		let (a, b) = tokio::join!(
+			us.call(key("a"), &args, &cached, counted(&calls, Ok(10))),
+			async {
+				tokio::time::sleep(Duration::from_millis(5)).await;
+				eu.call(key("a"), &args, &cached, counted(&calls, Ok(20)))
+					.await
+			},
+		);
+		assert_eq!(a, Ok(IdempotentOutcome::Ran(11)));
+		assert!(b.unwrap_err().contains("still in progress"));
+		let c = eu
+			.call(key("a"), &args, &cached, counted(&calls, Ok(30)))
+			.await;
+		assert_eq!(c, Ok(IdempotentOutcome::Replayed(11)));
+		assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+		// A failed call releases its shared claim
+		let d = us
+			.call(key("b"), &args, &cached, counted(&calls, Err("down".into())))
+			.await;
+		assert_eq!(d, Err("down".to_string()));
+		let e = eu
+			.call(key("b"), &args, &cached, counted(&calls, Ok(0)))
+			.await;
+		assert_eq!(e, Ok(IdempotentOutcome::Ran(3)));
+	}
+
+	#[test]
+	fn test_idempotency_key_from_meta() {
+		let meta = |value: Value| {
+			let Value::Object(meta) = json!({ IDEMPOTENCY_KEY_META_KEY: value }) else {
+				unreachable!()
+			};
+			meta
+		};
+		assert_eq!(
+			idempotency_key_from_meta(&meta(json!("req-1"))).unwrap(),
+			Some("req-1".to_string())
+		);
+		assert_eq!(idempotency_key_from_meta(&Map::new()).unwrap(), None);
+		for invalid in [json!(""), json!(42), json!("k".repeat(256))] {
+			assert!(matches!(
+				idempotency_key_from_meta(&meta(invalid)),
+				Err(ExecutionError::InvalidInput(_))
+			));
+		}
+	}
+}
@@ -0,0 +1,274 @@
+// Polling of job-style source tools
+//
+// Some backend tools don't answer with a result: they submit a job and hand back its id, and
+// a separate status tool reports how the job is doing. A source tool with `jobPolling`
+// configured waits for the job itself, calling the status tool every `intervalMs` until the
+// status matches `completeWhen` (or `failWhen`), so agents and compositions make one call and
+// get the job's result instead of each running its own poll loop.
+//
+// A submit response that already matches `completeWhen` is taken as the result without
+// polling, for backends that finish small jobs straight away.
+
+use std::future::Future;
+use std::time::Instant;
+
+use serde_json::Value;
+use serde_json_path::JsonPath;
+
+use super::executor::FilterExecutor;
+use super::patterns::FieldPredicate;
+use super::types::JobPolling;
+
+/// A job didn't finish, or its responses didn't have the shape the polling config describes
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+pub enum JobPollingError {
+	#[error("submit response has no job id at {path}")]
+	NoJobId { path: String },
+	#[error("job {job_id} failed: {status}")]
+	Failed { job_id: String, status: Value },
+	#[error("job {job_id} didn't finish within {waited_ms}ms")]
+	TimedOut { job_id: String, waited_ms: u128 },
+	#[error("status of finished job {job_id} has no result at {path}")]
+	NoResult { job_id: String, path: String },
+}
+
+impl JobPolling {
+	/// Check the config, so a bad path or predicate fails the registry load rather than each
+	/// call
+	pub fn validate(&self) -> Result<(), String> {
+		if self.status_tool.is_empty() {
+			return Err("jobPolling statusTool must not be empty".to_string());
+		}
+		if self.job_id_field.is_empty() {
+			return Err("jobPolling jobIdField must not be empty".to_string());
+		}
+		let paths = [
+			("jobIdPath", Some(&self.job_id_path)),
+			("resultPath", self.result_path.as_ref()),
+		];
+		for (key, path) in paths {
+			if let Some(path) = path {
+				JsonPath::parse(path)
+					.map_err(|e| format!("jobPolling {key} '{path}' is not a valid JSONPath: {e}"))?;
+			}
+		}
+		let predicates = [
+			("completeWhen", Some(&self.complete_when)),
+			("failWhen", self.fail_when.as_ref()),
+		];
+		for (key, predicate) in predicates {
+			let Some(predicate) = predicate else {
+				continue;
+			};
+			if !FilterExecutor::is_supported_op(&predicate.op) {
+				return Err(format!(
+					"jobPolling {key} has unknown operator '{}'",
+					predicate.op
+				));
+			}
+			JsonPath::parse(&predicate.field).map_err(|e| {
+				format!(
+					"jobPolling {key} field '{}' is not a valid JSONPath: {e}",
+					predicate.field
+				)
+			})?;
+		}
+		if self.interval_ms == 0 {
+			return Err("jobPolling intervalMs must be greater than 0".to_string());
+		}
+		if self.max_wait_ms < self.interval_ms {
+			return Err("jobPolling maxWaitMs must be at least intervalMs".to_string());
+		}
+		Ok(())
+	}
+
+	/// Wait for the job `submitted` describes to finish, and return its result
+	///
+	/// `status` makes one status tool call with the given arguments.
+	pub async fn wait<F, Fut, E>(&self, submitted: Value, mut status: F) -> Result<Value, E>
+	where
+		F: FnMut(Value) -> Fut,
+		Fut: Future<Output = Result<Value, E>>,
+		E: From<JobPollingError>,
+	{
+		if matches(&self.complete_when, &submitted) {
+			return Ok(submitted);
+		}
+		let job_id = first(&self.job_id_path, &submitted).ok_or_else(|| JobPollingError::NoJobId {
+			path: self.job_id_path.clone(),
+		})?;
+		let args = Value::Object(serde_json::Map::from_iter([(
+			self.job_id_field.clone(),
+			job_id.clone(),
+		)]));
+		let job_id = match job_id {
+			Value::String(id) => id,
+			id => id.to_string(),
+		};
+
+		let started = Instant::now();
+		loop {
+			if started.elapsed() + self.interval() > self.max_wait() {
+				return Err(
+					JobPollingError::TimedOut {
+						job_id,
+						waited_ms: started.elapsed().as_millis(),
+					}
+					.into(),
+				);
+			}
+			tokio::time::sleep(self.interval()).await;
+			let response = status(args.clone()).await?;
+			if let Some(failed) = &self.fail_when
+				&& matches(failed, &response)
+			{
+				return Err(
+					JobPollingError::Failed {
+						job_id,
+						status: response,
+					}
+					.into(),
+				);
+			}
+			if !matches(&self.complete_when, &response) {
+				continue;
+			}
+			return match &self.result_path {
+				Some(path) => first(path, &response).ok_or_else(|| {
+					JobPollingError::NoResult {
+						job_id,
+						path: path.clone(),
+					}
+					.into()
+				}),
+				None => Ok(response),
+			};
+		}
+	}
+}
+
+fn matches(predicate: &FieldPredicate, response: &Value) -> bool {
+	let Ok(field) = JsonPath::parse(&predicate.field) else {
+		return false;
+	};
+	let nodes = field.query(response);
+	FilterExecutor::evaluate_condition(&predicate.op, nodes.first(), &predicate.value)
+		.unwrap_or(false)
+}
+
+fn first(path: &str, value: &Value) -> Option<Value> {
+	let path = JsonPath::parse(path).ok()?;
+	path.query(value).first().map(|v| (*v).clone())
+}
+
+#[cfg(test)]
+mod tests {
+	use std::sync::Mutex;
+
+	use serde_json::json;
+
+	use super::*;
+
+	fn polling() -> JobPolling {
+		serde_json::from_value(json!({
+			"statusTool": "get_render",
+			"jobIdPath": "$.job.id",
+			"jobIdField": "id",
+			"completeWhen": {"field": "$.state", "op": "eq", "value": {"stringValue": "done"}},
+			"failWhen": {"field": "$.state", "op": "eq", "value": {"stringValue": "error"}},
+			"resultPath": "$.output",
+			"intervalMs": "5ms",
+			"maxWaitMs": 200
+		}))
+		.unwrap()
+	}
+
+	impl From<JobPollingError> for String {
+		fn from(e: JobPollingError) -> Self {
+			e.to_string()
+		}
+	}
+
+	/// A status tool reporting each of `states` in turn, then the last one forever
+	async fn status(
+		calls: &Mutex<Vec<Value>>,
+		args: Value,
+		states: &[&str],
+	) -> Result<Value, String> {
+		let mut calls = calls.lock().unwrap();
+		calls.push(args);
+		let state = states[(calls.len() - 1).min(states.len() - 1)];
+		Ok(json!({"state": state, "output": {"url": "https://cdn/render.png"}}))
+	}
+
+	#[tokio::test]
+	async fn test_polls_until_complete() {
+		let calls = Mutex::new(Vec::new());
+		let result = polling()
+			.wait(json!({"job": {"id": 42}}), |args| {
+				status(&calls, args, &["queued", "running", "done"])
+			})
+			.await
+			.unwrap();
+		assert_eq!(result, json!({"url": "https://cdn/render.png"}));
+		let calls = calls.into_inner().unwrap();
+		assert_eq!(calls.len(), 3);
+		assert_eq!(calls[0], json!({"id": 42}));
+
+		// A submit response that is already done isn't polled
+		let result = polling()
+			.wait(json!({"state": "done", "output": 1}), |_| async {
+				Err::<Value, _>("polled".to_string())
+			})
+			.await
+			.unwrap();
+		assert_eq!(result, json!({"state": "done", "output": 1}));
+	}
+
+	#[tokio::test]
+	async fn test_failed_and_unfinished_jobs() {
+		let calls = Mutex::new(Vec::new());
+		let err = polling()
+			.wait(json!({"job": {"id": "r-1"}}), |args| {
+				status(&calls, args, &["running", "error"])
+			})
+			.await
+			.unwrap_err();
+		assert!(err.starts_with("job r-1 failed: {"), "{err}");
+
+		let err = polling()
+			.wait(json!({"job": {"id": "r-2"}}), |args| {
+				status(&calls, args, &["running"])
+			})
+			.await
+			.unwrap_err();
+		assert!(err.starts_with("job r-2 didn't finish within"), "{err}");
+
+		let err = polling()
+			.wait(json!({"accepted": true}), |args| {
+				status(&calls, args, &["done"])
+			})
+			.await
+			.unwrap_err();
+		assert_eq!(err, "submit response has no job id at $.job.id");
+	}
+
+	#[test]
+	fn test_validate() {
+		assert!(polling().validate().is_ok());
+		let invalid = |key: &str, value: Value| {
+			let mut config = serde_json::to_value(polling()).unwrap();
+			config[key] = value;
+			let config: JobPolling = serde_json::from_value(config).unwrap();
+			config.validate().is_err()
+		};
+		assert!(invalid("jobIdPath", json!("$.[bad")));
+		assert!(invalid("jobIdField", json!("")));
+		assert!(invalid(
+			"completeWhen",
+			json!({"field": "$.state", "op": "like", "value": {"stringValue": "done"}})
+		));
+		assert!(invalid("maxWaitMs", json!(1)));
+		assert!(!invalid("resultPath", Value::Null));
+	}
+}
@@ -0,0 +1,154 @@
+// Limits on JSON parsed from backend responses
+//
+// Tool results arrive as text that the gateway parses into JSON, both to hand compositions
+// structured data and to run output transforms. A broken or malicious backend can answer
+// with an enormous or absurdly nested document; these limits bound the work done on it and
+// turn such responses into a clear error instead.
+
+use serde_json::Value;
+
+/// Default cap on the size of backend response text parsed as JSON
+pub const DEFAULT_MAX_RESPONSE_BYTES: usize = 16 * 1024 * 1024;
+
+/// Default cap on the nesting depth of JSON parsed from backend responses
+///
+/// serde_json refuses documents nested deeper than 128 on its own, so larger values have no
+/// further effect.
+pub const DEFAULT_MAX_RESPONSE_DEPTH: usize = 64;
+
+/// A backend response exceeded a [`JsonLimits`] bound
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum JsonLimitError {
+	#[error("response is {size} bytes, over the {limit} byte limit")]
+	TooLarge { size: usize, limit: usize },
+	#[error("response JSON is nested deeper than the limit of {limit}")]
+	TooDeep { limit: usize },
+}
+
+/// Size and depth bounds applied to backend responses before and after JSON parsing
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct JsonLimits {
+	pub max_bytes: usize,
+	pub max_depth: usize,
+}
+
+impl Default for JsonLimits {
+	fn default() -> Self {
+		Self {
+			max_bytes: DEFAULT_MAX_RESPONSE_BYTES,
+			max_depth: DEFAULT_MAX_RESPONSE_DEPTH,
+		}
+	}
+}
+
+impl JsonLimits {
+	/// Reject text over the size limit
+	pub fn check_size(&self, text: &str) -> Result<(), JsonLimitError> {
+		if text.len() > self.max_bytes {
+			return Err(JsonLimitError::TooLarge {
+				size: text.len(),
+				limit: self.max_bytes,
+			});
+		}
+		Ok(())
+	}
+
+	/// Reject a document nested deeper than the depth limit
+	pub fn check_depth(&self, value: &Value) -> Result<(), JsonLimitError> {
+		if depth(value) > self.max_depth {
+			return Err(JsonLimitError::TooDeep {
+				limit: self.max_depth,
+			});
+		}
+		Ok(())
+	}
+
+	/// Parse backend text as JSON within the limits
+	///
+	/// Returns `Ok(None)` for text that isn't JSON, so callers can fall back to treating it
+	/// as plain text.
+	pub fn parse(&self, text: &str) -> Result<Option<Value>, JsonLimitError> {
+		self.check_size(text)?;
+		match serde_json::from_str(text) {
+			Ok(value) => {
+				self.check_depth(&value)?;
+				Ok(Some(value))
+			},
+			// serde_json's own recursion guard tripped
+			Err(e) if e.to_string().starts_with("recursion limit exceeded") => {
+				Err(JsonLimitError::TooDeep {
+					limit: self.max_depth,
+				})
+			},
+			Err(_) => Ok(None),
+		}
+	}
+}
+
+/// Nesting depth of a document: 0 for scalars, 1 for a flat object or array
+fn depth(value: &Value) -> usize {
+	let mut max = 0;
+	let mut stack = vec![(value, 1)];
+	while let Some((value, level)) = stack.pop() {
+		match value {
+			Value::Array(items) => {
+				max = max.max(level);
+				stack.extend(items.iter().map(|v| (v, level + 1)));
+			},
+			Value::Object(fields) => {
+				max = max.max(level);
+				stack.extend(fields.values().map(|v| (v, level + 1)));
+			},
+			_ => {},
+		}
+	}
+	max
+}
+
+#[cfg(test)]
+mod tests {
+	use serde_json::json;
+
+	use super::*;
+
+	fn nested(levels: usize) -> String {
+		format!("{}{}", "[".repeat(levels), "]".repeat(levels))
+	}
+
+	#[test]
+	fn test_depth() {
+		assert_eq!(depth(&json!(1)), 0);
+		assert_eq!(depth(&json!({"a": 1})), 1);
+		assert_eq!(depth(&json!({"a": [1, {"b": []}], "c": 2})), 4);
+	}
+
+	#[test]
+	fn test_parse() {
+		let limits = JsonLimits {
+			max_bytes: 64,
+			max_depth: 4,
+		};
+		assert_eq!(limits.parse(r#"{"a": [1]}"#), Ok(Some(json!({"a": [1]}))));
+		assert_eq!(limits.parse("plain text"), Ok(None));
+		assert_eq!(limits.parse(&nested(4)).map(|v| v.is_some()), Ok(true));
+		assert_eq!(
+			limits.parse(&nested(5)),
+			Err(JsonLimitError::TooDeep { limit: 4 })
+		);
+		assert_eq!(
+			limits.parse(&"x".repeat(65)),
+			Err(JsonLimitError::TooLarge {
+				size: 65,
+				limit: 64
+			})
+		);
+
+		// Deeper than serde_json will go is still reported as too deep, not as plain text
+		assert_eq!(
+			JsonLimits::default().parse(&nested(200)),
+			Err(JsonLimitError::TooDeep {
+				limit: DEFAULT_MAX_RESPONSE_DEPTH
+			})
+		);
+	}
+}
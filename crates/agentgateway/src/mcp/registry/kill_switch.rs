@@ -0,0 +1,176 @@
+// Incident response switches for virtual tools
+//
+// When a composition misbehaves in production, the fix is a registry change that takes a
+// review and a reload to land. Until then operators need to stop it at once: a disabled tool
+// is withdrawn from tools/list and its calls are rejected with the reason given, and the
+// global kill switch sets the whole registry aside, so that clients only see the backend
+// tools, unchanged, as if no registry were configured. Both are runtime state set through
+// the admin server; they survive registry reloads but not restarts.
+
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Instant;
+
+use parking_lot::Mutex;
+use serde::Serialize;
+use tracing::warn;
+
+/// A tool disabled by an operator
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DisabledTool {
+	/// Why the tool was disabled, reported to callers
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub reason: Option<String>,
+	/// How long ago the tool was disabled
+	pub disabled_secs: u64,
+}
+
+/// Active switches, as reported by the admin server
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct KillSwitchStatus {
+	/// Whether the registry is set aside and backend tools pass through unchanged
+	pub passthrough: bool,
+	pub disabled_tools: BTreeMap<String, DisabledTool>,
+}
+
+/// Operator switches for disabling tools or the whole registry
+#[derive(Debug, Default)]
+pub struct KillSwitch {
+	passthrough: AtomicBool,
+	disabled: Mutex<BTreeMap<String, (Option<String>, Instant)>>,
+}
+
+impl KillSwitch {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Disable a tool, replacing the reason if it already is
+	pub fn disable(&self, tool: &str, reason: Option<String>) {
+		warn!(target: "virtual_tools", tool, ?reason, "tool disabled by operator");
+		self
+			.disabled
+			.lock()
+			.insert(tool.to_string(), (reason, Instant::now()));
+	}
+
+	/// Enable a disabled tool, returning whether it was disabled
+	pub fn enable(&self, tool: &str) -> bool {
+		let was_disabled = self.disabled.lock().remove(tool).is_some();
+		if was_disabled {
+			warn!(target: "virtual_tools", tool, "tool re-enabled by operator");
+		}
+		was_disabled
+	}
+
+	/// Enable every disabled tool
+	pub fn enable_all(&self) {
+		self.disabled.lock().clear();
+	}
+
+	pub fn is_disabled(&self, tool: &str) -> bool {
+		self.disabled.lock().contains_key(tool)
+	}
+
+	/// Error for a call to `tool`, if it is disabled
+	pub fn disabled_error(&self, tool: &str) -> Option<String> {
+		let disabled = self.disabled.lock();
+		let (reason, _) = disabled.get(tool)?;
+		Some(match reason {
+			Some(reason) => format!("tool '{tool}' is disabled by an operator: {reason}"),
+			None => format!("tool '{tool}' is disabled by an operator"),
+		})
+	}
+
+	/// Set the registry aside (`true`) or bring it back (`false`)
+	pub fn set_passthrough(&self, passthrough: bool) {
+		if self.passthrough.swap(passthrough, Ordering::Relaxed) != passthrough {
+			warn!(
+				target: "virtual_tools",
+				passthrough,
+				"registry kill switch {}",
+				if passthrough { "engaged" } else { "released" }
+			);
+		}
+	}
+
+	/// Whether the registry is set aside
+	pub fn is_passthrough(&self) -> bool {
+		self.passthrough.load(Ordering::Relaxed)
+	}
+
+	pub fn status(&self) -> KillSwitchStatus {
+		let now = Instant::now();
+		KillSwitchStatus {
+			passthrough: self.is_passthrough(),
+			disabled_tools: self
+				.disabled
+				.lock()
+				.iter()
+				.map(|(tool, (reason, since))| {
+					(
+						tool.clone(),
+						DisabledTool {
+							reason: reason.clone(),
+							disabled_secs: now.duration_since(*since).as_secs(),
+						},
+					)
+				})
+				.collect(),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_disable_and_enable_tools() {
+		let switch = KillSwitch::new();
+		switch.disable(
+			"create_ticket",
+			Some("duplicate tickets, INC-42".to_string()),
+		);
+		switch.disable("summarize", None);
+
+		assert!(switch.is_disabled("create_ticket"));
+		assert!(!switch.is_disabled("get_weather"));
+		assert_eq!(
+			switch.disabled_error("create_ticket").unwrap(),
+			"tool 'create_ticket' is disabled by an operator: duplicate tickets, INC-42"
+		);
+		assert_eq!(
+			switch.disabled_error("summarize").unwrap(),
+			"tool 'summarize' is disabled by an operator"
+		);
+		assert_eq!(switch.disabled_error("get_weather"), None);
+
+		let status = switch.status();
+		assert!(!status.passthrough);
+		assert_eq!(
+			status.disabled_tools.keys().collect::<Vec<_>>(),
+			vec!["create_ticket", "summarize"]
+		);
+
+		assert!(switch.enable("create_ticket"));
+		assert!(!switch.enable("create_ticket"));
+		switch.enable_all();
+		assert!(switch.status().disabled_tools.is_empty());
+	}
+
+	#[test]
+	fn test_passthrough() {
+		let switch = KillSwitch::new();
+		assert!(!switch.is_passthrough());
+		switch.set_passthrough(true);
+		assert!(switch.is_passthrough());
+		// Tool switches are kept while the registry is set aside
+		switch.disable("create_ticket", None);
+		switch.set_passthrough(false);
+		assert!(!switch.is_passthrough());
+		assert!(switch.is_disabled("create_ticket"));
+	}
+}
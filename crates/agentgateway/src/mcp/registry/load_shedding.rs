@@ -0,0 +1,261 @@
+// Load shedding on upstream latency SLO breaches
+//
+// During a backend brownout every call to it slows down, and batch or background work keeps
+// piling onto the struggling upstream alongside interactive calls. The shedder tracks the
+// latency of recent calls to each upstream target, and while a target's p95 is over the
+// threshold configured for a priority, calls of that priority to it are refused without
+// being sent. Thresholds are per priority, so low-priority traffic can be shed well before
+// interactive traffic notices. Only calls made through the registry are shed; their
+// priority is that of the virtual tool called, or of the composition making the call.
+
+use std::collections::{HashMap, VecDeque};
+use std::time::{Duration, Instant};
+
+use parking_lot::Mutex;
+use serde::Serialize;
+
+use super::types::ToolPriority;
+
+/// Default window of calls a target's p95 latency is computed over
+pub const DEFAULT_LATENCY_WINDOW: Duration = Duration::from_secs(60);
+
+/// Default number of calls in the window before a target can be judged
+pub const DEFAULT_MINIMUM_CALLS: usize = 20;
+
+/// Most recent calls kept per target, however many the window holds
+pub const MAX_LATENCY_SAMPLES: usize = 1000;
+
+/// When calls of each priority are shed
+#[derive(Debug, Clone, PartialEq)]
+pub struct LoadSheddingConfig {
+	/// p95 latency above which a target's calls of each priority are shed; priorities
+	/// without a threshold are never shed
+	pub thresholds: HashMap<ToolPriority, Duration>,
+	/// How far back the calls a p95 is computed over go
+	pub window: Duration,
+	/// Calls a target needs within the window before its p95 is trusted
+	pub minimum_calls: usize,
+}
+
+impl LoadSheddingConfig {
+	pub fn new(thresholds: HashMap<ToolPriority, Duration>) -> Self {
+		Self {
+			thresholds,
+			window: DEFAULT_LATENCY_WINDOW,
+			minimum_calls: DEFAULT_MINIMUM_CALLS,
+		}
+	}
+}
+
+/// A call refused because its target is over the latency threshold for its priority
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error(
+	"upstream {target} is shedding {priority} priority calls: p95 latency {}ms is over {}ms",
+	p95.as_millis(),
+	threshold.as_millis()
+)]
+pub struct Shed {
+	pub target: String,
+	pub priority: ToolPriority,
+	pub p95: Duration,
+	pub threshold: Duration,
+}
+
+/// Latency of one target, as reported by the admin server
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TargetLatency {
+	pub target: String,
+	/// Calls within the window
+	pub calls: usize,
+	/// p95 latency in milliseconds, once the window holds `minimumCalls` calls
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub p95_ms: Option<u64>,
+	/// Priorities whose calls to the target are being shed
+	#[serde(skip_serializing_if = "Vec::is_empty")]
+	pub shedding: Vec<ToolPriority>,
+}
+
+/// Recent call latencies of every upstream target, and the calls they shed
+#[derive(Debug, Default)]
+pub struct LoadShedder {
+	/// Unset when load shedding isn't configured, in which case nothing is tracked
+	config: Option<LoadSheddingConfig>,
+	latencies: Mutex<HashMap<String, VecDeque<(Instant, Duration)>>>,
+}
+
+impl LoadShedder {
+	pub fn new(config: LoadSheddingConfig) -> Self {
+		Self {
+			config: Some(config),
+			latencies: Mutex::new(HashMap::new()),
+		}
+	}
+
+	/// Record how long a call to `target` took
+	pub fn record(&self, target: &str, latency: Duration) {
+		let Some(config) = &self.config else {
+			return;
+		};
+		let now = Instant::now();
+		let mut latencies = self.latencies.lock();
+		let samples = latencies.entry(target.to_string()).or_default();
+		samples.push_back((now, latency));
+		if samples.len() > MAX_LATENCY_SAMPLES {
+			samples.pop_front();
+		}
+		expire(samples, config.window, now);
+	}
+
+	/// p95 latency of the calls to `target` within the window, if there were enough of them
+	pub fn p95(&self, target: &str) -> Option<Duration> {
+		let config = self.config.as_ref()?;
+		let mut latencies = self.latencies.lock();
+		let samples = latencies.get_mut(target)?;
+		expire(samples, config.window, Instant::now());
+		p95(samples, config.minimum_calls)
+	}
+
+	/// Refuse a call of `priority` to `target` if the target's p95 latency is over the
+	/// priority's threshold
+	pub fn check(&self, target: &str, priority: ToolPriority) -> Result<(), Shed> {
+		let Some(threshold) = self
+			.config
+			.as_ref()
+			.and_then(|c| c.thresholds.get(&priority))
+		else {
+			return Ok(());
+		};
+		match self.p95(target) {
+			Some(p95) if p95 > *threshold => Err(Shed {
+				target: target.to_string(),
+				priority,
+				p95,
+				threshold: *threshold,
+			}),
+			_ => Ok(()),
+		}
+	}
+
+	/// Latency of every target called within the window, sorted by target
+	pub fn snapshot(&self) -> Vec<TargetLatency> {
+		let Some(config) = &self.config else {
+			return Vec::new();
+		};
+		let now = Instant::now();
+		let mut latencies = self.latencies.lock();
+		latencies.retain(|_, samples| {
+			expire(samples, config.window, now);
+			!samples.is_empty()
+		});
+		let mut snapshot: Vec<_> = latencies
+			.iter()
+			.map(|(target, samples)| {
+				let p95 = p95(samples, config.minimum_calls);
+				let mut shedding: Vec<_> = config
+					.thresholds
+					.iter()
+					.filter(|(_, threshold)| p95.is_some_and(|p95| p95 > **threshold))
+					.map(|(priority, _)| *priority)
+					.collect();
+				shedding.sort_by_key(|p| *p as u8);
+				TargetLatency {
+					target: target.clone(),
+					calls: samples.len(),
+					p95_ms: p95.map(|p95| p95.as_millis() as u64),
+					shedding,
+				}
+			})
+			.collect();
+		snapshot.sort_by(|a, b| a.target.cmp(&b.target));
+		snapshot
+	}
+}
+
+fn expire(samples: &mut VecDeque<(Instant, Duration)>, window: Duration, now: Instant) {
+	while let Some((at, _)) = samples.front()
+		&& now.duration_since(*at) > window
+	{
+		samples.pop_front();
+	}
+}
+
+fn p95(samples: &VecDeque<(Instant, Duration)>, minimum_calls: usize) -> Option<Duration> {
+	if samples.is_empty() || samples.len() < minimum_calls {
+		return None;
+	}
+	let mut sorted: Vec<Duration> = samples.iter().map(|(_, latency)| *latency).collect();
+	sorted.sort_unstable();
+	// Nearest rank
+	let rank = (sorted.len() * 95).div_ceil(100);
+	Some(sorted[rank.saturating_sub(1)])
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn shedder(minimum_calls: usize) -> LoadShedder {
+		LoadShedder::new(LoadSheddingConfig {
+			minimum_calls,
+			..LoadSheddingConfig::new(HashMap::from([
+				(ToolPriority::Low, Duration::from_millis(500)),
+				(ToolPriority::Normal, Duration::from_secs(2)),
+			]))
+		})
+	}
+
+	fn record(shedder: &LoadShedder, target: &str, millis: impl IntoIterator<Item = u64>) {
+		for ms in millis {
+			shedder.record(target, Duration::from_millis(ms));
+		}
+	}
+
+	#[test]
+	fn test_sheds_by_priority_threshold() {
+		let shedder = shedder(20);
+		// 18 fast calls and 2 slow ones put the p95 at the slow ones
+		record(&shedder, "search", std::iter::repeat_n(100, 18));
+		record(&shedder, "search", [900, 900]);
+		assert_eq!(shedder.p95("search"), Some(Duration::from_millis(900)));
+
+		let err = shedder.check("search", ToolPriority::Low).unwrap_err();
+		assert_eq!(
+			err.to_string(),
+			"upstream search is shedding low priority calls: p95 latency 900ms is over 500ms"
+		);
+		assert!(shedder.check("search", ToolPriority::Normal).is_ok());
+		assert!(shedder.check("search", ToolPriority::High).is_ok());
+		// Other targets are unaffected
+		assert!(shedder.check("weather", ToolPriority::Low).is_ok());
+
+		assert_eq!(
+			shedder.snapshot(),
+			vec![TargetLatency {
+				target: "search".to_string(),
+				calls: 20,
+				p95_ms: Some(900),
+				shedding: vec![ToolPriority::Low],
+			}]
+		);
+	}
+
+	#[test]
+	fn test_too_few_calls_are_not_judged() {
+		let shedder = shedder(20);
+		record(&shedder, "search", std::iter::repeat_n(5000, 19));
+		assert_eq!(shedder.p95("search"), None);
+		assert!(shedder.check("search", ToolPriority::Low).is_ok());
+		record(&shedder, "search", [5000]);
+		assert!(shedder.check("search", ToolPriority::Normal).is_err());
+	}
+
+	#[test]
+	fn test_unconfigured_shedder_tracks_nothing() {
+		let shedder = LoadShedder::default();
+		record(&shedder, "search", std::iter::repeat_n(5000, 50));
+		assert_eq!(shedder.p95("search"), None);
+		assert!(shedder.check("search", ToolPriority::Low).is_ok());
+		assert!(shedder.snapshot().is_empty());
+	}
+}
@@ -0,0 +1,90 @@
+// Locale selection for localized tool descriptions
+//
+// Clients state their preferred languages with an Accept-Language style list
+// (`fr-CA, fr;q=0.9, en;q=0.5`). Each preference is matched against the locales a tool
+// defines using RFC 4647 lookup: `fr-CA` falls back to `fr` before the next preference is
+// tried. Matching is case-insensitive, and `*` never matches, leaving the default description.
+
+use std::collections::HashMap;
+
+/// Language tags from an Accept-Language value, most preferred first
+///
+/// Entries with a quality of 0 or one that doesn't parse are dropped.
+pub fn preferred_locales(accept_language: &str) -> Vec<&str> {
+	let mut ranked: Vec<(&str, f32)> = accept_language
+		.split(',')
+		.filter_map(|entry| {
+			let mut parts = entry.split(';');
+			let tag = parts.next()?.trim();
+			let quality = parts
+				.find_map(|p| p.trim().strip_prefix("q="))
+				.map_or(Some(1.0), |q| q.trim().parse::<f32>().ok())?;
+			(!tag.is_empty() && quality > 0.0).then_some((tag, quality))
+		})
+		.collect();
+	// Stable, so equally preferred tags keep the client's order
+	ranked.sort_by(|a, b| b.1.total_cmp(&a.1));
+	ranked.into_iter().map(|(tag, _)| tag).collect()
+}
+
+/// The localized text best matching an Accept-Language value, if any locale matches
+pub fn select_localized<'a>(
+	localized: &'a HashMap<String, String>,
+	accept_language: &str,
+) -> Option<&'a str> {
+	if localized.is_empty() {
+		return None;
+	}
+	preferred_locales(accept_language)
+		.into_iter()
+		.filter(|tag| *tag != "*")
+		.find_map(|tag| lookup(localized, tag))
+}
+
+fn lookup<'a>(localized: &'a HashMap<String, String>, tag: &str) -> Option<&'a str> {
+	let mut range = tag;
+	loop {
+		if let Some((_, text)) = localized
+			.iter()
+			.find(|(locale, _)| locale.eq_ignore_ascii_case(range))
+		{
+			return Some(text);
+		}
+		let (shorter, _) = range.rsplit_once('-')?;
+		range = shorter;
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_preferred_locales() {
+		assert_eq!(
+			preferred_locales("en;q=0.5, fr-CA, fr;q=0.9, de;q=0, *;q=0.1"),
+			vec!["fr-CA", "fr", "en", "*"]
+		);
+		assert_eq!(preferred_locales("ja;q=abc, es"), vec!["es"]);
+		assert!(preferred_locales("").is_empty());
+	}
+
+	#[test]
+	fn test_select_localized() {
+		let localized: HashMap<String, String> = [("fr", "Météo"), ("pt-BR", "Clima")]
+			.into_iter()
+			.map(|(k, v)| (k.to_string(), v.to_string()))
+			.collect();
+
+		assert_eq!(select_localized(&localized, "fr-CA"), Some("Météo"));
+		assert_eq!(select_localized(&localized, "pt-br"), Some("Clima"));
+		assert_eq!(
+			select_localized(&localized, "de, pt-BR;q=0.8"),
+			Some("Clima")
+		);
+		// `pt` doesn't match the more specific `pt-BR`
+		assert_eq!(select_localized(&localized, "pt"), None);
+		assert_eq!(select_localized(&localized, "*"), None);
+		assert_eq!(select_localized(&HashMap::new(), "fr"), None);
+	}
+}
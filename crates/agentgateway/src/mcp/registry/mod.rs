@@ -7,40 +7,136 @@
 // - Output transformation via JSONPath
 // - Hot-reloadable registry from file or HTTP sources
 
+mod activation;
+mod archive;
+mod arguments;
+mod authorizer;
+mod catalog;
+mod changelog;
 mod client;
 mod compiled;
+mod confirmation;
+mod debounce;
+mod docs;
+mod drift;
+mod env_policy;
 mod error;
 pub mod execution_graph;
 pub mod executor;
+mod fan_out;
+mod idempotency;
+mod job_polling;
+mod json_limits;
+mod kill_switch;
+mod load_shedding;
+mod locale;
+mod pagination;
 pub mod patterns;
+mod readiness;
 pub mod runtime_hooks;
+mod scaffold;
+mod signature;
+mod snapshot;
+mod snapshot_memo;
+mod stats;
 mod store;
 mod types;
+pub mod types_compat;
+mod usage;
 pub mod validation;
 
-pub use client::{AuthConfig, RegistryClient, RegistrySource, parse_duration};
+pub use activation::{
+	Candidate, CandidateSummary, ManualPromotion, PromotionError, SmokeTest, SmokeTestResult,
+	StagedRegistry,
+};
+#[cfg(feature = "testing")]
+pub use archive::S3Sink;
+pub use archive::{
+	ArchiveError, ArchiveRecord, ArchiveSink, ArchiveStats, FilesystemSink, PayloadArchiver,
+};
+pub use arguments::{
+	ArgumentCheckPolicy, ArgumentProblem, argument_problems, check_arguments, coerce_arguments,
+};
+pub use authorizer::{
+	AuthorizationDecision, AuthorizationRequest, Authorizer, AuthorizerError,
+	DEFAULT_AUTHORIZER_TIMEOUT, HttpAuthorizer, ToolCallAuthorizer,
+};
+pub use catalog::{DESCRIBE_TOOL, ToolDoc, describe, describe_tool_definition};
+pub use changelog::{
+	DEFAULT_CHANGELOG_SIZE, FieldChange, RegistryChange, RegistryChangelog, RegistryDiff, ToolChange,
+};
+pub use client::{
+	AuthConfig, CacheValidators, FetchedRegistry, RegistryClient, RegistrySource, TlsConfig,
+	parse_duration, parse_registry,
+};
 pub use compiled::{
-	CompiledComposition, CompiledFieldSource, CompiledImplementation, CompiledOutputField,
-	CompiledOutputTransform, CompiledRegistry, CompiledSourceTool, CompiledTool, CompiledVirtualTool,
+	CompiledComposition, CompiledConditional, CompiledFieldSource, CompiledImplementation,
+	CompiledOutputField, CompiledOutputTransform, CompiledRegistry, CompiledSourceTool,
+	CompiledTargetPolicy, CompiledTool, CompiledVirtualTool,
 };
+pub use confirmation::{CONFIRMED_META_KEY, check_confirmation, confirmed_in_meta};
+pub use debounce::{CallDebouncer, CallKey};
+pub use docs::{DocsFormat, composition_diagram, render_docs, render_tool_docs};
+pub use drift::{DriftIssue, SchemaDriftPolicy, SchemaDriftTracker, check_compatibility};
+pub use env_policy::EnvVarPolicy;
 pub use error::RegistryError;
+pub use fan_out::{ASSUMED_ITEMS, FanOutWarning, WIDE_FAN_OUT, estimate as estimate_fan_out};
+pub use idempotency::{
+	DEFAULT_MAX_IDEMPOTENCY_KEYS, IDEMPOTENCY_KEY_META_KEY, IDEMPOTENT_REPLAY_META_KEY,
+	IdempotencyKey, IdempotencyStore, IdempotentOutcome, MAX_IDEMPOTENCY_KEY_LEN,
+	idempotency_key_from_meta,
+};
+pub use job_polling::JobPollingError;
+pub use json_limits::{
+	DEFAULT_MAX_RESPONSE_BYTES, DEFAULT_MAX_RESPONSE_DEPTH, JsonLimitError, JsonLimits,
+};
+pub use kill_switch::{DisabledTool, KillSwitch, KillSwitchStatus};
+pub use load_shedding::{
+	DEFAULT_LATENCY_WINDOW, DEFAULT_MINIMUM_CALLS, LoadShedder, LoadSheddingConfig, Shed,
+	TargetLatency,
+};
+pub use locale::{preferred_locales, select_localized};
+pub use pagination::PaginationError;
 pub use patterns::{
-	AggregationOp, AggregationStrategy, CoalesceSource, ConcatSource, DataBinding, DedupeOp,
-	FieldPredicate, FieldSource, FilterSpec, InputBinding, LimitOp, LiteralValue, MapEachInner,
-	MapEachSpec, PatternSpec, PipelineSpec, PipelineStep, PredicateValue, ScatterGatherSpec,
-	ScatterTarget, SchemaMapSpec, SortOp, StepBinding, StepOperation, TemplateSource, ToolCall,
+	AggregationOp, AggregationStrategy, CoalesceSource, CoerceSource, CoerceType, ConcatSource,
+	ConditionalSource, DataBinding, DedupeOp, FieldPredicate, FieldSource, FilterSpec, InputBinding,
+	LimitOp, LiteralValue, MapEachInner, MapEachSpec, PatternSpec, PipelineSpec, PipelineStep,
+	PredicateValue, ScatterGatherSpec, ScatterOperation, ScatterTarget, SchemaMapSpec, SortOp,
+	StepBinding, StepOperation, StepRetry, TargetSample, TemplateSource, TokenBudgetOp,
+	TokenizerKind, ToolCall,
 };
-pub use store::{RegistryStore, RegistryStoreRef};
+pub use readiness::{ReadinessCheck, RegistryReadiness, UPSTREAM_FAILURE_TTL, UpstreamReadiness};
+pub use runtime_hooks::{
+	CallContext, CallerIdentity, DependencyCheckResult, RuntimeHooks, ToolVisibility,
+};
+pub use scaffold::scaffold_registry;
+pub use signature::{SignatureVerifier, signature_path, signature_url};
+pub use snapshot::RegistrySnapshot;
+pub use snapshot_memo::{MAX_MEMOIZED_NAMES, SnapshotMemo};
+pub use stats::{
+	DEFAULT_RELOAD_HISTORY, RegistryDump, RegistryStats, RegistryStatsSnapshot, ReloadRecord,
+	ReloadTrigger, ToolSummary, ValidationSummary,
+};
+pub use store::{LoadFailurePolicy, RegistryStore, RegistryStoreRef};
 pub use types::{
-	OutputField, OutputSchema, OutputTransform, Registry, SourceTool, ToolDefinition,
-	ToolImplementation, ToolSource, VirtualToolDef,
+	Debounce, Idempotency, JobPolling, OutputField, OutputSchema, OutputTransform, OutputVariant,
+	Pagination, Registry, RegistryDefaults, SourceTool, TargetPolicy, ToolAnnotations,
+	ToolDefinition, ToolExample, ToolImplementation, ToolPriority, ToolSource, TransportRetry,
+	VirtualToolDef,
+};
+pub use usage::{DEFAULT_USAGE_INTERVAL, ToolUsage, UsageAnalytics, UsagePrivacy, UsageReport};
+pub use validation::{
+	RegistryValidator, ValidationError, ValidationResult, ValidationWarning, find_unknown_fields,
+	validate_registry,
 };
-pub use validation::{validate_registry, RegistryValidator, ValidationError, ValidationResult, ValidationWarning};
-pub use runtime_hooks::{CallerIdentity, CallContext, DependencyCheckResult, RuntimeHooks, ToolVisibility};
 
 // Executor exports
 pub use execution_graph::{ExecutionGraph, ExecutionNode, NodeInput, NodeOperation};
 pub use executor::{
-	CompositionExecutor, ExecutionContext, ExecutionError, FilterExecutor, MapEachExecutor,
-	PipelineExecutor, ScatterGatherExecutor, SchemaMapExecutor, ToolInvoker,
+	CompositionExecutor, CompositionVerbosity, ExecutionContext, ExecutionError, ExecutionLog,
+	ExecutionPriority, ExecutionRecord, FilterExecutor, InvocationMeta, MapEachExecutor,
+	MemoryBudget, PipelineExecutor, ScatterGatherExecutor, SchemaMapExecutor, ToolInvoker,
+	VerbosityOverrides, WorkerPool,
 };
+#[cfg(any(test, feature = "testing"))]
+pub use executor::{MockCall, MockToolInvoker, RegistryAwareInvoker};
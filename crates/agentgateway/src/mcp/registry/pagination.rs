@@ -0,0 +1,263 @@
+// Cursor pagination of list-style source tools
+//
+// Agents page through list APIs badly: they stop after the first page, or lose the cursor
+// between calls. A source tool with `pagination` configured follows the backend's cursor
+// itself, so one call returns every item. The first page's response is kept as the result,
+// with its item array replaced by the items of every page and its cursor by the one left over
+// (null once the list is exhausted, set when `maxPages` cut it short). A cursor the backend
+// already handed out ends pagination, so a backend cycling through cursors isn't called until
+// `maxPages`.
+
+use std::future::Future;
+
+use serde_json::Value;
+
+use super::types::Pagination;
+
+/// A page didn't have the shape the pagination config describes
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum PaginationError {
+	#[error("page {page} has no item array at {path}")]
+	NotAList { page: u32, path: String },
+}
+
+impl Pagination {
+	/// Check the config, so a bad path fails the registry load rather than each call
+	pub fn validate(&self) -> Result<(), String> {
+		if self.cursor_field.is_empty() {
+			return Err("pagination cursorField must not be empty".to_string());
+		}
+		match segments(&self.cursor_path) {
+			Some(s) if !s.is_empty() => {},
+			_ => {
+				return Err(format!(
+					"pagination cursorPath '{}' must be a path like $.nextCursor",
+					self.cursor_path
+				));
+			},
+		}
+		if segments(&self.items_path).is_none() {
+			return Err(format!(
+				"pagination itemsPath '{}' must be a path like $.items",
+				self.items_path
+			));
+		}
+		if self.page_size.is_some() && self.page_size_field.is_none() {
+			return Err("pagination pageSize requires pageSizeField".to_string());
+		}
+		if !(1..=Self::MAX_PAGES_LIMIT).contains(&self.max_pages) {
+			return Err(format!(
+				"pagination maxPages must be between 1 and {}, got {}",
+				Self::MAX_PAGES_LIMIT,
+				self.max_pages
+			));
+		}
+		Ok(())
+	}
+
+	/// Fetch every page, starting from `args`, and merge them into one response
+	///
+	/// `fetch` makes one backend call with the given arguments.
+	pub async fn fetch_all<F, Fut, E>(&self, mut args: Value, mut fetch: F) -> Result<Value, E>
+	where
+		F: FnMut(Value) -> Fut,
+		Fut: Future<Output = Result<Value, E>>,
+		E: From<PaginationError>,
+	{
+		if let (Some(field), Some(size), Some(fields)) =
+			(&self.page_size_field, self.page_size, args.as_object_mut())
+		{
+			fields.entry(field.clone()).or_insert(size.into());
+		}
+
+		let mut merged = fetch(args.clone()).await?;
+		let mut items = self.take_items(&mut merged, 1)?;
+		let mut cursor = self.cursor(&merged);
+		let mut sent = Vec::new();
+		let mut pages = 1;
+		while pages < self.max_pages
+			&& let Some(next) = cursor.take()
+			&& let Some(fields) = args.as_object_mut()
+		{
+			fields.insert(self.cursor_field.clone(), next.clone());
+			sent.push(next);
+			let mut page = fetch(args.clone()).await?;
+			pages += 1;
+			items.extend(self.take_items(&mut page, pages)?);
+			// A cursor seen before would only page through the same items again
+			cursor = self.cursor(&page).filter(|c| !sent.contains(c));
+		}
+
+		if let Some(slot) = lookup_mut(&mut merged, &self.items_path) {
+			*slot = Value::Array(items);
+		}
+		if let Some(slot) = lookup_mut(&mut merged, &self.cursor_path) {
+			*slot = cursor.unwrap_or(Value::Null);
+		}
+		Ok(merged)
+	}
+
+	/// Cursor of the next page, if the response has one
+	fn cursor(&self, page: &Value) -> Option<Value> {
+		let mut value = page;
+		for segment in segments(&self.cursor_path)? {
+			value = value.get(segment)?;
+		}
+		match value {
+			Value::Null => None,
+			Value::String(s) if s.is_empty() => None,
+			cursor => Some(cursor.clone()),
+		}
+	}
+
+	fn take_items(&self, page: &mut Value, number: u32) -> Result<Vec<Value>, PaginationError> {
+		match lookup_mut(page, &self.items_path) {
+			Some(Value::Array(items)) => Ok(std::mem::take(items)),
+			_ => Err(PaginationError::NotAList {
+				page: number,
+				path: self.items_path.clone(),
+			}),
+		}
+	}
+}
+
+/// Keys of a `$.a.b` path (empty for `$`), or None for anything fancier
+fn segments(path: &str) -> Option<Vec<&str>> {
+	let rest = path.strip_prefix('$')?;
+	if rest.is_empty() {
+		return Some(Vec::new());
+	}
+	rest
+		.strip_prefix('.')?
+		.split('.')
+		.map(|key| {
+			let plain = !key.is_empty() && !key.contains(['[', ']', '*', '?', '(', ')', '@']);
+			plain.then_some(key)
+		})
+		.collect()
+}
+
+fn lookup_mut<'a>(value: &'a mut Value, path: &str) -> Option<&'a mut Value> {
+	segments(path)?
+		.into_iter()
+		.try_fold(value, |value, key| value.get_mut(key))
+}
+
+#[cfg(test)]
+mod tests {
+	use std::sync::Mutex;
+
+	use serde_json::json;
+
+	use super::*;
+
+	fn pagination() -> Pagination {
+		serde_json::from_value(json!({
+			"cursorField": "cursor",
+			"cursorPath": "$.page.next",
+			"itemsPath": "$.items",
+			"pageSizeField": "limit",
+			"pageSize": 2,
+			"maxPages": 3
+		}))
+		.unwrap()
+	}
+
+	/// A backend serving `total` numbered items, two per page
+	async fn backend(calls: &Mutex<Vec<Value>>, args: Value, total: u64) -> Result<Value, String> {
+		calls.lock().unwrap().push(args.clone());
+		let start = args["cursor"].as_str().map_or(0, |c| c.parse().unwrap());
+		let end = (start + 2).min(total);
+		let next = (end < total).then(|| end.to_string());
+		Ok(json!({"items": (start..end).collect::<Vec<_>>(), "page": {"next": next}, "total": total}))
+	}
+
+	impl From<PaginationError> for String {
+		fn from(e: PaginationError) -> Self {
+			e.to_string()
+		}
+	}
+
+	#[tokio::test]
+	async fn test_fetch_all_pages() {
+		let calls = Mutex::new(Vec::new());
+		let result = pagination()
+			.fetch_all(json!({"q": "x"}), |args| backend(&calls, args, 5))
+			.await
+			.unwrap();
+		assert_eq!(
+			result,
+			json!({"items": [0, 1, 2, 3, 4], "page": {"next": null}, "total": 5})
+		);
+		let calls = calls.into_inner().unwrap();
+		assert_eq!(calls[0], json!({"q": "x", "limit": 2}));
+		assert_eq!(calls[2], json!({"q": "x", "limit": 2, "cursor": "4"}));
+	}
+
+	#[tokio::test]
+	async fn test_fetch_all_stops_at_max_pages() {
+		let calls = Mutex::new(Vec::new());
+		let result = pagination()
+			.fetch_all(json!({"limit": 1}), |args| backend(&calls, args, 20))
+			.await
+			.unwrap();
+		// The caller's page size wins, and the leftover cursor says where to continue
+		assert_eq!(result["items"], json!([0, 1, 2, 3, 4, 5]));
+		assert_eq!(result["page"]["next"], "6");
+		assert_eq!(calls.lock().unwrap()[0]["limit"], 1);
+
+		// A repeated cursor ends pagination instead of looping
+		let result = pagination()
+			.fetch_all(json!({}), |_| async {
+				Ok::<_, String>(json!({"items": [1], "page": {"next": "same"}}))
+			})
+			.await
+			.unwrap();
+		assert_eq!(result["items"], json!([1, 1]));
+		assert_eq!(result["page"]["next"], Value::Null);
+
+		// So does a cursor handed out earlier, not just the one before
+		let mut config = pagination();
+		config.max_pages = 10;
+		let cycle = Mutex::new(Vec::new());
+		let result = config
+			.fetch_all(json!({}), |args| {
+				cycle.lock().unwrap().push(args["cursor"].clone());
+				let next = if args["cursor"] == "a" { "b" } else { "a" };
+				async move { Ok::<_, String>(json!({"items": [1], "page": {"next": next}})) }
+			})
+			.await
+			.unwrap();
+		assert_eq!(result["items"], json!([1, 1, 1]));
+		assert_eq!(result["page"]["next"], Value::Null);
+		assert_eq!(
+			cycle.into_inner().unwrap(),
+			[Value::Null, json!("a"), json!("b")]
+		);
+
+		let err = pagination()
+			.fetch_all(json!({}), |_| async { Ok::<_, String>(json!("not json")) })
+			.await
+			.unwrap_err();
+		assert_eq!(err, "page 1 has no item array at $.items");
+	}
+
+	#[test]
+	fn test_validate() {
+		assert!(pagination().validate().is_ok());
+		let invalid = |key: &str, value: Value| {
+			let mut config = serde_json::to_value(pagination()).unwrap();
+			config[key] = value;
+			let config: Pagination = serde_json::from_value(config).unwrap();
+			config.validate().is_err()
+		};
+		assert!(invalid("cursorPath", json!("$")));
+		assert!(invalid("itemsPath", json!("$.results[*]")));
+		assert!(invalid("itemsPath", json!("items")));
+		assert!(invalid("pageSizeField", Value::Null));
+		assert!(invalid("maxPages", json!(0)));
+		assert!(invalid("maxPages", json!(Pagination::MAX_PAGES_LIMIT + 1)));
+		assert!(!invalid("maxPages", json!(Pagination::MAX_PAGES_LIMIT)));
+		assert!(!invalid("itemsPath", json!("$")));
+	}
+}
@@ -10,6 +10,41 @@ use super::PatternSpec;
 pub struct MapEachSpec {
 	/// The operation to apply to each element
 	pub inner: MapEachInner,
+
+	/// Most elements the input array may have; a larger array fails the operation
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub max_items: Option<u32>,
+
+	/// Send each result to the client as it completes instead of returning the array,
+	/// when the client is connected over a stream
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub stream: Option<MapEachStream>,
+}
+
+/// Incremental emission of a MapEach's results
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MapEachStream {
+	/// Emit results as they complete rather than in input order
+	#[serde(default)]
+	pub unordered: bool,
+
+	/// Elements processed at once (default 4, at most 32)
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub concurrency: Option<u32>,
+}
+
+impl MapEachStream {
+	pub const DEFAULT_CONCURRENCY: u32 = 4;
+	pub const MAX_CONCURRENCY: u32 = 32;
+
+	/// Effective concurrency, clamped to `1..=MAX_CONCURRENCY`
+	pub fn concurrency(&self) -> usize {
+		self
+			.concurrency
+			.unwrap_or(Self::DEFAULT_CONCURRENCY)
+			.clamp(1, Self::MAX_CONCURRENCY) as usize
+	}
 }
 
 impl MapEachSpec {
@@ -17,6 +52,8 @@ impl MapEachSpec {
 	pub fn tool(name: impl Into<String>) -> Self {
 		Self {
 			inner: MapEachInner::Tool(name.into()),
+			max_items: None,
+			stream: None,
 		}
 	}
 
@@ -24,9 +61,23 @@ impl MapEachSpec {
 	pub fn pattern(spec: PatternSpec) -> Self {
 		Self {
 			inner: MapEachInner::Pattern(Box::new(spec)),
+			max_items: None,
+			stream: None,
 		}
 	}
 
+	/// Fail on input arrays with more than `max_items` elements
+	pub fn with_max_items(mut self, max_items: u32) -> Self {
+		self.max_items = Some(max_items);
+		self
+	}
+
+	/// Stream results to the client as they complete
+	pub fn with_stream(mut self, stream: MapEachStream) -> Self {
+		self.stream = Some(stream);
+		self
+	}
+
 	/// Get the names of tools referenced by this map-each
 	pub fn referenced_tools(&self) -> Vec<&str> {
 		self.inner.referenced_tools()
@@ -6,27 +6,40 @@
 mod filter;
 mod map_each;
 mod pipeline;
+mod rank;
 mod scatter_gather;
 mod schema_map;
 mod stateful;
+pub mod units;
 mod vision;
 
 pub use filter::{FieldPredicate, FilterSpec, PredicateValue};
-pub use map_each::{MapEachInner, MapEachSpec};
+pub use map_each::{MapEachInner, MapEachSpec, MapEachStream};
 pub use pipeline::{
-	ConstructBinding, DataBinding, InputBinding, PipelineSpec, PipelineStep, StepBinding,
-	StepOperation, ToolCall,
+	AssertSeverity, AssertSpec, CompositionCall, ConstructBinding, DataBinding, InputBinding,
+	JsonPatchOp, JsonPatchOperation, PatchSpec, PipelineSpec, PipelineStep, SampleSpec, ScriptLimits,
+	ScriptSpec, SessionBinding, StepBinding, StepCache, StepOperation, StepRetry, TemplateBinding,
+	ToolCall,
 };
+pub(crate) use pipeline::{default_sample_max_tokens, default_sample_timeout_ms};
+pub(crate) use rank::default_llm_timeout_ms;
+pub use rank::{EmbeddingScorer, LlmScorer, RankScorer, RankSpec, WeightedField};
 pub use scatter_gather::{
-	AggregationOp, AggregationStrategy, DedupeOp, LimitOp, ScatterGatherSpec, ScatterTarget, SortOp,
+	AggregationOp, AggregationStrategy, DedupeOp, LimitOp, ScatterGatherSpec, ScatterOperation,
+	ScatterTarget, SortOp, TargetSample, TokenBudgetOp, TokenizerKind,
 };
 pub use schema_map::{
-	CoalesceSource, ConcatSource, FieldSource, LiteralValue, SchemaMapSpec, TemplateSource,
+	CoalesceSource, CoerceSource, CoerceType, ConcatSource, ConditionalSource, FieldSource,
+	LiteralValue, SchemaMapSpec, TemplateSource,
 };
 pub use stateful::{
-	BackoffStrategy, CacheSpec, CircuitBreakerSpec, ClaimCheckSpec, DeadLetterSpec,
-	ExponentialBackoff, FixedBackoff, IdempotentSpec, LinearBackoff, OnDuplicate, OnExceeded,
-	RetrySpec, SagaSpec, SagaStep, ThrottleSpec, ThrottleStrategy, TimeoutSpec,
+	BackoffStrategy, CacheSpec, CircuitBreakerShorthand, CircuitBreakerSpec, ClaimCheckSpec,
+	DeadLetterSpec, ExponentialBackoff, FixedBackoff, IdempotentSpec, LinearBackoff,
+	NegativeCacheSpec, OnDuplicate, OnExceeded, Resilience, RetrySpec, SagaSpec, SagaStep,
+	ThrottleSpec, ThrottleStrategy, TimeoutSpec,
+};
+pub(crate) use stateful::{
+	default_failure_threshold, default_failure_window_seconds, default_reset_timeout_seconds,
 };
 pub use vision::{
 	CapabilityRouterSpec, ConfidenceAggregatorSpec, ConfidenceStrategy, DedupKeepStrategy,
@@ -56,6 +69,9 @@ pub enum PatternSpec {
 	/// Apply operation to each array element
 	MapEach(MapEachSpec),
 
+	/// Score, sort and limit array elements
+	Rank(RankSpec),
+
 	// Stateful patterns (IR defined, runtime not yet implemented)
 	/// Retry with configurable backoff
 	Retry(RetrySpec),
@@ -117,13 +133,16 @@ impl PatternSpec {
 			PatternSpec::Filter(_) => vec![],
 			PatternSpec::SchemaMap(_) => vec![],
 			PatternSpec::MapEach(me) => me.referenced_tools(),
-			// Stateful patterns - return empty for now as they're not executed
-			PatternSpec::Retry(_) => vec![],
-			PatternSpec::Timeout(_) => vec![],
-			PatternSpec::Cache(_) => vec![],
+			PatternSpec::Rank(r) => r.referenced_tools(),
+			// Resilience wrappers (timeouts are also produced by the resilience shorthand) report
+			// their inner operations, so wrapping a composition doesn't hide its references
+			PatternSpec::Retry(r) => r.inner.referenced_tools(),
+			PatternSpec::Timeout(t) => t.referenced_tools(),
+			PatternSpec::CircuitBreaker(cb) => cb.referenced_tools(),
+			PatternSpec::Cache(c) => c.inner.referenced_tools(),
+			PatternSpec::DeadLetter(dl) => dl.referenced_tools(),
+			// Other stateful patterns - return empty for now as they're not executed
 			PatternSpec::Idempotent(_) => vec![],
-			PatternSpec::CircuitBreaker(_) => vec![],
-			PatternSpec::DeadLetter(_) => vec![],
 			PatternSpec::Saga(_) => vec![],
 			PatternSpec::ClaimCheck(_) => vec![],
 			PatternSpec::Throttle(_) => vec![],
@@ -138,17 +157,227 @@ impl PatternSpec {
 		}
 	}
 
+	/// Explicit calls to compositions anywhere in this pattern
+	pub fn composition_calls(&self) -> Vec<&CompositionCall> {
+		match self {
+			PatternSpec::ScatterGather(sg) => sg
+				.targets
+				.iter()
+				.flat_map(|t| match &t.operation {
+					ScatterOperation::Tool(_) => vec![],
+					ScatterOperation::Pattern(p) => p.composition_calls(),
+				})
+				.collect(),
+			PatternSpec::MapEach(me) => match &me.inner {
+				MapEachInner::Tool(_) => vec![],
+				MapEachInner::Pattern(p) => p.composition_calls(),
+			},
+			_ => self
+				.operations()
+				.into_iter()
+				.flat_map(StepOperation::composition_calls)
+				.collect(),
+		}
+	}
+
+	/// Step operations this pattern runs; scatter-gather targets and map-each inners are
+	/// tool names or patterns instead
+	fn operations(&self) -> Vec<&StepOperation> {
+		match self {
+			PatternSpec::Pipeline(p) => p.steps.iter().map(|s| &s.operation).collect(),
+			PatternSpec::Retry(r) => vec![&r.inner],
+			PatternSpec::Timeout(t) => std::iter::once(&t.inner)
+				.chain(&t.fallback)
+				.map(AsRef::as_ref)
+				.collect(),
+			PatternSpec::Cache(c) => vec![&c.inner],
+			PatternSpec::Idempotent(i) => vec![&i.inner],
+			PatternSpec::CircuitBreaker(cb) => std::iter::once(&cb.inner)
+				.chain(&cb.fallback)
+				.map(AsRef::as_ref)
+				.collect(),
+			PatternSpec::DeadLetter(dl) => vec![&dl.inner],
+			PatternSpec::Saga(s) => s
+				.steps
+				.iter()
+				.flat_map(|step| std::iter::once(&step.action).chain(&step.compensate))
+				.collect(),
+			PatternSpec::ClaimCheck(cc) => vec![&cc.inner],
+			PatternSpec::Throttle(t) => vec![&t.inner],
+			PatternSpec::Router(r) => r
+				.routes
+				.iter()
+				.map(|route| &route.then)
+				.chain(r.otherwise.as_deref())
+				.collect(),
+			PatternSpec::Enricher(e) => e.enrichments.iter().map(|e| &e.operation).collect(),
+			PatternSpec::WireTap(w) => vec![&w.inner],
+			PatternSpec::CapabilityRouter(cr) => cr.fallback.as_deref().into_iter().collect(),
+			PatternSpec::ConfidenceAggregator(ca) => ca.sources.iter().map(|s| &s.operation).collect(),
+			PatternSpec::ScatterGather(_)
+			| PatternSpec::MapEach(_)
+			| PatternSpec::Filter(_)
+			| PatternSpec::SchemaMap(_)
+			| PatternSpec::Rank(_)
+			| PatternSpec::RecipientList(_)
+			| PatternSpec::SemanticDedup(_) => vec![],
+		}
+	}
+
+	/// This pattern and every pattern nested in it, outermost first
+	pub fn patterns(&self) -> Vec<&PatternSpec> {
+		let nested: Vec<&PatternSpec> = match self {
+			PatternSpec::ScatterGather(sg) => sg
+				.targets
+				.iter()
+				.filter_map(|t| match &t.operation {
+					ScatterOperation::Tool(_) => None,
+					ScatterOperation::Pattern(p) => Some(p.as_ref()),
+				})
+				.collect(),
+			PatternSpec::MapEach(me) => match &me.inner {
+				MapEachInner::Tool(_) => vec![],
+				MapEachInner::Pattern(p) => vec![p.as_ref()],
+			},
+			_ => self
+				.operations()
+				.into_iter()
+				.filter_map(|op| match op {
+					StepOperation::Pattern(p) => Some(p.as_ref()),
+					_ => None,
+				})
+				.collect(),
+		};
+		std::iter::once(self)
+			.chain(nested.into_iter().flat_map(PatternSpec::patterns))
+			.collect()
+	}
+
+	/// Check that this pattern's durations are greater than 0 and its percentages within 0-1,
+	/// including those of the sampling steps it runs; nested patterns are not checked
+	pub fn check_bounds(&self) -> Result<(), String> {
+		match self {
+			PatternSpec::ScatterGather(sg) => sg
+				.timeout_ms
+				.map_or(Ok(()), |t| units::check_nonzero("timeoutMs", t))?,
+			PatternSpec::Rank(r) => {
+				if let RankScorer::Llm(llm) = &r.scorer {
+					units::check_nonzero("timeoutMs", llm.timeout_ms)?;
+				}
+			},
+			PatternSpec::Retry(r) => {
+				if let Some(jitter) = r.jitter {
+					units::check_fraction("jitter", jitter)?;
+				}
+				if let Some(timeout) = r.attempt_timeout_ms {
+					units::check_nonzero("attemptTimeoutMs", timeout)?;
+				}
+			},
+			PatternSpec::Timeout(t) => units::check_nonzero("durationMs", t.duration_ms)?,
+			PatternSpec::CircuitBreaker(cb) => {
+				units::check_nonzero("failureWindowSeconds", cb.failure_window_seconds)?
+			},
+			PatternSpec::Saga(s) => s
+				.timeout_ms
+				.map_or(Ok(()), |t| units::check_nonzero("timeoutMs", t))?,
+			PatternSpec::Throttle(t) => units::check_nonzero("windowMs", t.window_ms)?,
+			PatternSpec::Enricher(e) => e
+				.timeout_ms
+				.map_or(Ok(()), |t| units::check_nonzero("timeoutMs", t))?,
+			PatternSpec::SemanticDedup(sd) => units::check_fraction("threshold", sd.threshold)?,
+			_ => {},
+		}
+		for operation in self.operations() {
+			if let StepOperation::Sample(sample) = operation {
+				units::check_nonzero("timeoutMs", sample.timeout_ms)?;
+			}
+		}
+		Ok(())
+	}
+
+	/// Assert steps this pattern runs; those of nested patterns are not included
+	pub fn assertions(&self) -> Vec<&AssertSpec> {
+		self
+			.operations()
+			.into_iter()
+			.filter_map(|op| match op {
+				StepOperation::Assert(assert) => Some(assert),
+				_ => None,
+			})
+			.collect()
+	}
+
+	/// Script steps anywhere in this pattern, for compiling their modules once
+	pub fn scripts_mut(&mut self) -> Vec<&mut ScriptSpec> {
+		let operations: Vec<&mut StepOperation> = match self {
+			PatternSpec::Pipeline(p) => p.steps.iter_mut().map(|s| &mut s.operation).collect(),
+			PatternSpec::ScatterGather(sg) => {
+				return sg
+					.targets
+					.iter_mut()
+					.flat_map(|t| match &mut t.operation {
+						ScatterOperation::Tool(_) => vec![],
+						ScatterOperation::Pattern(p) => p.scripts_mut(),
+					})
+					.collect();
+			},
+			PatternSpec::MapEach(me) => {
+				return match &mut me.inner {
+					MapEachInner::Tool(_) => vec![],
+					MapEachInner::Pattern(p) => p.scripts_mut(),
+				};
+			},
+			PatternSpec::Retry(r) => vec![&mut r.inner],
+			PatternSpec::Timeout(t) => std::iter::once(&mut t.inner)
+				.chain(&mut t.fallback)
+				.map(AsMut::as_mut)
+				.collect(),
+			PatternSpec::Cache(c) => vec![&mut c.inner],
+			PatternSpec::Idempotent(i) => vec![&mut i.inner],
+			PatternSpec::CircuitBreaker(cb) => std::iter::once(&mut cb.inner)
+				.chain(&mut cb.fallback)
+				.map(AsMut::as_mut)
+				.collect(),
+			PatternSpec::DeadLetter(dl) => vec![&mut dl.inner],
+			PatternSpec::Saga(s) => s
+				.steps
+				.iter_mut()
+				.flat_map(|step| std::iter::once(&mut step.action).chain(&mut step.compensate))
+				.collect(),
+			PatternSpec::ClaimCheck(cc) => vec![&mut cc.inner],
+			PatternSpec::Throttle(t) => vec![&mut t.inner],
+			PatternSpec::Router(r) => r
+				.routes
+				.iter_mut()
+				.map(|route| &mut route.then)
+				.chain(r.otherwise.as_deref_mut())
+				.collect(),
+			PatternSpec::Enricher(e) => e.enrichments.iter_mut().map(|e| &mut e.operation).collect(),
+			PatternSpec::WireTap(w) => vec![&mut w.inner],
+			PatternSpec::CapabilityRouter(cr) => cr.fallback.as_deref_mut().into_iter().collect(),
+			PatternSpec::ConfidenceAggregator(ca) => {
+				ca.sources.iter_mut().map(|s| &mut s.operation).collect()
+			},
+			PatternSpec::Filter(_)
+			| PatternSpec::SchemaMap(_)
+			| PatternSpec::Rank(_)
+			| PatternSpec::RecipientList(_)
+			| PatternSpec::SemanticDedup(_) => vec![],
+		};
+		operations
+			.into_iter()
+			.flat_map(StepOperation::scripts_mut)
+			.collect()
+	}
+
 	/// Returns true if this is a stateful or vision pattern that is not yet implemented
 	pub fn is_stateful_unimplemented(&self) -> bool {
 		matches!(
 			self,
 			// Stateful patterns
 			PatternSpec::Retry(_)
-				| PatternSpec::Timeout(_)
-				| PatternSpec::Cache(_)
 				| PatternSpec::Idempotent(_)
 				| PatternSpec::CircuitBreaker(_)
-				| PatternSpec::DeadLetter(_)
 				| PatternSpec::Saga(_)
 				| PatternSpec::ClaimCheck(_)
 				| PatternSpec::Throttle(_)
@@ -163,6 +392,22 @@ impl PatternSpec {
 		)
 	}
 
+	/// JSONPaths this pattern reads from its input, used to infer an input schema for
+	/// compositions that don't declare one
+	pub fn input_paths(&self) -> Vec<&str> {
+		match self {
+			PatternSpec::Pipeline(p) => p.input_paths(),
+			PatternSpec::SchemaMap(sm) => sm.input_paths(),
+			PatternSpec::Rank(r) => r.input_paths(),
+			// Resilience wrappers pass their input straight to the wrapped operation
+			PatternSpec::Retry(r) => r.inner.input_paths(),
+			PatternSpec::Timeout(t) => t.inner.input_paths(),
+			PatternSpec::CircuitBreaker(cb) => cb.inner.input_paths(),
+			// Other patterns take their input whole (scatter-gather) or operate on arrays
+			_ => vec![],
+		}
+	}
+
 	/// Get the pattern name for error messages
 	pub fn pattern_name(&self) -> &'static str {
 		match self {
@@ -171,6 +416,7 @@ impl PatternSpec {
 			PatternSpec::Filter(_) => "filter",
 			PatternSpec::SchemaMap(_) => "schema_map",
 			PatternSpec::MapEach(_) => "map_each",
+			PatternSpec::Rank(_) => "rank",
 			PatternSpec::Retry(_) => "retry",
 			PatternSpec::Timeout(_) => "timeout",
 			PatternSpec::Cache(_) => "cache",
@@ -1,8 +1,11 @@
 // Pipeline pattern types
 
-use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Arc;
 
-use super::PatternSpec;
+use serde::{Deserialize, Deserializer, Serialize};
+
+use super::{CacheSpec, FieldPredicate, FieldSource, PatternSpec, units};
 
 /// PipelineSpec executes steps sequentially, passing output to next step
 #[derive(Debug, Clone, Deserialize, Serialize)]
@@ -21,6 +24,38 @@ impl PipelineSpec {
 			.flat_map(|step| step.operation.referenced_tools())
 			.collect()
 	}
+
+	/// JSONPaths the pipeline reads from its input
+	pub fn input_paths(&self) -> Vec<&str> {
+		let mut paths = Vec::new();
+		for (i, step) in self.steps.iter().enumerate() {
+			match &step.input {
+				Some(binding) => paths.extend(binding.input_paths()),
+				// The first step receives the pipeline input as is
+				None if i == 0 => paths.extend(step.operation.input_paths()),
+				None => {},
+			}
+		}
+		paths
+	}
+
+	/// Check that step ids are unique and that bindings only read steps that run earlier
+	///
+	/// A binding to a missing or later step otherwise only fails once a call reaches it.
+	pub fn check_bindings(&self) -> Result<(), String> {
+		for (i, step) in self.steps.iter().enumerate() {
+			let earlier = &self.steps[..i];
+			if earlier.iter().any(|s| s.id == step.id) {
+				return Err(format!("duplicate pipeline step id '{}'", step.id));
+			}
+			if let Some(binding) = &step.input {
+				binding
+					.check_steps(earlier, &self.steps)
+					.map_err(|e| format!("step '{}' {e}", step.id))?;
+			}
+		}
+		Ok(())
+	}
 }
 
 /// A single step in a pipeline
@@ -36,6 +71,70 @@ pub struct PipelineStep {
 	/// Input binding for this step
 	#[serde(default)]
 	pub input: Option<DataBinding>,
+
+	/// Store this step's output in session-scoped state ($session.set)
+	#[serde(default)]
+	pub session_set: Option<SessionBinding>,
+
+	/// Retry the step's operation when it fails
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub retry: Option<StepRetry>,
+
+	/// Cache the step's output
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub cache: Option<StepCache>,
+}
+
+/// Inline retry for a pipeline step or scatter-gather target, a lighter alternative to
+/// wrapping the operation in a `retry` pattern
+///
+/// Failures that would fail again (bad input, unknown tools, assertions) are not retried.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StepRetry {
+	/// Total attempts including the first
+	pub max_attempts: u32,
+
+	/// Delay between attempts in milliseconds
+	#[serde(default, deserialize_with = "units::millis")]
+	pub backoff_ms: u64,
+}
+
+/// Inline caching of a pipeline step's output, a lighter alternative to wrapping the
+/// operation in a `cache` pattern
+///
+/// Entries are kept per step, so cached steps of one composition never share them.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StepCache {
+	/// TTL in seconds
+	#[serde(deserialize_with = "units::seconds")]
+	pub ttl_seconds: u32,
+
+	/// JSONPath expressions into the step input to derive the cache key (default: the whole
+	/// input)
+	#[serde(default = "default_step_cache_key_paths")]
+	pub key_paths: Vec<String>,
+}
+
+fn default_step_cache_key_paths() -> Vec<String> {
+	vec!["$".to_string()]
+}
+
+impl StepCache {
+	/// The cache pattern this step cache stands for, wrapping `operation`
+	pub fn cache_spec(&self, operation: &StepOperation) -> CacheSpec {
+		CacheSpec {
+			key_paths: self.key_paths.clone(),
+			inner: Box::new(operation.clone()),
+			store: "default".to_string(),
+			ttl_seconds: self.ttl_seconds,
+			stale_while_revalidate_seconds: None,
+			cache_if: None,
+			stale_if: None,
+			negative: None,
+		}
+	}
 }
 
 /// StepOperation defines what a step does
@@ -47,6 +146,24 @@ pub enum StepOperation {
 
 	/// Inline pattern (no separate name)
 	Pattern(Box<PatternSpec>),
+
+	/// Call another composition of the registry, resolved when the registry is compiled
+	Composition(CompositionCall),
+
+	/// Check the step input and pass it through unchanged
+	Assert(AssertSpec),
+
+	/// Transform the step input with a sandboxed WebAssembly module
+	Script(ScriptSpec),
+
+	/// Ask the connected client's LLM to judge the step input (MCP sampling)
+	Sample(SampleSpec),
+
+	/// Edit the step input with a JSON Patch or merge patch document
+	Patch(PatchSpec),
+
+	/// Output a fixed value, ignoring the step input
+	Constant(serde_json::Value),
 }
 
 impl StepOperation {
@@ -55,6 +172,56 @@ impl StepOperation {
 		match self {
 			StepOperation::Tool(tc) => vec![tc.name.as_str()],
 			StepOperation::Pattern(p) => p.referenced_tools(),
+			StepOperation::Composition(cc) => vec![cc.name.as_str()],
+			StepOperation::Assert(_)
+			| StepOperation::Script(_)
+			| StepOperation::Sample(_)
+			| StepOperation::Patch(_)
+			| StepOperation::Constant(_) => vec![],
+		}
+	}
+
+	/// Explicit calls to compositions made by this operation, including nested ones
+	pub fn composition_calls(&self) -> Vec<&CompositionCall> {
+		match self {
+			StepOperation::Composition(cc) => vec![cc],
+			StepOperation::Pattern(p) => p.composition_calls(),
+			StepOperation::Tool(_)
+			| StepOperation::Assert(_)
+			| StepOperation::Script(_)
+			| StepOperation::Sample(_)
+			| StepOperation::Patch(_)
+			| StepOperation::Constant(_) => vec![],
+		}
+	}
+
+	/// Script steps of this operation, including nested ones
+	pub fn scripts_mut(&mut self) -> Vec<&mut ScriptSpec> {
+		match self {
+			StepOperation::Script(s) => vec![s],
+			StepOperation::Pattern(p) => p.scripts_mut(),
+			StepOperation::Tool(_)
+			| StepOperation::Composition(_)
+			| StepOperation::Assert(_)
+			| StepOperation::Sample(_)
+			| StepOperation::Patch(_)
+			| StepOperation::Constant(_) => vec![],
+		}
+	}
+
+	/// JSONPaths the operation reads from its input (tools take it whole)
+	pub fn input_paths(&self) -> Vec<&str> {
+		match self {
+			StepOperation::Tool(_) => vec![],
+			StepOperation::Pattern(p) => p.input_paths(),
+			StepOperation::Composition(cc) => cc
+				.input
+				.iter()
+				.flat_map(|mappings| mappings.values().flat_map(FieldSource::input_paths))
+				.collect(),
+			StepOperation::Assert(a) => vec![a.predicate.field.as_str()],
+			StepOperation::Patch(p) => p.vars.values().map(String::as_str).collect(),
+			StepOperation::Script(_) | StepOperation::Sample(_) | StepOperation::Constant(_) => vec![],
 		}
 	}
 }
@@ -67,6 +234,268 @@ pub struct ToolCall {
 	pub name: String,
 }
 
+/// Call to another composition, with its arguments and result remapped at the call site
+///
+/// Unlike a tool call naming a composition, the callee must be a composition of the same
+/// registry, and calls that lead back to the caller are rejected when the registry is compiled.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CompositionCall {
+	/// Name of the composition to run
+	pub name: String,
+
+	/// The composition's arguments, mapped from the step input (default: the step input as is)
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub input: Option<HashMap<String, FieldSource>>,
+
+	/// The step output, mapped from the composition's result (default: the result as is)
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub output: Option<HashMap<String, FieldSource>>,
+}
+
+impl CompositionCall {
+	pub fn new(name: impl Into<String>) -> Self {
+		Self {
+			name: name.into(),
+			input: None,
+			output: None,
+		}
+	}
+
+	/// Builder: map the composition's arguments from the step input
+	pub fn with_input(mut self, mappings: HashMap<String, FieldSource>) -> Self {
+		self.input = Some(mappings);
+		self
+	}
+
+	/// Builder: map the step output from the composition's result
+	pub fn with_output(mut self, mappings: HashMap<String, FieldSource>) -> Self {
+		self.output = Some(mappings);
+		self
+	}
+}
+
+/// Assertion over an intermediate result, so a pipeline stops with a meaningful error
+/// instead of passing an empty or malformed value to later steps
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AssertSpec {
+	/// Predicate the step input must satisfy
+	pub predicate: FieldPredicate,
+
+	/// Error message when the predicate doesn't hold (describes the predicate by default)
+	#[serde(default)]
+	pub message: Option<String>,
+
+	/// Whether a failed assertion stops the pipeline
+	#[serde(default)]
+	pub severity: AssertSeverity,
+}
+
+/// What a failed assertion does
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AssertSeverity {
+	/// Fail the composition with the assertion's message
+	#[default]
+	Fail,
+	/// Log the message, report it in the result envelope's `errors` and continue
+	Warn,
+}
+
+/// Transformation beyond declarative mappings, run in a WebAssembly sandbox
+///
+/// The module gets no imports, so it can only compute. It must export its `memory`, an
+/// `alloc(len: i32) -> i32` function and the entrypoint `(ptr: i32, len: i32) -> i64`, which
+/// reads the input JSON written at `ptr` and returns where its output JSON is, packed as
+/// `ptr << 32 | len`.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScriptSpec {
+	/// Base64-encoded WebAssembly module
+	pub wasm: String,
+
+	/// Exported function to call
+	#[serde(default = "default_script_entrypoint")]
+	pub entrypoint: String,
+
+	/// Resource limits for a single run
+	#[serde(default)]
+	pub limits: ScriptLimits,
+
+	/// The decoded and validated module, set when the registry is compiled
+	#[serde(skip)]
+	pub module: Option<Arc<wasmi::Module>>,
+}
+
+fn default_script_entrypoint() -> String {
+	"transform".to_string()
+}
+
+/// Resource limits for a script run; exceeding one fails the step
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ScriptLimits {
+	/// Execution budget in fuel units (about one per instruction)
+	pub fuel: u64,
+
+	/// Largest linear memory the module may grow to, in bytes
+	pub memory_bytes: u64,
+
+	/// Largest output accepted, in bytes
+	pub output_bytes: u64,
+
+	/// Wall-clock budget for a run, in milliseconds
+	pub timeout_ms: u64,
+}
+
+impl ScriptLimits {
+	/// Highest limits a registry may set
+	pub const MAX: ScriptLimits = ScriptLimits {
+		fuel: 1_000_000_000,
+		memory_bytes: 256 * 1024 * 1024,
+		output_bytes: 16 * 1024 * 1024,
+		timeout_ms: 30_000,
+	};
+
+	/// Check each limit is at most [`ScriptLimits::MAX`]
+	pub fn check(&self) -> Result<(), String> {
+		let max = Self::MAX;
+		for (name, value, ceiling) in [
+			("fuel", self.fuel, max.fuel),
+			("memoryBytes", self.memory_bytes, max.memory_bytes),
+			("outputBytes", self.output_bytes, max.output_bytes),
+			("timeoutMs", self.timeout_ms, max.timeout_ms),
+		] {
+			if value > ceiling {
+				return Err(format!(
+					"{name} of {value} exceeds the maximum of {ceiling}"
+				));
+			}
+		}
+		Ok(())
+	}
+}
+
+impl Default for ScriptLimits {
+	fn default() -> Self {
+		Self {
+			fuel: 10_000_000,
+			memory_bytes: 16 * 1024 * 1024,
+			output_bytes: 1024 * 1024,
+			timeout_ms: 5_000,
+		}
+	}
+}
+
+/// Completion request sent to the connected client's LLM through MCP sampling, so a
+/// composition can use model judgment (e.g. ranking results) without gateway-side LLM
+/// credentials
+///
+/// The step input is sent as JSON after the prompt. The step fails if the client didn't
+/// declare the sampling capability or doesn't answer within the timeout.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SampleSpec {
+	/// Instruction for the model
+	pub prompt: String,
+
+	/// System prompt for the completion
+	#[serde(default)]
+	pub system_prompt: Option<String>,
+
+	/// Largest completion to request
+	#[serde(default = "default_sample_max_tokens")]
+	pub max_tokens: u32,
+
+	/// Sampling temperature
+	#[serde(default)]
+	pub temperature: Option<f32>,
+
+	/// Preferred models or model families, most preferred first
+	#[serde(default)]
+	pub model_hints: Vec<String>,
+
+	/// Parse the completion as JSON instead of returning `{text, model}`
+	#[serde(default)]
+	pub json: bool,
+
+	/// How long to wait for the client, in milliseconds
+	#[serde(
+		default = "default_sample_timeout_ms",
+		deserialize_with = "units::millis"
+	)]
+	pub timeout_ms: u32,
+}
+
+pub(crate) fn default_sample_max_tokens() -> u32 {
+	1024
+}
+
+pub(crate) fn default_sample_timeout_ms() -> u32 {
+	60_000
+}
+
+/// Surgical edit of the step input, for changes a whole-object schema map can't express
+///
+/// Exactly one of `json_patch` (RFC 6902) and `merge_patch` (RFC 7386) must be set. Strings
+/// in the patch may hold `{name}` placeholders filled from `vars`; a string that is only a
+/// placeholder takes the variable's value as is, keeping its type.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PatchSpec {
+	/// RFC 6902 operations, applied in order
+	#[serde(default)]
+	pub json_patch: Option<Vec<JsonPatchOperation>>,
+
+	/// RFC 7386 merge patch document
+	#[serde(default)]
+	pub merge_patch: Option<serde_json::Value>,
+
+	/// Placeholder name -> JSONPath into the step input
+	#[serde(default)]
+	pub vars: HashMap<String, String>,
+}
+
+/// A single RFC 6902 operation
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JsonPatchOperation {
+	pub op: JsonPatchOp,
+
+	/// JSON Pointer to the location the operation applies to
+	pub path: String,
+
+	/// JSON Pointer to the source location (`move` and `copy`)
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub from: Option<String>,
+
+	/// Value to add, replace or test against (an explicit `null` counts as a value)
+	#[serde(
+		default,
+		deserialize_with = "present",
+		skip_serializing_if = "Option::is_none"
+	)]
+	pub value: Option<serde_json::Value>,
+}
+
+/// Deserialize a field that is present, even as `null`, to `Some`
+fn present<'de, D: Deserializer<'de>>(d: D) -> Result<Option<serde_json::Value>, D::Error> {
+	serde_json::Value::deserialize(d).map(Some)
+}
+
+/// RFC 6902 operation kinds
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum JsonPatchOp {
+	Add,
+	Remove,
+	Replace,
+	Move,
+	Copy,
+	Test,
+}
+
 /// DataBinding specifies where step input comes from
 #[derive(Debug, Clone, Deserialize, Serialize)]
 #[serde(rename_all = "camelCase")]
@@ -83,6 +512,77 @@ pub enum DataBinding {
 	/// Construct an object from multiple bindings
 	/// This enables input schema construction from prior step outputs
 	Construct(ConstructBinding),
+
+	/// From session-scoped state ($session.get)
+	Session(SessionBinding),
+
+	/// Interpolate other bindings into a string
+	Template(TemplateBinding),
+}
+
+impl DataBinding {
+	/// JSONPaths the binding reads from the pipeline input
+	pub fn input_paths(&self) -> Vec<&str> {
+		match self {
+			DataBinding::Input(ib) => vec![ib.path.as_str()],
+			DataBinding::Construct(cb) => cb.fields.values().flat_map(|b| b.input_paths()).collect(),
+			DataBinding::Template(tb) => tb.vars.values().flat_map(|b| b.input_paths()).collect(),
+			DataBinding::Step(_) | DataBinding::Constant(_) | DataBinding::Session(_) => vec![],
+		}
+	}
+
+	/// Check the steps the binding reads against the `earlier` steps of `pipeline`
+	fn check_steps(&self, earlier: &[PipelineStep], pipeline: &[PipelineStep]) -> Result<(), String> {
+		let declared = |id: &str| pipeline.iter().any(|s| s.id == id);
+		match self {
+			DataBinding::Step(sb) if !earlier.iter().any(|s| s.id == sb.step_id) => {
+				if declared(&sb.step_id) {
+					Err(format!(
+						"binds to step '{}', which doesn't run before it",
+						sb.step_id
+					))
+				} else {
+					Err(format!("binds to unknown step '{}'", sb.step_id))
+				}
+			},
+			// `$steps.<id>` reads the pipeline input, not the output of step <id>
+			DataBinding::Input(ib) => match ib.path.strip_prefix("$steps.") {
+				Some(rest) => {
+					let id = rest.split(['.', '[']).next().unwrap_or_default();
+					if declared(id) {
+						Err(format!(
+							"reads '{}' from the pipeline input; use a step binding to read step '{id}'",
+							ib.path
+						))
+					} else {
+						Err(format!("reads '{}', but there is no step '{id}'", ib.path))
+					}
+				},
+				None => Ok(()),
+			},
+			DataBinding::Construct(cb) => {
+				let mut fields: Vec<_> = cb.fields.iter().collect();
+				fields.sort_by_key(|(name, _)| *name);
+				for (name, binding) in fields {
+					binding
+						.check_steps(earlier, pipeline)
+						.map_err(|e| format!("field '{name}' {e}"))?;
+				}
+				Ok(())
+			},
+			DataBinding::Template(tb) => {
+				let mut vars: Vec<_> = tb.vars.iter().collect();
+				vars.sort_by_key(|(name, _)| *name);
+				for (name, binding) in vars {
+					binding
+						.check_steps(earlier, pipeline)
+						.map_err(|e| format!("template variable '{name}' {e}"))?;
+				}
+				Ok(())
+			},
+			DataBinding::Step(_) | DataBinding::Constant(_) | DataBinding::Session(_) => Ok(()),
+		}
+	}
 }
 
 impl Default for DataBinding {
@@ -112,6 +612,25 @@ pub struct StepBinding {
 	pub path: String,
 }
 
+/// Session binding - reference to a value in session-scoped composition state
+///
+/// When reading, `path` is applied to the stored value (missing keys resolve to null).
+/// When writing, `path` selects the part of the step output to store under `key`.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionBinding {
+	/// Session state key
+	pub key: String,
+
+	/// JSONPath into the value (default: "$")
+	#[serde(default = "default_session_path")]
+	pub path: String,
+}
+
+fn default_session_path() -> String {
+	"$".to_string()
+}
+
 /// Construct binding - build an object from multiple bindings
 /// Enables symmetric input construction (like outputTransform does for outputs)
 #[derive(Debug, Clone, Deserialize, Serialize)]
@@ -121,6 +640,54 @@ pub struct ConstructBinding {
 	pub fields: std::collections::HashMap<String, DataBinding>,
 }
 
+/// Template binding - build a string from other bindings
+///
+/// Lets an input or output combine several step outputs with computed text, where a
+/// construct binding can only place values side by side.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TemplateBinding {
+	/// Template string with {var} placeholders
+	pub template: String,
+
+	/// Variable name -> binding that produces its value
+	pub vars: std::collections::HashMap<String, DataBinding>,
+}
+
+impl TemplateBinding {
+	/// Fill the placeholders with the resolved variable values
+	///
+	/// Strings are inserted as they are, null as nothing and other values as JSON.
+	/// Placeholders without a variable are left in place.
+	pub fn render(&self, values: &std::collections::HashMap<&str, serde_json::Value>) -> String {
+		let mut result = String::with_capacity(self.template.len());
+		let mut rest = self.template.as_str();
+		while let Some(start) = rest.find('{') {
+			result.push_str(&rest[..start]);
+			rest = &rest[start..];
+			let value = rest
+				.find('}')
+				.and_then(|end| Some((end, values.get(&rest[1..end])?)));
+			match value {
+				Some((end, value)) => {
+					match value {
+						serde_json::Value::String(s) => result.push_str(s),
+						serde_json::Value::Null => {},
+						other => result.push_str(&other.to_string()),
+					}
+					rest = &rest[end + 1..];
+				},
+				None => {
+					result.push('{');
+					rest = &rest[1..];
+				},
+			}
+		}
+		result.push_str(rest);
+		result
+	}
+}
+
 #[cfg(test)]
 mod tests {
 	use super::*;
@@ -158,6 +725,96 @@ mod tests {
 		}
 	}
 
+	#[test]
+	fn test_parse_step_operation_assert() {
+		let json = r#"{ "assert": {
+			"predicate": { "field": "$.results[0]", "op": "ne", "value": { "nullValue": true } },
+			"message": "search returned no results"
+		} }"#;
+		let op: StepOperation = serde_json::from_str(json).unwrap();
+		let StepOperation::Assert(assert) = op else {
+			panic!("expected an assert operation");
+		};
+		assert_eq!(assert.predicate.op, "ne");
+		assert_eq!(
+			assert.message.as_deref(),
+			Some("search returned no results")
+		);
+		assert_eq!(assert.severity, AssertSeverity::Fail);
+
+		let json = r#"{ "assert": {
+			"predicate": { "field": "$.total", "op": "gt", "value": { "numberValue": 0 } },
+			"severity": "warn"
+		} }"#;
+		let op: StepOperation = serde_json::from_str(json).unwrap();
+		assert!(matches!(op, StepOperation::Assert(a) if a.severity == AssertSeverity::Warn));
+	}
+
+	#[test]
+	fn test_parse_step_operation_script() {
+		let json = r#"{ "script": { "wasm": "AGFzbQEAAAA=", "limits": { "fuel": 5000 } } }"#;
+		let op: StepOperation = serde_json::from_str(json).unwrap();
+		let StepOperation::Script(script) = op else {
+			panic!("expected a script operation");
+		};
+		assert_eq!(script.entrypoint, "transform");
+		assert_eq!(script.limits.fuel, 5000);
+		assert_eq!(
+			script.limits.memory_bytes,
+			ScriptLimits::default().memory_bytes
+		);
+	}
+
+	#[test]
+	fn test_parse_step_operation_sample() {
+		let json = r#"{ "sample": {
+			"prompt": "Rank these results by relevance",
+			"modelHints": ["claude"],
+			"json": true
+		} }"#;
+		let op: StepOperation = serde_json::from_str(json).unwrap();
+		let StepOperation::Sample(sample) = op else {
+			panic!("expected a sample operation");
+		};
+		assert_eq!(sample.model_hints, vec!["claude"]);
+		assert!(sample.json);
+		assert_eq!(sample.max_tokens, 1024);
+		assert_eq!(sample.timeout_ms, 60_000);
+	}
+
+	#[test]
+	fn test_parse_step_operation_patch() {
+		let json = r#"{ "patch": {
+			"jsonPatch": [
+				{ "op": "replace", "path": "/status", "value": null },
+				{ "op": "move", "from": "/tmp", "path": "/kept" },
+				{ "op": "add", "path": "/owner", "value": "{owner}" }
+			],
+			"vars": { "owner": "$.user.login" }
+		} }"#;
+		let op: StepOperation = serde_json::from_str(json).unwrap();
+		assert_eq!(op.input_paths(), vec!["$.user.login"]);
+		let StepOperation::Patch(patch) = op else {
+			panic!("expected a patch operation");
+		};
+		let ops = patch.json_patch.unwrap();
+		assert_eq!(ops[0].op, JsonPatchOp::Replace);
+		assert_eq!(ops[0].value, Some(serde_json::Value::Null));
+		assert_eq!(ops[1].from.as_deref(), Some("/tmp"));
+		assert_eq!(ops[1].value, None);
+		assert!(patch.merge_patch.is_none());
+	}
+
+	#[test]
+	fn test_parse_step_operation_constant() {
+		let json = r#"{ "constant": { "limit": 10, "sort": "recent" } }"#;
+		let op: StepOperation = serde_json::from_str(json).unwrap();
+		let StepOperation::Constant(value) = op else {
+			panic!("expected a constant operation");
+		};
+		assert_eq!(value, serde_json::json!({"limit": 10, "sort": "recent"}));
+	}
+
 	#[test]
 	fn test_parse_data_binding_input() {
 		let json = r#"{ "input": { "path": "$.query" } }"#;
@@ -179,6 +836,48 @@ mod tests {
 		assert!(matches!(binding, DataBinding::Constant(_)));
 	}
 
+	#[test]
+	fn test_parse_data_binding_session() {
+		let json = r#"{ "session": { "key": "cursor" } }"#;
+		let binding: DataBinding = serde_json::from_str(json).unwrap();
+		let DataBinding::Session(sb) = binding else {
+			panic!("expected session binding");
+		};
+		assert_eq!(sb.key, "cursor");
+		assert_eq!(sb.path, "$");
+	}
+
+	#[test]
+	fn test_parse_step_session_set() {
+		let json = r#"{
+			"id": "page",
+			"operation": { "tool": { "name": "list_items" } },
+			"sessionSet": { "key": "cursor", "path": "$.nextCursor" }
+		}"#;
+		let step: PipelineStep = serde_json::from_str(json).unwrap();
+		let set = step.session_set.unwrap();
+		assert_eq!(set.key, "cursor");
+		assert_eq!(set.path, "$.nextCursor");
+	}
+
+	#[test]
+	fn test_parse_step_cache() {
+		let json = r#"{
+			"id": "search",
+			"operation": { "tool": { "name": "web_search" } },
+			"cache": { "ttlSeconds": 300 }
+		}"#;
+		let step: PipelineStep = serde_json::from_str(json).unwrap();
+		let cache = step.cache.unwrap();
+		assert_eq!(cache.ttl_seconds, 300);
+		// Keyed by the whole step input by default
+		assert_eq!(cache.key_paths, vec!["$"]);
+
+		let spec = cache.cache_spec(&step.operation);
+		assert_eq!(spec.inner.referenced_tools(), vec!["web_search"]);
+		assert_eq!(spec.ttl_seconds, 300);
+	}
+
 	#[test]
 	fn test_referenced_tools() {
 		let json = r#"{
@@ -198,4 +897,72 @@ mod tests {
 		let refs = pipeline.referenced_tools();
 		assert_eq!(refs, vec!["tool_a", "tool_b"]);
 	}
+
+	#[test]
+	fn test_parse_composition_call() {
+		let json = r#"{ "composition": {
+			"name": "forecast",
+			"input": { "city": { "path": "$.destination" } }
+		} }"#;
+		let op: StepOperation = serde_json::from_str(json).unwrap();
+		let StepOperation::Composition(call) = &op else {
+			panic!("expected a composition call");
+		};
+		assert_eq!(call.name, "forecast");
+		assert!(call.output.is_none());
+		assert_eq!(op.referenced_tools(), vec!["forecast"]);
+		assert_eq!(op.input_paths(), vec!["$.destination"]);
+		assert_eq!(op.composition_calls().len(), 1);
+	}
+
+	#[test]
+	fn test_check_bindings() {
+		let check = |summarize_input: serde_json::Value| {
+			let pipeline: PipelineSpec = serde_json::from_value(serde_json::json!({
+				"steps": [
+					{"id": "search", "operation": {"tool": {"name": "web_search"}}},
+					{"id": "summarize", "operation": {"tool": {"name": "summarize"}}, "input": summarize_input},
+					{"id": "publish", "operation": {"tool": {"name": "publish"}}}
+				]
+			}))
+			.unwrap();
+			pipeline.check_bindings()
+		};
+
+		let valid = check(serde_json::json!({"construct": {"fields": {
+			"text": {"step": {"stepId": "search", "path": "$.results"}},
+			"query": {"input": {"path": "$.query"}}
+		}}}));
+		assert_eq!(valid, Ok(()));
+
+		let later = check(serde_json::json!({"step": {"stepId": "publish", "path": "$"}}));
+		assert_eq!(
+			later.unwrap_err(),
+			"step 'summarize' binds to step 'publish', which doesn't run before it"
+		);
+		let typo = check(serde_json::json!({"construct": {"fields": {
+			"text": {"step": {"stepId": "serach", "path": "$"}}
+		}}}));
+		assert_eq!(
+			typo.unwrap_err(),
+			"step 'summarize' field 'text' binds to unknown step 'serach'"
+		);
+		let steps_path =
+			check(serde_json::json!({"input": {"path": "$steps.search.results"}})).unwrap_err();
+		assert!(steps_path.contains("use a step binding to read step 'search'"));
+		let unknown = check(serde_json::json!({"input": {"path": "$steps.fetch"}}));
+		assert!(unknown.unwrap_err().contains("there is no step 'fetch'"));
+
+		let duplicate: PipelineSpec = serde_json::from_value(serde_json::json!({
+			"steps": [
+				{"id": "search", "operation": {"tool": {"name": "a"}}},
+				{"id": "search", "operation": {"tool": {"name": "b"}}}
+			]
+		}))
+		.unwrap();
+		assert_eq!(
+			duplicate.check_bindings().unwrap_err(),
+			"duplicate pipeline step id 'search'"
+		);
+	}
 }
@@ -0,0 +1,184 @@
+// Rank pattern types
+
+use serde::{Deserialize, Serialize};
+
+use super::pipeline::default_sample_max_tokens;
+use super::units;
+
+/// RankSpec scores the elements of an array and sorts them by score, best first
+///
+/// The reusable last step of search aggregations: after a scatter-gather has collected
+/// results from several sources, a rank step orders them with a pluggable scorer and keeps
+/// the best ones.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RankSpec {
+	/// How each element is scored
+	pub scorer: RankScorer,
+
+	/// JSONPath to the array to rank (default: the input itself)
+	#[serde(default)]
+	pub items_path: Option<String>,
+
+	/// Keep at most this many elements
+	#[serde(default)]
+	pub limit: Option<usize>,
+
+	/// Drop elements scoring below this
+	#[serde(default)]
+	pub min_score: Option<f64>,
+
+	/// Sort lowest score first
+	#[serde(default)]
+	pub ascending: bool,
+
+	/// Store each element's score in this field (object elements only)
+	#[serde(default)]
+	pub score_field: Option<String>,
+}
+
+impl RankSpec {
+	/// Get the names of tools referenced by this rank
+	pub fn referenced_tools(&self) -> Vec<&str> {
+		match &self.scorer {
+			RankScorer::Embedding(e) => vec![e.embedder.as_str()],
+			RankScorer::Fields(_) | RankScorer::Llm(_) => vec![],
+		}
+	}
+
+	/// JSONPaths the rank reads from its input
+	pub fn input_paths(&self) -> Vec<&str> {
+		let query = match &self.scorer {
+			RankScorer::Embedding(e) => Some(e.query_path.as_str()),
+			RankScorer::Llm(l) => l.query_path.as_deref(),
+			RankScorer::Fields(_) => None,
+		};
+		self
+			.items_path
+			.as_deref()
+			.into_iter()
+			.chain(query)
+			.collect()
+	}
+}
+
+/// How a rank step scores elements
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum RankScorer {
+	/// Weighted sum of numeric fields; missing or non-numeric fields count as 0
+	Fields(Vec<WeightedField>),
+
+	/// Cosine similarity between each element and a query, embedded by a tool
+	Embedding(EmbeddingScorer),
+
+	/// Scores from the connected client's LLM (MCP sampling)
+	Llm(LlmScorer),
+}
+
+/// A numeric field and its weight in a field score
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WeightedField {
+	/// JSONPath to the field, relative to the element
+	pub path: String,
+
+	/// Multiplier for the field's value
+	#[serde(default = "default_weight")]
+	pub weight: f64,
+}
+
+fn default_weight() -> f64 {
+	1.0
+}
+
+/// Ranks by semantic similarity to a query
+///
+/// The embedder tool is called once with `{"input": [query, text...]}` and must return one
+/// vector per text, as an array of vectors, `{"embeddings": [...]}` or OpenAI-style
+/// `{"data": [{"embedding": [...]}]}`.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EmbeddingScorer {
+	/// Embedding tool
+	pub embedder: String,
+
+	/// JSONPath to the query text in the rank input
+	pub query_path: String,
+
+	/// JSONPath to the text to embed, relative to the element (non-strings are embedded as JSON)
+	#[serde(default = "default_content_path")]
+	pub content_path: String,
+}
+
+fn default_content_path() -> String {
+	"$".to_string()
+}
+
+/// Asks the client's LLM to score the elements in one sampling request
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LlmScorer {
+	/// Scoring criteria for the model
+	pub prompt: String,
+
+	/// JSONPath to a query in the rank input, sent along with the elements
+	#[serde(default)]
+	pub query_path: Option<String>,
+
+	/// Preferred models or model families, most preferred first
+	#[serde(default)]
+	pub model_hints: Vec<String>,
+
+	/// Largest completion to request
+	#[serde(default = "default_sample_max_tokens")]
+	pub max_tokens: u32,
+
+	/// How long to wait for the client, in milliseconds
+	#[serde(default = "default_llm_timeout_ms", deserialize_with = "units::millis")]
+	pub timeout_ms: u32,
+}
+
+pub(crate) fn default_llm_timeout_ms() -> u32 {
+	60_000
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_parse_rank_scorers() {
+		let spec: RankSpec = serde_json::from_str(
+			r#"{
+				"scorer": {"fields": [{"path": "$.stars", "weight": 0.1}, {"path": "$.score"}]},
+				"limit": 5
+			}"#,
+		)
+		.unwrap();
+		let RankScorer::Fields(fields) = &spec.scorer else {
+			panic!("expected a field scorer");
+		};
+		assert_eq!(fields[1].weight, 1.0);
+		assert_eq!(spec.limit, Some(5));
+		assert!(spec.referenced_tools().is_empty());
+
+		let spec: RankSpec = serde_json::from_str(
+			r#"{
+				"itemsPath": "$.results",
+				"scorer": {"embedding": {"embedder": "embed", "queryPath": "$.query"}}
+			}"#,
+		)
+		.unwrap();
+		assert_eq!(spec.referenced_tools(), vec!["embed"]);
+		assert_eq!(spec.input_paths(), vec!["$.results", "$.query"]);
+
+		let spec: RankSpec =
+			serde_json::from_str(r#"{"scorer": {"llm": {"prompt": "Rank by relevance"}}}"#).unwrap();
+		let RankScorer::Llm(llm) = &spec.scorer else {
+			panic!("expected an llm scorer");
+		};
+		assert_eq!(llm.timeout_ms, 60_000);
+		assert_eq!(llm.max_tokens, 1024);
+	}
+}
@@ -2,7 +2,7 @@
 
 use serde::{Deserialize, Serialize};
 
-use super::PatternSpec;
+use super::{PatternSpec, StepRetry, units};
 
 /// ScatterGatherSpec fans out to multiple targets in parallel and aggregates results
 #[derive(Debug, Clone, Deserialize, Serialize)]
@@ -15,12 +15,37 @@ pub struct ScatterGatherSpec {
 	pub aggregation: AggregationStrategy,
 
 	/// Timeout in milliseconds (optional)
-	#[serde(default)]
+	#[serde(default, deserialize_with = "units::millis_opt")]
 	pub timeout_ms: Option<u32>,
 
 	/// If true, fail immediately on first error
 	#[serde(default)]
 	pub fail_fast: bool,
+
+	/// Invoke only a weighted random subset of the targets on each call (optional)
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub sample: Option<TargetSample>,
+}
+
+/// Which targets a sampled scatter-gather invokes on each call
+///
+/// The `always` highest-weighted targets are invoked on every call, ties going to the one
+/// declared first. `draw` more are drawn at random from the rest, each with probability
+/// proportional to its weight, so the long tail of targets is still covered over many calls.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TargetSample {
+	/// Targets invoked on every call
+	#[serde(default)]
+	pub always: u32,
+
+	/// Targets drawn from the rest on each call
+	#[serde(default)]
+	pub draw: u32,
+
+	/// Seed making the draw the same on every call, for tests (optional)
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub seed: Option<u64>,
 }
 
 impl ScatterGatherSpec {
@@ -32,12 +57,100 @@ impl ScatterGatherSpec {
 			.flat_map(|t| t.referenced_tools())
 			.collect()
 	}
+
+	/// Sample only from the targets, invoking the `always` highest-weighted ones and `draw`
+	/// more drawn by weight
+	pub fn with_sample(mut self, sample: TargetSample) -> Self {
+		self.sample = Some(sample);
+		self
+	}
+
+	/// Check that target weights are usable and that sampling invokes at least one target
+	pub fn check_sampling(&self) -> Result<(), String> {
+		if let Some(weight) = self
+			.targets
+			.iter()
+			.filter_map(|t| t.weight)
+			.find(|w| !w.is_finite() || *w < 0.0)
+		{
+			return Err(format!(
+				"scatterGather target weight must be a non-negative number, got {weight}"
+			));
+		}
+		if let Some(sample) = &self.sample
+			&& sample.always == 0
+			&& sample.draw == 0
+		{
+			return Err("scatterGather sample must invoke at least one target".to_string());
+		}
+		Ok(())
+	}
 }
 
 /// A target in a scatter-gather operation
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScatterTarget {
+	/// What the target invokes
+	#[serde(flatten)]
+	pub operation: ScatterOperation,
+
+	/// Retry the target when it fails
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub retry: Option<StepRetry>,
+
+	/// Relative weight when sampling targets (default 1); a zero-weight target is only
+	/// invoked as one of the `always` targets
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub weight: Option<f64>,
+}
+
+impl<'de> Deserialize<'de> for ScatterTarget {
+	fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+	where
+		D: serde::Deserializer<'de>,
+	{
+		ScatterTarget::try_from(ScatterTargetSerde::deserialize(deserializer)?)
+			.map_err(serde::de::Error::custom)
+	}
+}
+
+/// A target as written, with the operation spelled out as `tool`/`pattern`, so strict
+/// parsing sees the target's unknown fields (see `ToolDefinition`)
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ScatterTargetSerde {
+	#[serde(default)]
+	tool: Option<String>,
+	#[serde(default)]
+	pattern: Option<Box<PatternSpec>>,
+	#[serde(default)]
+	retry: Option<StepRetry>,
+	#[serde(default)]
+	weight: Option<f64>,
+}
+
+impl TryFrom<ScatterTargetSerde> for ScatterTarget {
+	type Error = &'static str;
+	fn try_from(value: ScatterTargetSerde) -> Result<Self, Self::Error> {
+		let operation = match (value.tool, value.pattern) {
+			(Some(tool), None) => ScatterOperation::Tool(tool),
+			(None, Some(pattern)) => ScatterOperation::Pattern(pattern),
+			(None, None) => return Err("scatter target needs a `tool` or a `pattern`"),
+			(Some(_), Some(_)) => return Err("scatter target has both a `tool` and a `pattern`"),
+		};
+		Ok(ScatterTarget {
+			operation,
+			retry: value.retry,
+			weight: value.weight,
+		})
+	}
+}
+
+/// What a scatter-gather target invokes
 #[derive(Debug, Clone, Deserialize, Serialize)]
 #[serde(rename_all = "camelCase")]
-pub enum ScatterTarget {
+pub enum ScatterOperation {
 	/// Tool name (resolved from registry or backend)
 	Tool(String),
 
@@ -46,11 +159,43 @@ pub enum ScatterTarget {
 }
 
 impl ScatterTarget {
+	/// Target calling a tool
+	pub fn tool(name: impl Into<String>) -> Self {
+		ScatterOperation::Tool(name.into()).into()
+	}
+
+	/// Target running an inline pattern
+	pub fn pattern(spec: PatternSpec) -> Self {
+		ScatterOperation::Pattern(Box::new(spec)).into()
+	}
+
+	/// Retry the target when it fails
+	pub fn with_retry(mut self, retry: StepRetry) -> Self {
+		self.retry = Some(retry);
+		self
+	}
+
+	/// Weight the target when sampling targets
+	pub fn with_weight(mut self, weight: f64) -> Self {
+		self.weight = Some(weight);
+		self
+	}
+
 	/// Get the names of tools referenced by this target
 	pub fn referenced_tools(&self) -> Vec<&str> {
-		match self {
-			ScatterTarget::Tool(name) => vec![name.as_str()],
-			ScatterTarget::Pattern(p) => p.referenced_tools(),
+		match &self.operation {
+			ScatterOperation::Tool(name) => vec![name.as_str()],
+			ScatterOperation::Pattern(p) => p.referenced_tools(),
+		}
+	}
+}
+
+impl From<ScatterOperation> for ScatterTarget {
+	fn from(operation: ScatterOperation) -> Self {
+		Self {
+			operation,
+			retry: None,
+			weight: None,
 		}
 	}
 }
@@ -92,6 +237,9 @@ pub enum AggregationOp {
 
 	/// Merge objects (for object results)
 	Merge(bool),
+
+	/// Keep the leading results that fit a token budget
+	TokenBudget(TokenBudgetOp),
 }
 
 /// Sort operation
@@ -121,6 +269,41 @@ pub struct LimitOp {
 	pub count: u32,
 }
 
+/// Token budget operation
+///
+/// Results are kept in order, so after a sort the lowest-ranked ones are dropped first, until
+/// the estimated tokens of the kept ones fit the budget.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TokenBudgetOp {
+	/// Most tokens the kept results may hold
+	pub max_tokens: u32,
+
+	/// JSONPath to the text counted in each result; without it the whole result is counted
+	/// as compact JSON
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub text_field: Option<String>,
+
+	/// Shorten the text field of the first result that doesn't fit instead of dropping it
+	#[serde(default)]
+	pub truncate: bool,
+
+	/// How tokens are estimated
+	#[serde(default)]
+	pub tokenizer: TokenizerKind,
+}
+
+/// Token estimate used by a token budget
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum TokenizerKind {
+	/// One token per 4 characters, close for English prose and JSON
+	#[default]
+	Chars,
+	/// 4 tokens per 3 words, steadier for prose in long words
+	Words,
+}
+
 #[cfg(test)]
 mod tests {
 	use super::*;
@@ -154,10 +337,60 @@ mod tests {
 	fn test_parse_scatter_target_tool() {
 		let json = r#"{ "tool": "my_tool" }"#;
 		let target: ScatterTarget = serde_json::from_str(json).unwrap();
-		assert!(matches!(target, ScatterTarget::Tool(_)));
-		if let ScatterTarget::Tool(name) = target {
+		assert!(matches!(target.operation, ScatterOperation::Tool(_)));
+		if let ScatterOperation::Tool(name) = target.operation {
 			assert_eq!(name, "my_tool");
 		}
+		assert!(target.retry.is_none());
+	}
+
+	#[test]
+	fn test_parse_scatter_target_retry() {
+		let json = r#"{ "tool": "flaky_search", "retry": { "maxAttempts": 3, "backoffMs": 50 } }"#;
+		let target: ScatterTarget = serde_json::from_str(json).unwrap();
+		assert_eq!(target.referenced_tools(), vec!["flaky_search"]);
+		assert_eq!(
+			target.retry,
+			Some(StepRetry {
+				max_attempts: 3,
+				backoff_ms: 50,
+			})
+		);
+		assert_eq!(
+			serde_json::to_value(&target).unwrap(),
+			serde_json::from_str::<serde_json::Value>(json).unwrap()
+		);
+	}
+
+	#[test]
+	fn test_parse_target_sample() {
+		let json = r#"{
+			"targets": [
+				{ "tool": "search_web", "weight": 3 },
+				{ "tool": "search_arxiv" },
+				{ "tool": "search_blogs", "weight": 0.5 }
+			],
+			"aggregation": { "ops": [{ "flatten": true }] },
+			"sample": { "always": 1, "draw": 1 }
+		}"#;
+		let sg: ScatterGatherSpec = serde_json::from_str(json).unwrap();
+		assert_eq!(sg.targets[0].weight, Some(3.0));
+		assert_eq!(sg.targets[1].weight, None);
+		assert_eq!(
+			sg.sample,
+			Some(TargetSample {
+				always: 1,
+				draw: 1,
+				seed: None,
+			})
+		);
+		assert!(sg.check_sampling().is_ok());
+
+		let none = sg.clone().with_sample(TargetSample::default());
+		assert!(none.check_sampling().is_err());
+		let mut negative = sg;
+		negative.targets[0].weight = Some(-1.0);
+		assert!(negative.check_sampling().is_err());
 	}
 
 	#[test]
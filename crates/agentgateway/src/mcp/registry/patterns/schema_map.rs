@@ -4,6 +4,8 @@ use std::collections::HashMap;
 
 use serde::{Deserialize, Serialize};
 
+use super::FieldPredicate;
+
 /// SchemaMapSpec transforms input to output using field mappings
 #[derive(Debug, Clone, Deserialize, Serialize)]
 #[serde(rename_all = "camelCase")]
@@ -25,6 +27,15 @@ impl SchemaMapSpec {
 		}
 	}
 
+	/// JSONPaths the mappings read from the input
+	pub fn input_paths(&self) -> Vec<&str> {
+		self
+			.mappings
+			.values()
+			.flat_map(FieldSource::input_paths)
+			.collect()
+	}
+
 	/// Add a path mapping
 	pub fn with_path(mut self, field: impl Into<String>, path: impl Into<String>) -> Self {
 		self
@@ -63,6 +74,12 @@ pub enum FieldSource {
 
 	/// Nested object mapping
 	Nested(Box<SchemaMapSpec>),
+
+	/// Source chosen by a predicate on the input
+	Conditional(Box<ConditionalSource>),
+
+	/// Another source's value converted to a number, string or boolean
+	Coerce(Box<CoerceSource>),
 }
 
 impl FieldSource {
@@ -95,6 +112,25 @@ impl FieldSource {
 	pub fn coalesce(paths: Vec<String>) -> Self {
 		FieldSource::Coalesce(CoalesceSource { paths })
 	}
+
+	/// JSONPaths the source reads from the input
+	pub fn input_paths(&self) -> Vec<&str> {
+		match self {
+			FieldSource::Path(p) => vec![p.as_str()],
+			FieldSource::Literal(_) => vec![],
+			FieldSource::Coalesce(c) => c.paths.iter().map(String::as_str).collect(),
+			FieldSource::Template(t) => t.vars.values().map(String::as_str).collect(),
+			FieldSource::Concat(c) => c.paths.iter().map(String::as_str).collect(),
+			FieldSource::Nested(sm) => sm.input_paths(),
+			FieldSource::Conditional(c) => {
+				let mut paths = vec![c.when.field.as_str()];
+				paths.extend(c.then.input_paths());
+				paths.extend(c.fallback.iter().flat_map(FieldSource::input_paths));
+				paths
+			},
+			FieldSource::Coerce(c) => c.source.input_paths(),
+		}
+	}
 }
 
 /// Literal value in a schema mapping
@@ -157,8 +193,128 @@ pub struct ConcatSource {
 	pub separator: Option<String>,
 }
 
+/// Conditional source - `then` when the predicate matches the input, otherwise `fallback`.
+/// Without a fallback the field is omitted, so polymorphic responses (e.g. success vs error
+/// shapes) can map each shape's fields only when present.
+///
+/// A field of the wrong type for the operator (e.g. `gt` on a string) doesn't match.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConditionalSource {
+	/// Predicate evaluated against the input
+	pub when: FieldPredicate,
+
+	/// Source used when the predicate matches
+	pub then: FieldSource,
+
+	/// Source used when it doesn't
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub fallback: Option<FieldSource>,
+}
+
+/// Coerce source - normalizes a value's type, e.g. for backends that return numbers as strings.
+/// Arrays are coerced element by element.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CoerceSource {
+	/// Source of the value to convert
+	pub source: FieldSource,
+
+	/// Type to convert to
+	#[serde(rename = "as")]
+	pub as_type: CoerceType,
+}
+
+/// Target type of a [`CoerceSource`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CoerceType {
+	/// Parsed with `.` as the decimal separator regardless of locale; `,` is accepted only as a
+	/// thousands separator (`1,234.5`). Booleans become 1 or 0.
+	Number,
+	/// Numbers and booleans are formatted; arrays and objects become JSON text
+	String,
+	/// `true`/`yes`/`on`/`1` and `false`/`no`/`off`/`0`/`""` (case-insensitive); numbers are
+	/// true unless zero
+	Boolean,
+}
+
+impl CoerceType {
+	/// Convert `value`, producing null when it can't be represented as the target type
+	pub fn coerce(self, value: serde_json::Value) -> serde_json::Value {
+		use serde_json::Value;
+		match (self, value) {
+			(_, Value::Null) => Value::Null,
+			(_, Value::Array(items)) => Value::Array(items.into_iter().map(|v| self.coerce(v)).collect()),
+			(CoerceType::Number, v @ Value::Number(_)) => v,
+			(CoerceType::Number, Value::Bool(b)) => Value::from(b as u8),
+			(CoerceType::Number, Value::String(s)) => parse_number(&s).unwrap_or(Value::Null),
+			(CoerceType::String, Value::String(s)) => Value::String(s),
+			(CoerceType::String, v) => Value::String(v.to_string()),
+			(CoerceType::Boolean, v @ Value::Bool(_)) => v,
+			(CoerceType::Boolean, Value::Number(n)) => Value::Bool(n.as_f64() != Some(0.0)),
+			(CoerceType::Boolean, Value::String(s)) => match s.trim().to_ascii_lowercase().as_str() {
+				"true" | "yes" | "on" | "1" => Value::Bool(true),
+				"false" | "no" | "off" | "0" | "" => Value::Bool(false),
+				_ => Value::Null,
+			},
+			(_, Value::Object(_)) => Value::Null,
+		}
+	}
+}
+
+/// Parse a number without regard to the process locale, keeping integers integral
+fn parse_number(s: &str) -> Option<serde_json::Value> {
+	let s = s.trim();
+	let s = s.strip_prefix('+').unwrap_or(s);
+	let cleaned;
+	let s = if s.contains(',') {
+		cleaned = strip_thousands_separators(s)?;
+		cleaned.as_str()
+	} else {
+		s
+	};
+	if let Ok(n) = s.parse::<i64>() {
+		return Some(n.into());
+	}
+	// Rust accepts "inf" and "NaN", which JSON can't represent
+	let n = s.parse::<f64>().ok().filter(|n| n.is_finite())?;
+	serde_json::Number::from_f64(n).map(serde_json::Value::Number)
+}
+
+/// Remove `,` thousands separators, rejecting commas anywhere else (e.g. a decimal comma)
+fn strip_thousands_separators(s: &str) -> Option<String> {
+	let (sign, rest) = match s.strip_prefix('-') {
+		Some(rest) => ("-", rest),
+		None => ("", s),
+	};
+	let (int, frac) = match rest.split_once('.') {
+		Some((int, frac)) => (int, Some(frac)),
+		None => (rest, None),
+	};
+	let mut groups = int.split(',');
+	let first = groups.next()?;
+	if first.is_empty() || first.len() > 3 || !first.bytes().all(|b| b.is_ascii_digit()) {
+		return None;
+	}
+	let mut out = format!("{sign}{first}");
+	for group in groups {
+		if group.len() != 3 || !group.bytes().all(|b| b.is_ascii_digit()) {
+			return None;
+		}
+		out.push_str(group);
+	}
+	if let Some(frac) = frac {
+		out.push('.');
+		out.push_str(frac);
+	}
+	Some(out)
+}
+
 #[cfg(test)]
 mod tests {
+	use serde_json::json;
+
 	use super::*;
 
 	#[test]
@@ -188,6 +344,49 @@ mod tests {
 		));
 	}
 
+	#[test]
+	fn test_coerce_number() {
+		let n = |v: serde_json::Value| CoerceType::Number.coerce(v);
+		assert_eq!(n(json!("42")), json!(42));
+		assert_eq!(n(json!(" -3.5 ")), json!(-3.5));
+		assert_eq!(n(json!("+1e3")), json!(1000.0));
+		assert_eq!(n(json!("1,234,567.25")), json!(1234567.25));
+		assert_eq!(n(json!(true)), json!(1));
+		assert_eq!(n(json!(["1", "x"])), json!([1, null]));
+		// Decimal commas, misplaced separators and non-finite values don't parse
+		assert_eq!(n(json!("1,5")), json!(null));
+		assert_eq!(n(json!("12,34")), json!(null));
+		assert_eq!(n(json!("NaN")), json!(null));
+		assert_eq!(n(json!({"a": 1})), json!(null));
+	}
+
+	#[test]
+	fn test_coerce_string_and_boolean() {
+		assert_eq!(CoerceType::String.coerce(json!(1.5)), json!("1.5"));
+		assert_eq!(CoerceType::String.coerce(json!(false)), json!("false"));
+		assert_eq!(
+			CoerceType::String.coerce(json!({"a": 1})),
+			json!(r#"{"a":1}"#)
+		);
+		assert_eq!(CoerceType::String.coerce(json!(null)), json!(null));
+
+		assert_eq!(CoerceType::Boolean.coerce(json!(" Yes ")), json!(true));
+		assert_eq!(CoerceType::Boolean.coerce(json!("0")), json!(false));
+		assert_eq!(CoerceType::Boolean.coerce(json!(0.0)), json!(false));
+		assert_eq!(CoerceType::Boolean.coerce(json!(2)), json!(true));
+		assert_eq!(CoerceType::Boolean.coerce(json!("maybe")), json!(null));
+	}
+
+	#[test]
+	fn test_parse_field_source_coerce() {
+		let json = r#"{ "coerce": { "source": { "path": "$.price" }, "as": "number" } }"#;
+		let source: FieldSource = serde_json::from_str(json).unwrap();
+		assert!(matches!(
+			source,
+			FieldSource::Coerce(ref c) if c.as_type == CoerceType::Number
+		));
+	}
+
 	#[test]
 	fn test_parse_field_source_path() {
 		let json = r#"{ "path": "$.data.field" }"#;
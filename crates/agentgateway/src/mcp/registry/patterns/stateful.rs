@@ -4,7 +4,10 @@
 // in the runtime. The IR types are defined so compositions can be parsed
 // and validated, with helpful errors when execution is attempted.
 
-use super::{DataBinding, FieldPredicate, StepOperation};
+use super::{
+	DataBinding, FieldPredicate, PatternSpec, PipelineSpec, PipelineStep, StepOperation, StepRetry,
+	units,
+};
 use serde::{Deserialize, Serialize};
 
 // =============================================================================
@@ -29,15 +32,23 @@ pub struct RetrySpec {
 	pub retry_if: Option<FieldPredicate>,
 
 	/// Jitter factor (0.0 - 1.0)
-	#[serde(default, skip_serializing_if = "Option::is_none")]
+	#[serde(
+		default,
+		skip_serializing_if = "Option::is_none",
+		deserialize_with = "units::percentage_opt"
+	)]
 	pub jitter: Option<f32>,
 
 	/// Per-attempt timeout in milliseconds
-	#[serde(default, skip_serializing_if = "Option::is_none")]
+	#[serde(
+		default,
+		skip_serializing_if = "Option::is_none",
+		deserialize_with = "units::millis_opt"
+	)]
 	pub attempt_timeout_ms: Option<u32>,
 }
 
-#[derive(Debug, Clone, Deserialize, Serialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub enum BackoffStrategy {
 	Fixed(FixedBackoff),
@@ -45,16 +56,36 @@ pub enum BackoffStrategy {
 	Linear(LinearBackoff),
 }
 
-#[derive(Debug, Clone, Deserialize, Serialize)]
+impl BackoffStrategy {
+	/// Delay before retrying after the given (1-based) failed attempt
+	pub fn delay(&self, attempt: u32) -> std::time::Duration {
+		let retries = attempt.saturating_sub(1);
+		let ms = match self {
+			BackoffStrategy::Fixed(f) => f.delay_ms as f64,
+			BackoffStrategy::Exponential(e) => (e.initial_delay_ms as f64
+				* (e.multiplier as f64).powi(retries as i32))
+			.min(e.max_delay_ms as f64),
+			BackoffStrategy::Linear(l) => (l.initial_delay_ms as f64
+				+ l.increment_ms as f64 * retries as f64)
+				.min(l.max_delay_ms as f64),
+		};
+		std::time::Duration::from_millis(ms as u64)
+	}
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct FixedBackoff {
+	#[serde(deserialize_with = "units::millis")]
 	pub delay_ms: u32,
 }
 
-#[derive(Debug, Clone, Deserialize, Serialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct ExponentialBackoff {
+	#[serde(deserialize_with = "units::millis")]
 	pub initial_delay_ms: u32,
+	#[serde(deserialize_with = "units::millis")]
 	pub max_delay_ms: u32,
 	#[serde(default = "default_multiplier")]
 	pub multiplier: f32,
@@ -64,11 +95,14 @@ fn default_multiplier() -> f32 {
 	2.0
 }
 
-#[derive(Debug, Clone, Deserialize, Serialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct LinearBackoff {
+	#[serde(deserialize_with = "units::millis")]
 	pub initial_delay_ms: u32,
+	#[serde(deserialize_with = "units::millis")]
 	pub increment_ms: u32,
+	#[serde(deserialize_with = "units::millis")]
 	pub max_delay_ms: u32,
 }
 
@@ -84,6 +118,7 @@ pub struct TimeoutSpec {
 	pub inner: Box<StepOperation>,
 
 	/// Timeout duration in milliseconds
+	#[serde(deserialize_with = "units::millis")]
 	pub duration_ms: u32,
 
 	/// Fallback on timeout (optional)
@@ -95,6 +130,15 @@ pub struct TimeoutSpec {
 	pub message: Option<String>,
 }
 
+impl TimeoutSpec {
+	/// Get the names of tools referenced by the wrapped operation and fallback
+	pub fn referenced_tools(&self) -> Vec<&str> {
+		let mut refs = self.inner.referenced_tools();
+		refs.extend(self.fallback.iter().flat_map(|f| f.referenced_tools()));
+		refs
+	}
+}
+
 // =============================================================================
 // Cache Pattern
 // =============================================================================
@@ -113,15 +157,45 @@ pub struct CacheSpec {
 	pub store: String,
 
 	/// TTL in seconds
+	#[serde(deserialize_with = "units::seconds")]
 	pub ttl_seconds: u32,
 
 	/// Stale-while-revalidate window in seconds
-	#[serde(default, skip_serializing_if = "Option::is_none")]
+	#[serde(
+		default,
+		skip_serializing_if = "Option::is_none",
+		deserialize_with = "units::seconds_opt"
+	)]
 	pub stale_while_revalidate_seconds: Option<u32>,
 
 	/// Condition to cache result (if absent, always cache)
 	#[serde(default, skip_serializing_if = "Option::is_none")]
 	pub cache_if: Option<FieldPredicate>,
+
+	/// Condition that forces revalidation of a cached result (e.g. empty results)
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub stale_if: Option<FieldPredicate>,
+
+	/// Short-lived caching of failures and empty results
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub negative: Option<NegativeCacheSpec>,
+}
+
+/// NegativeCacheSpec - protects backends from repeated identical failing queries
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NegativeCacheSpec {
+	/// TTL in seconds for negative entries
+	#[serde(deserialize_with = "units::seconds")]
+	pub ttl_seconds: u32,
+
+	/// Error kinds to cache, e.g. `tool_not_found` (if empty, failures that aren't retryable)
+	#[serde(default, skip_serializing_if = "Vec::is_empty")]
+	pub errors: Vec<String>,
+
+	/// Also cache empty results (null, "", [] or {})
+	#[serde(default)]
+	pub empty_results: bool,
 }
 
 // =============================================================================
@@ -142,7 +216,11 @@ pub struct IdempotentSpec {
 	pub store: String,
 
 	/// TTL in seconds (None = no expiry)
-	#[serde(default, skip_serializing_if = "Option::is_none")]
+	#[serde(
+		default,
+		skip_serializing_if = "Option::is_none",
+		deserialize_with = "units::seconds_opt"
+	)]
 	pub ttl_seconds: Option<u32>,
 
 	/// Behavior on duplicate
@@ -180,9 +258,11 @@ pub struct CircuitBreakerSpec {
 	pub failure_threshold: u32,
 
 	/// Window for counting failures (seconds)
+	#[serde(deserialize_with = "units::seconds")]
 	pub failure_window_seconds: u32,
 
 	/// Time to wait before half-open (seconds)
+	#[serde(deserialize_with = "units::seconds")]
 	pub reset_timeout_seconds: u32,
 
 	/// Successes needed in half-open to close (default: 1)
@@ -202,6 +282,15 @@ fn default_success_threshold() -> u32 {
 	1
 }
 
+impl CircuitBreakerSpec {
+	/// Get the names of tools referenced by the protected operation and fallback
+	pub fn referenced_tools(&self) -> Vec<&str> {
+		let mut refs = self.inner.referenced_tools();
+		refs.extend(self.fallback.iter().flat_map(|f| f.referenced_tools()));
+		refs
+	}
+}
+
 // =============================================================================
 // Dead Letter Pattern
 // =============================================================================
@@ -227,6 +316,30 @@ pub struct DeadLetterSpec {
 	/// Whether to rethrow after dead-lettering
 	#[serde(default)]
 	pub rethrow: bool,
+
+	/// Input fields to redact before dead-lettering, on top of the built-in credential keys
+	/// (matched case-insensitively as substrings of the key)
+	#[serde(default, skip_serializing_if = "Vec::is_empty")]
+	pub sensitive_fields: Vec<String>,
+
+	/// Most bytes of input (as compact JSON) sent inline to the dead letter tool; the error
+	/// text is cut to the same length
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub max_payload_bytes: Option<u32>,
+
+	/// Tool that stores an input over `max_payload_bytes` and returns a reference to send
+	/// instead; without it the input is left out
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub claim_check_tool: Option<String>,
+}
+
+impl DeadLetterSpec {
+	pub fn referenced_tools(&self) -> Vec<&str> {
+		let mut refs = self.inner.referenced_tools();
+		refs.push(&self.dead_letter_tool);
+		refs.extend(self.claim_check_tool.as_deref());
+		refs
+	}
 }
 
 fn default_max_attempts() -> u32 {
@@ -253,7 +366,11 @@ pub struct SagaSpec {
 	pub saga_id_path: Option<String>,
 
 	/// Timeout for entire saga in milliseconds
-	#[serde(default, skip_serializing_if = "Option::is_none")]
+	#[serde(
+		default,
+		skip_serializing_if = "Option::is_none",
+		deserialize_with = "units::millis_opt"
+	)]
 	pub timeout_ms: Option<u32>,
 
 	/// Output binding
@@ -318,6 +435,7 @@ pub struct ThrottleSpec {
 	pub rate: u32,
 
 	/// Window size in milliseconds
+	#[serde(deserialize_with = "units::millis")]
 	pub window_ms: u32,
 
 	/// Rate limiting strategy
@@ -352,6 +470,129 @@ pub enum OnExceeded {
 	Queue,
 }
 
+// =============================================================================
+// Resilience Shorthand
+// =============================================================================
+
+/// Resilience - shorthand on a composition, expanded at compile time into a Timeout pattern
+/// wrapping the composition's own pattern, run as a retried pipeline step
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Resilience {
+	/// Timeout for each attempt in milliseconds
+	#[serde(
+		default,
+		skip_serializing_if = "Option::is_none",
+		deserialize_with = "units::millis_opt"
+	)]
+	pub timeout_ms: Option<u32>,
+
+	/// Retries after the first attempt
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub retries: Option<u32>,
+
+	/// Backoff between retries; only `fixed` is supported (default: fixed 100ms)
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub backoff: Option<BackoffStrategy>,
+
+	/// Circuit breaker around all attempts. Not supported yet: registries setting it fail
+	/// to compile rather than run without one.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub circuit_breaker: Option<CircuitBreakerShorthand>,
+}
+
+/// Circuit breaker settings for the resilience shorthand (the circuit is named after the tool)
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CircuitBreakerShorthand {
+	/// Store for circuit state
+	pub store: String,
+
+	/// Number of failures to trip the circuit (default: 5)
+	#[serde(default = "default_failure_threshold")]
+	pub failure_threshold: u32,
+
+	/// Window for counting failures in seconds (default: 60)
+	#[serde(
+		default = "default_failure_window_seconds",
+		deserialize_with = "units::seconds"
+	)]
+	pub failure_window_seconds: u32,
+
+	/// Time to wait before half-open in seconds (default: 30)
+	#[serde(
+		default = "default_reset_timeout_seconds",
+		deserialize_with = "units::seconds"
+	)]
+	pub reset_timeout_seconds: u32,
+}
+
+pub(crate) fn default_failure_threshold() -> u32 {
+	5
+}
+
+pub(crate) fn default_failure_window_seconds() -> u32 {
+	60
+}
+
+pub(crate) fn default_reset_timeout_seconds() -> u32 {
+	30
+}
+
+impl Resilience {
+	/// Delay between retries when no backoff is set
+	const DEFAULT_RETRY_DELAY_MS: u64 = 100;
+
+	/// Why this shorthand can't be expanded into patterns the executor runs, if it can't
+	pub fn unsupported(&self) -> Option<&'static str> {
+		if self.circuit_breaker.is_some() {
+			return Some("circuitBreaker is not supported for compositions yet");
+		}
+		match &self.backoff {
+			None | Some(BackoffStrategy::Fixed(_)) => None,
+			Some(_) => Some("backoff must be fixed; composition retries wait the same delay each time"),
+		}
+	}
+
+	/// Wrap a composition's pattern in the patterns this shorthand describes.
+	///
+	/// The timeout applies to each attempt rather than the whole call. Retries run the
+	/// result as the only step of a pipeline, retried like any other step. Settings
+	/// [`Resilience::unsupported`] reports are ignored.
+	pub fn expand(&self, name: &str, spec: PatternSpec) -> PatternSpec {
+		let spec = match self.timeout_ms {
+			Some(duration_ms) => PatternSpec::Timeout(TimeoutSpec {
+				inner: Box::new(StepOperation::Pattern(Box::new(spec))),
+				duration_ms,
+				fallback: None,
+				message: None,
+			}),
+			None => spec,
+		};
+
+		let Some(retries) = self.retries.filter(|r| *r > 0) else {
+			return spec;
+		};
+		let backoff_ms = match &self.backoff {
+			Some(BackoffStrategy::Fixed(f)) => f.delay_ms as u64,
+			_ => Self::DEFAULT_RETRY_DELAY_MS,
+		};
+		PatternSpec::Pipeline(PipelineSpec {
+			steps: vec![PipelineStep {
+				id: name.to_string(),
+				operation: StepOperation::Pattern(Box::new(spec)),
+				input: None,
+				session_set: None,
+				retry: Some(StepRetry {
+					max_attempts: retries.saturating_add(1),
+					backoff_ms,
+				}),
+				cache: None,
+			}],
+		})
+	}
+}
+
 // =============================================================================
 // Tests
 // =============================================================================
@@ -400,13 +641,19 @@ mod tests {
             "keyPaths": ["$.query", "$.filters"],
             "inner": { "tool": { "name": "search" } },
             "store": "result_cache",
-            "ttlSeconds": 900
+            "ttlSeconds": 900,
+            "staleIf": { "field": "$.count", "op": "eq", "value": { "numberValue": 0 } },
+            "negative": { "ttlSeconds": 10, "errors": ["tool_not_found"] }
         }"#;
 
 		let spec: CacheSpec = serde_json::from_str(json).unwrap();
 		assert_eq!(spec.key_paths.len(), 2);
 		assert_eq!(spec.store, "result_cache");
 		assert_eq!(spec.ttl_seconds, 900);
+		assert_eq!(spec.stale_if.unwrap().field, "$.count");
+		let negative = spec.negative.unwrap();
+		assert_eq!(negative.ttl_seconds, 10);
+		assert!(!negative.empty_results);
 	}
 
 	#[test]
@@ -426,6 +673,72 @@ mod tests {
 		assert_eq!(spec.success_threshold, 1); // default
 	}
 
+	#[test]
+	fn test_expand_resilience() {
+		let json = r#"{
+            "timeoutMs": 10000,
+            "retries": 2,
+            "backoff": { "fixed": { "delayMs": 250 } }
+        }"#;
+		let resilience: Resilience = serde_json::from_str(json).unwrap();
+		assert_eq!(resilience.unsupported(), None);
+
+		let inner = PatternSpec::Pipeline(PipelineSpec { steps: vec![] });
+		let PatternSpec::Pipeline(pipeline) = resilience.expand("search", inner) else {
+			panic!("Expected Pipeline");
+		};
+		let [step] = pipeline.steps.as_slice() else {
+			panic!("Expected a single step");
+		};
+		assert_eq!(step.id, "search");
+		assert_eq!(
+			step.retry,
+			Some(StepRetry {
+				max_attempts: 3,
+				backoff_ms: 250
+			})
+		);
+		// The timeout applies to each attempt
+		let StepOperation::Pattern(timeout) = &step.operation else {
+			panic!("Expected inline pattern");
+		};
+		let PatternSpec::Timeout(timeout) = &**timeout else {
+			panic!("Expected Timeout");
+		};
+		assert_eq!(timeout.duration_ms, 10000);
+		assert!(matches!(
+			*timeout.inner,
+			StepOperation::Pattern(ref p) if matches!(**p, PatternSpec::Pipeline(_))
+		));
+
+		// A timeout without retries wraps the pattern directly
+		let timeout_only = Resilience {
+			timeout_ms: Some(500),
+			..Default::default()
+		};
+		let inner = PatternSpec::Pipeline(PipelineSpec { steps: vec![] });
+		assert!(matches!(
+			timeout_only.expand("search", inner),
+			PatternSpec::Timeout(TimeoutSpec {
+				duration_ms: 500,
+				..
+			})
+		));
+	}
+
+	#[test]
+	fn test_resilience_unsupported_settings() {
+		let circuit_breaker: Resilience =
+			serde_json::from_str(r#"{ "circuitBreaker": { "store": "circuit_state" } }"#).unwrap();
+		assert!(circuit_breaker.unsupported().is_some());
+
+		let exponential: Resilience = serde_json::from_str(
+			r#"{ "retries": 2, "backoff": { "exponential": { "initialDelayMs": 100, "maxDelayMs": 5000 } } }"#,
+		)
+		.unwrap();
+		assert!(exponential.unsupported().is_some());
+	}
+
 	#[test]
 	fn test_parse_saga_spec() {
 		let json = r#"{
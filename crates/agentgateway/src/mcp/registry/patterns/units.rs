@@ -0,0 +1,269 @@
+// Durations and percentages in pattern specs
+//
+// Duration fields keep their unit in the field name (`timeoutMs`, `ttlSeconds`) and serialize
+// as plain numbers in that unit, but also accept a string with units, so `"timeoutMs": "30s"`
+// reads as 30000 and `"ttlSeconds": "5m"` as 300. Percentage fields take a fraction (0.25) or
+// a percent string ("25%").
+//
+// Parsing only converts units. Bounds (no 0ms timeouts, fractions within 0-1) are checked on
+// the typed specs when a registry is compiled, so registries loaded from protobuf, which carry
+// plain numbers, are held to the same rules.
+
+use std::time::Duration;
+
+use serde::{Deserialize, Deserializer};
+
+/// Parse a duration such as "250ms", "5s", "2m", "1h" or "1m30s"
+pub fn parse_duration(text: &str) -> Result<Duration, String> {
+	let invalid = || {
+		format!(
+			"invalid duration \"{text}\": expected a number with a unit (ms, s, m or h), e.g. \"5s\" or \"2m\""
+		)
+	};
+	let mut rest = text.trim();
+	if rest.is_empty() {
+		return Err(invalid());
+	}
+	let mut total = Duration::ZERO;
+	while !rest.is_empty() {
+		let number_len = rest
+			.find(|c: char| !c.is_ascii_digit() && c != '.')
+			.ok_or_else(invalid)?;
+		let number: f64 = rest[..number_len].parse().map_err(|_| invalid())?;
+		rest = &rest[number_len..];
+		let unit_len = rest
+			.find(|c: char| c.is_ascii_digit() || c == '.')
+			.unwrap_or(rest.len());
+		let unit_secs = match &rest[..unit_len] {
+			"ms" => 0.001,
+			"s" => 1.0,
+			"m" => 60.0,
+			"h" => 3600.0,
+			_ => return Err(invalid()),
+		};
+		rest = &rest[unit_len..];
+		total += Duration::try_from_secs_f64(number * unit_secs).map_err(|_| invalid())?;
+	}
+	Ok(total)
+}
+
+/// Parse a percentage: a fraction (0.25) or a percent string ("25%")
+pub fn parse_percentage(text: &str) -> Result<f64, String> {
+	let invalid = || format!("invalid percentage \"{text}\": expected e.g. \"25%\" or 0.25");
+	match text.trim().strip_suffix('%') {
+		Some(percent) => Ok(percent.trim().parse::<f64>().map_err(|_| invalid())? / 100.0),
+		None => text.trim().parse().map_err(|_| invalid()),
+	}
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum NumberOrText {
+	Number(f64),
+	Text(String),
+}
+
+impl NumberOrText {
+	/// Count of `unit`s in a number in that unit or a string with units
+	fn duration_in<T: TryFrom<u64>>(self, unit: Duration) -> Result<T, String> {
+		let count = match self {
+			NumberOrText::Number(n) if n >= 0.0 && n.fract() == 0.0 => n as u64,
+			NumberOrText::Number(n) => {
+				return Err(format!(
+					"invalid duration {n}: expected a whole, non-negative number or a string such as \"5s\""
+				));
+			},
+			NumberOrText::Text(text) => {
+				let duration = parse_duration(&text)?;
+				if duration.as_nanos() % unit.as_nanos() != 0 {
+					let unit = if unit == Duration::from_secs(1) {
+						"seconds"
+					} else {
+						"milliseconds"
+					};
+					return Err(format!(
+						"invalid duration \"{text}\": must be a whole number of {unit}"
+					));
+				}
+				(duration.as_nanos() / unit.as_nanos()) as u64
+			},
+		};
+		T::try_from(count).map_err(|_| format!("duration {count} is too large"))
+	}
+
+	fn percentage(self) -> Result<f32, String> {
+		match self {
+			NumberOrText::Number(n) => Ok(n as f32),
+			NumberOrText::Text(text) => parse_percentage(&text).map(|f| f as f32),
+		}
+	}
+}
+
+/// Deserialize a millisecond field from a number of milliseconds or a string such as "5s"
+pub fn millis<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+	D: Deserializer<'de>,
+	T: TryFrom<u64>,
+{
+	NumberOrText::deserialize(deserializer)?
+		.duration_in(Duration::from_millis(1))
+		.map_err(serde::de::Error::custom)
+}
+
+/// Optional form of [`millis`]
+pub fn millis_opt<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+	D: Deserializer<'de>,
+	T: TryFrom<u64>,
+{
+	Option::<NumberOrText>::deserialize(deserializer)?
+		.map(|v| v.duration_in(Duration::from_millis(1)))
+		.transpose()
+		.map_err(serde::de::Error::custom)
+}
+
+/// Deserialize a seconds field from a number of seconds or a string such as "5m"
+pub fn seconds<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+	D: Deserializer<'de>,
+	T: TryFrom<u64>,
+{
+	NumberOrText::deserialize(deserializer)?
+		.duration_in(Duration::from_secs(1))
+		.map_err(serde::de::Error::custom)
+}
+
+/// Optional form of [`seconds`]
+pub fn seconds_opt<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+	D: Deserializer<'de>,
+	T: TryFrom<u64>,
+{
+	Option::<NumberOrText>::deserialize(deserializer)?
+		.map(|v| v.duration_in(Duration::from_secs(1)))
+		.transpose()
+		.map_err(serde::de::Error::custom)
+}
+
+/// Deserialize a fraction from a number or a percent string such as "25%"
+pub fn percentage<'de, D>(deserializer: D) -> Result<f32, D::Error>
+where
+	D: Deserializer<'de>,
+{
+	NumberOrText::deserialize(deserializer)?
+		.percentage()
+		.map_err(serde::de::Error::custom)
+}
+
+/// Optional form of [`percentage`]
+pub fn percentage_opt<'de, D>(deserializer: D) -> Result<Option<f32>, D::Error>
+where
+	D: Deserializer<'de>,
+{
+	Option::<NumberOrText>::deserialize(deserializer)?
+		.map(NumberOrText::percentage)
+		.transpose()
+		.map_err(serde::de::Error::custom)
+}
+
+/// Check that a duration field, named `key` with its unit, is greater than 0
+pub fn check_nonzero(key: &str, value: impl Into<u64>) -> Result<(), String> {
+	if value.into() == 0 {
+		let zero = if key.ends_with("Ms") { "0ms" } else { "0s" };
+		return Err(format!("{key} must be greater than 0, got {zero}"));
+	}
+	Ok(())
+}
+
+/// Check that a percentage field holds a fraction between 0 and 1
+pub fn check_fraction(key: &str, fraction: f32) -> Result<(), String> {
+	if !(0.0..=1.0).contains(&fraction) {
+		return Err(format!(
+			"{key} must be between 0 and 1 (or \"0%\" and \"100%\"), got {fraction}"
+		));
+	}
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::mcp::registry::patterns::{ThrottleSpec, TimeoutSpec};
+	use serde_json::json;
+
+	#[test]
+	fn test_parse_duration() {
+		assert_eq!(parse_duration("250ms"), Ok(Duration::from_millis(250)));
+		assert_eq!(parse_duration("5s"), Ok(Duration::from_secs(5)));
+		assert_eq!(parse_duration("1.5s"), Ok(Duration::from_millis(1500)));
+		assert_eq!(parse_duration("1m30s"), Ok(Duration::from_secs(90)));
+		assert_eq!(parse_duration("2h"), Ok(Duration::from_secs(7200)));
+		for invalid in ["", "5", "5x", "s", "-5s", "1..5s"] {
+			let err = parse_duration(invalid).unwrap_err();
+			assert!(
+				err.contains("expected a number with a unit"),
+				"{invalid}: {err}"
+			);
+		}
+	}
+
+	#[test]
+	fn test_parse_percentage() {
+		assert_eq!(parse_percentage("25%"), Ok(0.25));
+		assert_eq!(parse_percentage("0.5"), Ok(0.5));
+		assert!(parse_percentage("lots").is_err());
+	}
+
+	#[test]
+	fn test_duration_fields_accept_units() {
+		let spec: TimeoutSpec = serde_json::from_value(json!({
+			"inner": {"tool": {"name": "fetch"}},
+			"durationMs": "1m30s"
+		}))
+		.unwrap();
+		assert_eq!(spec.duration_ms, 90_000);
+
+		// Plain numbers are still in the unit of the field
+		let spec: TimeoutSpec = serde_json::from_value(json!({
+			"inner": {"tool": {"name": "fetch"}},
+			"durationMs": 250
+		}))
+		.unwrap();
+		assert_eq!(spec.duration_ms, 250);
+		assert_eq!(serde_json::to_value(&spec).unwrap()["durationMs"], 250);
+
+		let spec: ThrottleSpec = serde_json::from_value(json!({
+			"inner": {"tool": {"name": "fetch"}},
+			"rate": 10,
+			"windowMs": "1s"
+		}))
+		.unwrap();
+		assert_eq!(spec.window_ms, 1000);
+
+		let err = serde_json::from_value::<TimeoutSpec>(json!({
+			"inner": {"tool": {"name": "fetch"}},
+			"durationMs": 1.5
+		}))
+		.unwrap_err();
+		assert!(
+			err
+				.to_string()
+				.contains("expected a whole, non-negative number")
+		);
+	}
+
+	#[test]
+	fn test_check_bounds() {
+		assert_eq!(
+			check_nonzero("timeoutMs", 0u32).unwrap_err(),
+			"timeoutMs must be greater than 0, got 0ms"
+		);
+		assert_eq!(
+			check_nonzero("failureWindowSeconds", 0u32).unwrap_err(),
+			"failureWindowSeconds must be greater than 0, got 0s"
+		);
+		assert!(check_nonzero("timeoutMs", 1u32).is_ok());
+		assert!(check_fraction("threshold", 1.2).is_err());
+		assert!(check_fraction("threshold", 0.8).is_ok());
+	}
+}
@@ -5,7 +5,9 @@
 // enable sophisticated content-based routing, parallel enrichment, observability
 // taps, and intelligent result aggregation.
 
-use super::{AggregationStrategy, DataBinding, FieldPredicate, SchemaMapSpec, StepOperation};
+use super::{
+	AggregationStrategy, DataBinding, FieldPredicate, SchemaMapSpec, StepOperation, units,
+};
 use serde::{Deserialize, Serialize};
 
 // =============================================================================
@@ -98,7 +100,11 @@ pub struct EnricherSpec {
 	pub ignore_failures: bool,
 
 	/// Timeout for enrichment calls in milliseconds
-	#[serde(default, skip_serializing_if = "Option::is_none")]
+	#[serde(
+		default,
+		skip_serializing_if = "Option::is_none",
+		deserialize_with = "units::millis_opt"
+	)]
 	pub timeout_ms: Option<u32>,
 }
 
@@ -344,6 +350,7 @@ pub struct SemanticDedupSpec {
 	pub content_path: String,
 
 	/// Similarity threshold (0.0 - 1.0)
+	#[serde(deserialize_with = "units::percentage")]
 	pub threshold: f32,
 
 	/// Strategy for choosing representative item
@@ -0,0 +1,287 @@
+// Readiness of a registry store
+//
+// A gateway whose registry never loaded, or whose state stores can't be written, answers every
+// virtual tool call with an error. The readiness probe reports it as not ready, so
+// orchestration platforms route traffic elsewhere until it recovers. The write checks behind
+// the state stores are reused for `STORAGE_CHECK_TTL`, so frequent probes don't write to the
+// journal and archive directories on every request.
+//
+// Failing composition upstreams are only reported by the registry's own health report, not
+// held against the gateway's readiness: a backend shared by every replica would otherwise take
+// them all out of rotation at once, along with routes that never call it. Upstreams are
+// initialized lazily, when clients connect, so a target is only reported once a fanout to it
+// has failed, and only for `UPSTREAM_FAILURE_TTL`. A source tool with fallback targets only
+// counts as failing while every target it fails over to is.
+
+use std::collections::{BTreeSet, HashMap};
+use std::future::Future;
+use std::time::{Duration, Instant};
+
+use parking_lot::Mutex;
+use serde::Serialize;
+
+use super::compiled::CompiledRegistry;
+
+/// How long a failed upstream is reported as failing
+pub const UPSTREAM_FAILURE_TTL: Duration = Duration::from_secs(30);
+
+/// How long the outcome of the state store write checks is reused
+pub const STORAGE_CHECK_TTL: Duration = Duration::from_secs(5);
+
+/// Outcome of one readiness check
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReadinessCheck {
+	pub name: String,
+	pub ready: bool,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub message: Option<String>,
+}
+
+impl ReadinessCheck {
+	pub fn ready(name: &str, message: Option<String>) -> Self {
+		Self {
+			name: name.to_string(),
+			ready: true,
+			message,
+		}
+	}
+
+	pub fn not_ready(name: &str, message: String) -> Self {
+		Self {
+			name: name.to_string(),
+			ready: false,
+			message: Some(message),
+		}
+	}
+}
+
+/// Readiness of a registry store and the checks it was derived from
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RegistryReadiness {
+	pub ready: bool,
+	pub checks: Vec<ReadinessCheck>,
+}
+
+impl RegistryReadiness {
+	pub fn new(checks: Vec<ReadinessCheck>) -> Self {
+		Self {
+			ready: checks.iter().all(|c| c.ready),
+			checks,
+		}
+	}
+
+	/// The checks that failed, as `name: message` for a plaintext probe response
+	pub fn failures(&self) -> Vec<String> {
+		self
+			.checks
+			.iter()
+			.filter(|c| !c.ready)
+			.map(|c| {
+				format!(
+					"{}: {}",
+					c.name,
+					c.message.as_deref().unwrap_or("not ready")
+				)
+			})
+			.collect()
+	}
+}
+
+/// Last outcome of the state store write checks, reused for `STORAGE_CHECK_TTL`
+#[derive(Debug, Default)]
+pub struct StorageChecks {
+	last: tokio::sync::Mutex<Option<(tokio::time::Instant, Vec<ReadinessCheck>)>>,
+}
+
+impl StorageChecks {
+	/// The last outcome if it is recent enough, or else the outcome of running `check`
+	///
+	/// Concurrent probes wait for one run of `check` rather than each writing probe files.
+	pub async fn get_or_check<F, Fut>(&self, check: F) -> Vec<ReadinessCheck>
+	where
+		F: FnOnce() -> Fut,
+		Fut: Future<Output = Vec<ReadinessCheck>>,
+	{
+		let mut last = self.last.lock().await;
+		if let Some((at, checks)) = last.as_ref()
+			&& at.elapsed() < STORAGE_CHECK_TTL
+		{
+			return checks.clone();
+		}
+		let checks = check().await;
+		*last = Some((tokio::time::Instant::now(), checks.clone()));
+		checks
+	}
+}
+
+/// Latest initialization failures of upstream targets
+#[derive(Debug, Default)]
+pub struct UpstreamReadiness {
+	failures: Mutex<HashMap<String, (Instant, String)>>,
+}
+
+impl UpstreamReadiness {
+	/// Record that a target answered
+	pub fn record_success(&self, target: &str) {
+		self.failures.lock().remove(target);
+	}
+
+	/// Record that a target failed to initialize or answer
+	pub fn record_failure(&self, target: &str, error: impl Into<String>) {
+		self
+			.failures
+			.lock()
+			.insert(target.to_string(), (Instant::now(), error.into()));
+	}
+
+	/// The error a target last failed with, if it failed within `UPSTREAM_FAILURE_TTL`
+	pub fn failure(&self, target: &str) -> Option<String> {
+		let mut failures = self.failures.lock();
+		let (at, error) = failures.get(target)?;
+		if at.elapsed() > UPSTREAM_FAILURE_TTL {
+			failures.remove(target);
+			return None;
+		}
+		Some(error.clone())
+	}
+
+	/// Check the targets compositions of `registry` call
+	pub fn check(&self, registry: &CompiledRegistry) -> ReadinessCheck {
+		let failed: Vec<String> = composition_targets(registry)
+			.into_iter()
+			.filter_map(|group| {
+				let failures = group
+					.iter()
+					.map(|target| Some(format!("{target} ({})", self.failure(target)?)))
+					.collect::<Option<Vec<_>>>()?;
+				Some(failures.join(", "))
+			})
+			.collect();
+		if failed.is_empty() {
+			ReadinessCheck::ready("upstreams", None)
+		} else {
+			ReadinessCheck::not_ready(
+				"upstreams",
+				format!("composition targets failing: {}", failed.join(", ")),
+			)
+		}
+	}
+}
+
+/// Failover groups of the source tools compositions reference: each tool's target followed
+/// by its fallback targets
+fn composition_targets(registry: &CompiledRegistry) -> BTreeSet<Vec<String>> {
+	registry
+		.tools()
+		.filter_map(|tool| tool.composition_info())
+		.flat_map(|composition| &composition.resolved_references)
+		.filter_map(|name| registry.get_tool(name)?.source_info())
+		.map(|source| source.source.targets().map(str::to_string).collect())
+		.collect()
+}
+
+#[cfg(test)]
+mod tests {
+	use std::sync::atomic::{AtomicU32, Ordering};
+
+	use super::*;
+	use crate::mcp::registry::patterns::{
+		PatternSpec, PipelineSpec, PipelineStep, StepOperation, ToolCall,
+	};
+	use crate::mcp::registry::types::{Registry, ToolDefinition, ToolImplementation};
+
+	fn step(tool: &str) -> PipelineStep {
+		PipelineStep {
+			id: tool.to_string(),
+			operation: StepOperation::Tool(ToolCall {
+				name: tool.to_string(),
+			}),
+			input: None,
+			session_set: None,
+			retry: None,
+			cache: None,
+		}
+	}
+
+	#[test]
+	fn test_failed_composition_targets_are_not_ready() {
+		let step = step("web_search");
+		let registry = Registry::with_tool_definitions(vec![
+			ToolDefinition::source("web_search", "search", "query"),
+			ToolDefinition::source("get_weather", "weather", "fetch"),
+			ToolDefinition::composition(
+				"research",
+				PatternSpec::Pipeline(PipelineSpec { steps: vec![step] }),
+			),
+		]);
+		let compiled = CompiledRegistry::compile(registry).unwrap();
+		assert_eq!(
+			composition_targets(&compiled),
+			BTreeSet::from([vec!["search".to_string()]])
+		);
+
+		let upstreams = UpstreamReadiness::default();
+		assert!(upstreams.check(&compiled).ready);
+		// Only targets compositions call count
+		upstreams.record_failure("weather", "connection refused");
+		assert!(upstreams.check(&compiled).ready);
+		upstreams.record_failure("search", "initialize timed out");
+		let check = upstreams.check(&compiled);
+		assert!(!check.ready);
+		assert_eq!(
+			check.message.as_deref(),
+			Some("composition targets failing: search (initialize timed out)")
+		);
+		upstreams.record_success("search");
+		assert!(upstreams.check(&compiled).ready);
+	}
+
+	#[tokio::test(start_paused = true)]
+	async fn test_storage_checks_are_reused() {
+		let storage = StorageChecks::default();
+		let runs = AtomicU32::new(0);
+		let runs = &runs;
+		let check = move || async move {
+			runs.fetch_add(1, Ordering::SeqCst);
+			vec![ReadinessCheck::ready("journal", None)]
+		};
+
+		storage.get_or_check(check).await;
+		storage.get_or_check(check).await;
+		assert_eq!(runs.load(Ordering::SeqCst), 1);
+		tokio::time::advance(STORAGE_CHECK_TTL).await;
+		storage.get_or_check(check).await;
+		assert_eq!(runs.load(Ordering::SeqCst), 2);
+	}
+
+	#[test]
+	fn test_failover_group_fails_with_its_last_target() {
+		let mut search = ToolDefinition::source("web_search", "search", "query");
+		if let ToolImplementation::Source(source) = &mut search.implementation {
+			source.fallback_targets = vec!["search-replica".to_string()];
+		}
+		let registry = Registry::with_tool_definitions(vec![
+			search,
+			ToolDefinition::composition(
+				"research",
+				PatternSpec::Pipeline(PipelineSpec {
+					steps: vec![step("web_search")],
+				}),
+			),
+		]);
+		let compiled = CompiledRegistry::compile(registry).unwrap();
+
+		let upstreams = UpstreamReadiness::default();
+		upstreams.record_failure("search", "connection refused");
+		assert!(upstreams.check(&compiled).ready);
+		upstreams.record_failure("search-replica", "connection refused");
+		assert_eq!(
+			upstreams.check(&compiled).message.as_deref(),
+			Some(
+				"composition targets failing: search (connection refused), search-replica (connection refused)"
+			)
+		);
+	}
+}
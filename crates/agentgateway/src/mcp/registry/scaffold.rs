@@ -0,0 +1,88 @@
+// Registry scaffolding for newly onboarded MCP servers
+//
+// A registry for a new server usually starts as one virtual tool per backend tool, which
+// authors then rename, document, trim with defaults and hidden fields, or compose. The
+// admin server lists the tools of a configured target and turns them into such skeleton
+// entries, so nobody has to copy tool names out of a tools/list response by hand.
+
+use rmcp::model::Tool;
+use serde_json::Value;
+
+use super::types::{Registry, ToolDefinition};
+
+/// A registry document with a source mapping for each of `target`'s tools
+///
+/// Each entry keeps the backend tool's name and description, with empty `defaults` and
+/// `hideFields` to fill in. Unset optional fields are left out.
+pub fn scaffold_registry(target: &str, tools: &[Tool]) -> Value {
+	let registry = Registry::with_tool_definitions(
+		tools
+			.iter()
+			.map(|tool| {
+				let mut def = ToolDefinition::source(tool.name.as_ref(), target, tool.name.as_ref());
+				def.description = tool.description.as_deref().map(str::to_string);
+				def
+			})
+			.collect(),
+	);
+	let mut doc = serde_json::to_value(&registry).unwrap_or_default();
+	strip_nulls(&mut doc);
+	doc
+}
+
+fn strip_nulls(value: &mut Value) {
+	match value {
+		Value::Object(fields) => {
+			fields.retain(|_, v| !v.is_null());
+			fields.values_mut().for_each(strip_nulls);
+		},
+		Value::Array(items) => items.iter_mut().for_each(strip_nulls),
+		_ => {},
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::sync::Arc;
+
+	use serde_json::json;
+
+	use super::*;
+	use crate::mcp::registry::{CompiledRegistry, ToolImplementation};
+
+	#[test]
+	fn test_scaffold_registry() {
+		let schema = Arc::new(
+			json!({"type": "object", "properties": {"city": {"type": "string"}}})
+				.as_object()
+				.unwrap()
+				.clone(),
+		);
+		let tools = vec![
+			Tool::new("get_forecast", "Forecast for a city", schema.clone()),
+			Tool {
+				description: None,
+				..Tool::new("get_alerts", "", schema)
+			},
+		];
+
+		let doc = scaffold_registry("weather", &tools);
+		assert_eq!(
+			doc["tools"][0],
+			json!({
+				"name": "get_forecast",
+				"description": "Forecast for a city",
+				"source": {"target": "weather", "tool": "get_forecast", "defaults": {}, "hideFields": []}
+			})
+		);
+		assert!(doc["tools"][1].get("description").is_none());
+
+		// The scaffold loads as is
+		let registry: Registry = serde_json::from_value(doc).unwrap();
+		assert!(matches!(
+			&registry.tools[1].implementation,
+			ToolImplementation::Source(s) if s.target == "weather" && s.tool == "get_alerts"
+		));
+		assert_eq!(CompiledRegistry::compile(registry).unwrap().len(), 2);
+	}
+}
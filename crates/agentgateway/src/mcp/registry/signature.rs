@@ -0,0 +1,169 @@
+// Detached signatures over registry documents
+//
+// A registry source can be required to publish a base64 Ed25519 signature of each document
+// next to it, at `<source>.sig` (e.g. `openssl pkeyutl -sign -rawin -inkey key.pem -in
+// registry.json | base64`). Documents whose signature doesn't verify against one of the
+// trusted keys are rejected before they're parsed, so a tampered or unsigned registry is
+// never applied.
+
+use std::path::{Path, PathBuf};
+
+use aws_lc_rs::signature::{ED25519, UnparsedPublicKey};
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+
+use super::error::RegistryError;
+
+/// Length of a raw Ed25519 public key
+const ED25519_KEY_LEN: usize = 32;
+
+/// DER prefix of an Ed25519 SubjectPublicKeyInfo, as found in PEM public keys
+const ED25519_SPKI_PREFIX: [u8; 12] = [
+	0x30, 0x2a, 0x30, 0x05, 0x06, 0x03, 0x2b, 0x65, 0x70, 0x03, 0x21, 0x00,
+];
+
+/// Extension appended to a source to locate its detached signature
+pub const SIGNATURE_EXTENSION: &str = "sig";
+
+/// Location of the detached signature of a registry file
+pub fn signature_path(path: &Path) -> PathBuf {
+	let mut signature = path.as_os_str().to_owned();
+	signature.push(".");
+	signature.push(SIGNATURE_EXTENSION);
+	PathBuf::from(signature)
+}
+
+/// Location of the detached signature of a registry URL, keeping its query string
+pub fn signature_url(url: &http::Uri) -> Result<http::Uri, RegistryError> {
+	let path_and_query = match url.query() {
+		Some(query) => format!("{}.{SIGNATURE_EXTENSION}?{query}", url.path()),
+		None => format!("{}.{SIGNATURE_EXTENSION}", url.path()),
+	};
+	let mut parts = url.clone().into_parts();
+	parts.path_and_query = Some(
+		path_and_query
+			.parse()
+			.map_err(|e| RegistryError::InvalidSource(format!("invalid signature URL: {e}")))?,
+	);
+	http::Uri::from_parts(parts)
+		.map_err(|e| RegistryError::InvalidSource(format!("invalid signature URL: {e}")))
+}
+
+/// Checks registry documents against a set of trusted Ed25519 public keys
+#[derive(Debug, Clone)]
+pub struct SignatureVerifier {
+	keys: Vec<Vec<u8>>,
+}
+
+impl SignatureVerifier {
+	/// Trust the given public keys
+	///
+	/// Each key is either a PEM `PUBLIC KEY` block or base64, of the raw 32-byte key or of
+	/// its DER SubjectPublicKeyInfo.
+	pub fn new<S: AsRef<str>>(keys: &[S]) -> Result<Self, RegistryError> {
+		if keys.is_empty() {
+			return Err(RegistryError::InvalidSignature(
+				"at least one public key is required".to_string(),
+			));
+		}
+		let keys = keys
+			.iter()
+			.map(|k| parse_public_key(k.as_ref()))
+			.collect::<Result<_, _>>()?;
+		Ok(Self { keys })
+	}
+
+	/// Verify `signature` (base64, surrounding whitespace ignored) over `document`
+	pub fn verify(&self, document: &[u8], signature: &str) -> Result<(), RegistryError> {
+		let signature = STANDARD
+			.decode(signature.trim())
+			.map_err(|e| RegistryError::InvalidSignature(format!("signature is not base64: {e}")))?;
+		if self.keys.iter().any(|key| {
+			UnparsedPublicKey::new(&ED25519, key)
+				.verify(document, &signature)
+				.is_ok()
+		}) {
+			Ok(())
+		} else {
+			Err(RegistryError::InvalidSignature(
+				"signature does not match any trusted key".to_string(),
+			))
+		}
+	}
+}
+
+fn parse_public_key(key: &str) -> Result<Vec<u8>, RegistryError> {
+	let encoded: String = key
+		.lines()
+		.map(str::trim)
+		.filter(|line| !line.starts_with("-----"))
+		.collect();
+	let der = STANDARD
+		.decode(encoded)
+		.map_err(|e| RegistryError::InvalidSignature(format!("public key is not base64: {e}")))?;
+	match der.strip_prefix(&ED25519_SPKI_PREFIX[..]) {
+		Some(raw) if raw.len() == ED25519_KEY_LEN => Ok(raw.to_vec()),
+		_ if der.len() == ED25519_KEY_LEN => Ok(der),
+		_ => Err(RegistryError::InvalidSignature(
+			"public key is not an Ed25519 key".to_string(),
+		)),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	// RFC 8032 section 7.1, test 2
+	const PUBLIC_KEY: &str = "3d4017c3e843895a92b70aa74d1b7ebc9c982ccf2ec4968cc0cd55f12af4660c";
+	const SIGNATURE: &str = "92a009a9f0d4cab8720e820b5f642540a2b27b5416503f8fb3762223ebdb69da\
+		085ac1e43e15996e458f3613d0f11d8c387b2eaeb4302aeeb00d291612bb0c00";
+	const MESSAGE: &[u8] = &[0x72];
+
+	fn base64_of_hex(hex: &str) -> String {
+		STANDARD.encode(hex::decode(hex).unwrap())
+	}
+
+	#[test]
+	fn test_verify() {
+		let raw = base64_of_hex(PUBLIC_KEY);
+		let pem = format!(
+			"-----BEGIN PUBLIC KEY-----\n{}\n-----END PUBLIC KEY-----\n",
+			STANDARD.encode([&ED25519_SPKI_PREFIX[..], &hex::decode(PUBLIC_KEY).unwrap()].concat())
+		);
+		let signature = format!("{}\n", base64_of_hex(SIGNATURE));
+
+		for key in [raw, pem] {
+			let verifier = SignatureVerifier::new(&[key]).unwrap();
+			verifier.verify(MESSAGE, &signature).unwrap();
+			assert!(verifier.verify(b"s", &signature).is_err());
+		}
+
+		// Any trusted key may have signed the document
+		let other = base64_of_hex(&"11".repeat(ED25519_KEY_LEN));
+		let verifier = SignatureVerifier::new(&[other, base64_of_hex(PUBLIC_KEY)]).unwrap();
+		verifier.verify(MESSAGE, &signature).unwrap();
+	}
+
+	#[test]
+	fn test_signature_locations() {
+		assert_eq!(
+			signature_path(Path::new("/etc/registry.json")),
+			PathBuf::from("/etc/registry.json.sig")
+		);
+		let url = "https://registry.internal/tools.json?team=search"
+			.parse()
+			.unwrap();
+		assert_eq!(
+			signature_url(&url).unwrap(),
+			"https://registry.internal/tools.json.sig?team=search"
+		);
+	}
+
+	#[test]
+	fn test_rejects_invalid_keys() {
+		assert!(SignatureVerifier::new::<&str>(&[]).is_err());
+		assert!(SignatureVerifier::new(&["not base64!"]).is_err());
+		assert!(SignatureVerifier::new(&[STANDARD.encode([0u8; 16])]).is_err());
+	}
+}
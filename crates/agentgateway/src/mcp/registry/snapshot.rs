@@ -0,0 +1,112 @@
+// Last-known-good registry snapshots
+//
+// After each successful load the store can write the registry document to disk, exactly as
+// its source served it, along with a content hash, so a restart during a registry-source
+// outage still serves the previous tool set instead of none.
+
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use aws_lc_rs::digest;
+use serde::{Deserialize, Serialize};
+
+use super::client::{FetchedRegistry, parse_registry};
+use super::error::RegistryError;
+
+/// A registry persisted after a successful load
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RegistrySnapshot {
+	/// When the registry was loaded (milliseconds since the Unix epoch)
+	pub saved_at_ms: u64,
+	/// Hex SHA-256 of `document`
+	pub sha256: String,
+	/// The registry document as its source served it
+	pub document: String,
+}
+
+impl RegistrySnapshot {
+	/// Snapshot the document of a registry that has just loaded successfully
+	pub fn new(document: String) -> Self {
+		Self {
+			saved_at_ms: SystemTime::now()
+				.duration_since(UNIX_EPOCH)
+				.map(|d| d.as_millis() as u64)
+				.unwrap_or_default(),
+			sha256: content_hash(&document),
+			document,
+		}
+	}
+
+	/// Write the snapshot through a temporary file, so a crash never leaves a partial
+	/// snapshot behind
+	pub fn save(&self, path: &Path) -> std::io::Result<()> {
+		if let Some(parent) = path.parent() {
+			std::fs::create_dir_all(parent)?;
+		}
+		let tmp = path.with_extension("tmp");
+		std::fs::write(&tmp, serde_json::to_vec_pretty(self)?)?;
+		std::fs::rename(&tmp, path)
+	}
+
+	/// Read a snapshot, rejecting it if the document doesn't match its hash
+	pub fn load(path: &Path) -> Result<Self, RegistryError> {
+		let snapshot: Self = serde_json::from_str(&std::fs::read_to_string(path)?)?;
+		if content_hash(&snapshot.document) != snapshot.sha256 {
+			return Err(RegistryError::CorruptSnapshot(format!(
+				"{} does not match its sha256",
+				path.display()
+			)));
+		}
+		Ok(snapshot)
+	}
+
+	/// Parse the snapshotted document
+	///
+	/// The document passed the source's checks when it was loaded, so it's parsed leniently.
+	pub fn into_fetched(self) -> Result<FetchedRegistry, RegistryError> {
+		Ok(FetchedRegistry {
+			registry: parse_registry(&self.document, false)?,
+			document: self.document,
+		})
+	}
+}
+
+fn content_hash(document: &str) -> String {
+	hex::encode(digest::digest(&digest::SHA256, document.as_bytes()))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_snapshot_round_trip() {
+		let dir = tempfile::tempdir().unwrap();
+		let path = dir.path().join("registry.json");
+		// Formatting, key order and fields the registry model doesn't know survive as-is
+		let document = r#"{
+  "tools": [{"source": {"tool": "web_search", "target": "backend"}, "name": "search"}],
+  "schemaVersion": "1.0",
+  "x-owner": "search-team"
+}
+"#;
+
+		let snapshot = RegistrySnapshot::new(document.to_string());
+		snapshot.save(&path).unwrap();
+		let loaded = RegistrySnapshot::load(&path).unwrap();
+		assert_eq!(loaded, snapshot);
+		let fetched = loaded.into_fetched().unwrap();
+		assert_eq!(fetched.document, document);
+		assert_eq!(fetched.registry.tools[0].name, "search");
+
+		// A snapshot edited after it was written is rejected
+		let mut tampered = snapshot.clone();
+		tampered.document = document.replace("search", "fetch");
+		tampered.save(&path).unwrap();
+		assert!(matches!(
+			RegistrySnapshot::load(&path),
+			Err(RegistryError::CorruptSnapshot(_))
+		));
+	}
+}
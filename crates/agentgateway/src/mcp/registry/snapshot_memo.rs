@@ -0,0 +1,149 @@
+// Per-snapshot memo of values derived from tool names
+//
+// Every tool call, including each step of every composition, resolves its tool name against
+// the registry: a lookup, then routing details copied out of the compiled tool, or the name
+// split into target and tool. The outcome only depends on the name and the registry
+// snapshot, so it is worked out once per snapshot. A reload swaps in a new snapshot, which
+// starts an empty memo.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Weak};
+
+use parking_lot::Mutex;
+
+use super::compiled::CompiledRegistry;
+
+/// Most names memoized per snapshot; the memo starts over when it fills up, since unknown
+/// names come from clients
+pub const MAX_MEMOIZED_NAMES: usize = 1024;
+
+/// Values derived from tool names, valid for one registry snapshot at a time
+#[derive(Debug)]
+pub struct SnapshotMemo<V> {
+	inner: Mutex<Memo<V>>,
+}
+
+#[derive(Debug)]
+struct Memo<V> {
+	/// The snapshot the entries were derived from. Holding it keeps the allocation, so no
+	/// later snapshot can share its address.
+	snapshot: Weak<CompiledRegistry>,
+	entries: HashMap<String, V>,
+}
+
+impl<V> Default for SnapshotMemo<V> {
+	fn default() -> Self {
+		Self {
+			inner: Mutex::new(Memo {
+				snapshot: Weak::new(),
+				entries: HashMap::new(),
+			}),
+		}
+	}
+}
+
+impl<V: Clone> SnapshotMemo<V> {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// The value memoized for `name` in `snapshot`, or the one `derive` produces, which is
+	/// memoized if it succeeds
+	pub fn get_or_try_insert<E>(
+		&self,
+		snapshot: &Arc<CompiledRegistry>,
+		name: &str,
+		derive: impl FnOnce() -> Result<V, E>,
+	) -> Result<V, E> {
+		{
+			let mut memo = self.inner.lock();
+			if memo.snapshot.as_ptr() != Arc::as_ptr(snapshot) {
+				memo.snapshot = Arc::downgrade(snapshot);
+				memo.entries.clear();
+			} else if let Some(value) = memo.entries.get(name) {
+				return Ok(value.clone());
+			}
+		}
+		let value = derive()?;
+		let mut memo = self.inner.lock();
+		// A reload may have replaced the snapshot while the value was derived
+		if memo.snapshot.as_ptr() == Arc::as_ptr(snapshot) {
+			if memo.entries.len() >= MAX_MEMOIZED_NAMES {
+				memo.entries.clear();
+			}
+			memo.entries.insert(name.to_string(), value.clone());
+		}
+		Ok(value)
+	}
+
+	/// Number of names memoized for the current snapshot
+	pub fn len(&self) -> usize {
+		self.inner.lock().entries.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.len() == 0
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::cell::Cell;
+
+	use super::*;
+	use crate::mcp::registry::types::Registry;
+
+	fn snapshot() -> Arc<CompiledRegistry> {
+		Arc::new(CompiledRegistry::compile(Registry::default()).unwrap())
+	}
+
+	#[test]
+	fn test_memo_is_per_snapshot() {
+		let memo = SnapshotMemo::new();
+		let derived = Cell::new(0);
+		let derive = |value: &str| {
+			derived.set(derived.get() + 1);
+			Ok::<_, String>(value.to_string())
+		};
+
+		let first = snapshot();
+		assert_eq!(
+			memo.get_or_try_insert(&first, "search", || derive("a")),
+			Ok("a".into())
+		);
+		assert_eq!(
+			memo.get_or_try_insert(&first, "search", || derive("b")),
+			Ok("a".into())
+		);
+		assert_eq!(derived.get(), 1);
+
+		// Failures aren't memoized
+		assert!(
+			memo
+				.get_or_try_insert(&first, "bad", || Err::<String, _>("invalid".to_string()))
+				.is_err()
+		);
+		assert_eq!(memo.len(), 1);
+
+		// A new snapshot starts over
+		let second = snapshot();
+		assert_eq!(
+			memo.get_or_try_insert(&second, "search", || derive("c")),
+			Ok("c".into())
+		);
+		assert_eq!(derived.get(), 2);
+		assert_eq!(memo.len(), 1);
+	}
+
+	#[test]
+	fn test_memo_is_bounded() {
+		let memo = SnapshotMemo::new();
+		let registry = snapshot();
+		for i in 0..=MAX_MEMOIZED_NAMES {
+			memo
+				.get_or_try_insert(&registry, &format!("tool_{i}"), || Ok::<_, ()>(i))
+				.unwrap();
+		}
+		assert_eq!(memo.len(), 1);
+	}
+}
@@ -0,0 +1,412 @@
+// Registry reload history and operational stats
+
+use std::collections::VecDeque;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use parking_lot::Mutex;
+use serde::Serialize;
+
+use super::compiled::{CompiledImplementation, CompiledRegistry, CompiledTool};
+
+/// Default number of reload attempts kept in the history
+pub const DEFAULT_RELOAD_HISTORY: usize = 16;
+
+/// What triggered a registry (re)load
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReloadTrigger {
+	/// Load at startup
+	Initial,
+	/// Periodic refresh of an HTTP source
+	Poll,
+	/// Change to a watched registry file
+	FileWatch,
+	/// Direct update through the store API
+	Api,
+	/// Startup fallback to the last registry persisted to disk
+	Snapshot,
+	/// Promotion of a staged candidate registry through the admin API
+	Promotion,
+}
+
+impl ReloadTrigger {
+	pub fn as_str(&self) -> &'static str {
+		match self {
+			ReloadTrigger::Initial => "initial",
+			ReloadTrigger::Poll => "poll",
+			ReloadTrigger::FileWatch => "file_watch",
+			ReloadTrigger::Api => "api",
+			ReloadTrigger::Snapshot => "snapshot",
+			ReloadTrigger::Promotion => "promotion",
+		}
+	}
+}
+
+/// A single reload attempt
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReloadRecord {
+	/// Completion time (milliseconds since the Unix epoch)
+	pub at_ms: u64,
+	/// Time spent fetching, validating and compiling
+	pub duration_ms: f64,
+	/// Time spent compiling (zero if the fetch failed)
+	pub compile_ms: f64,
+	pub trigger: ReloadTrigger,
+	/// Where the registry was loaded from, if it came from a configured source
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub source: Option<String>,
+	/// Tools in the loaded registry, compositions included
+	pub tools: usize,
+	/// Compositions in the loaded registry
+	pub compositions: usize,
+	/// Validation errors found in the loaded registry
+	pub validation_errors: usize,
+	/// Validation warnings found in the loaded registry
+	pub validation_warnings: usize,
+	/// Failure reason; the previous registry stays active when a reload fails
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub error: Option<String>,
+	/// Whether the loaded registry was staged as a candidate instead of becoming active
+	#[serde(skip_serializing_if = "std::ops::Not::not")]
+	pub staged: bool,
+}
+
+impl ReloadRecord {
+	/// Start a record for a reload that took `duration`, completing now
+	pub fn new(trigger: ReloadTrigger, source: Option<String>, duration: Duration) -> Self {
+		let at_ms = SystemTime::now()
+			.duration_since(UNIX_EPOCH)
+			.map(|d| d.as_millis() as u64)
+			.unwrap_or_default();
+		Self {
+			at_ms,
+			duration_ms: duration.as_secs_f64() * 1000.0,
+			compile_ms: 0.0,
+			trigger,
+			source,
+			tools: 0,
+			compositions: 0,
+			validation_errors: 0,
+			validation_warnings: 0,
+			error: None,
+			staged: false,
+		}
+	}
+
+	pub fn is_success(&self) -> bool {
+		self.error.is_none()
+	}
+}
+
+/// Reload history of a registry store, kept across reloads
+#[derive(Debug)]
+pub struct RegistryStats {
+	capacity: usize,
+	inner: Mutex<StatsInner>,
+}
+
+#[derive(Debug, Default)]
+struct StatsInner {
+	/// Newest first
+	history: VecDeque<ReloadRecord>,
+	last_success: Option<ReloadRecord>,
+	successes: u64,
+	failures: u64,
+	degraded: bool,
+	snapshot_saved_at_ms: Option<u64>,
+}
+
+/// Point-in-time view of a registry store, for the admin dump
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RegistryStatsSnapshot {
+	/// Tools in the active registry, compositions included
+	pub tools: usize,
+	/// Compositions in the active registry
+	pub compositions: usize,
+	/// Successful reloads since startup
+	pub reloads: u64,
+	/// Failed reloads since startup
+	pub reload_failures: u64,
+	/// The reload that produced the active registry
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub last_success: Option<ReloadRecord>,
+	/// Recent reload attempts, newest first
+	pub history: Vec<ReloadRecord>,
+	/// Whether a startup fallback registry is being served because the source couldn't be
+	/// loaded
+	pub degraded: bool,
+	/// Save time of the persisted snapshot being served while degraded (milliseconds since
+	/// the Unix epoch)
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub snapshot_saved_at_ms: Option<u64>,
+}
+
+/// Summary of a registry store for the admin config dump
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RegistryDump {
+	/// Where the registry is loaded from, if it has a configured source
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub source: Option<String>,
+	/// Validation outcome of the active registry
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub validation: Option<ValidationSummary>,
+	/// Tools in the active registry, sorted by name
+	pub tools: Vec<ToolSummary>,
+	pub stats: RegistryStatsSnapshot,
+}
+
+/// Validation outcome of a loaded registry
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ValidationSummary {
+	pub valid: bool,
+	pub errors: usize,
+	pub warnings: usize,
+}
+
+/// A tool in the active registry
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ToolSummary {
+	pub name: String,
+	/// `source` for virtual tools, otherwise the composition pattern (e.g. `pipeline`)
+	#[serde(rename = "type")]
+	pub kind: &'static str,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub version: Option<String>,
+	/// Backend tool (`target/tool`) of a virtual tool, or the tools a composition calls
+	pub sources: Vec<String>,
+}
+
+impl From<&CompiledTool> for ToolSummary {
+	fn from(tool: &CompiledTool) -> Self {
+		let (kind, sources) = match &tool.compiled {
+			CompiledImplementation::Source(source) => (
+				"source",
+				vec![format!("{}/{}", source.source.target, source.source.tool)],
+			),
+			CompiledImplementation::Composition(composition) => (
+				composition.spec.pattern_name(),
+				composition.resolved_references.clone(),
+			),
+		};
+		Self {
+			name: tool.def.name.clone(),
+			kind,
+			version: tool.def.version.clone(),
+			sources,
+		}
+	}
+}
+
+impl RegistryDump {
+	/// Summarize the active registry and its reload history
+	pub fn new(
+		stats: &RegistryStats,
+		registry: Option<&CompiledRegistry>,
+		source: Option<String>,
+	) -> Self {
+		let mut tools: Vec<ToolSummary> = registry
+			.into_iter()
+			.flat_map(CompiledRegistry::tools)
+			.map(|t| ToolSummary::from(t.as_ref()))
+			.collect();
+		tools.sort_by(|a, b| a.name.cmp(&b.name));
+		let stats = stats.snapshot(registry);
+		let validation = registry
+			.and(stats.last_success.as_ref())
+			.map(|r| ValidationSummary {
+				valid: r.validation_errors == 0,
+				errors: r.validation_errors,
+				warnings: r.validation_warnings,
+			});
+		Self {
+			source,
+			validation,
+			tools,
+			stats,
+		}
+	}
+}
+
+impl Default for RegistryStats {
+	fn default() -> Self {
+		Self::new(DEFAULT_RELOAD_HISTORY)
+	}
+}
+
+impl RegistryStats {
+	/// Create stats keeping the last `capacity` reload attempts (0 keeps only the counters)
+	pub fn new(capacity: usize) -> Self {
+		Self {
+			capacity,
+			inner: Mutex::new(StatsInner::default()),
+		}
+	}
+
+	/// Record a reload attempt
+	pub fn record(&self, record: ReloadRecord) {
+		let mut inner = self.inner.lock();
+		if record.is_success() {
+			inner.successes += 1;
+			if !record.staged {
+				inner.last_success = Some(record.clone());
+			}
+		} else {
+			inner.failures += 1;
+		}
+		if self.capacity == 0 {
+			return;
+		}
+		if inner.history.len() == self.capacity {
+			inner.history.pop_back();
+		}
+		inner.history.push_front(record);
+	}
+
+	/// Successful and failed reloads since startup
+	pub fn counts(&self) -> (u64, u64) {
+		let inner = self.inner.lock();
+		(inner.successes, inner.failures)
+	}
+
+	/// The most recent reload attempt
+	pub fn last(&self) -> Option<ReloadRecord> {
+		self.inner.lock().history.front().cloned()
+	}
+
+	/// The reload that produced the active registry
+	pub fn last_success(&self) -> Option<ReloadRecord> {
+		self.inner.lock().last_success.clone()
+	}
+
+	/// Mark whether a startup fallback registry is being served
+	pub fn set_degraded(&self, degraded: bool) {
+		let mut inner = self.inner.lock();
+		inner.degraded = degraded;
+		if !degraded {
+			inner.snapshot_saved_at_ms = None;
+		}
+	}
+
+	/// Record the save time of the persisted snapshot a degraded store is serving
+	pub fn set_snapshot_saved_at(&self, at_ms: u64) {
+		self.inner.lock().snapshot_saved_at_ms = Some(at_ms);
+	}
+
+	pub fn is_degraded(&self) -> bool {
+		self.inner.lock().degraded
+	}
+
+	/// Snapshot the stats, with tool counts taken from the active registry
+	pub fn snapshot(&self, registry: Option<&CompiledRegistry>) -> RegistryStatsSnapshot {
+		let inner = self.inner.lock();
+		RegistryStatsSnapshot {
+			tools: registry.map(CompiledRegistry::len).unwrap_or_default(),
+			compositions: registry
+				.map(CompiledRegistry::composition_count)
+				.unwrap_or_default(),
+			reloads: inner.successes,
+			reload_failures: inner.failures,
+			last_success: inner.last_success.clone(),
+			history: inner.history.iter().cloned().collect(),
+			degraded: inner.degraded,
+			snapshot_saved_at_ms: inner.snapshot_saved_at_ms,
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn record(trigger: ReloadTrigger, error: Option<&str>) -> ReloadRecord {
+		let mut record = ReloadRecord::new(trigger, None, Duration::from_millis(5));
+		record.error = error.map(str::to_string);
+		record
+	}
+
+	#[test]
+	fn test_history_is_bounded_and_newest_first() {
+		let stats = RegistryStats::new(2);
+		stats.record(record(ReloadTrigger::Initial, None));
+		stats.record(record(ReloadTrigger::Poll, Some("fetch failed")));
+		stats.record(record(ReloadTrigger::Poll, Some("fetch failed again")));
+
+		assert_eq!(stats.counts(), (1, 2));
+		// The successful load fell out of the history but is still the active one
+		assert_eq!(
+			stats.last_success().map(|r| r.trigger),
+			Some(ReloadTrigger::Initial)
+		);
+
+		let snapshot = stats.snapshot(None);
+		let errors: Vec<_> = snapshot
+			.history
+			.iter()
+			.map(|r| r.error.as_deref())
+			.collect();
+		assert_eq!(
+			errors,
+			vec![Some("fetch failed again"), Some("fetch failed")]
+		);
+		assert_eq!(snapshot.tools, 0);
+	}
+
+	#[test]
+	fn test_dump_summarizes_tools() {
+		use crate::mcp::registry::types::{Registry, ToolDefinition};
+
+		let mut forecast = ToolDefinition::source("forecast", "weather", "get_forecast");
+		forecast.version = Some("1.2.0".to_string());
+		let registry = Registry::with_tool_definitions(vec![
+			forecast,
+			ToolDefinition::source("alerts", "weather", "get_alerts"),
+		]);
+		let registry = CompiledRegistry::compile(registry).unwrap();
+
+		let stats = RegistryStats::default();
+		let mut loaded = record(ReloadTrigger::Initial, None);
+		loaded.validation_warnings = 1;
+		stats.record(loaded);
+
+		let dump = RegistryDump::new(&stats, Some(&registry), Some("file://registry.json".into()));
+		let names: Vec<_> = dump.tools.iter().map(|t| t.name.as_str()).collect();
+		assert_eq!(names, vec!["alerts", "forecast"]);
+		assert_eq!(
+			dump.tools[1],
+			ToolSummary {
+				name: "forecast".to_string(),
+				kind: "source",
+				version: Some("1.2.0".to_string()),
+				sources: vec!["weather/get_forecast".to_string()],
+			}
+		);
+		assert_eq!(
+			dump.validation,
+			Some(ValidationSummary {
+				valid: true,
+				errors: 0,
+				warnings: 1,
+			})
+		);
+		assert_eq!(dump.stats.tools, 2);
+
+		// Nothing to report validation for until a registry is active
+		let empty = RegistryDump::new(&stats, None, None);
+		assert!(empty.tools.is_empty());
+		assert!(empty.validation.is_none());
+	}
+
+	#[test]
+	fn test_zero_capacity_keeps_counters() {
+		let stats = RegistryStats::new(0);
+		stats.record(record(ReloadTrigger::Api, None));
+		assert_eq!(stats.counts(), (1, 0));
+		assert!(stats.last().is_none());
+		assert!(stats.last_success().is_some());
+	}
+}
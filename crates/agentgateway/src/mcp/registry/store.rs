@@ -1,17 +1,84 @@
 // Registry store for hot-reloadable registry management
 
-use std::path::{PathBuf, absolute};
+use std::path::{Path, PathBuf, absolute};
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 use arc_swap::ArcSwap;
 use notify::{EventKind, RecursiveMode};
-use tracing::{error, info, warn};
+use prometheus_client::metrics::gauge::Gauge;
+use rmcp::model::CallToolResult;
+use tracing::{debug, error, info, warn};
 
-use super::client::RegistryClient;
+use super::activation::{Candidate, ManualPromotion, PromotionError, SmokeTest, StagedRegistry};
+use super::archive::PayloadArchiver;
+use super::arguments::ArgumentCheckPolicy;
+use super::authorizer::ToolCallAuthorizer;
+use super::changelog::{RegistryChangelog, RegistryDiff};
+use super::client::{FetchedRegistry, RegistryClient};
 use super::compiled::CompiledRegistry;
+use super::debounce::CallDebouncer;
+use super::drift::{SchemaDriftPolicy, SchemaDriftTracker};
+use super::env_policy::EnvVarPolicy;
 use super::error::RegistryError;
+use super::executor::{
+	CompositionExecutor, DeadLetterQueue, ExecutionCapture, ExecutionCaptures, ExecutionJournal,
+	ExecutionLog, ExecutionPriority, MemoryBudget, RateLimiterRegistry, ReplayOutcome, RerunError,
+	ResultCache, SharedRateLimiterRegistry, ToolInvoker, VerbosityOverrides, WorkerPermit,
+	WorkerPool,
+};
+use super::idempotency::IdempotencyStore;
+use super::json_limits::JsonLimits;
+use super::kill_switch::KillSwitch;
+use super::load_shedding::{LoadShedder, LoadSheddingConfig};
+use super::readiness::{ReadinessCheck, RegistryReadiness, StorageChecks, UpstreamReadiness};
+use super::snapshot::RegistrySnapshot;
+use super::stats::{
+	RegistryDump, RegistryStats, RegistryStatsSnapshot, ReloadRecord, ReloadTrigger,
+};
 use super::types::Registry;
+use super::usage::UsageAnalytics;
+use super::validation::validate_registry;
+use crate::events::{EventBus, EventKind as GatewayEventKind};
+use crate::mcp::upstream::SharedFailure;
+use crate::stateful::{EvictionStats, StateStore, TieredStore};
+use crate::telemetry::metrics::{CompositionLane, CompositionLaneLabels, Metrics};
+
+/// First delay between retries of a source that failed to load at startup; doubles up to
+/// the refresh interval
+const DEGRADED_RETRY_START: Duration = Duration::from_secs(1);
+
+/// Most refresh intervals that consecutive failed polls back off to
+const MAX_POLL_BACKOFF: u32 = 8;
+
+/// Factor the polling interval stretches by after each poll that finds the source unchanged
+const POLL_STRETCH: f64 = 1.5;
+
+/// Fraction of a poll delay it's randomly shortened or lengthened by, so gateways sharing a
+/// source don't poll it in lockstep
+const POLL_JITTER: f64 = 0.1;
+
+/// What the store serves when its source can't be loaded at startup
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LoadFailurePolicy {
+	/// Return the error, so the gateway refuses to start
+	#[default]
+	FailFast,
+	/// Serve the registry persisted after the last successful load and keep retrying the
+	/// source. Fails like `FailFast` if no snapshot can be read.
+	LastKnownGood,
+	/// Serve an empty registry and keep retrying the source
+	Empty,
+}
+
+/// Decrements a queue depth gauge on drop, so abandoned waits are accounted for
+struct QueuedGuard(Gauge);
+
+impl Drop for QueuedGuard {
+	fn drop(&mut self) {
+		self.0.dec();
+	}
+}
 
 /// Store for managing the compiled registry with hot-reload support
 #[derive(Debug)]
@@ -21,6 +88,67 @@ pub struct RegistryStore {
 	current: Arc<ArcSwap<Option<Arc<CompiledRegistry>>>>,
 	/// Client for fetching updates (optional - None means static registry)
 	client: Option<RegistryClient>,
+	/// Recent composition executions, kept across registry reloads
+	execution_log: Arc<ExecutionLog>,
+	/// Runtime per-tool composition debug verbosity
+	verbosity_overrides: Arc<VerbosityOverrides>,
+	/// Workers shared by every composition execution
+	worker_pool: Arc<WorkerPool>,
+	/// Payload bytes held by in-flight composition executions
+	memory_budget: Arc<MemoryBudget>,
+	/// Reload history, kept across registry reloads
+	stats: Arc<RegistryStats>,
+	/// What recent reloads changed, kept across registry reloads
+	changelog: Arc<RegistryChangelog>,
+	/// Bus that reload attempts are published to
+	events: EventBus,
+	/// Backend schema drift seen by virtual tools, kept across registry reloads
+	schema_drift: Arc<SchemaDriftTracker>,
+	/// Results of cache patterns, kept across registry reloads
+	result_cache: Arc<ResultCache>,
+	/// Failures captured by dead letter patterns, kept across registry reloads
+	dead_letters: Arc<DeadLetterQueue>,
+	/// Executions captured for time-travel debugging, kept across registry reloads
+	captures: Arc<ExecutionCaptures>,
+	/// Tool calls in flight or within their debounce window, kept across registry reloads
+	debouncer: Arc<CallDebouncer<CallToolResult, SharedFailure>>,
+	/// Idempotency keys of composition calls, kept across registry reloads
+	idempotency: Arc<IdempotencyStore<CallToolResult>>,
+	/// Rate limiter buckets of throttle patterns, kept across registry reloads
+	rate_limiters: SharedRateLimiterRegistry,
+	/// Operator switches disabling tools or the whole registry, kept across registry reloads
+	kill_switch: Arc<KillSwitch>,
+	/// Sink for full composition payloads (optional)
+	archiver: Option<Arc<PayloadArchiver>>,
+	/// Per-tool call counts for export to analytics systems (optional)
+	usage: Option<Arc<UsageAnalytics>>,
+	/// Journal of in-flight pipeline executions, shared with a standby (optional)
+	execution_journal: Option<Arc<ExecutionJournal>>,
+	/// What to serve if the initial load fails
+	load_failure_policy: LoadFailurePolicy,
+	/// File the last successfully loaded registry is persisted to (optional)
+	snapshot_path: Option<PathBuf>,
+	/// External policy check run before every tool call (optional)
+	authorizer: Option<Arc<ToolCallAuthorizer>>,
+	/// Bounds on backend responses parsed as JSON
+	json_limits: JsonLimits,
+	/// What compositions do with tool arguments that don't match the tool's input schema
+	argument_checks: ArgumentCheckPolicy,
+	/// What happens to callers' arguments that don't match the tool's input schema
+	input_checks: ArgumentCheckPolicy,
+	/// Whether callers must confirm calls of tools annotated destructive
+	confirm_destructive: bool,
+	/// Environment variables registry entries may reference
+	env_policy: EnvVarPolicy,
+	/// Candidate registry awaiting promotion, if reloads are staged instead of activated
+	/// (optional)
+	manual_promotion: Option<Arc<ManualPromotion>>,
+	/// Recent initialization failures of upstream targets, for readiness
+	upstreams: Arc<UpstreamReadiness>,
+	/// Last outcome of the journal and archive write checks
+	storage_checks: Arc<StorageChecks>,
+	/// Recent call latencies of upstream targets, and the calls they shed
+	load_shedder: Arc<LoadShedder>,
 }
 
 impl Clone for RegistryStore {
@@ -28,6 +156,35 @@ impl Clone for RegistryStore {
 		Self {
 			current: Arc::clone(&self.current),
 			client: self.client.clone(),
+			execution_log: Arc::clone(&self.execution_log),
+			verbosity_overrides: Arc::clone(&self.verbosity_overrides),
+			worker_pool: Arc::clone(&self.worker_pool),
+			memory_budget: Arc::clone(&self.memory_budget),
+			stats: Arc::clone(&self.stats),
+			changelog: Arc::clone(&self.changelog),
+			events: self.events.clone(),
+			schema_drift: Arc::clone(&self.schema_drift),
+			result_cache: Arc::clone(&self.result_cache),
+			dead_letters: Arc::clone(&self.dead_letters),
+			captures: Arc::clone(&self.captures),
+			debouncer: Arc::clone(&self.debouncer),
+			idempotency: Arc::clone(&self.idempotency),
+			kill_switch: Arc::clone(&self.kill_switch),
+			archiver: self.archiver.clone(),
+			usage: self.usage.clone(),
+			execution_journal: self.execution_journal.clone(),
+			load_failure_policy: self.load_failure_policy,
+			snapshot_path: self.snapshot_path.clone(),
+			authorizer: self.authorizer.clone(),
+			json_limits: self.json_limits,
+			argument_checks: self.argument_checks,
+			input_checks: self.input_checks,
+			confirm_destructive: self.confirm_destructive,
+			env_policy: self.env_policy.clone(),
+			manual_promotion: self.manual_promotion.clone(),
+			upstreams: Arc::clone(&self.upstreams),
+			storage_checks: Arc::clone(&self.storage_checks),
+			load_shedder: Arc::clone(&self.load_shedder),
 		}
 	}
 }
@@ -44,6 +201,36 @@ impl RegistryStore {
 		Self {
 			current: Arc::new(ArcSwap::new(Arc::new(None))),
 			client: None,
+			execution_log: Arc::new(ExecutionLog::default()),
+			verbosity_overrides: Arc::new(VerbosityOverrides::new()),
+			worker_pool: Arc::new(WorkerPool::default()),
+			memory_budget: Arc::new(MemoryBudget::default()),
+			stats: Arc::new(RegistryStats::default()),
+			changelog: Arc::new(RegistryChangelog::default()),
+			events: EventBus::default(),
+			schema_drift: Arc::new(SchemaDriftTracker::default()),
+			result_cache: Arc::new(ResultCache::new()),
+			dead_letters: Arc::new(DeadLetterQueue::default()),
+			captures: Arc::new(ExecutionCaptures::default()),
+			debouncer: Arc::new(CallDebouncer::new()),
+			idempotency: Arc::new(IdempotencyStore::new()),
+			rate_limiters: SharedRateLimiterRegistry::default(),
+			kill_switch: Arc::new(KillSwitch::new()),
+			archiver: None,
+			usage: None,
+			execution_journal: None,
+			load_failure_policy: LoadFailurePolicy::default(),
+			snapshot_path: None,
+			authorizer: None,
+			json_limits: JsonLimits::default(),
+			argument_checks: ArgumentCheckPolicy::default(),
+			input_checks: ArgumentCheckPolicy::default(),
+			confirm_destructive: false,
+			env_policy: EnvVarPolicy::default(),
+			manual_promotion: None,
+			upstreams: Arc::new(UpstreamReadiness::default()),
+			storage_checks: Arc::new(StorageChecks::default()),
+			load_shedder: Arc::new(LoadShedder::default()),
 		}
 	}
 
@@ -53,6 +240,466 @@ impl RegistryStore {
 		self
 	}
 
+	/// Builder: set how many recent executions to keep per composition (0 disables)
+	pub fn with_execution_log_capacity(mut self, capacity: usize) -> Self {
+		self.execution_log = Arc::new(ExecutionLog::new(capacity));
+		self
+	}
+
+	/// Builder: set how many registry changes to keep (0 only logs them)
+	pub fn with_changelog_capacity(mut self, capacity: usize) -> Self {
+		self.changelog = Arc::new(RegistryChangelog::new(capacity));
+		self
+	}
+
+	/// Builder: honor the verbosity header of callers with these JWT subjects
+	pub fn with_verbosity_callers(mut self, subjects: Vec<String>) -> Self {
+		self.verbosity_overrides = Arc::new(VerbosityOverrides::new().with_trusted_callers(subjects));
+		self
+	}
+
+	/// Builder: size the composition worker pool
+	pub fn with_worker_pool(mut self, max_workers: usize, max_batch_workers: usize) -> Self {
+		self.worker_pool = Arc::new(WorkerPool::new(max_workers, max_batch_workers));
+		self
+	}
+
+	/// Builder: cap the payload bytes held by in-flight composition executions
+	pub fn with_memory_limit(mut self, bytes: usize) -> Self {
+		self.memory_budget = Arc::new(MemoryBudget::new(Some(bytes)));
+		self
+	}
+
+	/// Builder: choose what happens to virtual tools whose backend schema drifts
+	pub fn with_schema_drift_policy(mut self, policy: SchemaDriftPolicy) -> Self {
+		self.schema_drift = Arc::new(SchemaDriftTracker::new(policy));
+		self
+	}
+
+	/// Builder: publish reload attempts to `events`, normally the gateway's bus
+	pub fn with_events(mut self, events: EventBus) -> Self {
+		self.events = events;
+		self
+	}
+
+	/// Builder: archive the payloads of composition executions
+	pub fn with_archiver(mut self, archiver: Arc<PayloadArchiver>) -> Self {
+		self.archiver = Some(archiver);
+		self
+	}
+
+	/// Builder: count tool calls for export to analytics systems
+	pub fn with_usage_analytics(mut self, usage: Arc<UsageAnalytics>) -> Self {
+		self.usage = Some(usage);
+		self
+	}
+
+	/// Builder: journal in-flight pipeline executions so a standby can take them over
+	pub fn with_execution_journal(mut self, journal: Arc<ExecutionJournal>) -> Self {
+		self.execution_journal = Some(journal);
+		self
+	}
+
+	/// Builder: choose what to serve if the initial load fails
+	pub fn with_load_failure_policy(mut self, policy: LoadFailurePolicy) -> Self {
+		self.load_failure_policy = policy;
+		self
+	}
+
+	/// Builder: persist every successfully loaded registry to `path`, for
+	/// [`LoadFailurePolicy::LastKnownGood`]
+	pub fn with_snapshot_path(mut self, path: impl Into<PathBuf>) -> Self {
+		self.snapshot_path = Some(path.into());
+		self
+	}
+
+	/// Builder: ask an external authorizer before every tool call
+	pub fn with_authorizer(mut self, authorizer: Arc<ToolCallAuthorizer>) -> Self {
+		self.authorizer = Some(authorizer);
+		self
+	}
+
+	/// Builder: bound the size and nesting depth of backend responses parsed as JSON
+	pub fn with_json_limits(mut self, limits: JsonLimits) -> Self {
+		self.json_limits = limits;
+		self
+	}
+
+	/// Builder: choose what compositions do with tool arguments that don't match the tool's
+	/// input schema
+	pub fn with_argument_checks(mut self, policy: ArgumentCheckPolicy) -> Self {
+		self.argument_checks = policy;
+		self
+	}
+
+	/// Builder: choose what happens to callers' arguments that don't match the input schema
+	/// listed for the tool they call
+	pub fn with_input_checks(mut self, policy: ArgumentCheckPolicy) -> Self {
+		self.input_checks = policy;
+		self
+	}
+
+	/// Builder: hold at most `max_entries` results of cache patterns
+	pub fn with_max_cache_entries(mut self, max_entries: usize) -> Self {
+		self.result_cache = Arc::new(ResultCache::with_max_entries(max_entries));
+		self
+	}
+
+	/// Builder: hold at most `max_keys` idempotency keys of composition calls
+	pub fn with_max_idempotency_keys(mut self, max_keys: usize) -> Self {
+		let mut idempotency = IdempotencyStore::with_max_keys(max_keys);
+		if let Some(shared) = self.idempotency.shared() {
+			idempotency = idempotency.with_shared(shared.clone());
+		}
+		self.idempotency = Arc::new(idempotency);
+		self
+	}
+
+	/// Builder: share idempotency keys and throttle buckets with other gateways through `state`,
+	/// each with the consistency `state` configures for its pattern
+	pub fn with_shared_state(mut self, state: TieredStore) -> Self {
+		let idempotency = IdempotencyStore::with_max_keys(self.idempotency.max_keys());
+		self.idempotency = Arc::new(idempotency.with_shared(state.for_pattern("idempotency")));
+		let rate_limiters = RateLimiterRegistry::new().with_shared(state.for_pattern("throttle"));
+		self.rate_limiters = Arc::new(tokio::sync::Mutex::new(rate_limiters));
+		self
+	}
+
+	/// Builder: require callers to confirm calls of tools the registry annotates destructive
+	pub fn with_destructive_confirmation(mut self, confirm: bool) -> Self {
+		self.confirm_destructive = confirm;
+		self
+	}
+
+	/// Builder: restrict the environment variables registry entries may reference
+	pub fn with_env_policy(mut self, policy: EnvVarPolicy) -> Self {
+		self.env_policy = policy;
+		self
+	}
+
+	/// Builder: stage reloaded registries as candidates that only go live once promoted,
+	/// after passing `smoke_tests`
+	pub fn with_manual_promotion(mut self, smoke_tests: Vec<SmokeTest>) -> Self {
+		self.manual_promotion = Some(Arc::new(ManualPromotion::new(smoke_tests)));
+		self
+	}
+
+	/// Builder: shed calls to upstreams whose latency is over the threshold for the calls'
+	/// priority
+	pub fn with_load_shedding(mut self, config: LoadSheddingConfig) -> Self {
+		self.load_shedder = Arc::new(LoadShedder::new(config));
+		self
+	}
+
+	/// Check if a startup fallback registry is being served
+	pub fn is_degraded(&self) -> bool {
+		self.stats.is_degraded()
+	}
+
+	/// Get the recent-execution log
+	pub fn execution_log(&self) -> &Arc<ExecutionLog> {
+		&self.execution_log
+	}
+
+	/// Get the runtime composition verbosity overrides
+	pub fn verbosity_overrides(&self) -> &Arc<VerbosityOverrides> {
+		&self.verbosity_overrides
+	}
+
+	/// Get the composition worker pool
+	pub fn worker_pool(&self) -> &Arc<WorkerPool> {
+		&self.worker_pool
+	}
+
+	/// Wait for a composition worker in `priority`'s lane, counted in the lane's queue depth
+	/// while waiting
+	///
+	/// Agent calls and admin replays and re-runs all take their worker here, so the queue depth
+	/// of each lane covers everything waiting for it.
+	pub async fn acquire_worker(
+		&self,
+		priority: ExecutionPriority,
+		metrics: Option<&Metrics>,
+	) -> WorkerPermit {
+		let _queued = metrics.map(|metrics| {
+			let lane = match priority {
+				ExecutionPriority::Interactive => CompositionLane::Interactive,
+				ExecutionPriority::Batch => CompositionLane::Batch,
+			};
+			let depth = metrics
+				.composition_queue_depth
+				.get_or_create(&CompositionLaneLabels { lane })
+				.clone();
+			depth.inc();
+			QueuedGuard(depth)
+		});
+		self.worker_pool.acquire(priority).await
+	}
+
+	/// Get the in-flight composition memory budget
+	pub fn memory_budget(&self) -> &Arc<MemoryBudget> {
+		&self.memory_budget
+	}
+
+	/// Get the reload history
+	pub fn stats(&self) -> &Arc<RegistryStats> {
+		&self.stats
+	}
+
+	/// Get the recent initialization failures of upstream targets
+	pub fn upstream_readiness(&self) -> &Arc<UpstreamReadiness> {
+		&self.upstreams
+	}
+
+	/// Get the upstream latency tracker that sheds calls
+	pub fn load_shedder(&self) -> &Arc<LoadShedder> {
+		&self.load_shedder
+	}
+
+	/// Check that a registry is loaded and its state stores can be written
+	///
+	/// The state store write checks are reused for `STORAGE_CHECK_TTL`.
+	pub async fn readiness(&self) -> RegistryReadiness {
+		RegistryReadiness::new(self.readiness_checks().await)
+	}
+
+	/// The readiness checks, and whether the upstreams compositions call are failing
+	///
+	/// Failing upstreams don't make the gateway unready, so they are only reported here.
+	pub async fn readiness_with_upstreams(&self) -> RegistryReadiness {
+		let mut checks = self.readiness_checks().await;
+		if let Some(registry) = self.get_arc() {
+			checks.push(self.upstreams.check(&registry));
+		}
+		RegistryReadiness::new(checks)
+	}
+
+	async fn readiness_checks(&self) -> Vec<ReadinessCheck> {
+		let mut checks = vec![match self.get_arc() {
+			None => {
+				let message = match self.stats.last().and_then(|r| r.error) {
+					Some(error) => format!("registry not loaded: {error}"),
+					None => "registry not loaded".to_string(),
+				};
+				ReadinessCheck::not_ready("registry", message)
+			},
+			// A fallback still serves every tool it knows
+			Some(_) if self.is_degraded() => ReadinessCheck::ready(
+				"registry",
+				Some("serving a fallback registry; the configured source failed to load".to_string()),
+			),
+			Some(_) => ReadinessCheck::ready("registry", None),
+		}];
+		let storage = self
+			.storage_checks
+			.get_or_check(|| async {
+				let mut checks = Vec::new();
+				if let Some(journal) = &self.execution_journal {
+					checks.push(match journal.check_writable().await {
+						Ok(()) => ReadinessCheck::ready("journal", None),
+						Err(e) => {
+							ReadinessCheck::not_ready("journal", format!("execution journal not writable: {e}"))
+						},
+					});
+				}
+				if let Some(archiver) = &self.archiver {
+					checks.push(match archiver.check().await {
+						Ok(()) => ReadinessCheck::ready("archive", None),
+						Err(e) => ReadinessCheck::not_ready("archive", e.to_string()),
+					});
+				}
+				checks
+			})
+			.await;
+		checks.extend(storage);
+		checks
+	}
+
+	/// Get what recent reloads changed
+	pub fn changelog(&self) -> &Arc<RegistryChangelog> {
+		&self.changelog
+	}
+
+	/// Get the event bus reloads and compositions publish to
+	pub fn events(&self) -> &EventBus {
+		&self.events
+	}
+
+	/// Get the backend schema drift tracker
+	pub fn schema_drift(&self) -> &Arc<SchemaDriftTracker> {
+		&self.schema_drift
+	}
+
+	/// Get the cache behind cache patterns
+	pub fn result_cache(&self) -> &Arc<ResultCache> {
+		&self.result_cache
+	}
+
+	/// Get the queue of dead-lettered composition failures
+	pub fn dead_letters(&self) -> &Arc<DeadLetterQueue> {
+		&self.dead_letters
+	}
+
+	/// Get the executions captured for time-travel debugging
+	pub fn captures(&self) -> &Arc<ExecutionCaptures> {
+		&self.captures
+	}
+
+	/// Get the tool calls in flight or within their debounce window
+	pub fn debouncer(&self) -> &Arc<CallDebouncer<CallToolResult, SharedFailure>> {
+		&self.debouncer
+	}
+
+	/// Get the idempotency keys of composition calls
+	pub fn idempotency(&self) -> &Arc<IdempotencyStore<CallToolResult>> {
+		&self.idempotency
+	}
+
+	/// Get the rate limiter buckets of throttle patterns
+	pub fn rate_limiters(&self) -> &SharedRateLimiterRegistry {
+		&self.rate_limiters
+	}
+
+	/// Size and eviction counters of the bounded in-memory stores, by store
+	pub fn eviction_stats(&self) -> Vec<(&'static str, EvictionStats)> {
+		vec![
+			("result_cache", self.result_cache.store().eviction_stats()),
+			("idempotency", self.idempotency.eviction_stats()),
+		]
+	}
+
+	/// Get the operator switches disabling tools or the whole registry
+	pub fn kill_switch(&self) -> &Arc<KillSwitch> {
+		&self.kill_switch
+	}
+
+	/// Get the archiver of composition payloads, if archival is configured
+	pub fn archiver(&self) -> Option<&Arc<PayloadArchiver>> {
+		self.archiver.as_ref()
+	}
+
+	/// Get the tool usage analytics, if they are configured
+	pub fn usage(&self) -> Option<&Arc<UsageAnalytics>> {
+		self.usage.as_ref()
+	}
+
+	/// Get the journal of in-flight pipeline executions, if one is configured
+	pub fn execution_journal(&self) -> Option<&Arc<ExecutionJournal>> {
+		self.execution_journal.as_ref()
+	}
+
+	/// Get the external tool call authorizer, if one is configured
+	pub fn authorizer(&self) -> Option<&Arc<ToolCallAuthorizer>> {
+		self.authorizer.as_ref()
+	}
+
+	/// Get the bounds on backend responses parsed as JSON
+	pub fn json_limits(&self) -> JsonLimits {
+		self.json_limits
+	}
+
+	/// Get the candidate registry of a store with manual promotion (optional)
+	pub fn manual_promotion(&self) -> Option<&Arc<ManualPromotion>> {
+		self.manual_promotion.as_ref()
+	}
+
+	/// Get the candidate registry awaiting promotion, if any
+	pub fn candidate(&self) -> Option<Arc<Candidate>> {
+		self.manual_promotion.as_ref()?.candidate()
+	}
+
+	/// Smoke test the candidate `id` (the staged one if `None`) and make it the active
+	/// registry if every test passes
+	pub async fn promote_candidate(&self, id: Option<u64>) -> Result<Arc<Candidate>, PromotionError> {
+		let promotion = self
+			.manual_promotion
+			.as_ref()
+			.ok_or(PromotionError::NoCandidate)?;
+		let started = Instant::now();
+		let candidate = promotion.promote(id).await?;
+		let mut record = ReloadRecord::new(
+			ReloadTrigger::Promotion,
+			candidate.source.clone(),
+			started.elapsed(),
+		);
+		record.tools = candidate.registry.len();
+		record.compositions = candidate.registry.composition_count();
+		record.validation_errors = candidate.validation_errors;
+		record.validation_warnings = candidate.validation_warnings;
+		self.activate(
+			candidate.registry.clone(),
+			ReloadTrigger::Promotion,
+			candidate.snapshot.as_ref(),
+		);
+		self.record(record);
+		info!(target: "virtual_tools", candidate = candidate.id, "Promoted candidate registry");
+		Ok(candidate)
+	}
+
+	/// Drop the candidate registry awaiting promotion, returning it
+	pub fn discard_candidate(&self) -> Option<Arc<Candidate>> {
+		let discarded = self.manual_promotion.as_ref()?.discard()?;
+		info!(target: "virtual_tools", candidate = discarded.id, "Discarded candidate registry");
+		Some(discarded)
+	}
+
+	/// Replay dead-lettered inputs through their composition in the current registry, on the
+	/// batch lane worker held by `_permit`
+	///
+	/// Returns None if no registry is loaded.
+	pub async fn replay_dead_letters(
+		&self,
+		ids: &[u64],
+		invoker: Arc<dyn ToolInvoker>,
+		_permit: WorkerPermit,
+	) -> Option<Vec<ReplayOutcome>> {
+		let registry = self.get_arc()?;
+		let executor = CompositionExecutor::new(registry, invoker)
+			.with_execution_log(self.execution_log.clone())
+			.with_memory_budget(self.memory_budget.clone())
+			.with_events(self.events.clone())
+			.with_result_cache(self.result_cache.clone());
+		let executor = match &self.archiver {
+			Some(archiver) => executor.with_archiver(archiver.clone()),
+			None => executor,
+		};
+		Some(self.dead_letters.replay(ids, executor).await)
+	}
+
+	/// Re-run a captured execution from a step through the current registry with the
+	/// operator's `invoker`, on the batch lane worker held by `_permit`
+	///
+	/// Returns None if no registry is loaded.
+	pub async fn rerun_capture(
+		&self,
+		id: u64,
+		from_step: &str,
+		overrides: serde_json::Map<String, serde_json::Value>,
+		invoker: Arc<dyn ToolInvoker>,
+		_permit: WorkerPermit,
+	) -> Option<Result<ExecutionCapture, RerunError>> {
+		let registry = self.get_arc()?;
+		let executor = CompositionExecutor::new(registry, invoker)
+			.with_memory_budget(self.memory_budget.clone())
+			.with_result_cache(self.result_cache.clone())
+			.with_captures(self.captures.clone());
+		Some(
+			self
+				.captures
+				.rerun(id, from_step, overrides, executor)
+				.await,
+		)
+	}
+
+	/// The runtime state stores to carry across instances, by snapshot name
+	pub fn state_stores(&self) -> Vec<(&'static str, Arc<dyn StateStore>)> {
+		vec![
+			("resultCache", self.result_cache.store().clone()),
+			("idempotencyKeys", self.idempotency.clone()),
+			("rateLimiters", self.rate_limiters.clone()),
+		]
+	}
+
 	/// Get current compiled registry (returns None if no registry configured)
 	///
 	/// Returns a guard that provides access to the registry. The registry
@@ -74,14 +721,155 @@ impl RegistryStore {
 
 	/// Update registry with new data
 	pub fn update(&self, registry: Registry) -> Result<(), RegistryError> {
-		let compiled = CompiledRegistry::compile(registry)?;
-		self.current.store(Arc::new(Some(Arc::new(compiled))));
-		info!(target: "virtual_tools", "Registry updated successfully");
-		Ok(())
+		let started = Instant::now();
+		self.reload(
+			FetchedRegistry::from_registry(registry),
+			ReloadTrigger::Api,
+			started,
+		)
+	}
+
+	/// Validate and compile a fetched registry, swapping it in on success and recording the
+	/// attempt in the reload history either way
+	fn reload(
+		&self,
+		fetched: Result<FetchedRegistry, RegistryError>,
+		trigger: ReloadTrigger,
+		started: Instant,
+	) -> Result<(), RegistryError> {
+		let source = match trigger {
+			ReloadTrigger::Snapshot => self.snapshot_path.as_ref().map(|p| p.display().to_string()),
+			_ => self.client.as_ref().map(|c| c.source().to_string()),
+		};
+		let FetchedRegistry { registry, document } = match fetched {
+			Ok(fetched) => fetched,
+			Err(e) => {
+				let mut record = ReloadRecord::new(trigger, source, started.elapsed());
+				record.error = Some(e.to_string());
+				self.record(record);
+				return Err(e);
+			},
+		};
+
+		let validation = validate_registry(&registry);
+		for warning in &validation.warnings {
+			warn!(
+				target: "virtual_tools",
+				tool = warning.tool.as_deref().unwrap_or_default(),
+				"Registry validation warning: {}",
+				warning
+			);
+		}
+		// Written once the registry goes live
+		let snapshot = match &self.snapshot_path {
+			Some(_) if trigger != ReloadTrigger::Snapshot => Some(RegistrySnapshot::new(document)),
+			_ => None,
+		};
+		let compile_started = Instant::now();
+		let compiled = CompiledRegistry::compile_with_env_policy(registry, &self.env_policy)
+			.map(|c| self.configure(c));
+		let compile_time = compile_started.elapsed();
+
+		let mut record = ReloadRecord::new(trigger, source.clone(), started.elapsed());
+		record.compile_ms = compile_time.as_secs_f64() * 1000.0;
+		record.validation_errors = validation.errors.len();
+		record.validation_warnings = validation.warnings.len();
+		let result = match compiled {
+			Ok(compiled) => {
+				record.tools = compiled.len();
+				record.compositions = compiled.composition_count();
+				let compiled = Arc::new(compiled);
+				if let Some(promotion) = &self.manual_promotion
+					&& matches!(trigger, ReloadTrigger::Poll | ReloadTrigger::FileWatch)
+					&& self.has_registry()
+					&& !self.stats.is_degraded()
+				{
+					let id = promotion.stage(StagedRegistry {
+						registry: compiled,
+						trigger,
+						source,
+						validation_errors: record.validation_errors,
+						validation_warnings: record.validation_warnings,
+						snapshot,
+					});
+					record.staged = true;
+					info!(
+						target: "virtual_tools",
+						tools = record.tools,
+						candidate = id,
+						"Registry staged as a candidate for promotion"
+					);
+					self.record(record);
+					return Ok(());
+				}
+				self.activate(compiled, trigger, snapshot.as_ref());
+				info!(
+					target: "virtual_tools",
+					tools = record.tools,
+					compile_ms = record.compile_ms,
+					"Registry updated successfully"
+				);
+				if matches!(
+					trigger,
+					ReloadTrigger::Initial | ReloadTrigger::Poll | ReloadTrigger::FileWatch
+				) && self.stats.is_degraded()
+				{
+					self.stats.set_degraded(false);
+					info!(target: "virtual_tools", "Registry source recovered, leaving degraded mode");
+				}
+				Ok(())
+			},
+			Err(e) => {
+				record.error = Some(e.to_string());
+				Err(e)
+			},
+		};
+		self.record(record);
+		result
+	}
+
+	/// Make a compiled registry the active one, recording what it changed and persisting it
+	/// as the last known good registry
+	fn activate(
+		&self,
+		compiled: Arc<CompiledRegistry>,
+		trigger: ReloadTrigger,
+		snapshot: Option<&RegistrySnapshot>,
+	) {
+		let diff = RegistryDiff::between(self.get_arc().as_deref(), &compiled);
+		self.current.store(Arc::new(Some(compiled)));
+		self.changelog.record(trigger, diff);
+		if let (Some(path), Some(snapshot)) = (&self.snapshot_path, snapshot)
+			&& let Err(e) = snapshot.save(path)
+		{
+			warn!(target: "virtual_tools", path = %path.display(), "Failed to persist registry snapshot: {}", e);
+		}
+	}
+
+	/// Add a reload attempt to the history and announce it on the event bus
+	fn record(&self, record: ReloadRecord) {
+		self.events.publish(GatewayEventKind::RegistryReloaded {
+			trigger: record.trigger,
+			source: record.source.clone(),
+			tools: record.tools,
+			duration_ms: record.duration_ms,
+			error: record.error.clone(),
+		});
+		self.stats.record(record);
+	}
+
+	/// Apply the store's call-time settings to a compiled registry about to go live
+	fn configure(&self, compiled: CompiledRegistry) -> CompiledRegistry {
+		compiled
+			.with_json_limits(self.json_limits)
+			.with_argument_checks(self.argument_checks)
+			.with_input_checks(self.input_checks)
+			.with_destructive_confirmation(self.confirm_destructive)
 	}
 
 	/// Update registry with pre-compiled data
 	pub fn update_compiled(&self, compiled: CompiledRegistry) {
+		let compiled = self.configure(compiled);
 		self.current.store(Arc::new(Some(Arc::new(compiled))));
 		info!(target: "virtual_tools", "Registry updated with compiled data");
 	}
@@ -98,51 +886,117 @@ impl RegistryStore {
 	}
 
 	/// Initial load from configured source
+	///
+	/// If the source can't be loaded, the load failure policy decides whether the error is
+	/// returned or the store starts degraded on a fallback registry. A degraded store keeps
+	/// retrying the source from [`Self::spawn_refresh_loop`].
 	pub async fn initial_load(&self) -> Result<(), RegistryError> {
 		let Some(client) = &self.client else {
 			return Ok(());
 		};
 
-		let registry = client.fetch().await?;
-		self.update(registry)?;
+		let started = Instant::now();
+		let fetched = client.fetch().await;
+		let Err(e) = self.reload(fetched, ReloadTrigger::Initial, started) else {
+			return Ok(());
+		};
+		match self.load_failure_policy {
+			LoadFailurePolicy::FailFast => return Err(e),
+			LoadFailurePolicy::LastKnownGood => {
+				let Some(path) = &self.snapshot_path else {
+					return Err(e);
+				};
+				match self.load_snapshot(path) {
+					Ok(saved_at_ms) => self.stats.set_snapshot_saved_at(saved_at_ms),
+					Err(snapshot_err) => {
+						error!(target: "virtual_tools", path = %path.display(), "No usable registry snapshot: {}", snapshot_err);
+						return Err(e);
+					},
+				}
+				warn!(target: "virtual_tools", "Failed to load registry ({}), serving the last known good snapshot", e);
+			},
+			LoadFailurePolicy::Empty => {
+				let empty = self.configure(CompiledRegistry::compile_with_env_policy(
+					Registry::default(),
+					&self.env_policy,
+				)?);
+				self.current.store(Arc::new(Some(Arc::new(empty))));
+				warn!(target: "virtual_tools", "Failed to load registry ({}), serving an empty registry", e);
+			},
+		}
+		self.stats.set_degraded(true);
 		Ok(())
 	}
 
-	/// Start background refresh loop (for HTTP sources)
+	/// Load the registry persisted by the last successful load, returning when it was saved
+	fn load_snapshot(&self, path: &Path) -> Result<u64, RegistryError> {
+		let started = Instant::now();
+		let snapshot = RegistrySnapshot::load(path);
+		let saved_at_ms = snapshot.as_ref().map_or(0, |s| s.saved_at_ms);
+		let fetched = snapshot.and_then(RegistrySnapshot::into_fetched);
+		self.reload(fetched, ReloadTrigger::Snapshot, started)?;
+		Ok(saved_at_ms)
+	}
+
+	/// Start background refresh loop
+	///
+	/// HTTP sources are polled every refresh interval, or, with a max refresh interval, at an
+	/// interval that stretches while the source is unchanged. File sources are reloaded by the
+	/// file watcher instead, and only polled while a degraded startup waits for them to recover.
 	pub fn spawn_refresh_loop(self: Arc<Self>) -> Option<tokio::task::JoinHandle<()>> {
 		let client = self.client.as_ref()?;
 
-		// Only spawn for HTTP sources
-		if client.is_file_source() {
+		let file_source = client.is_file_source();
+		if file_source && !self.is_degraded() {
 			return None;
 		}
 
-		let interval = client.refresh_interval();
+		let mut schedule = PollSchedule::new(client.refresh_interval(), client.max_refresh_interval());
 		let store = self;
 
 		Some(tokio::spawn(async move {
 			info!(
 				target: "virtual_tools",
 				"Starting registry refresh loop with interval {:?}",
-				interval
+				schedule.min
 			);
 
+			let mut failures = 0;
 			loop {
-				tokio::time::sleep(interval).await;
+				let degraded = store.is_degraded();
+				if file_source && !degraded {
+					break;
+				}
+				tokio::time::sleep(schedule.delay(degraded, failures)).await;
 
 				let Some(client) = &store.client else {
 					break;
 				};
 
-				match client.fetch().await {
-					Ok(registry) => {
-						if let Err(e) = store.update(registry) {
+				let started = Instant::now();
+				match client.fetch_if_changed().await {
+					// Unchanged since the last fetch, so there's nothing to reparse or recompile. A
+					// degraded store is still on the document that failed, so keeps backing off.
+					Ok(None) if degraded => failures += 1,
+					Ok(None) => {
+						failures = 0;
+						schedule.unchanged();
+					},
+					Ok(Some(fetched)) => match store.reload(Ok(fetched), ReloadTrigger::Poll, started) {
+						Ok(()) => {
+							failures = 0;
+							schedule.changed();
+						},
+						Err(e) => {
 							warn!(target: "virtual_tools", "Failed to compile registry: {}", e);
-						}
+							failures += 1;
+						},
 					},
 					Err(e) => {
 						warn!(target: "virtual_tools", "Failed to fetch registry: {}", e);
 						// Keep the old registry on fetch failure
+						let _ = store.reload(Err(e), ReloadTrigger::Poll, started);
+						failures += 1;
 					},
 				}
 			}
@@ -209,9 +1063,10 @@ impl RegistryStore {
 				info!(target: "virtual_tools", "Registry file changed, reloading...");
 
 				if let Some(client) = &self.client {
+					let started = Instant::now();
 					match client.fetch().await {
-						Ok(registry) => {
-							if let Err(e) = self.update(registry) {
+						Ok(fetched) => {
+							if let Err(e) = self.reload(Ok(fetched), ReloadTrigger::FileWatch, started) {
 								error!(target: "virtual_tools", "Failed to compile registry: {}", e);
 							} else {
 								info!(target: "virtual_tools", "Registry reloaded successfully");
@@ -219,6 +1074,7 @@ impl RegistryStore {
 						},
 						Err(e) => {
 							error!(target: "virtual_tools", "Failed to reload registry: {}", e);
+							let _ = self.reload(Err(e), ReloadTrigger::FileWatch, started);
 						},
 					}
 				}
@@ -230,6 +1086,55 @@ impl RegistryStore {
 	}
 }
 
+/// Polling interval of a source, adapting to how often it changes
+///
+/// Without a max interval the interval stays at the refresh interval. With one, every poll
+/// that finds the source unchanged stretches it by [`POLL_STRETCH`] up to the max, and a
+/// change brings it back to the refresh interval, since changes tend to come in bursts.
+#[derive(Debug, Clone, Copy)]
+struct PollSchedule {
+	min: Duration,
+	max: Duration,
+	interval: Duration,
+}
+
+impl PollSchedule {
+	fn new(interval: Duration, max: Option<Duration>) -> Self {
+		Self {
+			min: interval,
+			max: max.unwrap_or(interval).max(interval),
+			interval,
+		}
+	}
+
+	fn unchanged(&mut self) {
+		let stretched = self.interval.mul_f64(POLL_STRETCH).min(self.max);
+		if stretched != self.interval {
+			debug!(target: "virtual_tools", interval = ?stretched, "Registry unchanged, polling less often");
+		}
+		self.interval = stretched;
+	}
+
+	fn changed(&mut self) {
+		self.interval = self.min;
+	}
+
+	/// Delay before the next poll, after `failures` consecutive failed polls
+	///
+	/// A degraded store retries quickly, from [`DEGRADED_RETRY_START`] doubling up to the
+	/// refresh interval. Otherwise failures back off from the current interval, doubling up to
+	/// [`MAX_POLL_BACKOFF`] times it. Either way the delay is jittered by [`POLL_JITTER`].
+	fn delay(&self, degraded: bool, failures: u32) -> Duration {
+		let backoff = 2u32.saturating_pow(failures);
+		let delay = if degraded {
+			DEGRADED_RETRY_START.saturating_mul(backoff).min(self.min)
+		} else {
+			self.interval.saturating_mul(backoff.min(MAX_POLL_BACKOFF))
+		};
+		delay.mul_f64(1.0 + rand::random_range(-POLL_JITTER..=POLL_JITTER))
+	}
+}
+
 /// Wrapper for thread-safe access to the registry store
 #[derive(Debug, Clone)]
 pub struct RegistryStoreRef {
@@ -264,6 +1169,207 @@ impl RegistryStoreRef {
 		self.inner.has_registry()
 	}
 
+	/// Check if a startup fallback registry is being served
+	pub fn is_degraded(&self) -> bool {
+		self.inner.is_degraded()
+	}
+
+	/// Get the recent-execution log
+	pub fn execution_log(&self) -> &Arc<ExecutionLog> {
+		self.inner.execution_log()
+	}
+
+	/// Get the runtime composition verbosity overrides
+	pub fn verbosity_overrides(&self) -> &Arc<VerbosityOverrides> {
+		self.inner.verbosity_overrides()
+	}
+
+	/// Get the composition worker pool
+	pub fn worker_pool(&self) -> &Arc<WorkerPool> {
+		self.inner.worker_pool()
+	}
+
+	/// Wait for a composition worker in `priority`'s lane, counted in the lane's queue depth
+	/// while waiting
+	pub async fn acquire_worker(
+		&self,
+		priority: ExecutionPriority,
+		metrics: Option<&Metrics>,
+	) -> WorkerPermit {
+		self.inner.acquire_worker(priority, metrics).await
+	}
+
+	/// Get the in-flight composition memory budget
+	pub fn memory_budget(&self) -> &Arc<MemoryBudget> {
+		self.inner.memory_budget()
+	}
+
+	/// Get the reload history
+	pub fn stats(&self) -> &Arc<RegistryStats> {
+		self.inner.stats()
+	}
+
+	/// Get the recent initialization failures of upstream targets
+	pub fn upstream_readiness(&self) -> &Arc<UpstreamReadiness> {
+		self.inner.upstream_readiness()
+	}
+
+	/// Get the upstream latency tracker that sheds calls
+	pub fn load_shedder(&self) -> &Arc<LoadShedder> {
+		self.inner.load_shedder()
+	}
+
+	/// Check that a registry is loaded and its state stores can be written
+	pub async fn readiness(&self) -> RegistryReadiness {
+		self.inner.readiness().await
+	}
+
+	/// The readiness checks, and whether the upstreams compositions call are failing
+	pub async fn readiness_with_upstreams(&self) -> RegistryReadiness {
+		self.inner.readiness_with_upstreams().await
+	}
+
+	/// Get what recent reloads changed
+	pub fn changelog(&self) -> &Arc<RegistryChangelog> {
+		self.inner.changelog()
+	}
+
+	/// Get the event bus reloads and compositions publish to
+	pub fn events(&self) -> &EventBus {
+		self.inner.events()
+	}
+
+	/// Get the backend schema drift tracker
+	pub fn schema_drift(&self) -> &Arc<SchemaDriftTracker> {
+		self.inner.schema_drift()
+	}
+
+	/// Get the cache behind cache patterns
+	pub fn result_cache(&self) -> &Arc<ResultCache> {
+		self.inner.result_cache()
+	}
+
+	/// Get the queue of dead-lettered composition failures
+	pub fn dead_letters(&self) -> &Arc<DeadLetterQueue> {
+		self.inner.dead_letters()
+	}
+
+	/// Get the executions captured for time-travel debugging
+	pub fn captures(&self) -> &Arc<ExecutionCaptures> {
+		self.inner.captures()
+	}
+
+	/// Get the tool calls in flight or within their debounce window
+	pub fn debouncer(&self) -> &Arc<CallDebouncer<CallToolResult, SharedFailure>> {
+		self.inner.debouncer()
+	}
+
+	/// Get the idempotency keys of composition calls
+	pub fn idempotency(&self) -> &Arc<IdempotencyStore<CallToolResult>> {
+		self.inner.idempotency()
+	}
+
+	/// Get the rate limiter buckets of throttle patterns
+	pub fn rate_limiters(&self) -> &SharedRateLimiterRegistry {
+		self.inner.rate_limiters()
+	}
+
+	/// Size and eviction counters of the bounded in-memory stores, by store
+	pub fn eviction_stats(&self) -> Vec<(&'static str, EvictionStats)> {
+		self.inner.eviction_stats()
+	}
+
+	/// Get the operator switches disabling tools or the whole registry
+	pub fn kill_switch(&self) -> &Arc<KillSwitch> {
+		self.inner.kill_switch()
+	}
+
+	/// Get the archiver of composition payloads, if archival is configured
+	pub fn archiver(&self) -> Option<&Arc<PayloadArchiver>> {
+		self.inner.archiver()
+	}
+
+	/// Get the tool usage analytics, if they are configured
+	pub fn usage(&self) -> Option<&Arc<UsageAnalytics>> {
+		self.inner.usage()
+	}
+
+	/// Get the journal of in-flight pipeline executions, if one is configured
+	pub fn execution_journal(&self) -> Option<&Arc<ExecutionJournal>> {
+		self.inner.execution_journal()
+	}
+
+	/// Get the external tool call authorizer, if one is configured
+	pub fn authorizer(&self) -> Option<&Arc<ToolCallAuthorizer>> {
+		self.inner.authorizer()
+	}
+
+	/// Get the bounds on backend responses parsed as JSON
+	pub fn json_limits(&self) -> JsonLimits {
+		self.inner.json_limits()
+	}
+
+	/// Get the candidate registry of a store with manual promotion (optional)
+	pub fn manual_promotion(&self) -> Option<&Arc<ManualPromotion>> {
+		self.inner.manual_promotion()
+	}
+
+	/// Get the candidate registry awaiting promotion, if any
+	pub fn candidate(&self) -> Option<Arc<Candidate>> {
+		self.inner.candidate()
+	}
+
+	/// Smoke test a candidate and make it the active registry if every test passes
+	pub async fn promote_candidate(&self, id: Option<u64>) -> Result<Arc<Candidate>, PromotionError> {
+		self.inner.promote_candidate(id).await
+	}
+
+	/// Drop the candidate registry awaiting promotion, returning it
+	pub fn discard_candidate(&self) -> Option<Arc<Candidate>> {
+		self.inner.discard_candidate()
+	}
+
+	/// Replay dead-lettered inputs through their composition in the current registry
+	pub async fn replay_dead_letters(
+		&self,
+		ids: &[u64],
+		invoker: Arc<dyn ToolInvoker>,
+		permit: WorkerPermit,
+	) -> Option<Vec<ReplayOutcome>> {
+		self.inner.replay_dead_letters(ids, invoker, permit).await
+	}
+
+	/// Re-run a captured execution from a step through the current registry
+	pub async fn rerun_capture(
+		&self,
+		id: u64,
+		from_step: &str,
+		overrides: serde_json::Map<String, serde_json::Value>,
+		invoker: Arc<dyn ToolInvoker>,
+		permit: WorkerPermit,
+	) -> Option<Result<ExecutionCapture, RerunError>> {
+		self
+			.inner
+			.rerun_capture(id, from_step, overrides, invoker, permit)
+			.await
+	}
+
+	/// The runtime state stores to carry across instances, by snapshot name
+	pub fn state_stores(&self) -> Vec<(&'static str, Arc<dyn StateStore>)> {
+		self.inner.state_stores()
+	}
+
+	/// Snapshot the reload history and active tool counts
+	pub fn stats_snapshot(&self) -> RegistryStatsSnapshot {
+		self.inner.stats().snapshot(self.get_arc().as_deref())
+	}
+
+	/// Summarize the active registry and reload history for the admin config dump
+	pub fn dump(&self) -> RegistryDump {
+		let source = self.inner.client().map(|c| c.source().to_string());
+		RegistryDump::new(self.inner.stats(), self.get_arc().as_deref(), source)
+	}
+
 	/// Update the registry
 	pub fn update(&self, registry: Registry) -> Result<(), RegistryError> {
 		self.inner.update(registry)
@@ -307,21 +1413,38 @@ mod tests {
 		let tool = ToolDefinition {
 			name: "test_tool".to_string(),
 			description: Some("A test tool".to_string()),
+			descriptions: Default::default(),
 			implementation: ToolImplementation::Source(SourceTool {
 				target: "backend".to_string(),
+				fallback_targets: vec![],
 				tool: "original_tool".to_string(),
 				defaults: Default::default(),
+				caller_defaults: Default::default(),
 				hide_fields: vec![],
+				strict_args: false,
+				transport_retry: None,
+				pagination: None,
+				job_polling: None,
 			}),
 			input_schema: None,
 			output_transform: None,
 			output_schema: None,
+			result_envelope: false,
+			resilience: None,
+			cost: None,
+			debounce: None,
+			idempotency: None,
+			priority: Default::default(),
 			version: None,
+			examples: Vec::new(),
+			annotations: None,
 			metadata: Default::default(),
 		};
 		Registry {
 			schema_version: "1.0".to_string(),
 			tools: vec![tool],
+			defaults: Default::default(),
+			targets: Default::default(),
 		}
 	}
 
@@ -361,4 +1484,322 @@ mod tests {
 		store.update(registry).unwrap();
 		assert!(store.has_registry());
 	}
+
+	#[test]
+	fn test_reloads_are_recorded() {
+		let events = EventBus::new(8);
+		let mut rx = events.subscribe();
+		let store = RegistryStoreRef::new(RegistryStore::new().with_events(events));
+		store.update(create_test_registry()).unwrap();
+
+		// A registry that fails to compile keeps the previous one active
+		let mut broken = create_test_registry();
+		broken.tools.push(broken.tools[0].clone());
+		assert!(store.update(broken).is_err());
+		assert!(store.has_registry());
+
+		let snapshot = store.stats_snapshot();
+		assert_eq!((snapshot.reloads, snapshot.reload_failures), (1, 1));
+		assert_eq!((snapshot.tools, snapshot.compositions), (1, 0));
+		assert_eq!(snapshot.history.len(), 2);
+		assert!(snapshot.history[0].error.is_some());
+		assert_eq!(snapshot.history[1].trigger, ReloadTrigger::Api);
+		assert_eq!(snapshot.last_success.map(|r| r.tools), Some(1));
+
+		// Both attempts are announced on the store's bus
+		let errors: Vec<_> = std::iter::from_fn(|| rx.try_recv().ok())
+			.map(|e| match &e.kind {
+				GatewayEventKind::RegistryReloaded { error, .. } => error.is_some(),
+				other => panic!("unexpected event {other:?}"),
+			})
+			.collect();
+		assert_eq!(errors, vec![false, true]);
+	}
+
+	#[tokio::test]
+	async fn test_polled_registry_waits_for_promotion() {
+		let store = RegistryStore::new().with_manual_promotion(Vec::new());
+		store.update(create_test_registry()).unwrap();
+
+		let mut next = create_test_registry();
+		let mut added = next.tools[0].clone();
+		added.name = "added_tool".to_string();
+		next.tools.push(added);
+		store
+			.reload(
+				FetchedRegistry::from_registry(next),
+				ReloadTrigger::Poll,
+				Instant::now(),
+			)
+			.unwrap();
+		assert_eq!(store.get_arc().unwrap().len(), 1);
+		let candidate = store.candidate().unwrap();
+		assert_eq!(candidate.registry.len(), 2);
+		assert_eq!(
+			store.stats().last_success().unwrap().trigger,
+			ReloadTrigger::Api
+		);
+		assert!(store.stats().last().unwrap().staged);
+
+		let promoted = store.promote_candidate(Some(candidate.id)).await.unwrap();
+		assert_eq!(promoted.id, candidate.id);
+		assert_eq!(store.get_arc().unwrap().len(), 2);
+		assert!(store.candidate().is_none());
+		assert_eq!(
+			store.stats().last_success().unwrap().trigger,
+			ReloadTrigger::Promotion
+		);
+		assert_eq!(
+			store.changelog().recent(1)[0].diff.added,
+			vec!["added_tool"]
+		);
+		assert_eq!(
+			store.promote_candidate(None).await.unwrap_err(),
+			PromotionError::NoCandidate
+		);
+	}
+
+	fn file_client(path: &Path) -> RegistryClient {
+		let uri = format!("file://{}", path.display());
+		RegistryClient::from_uri(&uri, Duration::from_secs(60), None).unwrap()
+	}
+
+	#[tokio::test]
+	async fn test_load_failure_policies() {
+		let dir = tempfile::tempdir().unwrap();
+		let source = dir.path().join("registry.json");
+		let snapshot = dir.path().join("snapshots/registry.json");
+
+		let failing = |policy| {
+			RegistryStore::new()
+				.with_client(file_client(&source))
+				.with_load_failure_policy(policy)
+				.with_snapshot_path(&snapshot)
+		};
+
+		// Without a snapshot every policy but `Empty` refuses to start
+		for policy in [
+			LoadFailurePolicy::FailFast,
+			LoadFailurePolicy::LastKnownGood,
+		] {
+			let store = failing(policy);
+			assert!(store.initial_load().await.is_err());
+			assert!(!store.has_registry());
+		}
+
+		// The empty registry is configured like a loaded one
+		let store = failing(LoadFailurePolicy::Empty)
+			.with_argument_checks(ArgumentCheckPolicy::Enforce)
+			.with_input_checks(ArgumentCheckPolicy::Off);
+		store.initial_load().await.unwrap();
+		assert!(store.has_registry() && store.is_degraded());
+		let empty = store.get_arc().unwrap();
+		assert_eq!(empty.len(), 0);
+		assert_eq!(empty.argument_checks(), ArgumentCheckPolicy::Enforce);
+		assert_eq!(empty.input_checks(), ArgumentCheckPolicy::Off);
+
+		// A successful load persists the snapshot
+		RegistryStore::new()
+			.with_snapshot_path(&snapshot)
+			.update(create_test_registry())
+			.unwrap();
+		assert!(snapshot.exists());
+
+		let store = failing(LoadFailurePolicy::LastKnownGood);
+		store.initial_load().await.unwrap();
+		assert!(store.is_degraded());
+		assert_eq!(store.get_arc().unwrap().len(), 1);
+		let last = store.stats().last_success().unwrap();
+		assert_eq!(last.trigger, ReloadTrigger::Snapshot);
+		assert_eq!(last.source, Some(snapshot.display().to_string()));
+		let stats = store.stats().snapshot(None);
+		assert!(stats.degraded && stats.snapshot_saved_at_ms.is_some());
+	}
+
+	#[tokio::test]
+	async fn test_snapshot_keeps_the_source_document() {
+		let dir = tempfile::tempdir().unwrap();
+		let source = dir.path().join("registry.json");
+		let snapshot = dir.path().join("snapshot.json");
+		let document = format!(
+			"{{\n  \"x-owner\": \"search-team\",\n  \"tools\": {}\n}}\n",
+			serde_json::to_string(&create_test_registry().tools).unwrap()
+		);
+		std::fs::write(&source, &document).unwrap();
+
+		let store = RegistryStore::new()
+			.with_client(file_client(&source))
+			.with_snapshot_path(&snapshot);
+		store.initial_load().await.unwrap();
+		assert_eq!(
+			RegistrySnapshot::load(&snapshot).unwrap().document,
+			document
+		);
+
+		// A restart with the source gone serves the same registry from the snapshot
+		std::fs::remove_file(&source).unwrap();
+		let restarted = RegistryStore::new()
+			.with_client(file_client(&source))
+			.with_load_failure_policy(LoadFailurePolicy::LastKnownGood)
+			.with_snapshot_path(&snapshot);
+		restarted.initial_load().await.unwrap();
+		assert!(restarted.is_degraded());
+		assert_eq!(
+			restarted.get_arc().unwrap().len(),
+			store.get_arc().unwrap().len()
+		);
+	}
+
+	#[tokio::test]
+	async fn test_degraded_store_retries_source() {
+		let dir = tempfile::tempdir().unwrap();
+		let source = dir.path().join("registry.json");
+		let store = Arc::new(
+			RegistryStore::new()
+				.with_client(file_client(&source))
+				.with_load_failure_policy(LoadFailurePolicy::Empty),
+		);
+		store.initial_load().await.unwrap();
+		let handle = Arc::clone(&store).spawn_refresh_loop().unwrap();
+
+		let registry = serde_json::to_vec(&create_test_registry()).unwrap();
+		std::fs::write(&source, registry).unwrap();
+		tokio::time::timeout(Duration::from_secs(5), handle)
+			.await
+			.unwrap()
+			.unwrap();
+		assert!(!store.is_degraded());
+		assert_eq!(store.get_arc().unwrap().len(), 1);
+	}
+
+	#[tokio::test]
+	async fn test_readiness() {
+		let dir = tempfile::tempdir().unwrap();
+		let store = RegistryStore::new();
+		let readiness = store.readiness().await;
+		assert!(!readiness.ready);
+		assert_eq!(readiness.failures(), vec!["registry: registry not loaded"]);
+
+		store.update(create_test_registry()).unwrap();
+		let readiness = store.readiness().await;
+		assert!(readiness.ready);
+		let names: Vec<_> = readiness.checks.iter().map(|c| c.name.as_str()).collect();
+		assert_eq!(names, vec!["registry"]);
+		let report = store.readiness_with_upstreams().await;
+		let names: Vec<_> = report.checks.iter().map(|c| c.name.as_str()).collect();
+		assert_eq!(names, vec!["registry", "upstreams"]);
+
+		// A journal below a regular file can't be written
+		let blocked = dir.path().join("blocked");
+		std::fs::write(&blocked, b"").unwrap();
+		let store = RegistryStore::new().with_execution_journal(Arc::new(
+			crate::mcp::registry::executor::ExecutionJournal::new(blocked.join("journal"), "primary"),
+		));
+		store.update(create_test_registry()).unwrap();
+		let readiness = store.readiness().await;
+		assert!(!readiness.ready);
+		assert!(readiness.failures()[0].starts_with("journal: execution journal not writable"));
+	}
+
+	/// Test that admin replays wait in the batch lane and are counted in its queue depth, like
+	/// the composition calls of agents
+	#[tokio::test(start_paused = true)]
+	async fn test_batch_replays_are_counted_in_the_queue_depth() {
+		let metrics = Metrics::new(
+			&mut prometheus_client::registry::Registry::default(),
+			Default::default(),
+		);
+		let depth = |lane| {
+			metrics
+				.composition_queue_depth
+				.get_or_create(&CompositionLaneLabels { lane })
+				.get()
+		};
+		let store = RegistryStoreRef::new(RegistryStore::new().with_worker_pool(2, 1));
+		store.update(Registry::default()).unwrap();
+		let pool = store.worker_pool().clone();
+		let batch = pool.acquire(ExecutionPriority::Batch).await;
+
+		// With the batch lane taken, a replay waits...
+		let replay = async {
+			let permit = store
+				.acquire_worker(ExecutionPriority::Batch, Some(&metrics))
+				.await;
+			let invoker = Arc::new(crate::mcp::registry::executor::MockToolInvoker::new());
+			store.replay_dead_letters(&[1], invoker, permit).await
+		};
+		tokio::pin!(replay);
+		// Time is paused, so the timeouts only fire once nothing else can make progress
+		let wait = Duration::from_secs(1);
+		assert!(tokio::time::timeout(wait, &mut replay).await.is_err());
+		assert_eq!(depth(CompositionLane::Batch), 1);
+		// ...while an interactive call still gets a worker
+		let interactive = pool.acquire(ExecutionPriority::Interactive);
+		assert!(tokio::time::timeout(wait, interactive).await.is_ok());
+		assert_eq!(depth(CompositionLane::Interactive), 0);
+
+		drop(batch);
+		let outcomes = replay.await.unwrap();
+		assert_eq!(outcomes.len(), 1);
+		assert_eq!(depth(CompositionLane::Batch), 0);
+	}
+
+	#[tokio::test]
+	async fn test_shared_state_views() {
+		let remote = Arc::new(crate::stateful::memory::MemoryStore::new());
+		let state = TieredStore::new(remote, Default::default());
+		let store = RegistryStore::new()
+			.with_shared_state(state)
+			.with_max_idempotency_keys(10);
+		let idempotency = store.idempotency();
+		assert_eq!(idempotency.max_keys(), 10);
+		assert_eq!(
+			idempotency.shared().map(TieredStore::consistency),
+			Some(crate::stateful::Consistency::Strong)
+		);
+	}
+
+	#[test]
+	fn test_poll_delay() {
+		let interval = Duration::from_secs(60);
+		let within = |delay: Duration, expected: Duration| {
+			let expected = expected.as_secs_f64();
+			let delay = delay.as_secs_f64();
+			assert!(
+				(delay - expected).abs() <= expected * POLL_JITTER + 1e-9,
+				"{delay} not within jitter of {expected}"
+			);
+		};
+		let fixed = PollSchedule::new(interval, None);
+		within(fixed.delay(false, 0), interval);
+		within(fixed.delay(false, 2), interval * 4);
+		within(fixed.delay(false, 10), interval * MAX_POLL_BACKOFF);
+		within(fixed.delay(true, 0), DEGRADED_RETRY_START);
+		within(fixed.delay(true, 3), DEGRADED_RETRY_START * 8);
+		within(fixed.delay(true, 40), interval);
+
+		let mut fixed = fixed;
+		fixed.unchanged();
+		within(fixed.delay(false, 0), interval);
+	}
+
+	#[test]
+	fn test_poll_schedule_adapts_to_changes() {
+		let interval = Duration::from_secs(60);
+		let mut schedule = PollSchedule::new(interval, Some(Duration::from_secs(600)));
+		schedule.unchanged();
+		assert_eq!(schedule.interval, Duration::from_secs(90));
+		schedule.unchanged();
+		assert_eq!(schedule.interval, Duration::from_secs(135));
+		for _ in 0..10 {
+			schedule.unchanged();
+		}
+		assert_eq!(schedule.interval, Duration::from_secs(600));
+		// Failures back off from the stretched interval; a degraded store still retries quickly
+		assert!(schedule.delay(false, 1) > Duration::from_secs(1000));
+		assert!(schedule.delay(true, 0) < Duration::from_secs(2));
+
+		schedule.changed();
+		assert_eq!(schedule.interval, interval);
+	}
 }
@@ -4,10 +4,13 @@
 // These types correspond to the registry.proto schema.
 
 use std::collections::HashMap;
+use std::time::Duration;
 
 use serde::{Deserialize, Serialize};
 
-use super::patterns::{FieldSource, PatternSpec, SchemaMapSpec};
+use super::patterns::{
+	FieldPredicate, FieldSource, OnDuplicate, PatternSpec, Resilience, SchemaMapSpec, units,
+};
 
 /// Parsed registry from JSON
 #[derive(Debug, Clone, Deserialize, Serialize, Default)]
@@ -20,14 +23,56 @@ pub struct Registry {
 	/// List of tool definitions (virtual tools and compositions)
 	#[serde(default)]
 	pub tools: Vec<ToolDefinition>,
+
+	/// Policies applied to every tool that doesn't set its own
+	#[serde(default, skip_serializing_if = "RegistryDefaults::is_empty")]
+	pub defaults: RegistryDefaults,
+
+	/// Headers and `_meta` entries attached to every call to a target (target name -> policy)
+	#[serde(default, skip_serializing_if = "HashMap::is_empty")]
+	pub targets: HashMap<String, TargetPolicy>,
 }
 
 fn default_schema_version() -> String {
 	"1.0".to_string()
 }
 
+/// Settings applied to every request the gateway sends to a target
+///
+/// Values may reference environment variables as `${ENV_VAR}` (resolved when the registry is
+/// compiled) and the caller's JWT claims as `${claims.<name>}` (resolved per request).
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TargetPolicy {
+	/// HTTP headers to set on requests to the target
+	#[serde(default)]
+	pub headers: HashMap<String, String>,
+
+	/// Entries merged into the MCP `_meta` of requests to the target
+	#[serde(default)]
+	pub meta: serde_json::Map<String, serde_json::Value>,
+}
+
+/// Registry-wide defaults, applied to tool definitions at compile time
+///
+/// A tool's own setting always wins, so a tool opts out of a default by setting the field
+/// itself (e.g. `transportRetry: { maxAttempts: 1 }` disables retries for that tool, and
+/// `resilience: {}` leaves a composition unwrapped). Policies that can't be defaulted are
+/// rejected rather than ignored.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct RegistryDefaults {
+	/// Transport retry policy for source tools without one
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub transport_retry: Option<TransportRetry>,
+
+	/// Resilience shorthand for compositions without one
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub resilience: Option<Resilience>,
+}
+
 /// Unified tool definition - either a virtual tool or a composition
-#[derive(Debug, Clone, Deserialize, Serialize)]
+#[derive(Debug, Clone, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct ToolDefinition {
 	/// Name exposed to agents (unique identifier)
@@ -37,6 +82,10 @@ pub struct ToolDefinition {
 	#[serde(default)]
 	pub description: Option<String>,
 
+	/// Per-locale description overrides, keyed by language tag (e.g. `fr`, `pt-BR`)
+	#[serde(default, skip_serializing_if = "HashMap::is_empty")]
+	pub descriptions: HashMap<String, String>,
+
 	/// Tool implementation - either source-based or composition
 	#[serde(flatten)]
 	pub implementation: ToolImplementation,
@@ -53,15 +102,135 @@ pub struct ToolDefinition {
 	#[serde(default)]
 	pub output_schema: Option<serde_json::Value>,
 
+	/// Wrap composition output in a `{data, errors, meta}` envelope (raw output by default)
+	#[serde(default, skip_serializing_if = "crate::serdes::is_default")]
+	pub result_envelope: bool,
+
+	/// Resilience shorthand, expanded into wrapper patterns when the registry is compiled
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub resilience: Option<Resilience>,
+
+	/// Estimated cost of each call, accumulated per composition execution (source tools only)
+	#[serde(default)]
+	pub cost: Option<CostModel>,
+
+	/// Hand repeats of an identical call from the same caller the earlier call's result
+	#[serde(default)]
+	pub debounce: Option<Debounce>,
+
+	/// Replay the result of an earlier call with the same caller-supplied idempotency key
+	/// (compositions only)
+	#[serde(default)]
+	pub idempotency: Option<Idempotency>,
+
+	/// How readily the tool's backend calls are shed while an upstream's latency breaches
+	/// its SLO; a composition's calls are shed at the composition's priority
+	#[serde(default, skip_serializing_if = "ToolPriority::is_normal")]
+	pub priority: ToolPriority,
+
 	/// Semantic version of this tool definition
 	#[serde(default)]
 	pub version: Option<String>,
 
-	/// Arbitrary metadata (owner, classification, etc.)
+	/// Sample calls, advertised in the input schema's `examples` and by `describe_tool`
+	#[serde(default, skip_serializing_if = "Vec::is_empty")]
+	pub examples: Vec<ToolExample>,
+
+	/// Behavior hints advertised to clients (e.g. whether the tool is read-only)
 	#[serde(default)]
+	pub annotations: Option<ToolAnnotations>,
+
+	/// Arbitrary metadata (owner, classification, etc.)
+	#[serde(default, skip_serializing_if = "HashMap::is_empty")]
 	pub metadata: HashMap<String, serde_json::Value>,
 }
 
+impl<'de> Deserialize<'de> for ToolDefinition {
+	fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+	where
+		D: serde::Deserializer<'de>,
+	{
+		ToolDefinition::try_from(ToolDefinitionSerde::deserialize(deserializer)?)
+			.map_err(serde::de::Error::custom)
+	}
+}
+
+/// A tool definition as written, with the implementation spelled out as `source`/`spec`
+///
+/// A flattened implementation would be buffered before it is deserialized, which hides
+/// the fields nothing reads from strict parsing.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ToolDefinitionSerde {
+	name: String,
+	#[serde(default)]
+	description: Option<String>,
+	#[serde(default)]
+	descriptions: HashMap<String, String>,
+	#[serde(default)]
+	source: Option<SourceTool>,
+	#[serde(default)]
+	spec: Option<PatternSpec>,
+	#[serde(default)]
+	input_schema: Option<serde_json::Value>,
+	#[serde(default)]
+	output_transform: Option<OutputTransform>,
+	#[serde(default)]
+	output_schema: Option<serde_json::Value>,
+	#[serde(default)]
+	result_envelope: bool,
+	#[serde(default)]
+	resilience: Option<Resilience>,
+	#[serde(default)]
+	cost: Option<CostModel>,
+	#[serde(default)]
+	debounce: Option<Debounce>,
+	#[serde(default)]
+	idempotency: Option<Idempotency>,
+	#[serde(default)]
+	priority: ToolPriority,
+	#[serde(default)]
+	version: Option<String>,
+	#[serde(default)]
+	examples: Vec<ToolExample>,
+	#[serde(default)]
+	annotations: Option<ToolAnnotations>,
+	#[serde(default)]
+	metadata: HashMap<String, serde_json::Value>,
+}
+
+impl TryFrom<ToolDefinitionSerde> for ToolDefinition {
+	type Error = String;
+	fn try_from(value: ToolDefinitionSerde) -> Result<Self, Self::Error> {
+		let name = &value.name;
+		let implementation = match (value.source, value.spec) {
+			(Some(source), None) => ToolImplementation::Source(source),
+			(None, Some(spec)) => ToolImplementation::Spec(spec),
+			(None, None) => return Err(format!("tool '{name}' needs a `source` or a `spec`")),
+			(Some(_), Some(_)) => return Err(format!("tool '{name}' has both a `source` and a `spec`")),
+		};
+		Ok(ToolDefinition {
+			name: value.name,
+			description: value.description,
+			descriptions: value.descriptions,
+			implementation,
+			input_schema: value.input_schema,
+			output_transform: value.output_transform,
+			output_schema: value.output_schema,
+			result_envelope: value.result_envelope,
+			resilience: value.resilience,
+			cost: value.cost,
+			debounce: value.debounce,
+			idempotency: value.idempotency,
+			priority: value.priority,
+			version: value.version,
+			examples: value.examples,
+			annotations: value.annotations,
+			metadata: value.metadata,
+		})
+	}
+}
+
 /// Tool implementation - either source-based (1:1) or composition (N:1)
 #[derive(Debug, Clone, Deserialize, Serialize)]
 #[serde(rename_all = "camelCase")]
@@ -80,6 +249,11 @@ pub struct SourceTool {
 	/// Target name (MCP server/backend name)
 	pub target: String,
 
+	/// Targets serving the same tool that calls fail over to, in order, when a call to the
+	/// previous one fails before a response arrives or its circuit breaker is open
+	#[serde(default, skip_serializing_if = "Vec::is_empty")]
+	pub fallback_targets: Vec<String>,
+
 	/// Original tool name on that target
 	pub tool: String,
 
@@ -87,9 +261,334 @@ pub struct SourceTool {
 	#[serde(default)]
 	pub defaults: HashMap<String, serde_json::Value>,
 
+	/// Defaults for particular callers, keyed by JWT subject; applied over `defaults`
+	#[serde(default, skip_serializing_if = "HashMap::is_empty")]
+	pub caller_defaults: HashMap<String, HashMap<String, serde_json::Value>>,
+
 	/// Fields to remove from schema (hidden from agents)
 	#[serde(default)]
 	pub hide_fields: Vec<String>,
+
+	/// Refuse calls passing arguments the effective input schema doesn't list, hidden fields
+	/// included, instead of forwarding them to the backend
+	#[serde(default, skip_serializing_if = "std::ops::Not::not")]
+	pub strict_args: bool,
+
+	/// Retry on transport-level failures (connection reset, stream closed before response)
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub transport_retry: Option<TransportRetry>,
+
+	/// Follow the backend's cursor pagination, so one call returns every page
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub pagination: Option<Pagination>,
+
+	/// Treat the tool as submitting a job, and poll a status tool until the job finishes, so
+	/// one call returns the job's result
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub job_polling: Option<Box<JobPolling>>,
+}
+
+/// Cursor pagination of a list-style backend tool
+///
+/// The backend is called repeatedly, passing the cursor from each response into the next
+/// request, and the items of every page are concatenated into the first page's response.
+/// Both paths are simple `$.a.b` paths.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Pagination {
+	/// Request argument the cursor is sent in
+	pub cursor_field: String,
+
+	/// Path to the next page's cursor in a response; absent, null or "" ends pagination
+	pub cursor_path: String,
+
+	/// Path to the array of items in a response
+	pub items_path: String,
+
+	/// Request argument the page size is sent in, unless the caller sets it
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub page_size_field: Option<String>,
+
+	/// Page size to request (requires pageSizeField)
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub page_size: Option<u64>,
+
+	/// Most pages fetched per call (default: 10, at most 100)
+	#[serde(default = "default_max_pages")]
+	pub max_pages: u32,
+}
+
+fn default_max_pages() -> u32 {
+	Pagination::DEFAULT_MAX_PAGES
+}
+
+impl Pagination {
+	/// Pages fetched per call when maxPages isn't set
+	pub const DEFAULT_MAX_PAGES: u32 = 10;
+	/// Highest maxPages accepted, so one call can't page through a backend without end
+	pub const MAX_PAGES_LIMIT: u32 = 100;
+}
+
+/// Polling of a job-style backend tool
+///
+/// The source tool submits the job and answers with its id. The status tool, on the same
+/// target, is then called with the id every `intervalMs` until a status response matches
+/// `completeWhen`, and that response (or the part of it at `resultPath`) is the call's result.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JobPolling {
+	/// Tool on the same target that reports a job's status
+	pub status_tool: String,
+
+	/// JSONPath to the job id in the submit response
+	pub job_id_path: String,
+
+	/// Argument the job id is passed to the status tool in
+	pub job_id_field: String,
+
+	/// Matches a status response once the job has finished
+	pub complete_when: FieldPredicate,
+
+	/// Matches a status response once the job has failed, failing the call (optional)
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub fail_when: Option<FieldPredicate>,
+
+	/// JSONPath to the result in the finished job's status response (default: the whole
+	/// response)
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub result_path: Option<String>,
+
+	/// Delay before each status call in milliseconds (default: 1000)
+	#[serde(
+		default = "default_poll_interval_ms",
+		deserialize_with = "units::millis"
+	)]
+	pub interval_ms: u64,
+
+	/// Longest the job is waited for in milliseconds (default: 300000)
+	#[serde(default = "default_max_wait_ms", deserialize_with = "units::millis")]
+	pub max_wait_ms: u64,
+}
+
+fn default_poll_interval_ms() -> u64 {
+	JobPolling::DEFAULT_INTERVAL_MS
+}
+
+fn default_max_wait_ms() -> u64 {
+	JobPolling::DEFAULT_MAX_WAIT_MS
+}
+
+impl JobPolling {
+	/// Delay between status calls when intervalMs isn't set
+	pub const DEFAULT_INTERVAL_MS: u64 = 1000;
+
+	/// How long a job is waited for when maxWaitMs isn't set
+	pub const DEFAULT_MAX_WAIT_MS: u64 = 300_000;
+
+	pub fn interval(&self) -> Duration {
+		Duration::from_millis(self.interval_ms)
+	}
+
+	pub fn max_wait(&self) -> Duration {
+		Duration::from_millis(self.max_wait_ms)
+	}
+}
+
+/// Retry policy for transport-level failures when calling a backend tool
+///
+/// Unlike the Retry pattern, this only covers failures where no response was received,
+/// and is kept small and bounded.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TransportRetry {
+	/// Total attempts including the first (default: 2, capped at 3)
+	#[serde(default = "default_transport_attempts")]
+	pub max_attempts: u32,
+
+	/// Delay between attempts in milliseconds (default: 100)
+	#[serde(default = "default_transport_backoff_ms")]
+	pub backoff_ms: u64,
+}
+
+fn default_transport_attempts() -> u32 {
+	2
+}
+
+fn default_transport_backoff_ms() -> u64 {
+	100
+}
+
+impl Default for TransportRetry {
+	fn default() -> Self {
+		Self {
+			max_attempts: default_transport_attempts(),
+			backoff_ms: default_transport_backoff_ms(),
+		}
+	}
+}
+
+impl TransportRetry {
+	/// Upper bound on attempts, regardless of configuration
+	pub const MAX_ATTEMPTS: u32 = 3;
+
+	/// Effective number of attempts, clamped to `1..=MAX_ATTEMPTS`
+	pub fn attempts(&self) -> u32 {
+		self.max_attempts.clamp(1, Self::MAX_ATTEMPTS)
+	}
+
+	/// Delay between attempts
+	pub fn backoff(&self) -> Duration {
+		Duration::from_millis(self.backoff_ms)
+	}
+}
+
+/// Window in which identical calls from one caller share a single invocation
+///
+/// Cheaper than the Idempotent pattern: nothing is stored beyond the window, and calls
+/// are matched on their exact arguments rather than an idempotency key.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Debounce {
+	/// How long a successful result is handed to repeats of the call, in seconds
+	pub window_seconds: u32,
+}
+
+impl Debounce {
+	pub fn window(&self) -> Duration {
+		Duration::from_secs(self.window_seconds.into())
+	}
+}
+
+/// Priority of a tool's traffic, for load shedding
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ToolPriority {
+	High,
+	#[default]
+	Normal,
+	Low,
+}
+
+impl ToolPriority {
+	pub fn is_normal(&self) -> bool {
+		*self == ToolPriority::Normal
+	}
+}
+
+impl std::fmt::Display for ToolPriority {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		f.write_str(match self {
+			ToolPriority::High => "high",
+			ToolPriority::Normal => "normal",
+			ToolPriority::Low => "low",
+		})
+	}
+}
+
+/// Hints about a tool's behavior, advertised in its MCP tool annotations
+///
+/// A virtual tool's unset hints fall back to its backend tool's. The gateway itself only
+/// acts on `destructiveHint`, when destructive calls must be confirmed.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ToolAnnotations {
+	/// The tool doesn't modify its environment
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub read_only_hint: Option<bool>,
+	/// The tool may delete or overwrite, rather than only add (meaningful unless read-only)
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub destructive_hint: Option<bool>,
+	/// Repeating a call with the same arguments has no further effect
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub idempotent_hint: Option<bool>,
+	/// The tool interacts with an open set of external entities, such as the web
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub open_world_hint: Option<bool>,
+}
+
+impl ToolAnnotations {
+	/// Whether the tool is declared destructive. Unlike MCP clients, which assume tools are
+	/// destructive unless told otherwise, only an explicit `destructiveHint: true` counts.
+	pub fn is_destructive(&self) -> bool {
+		self.destructive_hint == Some(true) && self.read_only_hint != Some(true)
+	}
+}
+
+/// Idempotency keys accepted from callers of a composition
+///
+/// A caller sets a key under `agentgateway.dev/idempotencyKey` in the call's `_meta`; a
+/// later call from the same caller with the same key is a replay and is handled according
+/// to `on_duplicate` instead of running the composition again. Calls without a key run as
+/// usual.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Idempotency {
+	/// How long a key is remembered after its call succeeded, in seconds
+	pub ttl_seconds: u32,
+	/// What a replay gets
+	#[serde(default)]
+	pub on_duplicate: OnDuplicate,
+}
+
+impl Idempotency {
+	pub fn ttl(&self) -> Duration {
+		Duration::from_secs(self.ttl_seconds.into())
+	}
+}
+
+/// How the cost of a call to a tool is estimated
+///
+/// Costs are unitless; use whatever unit budgets and chargeback reports are kept in
+/// (e.g. USD or tokens).
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum CostModel {
+	/// Fixed cost per call
+	Static(f64),
+	/// Cost proportional to the size of the request and response payloads
+	Bytes(BytesCost),
+	/// Cost reported by the tool in its result
+	Reported(ReportedCost),
+}
+
+/// Payload-size based cost
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BytesCost {
+	/// Fixed cost per call
+	#[serde(default)]
+	pub per_call: f64,
+	/// Cost per KiB of serialized arguments
+	#[serde(default)]
+	pub per_kib_in: f64,
+	/// Cost per KiB of serialized result
+	#[serde(default)]
+	pub per_kib_out: f64,
+}
+
+/// Cost read from the tool's result
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReportedCost {
+	/// JSONPath to a number in the result (e.g. "$.usage.cost")
+	pub path: String,
+	/// Cost when the result doesn't contain one
+	#[serde(default)]
+	pub fallback: f64,
+}
+
+/// A sample call of a tool
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ToolExample {
+	/// What the example demonstrates
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub description: Option<String>,
+	/// Arguments of the call
+	pub input: serde_json::Value,
+	/// Result of the call
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub output: Option<serde_json::Value>,
 }
 
 /// Output transformation - enhanced version supporting all mapping features
@@ -98,26 +597,67 @@ pub struct SourceTool {
 pub struct OutputTransform {
 	/// Field name -> source mapping
 	pub mappings: HashMap<String, FieldSource>,
+
+	/// Mappings for results of other shapes (e.g. errors or partial results); the first
+	/// variant whose predicate matches the result is applied instead of `mappings`
+	#[serde(default, skip_serializing_if = "Vec::is_empty")]
+	pub variants: Vec<OutputVariant>,
+
+	/// Output field that tells the variants apart; each variant (and `mappings`, if set)
+	/// must map it to a distinct string literal
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub discriminator: Option<String>,
+}
+
+/// Mappings applied to results that match a predicate
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OutputVariant {
+	/// Predicate on the backend result selecting this variant
+	pub when: FieldPredicate,
+	/// Field name -> source mapping
+	pub mappings: HashMap<String, FieldSource>,
 }
 
 impl OutputTransform {
-	/// Create from a SchemaMapSpec
-	pub fn from_schema_map(schema_map: SchemaMapSpec) -> Self {
+	/// Create from field mappings
+	pub fn new(mappings: HashMap<String, FieldSource>) -> Self {
 		Self {
-			mappings: schema_map.mappings,
+			mappings,
+			variants: Vec::new(),
+			discriminator: None,
 		}
 	}
 
+	/// Create from a SchemaMapSpec
+	pub fn from_schema_map(schema_map: SchemaMapSpec) -> Self {
+		Self::new(schema_map.mappings)
+	}
+
 	/// Create an empty output transform
 	pub fn empty() -> Self {
-		Self {
-			mappings: HashMap::new(),
-		}
+		Self::new(HashMap::new())
+	}
+
+	/// Add a variant for results matching `when`
+	pub fn with_variant(
+		mut self,
+		when: FieldPredicate,
+		mappings: HashMap<String, FieldSource>,
+	) -> Self {
+		self.variants.push(OutputVariant { when, mappings });
+		self
+	}
+
+	/// Set the output field that tells the variants apart
+	pub fn with_discriminator(mut self, field: impl Into<String>) -> Self {
+		self.discriminator = Some(field.into());
+		self
 	}
 
 	/// Check if this transform has any mappings
 	pub fn is_empty(&self) -> bool {
-		self.mappings.is_empty()
+		self.mappings.is_empty() && self.variants.is_empty()
 	}
 }
 
@@ -218,6 +758,26 @@ pub struct OutputField {
 // Implementations
 // =============================================================================
 
+impl RegistryDefaults {
+	/// Check if no defaults are set
+	pub fn is_empty(&self) -> bool {
+		self.transport_retry.is_none() && self.resilience.is_none()
+	}
+
+	/// Fill in settings the tool leaves unset
+	pub fn apply(&self, tool: &mut ToolDefinition) {
+		match &mut tool.implementation {
+			ToolImplementation::Source(source) if source.transport_retry.is_none() => {
+				source.transport_retry = self.transport_retry.clone();
+			},
+			ToolImplementation::Spec(_) if tool.resilience.is_none() => {
+				tool.resilience = self.resilience.clone();
+			},
+			_ => {},
+		}
+	}
+}
+
 impl Registry {
 	/// Create an empty registry
 	pub fn new() -> Self {
@@ -229,6 +789,8 @@ impl Registry {
 		Self {
 			schema_version: default_schema_version(),
 			tools,
+			defaults: RegistryDefaults::default(),
+			targets: HashMap::new(),
 		}
 	}
 
@@ -237,9 +799,17 @@ impl Registry {
 		Self {
 			schema_version: default_schema_version(),
 			tools: tools.into_iter().map(ToolDefinition::from_legacy).collect(),
+			defaults: RegistryDefaults::default(),
+			targets: HashMap::new(),
 		}
 	}
 
+	/// Builder: set registry-wide defaults
+	pub fn with_defaults(mut self, defaults: RegistryDefaults) -> Self {
+		self.defaults = defaults;
+		self
+	}
+
 	/// Check if registry has any tools
 	pub fn is_empty(&self) -> bool {
 		self.tools.is_empty()
@@ -261,16 +831,31 @@ impl ToolDefinition {
 		Self {
 			name: name.into(),
 			description: None,
+			descriptions: HashMap::new(),
 			implementation: ToolImplementation::Source(SourceTool {
 				target: target.into(),
+				fallback_targets: Vec::new(),
 				tool: tool.into(),
 				defaults: HashMap::new(),
+				caller_defaults: HashMap::new(),
 				hide_fields: Vec::new(),
+				strict_args: false,
+				transport_retry: None,
+				pagination: None,
+				job_polling: None,
 			}),
 			input_schema: None,
 			output_transform: None,
 			output_schema: None,
+			result_envelope: false,
+			resilience: None,
+			cost: None,
+			debounce: None,
+			idempotency: None,
+			priority: ToolPriority::Normal,
 			version: None,
+			examples: Vec::new(),
+			annotations: None,
 			metadata: HashMap::new(),
 		}
 	}
@@ -280,11 +865,20 @@ impl ToolDefinition {
 		Self {
 			name: name.into(),
 			description: None,
+			descriptions: HashMap::new(),
 			implementation: ToolImplementation::Spec(spec),
 			input_schema: None,
 			output_transform: None,
 			output_schema: None,
+			result_envelope: false,
+			resilience: None,
+			cost: None,
+			debounce: None,
+			idempotency: None,
+			priority: ToolPriority::Normal,
 			version: None,
+			examples: Vec::new(),
+			annotations: None,
 			metadata: HashMap::new(),
 		}
 	}
@@ -305,26 +899,47 @@ impl ToolDefinition {
 					(name, source)
 				})
 				.collect();
-			OutputTransform { mappings }
+			OutputTransform::new(mappings)
 		});
 
 		Self {
 			name: legacy.name,
 			description: legacy.description,
+			descriptions: HashMap::new(),
 			implementation: ToolImplementation::Source(SourceTool {
 				target: legacy.source.target,
+				fallback_targets: Vec::new(),
 				tool: legacy.source.tool,
 				defaults: legacy.defaults,
+				caller_defaults: HashMap::new(),
 				hide_fields: legacy.hide_fields,
+				strict_args: false,
+				transport_retry: None,
+				pagination: None,
+				job_polling: None,
 			}),
 			input_schema: legacy.input_schema,
 			output_transform,
 			output_schema: None,
+			result_envelope: false,
+			resilience: None,
+			cost: None,
+			debounce: None,
+			idempotency: None,
+			priority: ToolPriority::Normal,
 			version: legacy.version,
+			examples: Vec::new(),
+			annotations: None,
 			metadata: legacy.metadata,
 		}
 	}
 
+	/// Builder: accept idempotency keys from callers
+	pub fn with_idempotency(mut self, idempotency: Idempotency) -> Self {
+		self.idempotency = Some(idempotency);
+		self
+	}
+
 	/// Builder: set description
 	pub fn with_description(mut self, desc: impl Into<String>) -> Self {
 		self.description = Some(desc.into());
@@ -343,6 +958,12 @@ impl ToolDefinition {
 		self
 	}
 
+	/// Builder: set behavior hints advertised to clients
+	pub fn with_annotations(mut self, annotations: ToolAnnotations) -> Self {
+		self.annotations = Some(annotations);
+		self
+	}
+
 	/// Check if this is a source-based tool
 	pub fn is_source(&self) -> bool {
 		matches!(self.implementation, ToolImplementation::Source(_))
@@ -385,11 +1006,61 @@ impl SourceTool {
 		self
 	}
 
+	/// Builder: add a default value for calls from `caller` (a JWT subject)
+	pub fn with_caller_default(
+		mut self,
+		caller: impl Into<String>,
+		key: impl Into<String>,
+		value: serde_json::Value,
+	) -> Self {
+		self
+			.caller_defaults
+			.entry(caller.into())
+			.or_default()
+			.insert(key.into(), value);
+		self
+	}
+
 	/// Builder: set hidden fields
 	pub fn with_hidden_fields(mut self, fields: Vec<String>) -> Self {
 		self.hide_fields = fields;
 		self
 	}
+
+	/// Builder: refuse arguments the effective input schema doesn't list
+	pub fn with_strict_args(mut self) -> Self {
+		self.strict_args = true;
+		self
+	}
+
+	/// Builder: retry transport-level failures
+	pub fn with_transport_retry(mut self, retry: TransportRetry) -> Self {
+		self.transport_retry = Some(retry);
+		self
+	}
+
+	/// Builder: follow the backend's cursor pagination
+	pub fn with_pagination(mut self, pagination: Pagination) -> Self {
+		self.pagination = Some(pagination);
+		self
+	}
+
+	/// Builder: poll the job each call submits until it finishes
+	pub fn with_job_polling(mut self, polling: JobPolling) -> Self {
+		self.job_polling = Some(Box::new(polling));
+		self
+	}
+
+	/// Builder: fail over to `targets`, in order
+	pub fn with_fallback_targets(mut self, targets: Vec<String>) -> Self {
+		self.fallback_targets = targets;
+		self
+	}
+
+	/// The target followed by its fallback targets, in the order calls try them
+	pub fn targets(&self) -> impl Iterator<Item = &str> {
+		std::iter::once(self.target.as_str()).chain(self.fallback_targets.iter().map(String::as_str))
+	}
 }
 
 // Legacy builder methods for VirtualToolDef
@@ -498,6 +1169,24 @@ mod tests {
 		let source = tool.source_tool().unwrap();
 		assert_eq!(source.target, "weather");
 		assert_eq!(source.tool, "fetch_weather");
+		assert!(source.transport_retry.is_none());
+	}
+
+	#[test]
+	fn test_parse_transport_retry() {
+		let json = r#"{
+			"name": "get_weather",
+			"source": {
+				"target": "weather",
+				"tool": "fetch_weather",
+				"transportRetry": { "maxAttempts": 10 }
+			}
+		}"#;
+
+		let tool: ToolDefinition = serde_json::from_str(json).unwrap();
+		let retry = tool.source_tool().unwrap().transport_retry.clone().unwrap();
+		assert_eq!(retry.backoff_ms, 100);
+		assert_eq!(retry.attempts(), TransportRetry::MAX_ATTEMPTS);
 	}
 
 	#[test]
@@ -554,6 +1243,27 @@ mod tests {
 		assert!(registry.tools[1].is_composition());
 	}
 
+	#[test]
+	fn test_parse_tool_needs_one_implementation() {
+		let parse = |tool: serde_json::Value| {
+			serde_json::from_value::<ToolDefinition>(tool)
+				.unwrap_err()
+				.to_string()
+		};
+		let source = serde_json::json!({ "target": "weather", "tool": "fetch_weather" });
+		let spec = serde_json::json!({ "pipeline": { "steps": [] } });
+
+		let err = parse(serde_json::json!({ "name": "t" }));
+		assert!(err.contains("needs a `source` or a `spec`"), "{err}");
+		let err = parse(serde_json::json!({ "name": "t", "source": source, "spec": spec }));
+		assert!(err.contains("has both a `source` and a `spec`"), "{err}");
+		let err = parse(serde_json::json!({
+			"name": "t",
+			"spec": { "scatterGather": { "targets": [{}], "aggregation": { "ops": [] } } }
+		}));
+		assert!(err.contains("needs a `tool` or a `pattern`"), "{err}");
+	}
+
 	#[test]
 	fn test_parse_tool_with_output_transform() {
 		let json = r#"{
@@ -616,6 +1326,9 @@ mod tests {
 					name: "search".to_string(),
 				}),
 				input: None,
+				session_set: None,
+				retry: None,
+				cache: None,
 			}],
 		});
 
@@ -650,6 +1363,33 @@ mod tests {
 		assert_eq!(registry.schema_version, "2.0");
 	}
 
+	#[test]
+	fn test_parse_registry_defaults() {
+		let registry: Registry = serde_json::from_value(serde_json::json!({
+			"defaults": {
+				"transportRetry": { "maxAttempts": 3 },
+				"resilience": { "timeoutMs": 5000, "retries": 2 }
+			},
+			"tools": []
+		}))
+		.unwrap();
+		assert!(registry.defaults.transport_retry.is_some());
+		let resilience = registry.defaults.resilience.unwrap();
+		assert_eq!(resilience.timeout_ms, Some(5000));
+		assert_eq!(resilience.retries, Some(2));
+
+		// Policies without a registry-wide default are rejected, not silently ignored
+		for key in ["cache", "rateLimit", "deadLetter"] {
+			let err = serde_json::from_value::<Registry>(serde_json::json!({
+				"defaults": { key: {} },
+				"tools": []
+			}))
+			.unwrap_err()
+			.to_string();
+			assert!(err.contains(&format!("unknown field `{key}`")), "{err}");
+		}
+	}
+
 	#[test]
 	fn test_registry_methods() {
 		let empty = Registry::new();
@@ -0,0 +1,2025 @@
+// Conversions from the canonical registry.proto format
+//
+// Registries may be authored in the canonical protobuf format (registry.proto)
+// instead of the serde JSON model. These conversions map the generated proto
+// types onto the runtime registry types so both formats compile identically.
+
+use std::collections::HashMap;
+
+use base64::Engine as _;
+use base64::engine::general_purpose::STANDARD;
+
+use super::error::RegistryError;
+use super::patterns::{
+	AggregationOp, AggregationStrategy, AssertSeverity, AssertSpec, BackoffStrategy, CacheSpec,
+	CapabilityRouterSpec, CircuitBreakerShorthand, CircuitBreakerSpec, ClaimCheckSpec,
+	CoalesceSource, CoerceSource, CoerceType, CompositionCall, ConcatSource, ConditionalSource,
+	ConfidenceAggregatorSpec, ConfidenceStrategy, ConstructBinding, DataBinding, DeadLetterSpec,
+	DedupKeepStrategy, DedupeOp, EmbeddingScorer, EnricherSpec, EnrichmentSource, ExponentialBackoff,
+	FieldPredicate, FieldSource, FilterSpec, FixedBackoff, IdempotentSpec, InputBinding, JsonPatchOp,
+	JsonPatchOperation, LimitOp, LinearBackoff, LiteralValue, LlmScorer, MapEachInner, MapEachSpec,
+	MapEachStream, MergeStrategy, NegativeCacheSpec, OnDuplicate, OnExceeded, PatchSpec, PatternSpec,
+	PipelineSpec, PipelineStep, PredicateValue, RankScorer, RankSpec, RecipientListSpec, Resilience,
+	RetrySpec, RouteCase, RouterSpec, SagaSpec, SagaStep, SampleSpec, ScatterGatherSpec,
+	ScatterOperation, ScatterTarget, SchemaMapSpec, ScriptLimits, ScriptSpec, SemanticDedupSpec,
+	SessionBinding, SortOp, StepBinding, StepCache, StepOperation, StepRetry, TapPoint, TapTarget,
+	TargetSample, TemplateBinding, TemplateSource, ThrottleSpec, ThrottleStrategy, TimeoutSpec,
+	TokenBudgetOp, TokenizerKind, ToolCall, WeightedField, WeightedSource, WireTapSpec,
+	default_failure_threshold, default_failure_window_seconds, default_llm_timeout_ms,
+	default_reset_timeout_seconds, default_sample_max_tokens, default_sample_timeout_ms,
+};
+use super::types::{
+	BytesCost, CostModel, Debounce, Idempotency, JobPolling, OutputTransform, OutputVariant,
+	Pagination, Registry, RegistryDefaults, ReportedCost, SourceTool, TargetPolicy, ToolAnnotations,
+	ToolDefinition, ToolExample, ToolImplementation, ToolPriority, TransportRetry,
+};
+
+#[allow(warnings)]
+#[allow(clippy::all)]
+pub mod proto {
+	tonic::include_proto!("agentgateway.dev.registry");
+}
+
+fn missing(field: &str) -> RegistryError {
+	RegistryError::InvalidProto(format!("missing required field '{}'", field))
+}
+
+fn required<'a, T>(value: Option<&'a T>, field: &str) -> Result<&'a T, RegistryError> {
+	value.ok_or_else(|| missing(field))
+}
+
+fn value_to_json(value: &prost_wkt_types::Value) -> Result<serde_json::Value, RegistryError> {
+	Ok(serde_json::to_value(value)?)
+}
+
+fn values_to_json(
+	values: &HashMap<String, prost_wkt_types::Value>,
+) -> Result<HashMap<String, serde_json::Value>, RegistryError> {
+	values
+		.iter()
+		.map(|(k, v)| Ok((k.clone(), value_to_json(v)?)))
+		.collect()
+}
+
+fn enum_value<E: TryFrom<i32>>(value: i32, name: &str) -> Result<E, RegistryError> {
+	E::try_from(value)
+		.map_err(|_| RegistryError::InvalidProto(format!("unknown {} value: {}", name, value)))
+}
+
+fn boxed_op(op: &proto::StepOperation) -> Result<Box<StepOperation>, RegistryError> {
+	Ok(Box::new(StepOperation::try_from(op)?))
+}
+
+// =============================================================================
+// Registry and tool definitions
+// =============================================================================
+
+impl TryFrom<&proto::Registry> for Registry {
+	type Error = RegistryError;
+
+	fn try_from(s: &proto::Registry) -> Result<Self, Self::Error> {
+		let tools = s
+			.tools
+			.iter()
+			.map(ToolDefinition::try_from)
+			.collect::<Result<Vec<_>, _>>()?;
+		let mut registry = Registry::with_tool_definitions(tools);
+		if !s.schema_version.is_empty() {
+			registry.schema_version = s.schema_version.clone();
+		}
+		if let Some(defaults) = &s.defaults {
+			registry.defaults = RegistryDefaults {
+				transport_retry: defaults.transport_retry.as_ref().map(TransportRetry::from),
+				resilience: defaults
+					.resilience
+					.as_ref()
+					.map(Resilience::try_from)
+					.transpose()?,
+			};
+		}
+		for (target, policy) in &s.targets {
+			registry.targets.insert(
+				target.clone(),
+				TargetPolicy {
+					headers: policy.headers.clone(),
+					meta: values_to_json(&policy.meta)?.into_iter().collect(),
+				},
+			);
+		}
+		Ok(registry)
+	}
+}
+
+impl TryFrom<&proto::ToolDefinition> for ToolDefinition {
+	type Error = RegistryError;
+
+	fn try_from(s: &proto::ToolDefinition) -> Result<Self, Self::Error> {
+		use proto::tool_definition::Implementation;
+		let implementation = match &s.implementation {
+			Some(Implementation::Source(src)) => ToolImplementation::Source(SourceTool::try_from(src)?),
+			Some(Implementation::Spec(spec)) => ToolImplementation::Spec(PatternSpec::try_from(spec)?),
+			None => {
+				return Err(RegistryError::InvalidProto(format!(
+					"tool '{}' has no implementation (expected source or spec)",
+					s.name
+				)));
+			},
+		};
+		let input_schema = s
+			.input_schema
+			.as_ref()
+			.map(serde_json::to_value)
+			.transpose()?;
+		let output_transform = s
+			.output_transform
+			.as_ref()
+			.map(OutputTransform::try_from)
+			.transpose()?;
+		let cost = s.cost.as_ref().map(CostModel::try_from).transpose()?;
+		let examples = s
+			.examples
+			.iter()
+			.map(ToolExample::try_from)
+			.collect::<Result<_, _>>()?;
+
+		Ok(Self {
+			name: s.name.clone(),
+			description: s.description.clone(),
+			descriptions: s.descriptions.clone(),
+			implementation,
+			input_schema,
+			output_transform,
+			output_schema: None,
+			result_envelope: s.result_envelope,
+			resilience: s
+				.resilience
+				.as_ref()
+				.map(Resilience::try_from)
+				.transpose()?,
+			cost,
+			debounce: s.debounce.as_ref().map(Debounce::from),
+			idempotency: s
+				.idempotency
+				.as_ref()
+				.map(Idempotency::try_from)
+				.transpose()?,
+			version: s.version.clone(),
+			examples,
+			annotations: s.annotations.as_ref().map(ToolAnnotations::from),
+			priority: match enum_value(s.priority, "ToolPriority")? {
+				proto::ToolPriority::Unspecified => ToolPriority::default(),
+				proto::ToolPriority::High => ToolPriority::High,
+				proto::ToolPriority::Normal => ToolPriority::Normal,
+				proto::ToolPriority::Low => ToolPriority::Low,
+			},
+			metadata: values_to_json(&s.metadata)?,
+		})
+	}
+}
+
+impl TryFrom<&proto::Resilience> for Resilience {
+	type Error = RegistryError;
+
+	fn try_from(r: &proto::Resilience) -> Result<Self, Self::Error> {
+		Ok(Self {
+			timeout_ms: r.timeout_ms,
+			retries: r.retries,
+			backoff: r
+				.backoff
+				.as_ref()
+				.map(BackoffStrategy::try_from)
+				.transpose()?,
+			circuit_breaker: r
+				.circuit_breaker
+				.as_ref()
+				.map(|cb| CircuitBreakerShorthand {
+					store: cb.store.clone(),
+					failure_threshold: cb
+						.failure_threshold
+						.unwrap_or_else(default_failure_threshold),
+					failure_window_seconds: cb
+						.failure_window_seconds
+						.unwrap_or_else(default_failure_window_seconds),
+					reset_timeout_seconds: cb
+						.reset_timeout_seconds
+						.unwrap_or_else(default_reset_timeout_seconds),
+				}),
+		})
+	}
+}
+
+impl TryFrom<&proto::ToolExample> for ToolExample {
+	type Error = RegistryError;
+
+	fn try_from(e: &proto::ToolExample) -> Result<Self, Self::Error> {
+		Ok(Self {
+			description: e.description.clone(),
+			input: value_to_json(required(e.input.as_ref(), "input")?)?,
+			output: e.output.as_ref().map(value_to_json).transpose()?,
+		})
+	}
+}
+
+impl TryFrom<&proto::SourceTool> for SourceTool {
+	type Error = RegistryError;
+
+	fn try_from(s: &proto::SourceTool) -> Result<Self, Self::Error> {
+		Ok(Self {
+			target: s.server.clone(),
+			fallback_targets: s.fallback_servers.clone(),
+			tool: s.tool.clone(),
+			defaults: values_to_json(&s.defaults)?,
+			caller_defaults: s
+				.caller_defaults
+				.iter()
+				.map(|(caller, d)| Ok((caller.clone(), values_to_json(&d.defaults)?)))
+				.collect::<Result<_, RegistryError>>()?,
+			hide_fields: s.hide_fields.clone(),
+			strict_args: s.strict_args,
+			transport_retry: s.transport_retry.as_ref().map(TransportRetry::from),
+			pagination: s.pagination.as_ref().map(Pagination::from),
+			job_polling: s
+				.job_polling
+				.as_ref()
+				.map(|p| JobPolling::try_from(p).map(Box::new))
+				.transpose()?,
+		})
+	}
+}
+
+impl TryFrom<&proto::Idempotency> for Idempotency {
+	type Error = RegistryError;
+
+	fn try_from(i: &proto::Idempotency) -> Result<Self, Self::Error> {
+		Ok(Self {
+			ttl_seconds: i.ttl_seconds,
+			on_duplicate: on_duplicate(i.on_duplicate)?,
+		})
+	}
+}
+
+impl From<&proto::Debounce> for Debounce {
+	fn from(d: &proto::Debounce) -> Self {
+		Self {
+			window_seconds: d.window_seconds,
+		}
+	}
+}
+
+impl From<&proto::ToolAnnotations> for ToolAnnotations {
+	fn from(a: &proto::ToolAnnotations) -> Self {
+		Self {
+			read_only_hint: a.read_only_hint,
+			destructive_hint: a.destructive_hint,
+			idempotent_hint: a.idempotent_hint,
+			open_world_hint: a.open_world_hint,
+		}
+	}
+}
+
+impl From<&proto::Pagination> for Pagination {
+	fn from(p: &proto::Pagination) -> Self {
+		Self {
+			cursor_field: p.cursor_field.clone(),
+			cursor_path: p.cursor_path.clone(),
+			items_path: p.items_path.clone(),
+			page_size_field: p.page_size_field.clone(),
+			page_size: p.page_size,
+			max_pages: if p.max_pages == 0 {
+				Pagination::DEFAULT_MAX_PAGES
+			} else {
+				p.max_pages
+			},
+		}
+	}
+}
+
+impl TryFrom<&proto::JobPolling> for JobPolling {
+	type Error = RegistryError;
+
+	fn try_from(p: &proto::JobPolling) -> Result<Self, Self::Error> {
+		Ok(Self {
+			status_tool: p.status_tool.clone(),
+			job_id_path: p.job_id_path.clone(),
+			job_id_field: p.job_id_field.clone(),
+			complete_when: FieldPredicate::try_from(required(
+				p.complete_when.as_ref(),
+				"complete_when",
+			)?)?,
+			fail_when: p
+				.fail_when
+				.as_ref()
+				.map(FieldPredicate::try_from)
+				.transpose()?,
+			result_path: p.result_path.clone(),
+			interval_ms: if p.interval_ms == 0 {
+				JobPolling::DEFAULT_INTERVAL_MS
+			} else {
+				p.interval_ms
+			},
+			max_wait_ms: if p.max_wait_ms == 0 {
+				JobPolling::DEFAULT_MAX_WAIT_MS
+			} else {
+				p.max_wait_ms
+			},
+		})
+	}
+}
+
+impl From<&proto::TransportRetry> for TransportRetry {
+	fn from(r: &proto::TransportRetry) -> Self {
+		Self {
+			max_attempts: if r.max_attempts == 0 {
+				TransportRetry::default().max_attempts
+			} else {
+				r.max_attempts
+			},
+			backoff_ms: r.backoff_ms.unwrap_or(TransportRetry::default().backoff_ms),
+		}
+	}
+}
+
+impl TryFrom<&proto::CostModel> for CostModel {
+	type Error = RegistryError;
+
+	fn try_from(c: &proto::CostModel) -> Result<Self, Self::Error> {
+		use proto::cost_model::Model;
+		match &c.model {
+			Some(Model::Static(cost)) => Ok(CostModel::Static(*cost)),
+			Some(Model::Bytes(b)) => Ok(CostModel::Bytes(BytesCost {
+				per_call: b.per_call,
+				per_kib_in: b.per_kib_in,
+				per_kib_out: b.per_kib_out,
+			})),
+			Some(Model::Reported(r)) => Ok(CostModel::Reported(ReportedCost {
+				path: r.path.clone(),
+				fallback: r.fallback,
+			})),
+			None => Err(RegistryError::InvalidProto(
+				"cost model is empty (expected static, bytes or reported)".to_string(),
+			)),
+		}
+	}
+}
+
+impl TryFrom<&proto::OutputTransform> for OutputTransform {
+	type Error = RegistryError;
+
+	fn try_from(s: &proto::OutputTransform) -> Result<Self, Self::Error> {
+		Ok(Self {
+			mappings: convert_mappings(&s.mappings)?,
+			variants: s
+				.variants
+				.iter()
+				.map(|v| {
+					Ok(OutputVariant {
+						when: FieldPredicate::try_from(required(v.when.as_ref(), "when")?)?,
+						mappings: convert_mappings(&v.mappings)?,
+					})
+				})
+				.collect::<Result<_, RegistryError>>()?,
+			discriminator: s.discriminator.clone(),
+		})
+	}
+}
+
+// =============================================================================
+// Pattern spec
+// =============================================================================
+
+impl TryFrom<&proto::PatternSpec> for PatternSpec {
+	type Error = RegistryError;
+
+	fn try_from(s: &proto::PatternSpec) -> Result<Self, Self::Error> {
+		use proto::pattern_spec::Pattern;
+		// prost decodes unknown oneof variants as None, so a missing pattern may also be
+		// one this gateway does not understand. Never fall back to an empty pipeline.
+		let Some(pattern) = s.pattern.as_ref() else {
+			return Err(RegistryError::InvalidProto(
+				"pattern spec has no recognized pattern set".to_string(),
+			));
+		};
+		Ok(match pattern {
+			// Stateless patterns
+			Pattern::Pipeline(p) => PatternSpec::Pipeline(PipelineSpec::try_from(p)?),
+			Pattern::ScatterGather(sg) => PatternSpec::ScatterGather(ScatterGatherSpec::try_from(sg)?),
+			Pattern::Filter(f) => PatternSpec::Filter(FilterSpec::try_from(f)?),
+			Pattern::SchemaMap(sm) => PatternSpec::SchemaMap(SchemaMapSpec::try_from(sm)?),
+			Pattern::MapEach(me) => PatternSpec::MapEach(MapEachSpec::try_from(me.as_ref())?),
+			Pattern::Rank(r) => PatternSpec::Rank(RankSpec::try_from(r)?),
+			// Stateful patterns
+			Pattern::Retry(r) => PatternSpec::Retry(RetrySpec::try_from(r.as_ref())?),
+			Pattern::Timeout(t) => PatternSpec::Timeout(TimeoutSpec::try_from(t.as_ref())?),
+			Pattern::Cache(c) => PatternSpec::Cache(CacheSpec::try_from(c.as_ref())?),
+			Pattern::Idempotent(i) => PatternSpec::Idempotent(IdempotentSpec::try_from(i.as_ref())?),
+			Pattern::CircuitBreaker(cb) => {
+				PatternSpec::CircuitBreaker(CircuitBreakerSpec::try_from(cb.as_ref())?)
+			},
+			Pattern::DeadLetter(dl) => PatternSpec::DeadLetter(DeadLetterSpec::try_from(dl.as_ref())?),
+			Pattern::Saga(s) => PatternSpec::Saga(SagaSpec::try_from(s)?),
+			Pattern::ClaimCheck(cc) => PatternSpec::ClaimCheck(ClaimCheckSpec::try_from(cc.as_ref())?),
+			Pattern::Throttle(t) => PatternSpec::Throttle(ThrottleSpec::try_from(t.as_ref())?),
+			// Vision patterns
+			Pattern::Router(r) => PatternSpec::Router(RouterSpec::try_from(r.as_ref())?),
+			Pattern::Enricher(e) => PatternSpec::Enricher(EnricherSpec::try_from(e)?),
+			Pattern::WireTap(w) => PatternSpec::WireTap(WireTapSpec::try_from(w.as_ref())?),
+			Pattern::RecipientList(rl) => PatternSpec::RecipientList(RecipientListSpec::try_from(rl)?),
+			Pattern::CapabilityRouter(cr) => {
+				PatternSpec::CapabilityRouter(CapabilityRouterSpec::try_from(cr.as_ref())?)
+			},
+			Pattern::SemanticDedup(sd) => PatternSpec::SemanticDedup(SemanticDedupSpec::try_from(sd)?),
+			Pattern::ConfidenceAggregator(ca) => {
+				PatternSpec::ConfidenceAggregator(ConfidenceAggregatorSpec::try_from(ca)?)
+			},
+		})
+	}
+}
+
+// =============================================================================
+// Pipeline
+// =============================================================================
+
+impl TryFrom<&proto::PipelineSpec> for PipelineSpec {
+	type Error = RegistryError;
+
+	fn try_from(s: &proto::PipelineSpec) -> Result<Self, Self::Error> {
+		let steps = s
+			.steps
+			.iter()
+			.map(PipelineStep::try_from)
+			.collect::<Result<Vec<_>, _>>()?;
+		Ok(Self { steps })
+	}
+}
+
+impl TryFrom<&proto::PipelineStep> for PipelineStep {
+	type Error = RegistryError;
+
+	fn try_from(s: &proto::PipelineStep) -> Result<Self, Self::Error> {
+		Ok(Self {
+			id: s.id.clone(),
+			operation: StepOperation::try_from(required(s.operation.as_ref(), "operation")?)?,
+			input: s.input.as_ref().map(DataBinding::try_from).transpose()?,
+			session_set: s.session_set.as_ref().map(SessionBinding::from),
+			retry: s.retry.as_ref().map(StepRetry::from),
+			cache: s.cache.as_ref().map(StepCache::from),
+		})
+	}
+}
+
+impl TryFrom<&proto::StepOperation> for StepOperation {
+	type Error = RegistryError;
+
+	fn try_from(s: &proto::StepOperation) -> Result<Self, Self::Error> {
+		use proto::step_operation::Op;
+		match required(s.op.as_ref(), "op")? {
+			Op::Tool(tc) => Ok(StepOperation::Tool(ToolCall {
+				name: tc.name.clone(),
+			})),
+			Op::Pattern(p) => Ok(StepOperation::Pattern(Box::new(PatternSpec::try_from(
+				p.as_ref(),
+			)?))),
+			Op::Composition(cc) => {
+				let mappings = |m: &HashMap<String, proto::FieldSource>| {
+					(!m.is_empty()).then(|| convert_mappings(m)).transpose()
+				};
+				Ok(StepOperation::Composition(CompositionCall {
+					name: cc.name.clone(),
+					input: mappings(&cc.input)?,
+					output: mappings(&cc.output)?,
+				}))
+			},
+			Op::Agent(a) => Err(RegistryError::InvalidProto(format!(
+				"agent step operations are not supported (agent '{}')",
+				a.name
+			))),
+			Op::Assert(a) => Ok(StepOperation::Assert(AssertSpec {
+				predicate: FieldPredicate::try_from(required(a.predicate.as_ref(), "predicate")?)?,
+				message: a.message.clone(),
+				severity: match enum_value(a.severity, "AssertSeverity")? {
+					proto::AssertSeverity::Unspecified => AssertSeverity::default(),
+					proto::AssertSeverity::Fail => AssertSeverity::Fail,
+					proto::AssertSeverity::Warn => AssertSeverity::Warn,
+				},
+			})),
+			Op::Constant(v) => Ok(StepOperation::Constant(value_to_json(v)?)),
+			Op::Sample(sa) => Ok(StepOperation::Sample(SampleSpec {
+				prompt: sa.prompt.clone(),
+				system_prompt: sa.system_prompt.clone(),
+				max_tokens: sa.max_tokens.unwrap_or_else(default_sample_max_tokens),
+				temperature: sa.temperature,
+				model_hints: sa.model_hints.clone(),
+				json: sa.json,
+				timeout_ms: sa.timeout_ms.unwrap_or_else(default_sample_timeout_ms),
+			})),
+			Op::Script(sc) => {
+				let defaults = ScriptLimits::default();
+				let limits = sc.limits.as_ref();
+				Ok(StepOperation::Script(ScriptSpec {
+					wasm: STANDARD.encode(&sc.wasm),
+					entrypoint: sc
+						.entrypoint
+						.clone()
+						.unwrap_or_else(|| "transform".to_string()),
+					limits: ScriptLimits {
+						fuel: limits.and_then(|l| l.fuel).unwrap_or(defaults.fuel),
+						memory_bytes: limits
+							.and_then(|l| l.memory_bytes)
+							.unwrap_or(defaults.memory_bytes),
+						output_bytes: limits
+							.and_then(|l| l.output_bytes)
+							.unwrap_or(defaults.output_bytes),
+						timeout_ms: limits
+							.and_then(|l| l.timeout_ms)
+							.unwrap_or(defaults.timeout_ms),
+					},
+					module: None,
+				}))
+			},
+			Op::Patch(pa) => {
+				let merge_patch = pa.merge_patch.as_ref().map(value_to_json).transpose()?;
+				let json_patch = match merge_patch {
+					Some(_) if !pa.json_patch.is_empty() => {
+						return Err(RegistryError::InvalidProto(
+							"patch step sets both json_patch and merge_patch".to_string(),
+						));
+					},
+					Some(_) => None,
+					None => Some(
+						pa.json_patch
+							.iter()
+							.map(JsonPatchOperation::try_from)
+							.collect::<Result<_, _>>()?,
+					),
+				};
+				Ok(StepOperation::Patch(PatchSpec {
+					json_patch,
+					merge_patch,
+					vars: pa.vars.clone(),
+				}))
+			},
+		}
+	}
+}
+
+impl TryFrom<&proto::JsonPatchOperation> for JsonPatchOperation {
+	type Error = RegistryError;
+
+	fn try_from(o: &proto::JsonPatchOperation) -> Result<Self, Self::Error> {
+		Ok(JsonPatchOperation {
+			op: match enum_value(o.op, "JsonPatchOp")? {
+				proto::JsonPatchOp::Unspecified => return Err(missing("op")),
+				proto::JsonPatchOp::Add => JsonPatchOp::Add,
+				proto::JsonPatchOp::Remove => JsonPatchOp::Remove,
+				proto::JsonPatchOp::Replace => JsonPatchOp::Replace,
+				proto::JsonPatchOp::Move => JsonPatchOp::Move,
+				proto::JsonPatchOp::Copy => JsonPatchOp::Copy,
+				proto::JsonPatchOp::Test => JsonPatchOp::Test,
+			},
+			path: o.path.clone(),
+			from: o.from.clone(),
+			value: o.value.as_ref().map(value_to_json).transpose()?,
+		})
+	}
+}
+
+impl TryFrom<&proto::DataBinding> for DataBinding {
+	type Error = RegistryError;
+
+	fn try_from(s: &proto::DataBinding) -> Result<Self, Self::Error> {
+		use proto::data_binding::Source;
+		Ok(match required(s.source.as_ref(), "source")? {
+			Source::Input(i) => DataBinding::Input(InputBinding {
+				path: i.path.clone(),
+			}),
+			Source::Step(st) => DataBinding::Step(StepBinding {
+				step_id: st.step_id.clone(),
+				path: st.path.clone(),
+			}),
+			Source::Constant(v) => DataBinding::Constant(value_to_json(v)?),
+			Source::Session(sb) => DataBinding::Session(SessionBinding::from(sb)),
+			Source::Construct(cb) => DataBinding::Construct(ConstructBinding {
+				fields: bindings(&cb.fields)?,
+			}),
+			Source::Template(tb) => DataBinding::Template(TemplateBinding {
+				template: tb.template.clone(),
+				vars: bindings(&tb.vars)?,
+			}),
+		})
+	}
+}
+
+fn bindings(
+	bindings: &HashMap<String, proto::DataBinding>,
+) -> Result<HashMap<String, DataBinding>, RegistryError> {
+	bindings
+		.iter()
+		.map(|(name, b)| Ok((name.clone(), DataBinding::try_from(b)?)))
+		.collect()
+}
+
+impl From<&proto::StepRetry> for StepRetry {
+	fn from(s: &proto::StepRetry) -> Self {
+		Self {
+			max_attempts: s.max_attempts,
+			backoff_ms: s.backoff_ms,
+		}
+	}
+}
+
+impl From<&proto::StepCache> for StepCache {
+	fn from(s: &proto::StepCache) -> Self {
+		let key_paths = if s.key_paths.is_empty() {
+			vec!["$".to_string()]
+		} else {
+			s.key_paths.clone()
+		};
+		Self {
+			ttl_seconds: s.ttl_seconds,
+			key_paths,
+		}
+	}
+}
+
+impl From<&proto::SessionBinding> for SessionBinding {
+	fn from(s: &proto::SessionBinding) -> Self {
+		Self {
+			key: s.key.clone(),
+			path: if s.path.is_empty() {
+				"$".to_string()
+			} else {
+				s.path.clone()
+			},
+		}
+	}
+}
+
+// =============================================================================
+// Scatter-Gather
+// =============================================================================
+
+impl TryFrom<&proto::ScatterGatherSpec> for ScatterGatherSpec {
+	type Error = RegistryError;
+
+	fn try_from(s: &proto::ScatterGatherSpec) -> Result<Self, Self::Error> {
+		let targets = s
+			.targets
+			.iter()
+			.map(ScatterTarget::try_from)
+			.collect::<Result<Vec<_>, _>>()?;
+		let aggregation = s
+			.aggregation
+			.as_ref()
+			.map(AggregationStrategy::try_from)
+			.transpose()?
+			.unwrap_or_default();
+		Ok(Self {
+			targets,
+			aggregation,
+			timeout_ms: s.timeout_ms,
+			fail_fast: s.fail_fast,
+			sample: s.sample.as_ref().map(|sample| TargetSample {
+				always: sample.always,
+				draw: sample.draw,
+				seed: sample.seed,
+			}),
+		})
+	}
+}
+
+impl TryFrom<&proto::ScatterTarget> for ScatterTarget {
+	type Error = RegistryError;
+
+	fn try_from(s: &proto::ScatterTarget) -> Result<Self, Self::Error> {
+		use proto::scatter_target::Target;
+		let operation = match required(s.target.as_ref(), "target")? {
+			Target::Tool(name) => ScatterOperation::Tool(name.clone()),
+			Target::Pattern(p) => ScatterOperation::Pattern(Box::new(PatternSpec::try_from(p)?)),
+		};
+		Ok(Self {
+			operation,
+			retry: s.retry.as_ref().map(StepRetry::from),
+			weight: s.weight,
+		})
+	}
+}
+
+impl TryFrom<&proto::AggregationStrategy> for AggregationStrategy {
+	type Error = RegistryError;
+
+	fn try_from(s: &proto::AggregationStrategy) -> Result<Self, Self::Error> {
+		let ops = s
+			.ops
+			.iter()
+			.map(AggregationOp::try_from)
+			.collect::<Result<Vec<_>, _>>()?;
+		Ok(Self { ops })
+	}
+}
+
+impl TryFrom<&proto::AggregationOp> for AggregationOp {
+	type Error = RegistryError;
+
+	fn try_from(s: &proto::AggregationOp) -> Result<Self, Self::Error> {
+		use proto::aggregation_op::Op;
+		Ok(match required(s.op.as_ref(), "op")? {
+			Op::Flatten(b) => AggregationOp::Flatten(*b),
+			Op::Sort(so) => AggregationOp::Sort(SortOp {
+				field: so.field.clone(),
+				order: so.order.clone(),
+			}),
+			Op::Dedupe(d) => AggregationOp::Dedupe(DedupeOp {
+				field: d.field.clone(),
+			}),
+			Op::Limit(l) => AggregationOp::Limit(LimitOp { count: l.count }),
+			Op::Concat(b) => AggregationOp::Concat(*b),
+			Op::Merge(b) => AggregationOp::Merge(*b),
+			Op::TokenBudget(t) => AggregationOp::TokenBudget(TokenBudgetOp {
+				max_tokens: t.max_tokens,
+				text_field: t.text_field.clone(),
+				truncate: t.truncate,
+				tokenizer: match enum_value(t.tokenizer, "Tokenizer")? {
+					proto::Tokenizer::Unspecified => TokenizerKind::default(),
+					proto::Tokenizer::Chars => TokenizerKind::Chars,
+					proto::Tokenizer::Words => TokenizerKind::Words,
+				},
+			}),
+		})
+	}
+}
+
+// =============================================================================
+// Filter
+// =============================================================================
+
+impl TryFrom<&proto::FilterSpec> for FilterSpec {
+	type Error = RegistryError;
+
+	fn try_from(s: &proto::FilterSpec) -> Result<Self, Self::Error> {
+		Ok(Self {
+			predicate: FieldPredicate::try_from(required(s.predicate.as_ref(), "predicate")?)?,
+		})
+	}
+}
+
+impl TryFrom<&proto::FieldPredicate> for FieldPredicate {
+	type Error = RegistryError;
+
+	fn try_from(s: &proto::FieldPredicate) -> Result<Self, Self::Error> {
+		Ok(Self {
+			field: s.field.clone(),
+			op: s.op.clone(),
+			value: PredicateValue::try_from(required(s.value.as_ref(), "value")?)?,
+		})
+	}
+}
+
+impl TryFrom<&proto::PredicateValue> for PredicateValue {
+	type Error = RegistryError;
+
+	fn try_from(s: &proto::PredicateValue) -> Result<Self, Self::Error> {
+		use proto::predicate_value::Value;
+		Ok(match required(s.value.as_ref(), "value")? {
+			Value::StringValue(v) => PredicateValue::StringValue(v.clone()),
+			Value::NumberValue(v) => PredicateValue::NumberValue(*v),
+			Value::BoolValue(v) => PredicateValue::BoolValue(*v),
+			Value::NullValue(v) => PredicateValue::NullValue(*v),
+			Value::ListValue(list) => PredicateValue::ListValue(
+				list
+					.values
+					.iter()
+					.map(PredicateValue::try_from)
+					.collect::<Result<Vec<_>, _>>()?,
+			),
+		})
+	}
+}
+
+// =============================================================================
+// Schema Map
+// =============================================================================
+
+fn convert_mappings(
+	mappings: &HashMap<String, proto::FieldSource>,
+) -> Result<HashMap<String, FieldSource>, RegistryError> {
+	mappings
+		.iter()
+		.map(|(k, v)| Ok((k.clone(), FieldSource::try_from(v)?)))
+		.collect()
+}
+
+impl TryFrom<&proto::SchemaMapSpec> for SchemaMapSpec {
+	type Error = RegistryError;
+
+	fn try_from(s: &proto::SchemaMapSpec) -> Result<Self, Self::Error> {
+		Ok(Self {
+			mappings: convert_mappings(&s.mappings)?,
+		})
+	}
+}
+
+impl TryFrom<&proto::FieldSource> for FieldSource {
+	type Error = RegistryError;
+
+	fn try_from(s: &proto::FieldSource) -> Result<Self, Self::Error> {
+		use proto::field_source::Source;
+		Ok(match required(s.source.as_ref(), "source")? {
+			Source::Path(p) => FieldSource::Path(p.clone()),
+			Source::Literal(l) => FieldSource::Literal(LiteralValue::try_from(l)?),
+			Source::Coalesce(c) => FieldSource::Coalesce(CoalesceSource {
+				paths: c.paths.clone(),
+			}),
+			Source::Template(t) => FieldSource::Template(TemplateSource {
+				template: t.template.clone(),
+				vars: t.vars.clone(),
+			}),
+			Source::Concat(c) => FieldSource::Concat(ConcatSource {
+				paths: c.paths.clone(),
+				separator: c.separator.clone(),
+			}),
+			Source::Nested(n) => FieldSource::Nested(Box::new(SchemaMapSpec::try_from(n)?)),
+			Source::Conditional(c) => FieldSource::Conditional(Box::new(ConditionalSource {
+				when: FieldPredicate::try_from(required(c.when.as_ref(), "when")?)?,
+				then: FieldSource::try_from(required(c.then.as_deref(), "then")?)?,
+				fallback: c
+					.fallback
+					.as_deref()
+					.map(FieldSource::try_from)
+					.transpose()?,
+			})),
+			Source::Coerce(c) => FieldSource::Coerce(Box::new(CoerceSource {
+				source: FieldSource::try_from(required(c.source.as_deref(), "source")?)?,
+				as_type: match enum_value(c.as_type, "CoerceType")? {
+					proto::CoerceType::Unspecified => return Err(missing("as_type")),
+					proto::CoerceType::Number => CoerceType::Number,
+					proto::CoerceType::String => CoerceType::String,
+					proto::CoerceType::Boolean => CoerceType::Boolean,
+				},
+			})),
+		})
+	}
+}
+
+impl TryFrom<&proto::LiteralValue> for LiteralValue {
+	type Error = RegistryError;
+
+	fn try_from(s: &proto::LiteralValue) -> Result<Self, Self::Error> {
+		use proto::literal_value::Value;
+		Ok(match required(s.value.as_ref(), "value")? {
+			Value::StringValue(v) => LiteralValue::StringValue(v.clone()),
+			Value::NumberValue(v) => LiteralValue::NumberValue(*v),
+			Value::BoolValue(v) => LiteralValue::BoolValue(*v),
+			Value::NullValue(v) => LiteralValue::NullValue(*v),
+		})
+	}
+}
+
+// =============================================================================
+// Map Each
+// =============================================================================
+
+impl TryFrom<&proto::MapEachSpec> for MapEachSpec {
+	type Error = RegistryError;
+
+	fn try_from(s: &proto::MapEachSpec) -> Result<Self, Self::Error> {
+		use proto::map_each_inner::Inner;
+		let inner = required(s.inner.as_deref(), "inner")?;
+		let inner = match required(inner.inner.as_ref(), "inner")? {
+			Inner::Tool(name) => MapEachInner::Tool(name.clone()),
+			Inner::Pattern(p) => MapEachInner::Pattern(Box::new(PatternSpec::try_from(p.as_ref())?)),
+		};
+		Ok(Self {
+			inner,
+			max_items: s.max_items,
+			stream: s.stream.as_ref().map(|st| MapEachStream {
+				unordered: st.unordered,
+				concurrency: st.concurrency,
+			}),
+		})
+	}
+}
+
+impl TryFrom<&proto::RankSpec> for RankSpec {
+	type Error = RegistryError;
+
+	fn try_from(s: &proto::RankSpec) -> Result<Self, Self::Error> {
+		use proto::rank_scorer::Scorer;
+		let scorer = required(s.scorer.as_ref(), "scorer")?;
+		let scorer = match required(scorer.scorer.as_ref(), "scorer")? {
+			Scorer::Fields(f) => RankScorer::Fields(
+				f.fields
+					.iter()
+					.map(|wf| WeightedField {
+						path: wf.path.clone(),
+						weight: wf.weight.unwrap_or(1.0),
+					})
+					.collect(),
+			),
+			Scorer::Embedding(e) => RankScorer::Embedding(EmbeddingScorer {
+				embedder: e.embedder.clone(),
+				query_path: e.query_path.clone(),
+				content_path: e.content_path.clone().unwrap_or_else(|| "$".to_string()),
+			}),
+			Scorer::Llm(l) => RankScorer::Llm(LlmScorer {
+				prompt: l.prompt.clone(),
+				query_path: l.query_path.clone(),
+				model_hints: l.model_hints.clone(),
+				max_tokens: l.max_tokens.unwrap_or_else(default_sample_max_tokens),
+				timeout_ms: l.timeout_ms.unwrap_or_else(default_llm_timeout_ms),
+			}),
+		};
+		Ok(Self {
+			scorer,
+			items_path: s.items_path.clone(),
+			limit: s.limit.map(|l| l as usize),
+			min_score: s.min_score,
+			ascending: s.ascending,
+			score_field: s.score_field.clone(),
+		})
+	}
+}
+
+// =============================================================================
+// Stateful patterns
+// =============================================================================
+
+impl TryFrom<&proto::RetrySpec> for RetrySpec {
+	type Error = RegistryError;
+
+	fn try_from(s: &proto::RetrySpec) -> Result<Self, Self::Error> {
+		Ok(Self {
+			inner: boxed_op(required(s.inner.as_deref(), "inner")?)?,
+			max_attempts: s.max_attempts,
+			backoff: BackoffStrategy::try_from(required(s.backoff.as_ref(), "backoff")?)?,
+			retry_if: s
+				.retry_if
+				.as_ref()
+				.map(FieldPredicate::try_from)
+				.transpose()?,
+			jitter: s.jitter,
+			attempt_timeout_ms: s.attempt_timeout_ms,
+		})
+	}
+}
+
+impl TryFrom<&proto::BackoffStrategy> for BackoffStrategy {
+	type Error = RegistryError;
+
+	fn try_from(s: &proto::BackoffStrategy) -> Result<Self, Self::Error> {
+		use proto::backoff_strategy::Strategy;
+		Ok(match required(s.strategy.as_ref(), "strategy")? {
+			Strategy::Fixed(f) => BackoffStrategy::Fixed(FixedBackoff {
+				delay_ms: f.delay_ms,
+			}),
+			Strategy::Exponential(e) => BackoffStrategy::Exponential(ExponentialBackoff {
+				initial_delay_ms: e.initial_delay_ms,
+				max_delay_ms: e.max_delay_ms,
+				multiplier: e.multiplier.unwrap_or(2.0),
+			}),
+			Strategy::Linear(l) => BackoffStrategy::Linear(LinearBackoff {
+				initial_delay_ms: l.initial_delay_ms,
+				increment_ms: l.increment_ms,
+				max_delay_ms: l.max_delay_ms,
+			}),
+		})
+	}
+}
+
+impl TryFrom<&proto::TimeoutSpec> for TimeoutSpec {
+	type Error = RegistryError;
+
+	fn try_from(s: &proto::TimeoutSpec) -> Result<Self, Self::Error> {
+		Ok(Self {
+			inner: boxed_op(required(s.inner.as_deref(), "inner")?)?,
+			duration_ms: s.duration_ms,
+			fallback: s.fallback.as_deref().map(boxed_op).transpose()?,
+			message: s.message.clone(),
+		})
+	}
+}
+
+impl TryFrom<&proto::CacheSpec> for CacheSpec {
+	type Error = RegistryError;
+
+	fn try_from(s: &proto::CacheSpec) -> Result<Self, Self::Error> {
+		Ok(Self {
+			key_paths: s.key_paths.clone(),
+			inner: boxed_op(required(s.inner.as_deref(), "inner")?)?,
+			store: s.store.clone(),
+			ttl_seconds: s.ttl_seconds,
+			stale_while_revalidate_seconds: s.stale_while_revalidate_seconds,
+			cache_if: s
+				.cache_if
+				.as_ref()
+				.map(FieldPredicate::try_from)
+				.transpose()?,
+			stale_if: s
+				.stale_if
+				.as_ref()
+				.map(FieldPredicate::try_from)
+				.transpose()?,
+			negative: s.negative.as_ref().map(|n| NegativeCacheSpec {
+				ttl_seconds: n.ttl_seconds,
+				errors: n.errors.clone(),
+				empty_results: n.empty_results,
+			}),
+		})
+	}
+}
+
+impl TryFrom<&proto::IdempotentSpec> for IdempotentSpec {
+	type Error = RegistryError;
+
+	fn try_from(s: &proto::IdempotentSpec) -> Result<Self, Self::Error> {
+		Ok(Self {
+			key_paths: s.key_paths.clone(),
+			inner: boxed_op(required(s.inner.as_deref(), "inner")?)?,
+			store: s.store.clone(),
+			ttl_seconds: s.ttl_seconds,
+			on_duplicate: on_duplicate(s.on_duplicate)?,
+		})
+	}
+}
+
+fn on_duplicate(value: i32) -> Result<OnDuplicate, RegistryError> {
+	Ok(match enum_value(value, "OnDuplicate")? {
+		proto::OnDuplicate::Unspecified => OnDuplicate::default(),
+		proto::OnDuplicate::Cached => OnDuplicate::Cached,
+		proto::OnDuplicate::Skip => OnDuplicate::Skip,
+		proto::OnDuplicate::Error => OnDuplicate::Error,
+	})
+}
+
+impl TryFrom<&proto::CircuitBreakerSpec> for CircuitBreakerSpec {
+	type Error = RegistryError;
+
+	fn try_from(s: &proto::CircuitBreakerSpec) -> Result<Self, Self::Error> {
+		Ok(Self {
+			name: s.name.clone(),
+			inner: boxed_op(required(s.inner.as_deref(), "inner")?)?,
+			store: s.store.clone(),
+			failure_threshold: s.failure_threshold,
+			failure_window_seconds: s.failure_window_seconds,
+			reset_timeout_seconds: s.reset_timeout_seconds,
+			success_threshold: s.success_threshold.unwrap_or(1),
+			fallback: s.fallback.as_deref().map(boxed_op).transpose()?,
+			failure_if: s
+				.failure_if
+				.as_ref()
+				.map(FieldPredicate::try_from)
+				.transpose()?,
+		})
+	}
+}
+
+impl TryFrom<&proto::DeadLetterSpec> for DeadLetterSpec {
+	type Error = RegistryError;
+
+	fn try_from(s: &proto::DeadLetterSpec) -> Result<Self, Self::Error> {
+		Ok(Self {
+			inner: boxed_op(required(s.inner.as_deref(), "inner")?)?,
+			dead_letter_tool: s.dead_letter_tool.clone(),
+			max_attempts: s.max_attempts.unwrap_or(1),
+			backoff: s
+				.backoff
+				.as_ref()
+				.map(BackoffStrategy::try_from)
+				.transpose()?,
+			rethrow: s.rethrow,
+			sensitive_fields: s.sensitive_fields.clone(),
+			max_payload_bytes: s.max_payload_bytes,
+			claim_check_tool: s.claim_check_tool.clone(),
+		})
+	}
+}
+
+impl TryFrom<&proto::SagaSpec> for SagaSpec {
+	type Error = RegistryError;
+
+	fn try_from(s: &proto::SagaSpec) -> Result<Self, Self::Error> {
+		let steps = s
+			.steps
+			.iter()
+			.map(SagaStep::try_from)
+			.collect::<Result<Vec<_>, _>>()?;
+		Ok(Self {
+			steps,
+			store: s.store.clone(),
+			saga_id_path: s.saga_id_path.clone(),
+			timeout_ms: s.timeout_ms,
+			output: s.output.as_ref().map(DataBinding::try_from).transpose()?,
+		})
+	}
+}
+
+impl TryFrom<&proto::SagaStep> for SagaStep {
+	type Error = RegistryError;
+
+	fn try_from(s: &proto::SagaStep) -> Result<Self, Self::Error> {
+		Ok(Self {
+			id: s.id.clone(),
+			name: s.name.clone(),
+			action: StepOperation::try_from(required(s.action.as_ref(), "action")?)?,
+			compensate: s
+				.compensate
+				.as_ref()
+				.map(StepOperation::try_from)
+				.transpose()?,
+			input: DataBinding::try_from(required(s.input.as_ref(), "input")?)?,
+		})
+	}
+}
+
+impl TryFrom<&proto::ClaimCheckSpec> for ClaimCheckSpec {
+	type Error = RegistryError;
+
+	fn try_from(s: &proto::ClaimCheckSpec) -> Result<Self, Self::Error> {
+		Ok(Self {
+			store_tool: s.store_tool.clone(),
+			retrieve_tool: s.retrieve_tool.clone(),
+			inner: boxed_op(required(s.inner.as_deref(), "inner")?)?,
+			retrieve_at_end: s.retrieve_at_end,
+		})
+	}
+}
+
+impl TryFrom<&proto::ThrottleSpec> for ThrottleSpec {
+	type Error = RegistryError;
+
+	fn try_from(s: &proto::ThrottleSpec) -> Result<Self, Self::Error> {
+		let strategy = match enum_value(s.strategy, "ThrottleStrategy")? {
+			proto::ThrottleStrategy::Unspecified => ThrottleStrategy::default(),
+			proto::ThrottleStrategy::SlidingWindow => ThrottleStrategy::SlidingWindow,
+			proto::ThrottleStrategy::TokenBucket => ThrottleStrategy::TokenBucket,
+			proto::ThrottleStrategy::FixedWindow => ThrottleStrategy::FixedWindow,
+			proto::ThrottleStrategy::LeakyBucket => ThrottleStrategy::LeakyBucket,
+		};
+		let on_exceeded = match enum_value(s.on_exceeded, "OnExceeded")? {
+			proto::OnExceeded::Unspecified => OnExceeded::default(),
+			proto::OnExceeded::Wait => OnExceeded::Wait,
+			proto::OnExceeded::Reject => OnExceeded::Reject,
+			proto::OnExceeded::Queue => OnExceeded::Queue,
+		};
+		Ok(Self {
+			inner: boxed_op(required(s.inner.as_deref(), "inner")?)?,
+			rate: s.rate,
+			window_ms: s.window_ms,
+			strategy,
+			on_exceeded,
+			store: s.store.clone(),
+		})
+	}
+}
+
+// =============================================================================
+// Vision patterns
+// =============================================================================
+
+impl TryFrom<&proto::RouterSpec> for RouterSpec {
+	type Error = RegistryError;
+
+	fn try_from(s: &proto::RouterSpec) -> Result<Self, Self::Error> {
+		let routes = s
+			.routes
+			.iter()
+			.map(RouteCase::try_from)
+			.collect::<Result<Vec<_>, _>>()?;
+		Ok(Self {
+			routes,
+			otherwise: s.otherwise.as_deref().map(boxed_op).transpose()?,
+		})
+	}
+}
+
+impl TryFrom<&proto::RouteCase> for RouteCase {
+	type Error = RegistryError;
+
+	fn try_from(s: &proto::RouteCase) -> Result<Self, Self::Error> {
+		Ok(Self {
+			when: FieldPredicate::try_from(required(s.when.as_ref(), "when")?)?,
+			then: StepOperation::try_from(required(s.then.as_ref(), "then")?)?,
+		})
+	}
+}
+
+impl TryFrom<&proto::EnricherSpec> for EnricherSpec {
+	type Error = RegistryError;
+
+	fn try_from(s: &proto::EnricherSpec) -> Result<Self, Self::Error> {
+		let enrichments = s
+			.enrichments
+			.iter()
+			.map(EnrichmentSource::try_from)
+			.collect::<Result<Vec<_>, _>>()?;
+		let merge = s
+			.merge
+			.as_ref()
+			.map(MergeStrategy::try_from)
+			.transpose()?
+			.unwrap_or_default();
+		Ok(Self {
+			enrichments,
+			merge,
+			ignore_failures: s.ignore_failures,
+			timeout_ms: s.timeout_ms,
+		})
+	}
+}
+
+impl TryFrom<&proto::EnrichmentSource> for EnrichmentSource {
+	type Error = RegistryError;
+
+	fn try_from(s: &proto::EnrichmentSource) -> Result<Self, Self::Error> {
+		Ok(Self {
+			field: s.field.clone(),
+			operation: StepOperation::try_from(required(s.operation.as_ref(), "operation")?)?,
+			input: s.input.as_ref().map(DataBinding::try_from).transpose()?,
+		})
+	}
+}
+
+impl TryFrom<&proto::MergeStrategy> for MergeStrategy {
+	type Error = RegistryError;
+
+	fn try_from(s: &proto::MergeStrategy) -> Result<Self, Self::Error> {
+		use proto::merge_strategy::Strategy;
+		Ok(match s.strategy.as_ref() {
+			None | Some(Strategy::Spread(_)) => MergeStrategy::Spread,
+			Some(Strategy::Nested(n)) => MergeStrategy::Nested { key: n.key.clone() },
+			Some(Strategy::SchemaMap(sm)) => MergeStrategy::SchemaMap(SchemaMapSpec::try_from(sm)?),
+		})
+	}
+}
+
+impl TryFrom<&proto::WireTapSpec> for WireTapSpec {
+	type Error = RegistryError;
+
+	fn try_from(s: &proto::WireTapSpec) -> Result<Self, Self::Error> {
+		let taps = s
+			.taps
+			.iter()
+			.map(TapTarget::try_from)
+			.collect::<Result<Vec<_>, _>>()?;
+		let tap_point = match enum_value(s.tap_point, "TapPoint")? {
+			proto::TapPoint::Unspecified => TapPoint::default(),
+			proto::TapPoint::Before => TapPoint::Before,
+			proto::TapPoint::After => TapPoint::After,
+			proto::TapPoint::Both => TapPoint::Both,
+		};
+		Ok(Self {
+			inner: boxed_op(required(s.inner.as_deref(), "inner")?)?,
+			taps,
+			tap_point,
+		})
+	}
+}
+
+impl TryFrom<&proto::TapTarget> for TapTarget {
+	type Error = RegistryError;
+
+	fn try_from(s: &proto::TapTarget) -> Result<Self, Self::Error> {
+		Ok(Self {
+			tool: s.tool.clone(),
+			transform: s
+				.transform
+				.as_ref()
+				.map(SchemaMapSpec::try_from)
+				.transpose()?,
+		})
+	}
+}
+
+impl TryFrom<&proto::RecipientListSpec> for RecipientListSpec {
+	type Error = RegistryError;
+
+	fn try_from(s: &proto::RecipientListSpec) -> Result<Self, Self::Error> {
+		Ok(Self {
+			recipients_path: s.recipients_path.clone(),
+			recipients_tool: s.recipients_tool.clone(),
+			aggregation: s
+				.aggregation
+				.as_ref()
+				.map(AggregationStrategy::try_from)
+				.transpose()?,
+			parallel: s.parallel.unwrap_or(true),
+			fail_on_error: s.fail_on_error,
+		})
+	}
+}
+
+impl TryFrom<&proto::CapabilityRouterSpec> for CapabilityRouterSpec {
+	type Error = RegistryError;
+
+	fn try_from(s: &proto::CapabilityRouterSpec) -> Result<Self, Self::Error> {
+		Ok(Self {
+			required: s.required.clone(),
+			preferred: s.preferred.clone(),
+			fallback: s.fallback.as_deref().map(boxed_op).transpose()?,
+		})
+	}
+}
+
+impl TryFrom<&proto::SemanticDedupSpec> for SemanticDedupSpec {
+	type Error = RegistryError;
+
+	fn try_from(s: &proto::SemanticDedupSpec) -> Result<Self, Self::Error> {
+		let keep = match enum_value(s.keep, "DedupKeepStrategy")? {
+			proto::DedupKeepStrategy::Unspecified => DedupKeepStrategy::default(),
+			proto::DedupKeepStrategy::First => DedupKeepStrategy::First,
+			proto::DedupKeepStrategy::Last => DedupKeepStrategy::Last,
+			proto::DedupKeepStrategy::HighestScore => DedupKeepStrategy::HighestScore,
+			proto::DedupKeepStrategy::MostComplete => DedupKeepStrategy::MostComplete,
+		};
+		Ok(Self {
+			embedder: s.embedder.clone(),
+			content_path: s.content_path.clone(),
+			threshold: s.threshold,
+			keep,
+		})
+	}
+}
+
+impl TryFrom<&proto::ConfidenceAggregatorSpec> for ConfidenceAggregatorSpec {
+	type Error = RegistryError;
+
+	fn try_from(s: &proto::ConfidenceAggregatorSpec) -> Result<Self, Self::Error> {
+		let sources = s
+			.sources
+			.iter()
+			.map(WeightedSource::try_from)
+			.collect::<Result<Vec<_>, _>>()?;
+		let strategy = match enum_value(s.strategy, "ConfidenceStrategy")? {
+			proto::ConfidenceStrategy::Unspecified => ConfidenceStrategy::default(),
+			proto::ConfidenceStrategy::HighestWeight => ConfidenceStrategy::HighestWeight,
+			proto::ConfidenceStrategy::WeightedVote => ConfidenceStrategy::WeightedVote,
+			proto::ConfidenceStrategy::Quorum => ConfidenceStrategy::Quorum,
+			proto::ConfidenceStrategy::All => ConfidenceStrategy::All,
+		};
+		Ok(Self {
+			sources,
+			strategy,
+			min_weight: s.min_weight,
+			conflict_threshold: s.conflict_threshold,
+		})
+	}
+}
+
+impl TryFrom<&proto::WeightedSource> for WeightedSource {
+	type Error = RegistryError;
+
+	fn try_from(s: &proto::WeightedSource) -> Result<Self, Self::Error> {
+		Ok(Self {
+			operation: StepOperation::try_from(required(s.operation.as_ref(), "operation")?)?,
+			weight: s.weight,
+		})
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn tool_op(name: &str) -> proto::StepOperation {
+		proto::StepOperation {
+			op: Some(proto::step_operation::Op::Tool(proto::ToolCall {
+				name: name.to_string(),
+				..Default::default()
+			})),
+		}
+	}
+
+	fn eq_predicate(field: &str, value: &str) -> proto::FieldPredicate {
+		proto::FieldPredicate {
+			field: field.to_string(),
+			op: "eq".to_string(),
+			value: Some(proto::PredicateValue {
+				value: Some(proto::predicate_value::Value::StringValue(
+					value.to_string(),
+				)),
+			}),
+		}
+	}
+
+	fn convert(pattern: proto::pattern_spec::Pattern) -> PatternSpec {
+		PatternSpec::try_from(&proto::PatternSpec {
+			pattern: Some(pattern),
+		})
+		.unwrap()
+	}
+
+	#[test]
+	fn test_convert_source_tool_definition() {
+		let tool = proto::ToolDefinition {
+			name: "get_weather".to_string(),
+			description: Some("Weather lookup".to_string()),
+			implementation: Some(proto::tool_definition::Implementation::Source(
+				proto::SourceTool {
+					server: "weather".to_string(),
+					tool: "fetch_weather".to_string(),
+					hide_fields: vec!["api_key".to_string()],
+					..Default::default()
+				},
+			)),
+			..Default::default()
+		};
+
+		let converted = ToolDefinition::try_from(&tool).unwrap();
+		assert_eq!(converted.name, "get_weather");
+		let source = converted.source_tool().unwrap();
+		assert_eq!(source.target, "weather");
+		assert_eq!(source.tool, "fetch_weather");
+		assert_eq!(source.hide_fields, vec!["api_key"]);
+	}
+
+	#[test]
+	fn test_convert_resilience() {
+		let tool = proto::ToolDefinition {
+			name: "search".to_string(),
+			implementation: Some(proto::tool_definition::Implementation::Spec(
+				proto::PatternSpec {
+					pattern: Some(proto::pattern_spec::Pattern::Pipeline(
+						proto::PipelineSpec::default(),
+					)),
+				},
+			)),
+			resilience: Some(proto::Resilience {
+				timeout_ms: Some(500),
+				retries: Some(2),
+				backoff: Some(proto::BackoffStrategy {
+					strategy: Some(proto::backoff_strategy::Strategy::Fixed(
+						proto::FixedBackoff { delay_ms: 50 },
+					)),
+				}),
+				circuit_breaker: Some(proto::CircuitBreakerShorthand {
+					store: "circuits".to_string(),
+					failure_threshold: Some(3),
+					..Default::default()
+				}),
+			}),
+			..Default::default()
+		};
+
+		let converted = ToolDefinition::try_from(&tool).unwrap();
+		assert_eq!(
+			converted.resilience,
+			Some(Resilience {
+				timeout_ms: Some(500),
+				retries: Some(2),
+				backoff: Some(BackoffStrategy::Fixed(FixedBackoff { delay_ms: 50 })),
+				circuit_breaker: Some(CircuitBreakerShorthand {
+					store: "circuits".to_string(),
+					failure_threshold: 3,
+					failure_window_seconds: 60,
+					reset_timeout_seconds: 30,
+				}),
+			})
+		);
+	}
+
+	#[test]
+	fn test_convert_registry_defaults() {
+		let registry = Registry::try_from(&proto::Registry {
+			defaults: Some(proto::RegistryDefaults {
+				transport_retry: None,
+				resilience: Some(proto::Resilience {
+					retries: Some(1),
+					..Default::default()
+				}),
+			}),
+			..Default::default()
+		})
+		.unwrap();
+		assert_eq!(
+			registry.defaults.resilience,
+			Some(Resilience {
+				retries: Some(1),
+				..Default::default()
+			})
+		);
+	}
+
+	#[test]
+	fn test_convert_pipeline() {
+		let spec = convert(proto::pattern_spec::Pattern::Pipeline(
+			proto::PipelineSpec {
+				steps: vec![proto::PipelineStep {
+					id: "search".to_string(),
+					operation: Some(tool_op("web_search")),
+					input: Some(proto::DataBinding {
+						source: Some(proto::data_binding::Source::Input(proto::InputBinding {
+							path: "$.query".to_string(),
+						})),
+					}),
+					session_set: None,
+					retry: Some(proto::StepRetry {
+						max_attempts: 3,
+						backoff_ms: 250,
+					}),
+					cache: None,
+				}],
+			},
+		));
+
+		assert_eq!(spec.referenced_tools(), vec!["web_search"]);
+		let PatternSpec::Pipeline(pipeline) = spec else {
+			panic!("expected a pipeline");
+		};
+		assert_eq!(
+			pipeline.steps[0].retry,
+			Some(StepRetry {
+				max_attempts: 3,
+				backoff_ms: 250,
+			})
+		);
+	}
+
+	#[test]
+	fn test_convert_assert_step() {
+		let op = StepOperation::try_from(&proto::StepOperation {
+			op: Some(proto::step_operation::Op::Assert(proto::AssertSpec {
+				predicate: Some(eq_predicate("$.status", "ok")),
+				message: Some("search failed".to_string()),
+				severity: proto::AssertSeverity::Warn as i32,
+			})),
+		})
+		.unwrap();
+		let StepOperation::Assert(assert) = op else {
+			panic!("expected an assert operation");
+		};
+		assert_eq!(assert.predicate.field, "$.status");
+		assert_eq!(assert.message.as_deref(), Some("search failed"));
+		assert_eq!(assert.severity, AssertSeverity::Warn);
+	}
+
+	#[test]
+	fn test_convert_script_step() {
+		let op = StepOperation::try_from(&proto::StepOperation {
+			op: Some(proto::step_operation::Op::Script(proto::ScriptSpec {
+				wasm: b"\0asm".to_vec(),
+				entrypoint: None,
+				limits: Some(proto::ScriptLimits {
+					fuel: Some(1000),
+					timeout_ms: Some(250),
+					..Default::default()
+				}),
+			})),
+		})
+		.unwrap();
+		let StepOperation::Script(script) = op else {
+			panic!("expected a script operation");
+		};
+		assert_eq!(script.wasm, "AGFzbQ==");
+		assert_eq!(script.entrypoint, "transform");
+		assert_eq!(script.limits.fuel, 1000);
+		assert_eq!(script.limits.timeout_ms, 250);
+		assert_eq!(
+			script.limits.output_bytes,
+			ScriptLimits::default().output_bytes
+		);
+	}
+
+	#[test]
+	fn test_convert_sample_step() {
+		let op = StepOperation::try_from(&proto::StepOperation {
+			op: Some(proto::step_operation::Op::Sample(proto::SampleSpec {
+				prompt: "Pick the best match".to_string(),
+				model_hints: vec!["claude".to_string()],
+				json: true,
+				timeout_ms: Some(5000),
+				..Default::default()
+			})),
+		})
+		.unwrap();
+		let StepOperation::Sample(sample) = op else {
+			panic!("expected a sample operation");
+		};
+		assert_eq!(sample.prompt, "Pick the best match");
+		assert_eq!(sample.max_tokens, 1024);
+		assert_eq!(sample.timeout_ms, 5000);
+		assert!(sample.json);
+	}
+
+	#[test]
+	fn test_convert_patch_step() {
+		let op = StepOperation::try_from(&proto::StepOperation {
+			op: Some(proto::step_operation::Op::Patch(proto::PatchSpec {
+				json_patch: vec![proto::JsonPatchOperation {
+					op: proto::JsonPatchOp::Move as i32,
+					path: "/kept".to_string(),
+					from: Some("/tmp".to_string()),
+					value: None,
+				}],
+				merge_patch: None,
+				vars: HashMap::from([("id".to_string(), "$.id".to_string())]),
+			})),
+		})
+		.unwrap();
+		let StepOperation::Patch(patch) = op else {
+			panic!("expected a patch operation");
+		};
+		let ops = patch.json_patch.unwrap();
+		assert_eq!(ops[0].op, JsonPatchOp::Move);
+		assert_eq!(ops[0].from.as_deref(), Some("/tmp"));
+		assert!(patch.merge_patch.is_none());
+		assert_eq!(patch.vars["id"], "$.id");
+
+		let unspecified = StepOperation::try_from(&proto::StepOperation {
+			op: Some(proto::step_operation::Op::Patch(proto::PatchSpec {
+				json_patch: vec![proto::JsonPatchOperation {
+					path: "/a".to_string(),
+					..Default::default()
+				}],
+				..Default::default()
+			})),
+		});
+		assert!(unspecified.is_err());
+	}
+
+	#[test]
+	fn test_convert_constant_step() {
+		let op = StepOperation::try_from(&proto::StepOperation {
+			op: Some(proto::step_operation::Op::Constant(
+				serde_json::from_value(serde_json::json!({"sort": "recent"})).unwrap(),
+			)),
+		})
+		.unwrap();
+		let StepOperation::Constant(value) = op else {
+			panic!("expected a constant operation");
+		};
+		assert_eq!(value, serde_json::json!({"sort": "recent"}));
+	}
+
+	#[test]
+	fn test_convert_rank() {
+		let spec = convert(proto::pattern_spec::Pattern::Rank(proto::RankSpec {
+			scorer: Some(proto::RankScorer {
+				scorer: Some(proto::rank_scorer::Scorer::Embedding(
+					proto::EmbeddingScorer {
+						embedder: "embed".to_string(),
+						query_path: "$.query".to_string(),
+						content_path: None,
+					},
+				)),
+			}),
+			items_path: Some("$.results".to_string()),
+			limit: Some(10),
+			..Default::default()
+		}));
+
+		let PatternSpec::Rank(rank) = &spec else {
+			panic!("expected rank, got {}", spec.pattern_name());
+		};
+		let RankScorer::Embedding(embedding) = &rank.scorer else {
+			panic!("expected an embedding scorer");
+		};
+		assert_eq!(embedding.content_path, "$");
+		assert_eq!(rank.limit, Some(10));
+		assert_eq!(spec.referenced_tools(), vec!["embed"]);
+	}
+
+	#[test]
+	fn test_convert_throttle() {
+		let spec = convert(proto::pattern_spec::Pattern::Throttle(Box::new(
+			proto::ThrottleSpec {
+				inner: Some(Box::new(tool_op("expensive_api"))),
+				rate: 100,
+				window_ms: 60000,
+				strategy: proto::ThrottleStrategy::TokenBucket as i32,
+				on_exceeded: proto::OnExceeded::Reject as i32,
+				store: Some("redis".to_string()),
+			},
+		)));
+
+		let PatternSpec::Throttle(throttle) = &spec else {
+			panic!("expected throttle, got {}", spec.pattern_name());
+		};
+		assert_eq!(throttle.rate, 100);
+		assert_eq!(throttle.window_ms, 60000);
+		assert_eq!(throttle.strategy, ThrottleStrategy::TokenBucket);
+		assert_eq!(throttle.on_exceeded, OnExceeded::Reject);
+		assert_eq!(throttle.store, Some("redis".to_string()));
+	}
+
+	#[test]
+	fn test_convert_throttle_defaults() {
+		let spec = convert(proto::pattern_spec::Pattern::Throttle(Box::new(
+			proto::ThrottleSpec {
+				inner: Some(Box::new(tool_op("expensive_api"))),
+				rate: 10,
+				window_ms: 1000,
+				..Default::default()
+			},
+		)));
+
+		let PatternSpec::Throttle(throttle) = spec else {
+			panic!("expected throttle");
+		};
+		assert_eq!(throttle.strategy, ThrottleStrategy::SlidingWindow);
+		assert_eq!(throttle.on_exceeded, OnExceeded::Wait);
+	}
+
+	#[test]
+	fn test_convert_empty_pattern_is_error() {
+		let result = PatternSpec::try_from(&proto::PatternSpec { pattern: None });
+		assert!(matches!(result, Err(RegistryError::InvalidProto(_))));
+	}
+
+	#[test]
+	fn test_convert_tool_with_empty_spec_is_error() {
+		let tool = proto::ToolDefinition {
+			name: "throttled".to_string(),
+			implementation: Some(proto::tool_definition::Implementation::Spec(
+				proto::PatternSpec { pattern: None },
+			)),
+			..Default::default()
+		};
+
+		assert!(ToolDefinition::try_from(&tool).is_err());
+	}
+
+	#[test]
+	fn test_convert_router() {
+		let spec = convert(proto::pattern_spec::Pattern::Router(Box::new(
+			proto::RouterSpec {
+				routes: vec![proto::RouteCase {
+					when: Some(eq_predicate("$.type", "pdf")),
+					then: Some(tool_op("pdf_processor")),
+				}],
+				otherwise: Some(Box::new(tool_op("default_processor"))),
+			},
+		)));
+
+		let PatternSpec::Router(router) = &spec else {
+			panic!("expected router, got {}", spec.pattern_name());
+		};
+		assert_eq!(router.routes.len(), 1);
+		assert_eq!(router.routes[0].when.field, "$.type");
+		assert_eq!(
+			spec.referenced_tools(),
+			vec!["pdf_processor", "default_processor"]
+		);
+	}
+
+	#[test]
+	fn test_convert_enricher() {
+		let spec = convert(proto::pattern_spec::Pattern::Enricher(
+			proto::EnricherSpec {
+				enrichments: vec![proto::EnrichmentSource {
+					field: "metadata".to_string(),
+					operation: Some(tool_op("fetch_metadata")),
+					input: None,
+				}],
+				merge: Some(proto::MergeStrategy {
+					strategy: Some(proto::merge_strategy::Strategy::Nested(
+						proto::NestedMerge {
+							key: "extra".to_string(),
+						},
+					)),
+				}),
+				ignore_failures: true,
+				timeout_ms: Some(5000),
+			},
+		));
+
+		let PatternSpec::Enricher(enricher) = &spec else {
+			panic!("expected enricher, got {}", spec.pattern_name());
+		};
+		assert!(matches!(&enricher.merge, MergeStrategy::Nested { key } if key == "extra"));
+		assert!(enricher.ignore_failures);
+		assert_eq!(enricher.timeout_ms, Some(5000));
+		assert_eq!(spec.referenced_tools(), vec!["fetch_metadata"]);
+	}
+
+	#[test]
+	fn test_convert_enricher_defaults_to_spread() {
+		let spec = convert(proto::pattern_spec::Pattern::Enricher(
+			proto::EnricherSpec::default(),
+		));
+
+		let PatternSpec::Enricher(enricher) = spec else {
+			panic!("expected enricher");
+		};
+		assert!(matches!(enricher.merge, MergeStrategy::Spread));
+	}
+
+	#[test]
+	fn test_convert_wire_tap() {
+		let spec = convert(proto::pattern_spec::Pattern::WireTap(Box::new(
+			proto::WireTapSpec {
+				inner: Some(Box::new(tool_op("main_process"))),
+				taps: vec![proto::TapTarget {
+					tool: "audit_logger".to_string(),
+					transform: None,
+				}],
+				tap_point: proto::TapPoint::Both as i32,
+			},
+		)));
+
+		let PatternSpec::WireTap(tap) = &spec else {
+			panic!("expected wire_tap, got {}", spec.pattern_name());
+		};
+		assert_eq!(tap.tap_point, TapPoint::Both);
+		assert_eq!(
+			spec.referenced_tools(),
+			vec!["main_process", "audit_logger"]
+		);
+	}
+
+	#[test]
+	fn test_convert_wire_tap_unspecified_tap_point() {
+		let spec = convert(proto::pattern_spec::Pattern::WireTap(Box::new(
+			proto::WireTapSpec {
+				inner: Some(Box::new(tool_op("main_process"))),
+				..Default::default()
+			},
+		)));
+
+		let PatternSpec::WireTap(tap) = spec else {
+			panic!("expected wire_tap");
+		};
+		assert_eq!(tap.tap_point, TapPoint::After);
+	}
+
+	#[test]
+	fn test_convert_recipient_list() {
+		let spec = convert(proto::pattern_spec::Pattern::RecipientList(
+			proto::RecipientListSpec {
+				recipients_path: Some("$.targets".to_string()),
+				fail_on_error: true,
+				..Default::default()
+			},
+		));
+
+		let PatternSpec::RecipientList(rl) = spec else {
+			panic!("expected recipient_list");
+		};
+		assert_eq!(rl.recipients_path, Some("$.targets".to_string()));
+		// parallel defaults to true when unset, matching the JSON format
+		assert!(rl.parallel);
+		assert!(rl.fail_on_error);
+	}
+
+	#[test]
+	fn test_convert_capability_router() {
+		let spec = convert(proto::pattern_spec::Pattern::CapabilityRouter(Box::new(
+			proto::CapabilityRouterSpec {
+				required: vec!["text-generation".to_string()],
+				preferred: vec!["low-latency".to_string()],
+				fallback: Some(Box::new(tool_op("default_tool"))),
+			},
+		)));
+
+		let PatternSpec::CapabilityRouter(cr) = &spec else {
+			panic!("expected capability_router, got {}", spec.pattern_name());
+		};
+		assert_eq!(cr.required, vec!["text-generation"]);
+		assert_eq!(cr.preferred, vec!["low-latency"]);
+		assert_eq!(spec.referenced_tools(), vec!["default_tool"]);
+	}
+
+	#[test]
+	fn test_convert_semantic_dedup() {
+		let spec = convert(proto::pattern_spec::Pattern::SemanticDedup(
+			proto::SemanticDedupSpec {
+				embedder: "text_embedder".to_string(),
+				content_path: "$.content".to_string(),
+				threshold: 0.9,
+				keep: proto::DedupKeepStrategy::HighestScore as i32,
+			},
+		));
+
+		let PatternSpec::SemanticDedup(sd) = &spec else {
+			panic!("expected semantic_dedup, got {}", spec.pattern_name());
+		};
+		assert_eq!(sd.keep, DedupKeepStrategy::HighestScore);
+		assert_eq!(sd.threshold, 0.9);
+		assert_eq!(spec.referenced_tools(), vec!["text_embedder"]);
+	}
+
+	#[test]
+	fn test_convert_confidence_aggregator() {
+		let spec = convert(proto::pattern_spec::Pattern::ConfidenceAggregator(
+			proto::ConfidenceAggregatorSpec {
+				sources: vec![
+					proto::WeightedSource {
+						operation: Some(tool_op("expert_api")),
+						weight: 0.9,
+					},
+					proto::WeightedSource {
+						operation: Some(tool_op("fallback_api")),
+						weight: 0.5,
+					},
+				],
+				strategy: proto::ConfidenceStrategy::WeightedVote as i32,
+				min_weight: Some(0.7),
+				conflict_threshold: None,
+			},
+		));
+
+		let PatternSpec::ConfidenceAggregator(ca) = &spec else {
+			panic!(
+				"expected confidence_aggregator, got {}",
+				spec.pattern_name()
+			);
+		};
+		assert_eq!(ca.strategy, ConfidenceStrategy::WeightedVote);
+		assert_eq!(ca.min_weight, Some(0.7));
+		assert_eq!(spec.referenced_tools(), vec!["expert_api", "fallback_api"]);
+	}
+
+	#[test]
+	fn test_convert_unknown_enum_value() {
+		let result = PatternSpec::try_from(&proto::PatternSpec {
+			pattern: Some(proto::pattern_spec::Pattern::SemanticDedup(
+				proto::SemanticDedupSpec {
+					embedder: "text_embedder".to_string(),
+					keep: 99,
+					..Default::default()
+				},
+			)),
+		});
+
+		assert!(matches!(result, Err(RegistryError::InvalidProto(_))));
+	}
+
+	#[test]
+	fn test_convert_missing_required_field() {
+		let result = PatternSpec::try_from(&proto::PatternSpec {
+			pattern: Some(proto::pattern_spec::Pattern::Router(Box::new(
+				proto::RouterSpec {
+					routes: vec![proto::RouteCase {
+						when: None,
+						then: Some(tool_op("pdf_processor")),
+					}],
+					otherwise: None,
+				},
+			))),
+		});
+
+		assert!(matches!(result, Err(RegistryError::InvalidProto(_))));
+	}
+
+	#[test]
+	fn test_convert_cost_model() {
+		let cost = CostModel::try_from(&proto::CostModel {
+			model: Some(proto::cost_model::Model::Reported(proto::ReportedCost {
+				path: "$.usage.cost".to_string(),
+				fallback: 0.01,
+			})),
+		})
+		.unwrap();
+		assert_eq!(
+			cost,
+			CostModel::Reported(ReportedCost {
+				path: "$.usage.cost".to_string(),
+				fallback: 0.01,
+			})
+		);
+
+		let empty = CostModel::try_from(&proto::CostModel { model: None });
+		assert!(matches!(empty, Err(RegistryError::InvalidProto(_))));
+	}
+
+	#[test]
+	fn test_convert_template_binding() {
+		use proto::data_binding::Source;
+
+		let step = |path: &str| proto::DataBinding {
+			source: Some(Source::Step(proto::StepBinding {
+				step_id: "charge".to_string(),
+				path: path.to_string(),
+			})),
+		};
+		let template = proto::DataBinding {
+			source: Some(Source::Template(proto::TemplateBinding {
+				template: "Order {id} charged".to_string(),
+				vars: HashMap::from([("id".to_string(), step("$.orderId"))]),
+			})),
+		};
+		let binding = DataBinding::try_from(&proto::DataBinding {
+			source: Some(Source::Construct(proto::ConstructBinding {
+				fields: HashMap::from([
+					("message".to_string(), template),
+					("receipt".to_string(), step("$")),
+				]),
+			})),
+		})
+		.unwrap();
+
+		let DataBinding::Construct(construct) = binding else {
+			panic!("expected a construct binding");
+		};
+		let Some(DataBinding::Template(template)) = construct.fields.get("message") else {
+			panic!("expected a template binding");
+		};
+		assert_eq!(template.template, "Order {id} charged");
+		assert!(matches!(
+			template.vars.get("id"),
+			Some(DataBinding::Step(StepBinding { path, .. })) if path == "$.orderId"
+		));
+		assert!(matches!(
+			construct.fields.get("receipt"),
+			Some(DataBinding::Step(_))
+		));
+	}
+}
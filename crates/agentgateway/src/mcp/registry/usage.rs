@@ -0,0 +1,302 @@
+// Tool usage analytics
+//
+// Counts calls per tool, and the distinct callers behind them, in fixed windows for export
+// to analytics systems outside the gateway. Only tool names and salted caller hashes are
+// recorded: arguments and results never reach the recorder, and caller hashes never leave
+// it.
+//
+// In private mode a report can be shared beyond the gateway's operators without revealing
+// what any one caller did. Each caller's calls to a tool count at most `maxCallsPerCaller`
+// times, Laplace noise calibrated to `epsilon` is added to every count, and tools with fewer
+// than `minCallers` (noisy) distinct callers are left out. The noise is drawn once, when a
+// window closes, and only closed windows are reported, so reading a report again can't
+// average the noise away.
+
+use std::collections::HashMap;
+use std::hash::{BuildHasher, RandomState};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use parking_lot::Mutex;
+use rand::Rng;
+use serde::Serialize;
+
+/// Default length of a reporting window
+pub const DEFAULT_USAGE_INTERVAL: Duration = Duration::from_secs(3600);
+
+/// Most distinct callers tracked per tool and window; calls from further callers are still
+/// counted, but not as callers
+const MAX_CALLERS_PER_TOOL: usize = 100_000;
+
+/// How private usage reports are aggregated
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct UsagePrivacy {
+	/// Tools with fewer distinct callers in a window are left out of its report
+	pub min_callers: u64,
+	/// Privacy budget of each count; smaller values add more noise
+	pub epsilon: f64,
+	/// Most calls counted per caller, tool and window
+	pub max_calls_per_caller: u32,
+}
+
+impl Default for UsagePrivacy {
+	fn default() -> Self {
+		Self {
+			min_callers: 10,
+			epsilon: 1.0,
+			max_calls_per_caller: 20,
+		}
+	}
+}
+
+/// Usage of every tool called in one window
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UsageReport {
+	/// Window start (milliseconds since the Unix epoch)
+	pub since_ms: u64,
+	/// Window end (milliseconds since the Unix epoch)
+	pub until_ms: u64,
+	/// Whether counts are noisy and thresholded
+	pub private: bool,
+	/// Tools by name. Private reports don't say how many tools were left out, since an exact
+	/// count would reveal rare callers.
+	pub tools: Vec<ToolUsage>,
+}
+
+/// Calls of one tool in a window
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ToolUsage {
+	pub tool: String,
+	pub calls: u64,
+	/// Distinct callers (JWT subjects, or sessions for anonymous callers)
+	pub callers: u64,
+}
+
+/// Per-tool call counts, aggregated in windows for export
+#[derive(Debug)]
+pub struct UsageAnalytics {
+	interval: Duration,
+	privacy: Option<UsagePrivacy>,
+	/// Salts caller hashes, so they can't be matched against known identities
+	hasher: RandomState,
+	window: Mutex<Window>,
+}
+
+#[derive(Debug)]
+struct Window {
+	started: Instant,
+	started_at_ms: u64,
+	tools: HashMap<String, ToolWindow>,
+	/// Report of the last closed window
+	last: Option<UsageReport>,
+}
+
+#[derive(Debug, Default)]
+struct ToolWindow {
+	calls: u64,
+	/// Calls per caller hash
+	callers: HashMap<u64, u32>,
+}
+
+impl UsageAnalytics {
+	/// Count usage in windows of `interval`, aggregated privately if `privacy` is set
+	pub fn new(interval: Duration, privacy: Option<UsagePrivacy>) -> Self {
+		Self {
+			interval,
+			privacy,
+			hasher: RandomState::new(),
+			window: Mutex::new(Window {
+				started: Instant::now(),
+				started_at_ms: now_ms(),
+				tools: HashMap::new(),
+				last: None,
+			}),
+		}
+	}
+
+	/// Whether reports are noisy and thresholded
+	pub fn is_private(&self) -> bool {
+		self.privacy.is_some()
+	}
+
+	/// Count a call of `tool` by `caller`
+	pub fn record(&self, tool: &str, caller: &str) {
+		let caller = self.hasher.hash_one(caller);
+		let mut window = self.window.lock();
+		self.roll(&mut window);
+		if !window.tools.contains_key(tool) {
+			window.tools.insert(tool.to_string(), ToolWindow::default());
+		}
+		let usage = window.tools.get_mut(tool).expect("inserted above");
+		usage.calls += 1;
+		if let Some(calls) = usage.callers.get_mut(&caller) {
+			*calls = calls.saturating_add(1);
+		} else if usage.callers.len() < MAX_CALLERS_PER_TOOL {
+			usage.callers.insert(caller, 1);
+		}
+	}
+
+	/// Report of the last closed window, if one has closed
+	pub fn last_report(&self) -> Option<UsageReport> {
+		let mut window = self.window.lock();
+		self.roll(&mut window);
+		window.last.clone()
+	}
+
+	/// Exact counts of the open window so far; not available in private mode
+	pub fn current_report(&self) -> Option<UsageReport> {
+		if self.is_private() {
+			return None;
+		}
+		let mut window = self.window.lock();
+		self.roll(&mut window);
+		Some(summarize(&window, None, now_ms()))
+	}
+
+	/// Close the open window now, and start the next one
+	pub fn close_window(&self) {
+		let mut window = self.window.lock();
+		let report = summarize(&window, self.privacy.as_ref(), now_ms());
+		window.started = Instant::now();
+		window.started_at_ms = report.until_ms;
+		window.tools.clear();
+		window.last = Some(report);
+	}
+
+	/// Close the open window if its interval has passed
+	fn roll(&self, window: &mut Window) {
+		if window.started.elapsed() < self.interval {
+			return;
+		}
+		let until_ms = window.started_at_ms + self.interval.as_millis() as u64;
+		let report = summarize(window, self.privacy.as_ref(), until_ms);
+		window.started = Instant::now();
+		window.started_at_ms = now_ms();
+		window.tools.clear();
+		window.last = Some(report);
+	}
+}
+
+/// Aggregate a window's counts, privately if `privacy` is set
+fn summarize(window: &Window, privacy: Option<&UsagePrivacy>, until_ms: u64) -> UsageReport {
+	let mut tools = Vec::with_capacity(window.tools.len());
+	for (tool, usage) in &window.tools {
+		let (calls, callers) = match privacy {
+			None => (usage.calls, usage.callers.len() as u64),
+			Some(privacy) => {
+				let cap = u64::from(privacy.max_calls_per_caller);
+				let calls: u64 = usage.callers.values().map(|c| u64::from(*c).min(cap)).sum();
+				let callers = noisy(usage.callers.len() as u64, 1.0 / privacy.epsilon);
+				if callers < privacy.min_callers {
+					continue;
+				}
+				(noisy(calls, cap as f64 / privacy.epsilon), callers)
+			},
+		};
+		tools.push(ToolUsage {
+			tool: tool.clone(),
+			calls,
+			callers,
+		});
+	}
+	tools.sort_by(|a, b| a.tool.cmp(&b.tool));
+	UsageReport {
+		since_ms: window.started_at_ms,
+		until_ms,
+		private: privacy.is_some(),
+		tools,
+	}
+}
+
+/// `count` plus Laplace noise of the given scale, rounded and clamped at zero
+fn noisy(count: u64, scale: f64) -> u64 {
+	let u: f64 = rand::rng().random_range(-0.5..0.5);
+	let noise = -scale * u.signum() * (1.0 - 2.0 * u.abs()).max(f64::MIN_POSITIVE).ln();
+	(count as f64 + noise).round().max(0.0) as u64
+}
+
+fn now_ms() -> u64 {
+	SystemTime::now()
+		.duration_since(UNIX_EPOCH)
+		.map(|d| d.as_millis() as u64)
+		.unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn usage(report: &UsageReport) -> Vec<(&str, u64, u64)> {
+		report
+			.tools
+			.iter()
+			.map(|t| (t.tool.as_str(), t.calls, t.callers))
+			.collect()
+	}
+
+	#[test]
+	fn test_exact_counts() {
+		let analytics = UsageAnalytics::new(DEFAULT_USAGE_INTERVAL, None);
+		analytics.record("search", "sub:alice");
+		analytics.record("search", "sub:alice");
+		analytics.record("search", "sub:bob");
+		analytics.record("fetch", "session:1");
+
+		assert!(analytics.last_report().is_none());
+		let current = analytics.current_report().unwrap();
+		assert_eq!(usage(&current), [("fetch", 1, 1), ("search", 3, 2)]);
+
+		analytics.close_window();
+		let last = analytics.last_report().unwrap();
+		assert_eq!(usage(&last), usage(&current));
+		assert!(!last.private);
+		assert!(analytics.current_report().unwrap().tools.is_empty());
+		// Callers are only ever reported as counts
+		assert!(!serde_json::to_string(&last).unwrap().contains("alice"));
+	}
+
+	#[test]
+	fn test_private_counts_are_bounded_and_thresholded() {
+		// A huge budget makes the noise negligible, so the counts can be checked
+		let privacy = UsagePrivacy {
+			min_callers: 3,
+			epsilon: 1e9,
+			max_calls_per_caller: 2,
+		};
+		let analytics = UsageAnalytics::new(DEFAULT_USAGE_INTERVAL, Some(privacy));
+		for caller in ["a", "b", "c"] {
+			for _ in 0..5 {
+				analytics.record("search", caller);
+			}
+		}
+		analytics.record("rare", "a");
+		analytics.record("rare", "b");
+
+		assert!(analytics.current_report().is_none());
+		analytics.close_window();
+		let report = analytics.last_report().unwrap();
+		assert!(report.private);
+		assert_eq!(usage(&report), [("search", 6, 3)]);
+		// Reading again doesn't draw new noise
+		assert_eq!(analytics.last_report().unwrap(), report);
+	}
+
+	#[test]
+	fn test_noise() {
+		let samples: Vec<u64> = (0..2000).map(|_| noisy(100, 2.0)).collect();
+		let mean = samples.iter().sum::<u64>() as f64 / samples.len() as f64;
+		assert!((mean - 100.0).abs() < 1.0, "mean {mean}");
+		assert!(samples.iter().any(|s| *s != 100));
+	}
+
+	#[test]
+	fn test_windows_roll_over() {
+		let analytics = UsageAnalytics::new(Duration::from_millis(20), None);
+		analytics.record("search", "a");
+		std::thread::sleep(Duration::from_millis(30));
+		let report = analytics.last_report().unwrap();
+		assert_eq!(usage(&report), [("search", 1, 1)]);
+		assert_eq!(report.until_ms - report.since_ms, 20);
+	}
+}
@@ -6,8 +6,18 @@
 // - Schema reference validation
 // - Deprecation warnings
 // - Version constraint validation
-
-use super::types::Registry;
+// - JSONPath and template checks
+
+use std::borrow::Cow;
+
+use super::fan_out;
+use super::patterns::{
+	AggregationOp, AggregationStrategy, DataBinding, FieldPredicate, FieldSource, MapEachInner,
+	MergeStrategy, PatternSpec, RankScorer, ScatterOperation, StepOperation,
+};
+use super::types::{CostModel, OutputTransform, Registry, ToolImplementation};
+use serde_json::Value;
+use serde_json_path::JsonPath;
 use thiserror::Error;
 
 /// Validation errors for registry v2
@@ -54,6 +64,9 @@ pub enum ValidationError {
 
 	#[error("duplicate agent name: '{0}'")]
 	DuplicateAgentName(String),
+
+	#[error("unknown field '{field}' at {pointer}")]
+	UnknownField { pointer: String, field: String },
 }
 
 /// Validation warning (non-fatal)
@@ -61,6 +74,17 @@ pub enum ValidationError {
 pub struct ValidationWarning {
 	pub message: String,
 	pub tool: Option<String>,
+	/// RFC 6901 JSON pointer to the offending value (e.g. `/tools/0/spec/pipeline/steps/1/input`)
+	pub pointer: Option<String>,
+}
+
+impl std::fmt::Display for ValidationWarning {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match &self.pointer {
+			Some(pointer) => write!(f, "{} at {}", self.message, pointer),
+			None => f.write_str(&self.message),
+		}
+	}
 }
 
 /// Result of registry validation
@@ -115,9 +139,9 @@ impl<'a> RegistryValidator<'a> {
 
 	/// Validate the registry and return all errors and warnings
 	pub fn validate(&self) -> ValidationResult {
-		// TODO(WP3): Implement full validation
-		// This is a stub that returns Ok - tests will fail until implemented
-		ValidationResult::ok()
+		let mut result = self.validate_paths();
+		result.warnings.extend(self.validate_fan_out().warnings);
+		result
 	}
 
 	/// Check for duplicate names (tools, schemas, servers, agents)
@@ -155,6 +179,495 @@ impl<'a> RegistryValidator<'a> {
 		// TODO(WP3): Implement version constraint validation
 		ValidationResult::ok()
 	}
+
+	/// Check the JSONPaths and templates of compositions and output transforms
+	///
+	/// Paths are otherwise only parsed when a call first evaluates them, and a path that
+	/// matches nothing quietly yields null, so typos like `$input.query` (for `$.query`) or a
+	/// template placeholder without a variable are reported here as warnings.
+	pub fn validate_paths(&self) -> ValidationResult {
+		let mut result = ValidationResult::ok();
+		for (i, tool) in self.registry.tools.iter().enumerate() {
+			let mut check = PathCheck {
+				tool: &tool.name,
+				result: &mut result,
+			};
+			let pointer = format!("/tools/{}", i);
+			if let ToolImplementation::Spec(spec) = &tool.implementation {
+				check.pattern(spec, &format!("{}/spec", pointer));
+			}
+			if let Some(transform) = &tool.output_transform {
+				check.output_transform(transform, &format!("{}/outputTransform", pointer));
+			}
+			if let Some(CostModel::Reported(cost)) = &tool.cost {
+				check.path(&cost.path, &format!("{}/cost/reported/path", pointer));
+			}
+		}
+		result
+	}
+
+	/// Flag compositions whose mapEach over an unbounded array multiplies the calls they make,
+	/// with an estimate of the worst case
+	pub fn validate_fan_out(&self) -> ValidationResult {
+		let mut result = ValidationResult::ok();
+		for (i, tool) in self.registry.tools.iter().enumerate() {
+			let ToolImplementation::Spec(spec) = &tool.implementation else {
+				continue;
+			};
+			for warning in fan_out::check(spec, &format!("/tools/{}/spec", i)) {
+				result.add_warning(ValidationWarning {
+					message: warning.message,
+					tool: Some(tool.name.clone()),
+					pointer: Some(warning.pointer),
+				});
+			}
+		}
+		result
+	}
+}
+
+/// Walks a tool definition, checking every JSONPath, key path and template it holds
+///
+/// Pointers are built with the names fields and variants have in registry documents, so
+/// warnings point at the offending value.
+struct PathCheck<'a> {
+	tool: &'a str,
+	result: &'a mut ValidationResult,
+}
+
+impl PathCheck<'_> {
+	fn warn(&mut self, pointer: &str, message: String) {
+		self.result.add_warning(ValidationWarning {
+			message,
+			tool: Some(self.tool.to_string()),
+			pointer: Some(pointer.to_string()),
+		})
+	}
+
+	fn path(&mut self, path: &str, pointer: &str) {
+		if let Some(message) = check_path(path) {
+			self.warn(pointer, message);
+		}
+	}
+
+	/// Cache and idempotency keys are dot paths unless they start with the `$` root
+	fn key_path(&mut self, path: &str, pointer: &str) {
+		if path.starts_with('$') {
+			self.path(path, pointer);
+		}
+	}
+
+	fn key_paths(&mut self, paths: &[String], pointer: &str) {
+		for (i, path) in paths.iter().enumerate() {
+			self.key_path(path, &format!("{}/{}", pointer, i));
+		}
+	}
+
+	fn template<'v>(
+		&mut self,
+		template: &str,
+		vars: impl Iterator<Item = &'v String>,
+		pointer: &str,
+	) {
+		let vars: Vec<&str> = vars.map(String::as_str).collect();
+		for message in check_template(template, &vars) {
+			self.warn(pointer, message);
+		}
+	}
+
+	fn predicate(&mut self, predicate: &FieldPredicate, pointer: &str) {
+		self.path(&predicate.field, &format!("{}/field", pointer));
+	}
+
+	/// Predicates of a cache read their field as a key path
+	fn cache_predicate(&mut self, predicate: &FieldPredicate, pointer: &str) {
+		self.key_path(&predicate.field, &format!("{}/field", pointer));
+	}
+
+	fn pattern(&mut self, spec: &PatternSpec, pointer: &str) {
+		match spec {
+			PatternSpec::Pipeline(p) => {
+				for (i, step) in p.steps.iter().enumerate() {
+					let pointer = format!("{}/pipeline/steps/{}", pointer, i);
+					self.operation(&step.operation, &format!("{}/operation", pointer));
+					if let Some(input) = &step.input {
+						self.binding(input, &format!("{}/input", pointer));
+					}
+					if let Some(session) = &step.session_set {
+						self.path(&session.path, &format!("{}/sessionSet/path", pointer));
+					}
+					if let Some(cache) = &step.cache {
+						self.key_paths(&cache.key_paths, &format!("{}/cache/keyPaths", pointer));
+					}
+				}
+			},
+			PatternSpec::ScatterGather(sg) => {
+				let pointer = format!("{}/scatterGather", pointer);
+				for (i, target) in sg.targets.iter().enumerate() {
+					if let ScatterOperation::Pattern(p) = &target.operation {
+						self.pattern(p, &format!("{}/targets/{}/pattern", pointer, i));
+					}
+				}
+				self.aggregation(&sg.aggregation, &format!("{}/aggregation", pointer));
+			},
+			PatternSpec::Filter(f) => {
+				self.predicate(&f.predicate, &format!("{}/filter/predicate", pointer))
+			},
+			PatternSpec::SchemaMap(m) => {
+				self.mappings(&m.mappings, &format!("{}/schemaMap/mappings", pointer))
+			},
+			PatternSpec::MapEach(me) => {
+				if let MapEachInner::Pattern(p) = &me.inner {
+					self.pattern(p, &format!("{}/mapEach/inner/pattern", pointer));
+				}
+			},
+			PatternSpec::Rank(r) => {
+				let pointer = format!("{}/rank", pointer);
+				if let Some(path) = &r.items_path {
+					self.path(path, &format!("{}/itemsPath", pointer));
+				}
+				let scorer = format!("{}/scorer", pointer);
+				match &r.scorer {
+					RankScorer::Fields(fields) => {
+						for (i, field) in fields.iter().enumerate() {
+							self.path(&field.path, &format!("{}/fields/{}/path", scorer, i));
+						}
+					},
+					RankScorer::Embedding(e) => {
+						self.path(&e.query_path, &format!("{}/embedding/queryPath", scorer));
+						self.path(
+							&e.content_path,
+							&format!("{}/embedding/contentPath", scorer),
+						);
+					},
+					RankScorer::Llm(l) => {
+						if let Some(path) = &l.query_path {
+							self.path(path, &format!("{}/llm/queryPath", scorer));
+						}
+					},
+				}
+			},
+			PatternSpec::Retry(r) => {
+				let pointer = format!("{}/retry", pointer);
+				self.operation(&r.inner, &format!("{}/inner", pointer));
+				if let Some(predicate) = &r.retry_if {
+					self.predicate(predicate, &format!("{}/retryIf", pointer));
+				}
+			},
+			PatternSpec::Timeout(t) => {
+				let pointer = format!("{}/timeout", pointer);
+				self.operation(&t.inner, &format!("{}/inner", pointer));
+				if let Some(fallback) = &t.fallback {
+					self.operation(fallback, &format!("{}/fallback", pointer));
+				}
+			},
+			PatternSpec::Cache(c) => {
+				let pointer = format!("{}/cache", pointer);
+				self.key_paths(&c.key_paths, &format!("{}/keyPaths", pointer));
+				self.operation(&c.inner, &format!("{}/inner", pointer));
+				if let Some(predicate) = &c.cache_if {
+					self.cache_predicate(predicate, &format!("{}/cacheIf", pointer));
+				}
+				if let Some(predicate) = &c.stale_if {
+					self.cache_predicate(predicate, &format!("{}/staleIf", pointer));
+				}
+			},
+			PatternSpec::Idempotent(i) => {
+				let pointer = format!("{}/idempotent", pointer);
+				self.key_paths(&i.key_paths, &format!("{}/keyPaths", pointer));
+				self.operation(&i.inner, &format!("{}/inner", pointer));
+			},
+			PatternSpec::CircuitBreaker(cb) => {
+				let pointer = format!("{}/circuitBreaker", pointer);
+				self.operation(&cb.inner, &format!("{}/inner", pointer));
+				if let Some(fallback) = &cb.fallback {
+					self.operation(fallback, &format!("{}/fallback", pointer));
+				}
+				if let Some(predicate) = &cb.failure_if {
+					self.predicate(predicate, &format!("{}/failureIf", pointer));
+				}
+			},
+			PatternSpec::DeadLetter(dl) => {
+				self.operation(&dl.inner, &format!("{}/deadLetter/inner", pointer))
+			},
+			PatternSpec::Saga(saga) => {
+				let pointer = format!("{}/saga", pointer);
+				for (i, step) in saga.steps.iter().enumerate() {
+					let pointer = format!("{}/steps/{}", pointer, i);
+					self.operation(&step.action, &format!("{}/action", pointer));
+					if let Some(compensate) = &step.compensate {
+						self.operation(compensate, &format!("{}/compensate", pointer));
+					}
+					self.binding(&step.input, &format!("{}/input", pointer));
+				}
+				if let Some(path) = &saga.saga_id_path {
+					self.path(path, &format!("{}/sagaIdPath", pointer));
+				}
+				if let Some(output) = &saga.output {
+					self.binding(output, &format!("{}/output", pointer));
+				}
+			},
+			PatternSpec::ClaimCheck(cc) => {
+				self.operation(&cc.inner, &format!("{}/claimCheck/inner", pointer))
+			},
+			PatternSpec::Throttle(t) => self.operation(&t.inner, &format!("{}/throttle/inner", pointer)),
+			PatternSpec::Router(r) => {
+				let pointer = format!("{}/router", pointer);
+				for (i, route) in r.routes.iter().enumerate() {
+					let pointer = format!("{}/routes/{}", pointer, i);
+					self.predicate(&route.when, &format!("{}/when", pointer));
+					self.operation(&route.then, &format!("{}/then", pointer));
+				}
+				if let Some(otherwise) = &r.otherwise {
+					self.operation(otherwise, &format!("{}/otherwise", pointer));
+				}
+			},
+			PatternSpec::Enricher(e) => {
+				let pointer = format!("{}/enricher", pointer);
+				for (i, enrichment) in e.enrichments.iter().enumerate() {
+					let pointer = format!("{}/enrichments/{}", pointer, i);
+					self.operation(&enrichment.operation, &format!("{}/operation", pointer));
+					if let Some(input) = &enrichment.input {
+						self.binding(input, &format!("{}/input", pointer));
+					}
+				}
+				if let MergeStrategy::SchemaMap(m) = &e.merge {
+					self.mappings(
+						&m.mappings,
+						&format!("{}/merge/schemaMap/mappings", pointer),
+					);
+				}
+			},
+			PatternSpec::WireTap(wt) => {
+				let pointer = format!("{}/wireTap", pointer);
+				self.operation(&wt.inner, &format!("{}/inner", pointer));
+				for (i, tap) in wt.taps.iter().enumerate() {
+					if let Some(transform) = &tap.transform {
+						let pointer = format!("{}/taps/{}/transform/mappings", pointer, i);
+						self.mappings(&transform.mappings, &pointer);
+					}
+				}
+			},
+			PatternSpec::RecipientList(rl) => {
+				let pointer = format!("{}/recipientList", pointer);
+				if let Some(path) = &rl.recipients_path {
+					self.path(path, &format!("{}/recipientsPath", pointer));
+				}
+				if let Some(aggregation) = &rl.aggregation {
+					self.aggregation(aggregation, &format!("{}/aggregation", pointer));
+				}
+			},
+			PatternSpec::CapabilityRouter(cr) => {
+				if let Some(fallback) = &cr.fallback {
+					self.operation(fallback, &format!("{}/capabilityRouter/fallback", pointer));
+				}
+			},
+			PatternSpec::SemanticDedup(sd) => {
+				self.path(
+					&sd.content_path,
+					&format!("{}/semanticDedup/contentPath", pointer),
+				);
+			},
+			PatternSpec::ConfidenceAggregator(ca) => {
+				for (i, source) in ca.sources.iter().enumerate() {
+					let pointer = format!("{}/confidenceAggregator/sources/{}/operation", pointer, i);
+					self.operation(&source.operation, &pointer);
+				}
+			},
+		}
+	}
+
+	fn operation(&mut self, op: &StepOperation, pointer: &str) {
+		match op {
+			StepOperation::Pattern(p) => self.pattern(p, &format!("{}/pattern", pointer)),
+			StepOperation::Composition(cc) => {
+				let pointer = format!("{}/composition", pointer);
+				if let Some(input) = &cc.input {
+					self.mappings(input, &format!("{}/input", pointer));
+				}
+				if let Some(output) = &cc.output {
+					self.mappings(output, &format!("{}/output", pointer));
+				}
+			},
+			StepOperation::Assert(a) => {
+				self.predicate(&a.predicate, &format!("{}/assert/predicate", pointer))
+			},
+			StepOperation::Patch(patch) => {
+				// JSON Patch locations are JSON Pointers and merge patches are data, so only the
+				// variables are paths
+				for (name, path) in sorted(&patch.vars) {
+					self.path(path, &format!("{}/patch/vars/{}", pointer, escape_pointer_token(name)));
+				}
+			},
+			StepOperation::Tool(_)
+			| StepOperation::Script(_)
+			| StepOperation::Sample(_)
+			| StepOperation::Constant(_) => {},
+		}
+	}
+
+	fn binding(&mut self, binding: &DataBinding, pointer: &str) {
+		match binding {
+			DataBinding::Input(b) => self.path(&b.path, &format!("{}/input/path", pointer)),
+			DataBinding::Step(b) => self.path(&b.path, &format!("{}/step/path", pointer)),
+			DataBinding::Session(b) => self.path(&b.path, &format!("{}/session/path", pointer)),
+			DataBinding::Construct(c) => {
+				for (name, field) in sorted(&c.fields) {
+					self.binding(
+						field,
+						&format!("{}/construct/fields/{}", pointer, escape_pointer_token(name)),
+					);
+				}
+			},
+			DataBinding::Template(t) => {
+				let pointer = format!("{}/template", pointer);
+				self.template(&t.template, t.vars.keys(), &format!("{}/template", pointer));
+				for (name, var) in sorted(&t.vars) {
+					self.binding(var, &format!("{}/vars/{}", pointer, escape_pointer_token(name)));
+				}
+			},
+			DataBinding::Constant(_) => {},
+		}
+	}
+
+	fn mappings(&mut self, mappings: &HashMap<String, FieldSource>, pointer: &str) {
+		for (name, source) in sorted(mappings) {
+			self.source(source, &format!("{}/{}", pointer, escape_pointer_token(name)));
+		}
+	}
+
+	fn source(&mut self, source: &FieldSource, pointer: &str) {
+		match source {
+			FieldSource::Path(path) => self.path(path, &format!("{}/path", pointer)),
+			FieldSource::Coalesce(c) => {
+				for (i, path) in c.paths.iter().enumerate() {
+					self.path(path, &format!("{}/coalesce/paths/{}", pointer, i));
+				}
+			},
+			FieldSource::Concat(c) => {
+				for (i, path) in c.paths.iter().enumerate() {
+					self.path(path, &format!("{}/concat/paths/{}", pointer, i));
+				}
+			},
+			FieldSource::Template(t) => {
+				let pointer = format!("{}/template", pointer);
+				self.template(&t.template, t.vars.keys(), &format!("{}/template", pointer));
+				for (name, path) in sorted(&t.vars) {
+					self.path(path, &format!("{}/vars/{}", pointer, escape_pointer_token(name)));
+				}
+			},
+			FieldSource::Nested(m) => self.mappings(&m.mappings, &format!("{}/nested/mappings", pointer)),
+			FieldSource::Conditional(c) => {
+				let pointer = format!("{}/conditional", pointer);
+				self.predicate(&c.when, &format!("{}/when", pointer));
+				self.source(&c.then, &format!("{}/then", pointer));
+				if let Some(fallback) = &c.fallback {
+					self.source(fallback, &format!("{}/fallback", pointer));
+				}
+			},
+			FieldSource::Coerce(c) => self.source(&c.source, &format!("{}/coerce/source", pointer)),
+			FieldSource::Literal(_) => {},
+		}
+	}
+
+	fn aggregation(&mut self, aggregation: &AggregationStrategy, pointer: &str) {
+		for (i, op) in aggregation.ops.iter().enumerate() {
+			let pointer = format!("{}/ops/{}", pointer, i);
+			match op {
+				AggregationOp::Sort(sort) => self.path(&sort.field, &format!("{}/sort/field", pointer)),
+				AggregationOp::Dedupe(dedupe) => {
+					self.path(&dedupe.field, &format!("{}/dedupe/field", pointer))
+				},
+				AggregationOp::TokenBudget(budget) => {
+					if let Some(path) = &budget.text_field {
+						self.path(path, &format!("{}/tokenBudget/textField", pointer));
+					}
+				},
+				AggregationOp::Flatten(_)
+				| AggregationOp::Limit(_)
+				| AggregationOp::Concat(_)
+				| AggregationOp::Merge(_) => {},
+			}
+		}
+	}
+
+	fn output_transform(&mut self, transform: &OutputTransform, pointer: &str) {
+		self.mappings(&transform.mappings, &format!("{}/mappings", pointer));
+		for (i, variant) in transform.variants.iter().enumerate() {
+			let pointer = format!("{}/variants/{}", pointer, i);
+			self.predicate(&variant.when, &format!("{}/when", pointer));
+			self.mappings(&variant.mappings, &format!("{}/mappings", pointer));
+		}
+	}
+}
+
+/// Map entries in name order, so warnings come out in a stable order
+fn sorted<V>(map: &HashMap<String, V>) -> Vec<(&String, &V)> {
+	let mut entries: Vec<_> = map.iter().collect();
+	entries.sort_by(|a, b| a.0.cmp(b.0));
+	entries
+}
+
+/// Escape a key as a single JSON Pointer reference token (RFC 6901), so it can't add
+/// segments of its own
+pub(crate) fn escape_pointer_token(key: &str) -> Cow<'_, str> {
+	if key.contains(['~', '/']) {
+		Cow::Owned(key.replace('~', "~0").replace('/', "~1"))
+	} else {
+		Cow::Borrowed(key)
+	}
+}
+
+/// Describe what's wrong with a JSONPath, if anything
+fn check_path(path: &str) -> Option<String> {
+	// `$input`, `$steps` etc.: a variable-style root instead of `$`
+	if let Some(rest) = path.strip_prefix('$')
+		&& rest.starts_with(|c: char| c.is_ascii_alphabetic())
+	{
+		let name_len = rest
+			.find(|c: char| !c.is_ascii_alphanumeric() && c != '_')
+			.unwrap_or(rest.len());
+		let (name, rest) = rest.split_at(name_len);
+		return Some(format!(
+			"JSONPath '{}' starts with '${}'; paths are rooted at '$' (did you mean '${}'?)",
+			path, name, rest
+		));
+	}
+	JsonPath::parse(path)
+		.err()
+		.map(|e| format!("invalid JSONPath '{}': {}", path, e))
+}
+
+/// Report template placeholders without a variable, and variables the template never uses
+fn check_template(template: &str, vars: &[&str]) -> Vec<String> {
+	let placeholders: Vec<&str> = template
+		.split('{')
+		.skip(1)
+		.filter_map(|s| s.split_once('}').map(|(name, _)| name))
+		.filter(|name| {
+			!name.is_empty()
+				&& name
+					.chars()
+					.all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.'))
+		})
+		.collect();
+	let mut messages: Vec<String> = placeholders
+		.iter()
+		.filter(|name| !vars.contains(name))
+		.map(|name| format!("template placeholder '{{{}}}' has no variable", name))
+		.collect();
+	let mut unused: Vec<&str> = vars
+		.iter()
+		.copied()
+		.filter(|name| !placeholders.contains(name))
+		.collect();
+	unused.sort();
+	messages.extend(
+		unused
+			.into_iter()
+			.map(|name| format!("template variable '{}' is not used by the template", name)),
+	);
+	messages
 }
 
 /// Convenience function to validate a registry
@@ -162,6 +675,75 @@ pub fn validate_registry(registry: &Registry) -> ValidationResult {
 	RegistryValidator::new(registry).validate()
 }
 
+/// Parse a raw registry document, collecting the fields the registry model does not
+/// recognize (e.g. a typo like `hideFeilds`)
+///
+/// Locations are reported as RFC 6901 JSON pointers (e.g. `/tools/0/source/hideFeilds`).
+pub fn find_unknown_fields(
+	raw: &Value,
+) -> Result<(Registry, Vec<ValidationError>), serde_json::Error> {
+	let mut errors = Vec::new();
+	let registry = serde_ignored::deserialize(raw, |path| {
+		let (pointer, field) = pointer_to(raw, &path);
+		errors.push(ValidationError::UnknownField { pointer, field });
+	})?;
+	Ok((registry, errors))
+}
+
+/// JSON pointer and last key of an ignored field's path in `raw`
+fn pointer_to(raw: &Value, path: &serde_ignored::Path) -> (String, String) {
+	use serde_ignored::Path;
+
+	let mut keys = Vec::new();
+	let mut path = path;
+	loop {
+		path = match path {
+			Path::Root => break,
+			Path::Seq { parent, index } => {
+				keys.push(Some(index.to_string()));
+				parent
+			},
+			Path::Map { parent, key } => {
+				keys.push(Some(key.clone()));
+				parent
+			},
+			// The variant name isn't part of the path; it is read back from the document
+			Path::NewtypeVariant { parent } => {
+				keys.push(None);
+				parent
+			},
+			Path::Some { parent } | Path::NewtypeStruct { parent } => parent,
+		};
+	}
+
+	let mut pointer = String::new();
+	let mut field = String::new();
+	let mut value = Some(raw);
+	for key in keys.into_iter().rev() {
+		// Externally tagged variants are objects keyed by the variant name alone. Struct
+		// variants leave no trace in the path, so a key missing from such an object is
+		// looked up inside it.
+		let variant = match (value, &key) {
+			(Some(Value::Object(fields)), key) if fields.len() == 1 => match key {
+				Some(key) if fields.contains_key(key) => None,
+				_ => fields.keys().next().cloned(),
+			},
+			_ => None,
+		};
+		for key in variant.into_iter().chain(key) {
+			value = value.and_then(|v| match v {
+				Value::Array(items) => key.parse().ok().and_then(|i: usize| items.get(i)),
+				Value::Object(fields) => fields.get(&key),
+				_ => None,
+			});
+			pointer.push('/');
+			pointer.push_str(&escape_pointer_token(&key));
+			field = key;
+		}
+	}
+	(pointer, field)
+}
+
 #[cfg(test)]
 mod tests {
 	use super::*;
@@ -776,4 +1358,161 @@ mod tests {
 		// Stub implementation returns Ok, so this passes
 		assert!(result.is_ok(), "Valid registry should pass validation");
 	}
+
+	// =============================================================================
+	// JSONPath and template checks
+	// =============================================================================
+
+	#[test]
+	fn test_warn_on_suspicious_paths() {
+		let registry: Registry = serde_json::from_value(serde_json::json!({
+			"schemaVersion": "1.0",
+			"tools": [{
+				"name": "research",
+				"spec": {
+					"pipeline": {
+						"steps": [
+							{"id": "search", "operation": {"tool": {"name": "web_search"}}, "input": {"input": {"path": "$input.query"}}},
+							{
+								"id": "pick",
+								"operation": {"pattern": {"filter": {"predicate": {"field": "$.score[", "op": "gt", "value": {"numberValue": 0.5}}}}},
+								"input": {"step": {"stepId": "search", "path": "$.results"}}
+							},
+							{
+								"id": "label",
+								"operation": {"pattern": {"schemaMap": {"mappings": {
+									"title": {"template": {"template": "{source}: {titel}", "vars": {"source": "$.source", "title": "$.title"}}},
+									"query": {"literal": {"stringValue": "$input"}}
+								}}}},
+								"input": {"step": {"stepId": "pick", "path": "$"}}
+							}
+						]
+					}
+				}
+			}]
+		}))
+		.unwrap();
+
+		let result = validate_registry(&registry);
+		assert!(result.is_ok());
+		let warnings: Vec<String> = result.warnings.iter().map(|w| w.to_string()).collect();
+		let steps = "/tools/0/spec/pipeline/steps";
+		let parse_error = JsonPath::parse("$.score[").unwrap_err();
+		assert_eq!(
+			warnings,
+			vec![
+				format!(
+					"JSONPath '$input.query' starts with '$input'; paths are rooted at '$' (did you mean '$.query'?) at {steps}/0/input/input/path"
+				),
+				format!(
+					"invalid JSONPath '$.score[': {parse_error} at {steps}/1/operation/pattern/filter/predicate/field"
+				),
+				format!(
+					"template placeholder '{{titel}}' has no variable at {steps}/2/operation/pattern/schemaMap/mappings/title/template/template"
+				),
+				format!(
+					"template variable 'title' is not used by the template at {steps}/2/operation/pattern/schemaMap/mappings/title/template/template"
+				),
+			]
+		);
+		assert!(
+			result
+				.warnings
+				.iter()
+				.all(|w| w.tool.as_deref() == Some("research"))
+		);
+
+		// Cache keys are dot paths, so only a variable-style root is reported
+		let registry: Registry = serde_json::from_value(serde_json::json!({
+			"schemaVersion": "1.0",
+			"tools": [{
+				"name": "cached_search",
+				"spec": {
+					"cache": {
+						"keyPaths": ["query", "$input.region"],
+						"inner": {"tool": {"name": "web_search"}},
+						"store": "default",
+						"ttlSeconds": 60
+					}
+				}
+			}]
+		}))
+		.unwrap();
+		let result = validate_registry(&registry);
+		assert_eq!(result.warnings.len(), 1);
+		assert_eq!(
+			result.warnings[0].pointer.as_deref(),
+			Some("/tools/0/spec/cache/keyPaths/1")
+		);
+
+		// Merge patches are data written into the step's input, not paths
+		let registry: Registry = serde_json::from_value(serde_json::json!({
+			"schemaVersion": "1.0",
+			"tools": [{
+				"name": "reassign",
+				"spec": {
+					"pipeline": {
+						"steps": [{
+							"id": "patch",
+							"operation": {"patch": {"mergePatch": {"path": "$input.query", "filter": {"field": "$.score["}}}}
+						}]
+					}
+				}
+			}]
+		}))
+		.unwrap();
+		let result = validate_registry(&registry);
+		assert!(!result.has_warnings(), "{:?}", result.warnings);
+	}
+
+	#[test]
+	fn test_paths_are_checked_where_the_model_has_them() {
+		let registry: Registry = serde_json::from_value(serde_json::json!({
+			"schemaVersion": "1.0",
+			"tools": [{
+				"name": "digest",
+				"spec": {
+					"pipeline": {
+						"steps": [
+							{
+								"id": "extra",
+								"operation": {"pattern": {"enricher": {
+									"enrichments": [{"field": "$extra", "operation": {"tool": {"name": "lookup"}}}],
+									"merge": "spread"
+								}}}
+							},
+							{
+								"id": "gather",
+								"operation": {"pattern": {"scatterGather": {
+									"targets": [{"tool": "a"}, {"tool": "b"}],
+									"aggregation": {"ops": [
+										{"sort": {"field": "score", "order": "desc"}},
+										{"tokenBudget": {"maxTokens": 100, "textField": "$input.text"}}
+									]}
+								}}}
+							}
+						]
+					}
+				}
+			}]
+		}))
+		.unwrap();
+
+		// An enrichment's field is a name, while sort fields and token budget text fields are
+		// JSONPaths whatever they are called
+		let result = validate_registry(&registry);
+		let pointers: Vec<_> = result
+			.warnings
+			.iter()
+			.map(|w| w.pointer.as_deref().unwrap())
+			.collect();
+		let gather = "/tools/0/spec/pipeline/steps/1/operation/pattern/scatterGather/aggregation/ops";
+		assert_eq!(
+			pointers,
+			vec![
+				format!("{gather}/0/sort/field"),
+				format!("{gather}/1/tokenBudget/textField"),
+			]
+		);
+	}
 }
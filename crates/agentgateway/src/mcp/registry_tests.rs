@@ -0,0 +1,412 @@
+// End-to-end tests of virtual tools and compositions
+//
+// Each test starts three in-process MCP servers as the targets of one multiplexed backend:
+// `echo` returns its arguments, `delay` sleeps before answering and `failing` rejects every
+// call. A registry is served on top of them, and a client talks to the gateway over the
+// streamable HTTP transport, so calls go through the session, the Relay, the registry and
+// the composition executor as they do in production.
+
+use std::net::SocketAddr;
+use std::time::{Duration, Instant};
+
+use agent_core::strng;
+use itertools::Itertools;
+use rmcp::RoleClient;
+use rmcp::ServiceError;
+use rmcp::model::{CallToolRequestParam, CallToolResult, ErrorCode, InitializeRequestParam};
+use rmcp::service::RunningService;
+use rmcp::transport::StreamableHttpServerConfig;
+use serde_json::{Value, json};
+
+use crate::mcp::registry::{ArgumentCheckPolicy, RegistryStore};
+use crate::mcp::tests::mcp_streamable_client;
+use crate::test_helpers::proxymock::{TestBind, basic_named_route, setup_proxy_test, simple_bind};
+
+#[tokio::test]
+async fn virtual_tools_are_listed_and_called_by_registry_name() {
+	let harness = Harness::start(json!([
+		{
+			"name": "shout",
+			"description": "Echo loudly",
+			"source": {"target": "echo", "tool": "echo", "defaults": {"volume": "loud"}}
+		}
+	]))
+	.await;
+
+	let tools = harness.tool_names().await;
+	assert!(tools.contains(&"shout".to_string()), "{tools:?}");
+	// The source tool is replaced by its virtual tool, other backend tools are prefixed
+	assert!(!tools.contains(&"echo_echo".to_string()), "{tools:?}");
+	assert!(tools.contains(&"delay_sleep".to_string()), "{tools:?}");
+
+	let result = harness.call("shout", json!({"text": "hi"})).await.unwrap();
+	assert_eq!(text_json(&result), json!({"text": "hi", "volume": "loud"}));
+
+	// Backend tools are still reachable by their prefixed names
+	let result = harness.call("delay_sleep", json!({"ms": 1})).await.unwrap();
+	assert_eq!(text_json(&result), json!({"sleptMs": 1}));
+}
+
+#[tokio::test]
+async fn pipeline_passes_results_between_targets() {
+	let harness = Harness::start(json!([
+		{
+			"name": "shout",
+			"source": {"target": "echo", "tool": "echo", "defaults": {"volume": "loud"}}
+		},
+		{
+			"name": "nap_then_shout",
+			"description": "Sleep, then echo how long",
+			"spec": {"pipeline": {"steps": [
+				{
+					"id": "nap",
+					"operation": {"tool": {"name": "delay_sleep"}},
+					"input": {"construct": {"fields": {"ms": {"input": {"path": "$.ms"}}}}}
+				},
+				{
+					"id": "report",
+					"operation": {"tool": {"name": "shout"}},
+					"input": {"construct": {"fields": {
+						"slept": {"step": {"stepId": "nap", "path": "$.sleptMs"}}
+					}}}
+				}
+			]}},
+			"inputSchema": {
+				"type": "object",
+				"properties": {"ms": {"type": "integer"}},
+				"required": ["ms"]
+			}
+		}
+	]))
+	.await;
+
+	assert!(
+		harness
+			.tool_names()
+			.await
+			.contains(&"nap_then_shout".to_string())
+	);
+	let result = harness
+		.call("nap_then_shout", json!({"ms": 5}))
+		.await
+		.unwrap();
+	assert_eq!(text_json(&result), json!({"slept": 5, "volume": "loud"}));
+}
+
+#[tokio::test]
+async fn scatter_gather_calls_targets_concurrently() {
+	let harness = Harness::start(json!([
+		{
+			"name": "nap_twice",
+			"spec": {"scatterGather": {
+				"targets": [{"tool": "delay_sleep"}, {"tool": "delay_sleep"}],
+				"aggregation": {"ops": []}
+			}}
+		}
+	]))
+	.await;
+
+	let started = Instant::now();
+	let result = harness.call("nap_twice", json!({"ms": 400})).await.unwrap();
+	let elapsed = started.elapsed();
+	assert_eq!(
+		text_json(&result),
+		json!([{"sleptMs": 400}, {"sleptMs": 400}])
+	);
+	assert!(elapsed < Duration::from_millis(750), "took {elapsed:?}");
+}
+
+#[tokio::test]
+async fn failing_upstream_fails_the_composition() {
+	let harness = Harness::start(json!([
+		{
+			"name": "echo_then_fail",
+			"spec": {"pipeline": {"steps": [
+				{"id": "first", "operation": {"tool": {"name": "echo_echo"}}, "input": {"input": {"path": "$"}}},
+				{"id": "second", "operation": {"tool": {"name": "failing_fail"}}, "input": {"step": {"stepId": "first", "path": "$"}}}
+			]}}
+		}
+	]))
+	.await;
+
+	match harness.call("echo_then_fail", json!({"text": "hi"})).await {
+		Err(ServiceError::McpError(error)) => assert!(
+			error.message.contains("upstream failure"),
+			"unexpected error: {}",
+			error.message
+		),
+		other => panic!("expected the composition to fail, got: {other:?}"),
+	}
+
+	// The session survives the failure
+	let result = harness
+		.call("echo_echo", json!({"text": "hi"}))
+		.await
+		.unwrap();
+	assert_eq!(text_json(&result), json!({"text": "hi"}));
+}
+
+#[tokio::test]
+async fn invalid_arguments_are_reported_together() {
+	let store = RegistryStore::new().with_input_checks(ArgumentCheckPolicy::Enforce);
+	let harness = Harness::start_with(
+		store,
+		json!([
+			{
+				"name": "nap",
+				"spec": {"pipeline": {"steps": [
+					{"id": "nap", "operation": {"tool": {"name": "delay_sleep"}}, "input": {"input": {"path": "$"}}}
+				]}},
+				"inputSchema": {
+					"type": "object",
+					"properties": {
+						"ms": {"type": "integer"},
+						"mode": {"enum": ["light", "deep"]}
+					},
+					"required": ["ms", "mode"],
+					"additionalProperties": false
+				}
+			}
+		]),
+	)
+	.await;
+
+	match harness
+		.call("nap", json!({"ms": "five", "snore": true}))
+		.await
+	{
+		Err(ServiceError::McpError(error)) => {
+			assert_eq!(error.code, ErrorCode::INVALID_PARAMS);
+			assert_eq!(
+				error.message,
+				"invalid arguments for tool nap: /: missing required field 'mode'; /ms: expected integer, got string; /snore: unknown field"
+			);
+			assert_eq!(
+				error.data.unwrap()["problems"][0],
+				json!({"pointer": "/", "message": "missing required field 'mode'"})
+			);
+		},
+		other => panic!("expected the call to be refused, got: {other:?}"),
+	}
+
+	// Quoted numbers are converted
+	let result = harness
+		.call("nap", json!({"ms": "5", "mode": "light"}))
+		.await
+		.unwrap();
+	assert_eq!(text_json(&result), json!({"sleptMs": 5}));
+}
+
+/// A gateway serving a registry over the echo, delay and failing servers, and a client
+/// connected to it
+struct Harness {
+	client: RunningService<RoleClient, InitializeRequestParam>,
+	_bind: TestBind,
+	_upstreams: Vec<Upstream>,
+}
+
+impl Harness {
+	async fn start(tools: Value) -> Self {
+		Self::start_with(RegistryStore::new(), tools).await
+	}
+
+	/// Serve the registry from `store`, configured by the test
+	async fn start_with(store: RegistryStore, tools: Value) -> Self {
+		let echo = Upstream::start(upstreams::Echo::new).await;
+		let delay = Upstream::start(upstreams::Delay::new).await;
+		let failing = Upstream::start(upstreams::Failing::new).await;
+		let registry = serde_json::from_value(json!({"schemaVersion": "1.0", "tools": tools})).unwrap();
+		let bind = setup_proxy_test("{}")
+			.unwrap()
+			.with_multiplex_mcp_backend(
+				"mcp",
+				vec![
+					("echo", echo.addr, false),
+					("delay", delay.addr, false),
+					("failing", failing.addr, false),
+				],
+				true,
+			)
+			.with_bind(simple_bind(basic_named_route(strng::new("/mcp"))))
+			.with_registry_store(store, registry);
+		let addr = bind.serve_real_listener(strng::new("bind")).await;
+		Self {
+			client: mcp_streamable_client(addr).await,
+			_bind: bind,
+			_upstreams: vec![echo, delay, failing],
+		}
+	}
+
+	async fn tool_names(&self) -> Vec<String> {
+		let tools = self.client.list_tools(None).await.unwrap();
+		tools
+			.tools
+			.into_iter()
+			.map(|t| t.name.to_string())
+			.sorted()
+			.collect_vec()
+	}
+
+	async fn call(&self, name: &str, args: Value) -> Result<CallToolResult, ServiceError> {
+		self
+			.client
+			.call_tool(CallToolRequestParam {
+				name: name.to_string().into(),
+				arguments: args.as_object().cloned(),
+			})
+			.await
+	}
+}
+
+/// The first text content of a result, parsed as JSON
+fn text_json(result: &CallToolResult) -> Value {
+	let text = &result.content[0].raw.as_text().unwrap().text;
+	serde_json::from_str(text).unwrap_or_else(|_| Value::String(text.clone()))
+}
+
+/// An in-process MCP server, stopped when dropped
+struct Upstream {
+	addr: SocketAddr,
+	_cancel: tokio::sync::oneshot::Sender<()>,
+}
+
+impl Upstream {
+	async fn start<S: rmcp::ServerHandler>(service: impl Fn() -> S + Send + Sync + 'static) -> Self {
+		use rmcp::transport::streamable_http_server::StreamableHttpService;
+		use rmcp::transport::streamable_http_server::session::local::LocalSessionManager;
+
+		let service = StreamableHttpService::new(
+			move || Ok(service()),
+			LocalSessionManager::default().into(),
+			StreamableHttpServerConfig {
+				sse_keep_alive: None,
+				stateful_mode: true,
+				cancellation_token: Default::default(),
+			},
+		);
+		let (tx, rx) = tokio::sync::oneshot::channel();
+		let router = axum::Router::new().nest_service("/mcp", service);
+		let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+		let addr = listener.local_addr().unwrap();
+		tokio::spawn(async move {
+			let _ = axum::serve(listener, router)
+				.with_graceful_shutdown(async { rx.await.unwrap() })
+				.await;
+		});
+		Self { addr, _cancel: tx }
+	}
+}
+
+mod upstreams {
+	use std::time::Duration;
+
+	use rmcp::handler::server::router::tool::ToolRouter;
+	use rmcp::handler::server::wrapper::Parameters;
+	use rmcp::model::*;
+	use rmcp::{ErrorData as McpError, ServerHandler, schemars, tool, tool_handler, tool_router};
+	use serde_json::json;
+
+	fn info(instructions: &str) -> ServerInfo {
+		ServerInfo {
+			protocol_version: ProtocolVersion::V_2025_06_18,
+			capabilities: ServerCapabilities::builder().enable_tools().build(),
+			server_info: Implementation::from_build_env(),
+			instructions: Some(instructions.to_string()),
+		}
+	}
+
+	/// Returns its arguments
+	#[derive(Clone)]
+	pub struct Echo {
+		tool_router: ToolRouter<Echo>,
+	}
+
+	#[tool_router]
+	impl Echo {
+		pub fn new() -> Self {
+			Self {
+				tool_router: Self::tool_router(),
+			}
+		}
+
+		#[tool(description = "Repeat the arguments")]
+		fn echo(&self, Parameters(object): Parameters<JsonObject>) -> Result<CallToolResult, McpError> {
+			Ok(CallToolResult::success(vec![Content::text(
+				serde_json::Value::Object(object).to_string(),
+			)]))
+		}
+	}
+
+	#[tool_handler]
+	impl ServerHandler for Echo {
+		fn get_info(&self) -> ServerInfo {
+			info("Echoes tool arguments.")
+		}
+	}
+
+	#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+	pub struct SleepRequest {
+		/// How long to sleep, in milliseconds
+		pub ms: u64,
+	}
+
+	/// Answers after a delay
+	#[derive(Clone)]
+	pub struct Delay {
+		tool_router: ToolRouter<Delay>,
+	}
+
+	#[tool_router]
+	impl Delay {
+		pub fn new() -> Self {
+			Self {
+				tool_router: Self::tool_router(),
+			}
+		}
+
+		#[tool(description = "Sleep, then report how long")]
+		async fn sleep(
+			&self,
+			Parameters(SleepRequest { ms }): Parameters<SleepRequest>,
+		) -> Result<CallToolResult, McpError> {
+			tokio::time::sleep(Duration::from_millis(ms)).await;
+			Ok(CallToolResult::success(vec![Content::text(
+				json!({"sleptMs": ms}).to_string(),
+			)]))
+		}
+	}
+
+	#[tool_handler]
+	impl ServerHandler for Delay {
+		fn get_info(&self) -> ServerInfo {
+			info("Sleeps before answering.")
+		}
+	}
+
+	/// Rejects every call
+	#[derive(Clone)]
+	pub struct Failing {
+		tool_router: ToolRouter<Failing>,
+	}
+
+	#[tool_router]
+	impl Failing {
+		pub fn new() -> Self {
+			Self {
+				tool_router: Self::tool_router(),
+			}
+		}
+
+		#[tool(description = "Always fail")]
+		fn fail(&self) -> Result<CallToolResult, McpError> {
+			Err(McpError::internal_error("upstream failure", None))
+		}
+	}
+
+	#[tool_handler]
+	impl ServerHandler for Failing {
+		fn get_info(&self) -> ServerInfo {
+			info("Fails every call.")
+		}
+	}
+}
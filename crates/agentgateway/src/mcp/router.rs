@@ -1,6 +1,7 @@
 use std::sync::Arc;
 
 use agent_core::prelude::Strng;
+use agent_core::strng;
 use axum::http::StatusCode;
 use axum::response::{IntoResponse, Response};
 use axum_core::RequestExt;
@@ -18,10 +19,15 @@ use crate::http::jwt::Claims;
 use crate::http::sessionpersistence::Encoder;
 use crate::http::*;
 use crate::json::from_body_with_limit;
-use crate::mcp::handler::Relay;
+use crate::mcp::circuit_breaker::CircuitBreakerScope;
+use crate::mcp::handler::{Relay, RelayToolInvoker};
+use crate::mcp::registry::executor::ToolInvoker;
+use crate::mcp::registry::scaffold_registry;
 use crate::mcp::session::SessionManager;
 use crate::mcp::sse::LegacySSEService;
 use crate::mcp::streamablehttp::{StreamableHttpServerConfig, StreamableHttpService};
+use crate::mcp::tools_cache::{ToolListCache, ToolListCacheScope};
+use crate::mcp::upstream::IncomingRequestContext;
 use crate::mcp::{MCPInfo, McpAuthorizationSet};
 use crate::proxy::ProxyError;
 use crate::proxy::httpproxy::PolicyClient;
@@ -29,7 +35,7 @@ use crate::store::{BackendPolicies, Stores};
 use crate::telemetry::log::AsyncLog;
 use crate::transport::stream::{TCPConnectionInfo, TLSConnectionInfo};
 use crate::types::agent::{
-	BackendTargetRef, McpAuthentication, McpBackend, McpIDP, McpTargetSpec, ResourceName,
+	Backend, BackendTargetRef, McpAuthentication, McpBackend, McpIDP, McpTargetSpec, ResourceName,
 	SimpleBackend, SimpleBackendReference,
 };
 use crate::{ProxyInputs, json};
@@ -38,12 +44,17 @@ use crate::{ProxyInputs, json};
 pub struct App {
 	state: Stores,
 	session: Arc<SessionManager>,
+	tools_cache: Arc<ToolListCache>,
 }
 
 impl App {
 	pub fn new(state: Stores, encoder: Encoder) -> Self {
 		let session: Arc<SessionManager> = Arc::new(crate::mcp::session::SessionManager::new(encoder));
-		Self { state, session }
+		Self {
+			state,
+			session,
+			tools_cache: Arc::new(ToolListCache::new()),
+		}
 	}
 
 	pub fn should_passthrough(
@@ -80,46 +91,22 @@ impl App {
 		log: AsyncLog<MCPInfo>,
 		start_time: String,
 	) -> Response {
-		let backends = {
-			let binds = self.state.read_binds();
-			let nt = backend
-				.targets
-				.iter()
-				.map(|t| {
-					let be = t
-						.spec
-						.backend()
-						.map(|b| crate::proxy::resolve_simple_backend_with_policies(b, &pi))
-						.transpose()?;
-					let inline_pols = be.as_ref().map(|pol| pol.inline_policies.as_slice());
-					let sub_backend_target = BackendTargetRef::Backend {
-						name: backend_group_name.name.as_ref(),
-						namespace: backend_group_name.namespace.as_ref(),
-						section: Some(t.name.as_ref()),
-					};
-					let backend_policies = backend_policies
-						.clone()
-						.merge(binds.sub_backend_policies(sub_backend_target, inline_pols));
-					Ok::<_, ProxyError>(Arc::new(McpTarget {
-						name: t.name.clone(),
-						spec: t.spec.clone(),
-						backend: be.map(|b| b.backend),
-						backend_policies,
-						always_use_prefix: backend.always_use_prefix,
-					}))
-				})
-				.collect::<Result<Vec<_>, _>>();
-			let Ok(nt) = nt else {
-				return ::http::Response::builder()
-					.status(StatusCode::INTERNAL_SERVER_ERROR)
-					.body(axum::body::Body::from("failed to resolve MCP backend"))
-					.unwrap();
-			};
-
-			McpBackendGroup {
-				targets: nt,
-				stateful: backend.stateful,
-			}
+		// Rate limited JSON-RPC messages are rejected in-band by the session; anything else
+		// gets a plain 429
+		if let Some(limited) = req
+			.extensions()
+			.get::<localratelimit::RateLimited>()
+			.copied()
+			&& req.method() != Method::POST
+		{
+			return ProxyError::from(limited).into_response();
+		}
+		let Ok(backends) = self.backend_group(&pi, &backend_group_name, &backend, &backend_policies)
+		else {
+			return ::http::Response::builder()
+				.status(StatusCode::INTERNAL_SERVER_ERROR)
+				.body(axum::body::Body::from("failed to resolve MCP backend"))
+				.unwrap();
 		};
 		let sm = self.session.clone();
 		let client = PolicyClient { inputs: pi.clone() };
@@ -230,6 +217,19 @@ impl App {
 
 		// Get the registry from stores if configured
 		let registry = self.state.get_registry();
+		let tools_cache = backend.tools_cache_ttl.map(|ttl| ToolListCacheScope {
+			cache: self.tools_cache.clone(),
+			backend: backend_group_name.clone(),
+			ttl,
+		});
+		let circuit_breaker = backend
+			.circuit_breaker
+			.clone()
+			.map(|config| CircuitBreakerScope {
+				health: self.state.upstream_health.clone(),
+				backend: backend_group_name.clone(),
+				config,
+			});
 
 		match (req.uri().path(), req.method(), authn) {
 			("/sse", _, _) => {
@@ -248,6 +248,12 @@ impl App {
 						if let Some(r) = reg.clone() {
 							relay = relay.with_registry(r);
 						}
+						if let Some(tc) = tools_cache.clone() {
+							relay = relay.with_tools_cache(tc);
+						}
+						if let Some(cb) = circuit_breaker.clone() {
+							relay = relay.with_circuit_breaker(cb);
+						}
 
 						Ok(relay)
 					},
@@ -291,6 +297,12 @@ impl App {
 						if let Some(r) = reg.clone() {
 							relay = relay.with_registry(r);
 						}
+						if let Some(tc) = tools_cache.clone() {
+							relay = relay.with_tools_cache(tc);
+						}
+						if let Some(cb) = circuit_breaker.clone() {
+							relay = relay.with_circuit_breaker(cb);
+						}
 
 						Ok(relay)
 					},
@@ -310,6 +322,124 @@ impl App {
 	}
 }
 
+impl App {
+	/// Resolve the targets of an MCP backend, with the policies that apply to each
+	fn backend_group(
+		&self,
+		pi: &ProxyInputs,
+		backend_group_name: &ResourceName,
+		backend: &McpBackend,
+		backend_policies: &BackendPolicies,
+	) -> Result<McpBackendGroup, ProxyError> {
+		let binds = self.state.read_binds();
+		let targets = backend
+			.targets
+			.iter()
+			.map(|t| {
+				let be = t
+					.spec
+					.backend()
+					.map(|b| crate::proxy::resolve_simple_backend_with_policies(b, pi))
+					.transpose()?;
+				let inline_pols = be.as_ref().map(|pol| pol.inline_policies.as_slice());
+				let sub_backend_target = BackendTargetRef::Backend {
+					name: backend_group_name.name.as_ref(),
+					namespace: backend_group_name.namespace.as_ref(),
+					section: Some(t.name.as_ref()),
+				};
+				let backend_policies = backend_policies
+					.clone()
+					.merge(binds.sub_backend_policies(sub_backend_target, inline_pols));
+				Ok(Arc::new(McpTarget {
+					name: t.name.clone(),
+					spec: t.spec.clone(),
+					backend: be.map(|b| b.backend),
+					backend_policies,
+					always_use_prefix: backend.always_use_prefix,
+				}))
+			})
+			.collect::<Result<Vec<_>, ProxyError>>()?;
+		Ok(McpBackendGroup {
+			targets,
+			stateful: backend.stateful,
+		})
+	}
+
+	/// Connect to one target of an MCP backend, list its tools and scaffold a registry entry
+	/// for each: a source mapping with nothing overridden yet
+	///
+	/// `backend` is the backend's `namespace/name`. Only the named target is started, so
+	/// scaffolding a stdio target doesn't spawn the backend's other servers.
+	pub async fn scaffold_registry(
+		&self,
+		pi: Arc<ProxyInputs>,
+		backend: &str,
+		target: &str,
+	) -> anyhow::Result<serde_json::Value> {
+		let Some(be) = self.state.read_binds().backend(&strng::new(backend)) else {
+			anyhow::bail!("unknown backend {backend}");
+		};
+		let Backend::MCP(name, mcp) = &be.backend else {
+			anyhow::bail!("backend {backend} is not an MCP backend");
+		};
+		let mut mcp = mcp.clone();
+		mcp.targets.retain(|t| t.name.as_str() == target);
+		if mcp.targets.is_empty() {
+			anyhow::bail!("backend {backend} has no target {target}");
+		}
+		let backends = self.backend_group(&pi, name, &mcp, &BackendPolicies::default())?;
+		let relay = Relay::new(
+			backends,
+			McpAuthorizationSet::new(RuleSets::from(Vec::new())),
+			PolicyClient { inputs: pi },
+		)?;
+		let tools = relay
+			.list_target_tools(target, &IncomingRequestContext::empty())
+			.await
+			.map_err(|e| anyhow::anyhow!("failed to list tools of {target}: {e}"))?;
+		Ok(scaffold_registry(target, &tools))
+	}
+
+	/// Build a tool invoker over the targets of an MCP backend, for an operator replaying or
+	/// re-running compositions from the admin API
+	///
+	/// `backend` is the backend's `namespace/name`. The invoker carries no caller identity, so
+	/// backends see the gateway's own credentials rather than those of the agent whose call
+	/// is replayed.
+	pub fn operator_invoker(
+		&self,
+		pi: Arc<ProxyInputs>,
+		backend: &str,
+	) -> anyhow::Result<Arc<dyn ToolInvoker>> {
+		let Some(be) = self.state.read_binds().backend(&strng::new(backend)) else {
+			anyhow::bail!("unknown backend {backend}");
+		};
+		let Backend::MCP(name, mcp) = &be.backend else {
+			anyhow::bail!("backend {backend} is not an MCP backend");
+		};
+		let backends = self.backend_group(&pi, name, mcp, &BackendPolicies::default())?;
+		let mut relay = Relay::new(
+			backends,
+			McpAuthorizationSet::new(RuleSets::from(Vec::new())),
+			PolicyClient { inputs: pi },
+		)?;
+		if let Some(registry) = self.state.get_registry() {
+			relay = relay.with_registry(registry);
+		}
+		if let Some(config) = mcp.circuit_breaker.clone() {
+			relay = relay.with_circuit_breaker(CircuitBreakerScope {
+				health: self.state.upstream_health.clone(),
+				backend: name.clone(),
+				config,
+			});
+		}
+		Ok(Arc::new(RelayToolInvoker::new(
+			Arc::new(relay),
+			IncomingRequestContext::empty(),
+		)))
+	}
+}
+
 #[derive(Debug, Clone)]
 pub struct McpBackendGroup {
 	pub targets: Vec<Arc<McpTarget>>,
@@ -0,0 +1,182 @@
+// Gateway-initiated MCP sampling
+//
+// Composition sample steps ask the downstream client's LLM for a completion. The request is
+// sent on the SSE stream of the tool call running the composition, and the client POSTs its
+// reply back to the session like any other message; the bridge pairs the two by request id.
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+
+use parking_lot::Mutex;
+use rmcp::ErrorData;
+use rmcp::model::{
+	ClientCapabilities, ClientResult, CreateMessageRequest, CreateMessageRequestParam,
+	CreateMessageResult, RequestId, ServerJsonRpcMessage, ServerRequest,
+};
+use tokio::sync::{mpsc, oneshot};
+
+use crate::mcp::registry::executor::ExecutionError;
+
+type Reply = Result<ClientResult, ErrorData>;
+
+/// Pairs a session's outstanding sampling requests with the client's replies
+#[derive(Debug, Default)]
+pub struct SamplingBridge {
+	/// Whether the client declared the sampling capability when it initialized
+	supported: AtomicBool,
+	next_id: AtomicU64,
+	pending: Mutex<HashMap<RequestId, oneshot::Sender<Reply>>>,
+}
+
+impl SamplingBridge {
+	/// Record the capabilities the client declared in its initialize request
+	pub fn set_client_capabilities(&self, capabilities: &ClientCapabilities) {
+		self
+			.supported
+			.store(capabilities.sampling.is_some(), Ordering::Relaxed);
+	}
+
+	pub fn is_supported(&self) -> bool {
+		self.supported.load(Ordering::Relaxed)
+	}
+
+	/// Hand a client reply to the sampling request waiting for it
+	///
+	/// Returns false if nothing is waiting: the reply is for another request, or arrived after
+	/// the step gave up.
+	pub fn complete(&self, id: &RequestId, reply: Reply) -> bool {
+		match self.pending.lock().remove(id) {
+			Some(tx) => {
+				let _ = tx.send(reply);
+				true
+			},
+			None => false,
+		}
+	}
+
+	/// Send a sampling request on a tool call's response stream and wait for the reply
+	pub async fn create_message(
+		&self,
+		stream: &mpsc::Sender<ServerJsonRpcMessage>,
+		params: CreateMessageRequestParam,
+	) -> Result<CreateMessageResult, ExecutionError> {
+		if !self.is_supported() {
+			return Err(ExecutionError::SamplingUnavailable(
+				"the client did not declare the sampling capability".to_string(),
+			));
+		}
+		let id = RequestId::String(
+			format!(
+				"agw-sampling-{}",
+				self.next_id.fetch_add(1, Ordering::Relaxed)
+			)
+			.into(),
+		);
+		let (tx, rx) = oneshot::channel();
+		self.pending.lock().insert(id.clone(), tx);
+		// Forget the request if the step stops waiting (e.g. it timed out)
+		let _pending = PendingRequest {
+			bridge: self,
+			id: &id,
+		};
+
+		let request = CreateMessageRequest {
+			method: Default::default(),
+			params,
+			extensions: Default::default(),
+		};
+		stream
+			.send(ServerJsonRpcMessage::request(
+				ServerRequest::CreateMessageRequest(request),
+				id.clone(),
+			))
+			.await
+			.map_err(|_| ExecutionError::SamplingUnavailable("the client disconnected".to_string()))?;
+
+		match rx.await {
+			Ok(Ok(ClientResult::CreateMessageResult(result))) => Ok(*result),
+			Ok(Ok(_)) => Err(ExecutionError::ToolExecutionFailed(
+				"unexpected reply to a sampling request".to_string(),
+			)),
+			Ok(Err(e)) => Err(ExecutionError::ToolExecutionFailed(format!(
+				"client rejected the sampling request: {}",
+				e.message
+			))),
+			Err(_) => Err(ExecutionError::SamplingUnavailable(
+				"the session closed".to_string(),
+			)),
+		}
+	}
+}
+
+struct PendingRequest<'a> {
+	bridge: &'a SamplingBridge,
+	id: &'a RequestId,
+}
+
+impl Drop for PendingRequest<'_> {
+	fn drop(&mut self) {
+		self.bridge.pending.lock().remove(self.id);
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use rmcp::model::{Content, JsonRpcMessage, Role, SamplingMessage};
+
+	use super::*;
+
+	fn params() -> CreateMessageRequestParam {
+		CreateMessageRequestParam {
+			messages: vec![SamplingMessage {
+				role: Role::User,
+				content: Content::text("rank these"),
+			}],
+			model_preferences: None,
+			system_prompt: None,
+			include_context: None,
+			temperature: None,
+			max_tokens: 100,
+			stop_sequences: None,
+			metadata: None,
+		}
+	}
+
+	#[tokio::test]
+	async fn test_reply_reaches_waiting_request() {
+		let bridge = SamplingBridge::default();
+		let (tx, mut rx) = mpsc::channel(1);
+		let err = bridge.create_message(&tx, params()).await.unwrap_err();
+		assert!(matches!(err, ExecutionError::SamplingUnavailable(_)));
+
+		bridge.set_client_capabilities(&ClientCapabilities {
+			sampling: Some(Default::default()),
+			..Default::default()
+		});
+		let client = async {
+			let Some(JsonRpcMessage::Request(request)) = rx.recv().await else {
+				panic!("expected a sampling request");
+			};
+			let reply = CreateMessageResult {
+				model: "client-model".to_string(),
+				stop_reason: None,
+				message: SamplingMessage {
+					role: Role::Assistant,
+					content: Content::text("2, 1"),
+				},
+			};
+			assert!(bridge.complete(
+				&request.id,
+				Ok(ClientResult::CreateMessageResult(Box::new(reply)))
+			));
+		};
+		let (result, ()) = tokio::join!(bridge.create_message(&tx, params()), client);
+		assert_eq!(result.unwrap().model, "client-model");
+		assert!(bridge.pending.lock().is_empty());
+		// Late or unknown replies are not routed
+		assert!(!bridge.complete(
+			&RequestId::Number(1),
+			Err(ErrorData::internal_error("x", None))
+		));
+	}
+}
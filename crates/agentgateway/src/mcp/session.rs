@@ -1,37 +1,61 @@
 use std::collections::HashMap;
 use std::convert::Infallible;
-use std::sync::Arc;
+use std::sync::{Arc, OnceLock};
 
 use ::http::StatusCode;
-use ::http::header::CONTENT_TYPE;
+use ::http::header::{ACCEPT_LANGUAGE, CONTENT_TYPE};
 use ::http::request::Parts;
 use agent_core::version::BuildInfo;
 use anyhow::anyhow;
-use futures_util::StreamExt;
+use futures_util::{StreamExt, TryFutureExt};
+use opentelemetry::trace::Span as _;
 use rmcp::ErrorData;
 use rmcp::model::{
-	ClientInfo, ClientJsonRpcMessage, ClientNotification, ClientRequest, ConstString, ErrorCode,
-	Implementation, JsonRpcError, ProtocolVersion, RequestId, ServerJsonRpcMessage,
+	CallToolResult, ClientInfo, ClientJsonRpcMessage, ClientNotification, ClientRequest, ConstString,
+	ErrorCode, Implementation, JsonRpcError, Meta, ProtocolVersion, RequestId, ServerJsonRpcMessage,
 };
 use rmcp::transport::common::http_header::{EVENT_STREAM_MIME_TYPE, JSON_MIME_TYPE};
 use sse_stream::{KeepAlive, Sse, SseBody, SseStream};
 use tokio::sync::mpsc::{Receiver, Sender};
 
 use crate::http::Response;
-use crate::mcp::handler::{Relay, RelayToolInvoker, ResolvedToolCall};
+use crate::http::localratelimit::RateLimited;
+use crate::mcp::composition_tracing::{create_composition_span, trace_exemplar};
+use crate::mcp::handler::{
+	BufferedCall, Relay, RelayToolInvoker, ResolvedToolCall, quota_meta, with_caller_quota,
+};
 use crate::mcp::mergestream::Messages;
-use crate::mcp::registry::executor::CompositionExecutor;
+use crate::mcp::registry::executor::{
+	BUILTIN_TARGET, CompositionExecutor, EXECUTION_ID_META_KEY, ExecutionId, ExecutionLabels,
+	ExecutionPriority, SessionStore, VERBOSITY_HEADER,
+};
+use crate::mcp::registry::{
+	CallKey, CompiledTool, DESCRIBE_TOOL, IDEMPOTENT_REPLAY_META_KEY, IdempotencyKey,
+	IdempotentOutcome, describe, idempotency_key_from_meta,
+};
+use crate::mcp::sampling::SamplingBridge;
 use crate::mcp::streamablehttp::{ServerSseMessage, StreamableHttpPostResponse};
-use crate::mcp::upstream::{IncomingRequestContext, UpstreamError};
+use crate::mcp::upstream::{IncomingRequestContext, SharedFailure, UpstreamError};
 use crate::mcp::{ClientError, MCPOperation, rbac};
+use crate::proxy::ProxyError;
 use crate::{mcp, *};
 
+/// JSON-RPC error code for requests rejected by a route rate limit (implementation-defined
+/// server error range)
+const RATE_LIMITED: ErrorCode = ErrorCode(-32029);
+
 #[derive(Debug, Clone)]
 pub struct Session {
 	encoder: http::sessionpersistence::Encoder,
 	relay: Arc<Relay>,
 	pub id: Arc<str>,
 	tx: Option<Sender<ServerJsonRpcMessage>>,
+	/// Session-scoped state available to compositions via `session` bindings
+	composition_state: Arc<SessionStore>,
+	/// Sampling requests compositions sent to the client, awaiting its replies
+	sampling: Arc<SamplingBridge>,
+	/// Accept-Language the client initialized with, for tool lists requested without one
+	accept_language: Arc<OnceLock<String>>,
 }
 
 impl Session {
@@ -41,6 +65,9 @@ impl Session {
 			ClientJsonRpcMessage::Request(r) => Some(r.id.clone()),
 			_ => None,
 		};
+		if let Some(limited) = parts.extensions.get::<RateLimited>() {
+			return rate_limited_response(req_id, *limited);
+		}
 		self
 			.send_internal(parts, message)
 			.await
@@ -56,7 +83,9 @@ impl Session {
 		message: ClientJsonRpcMessage,
 	) -> Response {
 		let is_init = matches!(&message, ClientJsonRpcMessage::Request(r) if matches!(&r.request, &ClientRequest::InitializeRequest(_)));
-		if !is_init {
+		// A rate limited message is rejected without reaching the upstreams
+		let rate_limited = parts.extensions.get::<RateLimited>().is_some();
+		if !is_init && !rate_limited {
 			// first, send the initialize
 			let init_request = rmcp::model::InitializeRequest {
 				method: Default::default(),
@@ -187,6 +216,21 @@ impl Session {
 				}) {
 				return http_json_error(StatusCode::OK, body);
 			}
+			// Argument mismatches go back as one invalid params error listing all of them, so
+			// an agent can correct every argument before retrying
+			if let UpstreamError::InvalidArguments { problems, .. } = &e
+				&& let Some(ref req_id) = req_id
+				&& let Ok(body) = serde_json::to_string(&JsonRpcError {
+					jsonrpc: Default::default(),
+					id: req_id.clone(),
+					error: ErrorData {
+						code: ErrorCode::INVALID_PARAMS,
+						message: e.to_string().into(),
+						data: Some(serde_json::json!({ "problems": problems })),
+					},
+				}) {
+				return http_json_error(StatusCode::OK, body);
+			}
 			let err = if let Some(req_id) = req_id {
 				serde_json::to_string(&JsonRpcError {
 					jsonrpc: Default::default(),
@@ -208,6 +252,91 @@ impl Session {
 		}
 	}
 
+	/// Who a call is from, for matching repeats of it: the JWT subject, or else the session
+	fn caller(&self, ctx: &IncomingRequestContext) -> String {
+		ctx.subject().map_or_else(
+			|| format!("session:{}", self.id),
+			|sub| format!("sub:{sub}"),
+		)
+	}
+
+	/// Let a caller's identical repeats of `call` share it, if the tool has a debounce window
+	fn debounced(
+		&self,
+		ctx: &IncomingRequestContext,
+		name: &str,
+		args: &serde_json::Value,
+		call: impl Future<Output = Result<CallToolResult, UpstreamError>> + Send + 'static,
+	) -> std::pin::Pin<Box<dyn Future<Output = Result<CallToolResult, UpstreamError>> + Send>> {
+		let (Some(debounce), Some(registry)) = (self.relay.debounce(name), self.relay.registry())
+		else {
+			return Box::pin(call);
+		};
+		let debouncer = registry.debouncer().clone();
+		let key = CallKey::new(self.caller(ctx), name, args);
+		let quota = ctx.quota();
+		Box::pin(async move {
+			let mut result = debouncer
+				.call(
+					key,
+					debounce.window(),
+					call.map_err(|e| SharedFailure::new(&e)),
+				)
+				.await
+				.map_err(UpstreamError::Shared)?;
+			// A repeat reports the caller's own quota, not the original call's
+			result.meta = with_caller_quota(result.meta.take(), quota);
+			Ok(result)
+		})
+	}
+
+	/// Run a composition call under the caller's idempotency key, if the call has one and
+	/// the composition accepts them
+	fn idempotent(
+		&self,
+		ctx: &IncomingRequestContext,
+		name: &str,
+		args: &serde_json::Value,
+		key: Option<String>,
+		call: impl Future<Output = Result<CallToolResult, UpstreamError>> + Send + 'static,
+	) -> std::pin::Pin<Box<dyn Future<Output = Result<CallToolResult, UpstreamError>> + Send>> {
+		let (Some(key), Some(idempotency), Some(registry)) =
+			(key, self.relay.idempotency(name), self.relay.registry())
+		else {
+			return Box::pin(call);
+		};
+		let store = registry.idempotency().clone();
+		let key = IdempotencyKey::new(self.caller(ctx), name, key);
+		let args = args.clone();
+		let quota = ctx.quota();
+		Box::pin(async move {
+			let outcome = store
+				.call(key, &args, &idempotency, call.map_err(|e| e.to_string()))
+				.await
+				.map_err(UpstreamError::InvalidRequest)?;
+			let replay_meta = || {
+				let mut meta = quota.map(quota_meta).unwrap_or_default();
+				meta
+					.0
+					.insert(IDEMPOTENT_REPLAY_META_KEY.to_string(), true.into());
+				Some(meta)
+			};
+			Ok(match outcome {
+				IdempotentOutcome::Ran(result) => result,
+				IdempotentOutcome::Replayed(mut result) => {
+					result.meta = replay_meta();
+					result
+				},
+				IdempotentOutcome::Skipped => CallToolResult {
+					content: vec![],
+					structured_content: None,
+					is_error: None,
+					meta: replay_meta(),
+				},
+			})
+		})
+	}
+
 	async fn send_internal(
 		&mut self,
 		parts: Parts,
@@ -233,6 +362,12 @@ impl Session {
 				match &mut r.request {
 					ClientRequest::InitializeRequest(ir) => {
 						let pv = ir.params.protocol_version.clone();
+						self
+							.sampling
+							.set_client_capabilities(&ir.params.capabilities);
+						if let Some(accept_language) = accept_language(&ctx) {
+							let _ = self.accept_language.set(accept_language);
+						}
 						let res = self
 							.relay
 							.send_fanout(r, ctx, self.relay.merge_initialize(pv))
@@ -251,9 +386,11 @@ impl Session {
 						log.non_atomic_mutate(|l| {
 							l.resource = Some(MCPOperation::Tool);
 						});
+						let accept_language =
+							accept_language(&ctx).or_else(|| self.accept_language.get().cloned());
 						self
 							.relay
-							.send_fanout(r, ctx, self.relay.merge_tools(cel.clone()))
+							.send_fanout_list_tools(r, ctx, self.relay.merge_tools(cel.clone(), accept_language))
 							.await
 					},
 					ClientRequest::PingRequest(_) | ClientRequest::SetLevelRequest(_) => {
@@ -314,8 +451,14 @@ impl Session {
 							.map(|v| serde_json::Value::Object(v))
 							.unwrap_or(serde_json::Value::Object(Default::default()));
 
+						// A strict-args virtual tool needs its backend's schema to check arguments by
+						self
+							.relay
+							.learn_backend_schema(self.relay.registry_snapshot().as_ref(), &name, &ctx)
+							.await;
 						// Resolve the tool call - may be a backend tool, virtual tool, or composition
-						let resolved = self.relay.resolve_tool_call(&name, args)?;
+						let resolved = self.relay.resolve_tool_call(&name, args, ctx.subject())?;
+						self.relay.record_usage(&name, &self.caller(&ctx));
 
 						match resolved {
 							ResolvedToolCall::Backend {
@@ -343,6 +486,15 @@ impl Session {
 										resource_name: name.to_string(),
 									});
 								}
+								self
+									.relay
+									.authorize_tool_call(&name, &target, &tool_name, &resolved_args, &ctx)
+									.await?;
+								if let Some(vname) = &virtual_name {
+									self
+										.relay
+										.check_confirmation(vname, ctr.extensions.get::<Meta>())?;
+								}
 
 								// Update the request with resolved tool name and args
 								ctr.params.name = tool_name.clone().into();
@@ -350,6 +502,38 @@ impl Session {
 									ctr.params.arguments = Some(obj.clone());
 								}
 
+								// Paginated virtual tools merge every page into one result, job-style ones
+								// wait for the job's, and debounced ones share it with repeats, so none
+								// are streamed from the backend
+								let pagination = self.relay.pagination(virtual_name.as_deref());
+								let job_polling = self.relay.job_polling(virtual_name.as_deref());
+								if let Some(vname) = virtual_name.clone()
+									&& (pagination.is_some()
+										|| job_polling.is_some()
+										|| self.relay.debounce(&vname).is_some())
+								{
+									let relay = self.relay.clone();
+									let call_ctx = ctx.clone();
+									let call_name = vname.clone();
+									let call_args = resolved_args.clone();
+									let call = async move {
+										let call = BufferedCall {
+											target: &target,
+											tool_name: &tool_name,
+											args: call_args,
+											virtual_name: &call_name,
+											pagination: pagination.as_ref(),
+											job_polling: job_polling.as_ref(),
+										};
+										relay.call_buffered(&call_ctx, call).await
+									};
+									let result = self.debounced(&ctx, &vname, &resolved_args, call).await?;
+									return crate::mcp::handler::messages_to_response(
+										r.id.clone(),
+										Messages::from_result(r.id.clone(), result),
+									);
+								}
+
 								// Use send_single_with_output_transform to apply outputTransform
 								self
 									.relay
@@ -379,6 +563,27 @@ impl Session {
 										resource_name: comp_name.to_string(),
 									});
 								}
+								self
+									.relay
+									.authorize_tool_call(&comp_name, "_composition", &comp_name, &comp_args, &ctx)
+									.await?;
+								self
+									.relay
+									.check_confirmation(&comp_name, ctr.extensions.get::<Meta>())?;
+
+								// Labels the caller attached for cost attribution, and its idempotency key
+								let (labels, idempotency_key) = match ctr.extensions.get::<Meta>() {
+									Some(meta) => (
+										ExecutionLabels::from_meta(&meta.0)
+											.map_err(|e| UpstreamError::InvalidRequest(e.to_string()))?,
+										idempotency_key_from_meta(&meta.0)
+											.map_err(|e| UpstreamError::InvalidRequest(e.to_string()))?,
+									),
+									None => (ExecutionLabels::default(), None),
+								};
+								if !labels.is_empty() {
+									log.non_atomic_mutate(|l| l.labels = Some(labels.to_string()));
+								}
 
 								// Execute the composition using CompositionExecutor
 								let registry_ref = self.relay.registry().ok_or_else(|| {
@@ -392,39 +597,188 @@ impl Session {
 									UpstreamError::InvalidRequest("Registry not loaded".to_string())
 								})?;
 
-								// Create a ToolInvoker that uses the Relay to make real backend calls
-								let tool_invoker = Arc::new(RelayToolInvoker::new(self.relay.clone(), ctx.clone()));
+								// A retry of a call that was in flight on an instance this one took over
+								// from resumes that execution, under its id
+								let caller = self.caller(&ctx);
+								let journal = registry_ref.execution_journal().cloned();
+								let adopted = journal
+									.as_ref()
+									.and_then(|j| j.adopt(&comp_name, &caller, &comp_args));
+
+								// One id correlates the result, logs, span, events and debug records
+								let execution_id = adopted
+									.as_ref()
+									.map_or_else(ExecutionId::new, |e| e.execution_id);
+								log.non_atomic_mutate(|l| l.execution_id = Some(execution_id.to_string()));
+								let mut comp_span = create_composition_span(
+									&comp_name,
+									&_span,
+									self.relay.composition_sampling(&comp_name),
+								);
+								comp_span.set_attribute(opentelemetry::KeyValue::new(
+									"composition.execution_id",
+									execution_id.to_string(),
+								));
 
-								// Create the executor and run the composition
-								// Spawn as a separate task to avoid scheduler starvation
-								let executor = CompositionExecutor::new(compiled_registry, tool_invoker);
+								// Create a ToolInvoker that uses the Relay to make real backend calls.
+								// It resolves tools against the same registry snapshot as the executor,
+								// so a reload mid-execution can't change what the composition sees.
+								// Sample steps send their requests to the client on this call's stream, as
+								// does progress when the client asked for it.
+								let (sampling_tx, sampling_rx) = tokio::sync::mpsc::channel(8);
+								let progress_token = ctr
+									.extensions
+									.get::<Meta>()
+									.and_then(|m| m.get_progress_token());
+								let tool_invoker = Arc::new(
+									RelayToolInvoker::new(self.relay.clone(), ctx.clone())
+										.with_span_context(comp_span.span_context().clone())
+										.with_registry_snapshot(compiled_registry.clone())
+										.with_sampling(self.sampling.clone(), sampling_tx)
+										.with_progress_token(progress_token),
+								);
+
+								// Create the executor and run the composition on the shared worker pool.
+								// Agent calls over MCP use the interactive lane, so queued batch work
+								// can't hold every worker; resuming a taken-over execution is batch work.
+								let executor = CompositionExecutor::new(compiled_registry, tool_invoker)
+									.with_session(self.composition_state.clone())
+									.with_execution_log(registry_ref.execution_log().clone())
+									.with_verbosity_overrides(registry_ref.verbosity_overrides().clone())
+									.with_verbosity(
+										registry_ref
+											.verbosity_overrides()
+											.requested(ctx.subject(), ctx.header(VERBOSITY_HEADER)),
+									)
+									.with_memory_budget(registry_ref.memory_budget().clone())
+									.with_events(registry_ref.events().clone())
+									.with_result_cache(registry_ref.result_cache().clone())
+									.with_dead_letters(registry_ref.dead_letters().clone())
+									.with_captures(registry_ref.captures().clone())
+									.with_labels(labels.clone())
+									.with_execution_id(execution_id);
+								let executor = match registry_ref.archiver() {
+									Some(archiver) => executor.with_archiver(archiver.clone()),
+									None => executor,
+								};
+								let executor = match journal {
+									Some(journal) => executor.with_journal(journal, caller),
+									None => executor,
+								};
+								let executor = if comp_span.span_context().is_valid() {
+									executor
+										.with_trace_id(comp_span.span_context().trace_id().to_string())
+										.with_span_id(comp_span.span_context().span_id().to_string())
+								} else {
+									executor
+								};
 								let comp_name_clone = comp_name.clone();
+								let call_name = comp_name.clone();
+								let call_args = comp_args.clone();
 
-								let result =
-									tokio::spawn(async move { executor.execute(&comp_name_clone, comp_args).await })
-										.await
-										.map_err(|e| {
-											UpstreamError::InvalidRequest(format!("Composition task panicked: {}", e))
-										})?
-										.map_err(|e| {
-											UpstreamError::InvalidRequest(format!("Composition execution failed: {}", e))
-										})?;
-
-								// Build a successful MCP CallToolResult response
-								let call_result = rmcp::model::CallToolResult {
-									content: vec![rmcp::model::Content::text(
-										serde_json::to_string(&result).unwrap_or_default(),
-									)],
-									structured_content: None,
-									is_error: None,
-									meta: None,
+								let pool = registry_ref.worker_pool().clone();
+								let priority = if adopted.is_some() {
+									ExecutionPriority::Batch
+								} else {
+									ExecutionPriority::Interactive
 								};
+								let permit = self
+									.relay
+									.acquire_composition_worker(&registry_ref, priority)
+									.await;
+								let relay = self.relay.clone();
+								let quota = ctx.quota();
+								let finish = Box::pin(async move {
+									let start = std::time::Instant::now();
+									let (result, cost) = pool
+										.spawn(permit, async move {
+											match adopted {
+												Some(execution) => executor.resume_journaled(execution).await,
+												None => executor.execute_metered(&comp_name_clone, comp_args).await,
+											}
+										})
+										.await
+										.map_err(|e| UpstreamError::InvalidRequest(e.to_string()))?;
+									if let Err(e) = &result {
+										comp_span.set_status(opentelemetry::trace::Status::error(e.to_string()));
+									}
+									relay.record_composition_duration(
+										&comp_name,
+										start.elapsed(),
+										result.is_ok(),
+										trace_exemplar(comp_span.span_context()),
+									);
+									relay.record_attributed_execution(&comp_name, &labels, result.is_ok());
+									relay.record_composition_cost(&comp_name, &cost, &labels);
+									comp_span.end();
+									let result = result.map_err(|e| {
+										UpstreamError::InvalidRequest(format!(
+											"Composition execution failed (execution {execution_id}): {e}"
+										))
+									})?;
+
+									let mut meta = quota.map(quota_meta).unwrap_or_default();
+									meta.0.insert(
+										EXECUTION_ID_META_KEY.to_string(),
+										serde_json::json!(execution_id),
+									);
+
+									// Build a successful MCP CallToolResult response
+									Ok::<_, UpstreamError>(CallToolResult {
+										content: vec![rmcp::model::Content::text(
+											serde_json::to_string(&result).unwrap_or_default(),
+										)],
+										structured_content: None,
+										is_error: None,
+										meta: Some(meta),
+									})
+								});
+								let finish = self.debounced(&ctx, &call_name, &call_args, finish);
+								let finish = self.idempotent(&ctx, &call_name, &call_args, idempotency_key, finish);
+
+								composition_response(r.id.clone(), finish, sampling_rx).await
+							},
+							ResolvedToolCall::Describe { args } => {
+								log.non_atomic_mutate(|l| {
+									l.resource_name = Some(DESCRIBE_TOOL.to_string());
+									l.target_name = Some(BUILTIN_TARGET.to_string());
+									l.resource = Some(MCPOperation::Tool);
+								});
+
+								if !self.relay.policies.validate(
+									&rbac::ResourceType::Tool(rbac::ResourceId::new(
+										BUILTIN_TARGET.to_string(),
+										DESCRIBE_TOOL.to_string(),
+									)),
+									cel.as_ref(),
+								) {
+									return Err(UpstreamError::Authorization {
+										resource_type: "tool".to_string(),
+										resource_name: name.to_string(),
+									});
+								}
+								self
+									.relay
+									.authorize_tool_call(&name, BUILTIN_TARGET, DESCRIBE_TOOL, &args, &ctx)
+									.await?;
 
-								// Convert to Response using the same pattern as regular tool calls
-								let id = r.id.clone();
+								// Answered from the registry snapshot, without reaching any backend
+								let registry = self.relay.registry_snapshot().ok_or_else(|| {
+									UpstreamError::InvalidRequest("Registry not loaded".to_string())
+								})?;
+								// Only the tools tools/list would show this caller are described
+								let visible = |tool: &CompiledTool| {
+									self.relay.is_tool_available(tool)
+										&& self.relay.is_tool_allowed(tool, cel.as_ref())
+								};
+								let mut result = match describe(&registry, &args, visible) {
+									Ok(doc) => CallToolResult::structured(doc),
+									Err(e) => CallToolResult::error(vec![rmcp::model::Content::text(e)]),
+								};
+								result.meta = ctx.quota().map(quota_meta);
 								crate::mcp::handler::messages_to_response(
-									id.clone(),
-									Messages::from_result(id, call_result),
+									r.id.clone(),
+									Messages::from_result(r.id.clone(), result),
 								)
 							},
 						}
@@ -514,13 +868,70 @@ impl Session {
 				self.relay.send_notification(r, ctx).await
 			},
 
-			_ => Err(UpstreamError::InvalidRequest(
-				"unsupported message type".to_string(),
-			)),
+			// Replies to sampling requests a composition sent to the client
+			ClientJsonRpcMessage::Response(r) => {
+				if self.sampling.complete(&r.id, Ok(r.result)) {
+					Ok(crate::mcp::handler::accepted_response())
+				} else {
+					Err(UpstreamError::InvalidRequest(
+						"unsupported message type".to_string(),
+					))
+				}
+			},
+			ClientJsonRpcMessage::Error(e) => {
+				if self.sampling.complete(&e.id, Err(e.error)) {
+					Ok(crate::mcp::handler::accepted_response())
+				} else {
+					Err(UpstreamError::InvalidRequest(
+						"unsupported message type".to_string(),
+					))
+				}
+			},
 		}
 	}
 }
 
+/// The client's Accept-Language preference for localized tool descriptions
+fn accept_language(ctx: &IncomingRequestContext) -> Option<String> {
+	ctx
+		.header(ACCEPT_LANGUAGE.as_str())
+		.and_then(|v| v.to_str().ok())
+		.map(str::to_string)
+}
+
+/// Respond with a composition's result once it finishes; if it sends the client a sampling
+/// request first, stream that request and any later ones ahead of the result instead, so
+/// the client can answer while the composition waits
+async fn composition_response(
+	id: RequestId,
+	mut finish: std::pin::Pin<Box<dyn Future<Output = Result<CallToolResult, UpstreamError>> + Send>>,
+	mut sampling: Receiver<ServerJsonRpcMessage>,
+) -> Result<Response, UpstreamError> {
+	let first = tokio::select! {
+		result = &mut finish => {
+			return crate::mcp::handler::messages_to_response(
+				id.clone(),
+				Messages::from_result(id, result?),
+			);
+		},
+		Some(request) = sampling.recv() => request,
+	};
+
+	let result_id = id.clone();
+	let result = futures::stream::once(async move {
+		Ok(match finish.await {
+			Ok(call_result) => ServerJsonRpcMessage::response(call_result.into(), result_id),
+			Err(e) => {
+				ServerJsonRpcMessage::error(ErrorData::internal_error(e.to_string(), None), result_id)
+			},
+		})
+	});
+	let requests = futures::stream::once(async { first })
+		.chain(tokio_stream::wrappers::ReceiverStream::new(sampling))
+		.map(Ok);
+	crate::mcp::handler::messages_to_response(id, futures::stream::select(requests, result))
+}
+
 #[derive(Debug)]
 pub struct SessionManager {
 	encoder: http::sessionpersistence::Encoder,
@@ -572,6 +983,9 @@ impl SessionManager {
 			relay: Arc::new(relay),
 			tx: None,
 			encoder: self.encoder.clone(),
+			composition_state: Default::default(),
+			sampling: Default::default(),
+			accept_language: Default::default(),
 		};
 		let mut sm = self.sessions.write().expect("write lock");
 		sm.insert(id.to_string(), sess.clone());
@@ -588,6 +1002,9 @@ impl SessionManager {
 			relay: Arc::new(relay),
 			tx: None,
 			encoder: self.encoder.clone(),
+			composition_state: Default::default(),
+			sampling: Default::default(),
+			accept_language: Default::default(),
 		}
 	}
 
@@ -607,6 +1024,9 @@ impl SessionManager {
 			relay: Arc::new(relay),
 			tx: None,
 			encoder: self.encoder.clone(),
+			composition_state: Default::default(),
+			sampling: Default::default(),
+			accept_language: Default::default(),
 		}
 	}
 
@@ -620,6 +1040,9 @@ impl SessionManager {
 			relay: Arc::new(relay),
 			tx: Some(tx),
 			encoder: self.encoder.clone(),
+			composition_state: Default::default(),
+			sampling: Default::default(),
+			accept_language: Default::default(),
 		};
 		let mut sm = self.sessions.write().expect("write lock");
 		sm.insert(id.to_string(), sess.clone());
@@ -668,6 +1091,24 @@ fn http_error(status: StatusCode, body: impl Into<http::Body>) -> Response {
 		.expect("valid response")
 }
 
+/// JSON-RPC error for a request over a route rate limit, carrying the quota in its data
+fn rate_limited_response(req_id: Option<RequestId>, limited: RateLimited) -> Response {
+	let Some(id) = req_id else {
+		return ProxyError::from(limited).into_response();
+	};
+	let body = serde_json::to_string(&JsonRpcError {
+		jsonrpc: Default::default(),
+		id,
+		error: ErrorData {
+			code: RATE_LIMITED,
+			message: "rate limit exceeded".into(),
+			data: Some(serde_json::Value::Object(quota_meta(limited.0).0)),
+		},
+	})
+	.unwrap_or_default();
+	http_json_error(StatusCode::OK, body)
+}
+
 fn http_json_error(status: StatusCode, body: impl Into<http::Body>) -> Response {
 	::http::Response::builder()
 		.status(status)
@@ -731,7 +1172,7 @@ impl sse_stream::Timer for TokioSseTimer {
 	}
 }
 
-fn get_client_info() -> ClientInfo {
+pub(crate) fn get_client_info() -> ClientInfo {
 	ClientInfo {
 		protocol_version: ProtocolVersion::V_2025_06_18,
 		capabilities: rmcp::model::ClientCapabilities {
@@ -747,3 +1188,209 @@ fn get_client_info() -> ClientInfo {
 		},
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::http::authorization::RuleSets;
+	use crate::http::sessionpersistence::{Encoder, MCPSessionState, SessionState};
+	use crate::mcp::McpAuthorizationSet;
+	use crate::mcp::registry::{Debounce, Registry, RegistryStore, RegistryStoreRef, ToolDefinition};
+	use crate::mcp::router::McpBackendGroup;
+	use crate::proxy::httpproxy::PolicyClient;
+	use crate::test_helpers::proxymock::setup_proxy_test;
+
+	#[tokio::test]
+	async fn test_resumed_session_drops_composition_state() {
+		let inputs = setup_proxy_test("{}").unwrap().inputs();
+		let builder: Arc<dyn Fn() -> Result<Relay, http::Error> + Send + Sync> = Arc::new(move || {
+			Relay::new(
+				McpBackendGroup {
+					targets: vec![],
+					stateful: true,
+				},
+				McpAuthorizationSet::new(RuleSets::from(Vec::new())),
+				PolicyClient {
+					inputs: inputs.clone(),
+				},
+			)
+			.map_err(|e| http::Error::new(e.to_string()))
+		});
+		let encoder = Encoder::base64();
+
+		let manager = SessionManager::new(encoder.clone());
+		let sess = manager.create_session(builder().unwrap());
+		sess
+			.composition_state
+			.set("cursor", serde_json::json!("abc"))
+			.await
+			.unwrap();
+		manager.insert_session(sess.clone());
+		let same = manager
+			.get_or_resume_session(&sess.id, builder.clone())
+			.unwrap();
+		assert_eq!(
+			same.composition_state.get("cursor").await,
+			Some(serde_json::json!("abc"))
+		);
+
+		// Another instance resumes from the encoded session ID alone, with empty state
+		let id = SessionState::MCP(MCPSessionState { sessions: vec![] })
+			.encode(&encoder)
+			.unwrap();
+		let resumed = SessionManager::new(encoder)
+			.get_or_resume_session(&id, builder)
+			.unwrap();
+		assert!(resumed.composition_state.is_empty().await);
+	}
+
+	#[tokio::test]
+	async fn test_debounced_result_keeps_meta() {
+		let mut tool = ToolDefinition::source("lookup", "backend", "lookup");
+		tool.debounce = Some(Debounce { window_seconds: 60 });
+		let store = RegistryStoreRef::new(RegistryStore::new());
+		store
+			.update(Registry::with_tool_definitions(vec![tool]))
+			.unwrap();
+		let relay = Relay::new(
+			McpBackendGroup {
+				targets: vec![],
+				stateful: true,
+			},
+			McpAuthorizationSet::new(RuleSets::from(Vec::new())),
+			PolicyClient {
+				inputs: setup_proxy_test("{}").unwrap().inputs(),
+			},
+		)
+		.unwrap()
+		.with_registry(store);
+		let sess = SessionManager::new(Encoder::base64()).create_session(relay);
+		let ctx = IncomingRequestContext::empty();
+		let args = serde_json::json!({"q": "x"});
+
+		let mut meta = Meta::default();
+		meta.0.insert(
+			EXECUTION_ID_META_KEY.to_string(),
+			serde_json::json!("exec-1"),
+		);
+		let first = CallToolResult {
+			content: vec![],
+			structured_content: None,
+			is_error: None,
+			meta: Some(meta),
+		};
+		let result = sess
+			.debounced(&ctx, "lookup", &args, async move { Ok(first) })
+			.await
+			.unwrap();
+		assert_eq!(
+			result.meta.unwrap().0.get(EXECUTION_ID_META_KEY),
+			Some(&serde_json::json!("exec-1"))
+		);
+
+		// A repeat within the window shares the first result, execution id included
+		let repeat = sess
+			.debounced(&ctx, "lookup", &args, async {
+				panic!("a debounced repeat should not run")
+			})
+			.await
+			.unwrap();
+		assert_eq!(
+			repeat.meta.unwrap().0.get(EXECUTION_ID_META_KEY),
+			Some(&serde_json::json!("exec-1"))
+		);
+
+		// A failure reaches the repeats waiting for it, classified as it was
+		let args = serde_json::json!({"q": "y"});
+		let (failed, repeat) = tokio::join!(
+			sess.debounced(&ctx, "lookup", &args, async {
+				tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+				Err(UpstreamError::Timeout("backend".to_string()))
+			}),
+			sess.debounced(&ctx, "lookup", &args, async {
+				panic!("a debounced repeat should not run")
+			}),
+		);
+		for e in [failed.unwrap_err(), repeat.unwrap_err()] {
+			assert_eq!(e.to_string(), "upstream backend timed out");
+			assert!(e.is_upstream_failure());
+			assert!(!e.is_transport());
+		}
+	}
+
+	#[tokio::test]
+	async fn test_composition_streams_sampling_requests() {
+		use rmcp::model::{
+			ClientCapabilities, ClientResult, Content, CreateMessageRequestParam, CreateMessageResult,
+			Role, SamplingMessage,
+		};
+
+		let bridge = Arc::new(SamplingBridge::default());
+		bridge.set_client_capabilities(&ClientCapabilities {
+			sampling: Some(Default::default()),
+			..Default::default()
+		});
+		let (tx, rx) = tokio::sync::mpsc::channel(8);
+		let composition = bridge.clone();
+		let finish = Box::pin(async move {
+			let params = CreateMessageRequestParam {
+				messages: vec![SamplingMessage {
+					role: Role::User,
+					content: Content::text("rank these"),
+				}],
+				model_preferences: None,
+				system_prompt: None,
+				include_context: None,
+				temperature: None,
+				max_tokens: 100,
+				stop_sequences: None,
+				metadata: None,
+			};
+			let reply = composition
+				.create_message(&tx, params)
+				.await
+				.map_err(|e| UpstreamError::InvalidRequest(e.to_string()))?;
+			Ok(CallToolResult {
+				content: vec![reply.message.content],
+				structured_content: None,
+				is_error: None,
+				meta: None,
+			})
+		});
+
+		let resp = composition_response(RequestId::Number(7), finish, rx)
+			.await
+			.unwrap();
+		assert_eq!(
+			resp.headers().get(CONTENT_TYPE).unwrap(),
+			EVENT_STREAM_MIME_TYPE
+		);
+		let mut events = SseStream::from_byte_stream(resp.into_body().into_data_stream()).boxed();
+		let mut next = async || -> serde_json::Value {
+			let event = events.next().await.unwrap().unwrap();
+			serde_json::from_str(&event.data.unwrap()).unwrap()
+		};
+
+		// The sampling request goes out on the tool call's stream ahead of the result
+		let request = next().await;
+		assert_eq!(request["method"], "sampling/createMessage");
+		let reply = CreateMessageResult {
+			model: "client-model".to_string(),
+			stop_reason: None,
+			message: SamplingMessage {
+				role: Role::Assistant,
+				content: Content::text("2, 1"),
+			},
+		};
+		assert!(bridge.complete(
+			&serde_json::from_value(request["id"].clone()).unwrap(),
+			Ok(ClientResult::CreateMessageResult(Box::new(reply)))
+		));
+
+		// Once the client answers, the composition finishes and its result ends the stream
+		let result = next().await;
+		assert_eq!(result["id"], 7);
+		assert_eq!(result["result"]["content"][0]["text"], "2, 1");
+		assert!(events.next().await.is_none());
+	}
+}
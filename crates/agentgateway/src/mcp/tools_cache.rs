@@ -0,0 +1,298 @@
+// Shared cache of upstream ListTools results
+//
+// When many sessions connect at once, as after a gateway restart, they all miss the empty
+// cache together. Concurrent misses for the same upstream share a single in-flight ListTools
+// instead of each sending their own, so the backend sees one request per target.
+
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::future::Future;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use agent_core::strng::Strng;
+use futures::FutureExt;
+use futures::channel::oneshot;
+use futures::future::Shared;
+use parking_lot::Mutex;
+use rmcp::model::{ListToolsResult, Tool};
+
+use crate::types::agent::ResourceName;
+
+/// Per-upstream tool lists shared by every session of an MCP backend.
+///
+/// Entries expire after the backend's `toolsCacheTtl` and are dropped early when an upstream
+/// sends `notifications/tools/list_changed`. Because the cache is shared across sessions, it
+/// should only be enabled for backends whose tool list does not depend on the caller.
+#[derive(Default)]
+pub struct ToolListCache {
+	entries: Mutex<HashMap<(ResourceName, Strng), (Instant, Vec<Tool>)>>,
+	/// ListTools requests in flight, with the outcome callers waiting on them will get
+	in_flight: Mutex<HashMap<(ResourceName, Strng), InFlight>>,
+}
+
+/// Outcome of an in-flight ListTools, failures as their message
+type InFlight = Shared<oneshot::Receiver<Result<ListToolsResult, String>>>;
+
+impl std::fmt::Debug for ToolListCache {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		f.debug_struct("ToolListCache")
+			.field("entries", &self.entries.lock().len())
+			.field("in_flight", &self.in_flight.lock().len())
+			.finish()
+	}
+}
+
+impl ToolListCache {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Cached tools for an upstream, if present and not expired
+	pub fn get(&self, backend: &ResourceName, target: &Strng) -> Option<Vec<Tool>> {
+		let mut entries = self.entries.lock();
+		let key = (backend.clone(), target.clone());
+		match entries.get(&key) {
+			Some((expires, tools)) if *expires > Instant::now() => Some(tools.clone()),
+			Some(_) => {
+				entries.remove(&key);
+				None
+			},
+			None => None,
+		}
+	}
+
+	pub fn insert(&self, backend: &ResourceName, target: &Strng, tools: Vec<Tool>, ttl: Duration) {
+		self.entries.lock().insert(
+			(backend.clone(), target.clone()),
+			(Instant::now() + ttl, tools),
+		);
+	}
+
+	/// Drop the cached tools for an upstream
+	pub fn invalidate(&self, backend: &ResourceName, target: &Strng) {
+		self
+			.entries
+			.lock()
+			.remove(&(backend.clone(), target.clone()));
+	}
+
+	/// Run `fetch`, unless a ListTools for the same upstream is already in flight, in which
+	/// case wait for that one's outcome instead
+	///
+	/// A failure reaches waiting callers through `shared_error`; only the caller that sent the
+	/// request sees the original error. A paginated result isn't shared, since its cursor
+	/// belongs to the session that sent the request, and neither is the outcome of a request
+	/// that was dropped before it finished: waiting callers then send their own.
+	pub async fn single_flight<F, Fut, E>(
+		&self,
+		backend: &ResourceName,
+		target: &Strng,
+		fetch: F,
+		shared_error: impl FnOnce(String) -> E,
+	) -> Result<ListToolsResult, E>
+	where
+		F: FnOnce() -> Fut,
+		Fut: Future<Output = Result<ListToolsResult, E>>,
+		E: Display,
+	{
+		let key = (backend.clone(), target.clone());
+		let waiting = {
+			let mut in_flight = self.in_flight.lock();
+			match in_flight.get(&key) {
+				Some(pending) => Err(pending.clone()),
+				None => {
+					let (tx, rx) = oneshot::channel();
+					in_flight.insert(key.clone(), rx.shared());
+					Ok(tx)
+				},
+			}
+		};
+		let tx = match waiting {
+			Ok(tx) => tx,
+			Err(pending) => {
+				return match pending.await {
+					Ok(Ok(result)) if result.next_cursor.is_none() => Ok(result),
+					Ok(Err(e)) => Err(shared_error(e)),
+					Ok(Ok(_)) | Err(oneshot::Canceled) => fetch().await,
+				};
+			},
+		};
+		// Clears the entry even if this future is dropped mid-request, which cancels `tx`
+		let _leader = Leader {
+			in_flight: &self.in_flight,
+			key,
+		};
+		let result = fetch().await;
+		let _ = tx.send(match &result {
+			Ok(result) => Ok(result.clone()),
+			Err(e) => Err(e.to_string()),
+		});
+		result
+	}
+}
+
+/// Entry of the request a caller is sending, removed once it is done
+struct Leader<'a> {
+	in_flight: &'a Mutex<HashMap<(ResourceName, Strng), InFlight>>,
+	key: (ResourceName, Strng),
+}
+
+impl Drop for Leader<'_> {
+	fn drop(&mut self) {
+		self.in_flight.lock().remove(&self.key);
+	}
+}
+
+/// A backend's view of the shared [`ToolListCache`]
+#[derive(Debug, Clone)]
+pub struct ToolListCacheScope {
+	pub cache: Arc<ToolListCache>,
+	pub backend: ResourceName,
+	pub ttl: Duration,
+}
+
+impl ToolListCacheScope {
+	pub fn get(&self, target: &Strng) -> Option<Vec<Tool>> {
+		self.cache.get(&self.backend, target)
+	}
+
+	pub fn insert(&self, target: &Strng, tools: Vec<Tool>) {
+		self.cache.insert(&self.backend, target, tools, self.ttl)
+	}
+
+	pub fn invalidate(&self, target: &Strng) {
+		self.cache.invalidate(&self.backend, target)
+	}
+
+	/// See [`ToolListCache::single_flight`]
+	pub async fn single_flight<F, Fut, E>(
+		&self,
+		target: &Strng,
+		fetch: F,
+		shared_error: impl FnOnce(String) -> E,
+	) -> Result<ListToolsResult, E>
+	where
+		F: FnOnce() -> Fut,
+		Fut: Future<Output = Result<ListToolsResult, E>>,
+		E: Display,
+	{
+		self
+			.cache
+			.single_flight(&self.backend, target, fetch, shared_error)
+			.await
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::sync::atomic::{AtomicUsize, Ordering};
+
+	use agent_core::strng;
+
+	use super::*;
+
+	fn tool(name: &str) -> Tool {
+		Tool::new(
+			name.to_string(),
+			"test tool",
+			Arc::new(serde_json::Map::new()),
+		)
+	}
+
+	fn list(tools: &[&str]) -> ListToolsResult {
+		ListToolsResult {
+			tools: tools.iter().map(|name| tool(name)).collect(),
+			next_cursor: None,
+			meta: None,
+		}
+	}
+
+	fn scope(ttl: Duration) -> ToolListCacheScope {
+		ToolListCacheScope {
+			cache: Arc::new(ToolListCache::new()),
+			backend: ResourceName::new(strng::literal!("mcp"), strng::literal!("default")),
+			ttl,
+		}
+	}
+
+	#[test]
+	fn test_insert_and_invalidate() {
+		let scope = scope(Duration::from_secs(60));
+		let target = strng::literal!("weather");
+		assert!(scope.get(&target).is_none());
+
+		scope.insert(&target, vec![tool("forecast")]);
+		assert_eq!(scope.get(&target).unwrap()[0].name, "forecast");
+
+		scope.invalidate(&target);
+		assert!(scope.get(&target).is_none());
+	}
+
+	#[test]
+	fn test_expired_entry_is_dropped() {
+		let scope = scope(Duration::ZERO);
+		let target = strng::literal!("weather");
+		scope.insert(&target, vec![tool("forecast")]);
+		assert!(scope.get(&target).is_none());
+	}
+
+	#[tokio::test]
+	async fn test_concurrent_misses_share_one_request() {
+		let scope = scope(Duration::from_secs(60));
+		let target = strng::literal!("weather");
+		let fetches = AtomicUsize::new(0);
+		let fetch = || async {
+			fetches.fetch_add(1, Ordering::SeqCst);
+			tokio::time::sleep(Duration::from_millis(20)).await;
+			Ok::<_, String>(list(&["forecast"]))
+		};
+		let results = futures::future::join_all(
+			(0..5).map(|_| scope.single_flight(&target, fetch, |e| format!("shared: {e}"))),
+		)
+		.await;
+		assert_eq!(fetches.load(Ordering::SeqCst), 1);
+		for result in results {
+			assert_eq!(result.unwrap().tools[0].name, "forecast");
+		}
+
+		// Once it is done, the next miss sends its own request
+		scope.single_flight(&target, fetch, |e| e).await.unwrap();
+		assert_eq!(fetches.load(Ordering::SeqCst), 2);
+	}
+
+	#[tokio::test]
+	async fn test_failure_is_shared_with_waiting_callers() {
+		let scope = scope(Duration::from_secs(60));
+		let target = strng::literal!("weather");
+		let fetches = AtomicUsize::new(0);
+		let fetch = || async {
+			fetches.fetch_add(1, Ordering::SeqCst);
+			tokio::time::sleep(Duration::from_millis(20)).await;
+			Err::<ListToolsResult, _>("connection refused".to_string())
+		};
+		let results = futures::future::join_all(
+			(0..3).map(|_| scope.single_flight(&target, fetch, |e| format!("shared: {e}"))),
+		)
+		.await;
+		assert_eq!(fetches.load(Ordering::SeqCst), 1);
+		assert_eq!(results[0], Err("connection refused".to_string()));
+		assert_eq!(results[1], Err("shared: connection refused".to_string()));
+
+		// A paginated list isn't shared: each caller gets a first page of its own
+		let paginated = || async {
+			fetches.fetch_add(1, Ordering::SeqCst);
+			tokio::time::sleep(Duration::from_millis(20)).await;
+			Ok::<_, String>(ListToolsResult {
+				next_cursor: Some("2".to_string()),
+				..list(&["forecast"])
+			})
+		};
+		let (a, b) = futures::join!(
+			scope.single_flight(&target, paginated, |e| e),
+			scope.single_flight(&target, paginated, |e| e),
+		);
+		assert!(a.is_ok() && b.is_ok());
+		assert_eq!(fetches.load(Ordering::SeqCst), 3);
+	}
+}
@@ -13,6 +13,7 @@ use thiserror::Error;
 use tokio::process::Command;
 
 use crate::http::jwt::Claims;
+use crate::http::localratelimit::RateLimitStatus;
 use crate::mcp::mergestream::Messages;
 use crate::mcp::router::{McpBackendGroup, McpTarget};
 use crate::mcp::streamablehttp::StreamableHttpPostResponse;
@@ -25,23 +26,46 @@ use crate::*;
 pub struct IncomingRequestContext {
 	headers: http::HeaderMap,
 	claims: Option<Claims>,
+	/// Quota left under the route's request rate limits
+	quota: Option<RateLimitStatus>,
 }
 
 impl IncomingRequestContext {
-	#[cfg(test)]
+	/// A context with no caller, for calls the gateway makes on its own behalf
 	pub fn empty() -> Self {
 		Self {
 			headers: http::HeaderMap::new(),
 			claims: None,
+			quota: None,
 		}
 	}
 	pub fn new(parts: ::http::request::Parts) -> Self {
 		let claims = parts.extensions.get::<Claims>().cloned();
+		let quota = parts.extensions.get::<RateLimitStatus>().copied();
 		Self {
 			headers: parts.headers,
 			claims,
+			quota,
 		}
 	}
+	/// Builder: set (or replace) a header propagated to the backend
+	pub fn with_header(mut self, name: http::HeaderName, value: http::HeaderValue) -> Self {
+		self.headers.insert(name, value);
+		self
+	}
+	pub fn header(&self, name: &str) -> Option<&http::HeaderValue> {
+		self.headers.get(name)
+	}
+	pub fn claims(&self) -> Option<&Claims> {
+		self.claims.as_ref()
+	}
+	/// Subject of the caller's JWT, if authenticated
+	pub fn subject(&self) -> Option<&str> {
+		self.claims()?.inner.get("sub")?.as_str()
+	}
+	pub fn quota(&self) -> Option<RateLimitStatus> {
+		self.quota
+	}
 	pub fn apply(&self, req: &mut http::Request) {
 		for (k, v) in &self.headers {
 			// Remove headers we do not want to propagate to the backend
@@ -67,6 +91,16 @@ pub enum UpstreamError {
 	},
 	#[error("invalid request: {0}")]
 	InvalidRequest(String),
+	/// A caller's arguments don't match the input schema listed for the tool, at every
+	/// place in `problems`
+	#[error(
+		"invalid arguments for tool {tool}: {}",
+		problems.iter().map(ToString::to_string).collect::<Vec<_>>().join("; ")
+	)]
+	InvalidArguments {
+		tool: String,
+		problems: Vec<crate::mcp::registry::ArgumentProblem>,
+	},
 	#[error("unsupported method: {0}")]
 	InvalidMethod(String),
 	#[error("method {0} is unsupported with multiplexing")]
@@ -83,6 +117,92 @@ pub enum UpstreamError {
 	Send,
 	#[error("upstream closed on receive")]
 	Recv,
+	#[error("upstream {0} timed out")]
+	Timeout(String),
+	#[error("upstream {0} is unavailable: its circuit breaker is open")]
+	CircuitOpen(String),
+	#[error("upstream process stopped ({0}) and is being restarted")]
+	ProcessRestarting(String),
+	#[error("{0}")]
+	LoadShed(crate::mcp::registry::Shed),
+	/// One failure reported to several callers, such as the debounced repeats of a call
+	#[error("{0}")]
+	Shared(SharedFailure),
+}
+
+/// A failure several callers can be given, such as the debounced repeats of a call
+///
+/// Only the message and how the failure is classified are kept, since the failure itself
+/// may hold a response that only one caller can read.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SharedFailure {
+	message: String,
+	transport: bool,
+	upstream_failure: bool,
+}
+
+impl SharedFailure {
+	pub fn new(e: &UpstreamError) -> Self {
+		Self {
+			message: e.to_string(),
+			transport: e.is_transport(),
+			upstream_failure: e.is_upstream_failure(),
+		}
+	}
+}
+
+impl std::fmt::Display for SharedFailure {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		f.write_str(&self.message)
+	}
+}
+
+impl From<crate::mcp::registry::PaginationError> for UpstreamError {
+	fn from(e: crate::mcp::registry::PaginationError) -> Self {
+		UpstreamError::InvalidRequest(e.to_string())
+	}
+}
+
+impl From<crate::mcp::registry::JobPollingError> for UpstreamError {
+	fn from(e: crate::mcp::registry::JobPollingError) -> Self {
+		UpstreamError::InvalidRequest(e.to_string())
+	}
+}
+
+impl UpstreamError {
+	/// Whether the failure happened at the transport level, before any response was received
+	pub fn is_transport(&self) -> bool {
+		if let UpstreamError::Shared(failure) = self {
+			return failure.transport;
+		}
+		matches!(
+			self,
+			UpstreamError::Send
+				| UpstreamError::Recv
+				| UpstreamError::Http(mcp::ClientError::General(_))
+				| UpstreamError::ProcessRestarting(_)
+		)
+	}
+
+	/// Whether the failure says something about the upstream's health, rather than about the
+	/// request
+	pub fn is_upstream_failure(&self) -> bool {
+		match self {
+			UpstreamError::Http(mcp::ClientError::Status(resp)) => resp.status().is_server_error(),
+			UpstreamError::Shared(failure) => failure.upstream_failure,
+			_ => matches!(
+				self,
+				UpstreamError::ServiceError(_)
+					| UpstreamError::Http(_)
+					| UpstreamError::OpenAPIError(_)
+					| UpstreamError::Stdio(_)
+					| UpstreamError::Send
+					| UpstreamError::Recv
+					| UpstreamError::Timeout(_)
+					| UpstreamError::ProcessRestarting(_)
+			),
+		}
+	}
 }
 
 // UpstreamTarget defines a source for MCP information.
@@ -287,7 +407,12 @@ impl UpstreamGroup {
 
 				upstream::Upstream::McpStreamable(client)
 			},
-			McpTargetSpec::Stdio { cmd, args, env } => {
+			McpTargetSpec::Stdio {
+				cmd,
+				args,
+				env,
+				restart,
+			} => {
 				tracing::info!(
 					target: "connections",
 					target_name = %target.name,
@@ -302,16 +427,22 @@ impl UpstreamGroup {
 				// for more context.
 				let cmd = which::which(cmd)?;
 				#[cfg(target_family = "unix")]
-				let mut c = Command::new(cmd);
-				#[cfg(target_os = "windows")]
-				let mut c = Command::new(&cmd);
-				c.args(args);
-				for (k, v) in env {
-					c.env(k, v);
-				}
-				let proc =
-					TokioChildProcess::new(c).context(format!("failed to run command '{:?}'", &cmd))?;
-				upstream::Upstream::McpStdio(upstream::stdio::Process::new(proc))
+				let cmd = cmd.clone();
+				let (args, env) = (args.clone(), env.clone());
+				let spawn = move || {
+					let mut c = Command::new(&cmd);
+					c.args(&args);
+					for (k, v) in &env {
+						c.env(k, v);
+					}
+					TokioChildProcess::new(c).context(format!("failed to run command '{:?}'", &cmd))
+				};
+				let proc = spawn()?;
+				upstream::Upstream::McpStdio(upstream::stdio::Process::supervised(
+					proc,
+					spawn,
+					restart.clone(),
+				))
 			},
 			McpTargetSpec::OpenAPI(open) => {
 				// Renamed for clarity
@@ -16,12 +16,20 @@ use tracing::{error, warn};
 
 use crate::mcp::mergestream::Messages;
 use crate::mcp::upstream::{IncomingRequestContext, UpstreamError};
+use crate::types::agent::StdioRestart;
+
+/// Callers waiting for the response to a request, by request id
+type PendingRequests =
+	Arc<Mutex<HashMap<RequestId, oneshot::Sender<Result<ServerJsonRpcMessage, UpstreamError>>>>>;
+
+/// How long a restarted process may take to answer the replayed `initialize` request
+const REINITIALIZE_TIMEOUT: Duration = Duration::from_secs(30);
 
 pub struct Process {
 	sender: mpsc::Sender<(ClientJsonRpcMessage, IncomingRequestContext)>,
 	shutdown_tx: agent_core::responsechannel::Sender<(), Option<UpstreamError>>,
 	event_stream: AtomicOption<mpsc::Sender<ServerJsonRpcMessage>>,
-	pending_requests: Arc<Mutex<HashMap<RequestId, oneshot::Sender<ServerJsonRpcMessage>>>>,
+	pending_requests: PendingRequests,
 }
 
 impl Process {
@@ -53,8 +61,7 @@ impl Process {
 			.await
 			.map_err(|_| UpstreamError::Send)?;
 
-		let response = receiver.await.map_err(|_| UpstreamError::Recv)?;
-		Ok(response)
+		receiver.await.map_err(|_| UpstreamError::Recv)?
 	}
 	pub async fn get_event_stream(&self) -> Messages {
 		let (tx, rx) = tokio::sync::mpsc::channel(10);
@@ -75,45 +82,140 @@ impl Process {
 	}
 }
 
+/// Why a transport stopped serving
+enum Exit {
+	/// `stop` was called
+	Shutdown(oneshot::Sender<Option<UpstreamError>>),
+	/// The `Process` was dropped
+	Dropped,
+	/// The transport failed or the server process exited
+	Died(String),
+}
+
+/// The client's initialize handshake, replayed to every restarted process so the session
+/// carries on where it left off
+#[derive(Default)]
+struct Handshake {
+	initialize: Option<(ClientJsonRpcMessage, IncomingRequestContext)>,
+	initialized: Option<ClientJsonRpcMessage>,
+}
+
+impl Handshake {
+	fn observe(&mut self, msg: &ClientJsonRpcMessage, ctx: &IncomingRequestContext) {
+		match msg {
+			JsonRpcMessage::Request(r) if matches!(r.request, ClientRequest::InitializeRequest(_)) => {
+				self.initialize = Some((msg.clone(), ctx.clone()));
+				self.initialized = None;
+			},
+			JsonRpcMessage::Notification(n)
+				if matches!(
+					n.notification,
+					ClientNotification::InitializedNotification(_)
+				) =>
+			{
+				self.initialized = Some(msg.clone());
+			},
+			_ => {},
+		}
+	}
+
+	/// Bring a freshly started server to the state the client left the previous one in
+	async fn replay(
+		&self,
+		proc: &mut impl MCPTransport,
+		events: &AtomicOption<Sender<ServerJsonRpcMessage>>,
+	) -> Result<(), UpstreamError> {
+		let Some((initialize, ctx)) = &self.initialize else {
+			return Ok(());
+		};
+		proc.send(initialize.clone(), ctx).await?;
+		// Anything the server sends before its initialize response goes to the event stream
+		let initialized = tokio::time::timeout(REINITIALIZE_TIMEOUT, async {
+			loop {
+				match proc.receive().await {
+					None => return Err(UpstreamError::Recv),
+					Some(JsonRpcMessage::Response(_)) => return Ok(()),
+					Some(JsonRpcMessage::Error(e)) => {
+						return Err(UpstreamError::InvalidRequest(format!(
+							"re-initializing the restarted process failed: {}",
+							e.error.message
+						)));
+					},
+					Some(other) => {
+						if let Some(sender) = events.load().as_ref() {
+							let _ = sender.send(other).await;
+						}
+					},
+				}
+			}
+		})
+		.await
+		.map_err(|_| UpstreamError::Timeout("stdio re-initialize".to_string()))?;
+		initialized?;
+		if let Some(notification) = &self.initialized {
+			proc.send(notification.clone(), ctx).await?;
+		}
+		Ok(())
+	}
+}
+
 impl Process {
-	pub fn new(mut proc: impl MCPTransport) -> Self {
+	/// Serve MCP over a transport that can't be restarted
+	pub fn new(proc: impl MCPTransport) -> Self {
+		Self::start(proc, None::<(fn() -> anyhow::Result<_>, StdioRestart)>)
+	}
+
+	/// Serve MCP over a child process, starting a replacement with `respawn` whenever it
+	/// exits.
+	///
+	/// Requests in flight when the process exits fail with [`UpstreamError::ProcessRestarting`],
+	/// which counts as a transport failure, so retries pick them up once the replacement is
+	/// running. Requests sent while restarting wait for the replacement. The client's
+	/// initialize handshake is replayed to each replacement before it receives anything else.
+	/// After `restart.max_attempts` failed restarts in a row the upstream is given up on.
+	pub fn supervised<T, F>(proc: T, respawn: F, restart: StdioRestart) -> Self
+	where
+		T: MCPTransport,
+		F: FnMut() -> anyhow::Result<T> + Send + 'static,
+	{
+		Self::start(proc, Some((respawn, restart)))
+	}
+
+	fn start<T, F>(mut proc: T, mut restart: Option<(F, StdioRestart)>) -> Self
+	where
+		T: MCPTransport,
+		F: FnMut() -> anyhow::Result<T> + Send + 'static,
+	{
 		let (sender_tx, mut sender_rx) =
 			mpsc::channel::<(ClientJsonRpcMessage, IncomingRequestContext)>(10);
 		let (shutdown_tx, mut shutdown_rx) =
 			agent_core::responsechannel::new::<(), Option<UpstreamError>>(10);
-		let pending_requests = Arc::new(Mutex::new(HashMap::<
-			RequestId,
-			oneshot::Sender<ServerJsonRpcMessage>,
-		>::new()));
+		let pending_requests = PendingRequests::default();
 		let pending_requests_clone = pending_requests.clone();
 		let event_stream: AtomicOption<Sender<ServerJsonRpcMessage>> = Default::default();
 		let event_stream_send: AtomicOption<Sender<ServerJsonRpcMessage>> = event_stream.clone();
 
 		tokio::spawn(async move {
+			let mut handshake = Handshake::default();
+			// Restarts since a process last answered a request
+			let mut restarts = 0;
 			loop {
-				tokio::select! {
-					Some((msg, ctx)) = sender_rx.recv() => {
-						if let Err(e) = proc.send(msg, &ctx).await {
-							error!("Error sending message to stdio process: {:?}", e);
-							break;
-						}
-					},
-					Some(msg) = proc.receive() => {
-						match msg {
-							JsonRpcMessage::Response(res) => {
-								let req_id = res.id.clone();
-								if let Some(sender) = pending_requests_clone.lock().unwrap().remove(&req_id) {
-									let _ = sender.send(ServerJsonRpcMessage::Response(res));
-								}
-							},
-							other => {
-								if let Some(sender) = event_stream_send.load().as_ref() {
-									let _ = sender.send(other).await;
-								}
-							}
-						}
-					},
-					Some((_, resp)) = shutdown_rx.recv() => {
+				let mut answered = false;
+				let exit = serve(
+					&mut proc,
+					&mut sender_rx,
+					&mut shutdown_rx,
+					&pending_requests_clone,
+					&event_stream_send,
+					&mut handshake,
+					&mut answered,
+				)
+				.await;
+				if answered {
+					restarts = 0;
+				}
+				let reason = match exit {
+					Exit::Shutdown(resp) => {
 						let err = proc.close().await;
 						if let Err(e) = &err {
 							warn!("Error shutting down stdio process: {:?}", e);
@@ -121,13 +223,69 @@ impl Process {
 						let _ = resp.send(err.err());
 						return;
 					},
-					else => {
+					Exit::Dropped => {
 						let err = proc.close().await;
 						if let Err(e) = err {
 							warn!("Error shutting down stdio process: {:?}", e);
 						}
 						return;
 					},
+					Exit::Died(reason) => reason,
+				};
+				let Some((respawn, policy)) = restart.as_mut() else {
+					error!("stdio process stopped: {reason}");
+					let _ = proc.close().await;
+					sender_rx.close();
+					pending_requests_clone.lock().unwrap().clear();
+					return;
+				};
+				warn!("stdio process stopped, restarting: {reason}");
+				for (_, sender) in pending_requests_clone.lock().unwrap().drain() {
+					let _ = sender.send(Err(UpstreamError::ProcessRestarting(reason.clone())));
+				}
+				let _ = proc.close().await;
+				// Start a replacement, backing off between failed attempts
+				loop {
+					if restarts >= policy.max_attempts {
+						error!(
+							attempts = restarts,
+							"giving up on stdio process after repeated restart failures"
+						);
+						sender_rx.close();
+						pending_requests_clone.lock().unwrap().clear();
+						return;
+					}
+					let delay = policy.backoff(restarts);
+					restarts += 1;
+					tokio::select! {
+						_ = tokio::time::sleep(delay) => {},
+						req = shutdown_rx.recv() => {
+							if let Some((_, resp)) = req {
+								let _ = resp.send(None);
+							}
+							return;
+						},
+					}
+					let mut replacement = match respawn() {
+						Ok(replacement) => replacement,
+						Err(e) => {
+							warn!(attempt = restarts, "failed to restart stdio process: {e:#}");
+							continue;
+						},
+					};
+					match handshake.replay(&mut replacement, &event_stream_send).await {
+						Ok(()) => {
+							proc = replacement;
+							break;
+						},
+						Err(e) => {
+							warn!(
+								attempt = restarts,
+								"failed to re-initialize stdio process: {e}"
+							);
+							let _ = replacement.close().await;
+						},
+					}
 				}
 			}
 		});
@@ -141,6 +299,55 @@ impl Process {
 	}
 }
 
+/// Relay messages between callers and `proc` until it stops
+async fn serve(
+	proc: &mut impl MCPTransport,
+	sender_rx: &mut mpsc::Receiver<(ClientJsonRpcMessage, IncomingRequestContext)>,
+	shutdown_rx: &mut agent_core::responsechannel::Receiver<(), Option<UpstreamError>>,
+	pending_requests: &PendingRequests,
+	event_stream: &AtomicOption<Sender<ServerJsonRpcMessage>>,
+	handshake: &mut Handshake,
+	answered: &mut bool,
+) -> Exit {
+	loop {
+		tokio::select! {
+			msg = sender_rx.recv() => {
+				let Some((msg, ctx)) = msg else {
+					return Exit::Dropped;
+				};
+				handshake.observe(&msg, &ctx);
+				if let Err(e) = proc.send(msg, &ctx).await {
+					error!("Error sending message to stdio process: {:?}", e);
+					return Exit::Died(e.to_string());
+				}
+			},
+			msg = proc.receive() => {
+				match msg {
+					None => return Exit::Died("the process closed its output".to_string()),
+					Some(JsonRpcMessage::Response(res)) => {
+						*answered = true;
+						let req_id = res.id.clone();
+						if let Some(sender) = pending_requests.lock().unwrap().remove(&req_id) {
+							let _ = sender.send(Ok(ServerJsonRpcMessage::Response(res)));
+						}
+					},
+					Some(other) => {
+						if let Some(sender) = event_stream.load().as_ref() {
+							let _ = sender.send(other).await;
+						}
+					},
+				}
+			},
+			req = shutdown_rx.recv() => {
+				return match req {
+					Some((_, resp)) => Exit::Shutdown(resp),
+					None => Exit::Dropped,
+				};
+			},
+		}
+	}
+}
+
 impl Debug for Process {
 	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
 		f.debug_struct("Process").finish()
@@ -182,3 +389,176 @@ impl MCPTransport for TokioChildProcess {
 		Transport::close(self).map_err(Into::into)
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use std::sync::atomic::{AtomicU32, Ordering};
+
+	use rmcp::model::{
+		ClientInfo, InitializeRequest, InitializedNotification, PingRequest, ServerResult,
+	};
+
+	use super::*;
+
+	/// A server process whose other end is played by the test
+	struct FakeProcess {
+		to_server: mpsc::UnboundedSender<ClientJsonRpcMessage>,
+		from_server: mpsc::UnboundedReceiver<ServerJsonRpcMessage>,
+	}
+
+	struct FakeServer {
+		requests: mpsc::UnboundedReceiver<ClientJsonRpcMessage>,
+		responses: mpsc::UnboundedSender<ServerJsonRpcMessage>,
+	}
+
+	impl FakeServer {
+		/// Receive the next message, answering it with an empty result if it is a request
+		async fn answer(&mut self) -> ClientJsonRpcMessage {
+			let msg = self.requests.recv().await.unwrap();
+			if let JsonRpcMessage::Request(r) = &msg {
+				let response = ServerJsonRpcMessage::response(ServerResult::empty(()), r.id.clone());
+				let _ = self.responses.send(response);
+			}
+			msg
+		}
+	}
+
+	fn fake() -> (FakeProcess, FakeServer) {
+		let (to_server, requests) = mpsc::unbounded_channel();
+		let (responses, from_server) = mpsc::unbounded_channel();
+		(
+			FakeProcess {
+				to_server,
+				from_server,
+			},
+			FakeServer {
+				requests,
+				responses,
+			},
+		)
+	}
+
+	impl MCPTransport for FakeProcess {
+		fn send(
+			&mut self,
+			item: ClientJsonRpcMessage,
+			_: &IncomingRequestContext,
+		) -> impl Future<Output = Result<(), UpstreamError>> + Send + 'static {
+			std::future::ready(self.to_server.send(item).map_err(|_| UpstreamError::Send))
+		}
+
+		fn receive(&mut self) -> impl Future<Output = Option<ServerJsonRpcMessage>> + Send {
+			self.from_server.recv()
+		}
+
+		fn close(&mut self) -> impl Future<Output = Result<(), UpstreamError>> + Send {
+			std::future::ready(Ok(()))
+		}
+	}
+
+	fn restart(max_attempts: u32) -> StdioRestart {
+		StdioRestart {
+			initial_backoff: Duration::ZERO,
+			max_backoff: Duration::ZERO,
+			max_attempts,
+		}
+	}
+
+	fn request(id: i64, request: ClientRequest) -> JsonRpcRequest<ClientRequest> {
+		JsonRpcRequest {
+			jsonrpc: Default::default(),
+			id: RequestId::Number(id),
+			request,
+		}
+	}
+
+	fn ping(id: i64) -> JsonRpcRequest<ClientRequest> {
+		request(
+			id,
+			ClientRequest::PingRequest(PingRequest {
+				method: Default::default(),
+				extensions: Default::default(),
+			}),
+		)
+	}
+
+	#[tokio::test]
+	async fn test_restart_replays_handshake() {
+		let (first, mut server) = fake();
+		let (spawned_tx, mut spawned) = mpsc::unbounded_channel();
+		let process = Process::supervised(
+			first,
+			move || {
+				let (proc, server) = fake();
+				spawned_tx.send(server).unwrap();
+				Ok(proc)
+			},
+			restart(3),
+		);
+		let ctx = IncomingRequestContext::empty();
+
+		let initialize = request(
+			1,
+			ClientRequest::InitializeRequest(InitializeRequest {
+				method: Default::default(),
+				params: ClientInfo::default(),
+				extensions: Default::default(),
+			}),
+		);
+		let (res, _) = tokio::join!(process.send_message(initialize, &ctx), server.answer());
+		assert!(res.is_ok());
+		let initialized = ClientNotification::InitializedNotification(InitializedNotification {
+			method: Default::default(),
+			extensions: Default::default(),
+		});
+		process.send_notification(initialized, &ctx).await.unwrap();
+		server.answer().await;
+
+		// The process exits with a request in flight
+		let (res, _) = tokio::join!(process.send_message(ping(2), &ctx), async move {
+			let mut server = server;
+			server.requests.recv().await
+		});
+		assert!(matches!(res, Err(UpstreamError::ProcessRestarting(_))));
+
+		// The replacement is initialized before it gets the retried request
+		let mut server = spawned.recv().await.unwrap();
+		assert!(matches!(
+			server.answer().await,
+			JsonRpcMessage::Request(r) if matches!(r.request, ClientRequest::InitializeRequest(_))
+		));
+		assert!(matches!(
+			server.answer().await,
+			JsonRpcMessage::Notification(_)
+		));
+		let (res, _) = tokio::join!(process.send_message(ping(3), &ctx), server.answer());
+		assert!(res.is_ok());
+	}
+
+	#[tokio::test]
+	async fn test_gives_up_after_max_attempts() {
+		let (first, server) = fake();
+		let attempts = Arc::new(AtomicU32::new(0));
+		let counter = attempts.clone();
+		let process = Process::supervised(
+			first,
+			move || {
+				counter.fetch_add(1, Ordering::SeqCst);
+				anyhow::bail!("command not found")
+			},
+			restart(2),
+		);
+		drop(server);
+
+		let ctx = IncomingRequestContext::empty();
+		let res = process.send_message(ping(1), &ctx).await;
+		assert!(matches!(res, Err(UpstreamError::ProcessRestarting(_))));
+
+		// Requests wait for the restart, and fail once it is given up on
+		let res = tokio::time::timeout(Duration::from_secs(5), process.send_message(ping(2), &ctx))
+			.await
+			.expect("requests fail once the process is given up on");
+		assert!(res.is_err());
+		assert_eq!(attempts.load(Ordering::SeqCst), 2);
+	}
+}
@@ -1,3 +1,5 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+
 use ::http::{Method, StatusCode, Version};
 use agent_core::strng;
 use assert_matches::assert_matches;
@@ -9,6 +11,7 @@ use serde::de::DeserializeOwned;
 use serde_json::{Value, json};
 use x509_parser::nom::AsBytes;
 
+use crate::http::remoteratelimit::{Descriptor, DescriptorEntry, DescriptorSet, RemoteRateLimit};
 use crate::http::tests_common::*;
 use crate::http::transformation_cel::Transformation;
 use crate::http::{Body, Response, transformation_cel};
@@ -18,7 +21,8 @@ use crate::test_helpers::proxymock::*;
 use crate::types::agent::{
 	Backend, BackendPolicy, BackendReference, BackendWithPolicies, Bind, BindProtocol, Listener,
 	ListenerProtocol, ListenerSet, PathMatch, PolicyTarget, ResourceName, Route,
-	RouteBackendReference, RouteMatch, RouteName, RouteSet, Target, TargetedPolicy, TrafficPolicy,
+	RouteBackendReference, RouteMatch, RouteName, RouteSet, SimpleBackendReference, Target,
+	TargetedPolicy, TrafficPolicy,
 };
 use crate::types::backend;
 use crate::*;
@@ -86,8 +90,201 @@ async fn local_ratelimit() {
 
 	let res = send_request(io.clone(), Method::GET, "http://lo").await;
 	assert_eq!(res.status(), 200);
+	assert_eq!(res.headers()["x-ratelimit-limit"], "1");
+	assert_eq!(res.headers()["x-ratelimit-remaining"], "0");
+	let res = send_request(io.clone(), Method::GET, "http://lo").await;
+	assert_eq!(res.status(), 429);
+	assert_eq!(res.headers()["x-ratelimit-remaining"], "0");
+}
+
+#[tokio::test]
+async fn local_ratelimit_applies_cors() {
+	let (_mock, bind, io) = basic_setup().await;
+	let route = PolicyTarget::Route(RouteName {
+		name: "route".into(),
+		namespace: "".into(),
+		rule_name: None,
+		kind: None,
+	});
+	let policy = |key: &str, policy: TrafficPolicy| TargetedPolicy {
+		key: strng::new(key),
+		name: None,
+		target: route.clone(),
+		policy: policy.into(),
+	};
+	let _bind = bind
+		.with_policy(policy(
+			"rl",
+			TrafficPolicy::LocalRateLimit(vec![
+				http::localratelimit::RateLimitSpec {
+					max_tokens: 1,
+					tokens_per_fill: 1,
+					fill_interval: Duration::from_secs(60),
+					limit_type: Default::default(),
+				}
+				.try_into()
+				.unwrap(),
+			]),
+		))
+		.with_policy(policy(
+			"csrf",
+			TrafficPolicy::Csrf(http::csrf::Csrf::new(Default::default())),
+		))
+		.with_policy(policy(
+			"cors",
+			TrafficPolicy::CORS(
+				http::cors::Cors::try_from(http::cors::CorsSerde {
+					allow_credentials: false,
+					allow_headers: vec![],
+					allow_methods: vec![],
+					allow_origins: vec!["http://app.example".to_string()],
+					expose_headers: vec![],
+					max_age: None,
+				})
+				.unwrap(),
+			),
+		));
+
+	let res = send_request(io.clone(), Method::GET, "http://lo").await;
+	assert_eq!(res.status(), 200);
+
+	// Still rejected, with the CORS headers the route adds
+	let res = send_request_headers(
+		io.clone(),
+		Method::GET,
+		"http://lo",
+		&[("origin", "http://app.example")],
+	)
+	.await;
+	assert_eq!(res.status(), 429);
+	assert_eq!(res.hdr("access-control-allow-origin"), "http://app.example");
+
+	// Rejected before CSRF validation runs
+	let res = send_request_headers(
+		io.clone(),
+		Method::POST,
+		"http://lo",
+		&[("sec-fetch-site", "cross-site")],
+	)
+	.await;
+	assert_eq!(res.status(), 429);
+}
+
+#[tokio::test]
+async fn local_ratelimit_skips_direct_response() {
+	let mock = simple_mock().await;
+	let bind = base_gateway(&mock).with_route(Route {
+		key: "route2".into(),
+		name: RouteName {
+			name: "route2".into(),
+			namespace: Default::default(),
+			rule_name: None,
+			kind: None,
+		},
+		hostnames: Default::default(),
+		matches: vec![RouteMatch {
+			headers: vec![],
+			path: PathMatch::PathPrefix("/p".into()),
+			method: None,
+			query: vec![],
+		}],
+		inline_policies: vec![
+			TrafficPolicy::LocalRateLimit(vec![
+				http::localratelimit::RateLimitSpec {
+					max_tokens: 1,
+					tokens_per_fill: 1,
+					fill_interval: Duration::from_secs(60),
+					limit_type: Default::default(),
+				}
+				.try_into()
+				.unwrap(),
+			]),
+			TrafficPolicy::DirectResponse(crate::http::filters::DirectResponse {
+				body: Bytes::from_static(b"hello"),
+				status: StatusCode::UNPROCESSABLE_ENTITY,
+			}),
+		],
+		backends: vec![],
+	});
+	let io = bind.serve_http(BIND_KEY);
+
+	let res = send_request(io.clone(), Method::GET, "http://lo/p").await;
+	assert_eq!(res.status(), 422);
+	let res = send_request(io.clone(), Method::GET, "http://lo/p").await;
+	assert_eq!(res.status(), 429);
+	assert_eq!(res.hdr("x-ratelimit-remaining"), "0");
+}
+
+#[tokio::test]
+async fn local_ratelimit_skips_remote_ratelimit() {
+	// Stands in for the rate limit service, counting the connections made to it
+	let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+	let service = listener.local_addr().unwrap();
+	let connections = Arc::new(AtomicUsize::new(0));
+	let accepted = connections.clone();
+	tokio::spawn(async move {
+		while let Ok((stream, _)) = listener.accept().await {
+			accepted.fetch_add(1, Ordering::SeqCst);
+			drop(stream);
+		}
+	});
+
+	let (_mock, bind, io) = basic_setup().await;
+	let route = PolicyTarget::Route(RouteName {
+		name: "route".into(),
+		namespace: "".into(),
+		rule_name: None,
+		kind: None,
+	});
+	let policy = |key: &str, policy: TrafficPolicy| TargetedPolicy {
+		key: strng::new(key),
+		name: None,
+		target: route.clone(),
+		policy: policy.into(),
+	};
+	let _bind = bind
+		.with_policy(policy(
+			"rl",
+			TrafficPolicy::LocalRateLimit(vec![
+				http::localratelimit::RateLimitSpec {
+					max_tokens: 1,
+					tokens_per_fill: 1,
+					fill_interval: Duration::from_secs(60),
+					limit_type: Default::default(),
+				}
+				.try_into()
+				.unwrap(),
+			]),
+		))
+		.with_policy(policy(
+			"rrl",
+			TrafficPolicy::RemoteRateLimit(RemoteRateLimit {
+				domain: "test".to_string(),
+				target: Arc::new(SimpleBackendReference::InlineBackend(Target::Address(
+					service,
+				))),
+				descriptors: Arc::new(DescriptorSet(vec![DescriptorEntry {
+					entries: Arc::new(vec![Descriptor(
+						"route".to_string(),
+						cel::Expression::new_strict("\"route\"").unwrap(),
+					)]),
+					limit_type: http::localratelimit::RateLimitType::Requests,
+				}])),
+				timeout: Some(Duration::from_secs(1)),
+			}),
+		));
+
+	// The service can't answer, so the request that took the only token fails there
+	let res = send_request(io.clone(), Method::GET, "http://lo").await;
+	assert_eq!(res.status(), 429);
+	let contacted = connections.load(Ordering::SeqCst);
+	assert!(contacted > 0);
+
+	// Over the local limit, the next is rejected without asking the service
 	let res = send_request(io.clone(), Method::GET, "http://lo").await;
 	assert_eq!(res.status(), 429);
+	assert_eq!(res.hdr("x-ratelimit-remaining"), "0");
+	assert_eq!(connections.load(Ordering::SeqCst), contacted);
 }
 
 #[tokio::test]
@@ -20,6 +20,7 @@ use crate::client::{ApplicationTransport, Transport};
 use crate::http::backendtls::BackendTLS;
 use crate::http::ext_proc::ExtProcRequest;
 use crate::http::filters::{AutoHostname, BackendRequestTimeout};
+use crate::http::localratelimit::{RateLimitStatus, RateLimited};
 use crate::http::transformation_cel::Transformation;
 use crate::http::{
 	Authority, HeaderName, HeaderValue, PolicyResponse, Request, Response, Scheme, StatusCode, Uri,
@@ -76,6 +77,47 @@ fn build_ctx<'a>(
 	Ok(res)
 }
 
+/// Check the local rate limits of `policies`, reporting the tightest quota in response headers.
+/// Returns whether the request is over a limit; it is then rejected once the backend is known,
+/// so MCP backends can answer in JSON-RPC.
+fn apply_local_rate_limit(
+	policies: &store::RoutePolicies,
+	req: &mut Request,
+	response_policies: &mut ResponsePolicies,
+) -> Result<bool, ProxyResponse> {
+	let mut quota: Option<RateLimitStatus> = None;
+	for lrl in &policies.local_rate_limit {
+		match lrl.check_request() {
+			Ok(status) => {
+				quota = match (quota, status) {
+					(Some(q), Some(s)) => Some(q.tightest(s)),
+					(q, s) => q.or(s),
+				}
+			},
+			Err(ProxyError::RateLimitExceeded {
+				limit,
+				remaining,
+				reset_seconds,
+			}) => {
+				let status = RateLimitStatus {
+					limit,
+					remaining,
+					reset_seconds,
+				};
+				status.apply_headers(response_policies.headers());
+				req.extensions_mut().insert(RateLimited(status));
+				return Ok(true);
+			},
+			Err(e) => return Err(e.into()),
+		}
+	}
+	if let Some(quota) = quota {
+		quota.apply_headers(response_policies.headers());
+		req.extensions_mut().insert(quota);
+	}
+	Ok(false)
+}
+
 async fn apply_request_policies(
 	policies: &store::RoutePolicies,
 	client: PolicyClient,
@@ -120,8 +162,16 @@ async fn apply_request_policies(
 			.map_err(|_| ProxyResponse::from(ProxyError::AuthorizationFailed))?;
 	}
 
-	for lrl in &policies.local_rate_limit {
-		lrl.check_request()?;
+	// A request over its local rate limit skips the remaining policies, so it never reserves an
+	// idempotency key or reaches a remote rate limit service. Only CORS still applies, so
+	// browsers can read the rejection.
+	if apply_local_rate_limit(policies, req, response_policies)? {
+		if let Some(c) = &policies.cors {
+			c.apply(req)
+				.map_err(ProxyError::from)?
+				.apply(response_policies.headers())?;
+		}
+		return Ok(());
 	}
 
 	// Check idempotency - may return cached response or error
@@ -693,9 +743,18 @@ impl HTTPProxy {
 		)
 		.await?;
 
-		let selected_backend =
-			select_backend(selected_route.as_ref(), &req).ok_or(ProxyError::NoValidBackends)?;
-		let selected_backend = resolve_backend(selected_backend, self.inputs.as_ref())?;
+		let selected_backend = select_backend(selected_route.as_ref(), &req)
+			.map(|b| resolve_backend(b, self.inputs.as_ref()));
+		// A rate limited request is only passed on to MCP backends, which answer it in JSON-RPC
+		if let Some(limited) = req.extensions().get::<RateLimited>()
+			&& !matches!(
+				&selected_backend,
+				Some(Ok(b)) if matches!(b.backend.backend, Backend::MCP(..))
+			)
+		{
+			return Err(ProxyError::from(*limited).into());
+		}
+		let selected_backend = selected_backend.ok_or(ProxyError::NoValidBackends)??;
 		let backend_policies = get_backend_policies(
 			self.inputs.as_ref(),
 			&selected_backend.backend,
@@ -187,6 +187,7 @@ impl LocalClient {
 		let config = crate::types::local::NormalizedLocalConfig::from(
 			self.client.clone(),
 			self.gateway.clone(),
+			self.stores.events.clone(),
 			config_content.as_str(),
 		)
 		.await?;
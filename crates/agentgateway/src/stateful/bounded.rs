@@ -0,0 +1,311 @@
+//! Bounded in-memory maps for state keyed by caller input.
+//!
+//! Keys such as per-user idempotency keys or per-argument throttle buckets are unbounded in
+//! number, so a long-running gateway can't keep them forever. A [`BoundedMap`] drops entries
+//! whose TTL has passed and, once it holds its maximum number of entries, evicts the least
+//! recently used one to make room. Both kinds of removal are counted, so they can be exported
+//! as metrics.
+
+use std::borrow::Borrow;
+use std::collections::{BTreeMap, HashMap};
+use std::hash::Hash;
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+
+/// Size and removal counters of a bounded map
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EvictionStats {
+	/// Entries held, including expired ones not yet removed
+	pub entries: usize,
+	/// Entries removed because their TTL passed
+	pub expired: u64,
+	/// Entries evicted to stay within the maximum size
+	pub evicted: u64,
+}
+
+#[derive(Debug)]
+struct Slot<V> {
+	value: V,
+	/// Stable id of the entry, ordering entries that expire at the same instant
+	id: u64,
+	/// When the entry was last used; a larger tick is more recent
+	tick: u64,
+	expires_at: Option<Instant>,
+}
+
+impl<V> Slot<V> {
+	fn is_expired(&self, now: Instant) -> bool {
+		self.expires_at.is_some_and(|exp| now >= exp)
+	}
+}
+
+/// Map whose entries expire after a TTL, with least-recently-used eviction at a maximum size
+#[derive(Debug)]
+pub struct BoundedMap<K, V> {
+	entries: HashMap<K, Slot<V>>,
+	/// Keys by last use, least recent first
+	recency: BTreeMap<u64, K>,
+	/// Keys of entries with a TTL, soonest to expire first
+	expiries: BTreeMap<(Instant, u64), K>,
+	next_tick: u64,
+	max_entries: Option<usize>,
+	expired: u64,
+	evicted: u64,
+}
+
+impl<K, V> Default for BoundedMap<K, V> {
+	fn default() -> Self {
+		Self {
+			entries: HashMap::new(),
+			recency: BTreeMap::new(),
+			expiries: BTreeMap::new(),
+			next_tick: 0,
+			max_entries: None,
+			expired: 0,
+			evicted: 0,
+		}
+	}
+}
+
+impl<K: Hash + Eq + Clone, V> BoundedMap<K, V> {
+	/// Create a map holding at most `max_entries` entries (unbounded if `None`)
+	pub fn new(max_entries: Option<usize>) -> Self {
+		Self {
+			max_entries,
+			..Default::default()
+		}
+	}
+
+	/// Most entries the map holds, if bounded
+	pub fn max_entries(&self) -> Option<usize> {
+		self.max_entries
+	}
+
+	/// Number of entries, including expired ones not yet removed
+	pub fn len(&self) -> usize {
+		self.entries.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.entries.is_empty()
+	}
+
+	/// Get an unexpired entry without marking it used
+	pub fn peek<Q: Hash + Eq + ?Sized>(&self, key: &Q) -> Option<&V>
+	where
+		K: Borrow<Q>,
+	{
+		self.peek_with_expiry(key).map(|(value, _)| value)
+	}
+
+	/// Get an unexpired entry and when it expires, without marking it used
+	pub fn peek_with_expiry<Q: Hash + Eq + ?Sized>(&self, key: &Q) -> Option<(&V, Option<Instant>)>
+	where
+		K: Borrow<Q>,
+	{
+		self
+			.entries
+			.get(key)
+			.filter(|slot| !slot.is_expired(Instant::now()))
+			.map(|slot| (&slot.value, slot.expires_at))
+	}
+
+	/// Get an unexpired entry, marking it used
+	pub fn get<Q: Hash + Eq + ?Sized>(&mut self, key: &Q) -> Option<&V>
+	where
+		K: Borrow<Q>,
+	{
+		self.get_mut(key).map(|value| &*value)
+	}
+
+	/// Get an unexpired entry mutably, marking it used
+	pub fn get_mut<Q: Hash + Eq + ?Sized>(&mut self, key: &Q) -> Option<&mut V>
+	where
+		K: Borrow<Q>,
+	{
+		let now = Instant::now();
+		let (stored, expired) = self
+			.entries
+			.get_key_value(key)
+			.map(|(stored, slot)| (stored.clone(), slot.is_expired(now)))?;
+		if expired {
+			self.remove(key);
+			self.expired += 1;
+			return None;
+		}
+		let tick = self.next_tick();
+		let slot = self.entries.get_mut(key)?;
+		self.recency.remove(&slot.tick);
+		slot.tick = tick;
+		self.recency.insert(tick, stored);
+		Some(&mut slot.value)
+	}
+
+	/// Insert an entry expiring after `ttl` (never if `None`), replacing any entry under
+	/// `key`; makes room by dropping expired entries, then the least recently used ones
+	pub fn insert(&mut self, key: K, value: V, ttl: Option<Duration>) -> Option<V> {
+		let previous = self.remove(&key);
+		self.purge_expired();
+		if let Some(max) = self.max_entries {
+			while self.entries.len() >= max.max(1) {
+				let Some((_, oldest)) = self.recency.pop_first() else {
+					break;
+				};
+				self.remove(&oldest);
+				self.evicted += 1;
+			}
+		}
+		let tick = self.next_tick();
+		let expires_at = ttl.map(|ttl| Instant::now() + ttl);
+		if let Some(expires_at) = expires_at {
+			self.expiries.insert((expires_at, tick), key.clone());
+		}
+		self.recency.insert(tick, key.clone());
+		self.entries.insert(
+			key,
+			Slot {
+				value,
+				id: tick,
+				tick,
+				expires_at,
+			},
+		);
+		previous
+	}
+
+	/// Make an entry expire `ttl` from now (never if `None`); false if there is no entry
+	pub fn set_ttl<Q: Hash + Eq + ?Sized>(&mut self, key: &Q, ttl: Option<Duration>) -> bool
+	where
+		K: Borrow<Q>,
+	{
+		let Some(stored) = self.entries.get_key_value(key).map(|(k, _)| k.clone()) else {
+			return false;
+		};
+		let slot = self.entries.get_mut(key).expect("looked up above");
+		if let Some(expires_at) = slot.expires_at {
+			self.expiries.remove(&(expires_at, slot.id));
+		}
+		slot.expires_at = ttl.map(|ttl| Instant::now() + ttl);
+		if let Some(expires_at) = slot.expires_at {
+			self.expiries.insert((expires_at, slot.id), stored);
+		}
+		true
+	}
+
+	/// Remove an entry, expired or not
+	pub fn remove<Q: Hash + Eq + ?Sized>(&mut self, key: &Q) -> Option<V>
+	where
+		K: Borrow<Q>,
+	{
+		let slot = self.entries.remove(key)?;
+		self.recency.remove(&slot.tick);
+		if let Some(expires_at) = slot.expires_at {
+			self.expiries.remove(&(expires_at, slot.id));
+		}
+		Some(slot.value)
+	}
+
+	/// Drop every entry whose TTL has passed, returning how many were dropped
+	pub fn purge_expired(&mut self) -> usize {
+		let now = Instant::now();
+		let mut purged = 0;
+		while let Some(entry) = self.expiries.first_entry()
+			&& entry.key().0 <= now
+		{
+			let key = entry.remove();
+			self.remove(&key);
+			purged += 1;
+		}
+		self.expired += purged as u64;
+		purged
+	}
+
+	/// Unexpired entries, in no particular order
+	pub fn iter(&self) -> impl Iterator<Item = (&K, &V)> {
+		let now = Instant::now();
+		self
+			.entries
+			.iter()
+			.filter(move |(_, slot)| !slot.is_expired(now))
+			.map(|(key, slot)| (key, &slot.value))
+	}
+
+	/// Remove every entry; removal counters are kept
+	pub fn clear(&mut self) {
+		self.entries.clear();
+		self.recency.clear();
+		self.expiries.clear();
+	}
+
+	pub fn stats(&self) -> EvictionStats {
+		EvictionStats {
+			entries: self.entries.len(),
+			expired: self.expired,
+			evicted: self.evicted,
+		}
+	}
+
+	fn next_tick(&mut self) -> u64 {
+		self.next_tick += 1;
+		self.next_tick
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_evicts_least_recently_used() {
+		let mut map = BoundedMap::new(Some(2));
+		map.insert("a", 1, None);
+		map.insert("b", 2, None);
+		// Using "a" makes "b" the least recently used
+		assert_eq!(map.get(&"a"), Some(&1));
+		map.insert("c", 3, None);
+
+		assert_eq!(map.peek(&"b"), None);
+		assert_eq!(map.peek(&"a"), Some(&1));
+		assert_eq!(map.peek(&"c"), Some(&3));
+		assert_eq!(
+			map.stats(),
+			EvictionStats {
+				entries: 2,
+				expired: 0,
+				evicted: 1
+			}
+		);
+
+		// Replacing an entry doesn't evict anything
+		map.insert("c", 4, None);
+		assert_eq!(map.stats().evicted, 1);
+		assert_eq!(map.len(), 2);
+	}
+
+	#[test]
+	fn test_expired_entries_are_dropped_before_evicting() {
+		let mut map = BoundedMap::new(Some(2));
+		map.insert("short", 1, Some(Duration::ZERO));
+		map.insert("long", 2, Some(Duration::from_secs(60)));
+		assert_eq!(map.peek(&"short"), None);
+
+		map.insert("new", 3, None);
+		assert_eq!(map.peek(&"long"), Some(&2));
+		assert_eq!(
+			map.stats(),
+			EvictionStats {
+				entries: 2,
+				expired: 1,
+				evicted: 0
+			}
+		);
+
+		map.set_ttl(&"long", Some(Duration::ZERO));
+		assert_eq!(map.get(&"long"), None);
+		assert_eq!(map.purge_expired(), 0);
+		assert_eq!(map.stats().expired, 2);
+		assert_eq!(map.iter().count(), 1);
+	}
+}
@@ -1,7 +1,11 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::time::Duration;
 
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
+use serde_json_path::JsonPath;
+use tracing::{debug, warn};
 
 use super::store::{StateStore, StoreError};
 
@@ -13,7 +17,7 @@ pub enum CacheError {
 	#[error("key derivation error: {0}")]
 	KeyDerivation(String),
 	#[error("inner execution error: {0}")]
-	InnerExecution(String),
+	InnerExecution(InnerFailure),
 	#[error("serialization error: {0}")]
 	Serialization(String),
 }
@@ -38,9 +42,26 @@ pub struct CacheSpec {
 	pub cache_if: Option<CachePredicate>,
 
 	/// Optional stale-while-revalidate duration in seconds.
-	/// If set, stale values can be returned while revalidating in the background.
+	/// If set, stale values are returned immediately while the inner operation
+	/// refreshes the entry in the background.
 	#[serde(skip_serializing_if = "Option::is_none")]
 	pub stale_while_revalidate_seconds: Option<u32>,
+
+	/// Optional predicate that forces revalidation.
+	/// A cached value matching it (e.g. an empty result) is treated as a miss,
+	/// even within its TTL.
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub stale_if: Option<CachePredicate>,
+
+	/// Optional negative caching of failures and empty results.
+	/// Protects backends from repeated identical failing queries, e.g. during
+	/// agent retry storms.
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub negative: Option<NegativeCacheSpec>,
+
+	/// Optional prefix for stored keys, to namespace caches sharing a store.
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub key_prefix: Option<String>,
 }
 
 /// Predicate for conditional caching
@@ -53,6 +74,95 @@ pub struct CachePredicate {
 	pub equals: Value,
 }
 
+/// A failure of the inner operation, as negative caching sees it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InnerFailure {
+	/// Kind of failure, matched against [`NegativeCacheSpec::errors`]
+	pub kind: String,
+	/// Error message
+	pub message: String,
+	/// Whether another attempt may succeed
+	#[serde(default)]
+	pub transient: bool,
+}
+
+impl std::fmt::Display for InnerFailure {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		f.write_str(&self.message)
+	}
+}
+
+/// A bare message is a permanent failure of kind `error`.
+impl From<String> for InnerFailure {
+	fn from(message: String) -> Self {
+		Self {
+			kind: "error".to_string(),
+			message,
+			transient: false,
+		}
+	}
+}
+
+/// Specification for negative caching.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NegativeCacheSpec {
+	/// Time-to-live in seconds for negative entries, usually much shorter
+	/// than the TTL for successful results.
+	pub ttl_seconds: u32,
+
+	/// Kinds of failure to cache, matched exactly.
+	/// If empty, every failure that isn't transient is cached.
+	#[serde(default, skip_serializing_if = "Vec::is_empty")]
+	pub errors: Vec<String>,
+
+	/// Whether empty results (null, "", [] or {}) are cached with the negative TTL.
+	#[serde(default)]
+	pub empty_results: bool,
+}
+
+impl NegativeCacheSpec {
+	/// Create a new NegativeCacheSpec caching permanent failures for the given TTL.
+	pub fn new(ttl_seconds: u32) -> Self {
+		Self {
+			ttl_seconds,
+			errors: Vec::new(),
+			empty_results: false,
+		}
+	}
+
+	/// Only cache failures of these kinds, transient or not.
+	pub fn with_errors(mut self, errors: Vec<String>) -> Self {
+		self.errors = errors;
+		self
+	}
+
+	/// Also cache empty results.
+	pub fn with_empty_results(mut self) -> Self {
+		self.empty_results = true;
+		self
+	}
+
+	fn caches_failure(&self, failure: &InnerFailure) -> bool {
+		if self.errors.is_empty() {
+			return !failure.transient;
+		}
+		self.errors.contains(&failure.kind)
+	}
+}
+
+/// Whether a result counts as empty for negative caching.
+fn is_empty_result(value: &Value) -> bool {
+	match value {
+		Value::Null => true,
+		Value::String(s) => s.is_empty(),
+		Value::Array(a) => a.is_empty(),
+		Value::Object(o) => o.is_empty(),
+		_ => false,
+	}
+}
+
 /// Cache entry metadata stored alongside the value
 #[derive(Debug, Clone, Serialize, Deserialize)]
 struct CacheEntry {
@@ -62,6 +172,27 @@ struct CacheEntry {
 	created_at: u64,
 	/// Original TTL in seconds (for SWR calculations)
 	ttl_seconds: u32,
+	/// The failure, for negatively cached failures
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	error: Option<InnerFailure>,
+}
+
+impl CacheEntry {
+	fn new(value: Value, ttl_seconds: u32) -> Self {
+		Self {
+			value,
+			created_at: now_millis(),
+			ttl_seconds,
+			error: None,
+		}
+	}
+
+	fn into_result(self) -> Result<Value, CacheError> {
+		match self.error {
+			Some(failure) => Err(CacheError::InnerExecution(failure)),
+			None => Ok(self.value),
+		}
+	}
 }
 
 impl CacheSpec {
@@ -72,6 +203,9 @@ impl CacheSpec {
 			ttl_seconds,
 			cache_if: None,
 			stale_while_revalidate_seconds: None,
+			stale_if: None,
+			negative: None,
+			key_prefix: None,
 		}
 	}
 
@@ -86,6 +220,66 @@ impl CacheSpec {
 		self.stale_while_revalidate_seconds = Some(seconds);
 		self
 	}
+
+	/// Set a predicate that forces revalidation of matching cached values.
+	pub fn with_stale_if(mut self, field: String, equals: Value) -> Self {
+		self.stale_if = Some(CachePredicate { field, equals });
+		self
+	}
+
+	/// Enable negative caching.
+	pub fn with_negative_cache(mut self, negative: NegativeCacheSpec) -> Self {
+		self.negative = Some(negative);
+		self
+	}
+
+	/// Prefix stored keys, e.g. with the name of the owning composition.
+	pub fn with_key_prefix(mut self, prefix: String) -> Self {
+		self.key_prefix = Some(prefix);
+		self
+	}
+
+	/// How long entries are kept in the store, including the SWR window.
+	fn storage_ttl_seconds(&self) -> u32 {
+		self
+			.stale_while_revalidate_seconds
+			.map(|swr| self.ttl_seconds + swr)
+			.unwrap_or(self.ttl_seconds)
+	}
+}
+
+/// Hit and miss counters for a cache.
+///
+/// Negatively cached failures count as hits; values forced stale by `stale_if`
+/// count as misses.
+#[derive(Debug, Default)]
+pub struct CacheStats {
+	hits: AtomicU64,
+	stale_hits: AtomicU64,
+	misses: AtomicU64,
+}
+
+/// Point-in-time copy of [`CacheStats`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CacheStatsSnapshot {
+	/// Lookups answered from a fresh entry
+	pub hits: u64,
+	/// Lookups answered from a stale entry while it revalidated
+	pub stale_hits: u64,
+	/// Lookups that ran the inner operation
+	pub misses: u64,
+}
+
+impl CacheStats {
+	/// Copy the current counter values.
+	pub fn snapshot(&self) -> CacheStatsSnapshot {
+		CacheStatsSnapshot {
+			hits: self.hits.load(Ordering::Relaxed),
+			stale_hits: self.stale_hits.load(Ordering::Relaxed),
+			misses: self.misses.load(Ordering::Relaxed),
+		}
+	}
 }
 
 /// Derive a cache key from the input using the specified key paths.
@@ -110,8 +304,14 @@ pub fn derive_cache_key(key_paths: &[String], input: &Value) -> Result<String, C
 	Ok(parts.join(":"))
 }
 
-/// Get a value from a JSON object using a dot-separated path.
+/// Get a value from a JSON object using a path.
+///
+/// A path starting with `$` is a JSONPath expression and yields its first match; any other
+/// path is dot-separated, with array indices as segments (`items.0.id`).
 fn get_json_path<'a>(value: &'a Value, path: &str) -> Option<&'a Value> {
+	if path.starts_with('$') {
+		return JsonPath::parse(path).ok()?.query(value).first();
+	}
 	let mut current = value;
 
 	for segment in path.split('.') {
@@ -131,13 +331,34 @@ fn get_json_path<'a>(value: &'a Value, path: &str) -> Option<&'a Value> {
 }
 
 /// Evaluate a cache predicate against a result value.
+///
+/// Numbers compare by value, so `0` equals `0.0`.
 pub fn evaluate_predicate(predicate: &CachePredicate, result: &Value) -> bool {
 	match get_json_path(result, &predicate.field) {
+		Some(Value::Number(n)) if predicate.equals.is_number() => {
+			n.as_f64() == predicate.equals.as_f64()
+		},
 		Some(value) => value == &predicate.equals,
 		None => false,
 	}
 }
 
+/// Suffix of the marker key held while an entry is revalidating in the background.
+const REVALIDATING_SUFFIX: &str = "#revalidating";
+
+/// Whether `key` is the marker of an entry revalidating in the background, rather than
+/// a cached entry.
+pub fn is_revalidation_marker(key: &str) -> bool {
+	key.ends_with(REVALIDATING_SUFFIX)
+}
+
+fn now_millis() -> u64 {
+	std::time::SystemTime::now()
+		.duration_since(std::time::UNIX_EPOCH)
+		.unwrap()
+		.as_millis() as u64
+}
+
 /// The cache executor handles cache lookup, miss handling, and storage.
 pub struct CacheExecutor;
 
@@ -148,7 +369,16 @@ impl CacheExecutor {
 	/// 1. Derives a cache key from the input
 	/// 2. Checks if a cached value exists
 	/// 3. If cache hit: returns the cached value
-	/// 4. If cache miss: executes the inner operation and caches the result
+	/// 4. If stale but within the stale-while-revalidate window: returns the stale
+	///    value and runs the inner operation in the background to refresh the entry
+	/// 5. If cache miss, or the cached value matches `stale_if`: executes the inner
+	///    operation and caches the result
+	///
+	/// With negative caching, matching failures are cached too and replayed as
+	/// the same [`InnerFailure`] until their (shorter) TTL expires; they are never
+	/// served stale.
+	///
+	/// The inner future is only polled when it is needed, so a fresh hit never runs it.
 	///
 	/// # Arguments
 	/// * `spec` - The cache specification
@@ -158,84 +388,203 @@ impl CacheExecutor {
 	///
 	/// # Type Parameters
 	/// * `F` - The future type for the inner execution
-	/// * `E` - The error type for the inner execution (must convert to CacheError)
+	/// * `E` - The error type for the inner execution (must convert to InnerFailure)
 	pub async fn execute<F, E>(
 		spec: &CacheSpec,
 		input: Value,
-		store: &dyn StateStore,
+		store: Arc<dyn StateStore>,
+		execute_inner: F,
+	) -> Result<Value, CacheError>
+	where
+		F: std::future::Future<Output = Result<Value, E>> + Send + 'static,
+		E: Into<InnerFailure> + Send + 'static,
+	{
+		Self::execute_with_stats(spec, input, store, &CacheStats::default(), execute_inner).await
+	}
+
+	/// Execute a cache-wrapped operation, counting hits and misses in `stats`.
+	///
+	/// See [`CacheExecutor::execute`].
+	pub async fn execute_with_stats<F, E>(
+		spec: &CacheSpec,
+		input: Value,
+		store: Arc<dyn StateStore>,
+		stats: &CacheStats,
 		execute_inner: F,
 	) -> Result<Value, CacheError>
 	where
-		F: std::future::Future<Output = Result<Value, E>>,
-		E: std::fmt::Display,
+		F: std::future::Future<Output = Result<Value, E>> + Send + 'static,
+		E: Into<InnerFailure> + Send + 'static,
 	{
 		let key = derive_cache_key(&spec.key_paths, &input)?;
+		let key = match &spec.key_prefix {
+			Some(prefix) => format!("{prefix}{key}"),
+			None => key,
+		};
 
 		// Check cache
 		if let Some(cached_bytes) = store.get(&key).await? {
 			let entry: CacheEntry = serde_json::from_slice(&cached_bytes)
 				.map_err(|e| CacheError::Serialization(e.to_string()))?;
 
-			let now = std::time::SystemTime::now()
-				.duration_since(std::time::UNIX_EPOCH)
-				.unwrap()
-				.as_millis() as u64;
-
-			let age_seconds = (now - entry.created_at) / 1000;
-			let is_stale = age_seconds > entry.ttl_seconds as u64;
-
-			// If within TTL, return cached value
-			if !is_stale {
-				return Ok(entry.value);
-			}
-
-			// Check stale-while-revalidate window
-			if let Some(swr_seconds) = spec.stale_while_revalidate_seconds {
+			let forced = spec
+				.stale_if
+				.as_ref()
+				.is_some_and(|p| evaluate_predicate(p, &entry.value));
+			let age_millis = now_millis().saturating_sub(entry.created_at);
+			let is_stale = age_millis > entry.ttl_seconds as u64 * 1000;
+
+			if forced {
+				debug!(key = %key, "cached value matches staleIf, revalidating");
+			} else if !is_stale {
+				// If within TTL, return cached value (or cached failure)
+				stats.hits.fetch_add(1, Ordering::Relaxed);
+				return entry.into_result();
+			} else if let (Some(swr_seconds), None) = (spec.stale_while_revalidate_seconds, &entry.error)
+			{
+				// Check stale-while-revalidate window
 				let swr_window = entry.ttl_seconds as u64 + swr_seconds as u64;
-				if age_seconds <= swr_window {
-					// Return stale value, but we could trigger background revalidation here
-					// For simplicity, we just return the stale value
+				if age_millis <= swr_window * 1000 {
+					Self::revalidate_in_background(spec, key, entry.value.clone(), store, execute_inner)
+						.await?;
+					stats.stale_hits.fetch_add(1, Ordering::Relaxed);
 					return Ok(entry.value);
 				}
 			}
 		}
 
 		// Cache miss - execute inner operation
-		let result = execute_inner
-			.await
-			.map_err(|e| CacheError::InnerExecution(e.to_string()))?;
+		stats.misses.fetch_add(1, Ordering::Relaxed);
+		let result = match execute_inner.await {
+			Ok(result) => result,
+			Err(e) => {
+				let failure = e.into();
+				Self::store_failure(spec, &key, &failure, store.as_ref()).await?;
+				return Err(CacheError::InnerExecution(failure));
+			},
+		};
+
+		Self::store_result(spec, &key, &result, store.as_ref()).await?;
+
+		Ok(result)
+	}
+
+	/// Refresh a stale entry without blocking the caller.
+	///
+	/// A marker key makes sure only one refresh per entry is in flight; other requests
+	/// keep getting the stale value meanwhile. A failed refresh leaves the stale entry
+	/// in place until it expires.
+	async fn revalidate_in_background<F, E>(
+		spec: &CacheSpec,
+		key: String,
+		stale: Value,
+		store: Arc<dyn StateStore>,
+		execute_inner: F,
+	) -> Result<(), CacheError>
+	where
+		F: std::future::Future<Output = Result<Value, E>> + Send + 'static,
+		E: Into<InnerFailure> + Send + 'static,
+	{
+		let marker = format!("{key}{REVALIDATING_SUFFIX}");
+		let claimed = store
+			.set_if_absent(
+				&marker,
+				Vec::new(),
+				Some(Duration::from_secs(spec.storage_ttl_seconds() as u64)),
+			)
+			.await?;
+		if !claimed {
+			return Ok(());
+		}
+
+		let spec = spec.clone();
+		tokio::spawn(async move {
+			// Errors are only logged, since nobody awaits the task
+			match execute_inner.await {
+				Ok(value) => {
+					if value == stale {
+						debug!(key = %key, "revalidated cache entry is unchanged");
+					} else {
+						debug!(key = %key, "revalidated cache entry changed");
+					}
+					if let Err(e) = Self::store_result(&spec, &key, &value, store.as_ref()).await {
+						warn!(key = %key, error = %e, "failed to store revalidated cache entry");
+					}
+				},
+				Err(e) => {
+					let failure: InnerFailure = e.into();
+					warn!(key = %key, error = %failure, "cache revalidation failed");
+				},
+			}
+			if let Err(e) = store.delete(&marker).await {
+				warn!(key = %key, error = %e, "failed to clear cache revalidation marker");
+			}
+		});
+		Ok(())
+	}
+
+	/// Store a result, unless `cache_if` rejects it.
+	///
+	/// Empty results are stored with the negative TTL when negative caching covers them.
+	async fn store_result(
+		spec: &CacheSpec,
+		key: &str,
+		result: &Value,
+		store: &dyn StateStore,
+	) -> Result<(), CacheError> {
+		if let Some(negative) = spec.negative.as_ref().filter(|n| n.empty_results)
+			&& is_empty_result(result)
+		{
+			let entry = CacheEntry::new(result.clone(), negative.ttl_seconds);
+			return Self::store_entry(key, &entry, negative.ttl_seconds, store).await;
+		}
 
 		// Check if we should cache the result
 		let should_cache = spec
 			.cache_if
 			.as_ref()
-			.map_or(true, |p| evaluate_predicate(p, &result));
-
-		if should_cache {
-			let entry = CacheEntry {
-				value: result.clone(),
-				created_at: std::time::SystemTime::now()
-					.duration_since(std::time::UNIX_EPOCH)
-					.unwrap()
-					.as_millis() as u64,
-				ttl_seconds: spec.ttl_seconds,
-			};
-
-			let bytes =
-				serde_json::to_vec(&entry).map_err(|e| CacheError::Serialization(e.to_string()))?;
-
-			// Use a longer storage TTL to support SWR
-			let storage_ttl = spec
-				.stale_while_revalidate_seconds
-				.map(|swr| spec.ttl_seconds + swr)
-				.unwrap_or(spec.ttl_seconds);
-
-			store
-				.set(&key, bytes, Some(Duration::from_secs(storage_ttl as u64)))
-				.await?;
+			.is_none_or(|p| evaluate_predicate(p, result));
+		if !should_cache {
+			return Ok(());
 		}
 
-		Ok(result)
+		// Use a longer storage TTL to support SWR
+		let entry = CacheEntry::new(result.clone(), spec.ttl_seconds);
+		Self::store_entry(key, &entry, spec.storage_ttl_seconds(), store).await
+	}
+
+	/// Store a failure, if negative caching covers it.
+	async fn store_failure(
+		spec: &CacheSpec,
+		key: &str,
+		failure: &InnerFailure,
+		store: &dyn StateStore,
+	) -> Result<(), CacheError> {
+		let Some(negative) = spec.negative.as_ref().filter(|n| n.caches_failure(failure)) else {
+			return Ok(());
+		};
+		let entry = CacheEntry {
+			error: Some(failure.clone()),
+			..CacheEntry::new(Value::Null, negative.ttl_seconds)
+		};
+		Self::store_entry(key, &entry, negative.ttl_seconds, store).await
+	}
+
+	async fn store_entry(
+		key: &str,
+		entry: &CacheEntry,
+		storage_ttl_seconds: u32,
+		store: &dyn StateStore,
+	) -> Result<(), CacheError> {
+		let bytes = serde_json::to_vec(entry).map_err(|e| CacheError::Serialization(e.to_string()))?;
+		store
+			.set(
+				key,
+				bytes,
+				Some(Duration::from_secs(storage_ttl_seconds as u64)),
+			)
+			.await?;
+		Ok(())
 	}
 }
 
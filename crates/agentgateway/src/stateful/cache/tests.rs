@@ -5,7 +5,9 @@
 //! - TTL expiration
 //! - Key derivation from multiple paths
 //! - Conditional caching with predicates
-//! - Stale-while-revalidate behavior
+//! - Stale-while-revalidate behavior with background refresh
+//! - Forced revalidation with staleIf
+//! - Negative caching of failures and empty results
 
 use std::sync::Arc;
 use std::sync::atomic::{AtomicU32, Ordering};
@@ -34,7 +36,7 @@ fn counting_inner(
 
 #[tokio::test]
 async fn test_cache_miss_then_hit() {
-	let store = MemoryStore::new();
+	let store = Arc::new(MemoryStore::new());
 	let call_count = Arc::new(AtomicU32::new(0));
 
 	let spec = CacheSpec::new(vec!["user_id".to_string()], 60);
@@ -45,7 +47,7 @@ async fn test_cache_miss_then_hit() {
 	let result = CacheExecutor::execute(
 		&spec,
 		input.clone(),
-		&store,
+		store.clone(),
 		counting_inner(call_count.clone(), expected_result.clone()),
 	)
 	.await
@@ -58,7 +60,7 @@ async fn test_cache_miss_then_hit() {
 	let result = CacheExecutor::execute(
 		&spec,
 		input.clone(),
-		&store,
+		store.clone(),
 		counting_inner(call_count.clone(), json!({"data": "different"})),
 	)
 	.await
@@ -72,7 +74,7 @@ async fn test_cache_miss_then_hit() {
 
 #[tokio::test]
 async fn test_cache_ttl_expiry() {
-	let store = MemoryStore::new();
+	let store = Arc::new(MemoryStore::new());
 	let call_count = Arc::new(AtomicU32::new(0));
 
 	// Very short TTL for testing
@@ -85,7 +87,7 @@ async fn test_cache_ttl_expiry() {
 	let result = CacheExecutor::execute(
 		&spec,
 		input.clone(),
-		&store,
+		store.clone(),
 		counting_inner(call_count.clone(), first_result.clone()),
 	)
 	.await
@@ -101,7 +103,7 @@ async fn test_cache_ttl_expiry() {
 	let result = CacheExecutor::execute(
 		&spec,
 		input.clone(),
-		&store,
+		store.clone(),
 		counting_inner(call_count.clone(), second_result.clone()),
 	)
 	.await
@@ -113,7 +115,7 @@ async fn test_cache_ttl_expiry() {
 
 #[tokio::test]
 async fn test_cache_key_derivation() {
-	let store = MemoryStore::new();
+	let store = Arc::new(MemoryStore::new());
 
 	// Multiple key paths
 	let spec = CacheSpec::new(vec!["user.id".to_string(), "action".to_string()], 60);
@@ -133,9 +135,14 @@ async fn test_cache_key_derivation() {
 
 	// Cache result for user1:read
 	let result1 = json!({"cached": "user1-read"});
-	CacheExecutor::execute(&spec, input1.clone(), &store, simple_inner(result1.clone()))
-		.await
-		.unwrap();
+	CacheExecutor::execute(
+		&spec,
+		input1.clone(),
+		store.clone(),
+		simple_inner(result1.clone()),
+	)
+	.await
+	.unwrap();
 
 	// Different action should miss cache
 	let result2 = json!({"cached": "user1-write"});
@@ -143,7 +150,7 @@ async fn test_cache_key_derivation() {
 	CacheExecutor::execute(
 		&spec,
 		input2.clone(),
-		&store,
+		store.clone(),
 		counting_inner(call_count.clone(), result2.clone()),
 	)
 	.await
@@ -156,7 +163,7 @@ async fn test_cache_key_derivation() {
 	CacheExecutor::execute(
 		&spec,
 		input3.clone(),
-		&store,
+		store.clone(),
 		counting_inner(call_count.clone(), result3.clone()),
 	)
 	.await
@@ -168,7 +175,7 @@ async fn test_cache_key_derivation() {
 	let cached = CacheExecutor::execute(
 		&spec,
 		input1.clone(),
-		&store,
+		store.clone(),
 		counting_inner(call_count.clone(), json!({"different": true})),
 	)
 	.await
@@ -179,7 +186,7 @@ async fn test_cache_key_derivation() {
 
 #[tokio::test]
 async fn test_cache_conditional() {
-	let store = MemoryStore::new();
+	let store = Arc::new(MemoryStore::new());
 
 	// Only cache if status is "success"
 	let spec = CacheSpec::new(vec!["id".to_string()], 60)
@@ -193,7 +200,7 @@ async fn test_cache_conditional() {
 	CacheExecutor::execute(
 		&spec,
 		input.clone(),
-		&store,
+		store.clone(),
 		counting_inner(call_count.clone(), error_result.clone()),
 	)
 	.await
@@ -206,7 +213,7 @@ async fn test_cache_conditional() {
 	let result = CacheExecutor::execute(
 		&spec,
 		input.clone(),
-		&store,
+		store.clone(),
 		counting_inner(call_count.clone(), success_result.clone()),
 	)
 	.await
@@ -219,7 +226,7 @@ async fn test_cache_conditional() {
 	let result = CacheExecutor::execute(
 		&spec,
 		input.clone(),
-		&store,
+		store.clone(),
 		counting_inner(call_count.clone(), json!({"different": true})),
 	)
 	.await
@@ -230,7 +237,7 @@ async fn test_cache_conditional() {
 
 #[tokio::test]
 async fn test_cache_stale_while_revalidate() {
-	let store = MemoryStore::new();
+	let store = Arc::new(MemoryStore::new());
 
 	// 1 second TTL with 2 second SWR window
 	let spec = CacheSpec::new(vec!["id".to_string()], 1).with_stale_while_revalidate(2);
@@ -243,7 +250,7 @@ async fn test_cache_stale_while_revalidate() {
 	let result = CacheExecutor::execute(
 		&spec,
 		input.clone(),
-		&store,
+		store.clone(),
 		counting_inner(call_count.clone(), original_result.clone()),
 	)
 	.await
@@ -254,21 +261,36 @@ async fn test_cache_stale_while_revalidate() {
 	// Wait for TTL to expire but stay within SWR window
 	tokio::time::sleep(Duration::from_millis(1500)).await;
 
-	// Should return stale value without calling inner
+	// Should return stale value immediately and refresh in the background
+	let refreshed_result = json!({"version": "refreshed"});
 	let call_count = Arc::new(AtomicU32::new(0));
 	let result = CacheExecutor::execute(
 		&spec,
 		input.clone(),
-		&store,
-		counting_inner(call_count.clone(), json!({"version": "new"})),
+		store.clone(),
+		counting_inner(call_count.clone(), refreshed_result.clone()),
 	)
 	.await
 	.unwrap();
 	assert_eq!(result, original_result); // Still returns stale value
-	assert_eq!(call_count.load(Ordering::SeqCst), 0); // No call made (in basic SWR)
+	tokio::time::sleep(Duration::from_millis(100)).await;
+	assert_eq!(call_count.load(Ordering::SeqCst), 1); // Refreshed in the background
 
-	// Wait for SWR window to also expire
-	tokio::time::sleep(Duration::from_secs(2)).await;
+	// Refreshed entry is fresh again
+	let call_count = Arc::new(AtomicU32::new(0));
+	let result = CacheExecutor::execute(
+		&spec,
+		input.clone(),
+		store.clone(),
+		counting_inner(call_count.clone(), json!({"version": "new"})),
+	)
+	.await
+	.unwrap();
+	assert_eq!(result, refreshed_result);
+	assert_eq!(call_count.load(Ordering::SeqCst), 0);
+
+	// Wait for TTL and SWR window to expire
+	tokio::time::sleep(Duration::from_millis(3500)).await;
 
 	// Now should call inner and get new value
 	let new_result = json!({"version": "new"});
@@ -276,7 +298,7 @@ async fn test_cache_stale_while_revalidate() {
 	let result = CacheExecutor::execute(
 		&spec,
 		input.clone(),
-		&store,
+		store.clone(),
 		counting_inner(call_count.clone(), new_result.clone()),
 	)
 	.await
@@ -285,6 +307,100 @@ async fn test_cache_stale_while_revalidate() {
 	assert_eq!(call_count.load(Ordering::SeqCst), 1);
 }
 
+#[tokio::test]
+async fn test_cache_stale_while_revalidate_single_refresh() {
+	let store = Arc::new(MemoryStore::new());
+	let spec = CacheSpec::new(vec!["id".to_string()], 1).with_stale_while_revalidate(5);
+	let input = json!({"id": "swr-single"});
+
+	CacheExecutor::execute(
+		&spec,
+		input.clone(),
+		store.clone(),
+		simple_inner(json!({"version": "original"})),
+	)
+	.await
+	.unwrap();
+	tokio::time::sleep(Duration::from_millis(1500)).await;
+
+	// Concurrent stale hits start only one refresh
+	let call_count = Arc::new(AtomicU32::new(0));
+	let slow_inner = |counter: Arc<AtomicU32>| async move {
+		counter.fetch_add(1, Ordering::SeqCst);
+		tokio::time::sleep(Duration::from_millis(200)).await;
+		Ok::<_, String>(json!({"version": "refreshed"}))
+	};
+	for _ in 0..3 {
+		let result = CacheExecutor::execute(
+			&spec,
+			input.clone(),
+			store.clone(),
+			slow_inner(call_count.clone()),
+		)
+		.await
+		.unwrap();
+		assert_eq!(result, json!({"version": "original"}));
+	}
+	tokio::time::sleep(Duration::from_millis(400)).await;
+	assert_eq!(call_count.load(Ordering::SeqCst), 1);
+
+	let result = CacheExecutor::execute(
+		&spec,
+		input.clone(),
+		store.clone(),
+		simple_inner(json!({"version": "new"})),
+	)
+	.await
+	.unwrap();
+	assert_eq!(result, json!({"version": "refreshed"}));
+}
+
+#[tokio::test]
+async fn test_cache_stale_if() {
+	let store = Arc::new(MemoryStore::new());
+
+	// Empty results are cached but always revalidated
+	let spec = CacheSpec::new(vec!["q".to_string()], 60).with_stale_if("count".to_string(), json!(0));
+	let input = json!({"q": "rust"});
+
+	let empty_result = json!({"count": 0, "items": []});
+	CacheExecutor::execute(
+		&spec,
+		input.clone(),
+		store.clone(),
+		simple_inner(empty_result.clone()),
+	)
+	.await
+	.unwrap();
+
+	// Cached empty result matches staleIf - inner runs inline, even within TTL
+	let full_result = json!({"count": 1, "items": ["axum"]});
+	let call_count = Arc::new(AtomicU32::new(0));
+	let result = CacheExecutor::execute(
+		&spec,
+		input.clone(),
+		store.clone(),
+		counting_inner(call_count.clone(), full_result.clone()),
+	)
+	.await
+	.unwrap();
+	assert_eq!(result, full_result);
+	assert_eq!(call_count.load(Ordering::SeqCst), 1);
+
+	// Non-matching value is served from cache
+	let call_count = Arc::new(AtomicU32::new(0));
+	let result = CacheExecutor::execute(
+		&spec,
+		input.clone(),
+		store.clone(),
+		counting_inner(call_count.clone(), empty_result.clone()),
+	)
+	.await
+	.unwrap();
+	assert_eq!(result, full_result);
+	assert_eq!(call_count.load(Ordering::SeqCst), 0);
+}
+
 #[tokio::test]
 async fn test_derive_cache_key_simple() {
 	let input = json!({"user": "alice", "action": "login"});
@@ -328,6 +444,32 @@ async fn test_derive_cache_key_missing_path() {
 	assert!(matches!(result.unwrap_err(), CacheError::KeyDerivation(_)));
 }
 
+#[tokio::test]
+async fn test_derive_cache_key_jsonpath() {
+	let input = json!({
+		"query": "rust",
+		"filters": {"lang.name": "en"},
+		"items": [{"id": 7}, {"id": 8}]
+	});
+
+	let key = derive_cache_key(
+		&[
+			"$.query".to_string(),
+			"$.filters['lang.name']".to_string(),
+			"$.items[1].id".to_string(),
+		],
+		&input,
+	)
+	.unwrap();
+	assert_eq!(key, "rust:en:8");
+
+	// A wildcard contributes its first match
+	let key = derive_cache_key(&["$.items[*].id".to_string()], &input).unwrap();
+	assert_eq!(key, "7");
+
+	assert!(derive_cache_key(&["$.missing".to_string()], &input).is_err());
+}
+
 #[tokio::test]
 async fn test_evaluate_predicate_simple() {
 	let result = json!({"status": "success", "code": 200});
@@ -376,23 +518,28 @@ async fn test_evaluate_predicate_nested() {
 
 #[tokio::test]
 async fn test_cache_with_array_key() {
-	let store = MemoryStore::new();
+	let store = Arc::new(MemoryStore::new());
 	let spec = CacheSpec::new(vec!["ids".to_string()], 60);
 
 	let input = json!({"ids": [1, 2, 3]});
 	let result = json!({"sum": 6});
 
 	// Cache with array key
-	CacheExecutor::execute(&spec, input.clone(), &store, simple_inner(result.clone()))
-		.await
-		.unwrap();
+	CacheExecutor::execute(
+		&spec,
+		input.clone(),
+		store.clone(),
+		simple_inner(result.clone()),
+	)
+	.await
+	.unwrap();
 
 	// Same array should hit cache
 	let call_count = Arc::new(AtomicU32::new(0));
 	let cached = CacheExecutor::execute(
 		&spec,
 		input.clone(),
-		&store,
+		store.clone(),
 		counting_inner(call_count.clone(), json!({"sum": 999})),
 	)
 	.await
@@ -406,10 +553,150 @@ async fn test_cache_with_array_key() {
 	CacheExecutor::execute(
 		&spec,
 		different_input,
-		&store,
+		store.clone(),
 		counting_inner(call_count.clone(), json!({"sum": 7})),
 	)
 	.await
 	.unwrap();
 	assert_eq!(call_count.load(Ordering::SeqCst), 1);
 }
+
+/// Helper to create an inner execution that fails and tracks call count
+fn failing_inner(
+	counter: Arc<AtomicU32>,
+	kind: &str,
+	transient: bool,
+) -> impl std::future::Future<Output = Result<Value, InnerFailure>> + use<> {
+	let failure = InnerFailure {
+		kind: kind.to_string(),
+		message: format!("{kind} failure"),
+		transient,
+	};
+	async move {
+		counter.fetch_add(1, Ordering::SeqCst);
+		Err(failure)
+	}
+}
+
+#[tokio::test]
+async fn test_negative_cache_errors() {
+	let store = Arc::new(MemoryStore::new());
+	let spec = CacheSpec::new(vec!["q".to_string()], 60)
+		.with_negative_cache(NegativeCacheSpec::new(1).with_errors(vec!["not_found".to_string()]));
+
+	// A listed kind is cached, even if transient, and replayed unchanged without calling inner
+	let input = json!({"q": "missing"});
+	let call_count = Arc::new(AtomicU32::new(0));
+	for _ in 0..3 {
+		let result = CacheExecutor::execute(
+			&spec,
+			input.clone(),
+			store.clone(),
+			failing_inner(call_count.clone(), "not_found", true),
+		)
+		.await;
+		assert!(matches!(result, Err(CacheError::InnerExecution(ref f))
+			if f.kind == "not_found" && f.message == "not_found failure" && f.transient));
+	}
+	assert_eq!(call_count.load(Ordering::SeqCst), 1);
+
+	// Other kinds are not cached, even if their message mentions a listed one
+	let input = json!({"q": "flaky"});
+	let call_count = Arc::new(AtomicU32::new(0));
+	for _ in 0..2 {
+		let result = CacheExecutor::execute(
+			&spec,
+			input.clone(),
+			store.clone(),
+			failing_inner(call_count.clone(), "not_found_upstream", false),
+		)
+		.await;
+		assert!(result.is_err());
+	}
+	assert_eq!(call_count.load(Ordering::SeqCst), 2);
+
+	// Negative entry expires with its own, shorter TTL
+	tokio::time::sleep(Duration::from_millis(1100)).await;
+	let expected = json!({"found": true});
+	let result = CacheExecutor::execute(
+		&spec,
+		json!({"q": "missing"}),
+		store.clone(),
+		simple_inner(expected.clone()),
+	)
+	.await
+	.unwrap();
+	assert_eq!(result, expected);
+}
+
+#[tokio::test]
+async fn test_negative_cache_skips_transient_failures_by_default() {
+	let store = Arc::new(MemoryStore::new());
+	let spec =
+		CacheSpec::new(vec!["q".to_string()], 60).with_negative_cache(NegativeCacheSpec::new(60));
+
+	let call_count = Arc::new(AtomicU32::new(0));
+	for _ in 0..2 {
+		let _ = CacheExecutor::execute(
+			&spec,
+			json!({"q": "timeout"}),
+			store.clone(),
+			failing_inner(call_count.clone(), "timeout", true),
+		)
+		.await;
+	}
+	assert_eq!(call_count.load(Ordering::SeqCst), 2);
+
+	let call_count = Arc::new(AtomicU32::new(0));
+	for _ in 0..2 {
+		let _ = CacheExecutor::execute(
+			&spec,
+			json!({"q": "bad"}),
+			store.clone(),
+			failing_inner(call_count.clone(), "invalid_input", false),
+		)
+		.await;
+	}
+	assert_eq!(call_count.load(Ordering::SeqCst), 1);
+}
+
+#[tokio::test]
+async fn test_negative_cache_empty_results() {
+	let store = Arc::new(MemoryStore::new());
+
+	// Empty results would be rejected by cacheIf, but are cached briefly
+	let spec = CacheSpec::new(vec!["q".to_string()], 60)
+		.with_cache_if("0".to_string(), json!("hit"))
+		.with_negative_cache(NegativeCacheSpec::new(1).with_empty_results());
+	let input = json!({"q": "nothing"});
+
+	CacheExecutor::execute(&spec, input.clone(), store.clone(), simple_inner(json!([])))
+		.await
+		.unwrap();
+
+	let call_count = Arc::new(AtomicU32::new(0));
+	let result = CacheExecutor::execute(
+		&spec,
+		input.clone(),
+		store.clone(),
+		counting_inner(call_count.clone(), json!(["hit"])),
+	)
+	.await
+	.unwrap();
+	assert_eq!(result, json!([]));
+	assert_eq!(call_count.load(Ordering::SeqCst), 0);
+
+	// Expires after the negative TTL
+	tokio::time::sleep(Duration::from_millis(1100)).await;
+	let call_count = Arc::new(AtomicU32::new(0));
+	let result = CacheExecutor::execute(
+		&spec,
+		input.clone(),
+		store.clone(),
+		counting_inner(call_count.clone(), json!(["hit"])),
+	)
+	.await
+	.unwrap();
+	assert_eq!(result, json!(["hit"]));
+	assert_eq!(call_count.load(Ordering::SeqCst), 1);
+}
@@ -1,46 +1,33 @@
-//! In-memory implementation of StateStore for testing.
+//! In-memory implementation of StateStore.
 
-use std::collections::HashMap;
 use std::sync::Mutex;
 use std::time::{Duration, Instant};
 
 use async_trait::async_trait;
 
+use super::bounded::{BoundedMap, EvictionStats};
 use super::store::{StateStore, StoreError};
 
-/// Entry in the memory store with optional expiration
-struct MemoryEntry {
-	value: Vec<u8>,
-	expires_at: Option<Instant>,
-}
-
-impl MemoryEntry {
-	fn new(value: Vec<u8>, ttl: Option<Duration>) -> Self {
-		Self {
-			value,
-			expires_at: ttl.map(|d| Instant::now() + d),
-		}
-	}
-
-	fn is_expired(&self) -> bool {
-		self.expires_at.map_or(false, |exp| Instant::now() > exp)
-	}
-}
-
 /// In-memory implementation of StateStore.
 ///
 /// This implementation is suitable for testing and single-instance deployments.
 /// For production use with multiple instances, use a distributed store like Redis.
-#[derive(Default)]
+#[derive(Debug, Default)]
 pub struct MemoryStore {
-	data: Mutex<HashMap<String, MemoryEntry>>,
+	data: Mutex<BoundedMap<String, Vec<u8>>>,
 }
 
 impl MemoryStore {
 	/// Create a new empty memory store.
 	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Create a store holding at most `max_entries` entries, evicting the least recently used
+	/// once full.
+	pub fn with_max_entries(max_entries: usize) -> Self {
 		Self {
-			data: Mutex::new(HashMap::new()),
+			data: Mutex::new(BoundedMap::new(Some(max_entries))),
 		}
 	}
 
@@ -58,28 +45,28 @@ impl MemoryStore {
 	pub fn is_empty(&self) -> bool {
 		self.data.lock().unwrap().is_empty()
 	}
+
+	/// Drop expired entries, returning how many were dropped.
+	pub fn purge_expired(&self) -> usize {
+		self.data.lock().unwrap().purge_expired()
+	}
+
+	/// Entry count and how many entries expired or were evicted so far.
+	pub fn eviction_stats(&self) -> EvictionStats {
+		self.data.lock().unwrap().stats()
+	}
 }
 
 #[async_trait]
 impl StateStore for MemoryStore {
 	async fn get(&self, key: &str) -> Result<Option<Vec<u8>>, StoreError> {
 		let mut data = self.data.lock().unwrap();
-
-		// Check if key exists and isn't expired
-		if let Some(entry) = data.get(key) {
-			if entry.is_expired() {
-				data.remove(key);
-				return Ok(None);
-			}
-			return Ok(Some(entry.value.clone()));
-		}
-
-		Ok(None)
+		Ok(data.get(key).cloned())
 	}
 
 	async fn set(&self, key: &str, value: Vec<u8>, ttl: Option<Duration>) -> Result<(), StoreError> {
 		let mut data = self.data.lock().unwrap();
-		data.insert(key.to_string(), MemoryEntry::new(value, ttl));
+		data.insert(key.to_string(), value, ttl);
 		Ok(())
 	}
 
@@ -88,6 +75,42 @@ impl StateStore for MemoryStore {
 		data.remove(key);
 		Ok(())
 	}
+
+	async fn set_if_absent(
+		&self,
+		key: &str,
+		value: Vec<u8>,
+		ttl: Option<Duration>,
+	) -> Result<bool, StoreError> {
+		let mut data = self.data.lock().unwrap();
+		if data.peek(key).is_some() {
+			return Ok(false);
+		}
+		data.insert(key.to_string(), value, ttl);
+		Ok(true)
+	}
+
+	async fn get_with_ttl(
+		&self,
+		key: &str,
+	) -> Result<Option<(Vec<u8>, Option<Duration>)>, StoreError> {
+		let data = self.data.lock().unwrap();
+		Ok(data.peek_with_expiry(key).map(|(value, expires_at)| {
+			let ttl = expires_at.map(|exp| exp.saturating_duration_since(Instant::now()));
+			(value.clone(), ttl)
+		}))
+	}
+
+	async fn keys(&self, prefix: &str) -> Result<Vec<String>, StoreError> {
+		let data = self.data.lock().unwrap();
+		Ok(
+			data
+				.iter()
+				.filter(|(key, _)| key.starts_with(prefix))
+				.map(|(key, _)| key.clone())
+				.collect(),
+		)
+	}
 }
 
 #[cfg(test)]
@@ -139,4 +162,67 @@ mod tests {
 
 		assert_eq!(store.get("key1").await.unwrap(), Some(b"value2".to_vec()));
 	}
+
+	#[tokio::test]
+	async fn test_memory_store_set_if_absent() {
+		let store = MemoryStore::new();
+		assert!(
+			store
+				.set_if_absent("lock", b"1".to_vec(), None)
+				.await
+				.unwrap()
+		);
+		assert!(
+			!store
+				.set_if_absent("lock", b"2".to_vec(), None)
+				.await
+				.unwrap()
+		);
+		assert_eq!(store.get("lock").await.unwrap(), Some(b"1".to_vec()));
+
+		// An expired key counts as absent
+		store
+			.set("old", b"1".to_vec(), Some(Duration::from_millis(1)))
+			.await
+			.unwrap();
+		tokio::time::sleep(Duration::from_millis(5)).await;
+		assert!(
+			store
+				.set_if_absent("old", b"2".to_vec(), None)
+				.await
+				.unwrap()
+		);
+	}
+
+	#[tokio::test]
+	async fn test_memory_store_keys() {
+		let store = MemoryStore::new();
+
+		store.set("search/rust", b"1".to_vec(), None).await.unwrap();
+		store.set("search/go", b"2".to_vec(), None).await.unwrap();
+		store.set("lookup/rust", b"3".to_vec(), None).await.unwrap();
+		store
+			.set("search/old", b"4".to_vec(), Some(Duration::from_millis(1)))
+			.await
+			.unwrap();
+		tokio::time::sleep(Duration::from_millis(5)).await;
+
+		let mut keys = store.keys("search/").await.unwrap();
+		keys.sort();
+		assert_eq!(keys, vec!["search/go", "search/rust"]);
+		assert_eq!(store.keys("").await.unwrap().len(), 3);
+	}
+
+	#[tokio::test]
+	async fn test_memory_store_evicts_least_recently_used() {
+		let store = MemoryStore::with_max_entries(2);
+		store.set("a", b"1".to_vec(), None).await.unwrap();
+		store.set("b", b"2".to_vec(), None).await.unwrap();
+		store.get("a").await.unwrap();
+		store.set("c", b"3".to_vec(), None).await.unwrap();
+
+		assert!(store.get("b").await.unwrap().is_none());
+		assert!(store.get("a").await.unwrap().is_some());
+		assert_eq!(store.eviction_stats().evicted, 1);
+	}
 }
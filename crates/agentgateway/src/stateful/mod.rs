@@ -1,13 +1,26 @@
 //! Stateful patterns for agent gateway operations.
 //!
 //! This module provides patterns for stateful operations like caching,
-//! rate limiting, and other state-dependent behaviors.
+//! rate limiting, and other state-dependent behaviors, on local or tiered
+//! multi-region stores.
 
+mod bounded;
 mod cache;
+mod snapshot;
 mod store;
+mod tiered;
 
-pub use cache::{CacheError, CacheExecutor, CacheSpec, derive_cache_key, evaluate_predicate};
+pub use bounded::{BoundedMap, EvictionStats};
+pub use cache::{
+	CacheError, CacheExecutor, CachePredicate, CacheSpec, CacheStats, CacheStatsSnapshot,
+	InnerFailure, NegativeCacheSpec, derive_cache_key, evaluate_predicate, is_revalidation_marker,
+};
+pub use snapshot::{SNAPSHOT_VERSION, SnapshotEntry, SnapshotError, StateSnapshot};
 pub use store::{StateStore, StateStoreExt, StoreError};
+pub use tiered::{Consistency, TieredStore, TieredStoreConfig};
 
+// The result cache keeps its entries in a memory store, which is only public for tests
 #[cfg(any(test, feature = "testing"))]
 pub mod memory;
+#[cfg(not(any(test, feature = "testing")))]
+pub(crate) mod memory;
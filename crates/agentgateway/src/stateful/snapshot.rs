@@ -0,0 +1,199 @@
+//! Point-in-time snapshots of state stores.
+//!
+//! A snapshot copies every unexpired entry of a set of named stores, with its
+//! remaining TTL, so the state can be restored on another instance (e.g. during
+//! a blue/green swap) and still expire when it would have on the original one.
+
+use std::collections::BTreeMap;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use base64::Engine as _;
+use base64::engine::general_purpose::STANDARD;
+use serde::{Deserialize, Serialize};
+
+use super::cache::is_revalidation_marker;
+use super::store::{StateStore, StoreError};
+
+/// Current snapshot format version.
+pub const SNAPSHOT_VERSION: u32 = 1;
+
+/// Error type for snapshot operations
+#[derive(Debug, thiserror::Error)]
+pub enum SnapshotError {
+	#[error("store error: {0}")]
+	Store(#[from] StoreError),
+	#[error("unsupported snapshot version {0} (expected {SNAPSHOT_VERSION})")]
+	UnsupportedVersion(u32),
+	#[error("invalid snapshot: {0}")]
+	Invalid(String),
+}
+
+/// The entries of a set of named stores at one point in time.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StateSnapshot {
+	/// Snapshot format version
+	pub version: u32,
+	/// When the snapshot was taken (Unix timestamp in millis)
+	pub created_at: u64,
+	/// Entries by store name
+	pub stores: BTreeMap<String, Vec<SnapshotEntry>>,
+}
+
+/// One store entry in a snapshot.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SnapshotEntry {
+	pub key: String,
+	/// Base64-encoded value
+	pub value: String,
+	/// Remaining time-to-live in millis when the snapshot was taken
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub ttl_ms: Option<u64>,
+}
+
+fn now_millis() -> u64 {
+	SystemTime::now()
+		.duration_since(UNIX_EPOCH)
+		.unwrap()
+		.as_millis() as u64
+}
+
+impl StateSnapshot {
+	/// Copy the unexpired entries of each named store.
+	pub async fn capture(stores: &[(&str, &dyn StateStore)]) -> Result<Self, SnapshotError> {
+		let mut snapshot = Self {
+			version: SNAPSHOT_VERSION,
+			created_at: now_millis(),
+			stores: BTreeMap::new(),
+		};
+		for (name, store) in stores {
+			let mut entries = Vec::new();
+			for key in store.keys("").await? {
+				// Revalidation markers belong to refreshes running on this instance
+				if is_revalidation_marker(&key) {
+					continue;
+				}
+				// Entries can expire between listing and reading them
+				let Some((value, ttl)) = store.get_with_ttl(&key).await? else {
+					continue;
+				};
+				entries.push(SnapshotEntry {
+					key,
+					value: STANDARD.encode(value),
+					ttl_ms: ttl.map(|ttl| ttl.as_millis() as u64),
+				});
+			}
+			entries.sort_by(|a, b| a.key.cmp(&b.key));
+			snapshot.stores.insert(name.to_string(), entries);
+		}
+		Ok(snapshot)
+	}
+
+	/// Write the snapshot's entries into the named stores, returning how many were restored.
+	///
+	/// TTLs count from when the snapshot was taken, so entries that expired since are
+	/// skipped. Stores missing from the snapshot are left alone, and snapshot stores
+	/// without a counterpart are ignored.
+	pub async fn restore(&self, stores: &[(&str, &dyn StateStore)]) -> Result<usize, SnapshotError> {
+		if self.version != SNAPSHOT_VERSION {
+			return Err(SnapshotError::UnsupportedVersion(self.version));
+		}
+		let elapsed = now_millis().saturating_sub(self.created_at);
+		let mut restored = 0;
+		for (name, store) in stores {
+			let Some(entries) = self.stores.get(*name) else {
+				continue;
+			};
+			for entry in entries {
+				let ttl = match entry.ttl_ms {
+					Some(ttl) if ttl <= elapsed => continue,
+					Some(ttl) => Some(Duration::from_millis(ttl - elapsed)),
+					None => None,
+				};
+				let value = STANDARD
+					.decode(&entry.value)
+					.map_err(|e| SnapshotError::Invalid(format!("value of '{}': {e}", entry.key)))?;
+				store.set(&entry.key, value, ttl).await?;
+				restored += 1;
+			}
+		}
+		Ok(restored)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::stateful::memory::MemoryStore;
+
+	#[tokio::test]
+	async fn test_snapshot_round_trip() {
+		let cache = MemoryStore::new();
+		let circuits = MemoryStore::new();
+		cache
+			.set("search/rust", b"axum".to_vec(), None)
+			.await
+			.unwrap();
+		cache
+			.set("search/go", b"gin".to_vec(), Some(Duration::from_secs(60)))
+			.await
+			.unwrap();
+		circuits
+			.set("payments", b"open".to_vec(), Some(Duration::from_millis(1)))
+			.await
+			.unwrap();
+		tokio::time::sleep(Duration::from_millis(5)).await;
+
+		let snapshot = StateSnapshot::capture(&[("cache", &cache), ("circuits", &circuits)])
+			.await
+			.unwrap();
+		assert_eq!(snapshot.stores["cache"].len(), 2);
+		assert!(snapshot.stores["circuits"].is_empty());
+
+		// Survives serialization, e.g. to a file
+		let snapshot: StateSnapshot =
+			serde_json::from_str(&serde_json::to_string(&snapshot).unwrap()).unwrap();
+		let target = MemoryStore::new();
+		let restored = snapshot.restore(&[("cache", &target)]).await.unwrap();
+		assert_eq!(restored, 2);
+		assert_eq!(
+			target.get("search/rust").await.unwrap(),
+			Some(b"axum".to_vec())
+		);
+		let (_, ttl) = target.get_with_ttl("search/go").await.unwrap().unwrap();
+		assert!(ttl.unwrap() <= Duration::from_secs(60));
+	}
+
+	#[tokio::test]
+	async fn test_restore_skips_expired_and_rejects_unknown_versions() {
+		let mut snapshot = StateSnapshot {
+			version: SNAPSHOT_VERSION,
+			created_at: now_millis() - 10_000,
+			stores: BTreeMap::from([(
+				"cache".to_string(),
+				vec![
+					SnapshotEntry {
+						key: "expired".to_string(),
+						value: STANDARD.encode("old"),
+						ttl_ms: Some(5_000),
+					},
+					SnapshotEntry {
+						key: "live".to_string(),
+						value: STANDARD.encode("new"),
+						ttl_ms: Some(60_000),
+					},
+				],
+			)]),
+		};
+		let target = MemoryStore::new();
+		assert_eq!(snapshot.restore(&[("cache", &target)]).await.unwrap(), 1);
+		assert!(target.get("expired").await.unwrap().is_none());
+
+		snapshot.version = SNAPSHOT_VERSION + 1;
+		assert!(matches!(
+			snapshot.restore(&[("cache", &target)]).await,
+			Err(SnapshotError::UnsupportedVersion(_))
+		));
+	}
+}
@@ -40,6 +40,44 @@ pub trait StateStore: Send + Sync {
 	async fn exists(&self, key: &str) -> Result<bool, StoreError> {
 		Ok(self.get(key).await?.is_some())
 	}
+
+	/// Set a value only if the key doesn't exist, returning whether it was set.
+	///
+	/// The default checks and sets in two steps; stores shared between tasks should
+	/// override it to do both atomically.
+	async fn set_if_absent(
+		&self,
+		key: &str,
+		value: Vec<u8>,
+		ttl: Option<Duration>,
+	) -> Result<bool, StoreError> {
+		if self.exists(key).await? {
+			return Ok(false);
+		}
+		self.set(key, value, ttl).await?;
+		Ok(true)
+	}
+
+	/// Get a value and its remaining time-to-live (`None` if it doesn't expire).
+	///
+	/// Stores that can't report TTLs keep the default, which fails.
+	async fn get_with_ttl(
+		&self,
+		_key: &str,
+	) -> Result<Option<(Vec<u8>, Option<Duration>)>, StoreError> {
+		Err(StoreError::Storage(
+			"this store can't report TTLs".to_string(),
+		))
+	}
+
+	/// List the unexpired keys starting with `prefix`.
+	///
+	/// Stores that can't list keys keep the default, which fails.
+	async fn keys(&self, _prefix: &str) -> Result<Vec<String>, StoreError> {
+		Err(StoreError::Storage(
+			"this store can't list keys".to_string(),
+		))
+	}
 }
 
 /// Extension trait for StateStore that provides convenience methods
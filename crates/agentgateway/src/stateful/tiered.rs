@@ -0,0 +1,391 @@
+//! Tiered implementation of StateStore for multi-region deployments.
+//!
+//! Each gateway keeps a local in-memory tier in front of a shared backend
+//! (e.g. a replicated Redis) that all regions read and write. Reads and writes
+//! are either strongly consistent, going to the shared backend on the request
+//! path, or eventually consistent, served from the local tier with writes
+//! replicated to the shared backend in the background.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use tokio::sync::{mpsc, oneshot};
+use tracing::warn;
+
+use super::memory::MemoryStore;
+use super::store::{StateStore, StoreError};
+
+/// How a pattern reads and writes a tiered store.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Consistency {
+	/// Every operation goes to the shared backend before returning.
+	Strong,
+	/// Reads are served locally when possible and writes replicate asynchronously.
+	Eventual,
+}
+
+/// Configuration for a [`TieredStore`].
+#[derive(Debug, Clone)]
+pub struct TieredStoreConfig {
+	/// Longest a value is served from the local tier before re-reading the shared backend
+	pub local_ttl: Duration,
+	/// Consistency of patterns without an explicit setting
+	pub default_consistency: Consistency,
+	/// Consistency by pattern name (e.g. "throttle", "idempotency")
+	pub patterns: HashMap<String, Consistency>,
+}
+
+impl Default for TieredStoreConfig {
+	/// Throttle and cache state tolerate approximate values; everything else,
+	/// notably idempotency keys, is strongly consistent.
+	fn default() -> Self {
+		Self {
+			local_ttl: Duration::from_secs(5),
+			default_consistency: Consistency::Strong,
+			patterns: HashMap::from([
+				("throttle".to_string(), Consistency::Eventual),
+				("cache".to_string(), Consistency::Eventual),
+			]),
+		}
+	}
+}
+
+impl TieredStoreConfig {
+	/// Set how long values are served from the local tier.
+	pub fn with_local_ttl(mut self, local_ttl: Duration) -> Self {
+		self.local_ttl = local_ttl;
+		self
+	}
+
+	/// Set the consistency of one pattern.
+	pub fn with_pattern_consistency(
+		mut self,
+		pattern: impl Into<String>,
+		consistency: Consistency,
+	) -> Self {
+		self.patterns.insert(pattern.into(), consistency);
+		self
+	}
+
+	/// The consistency a pattern uses.
+	pub fn consistency_for(&self, pattern: &str) -> Consistency {
+		self
+			.patterns
+			.get(pattern)
+			.copied()
+			.unwrap_or(self.default_consistency)
+	}
+}
+
+/// A write waiting to be replicated to the shared backend
+enum Replication {
+	Set {
+		key: String,
+		value: Vec<u8>,
+		expires_at: Option<Instant>,
+	},
+	Delete {
+		key: String,
+	},
+	Flush(oneshot::Sender<()>),
+}
+
+struct Shared {
+	local: MemoryStore,
+	remote: Arc<dyn StateStore>,
+	config: TieredStoreConfig,
+	replication: mpsc::UnboundedSender<Replication>,
+	replication_failures: Arc<AtomicU64>,
+}
+
+/// Local-first StateStore backed by a store shared between regions.
+///
+/// Clones share both tiers; [`TieredStore::for_pattern`] returns a view with the
+/// pattern's configured consistency. Listing keys and reading TTLs always go to
+/// the shared backend, so they don't include eventual writes that haven't
+/// replicated yet.
+#[derive(Clone)]
+pub struct TieredStore {
+	shared: Arc<Shared>,
+	consistency: Consistency,
+}
+
+impl fmt::Debug for TieredStore {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.debug_struct("TieredStore")
+			.field("consistency", &self.consistency)
+			.field("config", &self.shared.config)
+			.finish_non_exhaustive()
+	}
+}
+
+impl TieredStore {
+	/// Create a tiered store in front of `remote`.
+	///
+	/// Spawns the replication task, so this must be called within a Tokio runtime.
+	pub fn new(remote: Arc<dyn StateStore>, config: TieredStoreConfig) -> Self {
+		let (tx, rx) = mpsc::unbounded_channel();
+		let replication_failures = Arc::new(AtomicU64::new(0));
+		tokio::spawn(replicate(rx, remote.clone(), replication_failures.clone()));
+		let consistency = config.default_consistency;
+		Self {
+			shared: Arc::new(Shared {
+				local: MemoryStore::new(),
+				remote,
+				config,
+				replication: tx,
+				replication_failures,
+			}),
+			consistency,
+		}
+	}
+
+	/// A view of this store with the consistency configured for `pattern`.
+	pub fn for_pattern(&self, pattern: &str) -> Self {
+		self
+			.clone()
+			.with_consistency(self.shared.config.consistency_for(pattern))
+	}
+
+	/// A view of this store with the given consistency.
+	pub fn with_consistency(mut self, consistency: Consistency) -> Self {
+		self.consistency = consistency;
+		self
+	}
+
+	/// The consistency of this view.
+	pub fn consistency(&self) -> Consistency {
+		self.consistency
+	}
+
+	/// Number of eventual writes that failed to replicate.
+	pub fn replication_failures(&self) -> u64 {
+		self.shared.replication_failures.load(Ordering::Relaxed)
+	}
+
+	/// Wait until every eventual write queued so far has been replicated.
+	pub async fn flush(&self) {
+		let (tx, rx) = oneshot::channel();
+		if self.shared.replication.send(Replication::Flush(tx)).is_ok() {
+			let _ = rx.await;
+		}
+	}
+
+	/// The TTL of a local copy: the value's own TTL, capped to the local TTL
+	fn local_ttl(&self, ttl: Option<Duration>) -> Option<Duration> {
+		let local_ttl = self.shared.config.local_ttl;
+		Some(ttl.map_or(local_ttl, |ttl| ttl.min(local_ttl)))
+	}
+
+	fn enqueue(&self, replication: Replication) -> Result<(), StoreError> {
+		self
+			.shared
+			.replication
+			.send(replication)
+			.map_err(|_| StoreError::Storage("replication task stopped".to_string()))
+	}
+}
+
+#[async_trait]
+impl StateStore for TieredStore {
+	async fn get(&self, key: &str) -> Result<Option<Vec<u8>>, StoreError> {
+		if self.consistency == Consistency::Eventual
+			&& let Some(value) = self.shared.local.get(key).await?
+		{
+			return Ok(Some(value));
+		}
+		let Some((value, ttl)) = self.shared.remote.get_with_ttl(key).await? else {
+			self.shared.local.delete(key).await?;
+			return Ok(None);
+		};
+		self
+			.shared
+			.local
+			.set(key, value.clone(), self.local_ttl(ttl))
+			.await?;
+		Ok(Some(value))
+	}
+
+	async fn set(&self, key: &str, value: Vec<u8>, ttl: Option<Duration>) -> Result<(), StoreError> {
+		match self.consistency {
+			Consistency::Strong => self.shared.remote.set(key, value.clone(), ttl).await?,
+			Consistency::Eventual => self.enqueue(Replication::Set {
+				key: key.to_string(),
+				value: value.clone(),
+				expires_at: ttl.map(|ttl| Instant::now() + ttl),
+			})?,
+		}
+		self.shared.local.set(key, value, self.local_ttl(ttl)).await
+	}
+
+	async fn delete(&self, key: &str) -> Result<(), StoreError> {
+		match self.consistency {
+			Consistency::Strong => self.shared.remote.delete(key).await?,
+			Consistency::Eventual => self.enqueue(Replication::Delete {
+				key: key.to_string(),
+			})?,
+		}
+		self.shared.local.delete(key).await
+	}
+
+	/// Strong views claim the key in the shared backend, so at most one region wins it; eventual
+	/// views only claim it locally.
+	async fn set_if_absent(
+		&self,
+		key: &str,
+		value: Vec<u8>,
+		ttl: Option<Duration>,
+	) -> Result<bool, StoreError> {
+		match self.consistency {
+			Consistency::Strong => {
+				if !self.shared.remote.set_if_absent(key, value.clone(), ttl).await? {
+					return Ok(false);
+				}
+				self.shared.local.set(key, value, self.local_ttl(ttl)).await?;
+				Ok(true)
+			},
+			Consistency::Eventual => {
+				if !self
+					.shared
+					.local
+					.set_if_absent(key, value.clone(), self.local_ttl(ttl))
+					.await?
+				{
+					return Ok(false);
+				}
+				self.enqueue(Replication::Set {
+					key: key.to_string(),
+					value,
+					expires_at: ttl.map(|ttl| Instant::now() + ttl),
+				})?;
+				Ok(true)
+			},
+		}
+	}
+
+	async fn get_with_ttl(
+		&self,
+		key: &str,
+	) -> Result<Option<(Vec<u8>, Option<Duration>)>, StoreError> {
+		self.shared.remote.get_with_ttl(key).await
+	}
+
+	async fn keys(&self, prefix: &str) -> Result<Vec<String>, StoreError> {
+		self.shared.remote.keys(prefix).await
+	}
+}
+
+/// Apply queued writes to the shared backend in order, until every store handle is dropped
+async fn replicate(
+	mut rx: mpsc::UnboundedReceiver<Replication>,
+	remote: Arc<dyn StateStore>,
+	failures: Arc<AtomicU64>,
+) {
+	while let Some(replication) = rx.recv().await {
+		let (key, result) = match replication {
+			Replication::Set {
+				key,
+				value,
+				expires_at,
+			} => {
+				// Writes that expired while queued are dropped rather than stored forever
+				let ttl = match expires_at.map(|at| at.saturating_duration_since(Instant::now())) {
+					Some(Duration::ZERO) => continue,
+					ttl => ttl,
+				};
+				let result = remote.set(&key, value, ttl).await;
+				(key, result)
+			},
+			Replication::Delete { key } => {
+				let result = remote.delete(&key).await;
+				(key, result)
+			},
+			Replication::Flush(done) => {
+				let _ = done.send(());
+				continue;
+			},
+		};
+		if let Err(e) = result {
+			failures.fetch_add(1, Ordering::Relaxed);
+			warn!(key, error = %e, "failed to replicate state store write");
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn regions(config: TieredStoreConfig) -> (Arc<MemoryStore>, TieredStore, TieredStore) {
+		let remote = Arc::new(MemoryStore::new());
+		let us = TieredStore::new(remote.clone(), config.clone());
+		let eu = TieredStore::new(remote.clone(), config);
+		(remote, us, eu)
+	}
+
+	#[tokio::test]
+	async fn test_eventual_writes_are_local_first() {
+		let (remote, us, eu) = regions(TieredStoreConfig::default());
+		let us = us.for_pattern("throttle");
+		assert_eq!(us.consistency(), Consistency::Eventual);
+
+		us.set("bucket", b"9".to_vec(), None).await.unwrap();
+		assert_eq!(us.get("bucket").await.unwrap(), Some(b"9".to_vec()));
+
+		us.flush().await;
+		assert_eq!(remote.get("bucket").await.unwrap(), Some(b"9".to_vec()));
+		assert_eq!(eu.get("bucket").await.unwrap(), Some(b"9".to_vec()));
+
+		us.delete("bucket").await.unwrap();
+		us.flush().await;
+		assert!(remote.get("bucket").await.unwrap().is_none());
+		assert_eq!(us.replication_failures(), 0);
+	}
+
+	#[tokio::test]
+	async fn test_strong_consistency_for_idempotency() {
+		let (_, us, eu) = regions(TieredStoreConfig::default());
+		let us = us.for_pattern("idempotency");
+		let eu = eu.for_pattern("idempotency");
+		assert_eq!(us.consistency(), Consistency::Strong);
+
+		us.set("request-1", b"done".to_vec(), None).await.unwrap();
+		assert!(eu.exists("request-1").await.unwrap());
+
+		eu.delete("request-1").await.unwrap();
+		assert!(!us.exists("request-1").await.unwrap());
+	}
+
+	#[tokio::test]
+	async fn test_strong_claims_are_won_once_across_regions() {
+		let (_, us, eu) = regions(TieredStoreConfig::default());
+		let us = us.for_pattern("idempotency");
+		let eu = eu.for_pattern("idempotency");
+
+		assert!(us.set_if_absent("request-1", b"us".to_vec(), None).await.unwrap());
+		assert!(!eu.set_if_absent("request-1", b"eu".to_vec(), None).await.unwrap());
+		assert_eq!(eu.get("request-1").await.unwrap(), Some(b"us".to_vec()));
+	}
+
+	#[tokio::test]
+	async fn test_local_copies_expire_after_local_ttl() {
+		let config = TieredStoreConfig::default().with_local_ttl(Duration::from_millis(20));
+		let (remote, us, eu) = regions(config);
+		let eu = eu.with_consistency(Consistency::Eventual);
+
+		us.set("limit", b"10".to_vec(), None).await.unwrap();
+		assert_eq!(eu.get("limit").await.unwrap(), Some(b"10".to_vec()));
+
+		// Another region's update is picked up once the local copy expires
+		remote.set("limit", b"20".to_vec(), None).await.unwrap();
+		assert_eq!(eu.get("limit").await.unwrap(), Some(b"10".to_vec()));
+		tokio::time::sleep(Duration::from_millis(30)).await;
+		assert_eq!(eu.get("limit").await.unwrap(), Some(b"20".to_vec()));
+	}
+}
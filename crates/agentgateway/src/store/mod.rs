@@ -2,6 +2,7 @@ mod binds;
 
 use std::sync::Arc;
 
+use arc_swap::ArcSwapOption;
 pub use binds::{
 	BackendPolicies, FrontendPolices, GatewayPolicies, LLMRequestPolicies, LLMResponsePolicies,
 	RoutePath, RoutePolicies, Store as BindStore,
@@ -15,7 +16,10 @@ pub use discovery::{
 	LocalWorkload, PreviousState as DiscoveryPreviousState, Store as DiscoveryStore, WorkloadStore,
 };
 
-use crate::mcp::registry::RegistryStoreRef;
+use crate::events::EventBus;
+use crate::mcp::UpstreamHealth;
+use crate::mcp::registry::{RegistryDump, RegistryStoreRef};
+use crate::stateful::{SnapshotError, StateSnapshot, StateStore};
 use crate::store;
 
 #[derive(Clone, Debug)]
@@ -28,8 +32,13 @@ pub enum Event<T> {
 pub struct Stores {
 	pub discovery: discovery::StoreUpdater,
 	pub binds: binds::StoreUpdater,
-	/// Tool registry store for virtual tool mappings
-	pub registry: Arc<RwLock<Option<RegistryStoreRef>>>,
+	/// Tool registry store for virtual tool mappings. Read on every MCP request, so it is
+	/// swapped atomically rather than locked.
+	pub registry: Arc<ArcSwapOption<RegistryStoreRef>>,
+	/// Circuit breakers for MCP upstreams, shared by every relay
+	pub upstream_health: Arc<UpstreamHealth>,
+	/// Health events published by this gateway's components, served at `/debug/events`
+	pub events: EventBus,
 }
 
 impl Default for Stores {
@@ -43,7 +52,9 @@ impl Stores {
 		Stores {
 			discovery: discovery::StoreUpdater::new(Arc::new(RwLock::new(discovery::Store::new()))),
 			binds: binds::StoreUpdater::new(Arc::new(RwLock::new(binds::Store::new()))),
-			registry: Arc::new(RwLock::new(None)),
+			registry: Arc::new(ArcSwapOption::empty()),
+			upstream_health: Arc::new(UpstreamHealth::new()),
+			events: EventBus::default(),
 		}
 	}
 	pub fn read_binds(&self) -> std::sync::RwLockReadGuard<'_, store::BindStore> {
@@ -56,14 +67,36 @@ impl Stores {
 
 	/// Set the registry store
 	pub fn set_registry(&self, registry: Option<RegistryStoreRef>) {
-		if let Ok(mut reg) = self.registry.write() {
-			*reg = registry;
-		}
+		self.registry.store(registry.map(Arc::new));
 	}
 
 	/// Get the registry store
 	pub fn get_registry(&self) -> Option<RegistryStoreRef> {
-		self.registry.read().ok().and_then(|r| r.clone())
+		self.registry.load().as_deref().cloned()
+	}
+
+	/// The runtime state stores to carry across instances, by snapshot name
+	pub fn state_stores(&self) -> Vec<(&'static str, Arc<dyn StateStore>)> {
+		let mut stores: Vec<(&'static str, Arc<dyn StateStore>)> =
+			vec![("circuitBreakers", self.upstream_health.clone())];
+		if let Some(registry) = self.get_registry() {
+			stores.extend(registry.state_stores());
+		}
+		stores
+	}
+
+	/// Snapshot the runtime state stores
+	pub async fn export_state(&self) -> Result<StateSnapshot, SnapshotError> {
+		let stores = self.state_stores();
+		let stores: Vec<_> = stores.iter().map(|(n, s)| (*n, s.as_ref())).collect();
+		StateSnapshot::capture(&stores).await
+	}
+
+	/// Restore a snapshot taken by [`Self::export_state`], possibly on another instance
+	pub async fn import_state(&self, snapshot: &StateSnapshot) -> Result<usize, SnapshotError> {
+		let stores = self.state_stores();
+		let stores: Vec<_> = stores.iter().map(|(n, s)| (*n, s.as_ref())).collect();
+		snapshot.restore(&stores).await
 	}
 }
 
@@ -74,6 +107,8 @@ struct StoresDump {
 	discovery: discovery::Dump,
 	#[serde(flatten)]
 	binds: binds::Dump,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	registry: Option<RegistryDump>,
 }
 
 impl Serialize for Stores {
@@ -84,7 +119,99 @@ impl Serialize for Stores {
 		let serializable = StoresDump {
 			discovery: self.discovery.dump(),
 			binds: self.binds.dump(),
+			registry: self.get_registry().map(|r| r.dump()),
 		};
 		serializable.serialize(serializer)
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use std::time::Duration;
+
+	use agent_core::strng;
+	use rmcp::model::CallToolResult;
+	use serde_json::json;
+
+	use super::*;
+	use crate::mcp::registry::patterns::OnDuplicate;
+	use crate::mcp::registry::{Idempotency, IdempotencyKey};
+	use crate::types::agent::{McpCircuitBreaker, ResourceName};
+
+	#[tokio::test]
+	async fn test_state_moves_between_gateways() {
+		let blue = Stores::new();
+		blue.set_registry(Some(RegistryStoreRef::default()));
+		let registry = blue.get_registry().unwrap();
+		let backend = ResourceName::new(strng::literal!("mcp"), strng::literal!("default"));
+		let config = McpCircuitBreaker {
+			failure_rate: 0.5,
+			minimum_calls: 1,
+			window: Duration::from_secs(60),
+			slow_call_threshold: None,
+			open_duration: Duration::from_secs(60),
+		};
+		let target = strng::literal!("weather");
+		blue
+			.upstream_health
+			.record(&backend, &target, &config, Some("down".to_string()));
+		registry
+			.result_cache()
+			.store()
+			.set("search/rust", b"{}".to_vec(), Some(Duration::from_secs(60)))
+			.await
+			.unwrap();
+		let idempotency = Idempotency {
+			ttl_seconds: 60,
+			on_duplicate: OnDuplicate::Cached,
+		};
+		registry
+			.idempotency()
+			.call(
+				IdempotencyKey::new("sub:agent-7", "create_ticket", "req-1"),
+				&json!({}),
+				&idempotency,
+				async { Ok(CallToolResult::success(vec![])) },
+			)
+			.await
+			.unwrap();
+		registry
+			.rate_limiters()
+			.lock()
+			.await
+			.get_or_create("agent-7", Duration::from_secs(60));
+
+		let snapshot = blue.export_state().await.unwrap();
+		for store in [
+			"circuitBreakers",
+			"resultCache",
+			"idempotencyKeys",
+			"rateLimiters",
+		] {
+			assert_eq!(snapshot.stores[store].len(), 1, "{store}");
+		}
+
+		let green = Stores::new();
+		green.set_registry(Some(RegistryStoreRef::default()));
+		assert_eq!(green.import_state(&snapshot).await.unwrap(), 4);
+		assert!(
+			!green
+				.upstream_health
+				.is_available(&backend, &target, &config)
+		);
+		let registry = green.get_registry().unwrap();
+		assert!(
+			registry
+				.result_cache()
+				.store()
+				.exists("search/rust")
+				.await
+				.unwrap()
+		);
+		assert_eq!(registry.idempotency().len(), 1);
+		assert_eq!(
+			registry.rate_limiters().keys("").await.unwrap(),
+			["agent-7"]
+		);
+	}
+}
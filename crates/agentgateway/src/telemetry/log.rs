@@ -26,7 +26,8 @@ use crate::cel::{ContextBuilder, Expression};
 use crate::llm::{InputFormat, LLMInfo};
 use crate::proxy::ProxyResponseReason;
 use crate::telemetry::metrics::{
-	GenAILabels, GenAILabelsTokenUsage, HTTPLabels, MCPCall, Metrics, RouteIdentifier,
+	AttributionLabels, GenAILabels, GenAILabelsTokenUsage, HTTPLabels, MCPCall, Metrics,
+	RouteIdentifier,
 };
 use crate::telemetry::trc;
 use crate::telemetry::trc::TraceParent;
@@ -88,6 +89,7 @@ pub struct Config {
 	pub fields: LoggingFields,
 	pub metric_fields: Arc<MetricFields>,
 	pub excluded_metrics: FzHashSet<String>,
+	pub attribution_labels: AttributionLabels,
 	pub level: String,
 	pub format: crate::LoggingFormat,
 }
@@ -834,6 +836,17 @@ impl Drop for DropOnLog {
 					.and_then(|m| m.session_id.as_ref())
 					.map(display),
 			),
+			(
+				"mcp.labels",
+				mcp.as_ref().and_then(|m| m.labels.as_ref()).map(display),
+			),
+			(
+				"mcp.execution.id",
+				mcp
+					.as_ref()
+					.and_then(|m| m.execution_id.as_ref())
+					.map(display),
+			),
 			(
 				"inferencepool.selected_endpoint",
 				log.inference_pool.display(),
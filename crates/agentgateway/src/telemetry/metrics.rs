@@ -1,12 +1,19 @@
+use std::collections::{HashMap, HashSet};
 use std::fmt::Debug;
+use std::sync::atomic::AtomicU64;
+use std::time::{SystemTime, UNIX_EPOCH};
 
 use agent_core::metrics::{CustomField, DefaultedUnknown, EncodeArc, EncodeDebug, EncodeDisplay};
 use agent_core::strng::RichStrng;
 use agent_core::version;
 use frozen_collections::FzHashSet;
-use prometheus_client::encoding::EncodeLabelSet;
-use prometheus_client::metrics::counter;
+use prometheus_client::collector::Collector;
+use prometheus_client::encoding::{DescriptorEncoder, EncodeLabelSet, EncodeMetric};
+use prometheus_client::metrics::MetricType;
+use prometheus_client::metrics::counter::{self, ConstCounter};
+use prometheus_client::metrics::exemplar::HistogramWithExemplars;
 use prometheus_client::metrics::family::Family;
+use prometheus_client::metrics::gauge::{ConstGauge, Gauge};
 use prometheus_client::metrics::histogram::Histogram as PromHistogram;
 use prometheus_client::metrics::info::Info;
 use prometheus_client::registry::{Metric, Registry, Unit};
@@ -103,6 +110,104 @@ pub struct MCPCall {
 	pub custom: CustomField,
 }
 
+#[derive(
+	Copy, Clone, Hash, Debug, PartialEq, Eq, prometheus_client::encoding::EncodeLabelValue, Default,
+)]
+pub enum CompositionOutcome {
+	#[default]
+	Success,
+	Error,
+}
+
+#[derive(Clone, Hash, Debug, PartialEq, Eq, EncodeLabelSet)]
+pub struct CompositionLabels {
+	pub composition: DefaultedUnknown<RichStrng>,
+	pub outcome: CompositionOutcome,
+}
+
+/// Caller label values allowed on the attribution metrics, by label name
+///
+/// Label values come from callers, so only configured ones become metric label values: any
+/// other value of a configured label is reported as `other`, and labels that aren't configured
+/// are left out of the metrics.
+#[derive(Debug, Clone, Default, serde::Serialize)]
+pub struct AttributionLabels(HashMap<String, HashSet<String>>);
+
+impl AttributionLabels {
+	pub const OTHER: &'static str = "other";
+
+	pub fn new(allowed: HashMap<String, Vec<String>>) -> Self {
+		Self(
+			allowed
+				.into_iter()
+				.map(|(label, values)| (label, values.into_iter().collect()))
+				.collect(),
+		)
+	}
+
+	/// Value to report for a caller label, or None if the label isn't reported
+	pub fn value<'a>(&self, label: &str, value: &'a str) -> Option<&'a str> {
+		let allowed = self.0.get(label)?;
+		Some(if allowed.contains(value) {
+			value
+		} else {
+			Self::OTHER
+		})
+	}
+}
+
+// Attribution of composition executions to a label the caller attached via `_meta`
+#[derive(Clone, Hash, Debug, PartialEq, Eq, EncodeLabelSet)]
+pub struct CompositionAttributionLabels {
+	pub composition: DefaultedUnknown<RichStrng>,
+	pub label: DefaultedUnknown<RichStrng>,
+	pub value: DefaultedUnknown<RichStrng>,
+	pub outcome: CompositionOutcome,
+}
+
+// Attribution of backend tool calls made by compositions to a caller-supplied label
+#[derive(Clone, Hash, Debug, PartialEq, Eq, EncodeLabelSet)]
+pub struct ToolCallAttributionLabels {
+	pub composition: DefaultedUnknown<RichStrng>,
+	pub tool: DefaultedUnknown<RichStrng>,
+	pub label: DefaultedUnknown<RichStrng>,
+	pub value: DefaultedUnknown<RichStrng>,
+}
+
+#[derive(Clone, Hash, Debug, PartialEq, Eq, EncodeLabelSet)]
+pub struct ToolCostLabels {
+	pub composition: DefaultedUnknown<RichStrng>,
+	pub tool: DefaultedUnknown<RichStrng>,
+}
+
+// Attribution of estimated composition cost to a caller-supplied label
+#[derive(Clone, Hash, Debug, PartialEq, Eq, EncodeLabelSet)]
+pub struct CostAttributionLabels {
+	pub composition: DefaultedUnknown<RichStrng>,
+	pub label: DefaultedUnknown<RichStrng>,
+	pub value: DefaultedUnknown<RichStrng>,
+}
+
+#[derive(
+	Copy, Clone, Hash, Debug, PartialEq, Eq, prometheus_client::encoding::EncodeLabelValue, Default,
+)]
+pub enum CompositionLane {
+	#[default]
+	Interactive,
+	Batch,
+}
+
+#[derive(Clone, Hash, Debug, PartialEq, Eq, EncodeLabelSet)]
+pub struct CompositionLaneLabels {
+	pub lane: CompositionLane,
+}
+
+// Exemplar labels linking a histogram observation to the trace that produced it
+#[derive(Clone, Hash, Debug, PartialEq, Eq, EncodeLabelSet)]
+pub struct TraceExemplar {
+	pub trace_id: String,
+}
+
 #[derive(Clone, Hash, Debug, PartialEq, Eq, EncodeLabelSet)]
 pub struct TCPLabels {
 	pub bind: DefaultedUnknown<RichStrng>,
@@ -132,6 +237,12 @@ pub struct Metrics {
 	pub response_bytes: Family<HTTPLabels, counter::Counter>,
 
 	pub mcp_requests: Family<MCPCall, counter::Counter>,
+	pub composition_duration: Family<CompositionLabels, HistogramWithExemplars<TraceExemplar>>,
+	pub composition_queue_depth: Family<CompositionLaneLabels, Gauge>,
+	pub composition_attributed_executions: Family<CompositionAttributionLabels, counter::Counter>,
+	pub composition_attributed_tool_calls: Family<ToolCallAttributionLabels, counter::Counter>,
+	pub composition_tool_cost: Family<ToolCostLabels, counter::Counter<f64, AtomicU64>>,
+	pub composition_attributed_cost: Family<CostAttributionLabels, counter::Counter<f64, AtomicU64>>,
 
 	pub gen_ai_token_usage: Histogram<GenAILabelsTokenUsage>,
 	pub gen_ai_request_duration: Histogram<GenAILabels>,
@@ -148,6 +259,9 @@ pub struct Metrics {
 
 	// metrics for guardrail checks (allow/mask/reject) for request/response
 	pub guardrail_checks: Family<GuardrailLabels, counter::Counter>,
+
+	// caller label values allowed on the composition_attributed_* metrics
+	pub attribution_labels: AttributionLabels,
 }
 
 // FilteredRegistry is a wrapper around Registry that allows to filter out certain metrics.
@@ -291,6 +405,55 @@ impl Metrics {
 				"mcp_requests",
 				"Total number of MCP tool calls",
 			),
+			composition_duration: {
+				let m = Family::<CompositionLabels, _>::new_with_constructor(move || {
+					HistogramWithExemplars::new(HTTP_REQUEST_DURATION_BUCKET.into_iter())
+				});
+				registry.register_with_unit(
+					"composition_duration",
+					"Duration of tool composition executions (seconds)",
+					Unit::Seconds,
+					m.clone(),
+				);
+				m
+			},
+			composition_queue_depth: {
+				let m = Family::<CompositionLaneLabels, Gauge>::default();
+				registry.register(
+					"composition_queue_depth",
+					"Number of composition executions waiting for a worker, by priority lane",
+					m.clone(),
+				);
+				m
+			},
+			composition_attributed_executions: build(
+				&mut registry,
+				"composition_attributed_executions",
+				"Composition executions by caller-supplied label",
+			),
+			composition_attributed_tool_calls: build(
+				&mut registry,
+				"composition_attributed_tool_calls",
+				"Backend tool calls made by compositions, by caller-supplied label",
+			),
+			composition_tool_cost: {
+				let m = Family::<ToolCostLabels, counter::Counter<f64, AtomicU64>>::default();
+				registry.register(
+					"composition_tool_cost",
+					"Estimated cost of priced tool calls made by compositions",
+					m.clone(),
+				);
+				m
+			},
+			composition_attributed_cost: {
+				let m = Family::<CostAttributionLabels, counter::Counter<f64, AtomicU64>>::default();
+				registry.register(
+					"composition_attributed_cost",
+					"Estimated cost of composition executions, by caller-supplied label",
+					m.clone(),
+				);
+				m
+			},
 
 			gen_ai_token_usage,
 			gen_ai_request_duration,
@@ -363,7 +526,180 @@ impl Metrics {
 				);
 				m
 			},
+			attribution_labels: AttributionLabels::default(),
+		}
+	}
+
+	/// Builder: report these caller label values on the attribution metrics
+	pub fn with_attribution_labels(mut self, labels: AttributionLabels) -> Self {
+		self.attribution_labels = labels;
+		self
+	}
+}
+
+#[derive(Clone, Hash, Debug, PartialEq, Eq, EncodeLabelSet)]
+struct RegistryToolLabels {
+	kind: &'static str,
+}
+
+#[derive(Clone, Hash, Debug, PartialEq, Eq, EncodeLabelSet)]
+struct RegistryReloadLabels {
+	outcome: &'static str,
+}
+
+#[derive(Clone, Hash, Debug, PartialEq, Eq, EncodeLabelSet)]
+struct RegistryStateLabels {
+	store: &'static str,
+}
+
+#[derive(Clone, Hash, Debug, PartialEq, Eq, EncodeLabelSet)]
+struct RegistryEvictionLabels {
+	store: &'static str,
+	reason: &'static str,
+}
+
+#[derive(Clone, Hash, Debug, PartialEq, Eq, EncodeLabelSet)]
+struct RegistrySourceLabels {
+	trigger: &'static str,
+	source: String,
+}
+
+/// Tool registry stats, read from the configured registry store at scrape time so they
+/// reflect reloads made by its background refresh tasks
+#[derive(Debug)]
+pub struct RegistryCollector {
+	stores: crate::store::Stores,
+}
+
+impl RegistryCollector {
+	pub fn register(registry: &mut Registry, stores: crate::store::Stores) {
+		registry.register_collector(Box::new(RegistryCollector { stores }));
+	}
+}
+
+impl Collector for RegistryCollector {
+	fn encode(&self, mut encoder: DescriptorEncoder) -> Result<(), std::fmt::Error> {
+		let Some(store) = self.stores.get_registry() else {
+			return Ok(());
+		};
+		let stats = store.stats_snapshot();
+
+		let mut tools = encoder.encode_descriptor(
+			"registry_tools",
+			"Tools in the active registry, by kind",
+			None,
+			MetricType::Gauge,
+		)?;
+		for (kind, count) in [
+			("virtual", stats.tools - stats.compositions),
+			("composition", stats.compositions),
+		] {
+			ConstGauge::new(count as i64).encode(tools.encode_family(&RegistryToolLabels { kind })?)?;
 		}
+
+		let mut reloads = encoder.encode_descriptor(
+			"registry_reloads",
+			"Registry reload attempts since startup, by outcome",
+			None,
+			MetricType::Counter,
+		)?;
+		for (outcome, count) in [
+			("success", stats.reloads),
+			("failure", stats.reload_failures),
+		] {
+			ConstCounter::new(count).encode(reloads.encode_family(&RegistryReloadLabels { outcome })?)?;
+		}
+
+		ConstGauge::new(stats.degraded as i64).encode(encoder.encode_descriptor(
+			"registry_degraded",
+			"Whether a startup fallback registry is served because the source failed to load (0 or 1)",
+			None,
+			MetricType::Gauge,
+		)?)?;
+		if let Some(saved_at_ms) = stats.snapshot_saved_at_ms {
+			let now_ms = SystemTime::now()
+				.duration_since(UNIX_EPOCH)
+				.map(|d| d.as_millis() as u64)
+				.unwrap_or_default();
+			ConstGauge::new(now_ms.saturating_sub(saved_at_ms) as f64 / 1000.0).encode(
+				encoder.encode_descriptor(
+					"registry_snapshot_age",
+					"Age of the last-known-good snapshot served while degraded (seconds)",
+					Some(&Unit::Seconds),
+					MetricType::Gauge,
+				)?,
+			)?;
+		}
+
+		let state = store.eviction_stats();
+		let mut entries = encoder.encode_descriptor(
+			"registry_state_entries",
+			"Entries held by the registry's in-memory state stores, by store",
+			None,
+			MetricType::Gauge,
+		)?;
+		for &(store, counts) in &state {
+			ConstGauge::new(counts.entries as i64)
+				.encode(entries.encode_family(&RegistryStateLabels { store })?)?;
+		}
+		let mut evictions = encoder.encode_descriptor(
+			"registry_state_evictions",
+			"Entries removed from the registry's in-memory state stores since startup, by store and \
+			 reason",
+			None,
+			MetricType::Counter,
+		)?;
+		for &(store, counts) in &state {
+			for (reason, count) in [("expired", counts.expired), ("capacity", counts.evicted)] {
+				ConstCounter::new(count)
+					.encode(evictions.encode_family(&RegistryEvictionLabels { store, reason })?)?;
+			}
+		}
+
+		let Some(last) = stats.last_success else {
+			return Ok(());
+		};
+		ConstGauge::new(last.at_ms as f64 / 1000.0).encode(encoder.encode_descriptor(
+			"registry_last_reload_timestamp",
+			"Time the active registry was loaded (seconds since the Unix epoch)",
+			Some(&Unit::Seconds),
+			MetricType::Gauge,
+		)?)?;
+		ConstGauge::new(last.duration_ms / 1000.0).encode(encoder.encode_descriptor(
+			"registry_last_reload_duration",
+			"Time spent fetching, validating and compiling the active registry (seconds)",
+			Some(&Unit::Seconds),
+			MetricType::Gauge,
+		)?)?;
+		ConstGauge::new(last.compile_ms / 1000.0).encode(encoder.encode_descriptor(
+			"registry_last_compile_duration",
+			"Time spent compiling the active registry (seconds)",
+			Some(&Unit::Seconds),
+			MetricType::Gauge,
+		)?)?;
+		ConstGauge::new(last.validation_errors as i64).encode(encoder.encode_descriptor(
+			"registry_validation_errors",
+			"Validation errors found in the active registry",
+			None,
+			MetricType::Gauge,
+		)?)?;
+		ConstGauge::new(last.validation_warnings as i64).encode(encoder.encode_descriptor(
+			"registry_validation_warnings",
+			"Validation warnings found in the active registry",
+			None,
+			MetricType::Gauge,
+		)?)?;
+		let mut source = encoder.encode_descriptor(
+			"registry_last_reload_source",
+			"Trigger and source of the reload that produced the active registry (always 1)",
+			None,
+			MetricType::Gauge,
+		)?;
+		ConstGauge::new(1).encode(source.encode_family(&RegistrySourceLabels {
+			trigger: last.trigger.as_str(),
+			source: last.source.unwrap_or_default(),
+		})?)?;
+		Ok(())
 	}
 }
 
@@ -415,3 +751,23 @@ const OUTPUT_TOKEN_BUCKET: [f64; 14] = [
 const FIRST_TOKEN_BUCKET: [f64; 16] = [
 	0.001, 0.005, 0.01, 0.02, 0.04, 0.06, 0.08, 0.1, 0.25, 0.5, 0.75, 1.0, 2.5, 5.0, 7.5, 10.0,
 ];
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_attribution_labels_map_unlisted_values_to_other() {
+		let labels = AttributionLabels::new(HashMap::from([(
+			"project".to_string(),
+			vec!["search".to_string()],
+		)]));
+		assert_eq!(labels.value("project", "search"), Some("search"));
+		assert_eq!(labels.value("project", "anything-else"), Some("other"));
+		assert_eq!(labels.value("ticket", "OPS-12"), None);
+		assert_eq!(
+			AttributionLabels::default().value("project", "search"),
+			None
+		);
+	}
+}
@@ -34,6 +34,16 @@ pub enum Protocol {
 	Http,
 }
 
+/// Per-composition override of the trace sampling decision: `always` records the
+/// composition span even if the request is not sampled, `never` drops it even if it is.
+#[derive(serde::Serialize, serde::Deserialize, Copy, Eq, PartialEq, Clone, Debug)]
+#[serde(rename_all = "lowercase", deny_unknown_fields)]
+#[cfg_attr(feature = "schema", derive(crate::JsonSchema))]
+pub enum CompositionSampling {
+	Always,
+	Never,
+}
+
 #[derive(serde::Serialize, Clone, Debug)]
 pub struct Config {
 	pub endpoint: Option<String>,
@@ -43,6 +53,7 @@ pub struct Config {
 	pub random_sampling: Option<Arc<cel::Expression>>,
 	pub client_sampling: Option<Arc<cel::Expression>>,
 	pub path: String,
+	pub composition_sampling: Arc<HashMap<String, CompositionSampling>>,
 }
 
 mod semconv {
@@ -423,6 +423,8 @@ impl TestBind {
 				})],
 				stateful,
 				always_use_prefix: false,
+				tools_cache_ttl: None,
+				circuit_breaker: None,
 			},
 		);
 		{
@@ -470,6 +472,8 @@ impl TestBind {
 					.collect_vec(),
 				stateful,
 				always_use_prefix: false,
+				tools_cache_ttl: None,
+				circuit_breaker: None,
 			},
 		);
 		{
@@ -486,6 +490,25 @@ impl TestBind {
 		self
 	}
 
+	/// Serve `registry`'s virtual tools and compositions from every MCP backend
+	pub fn with_registry(self, registry: mcp::registry::Registry) -> Self {
+		self.with_registry_store(mcp::registry::RegistryStore::new(), registry)
+	}
+
+	/// Serve `registry` from a store the caller configured
+	pub fn with_registry_store(
+		self,
+		store: mcp::registry::RegistryStore,
+		registry: mcp::registry::Registry,
+	) -> Self {
+		store.update(registry).expect("registry should compile");
+		self
+			.pi
+			.stores
+			.set_registry(Some(mcp::registry::RegistryStoreRef::new(store)));
+		self
+	}
+
 	pub fn with_policy(self, p: TargetedPolicy) -> TestBind {
 		self.pi.stores.binds.write().insert_policy(p);
 		self
@@ -1091,6 +1091,42 @@ pub struct McpBackend {
 	pub targets: Vec<Arc<McpTarget>>,
 	pub stateful: bool,
 	pub always_use_prefix: bool,
+	/// How long upstream ListTools results are shared across sessions (disabled if unset)
+	#[serde(skip_serializing_if = "Option::is_none", with = "serde_dur_option")]
+	#[cfg_attr(feature = "schema", schemars(with = "Option<String>"))]
+	pub tools_cache_ttl: Option<Duration>,
+	/// Refuse calls to upstreams that are failing or slow (disabled if unset)
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub circuit_breaker: Option<McpCircuitBreaker>,
+}
+
+/// When an MCP backend stops sending calls to one of its upstreams
+#[apply(schema!)]
+pub struct McpCircuitBreaker {
+	/// Fraction of calls within `window` that must fail for the breaker to open
+	#[serde(default = "defaults::circuit_breaker_failure_rate")]
+	pub failure_rate: f64,
+	/// Calls needed within `window` before the failure rate is judged
+	#[serde(default = "defaults::circuit_breaker_minimum_calls")]
+	pub minimum_calls: u32,
+	/// Period over which the failure rate is measured
+	#[serde(with = "serde_dur")]
+	#[cfg_attr(feature = "schema", schemars(with = "String"))]
+	#[serde(default = "defaults::circuit_breaker_window")]
+	pub window: Duration,
+	/// Calls slower than this count as failures
+	#[serde(
+		default,
+		skip_serializing_if = "Option::is_none",
+		with = "serde_dur_option"
+	)]
+	#[cfg_attr(feature = "schema", schemars(with = "Option<String>"))]
+	pub slow_call_threshold: Option<Duration>,
+	/// How long calls are refused once the breaker opens, before a probe call is let through
+	#[serde(with = "serde_dur")]
+	#[cfg_attr(feature = "schema", schemars(with = "String"))]
+	#[serde(default = "defaults::circuit_breaker_open_duration")]
+	pub open_duration: Duration,
 }
 
 impl McpBackend {
@@ -1129,11 +1165,50 @@ pub enum McpTargetSpec {
 		args: Vec<String>,
 		#[serde(default, skip_serializing_if = "HashMap::is_empty")]
 		env: HashMap<String, String>,
+		#[serde(default)]
+		restart: StdioRestart,
 	},
 	#[serde(rename = "openapi")]
 	OpenAPI(OpenAPITarget),
 }
 
+/// How a stdio MCP server is restarted after its process exits
+#[apply(schema!)]
+pub struct StdioRestart {
+	/// Delay before the first restart attempt; it doubles after each failed attempt
+	#[serde(with = "serde_dur")]
+	#[cfg_attr(feature = "schema", schemars(with = "String"))]
+	#[serde(default = "defaults::stdio_restart_initial_backoff")]
+	pub initial_backoff: Duration,
+	#[serde(with = "serde_dur")]
+	#[cfg_attr(feature = "schema", schemars(with = "String"))]
+	#[serde(default = "defaults::stdio_restart_max_backoff")]
+	pub max_backoff: Duration,
+	/// Restart attempts in a row before the server is given up on (0 disables restarts)
+	#[serde(default = "defaults::stdio_restart_max_attempts")]
+	pub max_attempts: u32,
+}
+
+impl Default for StdioRestart {
+	fn default() -> Self {
+		StdioRestart {
+			initial_backoff: defaults::stdio_restart_initial_backoff(),
+			max_backoff: defaults::stdio_restart_max_backoff(),
+			max_attempts: defaults::stdio_restart_max_attempts(),
+		}
+	}
+}
+
+impl StdioRestart {
+	/// Delay before restart attempt `attempt` (0-based)
+	pub fn backoff(&self, attempt: u32) -> Duration {
+		self
+			.initial_backoff
+			.saturating_mul(2u32.saturating_pow(attempt))
+			.min(self.max_backoff)
+	}
+}
+
 impl McpTargetSpec {
 	pub fn backend(&self) -> Option<&SimpleBackendReference> {
 		match self {
@@ -2165,6 +2240,27 @@ pub mod defaults {
 	pub fn keepalive_time() -> Duration {
 		Duration::from_secs(180)
 	}
+	pub fn circuit_breaker_failure_rate() -> f64 {
+		0.5
+	}
+	pub fn circuit_breaker_minimum_calls() -> u32 {
+		10
+	}
+	pub fn circuit_breaker_window() -> Duration {
+		Duration::from_secs(60)
+	}
+	pub fn circuit_breaker_open_duration() -> Duration {
+		Duration::from_secs(30)
+	}
+	pub fn stdio_restart_initial_backoff() -> Duration {
+		Duration::from_millis(100)
+	}
+	pub fn stdio_restart_max_backoff() -> Duration {
+		Duration::from_secs(30)
+	}
+	pub fn stdio_restart_max_attempts() -> u32 {
+		5
+	}
 }
 
 #[cfg(test)]
@@ -736,6 +736,8 @@ impl TryFrom<&proto::agent::Backend> for BackendWithPolicies {
 						proto::agent::mcp_backend::PrefixMode::Always => true,
 						proto::agent::mcp_backend::PrefixMode::Conditional => false,
 					},
+					tools_cache_ttl: None,
+					circuit_breaker: None,
 				},
 			),
 			None => {
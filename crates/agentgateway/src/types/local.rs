@@ -10,6 +10,7 @@ use macro_rules_attribute::apply;
 use openapiv3::OpenAPI;
 
 use crate::client::Client;
+use crate::events::EventBus;
 use crate::http::auth::BackendAuth;
 use crate::http::backendtls::LocalBackendTLS;
 use crate::http::transformation_cel::LocalTransformationConfig;
@@ -21,11 +22,12 @@ use crate::types::agent::{
 	A2aPolicy, Authorization, Backend, BackendKey, BackendPolicy, BackendReference,
 	BackendWithPolicies, Bind, BindProtocol, FrontendPolicy, Listener, ListenerKey, ListenerName,
 	ListenerProtocol, ListenerSet, ListenerTarget, LocalMcpAuthentication, McpAuthentication,
-	McpBackend, McpTarget, McpTargetName, McpTargetSpec, OpenAPITarget, PathMatch, PolicyPhase,
-	PolicyTarget, PolicyType, ResourceName, Route, RouteBackendReference, RouteMatch, RouteName,
-	RouteSet, ServerTLSConfig, SimpleBackend, SimpleBackendReference, SimpleBackendWithPolicies,
-	SseTargetSpec, StreamableHTTPTargetSpec, TCPRoute, TCPRouteBackendReference, TCPRouteSet, Target,
-	TargetedPolicy, TracingConfig, TrafficPolicy, TunnelProtocol, TypedResourceName,
+	McpBackend, McpCircuitBreaker, McpTarget, McpTargetName, McpTargetSpec, OpenAPITarget, PathMatch,
+	PolicyPhase, PolicyTarget, PolicyType, ResourceName, Route, RouteBackendReference, RouteMatch,
+	RouteName, RouteSet, ServerTLSConfig, SimpleBackend, SimpleBackendReference,
+	SimpleBackendWithPolicies, SseTargetSpec, StdioRestart, StreamableHTTPTargetSpec, TCPRoute,
+	TCPRouteBackendReference, TCPRouteSet, Target, TargetedPolicy, TracingConfig, TrafficPolicy,
+	TunnelProtocol, TypedResourceName,
 };
 use crate::types::discovery::{NamespacedHostname, Service};
 use crate::types::{backend, frontend};
@@ -35,19 +37,28 @@ impl NormalizedLocalConfig {
 	pub async fn from(
 		client: client::Client,
 		gateway_name: ListenerTarget,
+		events: EventBus,
 		s: &str,
 	) -> anyhow::Result<NormalizedLocalConfig> {
 		// Avoid shell expanding the comment for schema. Probably there are better ways to do this!
 		let s = s.replace("# yaml-language-server: $schema", "#");
 		let s = shellexpand::full(&s)?;
 		let config: LocalConfig = serdes::yamlviajson::from_str(&s)?;
-		let t = convert(client, gateway_name, config).await?;
+		let t = convert(client, gateway_name, events, config).await?;
 		Ok(t)
 	}
 }
 
+#[cfg(feature = "testing")]
+use crate::mcp::registry::S3Sink;
+use crate::mcp::registry::executor::{DEFAULT_MAX_WORKERS, ExecutionJournal};
 use crate::mcp::registry::{
-	AuthConfig, RegistryClient, RegistryStore, RegistryStoreRef, parse_duration,
+	ArchiveSink, ArgumentCheckPolicy, AuthConfig, Authorizer, DEFAULT_AUTHORIZER_TIMEOUT,
+	DEFAULT_MAX_RESPONSE_BYTES, DEFAULT_MAX_RESPONSE_DEPTH, DEFAULT_USAGE_INTERVAL, EnvVarPolicy,
+	FilesystemSink, HttpAuthorizer, JsonLimits, LoadFailurePolicy, LoadSheddingConfig,
+	PayloadArchiver, RegistryClient, RegistryStore, RegistryStoreRef, SchemaDriftPolicy,
+	SignatureVerifier, SmokeTest, TlsConfig, ToolCallAuthorizer, ToolPriority, UsageAnalytics,
+	UsagePrivacy, parse_duration,
 };
 
 #[derive(Debug, Clone)]
@@ -104,9 +115,208 @@ pub struct LocalRegistryConfig {
 	/// Default: "5m"
 	#[serde(default = "default_refresh_interval")]
 	pub refresh_interval: String,
+	/// Let the polling interval of an HTTP source stretch from `refreshInterval` up to this
+	/// while the registry is unchanged; it drops back to `refreshInterval` when the registry
+	/// changes (optional, polling is fixed by default)
+	#[serde(default)]
+	pub max_refresh_interval: Option<String>,
 	/// Authentication configuration for HTTP sources (optional)
 	#[serde(default)]
 	pub auth: Option<LocalRegistryAuth>,
+	/// TLS settings for HTTPS sources: a CA bundle to trust instead of the system roots,
+	/// and a client certificate for sources that require mTLS (optional)
+	#[serde(default)]
+	pub tls: Option<LocalRegistryTls>,
+	/// Require every registry document to carry a detached Ed25519 signature at
+	/// `<source>.sig` made by one of these keys; unsigned or tampered documents are rejected
+	/// (optional)
+	#[serde(default)]
+	pub signature: Option<LocalRegistrySignature>,
+	/// Reject registry documents containing unknown fields instead of ignoring them.
+	/// Can also be enabled with the REGISTRY_STRICT=true environment variable.
+	/// Default: false
+	#[serde(default)]
+	pub strict: bool,
+	/// Number of recent executions kept per composition for the admin
+	/// `/debug/compositions` endpoint. Set to 0 to disable.
+	/// Default: 20
+	#[serde(default)]
+	pub execution_log_size: Option<usize>,
+	/// Number of recent registry reloads whose changes are kept for the admin
+	/// `/debug/registry/changes` endpoint. Set to 0 to only log them.
+	/// Default: 16
+	#[serde(default)]
+	pub changelog_size: Option<usize>,
+	/// JWT subjects allowed to raise composition debug verbosity for their own calls with the
+	/// `x-composition-verbosity` request header (e.g. `full`); other callers' headers are
+	/// ignored.
+	/// Default: none
+	#[serde(default)]
+	pub composition_verbosity_callers: Vec<String>,
+	/// Maximum number of compositions executing at once. Executions beyond this wait in a
+	/// queue for their priority lane.
+	/// Default: 64
+	#[serde(default)]
+	pub max_concurrent_compositions: Option<usize>,
+	/// Maximum number of batch (scheduled or webhook-triggered) compositions executing at
+	/// once. The remaining workers are reserved for interactive MCP calls.
+	/// Default: half of max_concurrent_compositions
+	#[serde(default)]
+	pub max_concurrent_batch_compositions: Option<usize>,
+	/// Approximate payload bytes that in-flight compositions may hold in total. Executions
+	/// that would exceed it fail with a memory limit error.
+	/// Default: unlimited
+	#[serde(default)]
+	pub max_in_flight_composition_bytes: Option<usize>,
+	/// Most results held by cache patterns; the least recently used are evicted beyond it.
+	/// Default: 10000
+	#[serde(default)]
+	pub max_cache_entries: Option<usize>,
+	/// Most idempotency keys held; the least recently used are evicted beyond it, and their
+	/// calls can then run again.
+	/// Default: 100000
+	#[serde(default)]
+	pub max_idempotency_keys: Option<usize>,
+	/// Largest backend tool response, in bytes, that is parsed as JSON. Larger responses fail
+	/// the tool call instead.
+	/// Default: 16 MiB
+	#[serde(default)]
+	pub max_backend_response_bytes: Option<usize>,
+	/// Deepest nesting of objects and arrays accepted in JSON parsed from backend tool
+	/// responses. Deeper responses fail the tool call instead.
+	/// Default: 64
+	#[serde(default)]
+	pub max_backend_response_depth: Option<usize>,
+	/// Stop exposing a virtual tool when its backend tool's schema changes incompatibly
+	/// (e.g. a hidden field becomes required), until the schemas match again. Incompatible
+	/// changes are always logged.
+	/// Default: false
+	#[serde(default)]
+	pub disable_on_schema_drift: bool,
+	/// Archive the full inputs and outputs of composition executions, keyed by
+	/// composition and trace id, for compliance or offline evaluation (optional)
+	#[serde(default)]
+	pub archive: Option<LocalArchiveConfig>,
+	/// What to do when the registry can't be loaded at startup:
+	/// - failFast: refuse to start
+	/// - lastKnownGood: serve the registry that `snapshotPath` holds from the last
+	///   successful load, and keep retrying the source
+	/// - empty: serve no virtual tools, and keep retrying the source
+	/// Default: failFast
+	#[serde(default)]
+	pub on_load_failure: LocalLoadFailurePolicy,
+	/// File every successfully loaded registry is written to, for `lastKnownGood` (optional)
+	#[serde(default)]
+	pub snapshot_path: Option<PathBuf>,
+	/// Ask an external policy service (such as OPA) whether each tool call may proceed
+	/// (optional)
+	#[serde(default)]
+	pub authorizer: Option<LocalToolCallAuthorizer>,
+	/// Restrict the environment variables registry entries may reference as `${VAR}`;
+	/// a registry referencing any other variable fails to load (optional)
+	/// Default: any variable
+	#[serde(default)]
+	pub env_vars: Option<LocalRegistryEnvVars>,
+	/// Check the arguments a composition step passes to a tool against the tool's input
+	/// schema, where the registry declares one:
+	/// - off: don't check
+	/// - warn: log mismatches and make the call
+	/// - enforce: fail the step with the mismatches instead of making the call
+	/// Default: warn
+	#[serde(default)]
+	pub step_argument_checks: LocalArgumentCheckPolicy,
+	/// Check the arguments callers pass to virtual tools and compositions against the input
+	/// schema listed for the tool, where the registry declares one:
+	/// - off: don't check
+	/// - warn: log mismatches and make the call
+	/// - enforce: convert quoted numbers and booleans, then refuse the call with every
+	///   remaining mismatch
+	/// Default: warn
+	#[serde(default)]
+	pub input_argument_checks: LocalArgumentCheckPolicy,
+	/// Refuse calls of tools the registry annotates `destructiveHint: true` unless the caller
+	/// sets `agentgateway.dev/confirmed: true` in the call's `_meta`
+	#[serde(default)]
+	pub confirm_destructive: bool,
+	/// Count calls per tool, and the distinct callers behind them, for export from
+	/// `/debug/registry/usage` (optional)
+	#[serde(default)]
+	pub usage_analytics: Option<LocalUsageAnalytics>,
+	/// Journal in-flight pipeline executions to a directory shared with a standby instance,
+	/// so the standby can take them over if this instance fails (optional)
+	#[serde(default)]
+	pub execution_journal: Option<LocalExecutionJournal>,
+	/// Stage each registry a poll or file change loads as a candidate next to the active one,
+	/// going live only when promoted through `/debug/registry/candidate` (optional)
+	#[serde(default)]
+	pub manual_promotion: Option<LocalManualPromotion>,
+	/// Refuse calls to upstream targets whose recent p95 latency is over the threshold for
+	/// the calling tool's priority, so low-priority traffic backs off a struggling backend
+	/// first (optional)
+	#[serde(default)]
+	pub load_shedding: Option<LocalLoadShedding>,
+}
+
+#[apply(schema_de!)]
+#[derive(Default)]
+pub enum LocalArgumentCheckPolicy {
+	Off,
+	#[default]
+	Warn,
+	Enforce,
+}
+
+#[apply(schema_de!)]
+#[derive(Default)]
+pub enum LocalLoadFailurePolicy {
+	#[default]
+	FailFast,
+	LastKnownGood,
+	Empty,
+}
+
+/// Payload archival configuration for registry compositions
+#[apply(schema_de!)]
+pub struct LocalArchiveConfig {
+	/// Where archived payloads are written
+	pub sink: LocalArchiveSink,
+	/// Fraction (0.0-1.0) of executions to archive, by composition name. "*" matches
+	/// compositions not listed explicitly.
+	/// Default: every execution of every composition
+	#[serde(default)]
+	pub compositions: HashMap<String, f64>,
+	/// How long archived payloads are kept, as a duration string like "30d" or "720h".
+	/// Default: forever
+	#[serde(default)]
+	pub retention: Option<String>,
+}
+
+/// Destination for archived composition payloads
+#[apply(schema_de!)]
+pub enum LocalArchiveSink {
+	/// One JSON file per execution under a local directory
+	Filesystem {
+		/// Root directory of the archive
+		path: PathBuf,
+	},
+	/// One object per execution in an S3-compatible bucket. Retention is enforced by a
+	/// bucket lifecycle rule on the `retention-days` object tag.
+	#[serde(rename_all = "camelCase")]
+	S3 {
+		/// Endpoint URL, e.g. https://s3.us-east-1.amazonaws.com
+		endpoint: String,
+		bucket: String,
+		region: String,
+		/// Prefix prepended to object keys
+		#[serde(default)]
+		prefix: Option<String>,
+		/// Access key id. Default: the AWS_ACCESS_KEY_ID environment variable
+		#[serde(default)]
+		access_key_id: Option<String>,
+		/// Secret access key. Default: the AWS_SECRET_ACCESS_KEY environment variable
+		#[serde(default)]
+		secret_access_key: Option<String>,
+	},
 }
 
 fn default_refresh_interval() -> String {
@@ -131,6 +341,152 @@ pub enum LocalRegistryAuth {
 	},
 }
 
+/// TLS settings for HTTPS registry sources
+#[apply(schema_de!)]
+pub struct LocalRegistryTls {
+	/// PEM client certificate chain, presented when the source requires mTLS
+	#[serde(default)]
+	pub cert: Option<PathBuf>,
+	/// PEM private key of `cert`
+	#[serde(default)]
+	pub key: Option<PathBuf>,
+	/// PEM CA bundle to trust instead of the system roots
+	#[serde(default)]
+	pub root: Option<PathBuf>,
+}
+
+/// Trusted signers of registry documents
+#[apply(schema_de!)]
+pub struct LocalRegistrySignature {
+	/// Ed25519 public keys, as PEM or base64 (raw 32-byte key or DER SubjectPublicKeyInfo)
+	pub public_keys: Vec<String>,
+}
+
+/// Environment variables registry entries may reference
+#[apply(schema_de!)]
+pub struct LocalRegistryEnvVars {
+	/// Name prefixes of allowed variables, e.g. `REGISTRY_`
+	#[serde(default)]
+	pub prefixes: Vec<String>,
+	/// Names of individually allowed variables
+	#[serde(default)]
+	pub names: Vec<String>,
+}
+
+/// External authorizer consulted before every tool call
+#[apply(schema_de!)]
+pub struct LocalToolCallAuthorizer {
+	/// URL the call is POSTed to as `{"input": ...}`, e.g. an OPA decision endpoint like
+	/// http://opa:8181/v1/data/mcp/tools/allow
+	pub url: String,
+	/// Bearer token for the policy service (supports ${ENV_VAR} substitution)
+	#[serde(default)]
+	pub bearer: Option<String>,
+	/// Time allowed for a decision, as a duration string like "500ms".
+	/// Default: "500ms"
+	#[serde(default)]
+	pub timeout: Option<String>,
+	/// Allow calls when the authorizer fails or times out, instead of denying them.
+	/// Default: false
+	#[serde(default)]
+	pub fail_open: bool,
+}
+
+/// Journal of in-flight executions, shared between a primary and its standby
+#[apply(schema_de!)]
+pub struct LocalExecutionJournal {
+	/// Directory both instances can read and write, such as a replicated volume
+	pub path: PathBuf,
+	/// Name of this instance, distinct from its standby's
+	pub node: String,
+	/// How long an execution is kept for a retry to resume it, as a duration string like "24h".
+	/// Default: "24h"
+	#[serde(default)]
+	pub retention: Option<String>,
+}
+
+/// Manual promotion of reloaded registries
+#[apply(schema_de!)]
+pub struct LocalManualPromotion {
+	/// Composition calls a candidate must pass before it can be promoted
+	#[serde(default)]
+	pub smoke_tests: Vec<LocalSmokeTest>,
+}
+
+/// A composition call made against a candidate registry, answered with canned backend
+/// responses
+#[apply(schema_de!)]
+pub struct LocalSmokeTest {
+	/// Composition to call
+	pub tool: String,
+	/// Default: {}
+	#[serde(default)]
+	pub arguments: Option<serde_json::Value>,
+	/// Result of each tool the composition calls, by tool name
+	#[serde(default)]
+	pub responses: HashMap<String, serde_json::Value>,
+	/// Value the result must match; an object matches results with at least its fields
+	/// (optional)
+	#[serde(default)]
+	pub expect: Option<serde_json::Value>,
+}
+
+/// Tool usage analytics
+#[apply(schema_de!)]
+pub struct LocalUsageAnalytics {
+	/// Length of a reporting window, as a duration string like "1h".
+	/// Default: "1h"
+	#[serde(default)]
+	pub interval: Option<String>,
+	/// Report noisy, thresholded counts that reveal nothing about any one caller (optional)
+	#[serde(default)]
+	pub privacy: Option<LocalUsagePrivacy>,
+}
+
+/// Load shedding on upstream latency
+#[apply(schema_de!)]
+pub struct LocalLoadShedding {
+	/// p95 latency, as a duration string like "500ms", above which a target's calls of each
+	/// priority are shed. Priorities without a threshold are never shed.
+	pub p95_thresholds: LocalPriorityThresholds,
+	/// How far back the calls a target's p95 is computed over go, as a duration string.
+	/// Default: "60s"
+	#[serde(default)]
+	pub window: Option<String>,
+	/// Calls a target needs within the window before it can be shed.
+	/// Default: 20
+	#[serde(default)]
+	pub minimum_calls: Option<usize>,
+}
+
+/// A latency threshold per tool priority
+#[apply(schema_de!)]
+pub struct LocalPriorityThresholds {
+	#[serde(default)]
+	pub high: Option<String>,
+	#[serde(default)]
+	pub normal: Option<String>,
+	#[serde(default)]
+	pub low: Option<String>,
+}
+
+/// Private aggregation of tool usage
+#[apply(schema_de!)]
+pub struct LocalUsagePrivacy {
+	/// Tools with fewer distinct callers in a window are left out of its report.
+	/// Default: 10
+	#[serde(default)]
+	pub min_callers: Option<u64>,
+	/// Privacy budget of each count; smaller values add more noise.
+	/// Default: 1.0
+	#[serde(default)]
+	pub epsilon: Option<f64>,
+	/// Most calls counted per caller, tool and window.
+	/// Default: 20
+	#[serde(default)]
+	pub max_calls_per_caller: Option<u32>,
+}
+
 #[apply(schema_de!)]
 struct LocalBind {
 	port: u16,
@@ -411,7 +767,17 @@ impl LocalBackend {
 								path: path.clone(),
 							})
 						},
-						LocalMcpTargetSpec::Stdio { cmd, args, env } => McpTargetSpec::Stdio { cmd, args, env },
+						LocalMcpTargetSpec::Stdio {
+							cmd,
+							args,
+							env,
+							restart,
+						} => McpTargetSpec::Stdio {
+							cmd,
+							args,
+							env,
+							restart,
+						},
 						LocalMcpTargetSpec::OpenAPI { backend, schema } => {
 							let (backend, _, tls) = backend.process()?;
 							let (bref, be) = mcp_to_simple_backend_and_ref(local_name(name.clone()), backend);
@@ -441,6 +807,8 @@ impl LocalBackend {
 						McpPrefixMode::Always => true,
 						McpPrefixMode::Conditional => false,
 					}),
+					tools_cache_ttl: tgt.tools_cache_ttl,
+					circuit_breaker: tgt.circuit_breaker.clone(),
 				};
 				backends.push(Backend::MCP(name, m).into());
 				backends
@@ -495,6 +863,21 @@ pub struct LocalMcpBackend {
 	pub stateful_mode: McpStatefulMode,
 	#[serde(default, skip_serializing_if = "Option::is_none")]
 	pub prefix_mode: Option<McpPrefixMode>,
+	/// Share upstream ListTools results across sessions for this long. Concurrent sessions
+	/// that miss the cache wait for one ListTools per upstream instead of each sending their
+	/// own. Only enable this when the tool list does not depend on the caller.
+	/// Initialize is never shared, since each session's initialize opens its own upstream
+	/// sessions.
+	#[serde(
+		default,
+		skip_serializing_if = "Option::is_none",
+		with = "serde_dur_option"
+	)]
+	#[cfg_attr(feature = "schema", schemars(with = "Option<String>"))]
+	pub tools_cache_ttl: Option<Duration>,
+	/// Stop sending calls to an upstream while too many of its recent calls fail or are slow
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub circuit_breaker: Option<McpCircuitBreaker>,
 }
 
 #[apply(schema_de!)]
@@ -566,6 +949,9 @@ pub enum LocalMcpTargetSpec {
 		args: Vec<String>,
 		#[serde(default, skip_serializing_if = "HashMap::is_empty")]
 		env: HashMap<String, String>,
+		/// Restart the server when its process exits
+		#[serde(default)]
+		restart: StdioRestart,
 	},
 	#[serde(rename = "openapi")]
 	OpenAPI {
@@ -956,6 +1342,7 @@ struct TCPFilterOrPolicy {
 async fn convert(
 	client: client::Client,
 	gateway: ListenerTarget,
+	events: EventBus,
 	i: LocalConfig,
 ) -> anyhow::Result<NormalizedLocalConfig> {
 	let LocalConfig {
@@ -1049,10 +1436,150 @@ async fn convert(
 				LocalRegistryAuth::Basic { username, password } => AuthConfig::Basic { username, password },
 			});
 
-			let registry_client = RegistryClient::from_uri(&reg_config.source, refresh_interval, auth)
-				.map_err(|e| anyhow!("Failed to create registry client: {}", e))?;
+			let strict = match std::env::var("REGISTRY_STRICT") {
+				Ok(v) => v
+					.parse::<bool>()
+					.map_err(|e| anyhow!("invalid env var REGISTRY_STRICT={} ({})", v, e))?,
+				Err(_) => false,
+			};
+			let mut registry_client =
+				RegistryClient::from_uri(&reg_config.source, refresh_interval, auth)
+					.map_err(|e| anyhow!("Failed to create registry client: {}", e))?
+					.with_strict(reg_config.strict || strict);
+			if let Some(signature) = reg_config.signature {
+				let verifier = SignatureVerifier::new(&signature.public_keys)
+					.map_err(|e| anyhow!("Invalid registry signature keys: {}", e))?;
+				registry_client = registry_client.with_signature_verifier(verifier);
+			}
+			if let Some(max) = reg_config.max_refresh_interval {
+				let max = parse_duration(&max)
+					.map_err(|e| anyhow!("Invalid registry max refresh interval: {}", e))?;
+				registry_client = registry_client
+					.with_max_refresh_interval(max)
+					.map_err(|e| anyhow!("Invalid registry max refresh interval: {}", e))?;
+			}
+			if let Some(tls) = reg_config.tls {
+				let tls = TlsConfig {
+					cert: tls.cert.map(fs_err::read).transpose()?,
+					key: tls.key.map(fs_err::read).transpose()?,
+					root: tls.root.map(fs_err::read).transpose()?,
+				};
+				registry_client = registry_client
+					.with_tls(tls)
+					.map_err(|e| anyhow!("Invalid registry TLS configuration: {}", e))?;
+			}
 
-			let store = RegistryStore::new().with_client(registry_client);
+			let mut store = RegistryStore::new()
+				.with_client(registry_client)
+				.with_events(events.clone());
+			if let Some(size) = reg_config.execution_log_size {
+				store = store.with_execution_log_capacity(size);
+			}
+			if let Some(size) = reg_config.changelog_size {
+				store = store.with_changelog_capacity(size);
+			}
+			if !reg_config.composition_verbosity_callers.is_empty() {
+				store = store.with_verbosity_callers(reg_config.composition_verbosity_callers);
+			}
+			if reg_config.max_concurrent_compositions.is_some()
+				|| reg_config.max_concurrent_batch_compositions.is_some()
+			{
+				let workers = reg_config
+					.max_concurrent_compositions
+					.unwrap_or(DEFAULT_MAX_WORKERS);
+				let batch_workers = reg_config
+					.max_concurrent_batch_compositions
+					.unwrap_or(workers / 2);
+				store = store.with_worker_pool(workers, batch_workers);
+			}
+			if let Some(bytes) = reg_config.max_in_flight_composition_bytes {
+				store = store.with_memory_limit(bytes);
+			}
+			match reg_config.max_cache_entries {
+				Some(0) => bail!("registry maxCacheEntries must be positive"),
+				Some(entries) => store = store.with_max_cache_entries(entries),
+				None => {},
+			}
+			match reg_config.max_idempotency_keys {
+				Some(0) => bail!("registry maxIdempotencyKeys must be positive"),
+				Some(keys) => store = store.with_max_idempotency_keys(keys),
+				None => {},
+			}
+			if reg_config.max_backend_response_bytes.is_some()
+				|| reg_config.max_backend_response_depth.is_some()
+			{
+				store = store.with_json_limits(JsonLimits {
+					max_bytes: reg_config
+						.max_backend_response_bytes
+						.unwrap_or(DEFAULT_MAX_RESPONSE_BYTES),
+					max_depth: reg_config
+						.max_backend_response_depth
+						.unwrap_or(DEFAULT_MAX_RESPONSE_DEPTH),
+				});
+			}
+			if let Some(env_vars) = reg_config.env_vars {
+				store = store.with_env_policy(EnvVarPolicy::Allowlist {
+					prefixes: env_vars.prefixes,
+					names: env_vars.names,
+				});
+			}
+			if reg_config.disable_on_schema_drift {
+				store = store.with_schema_drift_policy(SchemaDriftPolicy::Disable);
+			}
+			let argument_checks = |policy: &LocalArgumentCheckPolicy| match policy {
+				LocalArgumentCheckPolicy::Off => ArgumentCheckPolicy::Off,
+				LocalArgumentCheckPolicy::Warn => ArgumentCheckPolicy::Warn,
+				LocalArgumentCheckPolicy::Enforce => ArgumentCheckPolicy::Enforce,
+			};
+			store = store
+				.with_argument_checks(argument_checks(&reg_config.step_argument_checks))
+				.with_input_checks(argument_checks(&reg_config.input_argument_checks));
+			store = store.with_destructive_confirmation(reg_config.confirm_destructive);
+			let archiver = reg_config.archive.map(build_archiver).transpose()?;
+			if let Some(archiver) = &archiver {
+				store = store.with_archiver(archiver.clone());
+			}
+			let on_load_failure = match reg_config.on_load_failure {
+				LocalLoadFailurePolicy::FailFast => LoadFailurePolicy::FailFast,
+				LocalLoadFailurePolicy::LastKnownGood => LoadFailurePolicy::LastKnownGood,
+				LocalLoadFailurePolicy::Empty => LoadFailurePolicy::Empty,
+			};
+			if on_load_failure == LoadFailurePolicy::LastKnownGood && reg_config.snapshot_path.is_none() {
+				bail!("registry onLoadFailure: lastKnownGood requires a snapshotPath");
+			}
+			store = store.with_load_failure_policy(on_load_failure);
+			if let Some(path) = reg_config.snapshot_path {
+				store = store.with_snapshot_path(path);
+			}
+			if let Some(authorizer) = reg_config.authorizer {
+				store = store.with_authorizer(Arc::new(build_authorizer(authorizer, client.clone())?));
+			}
+			if let Some(usage) = reg_config.usage_analytics {
+				store = store.with_usage_analytics(Arc::new(build_usage_analytics(usage)?));
+			}
+			let journal = reg_config
+				.execution_journal
+				.map(build_execution_journal)
+				.transpose()?;
+			if let Some(journal) = &journal {
+				store = store.with_execution_journal(journal.clone());
+			}
+			if let Some(promotion) = reg_config.manual_promotion {
+				let smoke_tests = promotion
+					.smoke_tests
+					.into_iter()
+					.map(|t| SmokeTest {
+						tool: t.tool,
+						arguments: t.arguments.unwrap_or_else(|| serde_json::json!({})),
+						responses: t.responses,
+						expect: t.expect,
+					})
+					.collect();
+				store = store.with_manual_promotion(smoke_tests);
+			}
+			if let Some(shedding) = reg_config.load_shedding {
+				store = store.with_load_shedding(build_load_shedding(shedding)?);
+			}
 
 			// Wrap in RegistryStoreRef for Arc handling
 			let store_ref = RegistryStoreRef::new(store);
@@ -1075,8 +1602,14 @@ async fn convert(
 
 			if is_file_source {
 				let _ = Arc::clone(store_ref.inner()).spawn_file_watcher();
-			} else {
-				Arc::clone(store_ref.inner()).spawn_refresh_loop();
+			}
+			// Polls HTTP sources, and file sources while a degraded startup recovers
+			Arc::clone(store_ref.inner()).spawn_refresh_loop();
+			if let Some(archiver) = archiver {
+				archiver.spawn_retention();
+			}
+			if let Some(journal) = journal {
+				journal.spawn_retention();
 			}
 
 			Some(store_ref)
@@ -1094,6 +1627,161 @@ async fn convert(
 	})
 }
 
+fn build_authorizer(
+	config: LocalToolCallAuthorizer,
+	client: Client,
+) -> anyhow::Result<ToolCallAuthorizer> {
+	let timeout = config
+		.timeout
+		.as_deref()
+		.map(|t| parse_duration(t).map_err(|e| anyhow!("Invalid authorizer timeout: {}", e)))
+		.transpose()?
+		.unwrap_or(DEFAULT_AUTHORIZER_TIMEOUT);
+	Ok(
+		ToolCallAuthorizer::new(http_authorizer(&config, client)?)
+			.with_timeout(timeout)
+			.with_fail_open(config.fail_open),
+	)
+}
+
+fn build_execution_journal(config: LocalExecutionJournal) -> anyhow::Result<Arc<ExecutionJournal>> {
+	let mut journal = ExecutionJournal::new(config.path, config.node);
+	if let Some(retention) = config.retention {
+		journal = journal.with_retention(
+			parse_duration(&retention).map_err(|e| anyhow!("Invalid journal retention: {}", e))?,
+		);
+	}
+	Ok(Arc::new(journal))
+}
+
+fn build_load_shedding(config: LocalLoadShedding) -> anyhow::Result<LoadSheddingConfig> {
+	let thresholds = config.p95_thresholds;
+	let thresholds = [
+		(ToolPriority::High, thresholds.high),
+		(ToolPriority::Normal, thresholds.normal),
+		(ToolPriority::Low, thresholds.low),
+	]
+	.into_iter()
+	.filter_map(|(priority, threshold)| {
+		let threshold = parse_duration(&threshold?)
+			.map_err(|e| anyhow!("Invalid {} priority p95 threshold: {}", priority, e));
+		Some(threshold.map(|t| (priority, t)))
+	})
+	.collect::<anyhow::Result<HashMap<_, _>>>()?;
+	if thresholds.is_empty() {
+		bail!("load shedding needs a p95 threshold for at least one priority");
+	}
+	let mut shedding = LoadSheddingConfig::new(thresholds);
+	if let Some(window) = config.window {
+		shedding.window =
+			parse_duration(&window).map_err(|e| anyhow!("Invalid load shedding window: {}", e))?;
+		if shedding.window.is_zero() {
+			bail!("load shedding window must be positive");
+		}
+	}
+	if let Some(calls) = config.minimum_calls {
+		shedding.minimum_calls = calls.max(1);
+	}
+	Ok(shedding)
+}
+
+fn build_usage_analytics(config: LocalUsageAnalytics) -> anyhow::Result<UsageAnalytics> {
+	let interval = config
+		.interval
+		.as_deref()
+		.map(|i| parse_duration(i).map_err(|e| anyhow!("Invalid usage analytics interval: {}", e)))
+		.transpose()?
+		.unwrap_or(DEFAULT_USAGE_INTERVAL);
+	if interval.is_zero() {
+		bail!("usage analytics interval must be positive");
+	}
+	let privacy = config
+		.privacy
+		.map(|p| {
+			let default = UsagePrivacy::default();
+			let privacy = UsagePrivacy {
+				min_callers: p.min_callers.unwrap_or(default.min_callers),
+				epsilon: p.epsilon.unwrap_or(default.epsilon),
+				max_calls_per_caller: p
+					.max_calls_per_caller
+					.unwrap_or(default.max_calls_per_caller),
+			};
+			if !(privacy.epsilon.is_finite() && privacy.epsilon > 0.0) {
+				bail!("usage analytics epsilon must be positive");
+			}
+			if privacy.max_calls_per_caller == 0 {
+				bail!("usage analytics maxCallsPerCaller must be positive");
+			}
+			Ok(privacy)
+		})
+		.transpose()?;
+	Ok(UsageAnalytics::new(interval, privacy))
+}
+
+fn http_authorizer(
+	config: &LocalToolCallAuthorizer,
+	client: Client,
+) -> anyhow::Result<Arc<dyn Authorizer>> {
+	let url = config
+		.url
+		.parse()
+		.map_err(|e| anyhow!("Invalid authorizer url {}: {}", config.url, e))?;
+	let mut authorizer = HttpAuthorizer::new(client, url);
+	if let Some(bearer) = &config.bearer {
+		authorizer = authorizer.with_bearer(bearer);
+	}
+	Ok(Arc::new(authorizer))
+}
+
+fn build_archiver(config: LocalArchiveConfig) -> anyhow::Result<Arc<PayloadArchiver>> {
+	let retention = config
+		.retention
+		.map(|r| parse_duration(&r).map_err(|e| anyhow!("Invalid archive retention: {}", e)))
+		.transpose()?;
+	let sink: Arc<dyn ArchiveSink> = match config.sink {
+		LocalArchiveSink::Filesystem { path } => Arc::new(FilesystemSink::new(path)),
+		#[cfg(feature = "testing")]
+		LocalArchiveSink::S3 {
+			endpoint,
+			bucket,
+			region,
+			prefix,
+			access_key_id,
+			secret_access_key,
+		} => {
+			let access_key_id = access_key_id
+				.or_else(|| std::env::var("AWS_ACCESS_KEY_ID").ok())
+				.ok_or_else(|| anyhow!("S3 archive requires accessKeyId or AWS_ACCESS_KEY_ID"))?;
+			let secret_access_key = secret_access_key
+				.or_else(|| std::env::var("AWS_SECRET_ACCESS_KEY").ok())
+				.ok_or_else(|| anyhow!("S3 archive requires secretAccessKey or AWS_SECRET_ACCESS_KEY"))?;
+			let credentials = aws_credential_types::Credentials::builder()
+				.access_key_id(access_key_id)
+				.secret_access_key(secret_access_key)
+				.provider_name("archive")
+				.build();
+			let mut sink = S3Sink::new(&endpoint, &bucket, &region, credentials);
+			if let Some(prefix) = prefix {
+				sink = sink.with_prefix(&prefix);
+			}
+			if let Some(retention) = retention {
+				sink = sink.with_retention(retention);
+			}
+			Arc::new(sink)
+		},
+		#[cfg(not(feature = "testing"))]
+		LocalArchiveSink::S3 { .. } => bail!("S3 archival requires the 'testing' feature"),
+	};
+	let mut archiver = PayloadArchiver::new(sink);
+	if !config.compositions.is_empty() {
+		archiver = archiver.with_sample_rates(config.compositions);
+	}
+	if let Some(retention) = retention {
+		archiver = archiver.with_retention(retention);
+	}
+	Ok(Arc::new(archiver))
+}
+
 fn detect_bind_protocol(listeners: &ListenerSet) -> BindProtocol {
 	if listeners
 		.iter()
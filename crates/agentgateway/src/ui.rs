@@ -109,6 +109,7 @@ async fn write_config(
 	if let Err(e) = crate::types::local::NormalizedLocalConfig::from(
 		app.client.clone(),
 		app.state.gateway(),
+		crate::events::EventBus::default(),
 		yaml_content.as_str(),
 	)
 	.await
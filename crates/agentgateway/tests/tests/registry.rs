@@ -396,14 +396,15 @@ async fn test_mixed_registry() -> anyhow::Result<()> {
 		"multi_search",
 		PatternSpec::ScatterGather(ScatterGatherSpec {
 			targets: vec![
-				ScatterTarget::Tool("search_web".to_string()),
-				ScatterTarget::Tool("search_arxiv".to_string()),
+				ScatterTarget::tool("search_web"),
+				ScatterTarget::tool("search_arxiv"),
 			],
 			aggregation: AggregationStrategy {
 				ops: vec![AggregationOp::Flatten(true)],
 			},
 			timeout_ms: Some(5000),
 			fail_fast: false,
+			sample: None,
 		}),
 	);
 
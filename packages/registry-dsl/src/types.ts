@@ -279,6 +279,9 @@ export interface ToolDefinition {
   /** Optional description (for source-based, can inherit from backend) */
   description?: string;
 
+  /** Per-locale description overrides, keyed by language tag (e.g. "fr", "pt-BR") */
+  descriptions?: Record<string, string>;
+
   /** Tool implementation - either source-based or composition */
   implementation: ToolImplementation;
 
@@ -288,13 +291,102 @@ export interface ToolDefinition {
   /** Output transformation (applies to both virtual tools and compositions) */
   outputTransform?: OutputTransform;
 
+  /** Wrap composition output in a {data, errors, meta} envelope (raw output by default) */
+  resultEnvelope?: boolean;
+
+  /** Estimated cost of each call, accumulated per composition execution (source tools only) */
+  cost?: CostModel;
+
+  /** Hand repeats of an identical call from the same caller the earlier call's result */
+  debounce?: Debounce;
+
+  /** Replay the result of an earlier call with the same caller-supplied idempotency key (compositions only) */
+  idempotency?: Idempotency;
+
+  /** Priority the tool's calls are shed at while an upstream is over its latency SLO; a composition's calls use the composition's priority (default normal) */
+  priority?: ToolPriority;
+
   /** Semantic version of this tool definition */
   version?: string;
 
+  /** Sample calls, advertised in the input schema's examples and by describe_tool */
+  examples?: ToolExample[];
+
+  /** Behavior hints advertised to clients (e.g. whether the tool is read-only) */
+  annotations?: ToolAnnotations;
+
   /** Arbitrary metadata (owner, classification, etc.) */
   metadata?: Record<string, unknown>;
 }
 
+/** ToolAnnotations are hints about a tool's behavior, advertised in its MCP tool annotations */
+export interface ToolAnnotations {
+  /** The tool doesn't modify its environment */
+  readOnlyHint?: boolean;
+  /** The tool may delete or overwrite, rather than only add */
+  destructiveHint?: boolean;
+  /** Repeating a call with the same arguments has no further effect */
+  idempotentHint?: boolean;
+  /** The tool interacts with an open set of external entities, such as the web */
+  openWorldHint?: boolean;
+}
+
+/** ToolExample is a sample call of a tool */
+export interface ToolExample {
+  /** What the example demonstrates */
+  description?: string;
+
+  /** Arguments of the call */
+  input: Record<string, unknown>;
+
+  /** Result of the call */
+  output?: unknown;
+}
+
+/** Debounce is a window in which identical calls from one caller share a single invocation */
+export interface Debounce {
+  /** How long a successful result is handed to repeats of the call, in seconds */
+  windowSeconds: number;
+}
+
+/** Idempotency keys accepted from callers of a composition (via `_meta`) */
+export interface Idempotency {
+  /** How long a key is remembered after its call succeeded, in seconds */
+  ttlSeconds: number;
+  /** What a replay gets */
+  onDuplicate?: OnDuplicate;
+}
+
+/** ToolPriority orders which calls are shed first from a slow upstream */
+export type ToolPriority = 'high' | 'normal' | 'low';
+
+/** CostModel estimates the cost of a call to a tool */
+export type CostModel =
+  | { static: number }
+  | { bytes: BytesCost }
+  | { reported: ReportedCost };
+
+/** BytesCost prices a call by payload size */
+export interface BytesCost {
+  /** Fixed cost per call */
+  perCall?: number;
+
+  /** Cost per KiB of serialized arguments */
+  perKibIn?: number;
+
+  /** Cost per KiB of serialized result */
+  perKibOut?: number;
+}
+
+/** ReportedCost reads the cost from the tool's result */
+export interface ReportedCost {
+  /** JSONPath to a number in the result (e.g. "$.usage.cost") */
+  path: string;
+
+  /** Cost when the result doesn't contain one */
+  fallback?: number;
+}
+
 /** Tool implementation type */
 export type ToolImplementation =
   | { source: SourceTool }
@@ -305,23 +397,104 @@ export interface SourceTool {
   /** Server name (v2: references ServerDefinition.name) */
   server: string;
 
+  /**
+   * Servers serving the same tool that calls fail over to, in order, when a call to the
+   * previous one fails before a response arrives or its circuit breaker is open
+   */
+  fallbackServers?: string[];
+
   /** Original tool name on that server */
   tool: string;
 
   /** Fields to inject at call time (supports ${ENV_VAR} substitution) */
   defaults?: Record<string, unknown>;
 
+  /** Defaults for particular callers, keyed by JWT subject; applied over defaults */
+  callerDefaults?: Record<string, Record<string, unknown>>;
+
   /** Fields to remove from schema (hidden from agents) */
   hideFields?: string[];
 
+  /**
+   * Refuse calls passing arguments the effective input schema doesn't list, hidden fields
+   * included, instead of forwarding them to the backend
+   */
+  strictArgs?: boolean;
+
   /** Server version constraint (v2) */
   serverVersion?: string;
+
+  /** Follow the backend's cursor pagination, so one call returns every page */
+  pagination?: Pagination;
+
+  /**
+   * Treat the tool as submitting a job, and poll a status tool until the job finishes, so
+   * one call returns the job's result
+   */
+  jobPolling?: JobPolling;
+}
+
+/** Cursor pagination of a list-style backend tool */
+export interface Pagination {
+  /** Request argument the cursor is sent in */
+  cursorField: string;
+
+  /** Path to the next page's cursor in a response (e.g. "$.nextCursor") */
+  cursorPath: string;
+
+  /** Path to the array of items in a response (e.g. "$.items") */
+  itemsPath: string;
+
+  /** Request argument the page size is sent in, unless the caller sets it */
+  pageSizeField?: string;
+
+  /** Page size to request (requires pageSizeField) */
+  pageSize?: number;
+
+  /** Most pages fetched per call (default: 10) */
+  maxPages?: number;
+}
+
+/** Polling of a job-style backend tool until its job finishes */
+export interface JobPolling {
+  /** Tool on the same server that reports a job's status */
+  statusTool: string;
+
+  /** JSONPath to the job id in the submit response (e.g. "$.jobId") */
+  jobIdPath: string;
+
+  /** Argument the job id is passed to the status tool in */
+  jobIdField: string;
+
+  /** Matches a status response once the job has finished */
+  completeWhen: FieldPredicate;
+
+  /** Matches a status response once the job has failed, failing the call */
+  failWhen?: FieldPredicate;
+
+  /** JSONPath to the result in the finished job's status response (default: the whole response) */
+  resultPath?: string;
+
+  /** Delay before each status call (default: 1000ms) */
+  intervalMs?: Duration;
+
+  /** Longest the job is waited for (default: 300000ms) */
+  maxWaitMs?: Duration;
 }
 
 // =============================================================================
 // Pattern Specifications
 // =============================================================================
 
+/**
+ * Duration in the unit of the field name (`timeoutMs`, `ttlSeconds`), or a string with
+ * units such as "5s", "2m" or "1m30s"
+ */
+export type Duration = number | string;
+
+/** Fraction between 0 and 1, or a percent string such as "25%" */
+export type Percentage = number | string;
+
 /** PatternSpec defines a composition pattern */
 export type PatternSpec =
   // Stateless patterns
@@ -330,6 +503,7 @@ export type PatternSpec =
   | { filter: FilterSpec }
   | { schemaMap: SchemaMapSpec }
   | { mapEach: MapEachSpec }
+  | { rank: RankSpec }
   // Stateful patterns
   | { retry: RetrySpec }
   | { timeout: TimeoutSpec }
@@ -358,13 +532,145 @@ export interface PipelineStep {
 
   /** Input binding for this step */
   input?: DataBinding;
+
+  /** Retry the step's operation when it fails */
+  retry?: StepRetry;
+
+  /** Cache the step's output */
+  cache?: StepCache;
+}
+
+/** Inline caching of a pipeline step's output, kept per step */
+export interface StepCache {
+  /** TTL in seconds */
+  ttlSeconds: Duration;
+
+  /** JSONPaths into the step input to derive the cache key (default: the whole input) */
+  keyPaths?: string[];
+}
+
+/** Inline retry for a pipeline step or scatter-gather target */
+export interface StepRetry {
+  /** Total attempts including the first */
+  maxAttempts: number;
+
+  /** Delay between attempts in milliseconds (default: 0) */
+  backoffMs?: Duration;
 }
 
 /** StepOperation defines what a step does */
 export type StepOperation =
   | { tool: ToolCall }
   | { pattern: PatternSpec }
-  | { agent: AgentCall };
+  | { composition: CompositionCall }
+  | { agent: AgentCall }
+  | { assert: AssertSpec }
+  | { script: ScriptSpec }
+  | { sample: SampleSpec }
+  | { patch: PatchSpec }
+  | { constant: unknown };
+
+/** AssertSpec checks the step input and passes it through unchanged */
+export interface AssertSpec {
+  /** Predicate the step input must satisfy */
+  predicate: FieldPredicate;
+
+  /** Error message when the predicate doesn't hold (describes the predicate by default) */
+  message?: string;
+
+  /** `fail` stops the pipeline (default); `warn` logs, reports in the result envelope and continues */
+  severity?: 'fail' | 'warn';
+}
+
+/**
+ * ScriptSpec transforms the step input with a sandboxed WebAssembly module.
+ * The module gets no imports and must export `memory`, `alloc(len: i32) -> i32`
+ * and the entrypoint `(ptr: i32, len: i32) -> i64`, which returns its output JSON
+ * as `ptr << 32 | len`.
+ */
+export interface ScriptSpec {
+  /** Base64-encoded WebAssembly module */
+  wasm: string;
+
+  /** Exported function to call (default: `transform`) */
+  entrypoint?: string;
+
+  /** Resource limits for a single run */
+  limits?: ScriptLimits;
+}
+
+/** ScriptLimits bounds a script run; exceeding one fails the step */
+export interface ScriptLimits {
+  /** Execution budget in fuel units, about one per instruction (default: 10000000) */
+  fuel?: number;
+
+  /** Largest linear memory in bytes (default: 16 MiB) */
+  memoryBytes?: number;
+
+  /** Largest output in bytes (default: 1 MiB) */
+  outputBytes?: number;
+}
+
+/**
+ * SampleSpec asks the connected client's LLM for a completion through MCP
+ * sampling. The step input is sent as JSON after the prompt; the client must
+ * declare the sampling capability.
+ */
+export interface SampleSpec {
+  /** Instruction for the model */
+  prompt: string;
+
+  /** System prompt for the completion */
+  systemPrompt?: string;
+
+  /** Largest completion to request (default: 1024) */
+  maxTokens?: number;
+
+  /** Sampling temperature */
+  temperature?: number;
+
+  /** Preferred models or model families, most preferred first */
+  modelHints?: string[];
+
+  /** Parse the completion as JSON instead of returning `{text, model}` */
+  json?: boolean;
+
+  /** How long to wait for the client in milliseconds (default: 60000) */
+  timeoutMs?: Duration;
+
+  /** Largest completion to request (default: 1024) */
+  maxTokens?: number;
+}
+
+/**
+ * PatchSpec edits the step input with an RFC 6902 JSON Patch or an RFC 7386
+ * merge patch; exactly one must be set. Strings in the patch may hold `{name}`
+ * placeholders filled from `vars`.
+ */
+export interface PatchSpec {
+  /** RFC 6902 operations, applied in order */
+  jsonPatch?: JsonPatchOperation[];
+
+  /** RFC 7386 merge patch document (`null` deletes a field) */
+  mergePatch?: unknown;
+
+  /** Placeholder name -> JSONPath into the step input */
+  vars?: Record<string, string>;
+}
+
+/** JsonPatchOperation is a single RFC 6902 operation */
+export interface JsonPatchOperation {
+  op: 'add' | 'remove' | 'replace' | 'move' | 'copy' | 'test';
+
+  /** JSON Pointer to the location the operation applies to */
+  path: string;
+
+  /** JSON Pointer to the source location (`move` and `copy`) */
+  from?: string;
+
+  /** Value to add, replace or test against */
+  value?: unknown;
+}
 
 /** AgentCall invokes a registered agent as a step operation */
 export interface AgentCall {
@@ -378,6 +684,21 @@ export interface AgentCall {
   version?: string;
 }
 
+/**
+ * Call to another composition of the registry, resolved when the registry is compiled,
+ * with its arguments and result remapped at the call site
+ */
+export interface CompositionCall {
+  /** Name of the composition to run */
+  name: string;
+
+  /** The composition's arguments, mapped from the step input (default: the step input as is) */
+  input?: Record<string, FieldSource>;
+
+  /** The step output, mapped from the composition's result (default: the result as is) */
+  output?: Record<string, FieldSource>;
+}
+
 export interface ToolCall {
   /** Tool name (can be virtual tool, composition, or backend tool) */
   name: string;
@@ -393,7 +714,9 @@ export interface ToolCall {
 export type DataBinding =
   | { input: InputBinding }
   | { step: StepBinding }
-  | { constant: unknown };
+  | { constant: unknown }
+  | { construct: ConstructBinding }
+  | { template: TemplateBinding };
 
 export interface InputBinding {
   /** JSONPath into composition input (e.g., "$" for whole input, "$.query" for field) */
@@ -408,6 +731,19 @@ export interface StepBinding {
   path: string;
 }
 
+export interface ConstructBinding {
+  /** Field name -> binding that produces the field value */
+  fields: Record<string, DataBinding>;
+}
+
+export interface TemplateBinding {
+  /** Template string with {var} placeholders */
+  template: string;
+
+  /** Variable name -> binding that produces its value */
+  vars: Record<string, DataBinding>;
+}
+
 // =============================================================================
 // Scatter-Gather Pattern
 // =============================================================================
@@ -421,15 +757,34 @@ export interface ScatterGatherSpec {
   aggregation: AggregationStrategy;
 
   /** Timeout in milliseconds (optional) */
-  timeoutMs?: number;
+  timeoutMs?: Duration;
 
   /** If true, fail immediately on first error; if false, collect partial results */
   failFast?: boolean;
+
+  /** Invoke only a weighted random subset of the targets on each call (optional) */
+  sample?: TargetSample;
 }
 
-export type ScatterTarget =
-  | { tool: string }
-  | { pattern: PatternSpec };
+/** Which targets a sampled scatter-gather invokes on each call */
+export interface TargetSample {
+  /** The highest-weighted targets, invoked on every call */
+  always?: number;
+
+  /** Targets drawn from the rest on each call, with probability proportional to their weight */
+  draw?: number;
+
+  /** Seed making the draw the same on every call, for tests (optional) */
+  seed?: number;
+}
+
+export type ScatterTarget = ({ tool: string } | { pattern: PatternSpec }) & {
+  /** Retry the target when it fails */
+  retry?: StepRetry;
+
+  /** Relative weight when sampling targets (default 1) */
+  weight?: number;
+};
 
 /** AggregationStrategy defines how to combine scatter-gather results */
 export interface AggregationStrategy {
@@ -443,7 +798,8 @@ export type AggregationOp =
   | { dedupe: DedupeOp }
   | { limit: LimitOp }
   | { concat: boolean }
-  | { merge: boolean };
+  | { merge: boolean }
+  | { tokenBudget: TokenBudgetOp };
 
 export interface SortOp {
   /** JSONPath to the field to sort by */
@@ -463,6 +819,17 @@ export interface LimitOp {
   count: number;
 }
 
+export interface TokenBudgetOp {
+  /** Most tokens the kept results may hold */
+  maxTokens: number;
+  /** JSONPath to the text counted in each result (default: the whole result as JSON) */
+  textField?: string;
+  /** Shorten the text field of the first result over budget instead of dropping it */
+  truncate?: boolean;
+  /** How tokens are estimated (default: chars) */
+  tokenizer?: 'chars' | 'words';
+}
+
 // =============================================================================
 // Filter Pattern
 // =============================================================================
@@ -507,7 +874,9 @@ export type FieldSource =
   | { coalesce: CoalesceSource }
   | { template: TemplateSource }
   | { concat: ConcatSource }
-  | { nested: SchemaMapSpec };
+  | { nested: SchemaMapSpec }
+  | { conditional: ConditionalSource }
+  | { coerce: CoerceSource };
 
 export type LiteralValue =
   | { stringValue: string }
@@ -536,6 +905,25 @@ export interface ConcatSource {
   separator?: string;
 }
 
+export interface ConditionalSource {
+  /** Predicate evaluated against the input */
+  when: FieldPredicate;
+
+  /** Source used when the predicate matches */
+  then: FieldSource;
+
+  /** Source used when it doesn't (default: the field is omitted) */
+  fallback?: FieldSource;
+}
+
+export interface CoerceSource {
+  /** Source of the value to convert */
+  source: FieldSource;
+
+  /** Type to convert to; values that can't be converted become null */
+  as: "number" | "string" | "boolean";
+}
+
 // =============================================================================
 // Map Each Pattern
 // =============================================================================
@@ -544,12 +932,93 @@ export interface ConcatSource {
 export interface MapEachSpec {
   /** The operation to apply to each element */
   inner: MapEachInner;
+  /** Most elements the input array may have; a larger array fails the operation */
+  maxItems?: number;
+  /** Send each result to the client as it completes instead of returning the array */
+  stream?: MapEachStream;
+}
+
+/** MapEachStream emits a MapEach's results incrementally */
+export interface MapEachStream {
+  /** Emit results as they complete rather than in input order */
+  unordered?: boolean;
+  /** Elements processed at once (default 4, at most 32) */
+  concurrency?: number;
 }
 
 export type MapEachInner =
   | { tool: string }
   | { pattern: PatternSpec };
 
+// =============================================================================
+// Rank Pattern
+// =============================================================================
+
+/** RankSpec scores the elements of an array and sorts them by score, best first */
+export interface RankSpec {
+  /** How each element is scored */
+  scorer: RankScorer;
+
+  /** JSONPath to the array to rank (default: the input itself) */
+  itemsPath?: string;
+
+  /** Keep at most this many elements */
+  limit?: number;
+
+  /** Drop elements scoring below this */
+  minScore?: number;
+
+  /** Sort lowest score first */
+  ascending?: boolean;
+
+  /** Store each element's score in this field (object elements only) */
+  scoreField?: string;
+}
+
+export type RankScorer =
+  /** Weighted sum of numeric fields; missing or non-numeric fields count as 0 */
+  | { fields: WeightedField[] }
+  | { embedding: EmbeddingScorer }
+  | { llm: LlmScorer };
+
+export interface WeightedField {
+  /** JSONPath to the field, relative to the element */
+  path: string;
+
+  /** Multiplier for the field's value (default: 1) */
+  weight?: number;
+}
+
+/**
+ * EmbeddingScorer ranks by cosine similarity to a query. The embedder is called
+ * once with `{input: [query, text...]}` and returns one vector per text.
+ */
+export interface EmbeddingScorer {
+  /** Embedding tool */
+  embedder: string;
+
+  /** JSONPath to the query text in the rank input */
+  queryPath: string;
+
+  /** JSONPath to the text to embed, relative to the element (default: `$`) */
+  contentPath?: string;
+}
+
+/** LlmScorer asks the connected client's LLM to score the elements (MCP sampling) */
+export interface LlmScorer {
+  /** Scoring criteria for the model */
+  prompt: string;
+
+  /** JSONPath to a query in the rank input, sent along with the elements */
+  queryPath?: string;
+
+  /** Preferred models or model families, most preferred first */
+  modelHints?: string[];
+
+  /** How long to wait for the client in milliseconds (default: 60000) */
+  timeoutMs?: Duration;
+}
+
 // =============================================================================
 // Output Transform
 // =============================================================================
@@ -558,6 +1027,16 @@ export type MapEachInner =
 export interface OutputTransform {
   /** Field name -> source mapping */
   mappings: Record<string, FieldSource>;
+  /** Mappings for results of other shapes; the first matching variant replaces mappings */
+  variants?: OutputVariant[];
+  /** Output field each variant maps to a distinct string literal */
+  discriminator?: string;
+}
+
+/** OutputVariant maps results that match a predicate */
+export interface OutputVariant {
+  when: FieldPredicate;
+  mappings: Record<string, FieldSource>;
 }
 
 // =============================================================================
@@ -579,10 +1058,10 @@ export interface RetrySpec {
   retryIf?: FieldPredicate;
 
   /** Jitter factor (0.0 - 1.0) */
-  jitter?: number;
+  jitter?: Percentage;
 
   /** Per-attempt timeout in milliseconds */
-  attemptTimeoutMs?: number;
+  attemptTimeoutMs?: Duration;
 }
 
 export type BackoffStrategy =
@@ -591,19 +1070,19 @@ export type BackoffStrategy =
   | { linear: LinearBackoff };
 
 export interface FixedBackoff {
-  delayMs: number;
+  delayMs: Duration;
 }
 
 export interface ExponentialBackoff {
-  initialDelayMs: number;
-  maxDelayMs: number;
+  initialDelayMs: Duration;
+  maxDelayMs: Duration;
   multiplier?: number;
 }
 
 export interface LinearBackoff {
-  initialDelayMs: number;
-  incrementMs: number;
-  maxDelayMs: number;
+  initialDelayMs: Duration;
+  incrementMs: Duration;
+  maxDelayMs: Duration;
 }
 
 /** TimeoutSpec - enforce maximum execution duration */
@@ -612,7 +1091,7 @@ export interface TimeoutSpec {
   inner: StepOperation;
 
   /** Timeout duration in milliseconds */
-  durationMs: number;
+  durationMs: Duration;
 
   /** Fallback on timeout (optional) */
   fallback?: StepOperation;
@@ -633,13 +1112,31 @@ export interface CacheSpec {
   store: string;
 
   /** TTL in seconds */
-  ttlSeconds: number;
+  ttlSeconds: Duration;
 
   /** Stale-while-revalidate window in seconds */
-  staleWhileRevalidateSeconds?: number;
+  staleWhileRevalidateSeconds?: Duration;
 
   /** Condition to cache result (if absent, always cache) */
   cacheIf?: FieldPredicate;
+
+  /** Condition that forces revalidation of a cached result (e.g. empty results) */
+  staleIf?: FieldPredicate;
+
+  /** Short-lived caching of failures and empty results */
+  negative?: NegativeCacheSpec;
+}
+
+/** NegativeCacheSpec - protects backends from repeated identical failing queries */
+export interface NegativeCacheSpec {
+  /** TTL in seconds for negative entries */
+  ttlSeconds: Duration;
+
+  /** Error kinds to cache, e.g. `tool_not_found` (if empty, failures that aren't retryable) */
+  errors?: string[];
+
+  /** Also cache empty results (null, "", [] or {}) */
+  emptyResults?: boolean;
 }
 
 /** IdempotentSpec - prevent duplicate processing */
@@ -654,7 +1151,7 @@ export interface IdempotentSpec {
   store: string;
 
   /** TTL in seconds (0 = no expiry) */
-  ttlSeconds?: number;
+  ttlSeconds?: Duration;
 
   /** Behavior on duplicate */
   onDuplicate: OnDuplicate;
@@ -677,10 +1174,10 @@ export interface CircuitBreakerSpec {
   failureThreshold: number;
 
   /** Window for counting failures (seconds) */
-  failureWindowSeconds: number;
+  failureWindowSeconds: Duration;
 
   /** Time to wait before half-open (seconds) */
-  resetTimeoutSeconds: number;
+  resetTimeoutSeconds: Duration;
 
   /** Successes needed in half-open to close (default: 1) */
   successThreshold?: number;
@@ -708,6 +1205,15 @@ export interface DeadLetterSpec {
 
   /** Whether to rethrow after dead-lettering */
   rethrow: boolean;
+
+  /** Input fields to redact, on top of the built-in credential keys */
+  sensitiveFields?: string[];
+
+  /** Most bytes of input sent inline; the error text is cut to the same length */
+  maxPayloadBytes?: number;
+
+  /** Tool that stores an oversized input and returns a reference to send instead */
+  claimCheckTool?: string;
 }
 
 /** SagaSpec - distributed transaction with compensation */
@@ -722,7 +1228,7 @@ export interface SagaSpec {
   sagaIdPath?: string;
 
   /** Timeout for entire saga in milliseconds */
-  timeoutMs?: number;
+  timeoutMs?: Duration;
 
   /** Output binding */
   output?: DataBinding;